@@ -0,0 +1,109 @@
+//! Auth por API key para `/api/v2/*`.
+//!
+//! La clave viaja en `Authorization: Bearer ck_<prefijo>_<secreto>` o en
+//! `X-API-Key: ck_...`. Se busca por prefijo y se compara el hash del secreto
+//! en tiempo constante. Las claves rechazadas (401) cuentan para el límite por
+//! IP de `server::rate_limit`.
+
+use super::http::ApiV2Error;
+use crate::server::state::ServerState;
+use axum::http::{HeaderMap, StatusCode};
+use rusqlite::params;
+use std::sync::Arc;
+
+/// Clave validada (resultado de [`extraer_clave`]).
+#[derive(Debug, Clone)]
+pub struct ClaveApi {
+    pub id: i64,
+    pub nombre: String,
+    pub scopes: Vec<String>,
+}
+
+impl ClaveApi {
+    pub fn tiene(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == "*" || s == scope)
+    }
+
+    /// 403 si la clave no tiene el scope.
+    pub fn requiere(&self, scope: &str) -> Result<(), ApiV2Error> {
+        if self.tiene(scope) {
+            Ok(())
+        } else {
+            Err(ApiV2Error::new(
+                StatusCode::FORBIDDEN,
+                "scope_insuficiente",
+                format!("La API key no tiene el scope '{}'", scope),
+            ))
+        }
+    }
+}
+
+/// Genera una clave nueva. Devuelve `(clave_completa, prefijo, salt, hash)`.
+pub fn generar_clave() -> (String, String, String, String) {
+    let uuid = uuid::Uuid::new_v4().simple().to_string();
+    let prefijo = uuid[..8].to_string();
+    let secreto = format!("{}{}", &uuid[8..], uuid::Uuid::new_v4().simple());
+    let salt = crate::utils::generar_salt();
+    let hash = crate::utils::hash_pin(&salt, &secreto);
+    (format!("ck_{}_{}", prefijo, secreto), prefijo, salt, hash)
+}
+
+/// Separa `ck_<prefijo>_<secreto>` en sus partes.
+fn partir_clave(clave: &str) -> Option<(&str, &str)> {
+    let resto = clave.strip_prefix("ck_")?;
+    let (prefijo, secreto) = resto.split_once('_')?;
+    if prefijo.len() != 8 || secreto.is_empty() {
+        return None;
+    }
+    Some((prefijo, secreto))
+}
+
+/// Valida la API key de la request. Llamado al inicio de cada handler `/api/v2`.
+pub fn extraer_clave(headers: &HeaderMap, state: &Arc<ServerState>) -> Result<ClaveApi, ApiV2Error> {
+    let bearer = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let clave = bearer
+        .or_else(|| headers.get("x-api-key").and_then(|v| v.to_str().ok()))
+        .unwrap_or("")
+        .trim();
+
+    let no_autorizado = || {
+        ApiV2Error::new(StatusCode::UNAUTHORIZED, "no_autorizado", "API key inválida o revocada")
+    };
+    if clave.is_empty() {
+        return Err(ApiV2Error::new(
+            StatusCode::UNAUTHORIZED,
+            "no_autorizado",
+            "Falta la API key (header Authorization: Bearer ck_... o X-API-Key)",
+        ));
+    }
+    let (prefijo, secreto) = partir_clave(clave).ok_or_else(no_autorizado)?;
+
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+    let (id, nombre, salt, hash, scopes_json): (i64, String, String, String, String) = conn
+        .query_row(
+            "SELECT id, nombre, key_salt, key_hash, scopes FROM api_keys
+             WHERE prefijo = ?1 AND activa = 1",
+            params![prefijo],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
+        )
+        .map_err(|_| no_autorizado())?;
+
+    if !crate::utils::iguales_tiempo_constante(&crate::utils::hash_pin(&salt, secreto), &hash) {
+        return Err(no_autorizado());
+    }
+
+    // Best-effort, igual que `app_tokens.last_used_at`.
+    let _ = conn.execute(
+        "UPDATE api_keys SET last_used_at = datetime('now','localtime') WHERE id = ?1",
+        params![id],
+    );
+
+    Ok(ClaveApi {
+        id,
+        nombre,
+        scopes: serde_json::from_str(&scopes_json).unwrap_or_default(),
+    })
+}
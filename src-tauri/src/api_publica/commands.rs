@@ -0,0 +1,120 @@
+//! Comandos Tauri admin de la API pública.
+//!
+//! Los consume Configuración → Integraciones del POS escritorio: crear una
+//! API key por integración, ver cuándo se usó por última vez y revocarla.
+//! Todos requieren sesión ADMIN.
+
+use crate::commands::usuarios::verificar_admin;
+use crate::db::{Database, SesionState};
+use rusqlite::params;
+use serde::Serialize;
+use tauri::State;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ApiKeyInfo {
+    pub id: i64,
+    pub nombre: String,
+    /// Primeros caracteres de la clave (`ck_<prefijo>_...`) para identificarla.
+    pub prefijo: String,
+    pub scopes: Vec<String>,
+    pub activa: bool,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+}
+
+/// Respuesta de creación: la clave completa solo se devuelve aquí.
+#[derive(Debug, Serialize, Clone)]
+pub struct ApiKeyCreada {
+    pub id: i64,
+    pub clave: String,
+}
+
+#[tauri::command]
+pub fn api_listar_scopes() -> Vec<String> {
+    super::SCOPES.iter().map(|s| s.to_string()).collect()
+}
+
+#[tauri::command]
+pub fn api_listar_claves(
+    db: State<'_, Database>,
+    sesion: State<'_, SesionState>,
+) -> Result<Vec<ApiKeyInfo>, String> {
+    verificar_admin(&sesion)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, nombre, prefijo, scopes, activa, created_at, last_used_at
+             FROM api_keys ORDER BY activa DESC, id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let claves = stmt
+        .query_map([], |r| {
+            Ok(ApiKeyInfo {
+                id: r.get(0)?,
+                nombre: r.get(1)?,
+                prefijo: r.get(2)?,
+                scopes: serde_json::from_str(&r.get::<_, String>(3)?).unwrap_or_default(),
+                activa: r.get::<_, i64>(4)? != 0,
+                created_at: r.get(5)?,
+                last_used_at: r.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(claves)
+}
+
+/// Crea una API key. La clave completa se muestra una sola vez: en BD solo
+/// queda el hash. Si el servidor HTTP no estaba corriendo, arranca en el
+/// próximo inicio de la app (ver `lib.rs`).
+#[tauri::command]
+pub fn api_crear_clave(
+    db: State<'_, Database>,
+    sesion: State<'_, SesionState>,
+    nombre: String,
+    scopes: Vec<String>,
+) -> Result<ApiKeyCreada, String> {
+    verificar_admin(&sesion)?;
+    let nombre = nombre.trim();
+    if nombre.is_empty() {
+        return Err("Ingrese un nombre para identificar la integración".to_string());
+    }
+    if scopes.is_empty() {
+        return Err("Seleccione al menos un permiso (scope)".to_string());
+    }
+    if let Some(s) = scopes.iter().find(|s| !super::SCOPES.contains(&s.as_str())) {
+        return Err(format!("Scope desconocido: {}", s));
+    }
+
+    let (clave, prefijo, salt, hash) = super::auth::generar_clave();
+    let scopes_json = serde_json::to_string(&scopes).map_err(|e| e.to_string())?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO api_keys (nombre, prefijo, key_salt, key_hash, scopes) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![nombre, prefijo, salt, hash, scopes_json],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(ApiKeyCreada { id: conn.last_insert_rowid(), clave })
+}
+
+/// Revoca una clave (no la borra, queda para auditoría).
+#[tauri::command]
+pub fn api_revocar_clave(
+    db: State<'_, Database>,
+    sesion: State<'_, SesionState>,
+    id: i64,
+) -> Result<(), String> {
+    verificar_admin(&sesion)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let filas = conn
+        .execute(
+            "UPDATE api_keys SET activa = 0, revocada_at = datetime('now','localtime') WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+    if filas == 0 {
+        return Err("API key no encontrada".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,788 @@
+//! Endpoints `/api/v2/*` de la API pública.
+//!
+//! Convenciones (todas las rutas):
+//! - Auth: API key con scope (ver [`super::auth`]).
+//! - Listados paginados con `?pagina=1&por_pagina=50` (máx. 500). La respuesta
+//!   trae `{ data, pagina, por_pagina, total }`.
+//! - Fechas en `YYYY-MM-DD` (filtros `desde`/`hasta` inclusivos) y timestamps
+//!   como los guarda la BD (`YYYY-MM-DD HH:MM:SS`, hora local del negocio).
+//! - Errores: status HTTP + `{ "error": { "codigo", "mensaje" } }`.
+//!
+//! Rutas:
+//! - `GET  /api/v2/openapi.json`                      — sin auth
+//! - `GET  /api/v2/productos`, `/api/v2/productos/:id` — `productos:leer`
+//! - `GET  /api/v2/stock`                             — `stock:leer`
+//! - `GET  /api/v2/clientes`, `/api/v2/clientes/:id`   — `clientes:leer`
+//! - `POST /api/v2/clientes`                          — `clientes:escribir`
+//! - `GET  /api/v2/ventas`, `/api/v2/ventas/:id`       — `ventas:leer`
+//! - `GET  /api/v2/compras`, `/api/v2/compras/:id`     — `compras:leer`
+//! - `GET  /api/v2/reportes/ventas-diarias`           — `reportes:leer`
+//! - `GET  /api/v2/reportes/productos-mas-vendidos`   — `reportes:leer`
+
+use super::auth::extraer_clave;
+use crate::server::state::ServerState;
+use axum::{
+    extract::{Path, Query, State as AxumState},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use rusqlite::{params, ToSql};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+// ─── Errores ─────────────────────────────────────────────────────────────
+
+#[derive(Debug)]
+pub struct ApiV2Error {
+    pub status: StatusCode,
+    pub codigo: &'static str,
+    pub mensaje: String,
+}
+
+impl ApiV2Error {
+    pub fn new(status: StatusCode, codigo: &'static str, mensaje: impl Into<String>) -> Self {
+        Self { status, codigo, mensaje: mensaje.into() }
+    }
+    pub fn interno(e: impl std::fmt::Display) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "error_interno", e.to_string())
+    }
+    pub fn no_encontrado(recurso: &str) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "no_encontrado", format!("{} no encontrado", recurso))
+    }
+    pub fn invalido(e: impl std::fmt::Display) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "solicitud_invalida", e.to_string())
+    }
+}
+
+impl IntoResponse for ApiV2Error {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(json!({ "error": { "codigo": self.codigo, "mensaje": self.mensaje } })),
+        )
+            .into_response()
+    }
+}
+
+type Resultado = Result<Json<Value>, ApiV2Error>;
+
+// ─── Helpers de paginación y filtros ─────────────────────────────────────
+
+const POR_PAGINA_DEFAULT: i64 = 50;
+const POR_PAGINA_MAX: i64 = 500;
+
+/// Normaliza `pagina`/`por_pagina` → `(pagina, por_pagina, offset)`.
+fn paginacion(pagina: Option<i64>, por_pagina: Option<i64>) -> (i64, i64, i64) {
+    let pagina = pagina.unwrap_or(1).max(1);
+    let por_pagina = por_pagina.unwrap_or(POR_PAGINA_DEFAULT).clamp(1, POR_PAGINA_MAX);
+    (pagina, por_pagina, (pagina - 1) * por_pagina)
+}
+
+/// Acumula condiciones `WHERE` con parámetros posicionales `?`.
+#[derive(Default)]
+struct Filtros {
+    condiciones: Vec<String>,
+    valores: Vec<Box<dyn ToSql>>,
+}
+
+impl Filtros {
+    fn con(mut self, condicion: &str) -> Self {
+        self.condiciones.push(condicion.to_string());
+        self
+    }
+    fn si<T: ToSql + 'static>(mut self, valor: Option<T>, condicion: &str) -> Self {
+        if let Some(v) = valor {
+            self.condiciones.push(condicion.to_string());
+            self.valores.push(Box::new(v));
+        }
+        self
+    }
+    /// Igual que `si` pero la condición usa el mismo valor varias veces.
+    fn si_repetido<T: ToSql + Clone + 'static>(mut self, valor: Option<T>, condicion: &str, veces: usize) -> Self {
+        if let Some(v) = valor {
+            self.condiciones.push(condicion.to_string());
+            for _ in 0..veces {
+                self.valores.push(Box::new(v.clone()));
+            }
+        }
+        self
+    }
+    fn where_sql(&self) -> String {
+        if self.condiciones.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", self.condiciones.join(" AND "))
+        }
+    }
+}
+
+/// Ejecuta `SELECT COUNT(*)` + `SELECT ... LIMIT/OFFSET` con los mismos filtros
+/// y arma la respuesta paginada estándar.
+#[allow(clippy::too_many_arguments)]
+fn listar_paginado(
+    conn: &rusqlite::Connection,
+    from_sql: &str,
+    columnas: &str,
+    orden: &str,
+    filtros: Filtros,
+    pagina: Option<i64>,
+    por_pagina: Option<i64>,
+    fila: impl Fn(&rusqlite::Row) -> rusqlite::Result<Value>,
+) -> Resultado {
+    let (pagina, por_pagina, offset) = paginacion(pagina, por_pagina);
+    let where_sql = filtros.where_sql();
+    let mut valores: Vec<&dyn ToSql> = filtros.valores.iter().map(|b| b.as_ref()).collect();
+
+    let total: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) {} {}", from_sql, where_sql),
+            rusqlite::params_from_iter(valores.iter()),
+            |r| r.get(0),
+        )
+        .map_err(ApiV2Error::interno)?;
+
+    valores.push(&por_pagina);
+    valores.push(&offset);
+    let sql = format!(
+        "SELECT {} {} {} ORDER BY {} LIMIT ? OFFSET ?",
+        columnas, from_sql, where_sql, orden
+    );
+    let mut stmt = conn.prepare(&sql).map_err(ApiV2Error::interno)?;
+    let data: Vec<Value> = stmt
+        .query_map(rusqlite::params_from_iter(valores.iter()), &fila)
+        .map_err(ApiV2Error::interno)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ApiV2Error::interno)?;
+
+    Ok(Json(json!({
+        "data": data,
+        "pagina": pagina,
+        "por_pagina": por_pagina,
+        "total": total,
+    })))
+}
+
+/// Valida `YYYY-MM-DD` para filtros de fecha.
+fn fecha(valor: Option<String>) -> Result<Option<String>, ApiV2Error> {
+    match valor {
+        Some(f) if chrono::NaiveDate::parse_from_str(&f, "%Y-%m-%d").is_err() => Err(
+            ApiV2Error::invalido(format!("Fecha inválida '{}', use YYYY-MM-DD", f)),
+        ),
+        otro => Ok(otro),
+    }
+}
+
+// ─── OpenAPI ─────────────────────────────────────────────────────────────
+
+/// `GET /api/v2/openapi.json` — sin auth (el documento no expone datos).
+pub async fn openapi_json() -> Json<Value> {
+    Json(super::openapi::documento())
+}
+
+// ─── Productos ───────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct ProductosQuery {
+    pub q: Option<String>,
+    pub categoria_id: Option<i64>,
+    pub activo: Option<bool>,
+    /// Solo productos modificados desde este timestamp (sincronización incremental).
+    pub actualizado_desde: Option<String>,
+    pub pagina: Option<i64>,
+    pub por_pagina: Option<i64>,
+}
+
+fn producto_json(r: &rusqlite::Row) -> rusqlite::Result<Value> {
+    Ok(json!({
+        "id": r.get::<_, i64>(0)?,
+        "codigo": r.get::<_, Option<String>>(1)?,
+        "codigo_barras": r.get::<_, Option<String>>(2)?,
+        "nombre": r.get::<_, String>(3)?,
+        "descripcion": r.get::<_, Option<String>>(4)?,
+        "categoria_id": r.get::<_, Option<i64>>(5)?,
+        "categoria": r.get::<_, Option<String>>(6)?,
+        "precio_venta": r.get::<_, f64>(7)?,
+        "iva_porcentaje": r.get::<_, f64>(8)?,
+        "incluye_iva": r.get::<_, i64>(9)? != 0,
+        "stock_actual": r.get::<_, f64>(10)?,
+        "unidad_medida": r.get::<_, String>(11)?,
+        "es_servicio": r.get::<_, i64>(12)? != 0,
+        "activo": r.get::<_, i64>(13)? != 0,
+        "actualizado_at": r.get::<_, String>(14)?,
+    }))
+}
+
+const PRODUCTO_COLUMNAS: &str = "p.id, p.codigo, p.codigo_barras, p.nombre, p.descripcion, p.categoria_id,
+     c.nombre, p.precio_venta, p.iva_porcentaje, p.incluye_iva, p.stock_actual, p.unidad_medida,
+     p.es_servicio, p.activo, p.updated_at";
+
+pub async fn productos_listar(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Query(q): Query<ProductosQuery>,
+) -> Resultado {
+    extraer_clave(&headers, &state)?.requiere("productos:leer")?;
+    let filtros = Filtros::default()
+        .si_repetido(
+            q.q.filter(|s| !s.trim().is_empty()).map(|s| format!("%{}%", s.trim())),
+            "(p.nombre LIKE ? OR p.codigo LIKE ? OR p.codigo_barras LIKE ?)",
+            3,
+        )
+        .si(q.categoria_id, "p.categoria_id = ?")
+        .si(q.activo.map(|a| a as i64), "p.activo = ?")
+        .si(q.actualizado_desde, "p.updated_at >= ?");
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+    listar_paginado(
+        &conn,
+        "FROM productos p LEFT JOIN categorias c ON p.categoria_id = c.id",
+        PRODUCTO_COLUMNAS,
+        "p.id",
+        filtros,
+        q.pagina,
+        q.por_pagina,
+        producto_json,
+    )
+}
+
+pub async fn productos_obtener(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Resultado {
+    extraer_clave(&headers, &state)?.requiere("productos:leer")?;
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+    let producto = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM productos p LEFT JOIN categorias c ON p.categoria_id = c.id WHERE p.id = ?1",
+                PRODUCTO_COLUMNAS
+            ),
+            params![id],
+            producto_json,
+        )
+        .map_err(|_| ApiV2Error::no_encontrado("Producto"))?;
+    Ok(Json(json!({ "data": producto })))
+}
+
+// ─── Stock ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct StockQuery {
+    pub producto_id: Option<i64>,
+    pub establecimiento_id: Option<i64>,
+    /// `true` → solo productos en o bajo su stock mínimo.
+    pub bajo_minimo: Option<bool>,
+    pub pagina: Option<i64>,
+    pub por_pagina: Option<i64>,
+}
+
+/// `GET /api/v2/stock` — global por producto, o por establecimiento si se filtra.
+pub async fn stock_listar(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Query(q): Query<StockQuery>,
+) -> Resultado {
+    extraer_clave(&headers, &state)?.requiere("stock:leer")?;
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+    let bajo_minimo = q.bajo_minimo.unwrap_or(false);
+
+    if let Some(est_id) = q.establecimiento_id {
+        let mut filtros = Filtros::default()
+            .con("p.activo = 1 AND p.es_servicio = 0")
+            .si(Some(est_id), "se.establecimiento_id = ?")
+            .si(q.producto_id, "p.id = ?");
+        if bajo_minimo {
+            filtros = filtros.con("se.stock_actual <= se.stock_minimo");
+        }
+        return listar_paginado(
+            &conn,
+            "FROM stock_establecimiento se JOIN productos p ON se.producto_id = p.id",
+            "p.id, p.codigo, p.nombre, se.establecimiento_id, se.stock_actual, se.stock_minimo",
+            "p.id",
+            filtros,
+            q.pagina,
+            q.por_pagina,
+            |r| {
+                Ok(json!({
+                    "producto_id": r.get::<_, i64>(0)?,
+                    "codigo": r.get::<_, Option<String>>(1)?,
+                    "nombre": r.get::<_, String>(2)?,
+                    "establecimiento_id": r.get::<_, i64>(3)?,
+                    "stock_actual": r.get::<_, f64>(4)?,
+                    "stock_minimo": r.get::<_, f64>(5)?,
+                }))
+            },
+        );
+    }
+
+    let mut filtros = Filtros::default()
+        .con("p.activo = 1 AND p.es_servicio = 0")
+        .si(q.producto_id, "p.id = ?");
+    if bajo_minimo {
+        filtros = filtros.con("p.stock_actual <= p.stock_minimo");
+    }
+    listar_paginado(
+        &conn,
+        "FROM productos p",
+        "p.id, p.codigo, p.nombre, p.stock_actual, p.stock_minimo, p.updated_at",
+        "p.id",
+        filtros,
+        q.pagina,
+        q.por_pagina,
+        |r| {
+            Ok(json!({
+                "producto_id": r.get::<_, i64>(0)?,
+                "codigo": r.get::<_, Option<String>>(1)?,
+                "nombre": r.get::<_, String>(2)?,
+                "establecimiento_id": null,
+                "stock_actual": r.get::<_, f64>(3)?,
+                "stock_minimo": r.get::<_, f64>(4)?,
+            }))
+        },
+    )
+}
+
+// ─── Clientes ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct ClientesQuery {
+    pub q: Option<String>,
+    pub identificacion: Option<String>,
+    pub pagina: Option<i64>,
+    pub por_pagina: Option<i64>,
+}
+
+const CLIENTE_COLUMNAS: &str =
+    "id, tipo_identificacion, identificacion, nombre, direccion, telefono, email, activo, updated_at";
+
+fn cliente_json(r: &rusqlite::Row) -> rusqlite::Result<Value> {
+    Ok(json!({
+        "id": r.get::<_, i64>(0)?,
+        "tipo_identificacion": r.get::<_, String>(1)?,
+        "identificacion": r.get::<_, Option<String>>(2)?,
+        "nombre": r.get::<_, String>(3)?,
+        "direccion": r.get::<_, Option<String>>(4)?,
+        "telefono": r.get::<_, Option<String>>(5)?,
+        "email": r.get::<_, Option<String>>(6)?,
+        "activo": r.get::<_, i64>(7)? != 0,
+        "actualizado_at": r.get::<_, String>(8)?,
+    }))
+}
+
+pub async fn clientes_listar(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Query(q): Query<ClientesQuery>,
+) -> Resultado {
+    extraer_clave(&headers, &state)?.requiere("clientes:leer")?;
+    let filtros = Filtros::default()
+        .si_repetido(
+            q.q.filter(|s| !s.trim().is_empty()).map(|s| format!("%{}%", s.trim())),
+            "(nombre LIKE ? OR identificacion LIKE ?)",
+            2,
+        )
+        .si(q.identificacion, "identificacion = ?");
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+    listar_paginado(&conn, "FROM clientes", CLIENTE_COLUMNAS, "id", filtros, q.pagina, q.por_pagina, cliente_json)
+}
+
+pub async fn clientes_obtener(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Resultado {
+    extraer_clave(&headers, &state)?.requiere("clientes:leer")?;
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+    let cliente = conn
+        .query_row(
+            &format!("SELECT {} FROM clientes WHERE id = ?1", CLIENTE_COLUMNAS),
+            params![id],
+            cliente_json,
+        )
+        .map_err(|_| ApiV2Error::no_encontrado("Cliente"))?;
+    Ok(Json(json!({ "data": cliente })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CrearClienteBody {
+    pub tipo_identificacion: Option<String>,
+    pub identificacion: Option<String>,
+    pub nombre: String,
+    pub direccion: Option<String>,
+    pub telefono: Option<String>,
+    pub email: Option<String>,
+}
+
+/// `POST /api/v2/clientes` — idempotente por identificación: si ya existe,
+/// responde 200 con el existente en vez de 201.
+pub async fn clientes_crear(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(body): Json<CrearClienteBody>,
+) -> Result<(StatusCode, Json<Value>), ApiV2Error> {
+    extraer_clave(&headers, &state)?.requiere("clientes:escribir")?;
+    let nombre = body.nombre.trim();
+    if nombre.is_empty() {
+        return Err(ApiV2Error::invalido("El nombre es obligatorio"));
+    }
+    let identificacion = body.identificacion.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+
+    if let Some(ident) = &identificacion {
+        if let Ok(existente) = conn.query_row(
+            &format!("SELECT {} FROM clientes WHERE identificacion = ?1", CLIENTE_COLUMNAS),
+            params![ident],
+            cliente_json,
+        ) {
+            return Ok((StatusCode::OK, Json(json!({ "data": existente, "existente": true }))));
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO clientes (tipo_identificacion, identificacion, nombre, direccion, telefono, email, activo)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1)",
+        params![
+            body.tipo_identificacion.unwrap_or_else(|| "CEDULA".to_string()),
+            identificacion,
+            nombre,
+            body.direccion,
+            body.telefono,
+            body.email
+        ],
+    )
+    .map_err(ApiV2Error::interno)?;
+    let id = conn.last_insert_rowid();
+    let creado = conn
+        .query_row(
+            &format!("SELECT {} FROM clientes WHERE id = ?1", CLIENTE_COLUMNAS),
+            params![id],
+            cliente_json,
+        )
+        .map_err(ApiV2Error::interno)?;
+    Ok((StatusCode::CREATED, Json(json!({ "data": creado, "existente": false }))))
+}
+
+// ─── Ventas ──────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct VentasQuery {
+    pub desde: Option<String>,
+    pub hasta: Option<String>,
+    pub cliente_id: Option<i64>,
+    /// `FACTURA`, `NOTA_VENTA`, ...
+    pub tipo_documento: Option<String>,
+    pub incluir_anuladas: Option<bool>,
+    pub pagina: Option<i64>,
+    pub por_pagina: Option<i64>,
+}
+
+const VENTA_COLUMNAS: &str = "v.id, v.numero, v.fecha, v.cliente_id, c.nombre, c.identificacion,
+     v.subtotal_sin_iva, v.subtotal_con_iva, v.descuento, v.iva, v.total, v.forma_pago,
+     v.tipo_documento, v.estado_sri, v.autorizacion_sri, v.anulada";
+
+fn venta_json(r: &rusqlite::Row) -> rusqlite::Result<Value> {
+    Ok(json!({
+        "id": r.get::<_, i64>(0)?,
+        "numero": r.get::<_, String>(1)?,
+        "fecha": r.get::<_, String>(2)?,
+        "cliente_id": r.get::<_, Option<i64>>(3)?,
+        "cliente_nombre": r.get::<_, Option<String>>(4)?,
+        "cliente_identificacion": r.get::<_, Option<String>>(5)?,
+        "subtotal_sin_iva": r.get::<_, f64>(6)?,
+        "subtotal_con_iva": r.get::<_, f64>(7)?,
+        "descuento": r.get::<_, f64>(8)?,
+        "iva": r.get::<_, f64>(9)?,
+        "total": r.get::<_, f64>(10)?,
+        "forma_pago": r.get::<_, String>(11)?,
+        "tipo_documento": r.get::<_, String>(12)?,
+        "estado_sri": r.get::<_, Option<String>>(13)?,
+        "autorizacion_sri": r.get::<_, Option<String>>(14)?,
+        "anulada": r.get::<_, i64>(15)? != 0,
+    }))
+}
+
+pub async fn ventas_listar(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Query(q): Query<VentasQuery>,
+) -> Resultado {
+    extraer_clave(&headers, &state)?.requiere("ventas:leer")?;
+    let mut filtros = Filtros::default()
+        // Borradores, cotizaciones y guías no son ventas cerradas.
+        .con("COALESCE(v.tipo_estado, 'COMPLETADA') IN ('COMPLETADA', 'CONVERTIDA')")
+        .si(fecha(q.desde)?, "date(v.fecha) >= ?")
+        .si(fecha(q.hasta)?, "date(v.fecha) <= ?")
+        .si(q.cliente_id, "v.cliente_id = ?")
+        .si(q.tipo_documento, "v.tipo_documento = ?");
+    if !q.incluir_anuladas.unwrap_or(false) {
+        filtros = filtros.con("v.anulada = 0");
+    }
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+    listar_paginado(
+        &conn,
+        "FROM ventas v LEFT JOIN clientes c ON v.cliente_id = c.id",
+        VENTA_COLUMNAS,
+        "v.id",
+        filtros,
+        q.pagina,
+        q.por_pagina,
+        venta_json,
+    )
+}
+
+pub async fn ventas_obtener(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Resultado {
+    extraer_clave(&headers, &state)?.requiere("ventas:leer")?;
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+    let mut venta = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM ventas v LEFT JOIN clientes c ON v.cliente_id = c.id WHERE v.id = ?1",
+                VENTA_COLUMNAS
+            ),
+            params![id],
+            venta_json,
+        )
+        .map_err(|_| ApiV2Error::no_encontrado("Venta"))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT vd.producto_id, COALESCE(p.nombre, vd.descripcion, ''), p.codigo,
+                    vd.cantidad, vd.precio_unitario, vd.descuento, vd.iva_porcentaje, vd.subtotal
+             FROM venta_detalles vd LEFT JOIN productos p ON vd.producto_id = p.id
+             WHERE vd.venta_id = ?1 ORDER BY vd.id",
+        )
+        .map_err(ApiV2Error::interno)?;
+    let detalles: Vec<Value> = stmt
+        .query_map(params![id], |r| {
+            Ok(json!({
+                "producto_id": r.get::<_, Option<i64>>(0)?,
+                "nombre": r.get::<_, String>(1)?,
+                "codigo": r.get::<_, Option<String>>(2)?,
+                "cantidad": r.get::<_, f64>(3)?,
+                "precio_unitario": r.get::<_, f64>(4)?,
+                "descuento": r.get::<_, f64>(5)?,
+                "iva_porcentaje": r.get::<_, f64>(6)?,
+                "subtotal": r.get::<_, f64>(7)?,
+            }))
+        })
+        .map_err(ApiV2Error::interno)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ApiV2Error::interno)?;
+    venta["detalles"] = Value::Array(detalles);
+    Ok(Json(json!({ "data": venta })))
+}
+
+// ─── Compras ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct ComprasQuery {
+    pub desde: Option<String>,
+    pub hasta: Option<String>,
+    pub proveedor_id: Option<i64>,
+    pub estado: Option<String>,
+    pub pagina: Option<i64>,
+    pub por_pagina: Option<i64>,
+}
+
+const COMPRA_COLUMNAS: &str = "c.id, c.numero, c.fecha, c.proveedor_id, p.nombre, p.ruc,
+     c.numero_factura, c.subtotal, c.iva, c.total, c.estado, c.forma_pago, c.es_credito";
+
+fn compra_json(r: &rusqlite::Row) -> rusqlite::Result<Value> {
+    Ok(json!({
+        "id": r.get::<_, i64>(0)?,
+        "numero": r.get::<_, String>(1)?,
+        "fecha": r.get::<_, String>(2)?,
+        "proveedor_id": r.get::<_, i64>(3)?,
+        "proveedor_nombre": r.get::<_, Option<String>>(4)?,
+        "proveedor_ruc": r.get::<_, Option<String>>(5)?,
+        "numero_factura": r.get::<_, Option<String>>(6)?,
+        "subtotal": r.get::<_, f64>(7)?,
+        "iva": r.get::<_, f64>(8)?,
+        "total": r.get::<_, f64>(9)?,
+        "estado": r.get::<_, String>(10)?,
+        "forma_pago": r.get::<_, String>(11)?,
+        "es_credito": r.get::<_, i64>(12)? != 0,
+    }))
+}
+
+pub async fn compras_listar(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Query(q): Query<ComprasQuery>,
+) -> Resultado {
+    extraer_clave(&headers, &state)?.requiere("compras:leer")?;
+    let filtros = Filtros::default()
+        .si(fecha(q.desde)?, "date(c.fecha) >= ?")
+        .si(fecha(q.hasta)?, "date(c.fecha) <= ?")
+        .si(q.proveedor_id, "c.proveedor_id = ?")
+        .si(q.estado, "c.estado = ?");
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+    listar_paginado(
+        &conn,
+        "FROM compras c LEFT JOIN proveedores p ON c.proveedor_id = p.id",
+        COMPRA_COLUMNAS,
+        "c.id",
+        filtros,
+        q.pagina,
+        q.por_pagina,
+        compra_json,
+    )
+}
+
+pub async fn compras_obtener(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Resultado {
+    extraer_clave(&headers, &state)?.requiere("compras:leer")?;
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+    let mut compra = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM compras c LEFT JOIN proveedores p ON c.proveedor_id = p.id WHERE c.id = ?1",
+                COMPRA_COLUMNAS
+            ),
+            params![id],
+            compra_json,
+        )
+        .map_err(|_| ApiV2Error::no_encontrado("Compra"))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT cd.producto_id, COALESCE(p.nombre, cd.descripcion, ''), cd.cantidad,
+                    cd.precio_unitario, cd.subtotal
+             FROM compra_detalles cd LEFT JOIN productos p ON cd.producto_id = p.id
+             WHERE cd.compra_id = ?1 ORDER BY cd.id",
+        )
+        .map_err(ApiV2Error::interno)?;
+    let detalles: Vec<Value> = stmt
+        .query_map(params![id], |r| {
+            Ok(json!({
+                "producto_id": r.get::<_, Option<i64>>(0)?,
+                "nombre": r.get::<_, String>(1)?,
+                "cantidad": r.get::<_, f64>(2)?,
+                "precio_unitario": r.get::<_, f64>(3)?,
+                "subtotal": r.get::<_, f64>(4)?,
+            }))
+        })
+        .map_err(ApiV2Error::interno)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ApiV2Error::interno)?;
+    compra["detalles"] = Value::Array(detalles);
+    Ok(Json(json!({ "data": compra })))
+}
+
+// ─── Reportes ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct RangoQuery {
+    pub desde: Option<String>,
+    pub hasta: Option<String>,
+    pub limite: Option<i64>,
+}
+
+/// Rango por defecto: últimos 30 días.
+fn rango(q: &RangoQuery) -> Result<(String, String), ApiV2Error> {
+    let hoy = chrono::Local::now().date_naive();
+    let desde = fecha(q.desde.clone())?
+        .unwrap_or_else(|| (hoy - chrono::Duration::days(30)).format("%Y-%m-%d").to_string());
+    let hasta = fecha(q.hasta.clone())?.unwrap_or_else(|| hoy.format("%Y-%m-%d").to_string());
+    Ok((desde, hasta))
+}
+
+/// `GET /api/v2/reportes/ventas-diarias?desde&hasta` — totales por día.
+pub async fn reporte_ventas_diarias(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Query(q): Query<RangoQuery>,
+) -> Resultado {
+    extraer_clave(&headers, &state)?.requiere("reportes:leer")?;
+    let (desde, hasta) = rango(&q)?;
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT date(fecha) AS dia, COUNT(*), COALESCE(SUM(subtotal_sin_iva + subtotal_con_iva), 0),
+                    COALESCE(SUM(iva), 0), COALESCE(SUM(total), 0)
+             FROM ventas
+             WHERE anulada = 0 AND COALESCE(tipo_estado, 'COMPLETADA') IN ('COMPLETADA', 'CONVERTIDA')
+               AND date(fecha) >= ?1 AND date(fecha) <= ?2
+             GROUP BY dia ORDER BY dia",
+        )
+        .map_err(ApiV2Error::interno)?;
+    let data: Vec<Value> = stmt
+        .query_map(params![desde, hasta], |r| {
+            Ok(json!({
+                "fecha": r.get::<_, String>(0)?,
+                "num_ventas": r.get::<_, i64>(1)?,
+                "subtotal": r.get::<_, f64>(2)?,
+                "iva": r.get::<_, f64>(3)?,
+                "total": r.get::<_, f64>(4)?,
+            }))
+        })
+        .map_err(ApiV2Error::interno)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ApiV2Error::interno)?;
+    Ok(Json(json!({ "data": data, "desde": desde, "hasta": hasta })))
+}
+
+/// `GET /api/v2/reportes/productos-mas-vendidos?desde&hasta&limite`.
+pub async fn reporte_productos_mas_vendidos(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Query(q): Query<RangoQuery>,
+) -> Resultado {
+    extraer_clave(&headers, &state)?.requiere("reportes:leer")?;
+    let (desde, hasta) = rango(&q)?;
+    let limite = q.limite.unwrap_or(20).clamp(1, 500);
+    let conn = state.db.conn.lock().map_err(ApiV2Error::interno)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT p.id, p.codigo, p.nombre, SUM(vd.cantidad), SUM(vd.subtotal)
+             FROM venta_detalles vd
+             JOIN ventas v ON vd.venta_id = v.id
+             JOIN productos p ON vd.producto_id = p.id
+             WHERE v.anulada = 0 AND COALESCE(v.tipo_estado, 'COMPLETADA') IN ('COMPLETADA', 'CONVERTIDA')
+               AND date(v.fecha) >= ?1 AND date(v.fecha) <= ?2
+             GROUP BY p.id ORDER BY SUM(vd.cantidad) DESC LIMIT ?3",
+        )
+        .map_err(ApiV2Error::interno)?;
+    let data: Vec<Value> = stmt
+        .query_map(params![desde, hasta, limite], |r| {
+            Ok(json!({
+                "producto_id": r.get::<_, i64>(0)?,
+                "codigo": r.get::<_, Option<String>>(1)?,
+                "nombre": r.get::<_, String>(2)?,
+                "cantidad": r.get::<_, f64>(3)?,
+                "total": r.get::<_, f64>(4)?,
+            }))
+        })
+        .map_err(ApiV2Error::interno)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ApiV2Error::interno)?;
+    Ok(Json(json!({ "data": data, "desde": desde, "hasta": hasta })))
+}
+
+// ─── Router ──────────────────────────────────────────────────────────────
+
+pub fn rutas() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/v2/openapi.json", get(openapi_json))
+        .route("/api/v2/productos", get(productos_listar))
+        .route("/api/v2/productos/:id", get(productos_obtener))
+        .route("/api/v2/stock", get(stock_listar))
+        .route("/api/v2/clientes", get(clientes_listar).post(clientes_crear))
+        .route("/api/v2/clientes/:id", get(clientes_obtener))
+        .route("/api/v2/ventas", get(ventas_listar))
+        .route("/api/v2/ventas/:id", get(ventas_obtener))
+        .route("/api/v2/compras", get(compras_listar))
+        .route("/api/v2/compras/:id", get(compras_obtener))
+        .route("/api/v2/reportes/ventas-diarias", get(reporte_ventas_diarias))
+        .route("/api/v2/reportes/productos-mas-vendidos", get(reporte_productos_mas_vendidos))
+}
@@ -0,0 +1,61 @@
+//! API pública REST versionada (`/api/v2/*`) para integraciones externas:
+//! tienda e-commerce, herramientas de BI, contabilidad.
+//!
+//! A diferencia de `/api/v1/invoke` (nombre de comando + args sin tipar, solo
+//! para terminales Multi-POS) y de `/api/v1/app/*` (sesiones por usuario de la
+//! app móvil), aquí la auth es por **API key** con **scopes**: cada integración
+//! recibe su propia clave y solo ve los recursos que se le habilitaron.
+//!
+//! # Estructura del módulo
+//!
+//! - [`schema`]   — tabla `api_keys`
+//! - [`auth`]     — validación de la clave y scopes
+//! - [`http`]     — handlers y router `/api/v2/*`
+//! - [`openapi`]  — documento OpenAPI 3 servido en `/api/v2/openapi.json`
+//! - [`commands`] — comandos Tauri para que el admin cree/revoque claves
+//!
+//! # Estabilidad
+//!
+//! Los nombres de campos y rutas de `/api/v2` son un contrato: NO se renombran
+//! ni se quitan campos. Agregar campos nuevos sí está permitido.
+
+pub mod auth;
+pub mod commands;
+pub mod http;
+pub mod openapi;
+pub mod schema;
+
+use crate::db::Database;
+
+/// Scopes disponibles. `*` habilita todos.
+pub const SCOPES: [&str; 8] = [
+    "productos:leer",
+    "stock:leer",
+    "clientes:leer",
+    "clientes:escribir",
+    "ventas:leer",
+    "compras:leer",
+    "reportes:leer",
+    "*",
+];
+
+/// Inicializa el módulo: corre migraciones SQL.
+pub fn init(db: &Database) -> Result<(), rusqlite::Error> {
+    let conn = db.conn.lock().unwrap();
+    schema::create_tables(&conn)?;
+    Ok(())
+}
+
+/// ¿Hay al menos una API key activa? Si sí, el servidor HTTP debe arrancar
+/// aunque no haya Multi-POS ni app móvil.
+pub fn hay_claves_activas(db: &Database) -> bool {
+    db.conn
+        .lock()
+        .ok()
+        .and_then(|c| {
+            c.query_row("SELECT COUNT(*) FROM api_keys WHERE activa = 1", [], |r| r.get::<_, i64>(0))
+                .ok()
+        })
+        .unwrap_or(0)
+        > 0
+}
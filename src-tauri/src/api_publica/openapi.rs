@@ -0,0 +1,293 @@
+//! Documento OpenAPI 3.0 de `/api/v2`.
+//!
+//! Se arma a mano con `serde_json::json!` (no hay crate de generación en el
+//! proyecto). Al agregar una ruta en [`super::http::rutas`] hay que agregarla
+//! aquí también — el documento es lo que leen los equipos de integración.
+
+use serde_json::{json, Value};
+
+/// Parámetro de query opcional.
+fn param(nombre: &str, tipo: &str, descripcion: &str) -> Value {
+    json!({
+        "name": nombre,
+        "in": "query",
+        "required": false,
+        "description": descripcion,
+        "schema": { "type": tipo },
+    })
+}
+
+fn param_id() -> Value {
+    json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } })
+}
+
+fn paginacion() -> Vec<Value> {
+    vec![
+        param("pagina", "integer", "Página (desde 1)"),
+        param("por_pagina", "integer", "Registros por página (1-500, default 50)"),
+    ]
+}
+
+fn rango_fechas() -> Vec<Value> {
+    vec![
+        param("desde", "string", "Fecha inicial inclusive (YYYY-MM-DD)"),
+        param("hasta", "string", "Fecha final inclusive (YYYY-MM-DD)"),
+    ]
+}
+
+/// Respuesta `{ data: [...], pagina, por_pagina, total }` del schema dado.
+fn lista(schema: &str) -> Value {
+    json!({
+        "200": {
+            "description": "Listado paginado",
+            "content": { "application/json": { "schema": {
+                "type": "object",
+                "properties": {
+                    "data": { "type": "array", "items": { "$ref": format!("#/components/schemas/{}", schema) } },
+                    "pagina": { "type": "integer" },
+                    "por_pagina": { "type": "integer" },
+                    "total": { "type": "integer" },
+                },
+            }}},
+        },
+        "401": { "$ref": "#/components/responses/NoAutorizado" },
+        "403": { "$ref": "#/components/responses/SinScope" },
+    })
+}
+
+/// Respuesta `{ data: [...], desde, hasta }` de los reportes (sin paginar).
+fn reporte(schema: &str) -> Value {
+    json!({
+        "200": {
+            "description": "Reporte del rango",
+            "content": { "application/json": { "schema": {
+                "type": "object",
+                "properties": {
+                    "data": { "type": "array", "items": { "$ref": format!("#/components/schemas/{}", schema) } },
+                    "desde": { "type": "string" },
+                    "hasta": { "type": "string" },
+                },
+            }}},
+        },
+        "401": { "$ref": "#/components/responses/NoAutorizado" },
+        "403": { "$ref": "#/components/responses/SinScope" },
+    })
+}
+
+/// Respuesta `{ data: {...} }` del schema dado.
+fn uno(schema: &str) -> Value {
+    json!({
+        "200": {
+            "description": "OK",
+            "content": { "application/json": { "schema": {
+                "type": "object",
+                "properties": { "data": { "$ref": format!("#/components/schemas/{}", schema) } },
+            }}},
+        },
+        "401": { "$ref": "#/components/responses/NoAutorizado" },
+        "403": { "$ref": "#/components/responses/SinScope" },
+        "404": { "$ref": "#/components/responses/NoEncontrado" },
+    })
+}
+
+fn operacion(tag: &str, resumen: &str, scope: &str, parametros: Vec<Value>, respuestas: Value) -> Value {
+    json!({
+        "tags": [tag],
+        "summary": resumen,
+        "description": format!("Scope requerido: `{}`", scope),
+        "parameters": parametros,
+        "responses": respuestas,
+    })
+}
+
+/// Objeto con propiedades `(nombre, tipo)`; tipo `"string?"` = nullable.
+fn objeto(props: &[(&str, &str)]) -> Value {
+    let mut map = serde_json::Map::new();
+    for (nombre, tipo) in props {
+        let (t, nullable) = match tipo.strip_suffix('?') {
+            Some(t) => (t, true),
+            None => (*tipo, false),
+        };
+        map.insert(nombre.to_string(), json!({ "type": t, "nullable": nullable }));
+    }
+    json!({ "type": "object", "properties": map })
+}
+
+pub fn documento() -> Value {
+    let productos_params = [
+        vec![
+            param("q", "string", "Busca en nombre, código y código de barras"),
+            param("categoria_id", "integer", "Filtra por categoría"),
+            param("activo", "boolean", "Filtra por estado activo"),
+            param("actualizado_desde", "string", "Solo modificados desde este timestamp (YYYY-MM-DD HH:MM:SS)"),
+        ],
+        paginacion(),
+    ]
+    .concat();
+    let stock_params = [
+        vec![
+            param("producto_id", "integer", "Un solo producto"),
+            param("establecimiento_id", "integer", "Stock de un establecimiento en lugar del global"),
+            param("bajo_minimo", "boolean", "Solo productos en o bajo su stock mínimo"),
+        ],
+        paginacion(),
+    ]
+    .concat();
+    let clientes_params = [
+        vec![
+            param("q", "string", "Busca en nombre e identificación"),
+            param("identificacion", "string", "Identificación exacta (cédula/RUC)"),
+        ],
+        paginacion(),
+    ]
+    .concat();
+    let ventas_params = [
+        rango_fechas(),
+        vec![
+            param("cliente_id", "integer", "Filtra por cliente"),
+            param("tipo_documento", "string", "FACTURA, NOTA_VENTA, ..."),
+            param("incluir_anuladas", "boolean", "Incluye ventas anuladas (default false)"),
+        ],
+        paginacion(),
+    ]
+    .concat();
+    let compras_params = [
+        rango_fechas(),
+        vec![
+            param("proveedor_id", "integer", "Filtra por proveedor"),
+            param("estado", "string", "REGISTRADA, ANULADA, ..."),
+        ],
+        paginacion(),
+    ]
+    .concat();
+    let reportes_params = rango_fechas();
+    let top_params = [rango_fechas(), vec![param("limite", "integer", "Máximo de productos (default 20)")]].concat();
+
+    let mut venta_detalle = objeto(&[
+        ("producto_id", "integer?"),
+        ("nombre", "string"),
+        ("codigo", "string?"),
+        ("cantidad", "number"),
+        ("precio_unitario", "number"),
+        ("descuento", "number"),
+        ("iva_porcentaje", "number"),
+        ("subtotal", "number"),
+    ]);
+    venta_detalle["description"] = json!("Línea de venta (solo en GET /ventas/{id})");
+
+    let paths = json!({
+        "/api/v2/productos": { "get": operacion("productos", "Listar productos", "productos:leer", productos_params, lista("Producto")) },
+        "/api/v2/productos/{id}": { "get": operacion("productos", "Obtener producto", "productos:leer", vec![param_id()], uno("Producto")) },
+        "/api/v2/stock": { "get": operacion("stock", "Stock por producto (global o por establecimiento)", "stock:leer", stock_params, lista("Stock")) },
+        "/api/v2/clientes": {
+            "get": operacion("clientes", "Listar clientes", "clientes:leer", clientes_params, lista("Cliente")),
+            "post": {
+                "tags": ["clientes"],
+                "summary": "Crear cliente (idempotente por identificación)",
+                "description": "Scope requerido: `clientes:escribir`. Si la identificación ya existe responde 200 con el cliente existente.",
+                "requestBody": { "required": true, "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ClienteNuevo" } } } },
+                "responses": {
+                    "201": { "description": "Creado" },
+                    "200": { "description": "Ya existía" },
+                    "400": { "$ref": "#/components/responses/Invalida" },
+                },
+            },
+        },
+        "/api/v2/clientes/{id}": { "get": operacion("clientes", "Obtener cliente", "clientes:leer", vec![param_id()], uno("Cliente")) },
+        "/api/v2/ventas": { "get": operacion("ventas", "Listar ventas", "ventas:leer", ventas_params, lista("Venta")) },
+        "/api/v2/ventas/{id}": { "get": operacion("ventas", "Obtener venta con detalles", "ventas:leer", vec![param_id()], uno("Venta")) },
+        "/api/v2/compras": { "get": operacion("compras", "Listar compras", "compras:leer", compras_params, lista("Compra")) },
+        "/api/v2/compras/{id}": { "get": operacion("compras", "Obtener compra con detalles", "compras:leer", vec![param_id()], uno("Compra")) },
+        "/api/v2/reportes/ventas-diarias": { "get": operacion("reportes", "Totales de venta por día (default últimos 30 días)", "reportes:leer", reportes_params, reporte("VentaDiaria")) },
+        "/api/v2/reportes/productos-mas-vendidos": { "get": operacion("reportes", "Ranking de productos por cantidad vendida", "reportes:leer", top_params, reporte("ProductoVendido")) },
+    });
+
+    let schemas = json!({
+        "Error": {
+            "type": "object",
+            "properties": { "error": objeto(&[("codigo", "string"), ("mensaje", "string")]) },
+        },
+        "Producto": objeto(&[
+            ("id", "integer"), ("codigo", "string?"), ("codigo_barras", "string?"),
+            ("nombre", "string"), ("descripcion", "string?"), ("categoria_id", "integer?"),
+            ("categoria", "string?"), ("precio_venta", "number"), ("iva_porcentaje", "number"),
+            ("incluye_iva", "boolean"), ("stock_actual", "number"), ("unidad_medida", "string"),
+            ("es_servicio", "boolean"), ("activo", "boolean"), ("actualizado_at", "string"),
+        ]),
+        "Stock": objeto(&[
+            ("producto_id", "integer"), ("codigo", "string?"), ("nombre", "string"),
+            ("establecimiento_id", "integer?"), ("stock_actual", "number"), ("stock_minimo", "number"),
+        ]),
+        "Cliente": objeto(&[
+            ("id", "integer"), ("tipo_identificacion", "string"), ("identificacion", "string?"),
+            ("nombre", "string"), ("direccion", "string?"), ("telefono", "string?"),
+            ("email", "string?"), ("activo", "boolean"), ("actualizado_at", "string"),
+        ]),
+        "ClienteNuevo": objeto(&[
+            ("tipo_identificacion", "string?"), ("identificacion", "string?"), ("nombre", "string"),
+            ("direccion", "string?"), ("telefono", "string?"), ("email", "string?"),
+        ]),
+        "Venta": {
+            "allOf": [
+                objeto(&[
+                    ("id", "integer"), ("numero", "string"), ("fecha", "string"),
+                    ("cliente_id", "integer?"), ("cliente_nombre", "string?"), ("cliente_identificacion", "string?"),
+                    ("subtotal_sin_iva", "number"), ("subtotal_con_iva", "number"), ("descuento", "number"),
+                    ("iva", "number"), ("total", "number"), ("forma_pago", "string"),
+                    ("tipo_documento", "string"), ("estado_sri", "string?"), ("autorizacion_sri", "string?"),
+                    ("anulada", "boolean"),
+                ]),
+                { "type": "object", "properties": { "detalles": { "type": "array", "items": venta_detalle } } },
+            ],
+        },
+        "Compra": {
+            "allOf": [
+                objeto(&[
+                    ("id", "integer"), ("numero", "string"), ("fecha", "string"),
+                    ("proveedor_id", "integer"), ("proveedor_nombre", "string?"), ("proveedor_ruc", "string?"),
+                    ("numero_factura", "string?"), ("subtotal", "number"), ("iva", "number"),
+                    ("total", "number"), ("estado", "string"), ("forma_pago", "string"), ("es_credito", "boolean"),
+                ]),
+                { "type": "object", "properties": { "detalles": { "type": "array", "items": objeto(&[
+                    ("producto_id", "integer?"), ("nombre", "string"), ("cantidad", "number"),
+                    ("precio_unitario", "number"), ("subtotal", "number"),
+                ]) } } },
+            ],
+        },
+        "VentaDiaria": objeto(&[
+            ("fecha", "string"), ("num_ventas", "integer"), ("subtotal", "number"),
+            ("iva", "number"), ("total", "number"),
+        ]),
+        "ProductoVendido": objeto(&[
+            ("producto_id", "integer"), ("codigo", "string?"), ("nombre", "string"),
+            ("cantidad", "number"), ("total", "number"),
+        ]),
+    });
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Clouget POS — API pública",
+            "version": "2.0.0",
+            "description": "API REST para integraciones (e-commerce, BI, contabilidad). \
+                Auth con API key creada en Configuración → Integraciones. \
+                Los campos existentes no se renombran ni se quitan dentro de v2.",
+        },
+        "servers": [{ "url": "/" }],
+        "security": [{ "ApiKeyBearer": [] }, { "ApiKeyHeader": [] }],
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "ApiKeyBearer": { "type": "http", "scheme": "bearer", "description": "Authorization: Bearer ck_..." },
+                "ApiKeyHeader": { "type": "apiKey", "in": "header", "name": "X-API-Key" },
+            },
+            "responses": {
+                "NoAutorizado": { "description": "API key faltante, inválida o revocada", "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } } },
+                "SinScope": { "description": "La API key no tiene el scope requerido", "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } } },
+                "NoEncontrado": { "description": "Recurso no encontrado", "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } } },
+                "Invalida": { "description": "Parámetros inválidos", "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } } },
+            },
+            "schemas": schemas,
+        },
+    })
+}
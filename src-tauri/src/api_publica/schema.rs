@@ -0,0 +1,31 @@
+//! Schema SQL de la API pública.
+//!
+//! Tablas:
+//! - `api_keys` — claves de integración con sus scopes
+//!
+//! La clave completa (`ck_<prefijo>_<secreto>`) se muestra UNA sola vez al
+//! crearla. En BD solo queda el prefijo (para buscarla y mostrarla en la UI)
+//! y el hash SHA-256 con salt del secreto, igual que los PIN de usuarios.
+
+use rusqlite::Connection;
+
+/// Crea las tablas del módulo si no existen.
+pub fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS api_keys (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL,                     -- 'Tienda Shopify', 'Power BI', etc.
+            prefijo TEXT NOT NULL UNIQUE,             -- 8 hex, visible en la UI
+            key_salt TEXT NOT NULL,
+            key_hash TEXT NOT NULL,
+            scopes TEXT NOT NULL DEFAULT '[]',        -- JSON array de scopes (ver api_publica::SCOPES)
+            activa INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            last_used_at TEXT,
+            revocada_at TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_api_keys_prefijo ON api_keys(prefijo);
+        ",
+    )
+}
@@ -19,6 +19,8 @@ pub struct DispositivoApp {
     pub dispositivo_nombre: Option<String>,
     pub dispositivo_modelo: Option<String>,
     pub dispositivo_so: Option<String>,
+    /// APP (celular emparejado) | KDS (pantalla de cocina web)
+    pub tipo: String,
     pub created_at: String,
     pub last_used_at: String,
     pub revoked: bool,
@@ -36,7 +38,8 @@ pub fn app_listar_dispositivos(db: State<'_, Database>) -> Result<Vec<Dispositiv
             "SELECT t.id, t.usuario_id, u.nombre,
                     t.dispositivo_nombre, t.dispositivo_modelo, t.dispositivo_so,
                     t.created_at, t.last_used_at, t.revoked,
-                    CAST((julianday('now', 'localtime') - julianday(t.last_used_at)) * 24 * 60 AS INTEGER) AS mins
+                    CAST((julianday('now', 'localtime') - julianday(t.last_used_at)) * 24 * 60 AS INTEGER) AS mins,
+                    t.tipo
              FROM app_tokens t
              JOIN usuarios u ON t.usuario_id = u.id
              ORDER BY t.revoked ASC, t.last_used_at DESC",
@@ -56,6 +59,7 @@ pub fn app_listar_dispositivos(db: State<'_, Database>) -> Result<Vec<Dispositiv
                 last_used_at: r.get(7)?,
                 revoked: r.get::<_, i64>(8)? != 0,
                 minutos_inactivo: r.get::<_, Option<i64>>(9)?.unwrap_or(0),
+                tipo: r.get(10)?,
             })
         })
         .map_err(|e| e.to_string())?
@@ -13,6 +13,9 @@
 //! - `GET  /api/v1/app/mesas`        — grid de mesas (requiere atiende_mesas o ve_cocina)
 //! - `GET  /api/v1/app/ping`         — sin auth, prueba conectividad
 //!
+//! Los logins (`auth/pin`, `auth/password`) tienen límite de intentos por IP
+//! (middleware en `server::rate_limit`) y de fallos por usuario (aquí).
+//!
 //! Próximos sprints (3b/3c):
 //! - Endpoints de pedidos completos (abrir, items, cocina, dividir, unir, cobrar)
 //! - mDNS discovery
@@ -168,6 +171,7 @@ pub fn extract_app_session(
          JOIN usuarios u ON t.usuario_id = u.id
          WHERE t.token = ?1
            AND t.revoked = 0
+           AND t.tipo = 'APP'
            AND u.activo = 1",
         params![token],
         |r| {
@@ -341,6 +345,11 @@ pub async fn auth_pin(
         return Err((StatusCode::FORBIDDEN, Json(ApiError::new(msg))));
     }
 
+    // Límite de PINs fallidos por usuario (el límite por IP lo aplica el
+    // middleware `server::rate_limit::limitar_auth_por_ip`).
+    let clave_limite = format!("usuario:{}", req.usuario_id);
+    bloqueo_por_fallos(&state, &clave_limite)?;
+
     let pin = req.pin.trim();
     if pin.len() < 4 {
         return Err((
@@ -383,6 +392,7 @@ pub async fn auth_pin(
     // 3. Verificar PIN (mismo método que el comando local de login)
     let pin_calc = crate::utils::hash_pin(&pin_salt, pin);
     if pin_calc != pin_hash {
+        state.rate_limiter.registrar(&clave_limite);
         return Err((
             StatusCode::UNAUTHORIZED,
             Json(ApiError::new("PIN incorrecto")),
@@ -426,6 +436,8 @@ pub async fn auth_pin(
         ));
     }
 
+    state.rate_limiter.limpiar(&clave_limite);
+
     // 5. Generar token (UUID v4) y persistir
     let token = uuid::Uuid::new_v4().to_string();
     conn.execute(
@@ -473,6 +485,23 @@ pub struct LoginPasswordRequest {
     pub dispositivo_so: Option<String>,
 }
 
+/// 429 si la clave (`usuario:<id>`) acumuló demasiados intentos fallidos.
+fn bloqueo_por_fallos(state: &Arc<ServerState>, clave: &str) -> Result<(), (StatusCode, Json<ApiError>)> {
+    use crate::server::rate_limit::{MAX_FALLOS_POR_USUARIO, VENTANA_USUARIO};
+    state
+        .rate_limiter
+        .bloqueado(clave, MAX_FALLOS_POR_USUARIO, VENTANA_USUARIO)
+        .map_err(|seg| {
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(ApiError::new(format!(
+                    "Demasiados intentos fallidos para este usuario. Espere {} segundos.",
+                    seg
+                ))),
+            )
+        })
+}
+
 const PERMISOS_APP: [&str; 9] = [
     "atiende_mesas", "ve_cocina", "vende_piso", "inventaria", "dueno_dashboard",
     "cobra_caja", "gestionar_servicio_tecnico", "ver_servicio_tecnico", "recibir_abonos_st",
@@ -492,6 +521,11 @@ pub async fn auth_password(
         return Err((StatusCode::BAD_REQUEST, Json(ApiError::new("Ingrese su contraseña"))));
     }
 
+    let clave_limite = req.usuario_id.map(|id| format!("usuario:{}", id));
+    if let Some(clave) = &clave_limite {
+        bloqueo_por_fallos(&state, clave)?;
+    }
+
     let conn = state.db.conn.lock().map_err(err500)?;
 
     // Recorrer usuarios activos con password configurada y comparar el hash.
@@ -515,9 +549,15 @@ pub async fn auth_password(
             break;
         }
     }
-    let (id, nombre, rol, permisos_json) = encontrado.ok_or((
-        StatusCode::UNAUTHORIZED, Json(ApiError::new("Contraseña incorrecta")),
-    ))?;
+    let Some((id, nombre, rol, permisos_json)) = encontrado else {
+        if let Some(clave) = &clave_limite {
+            state.rate_limiter.registrar(clave);
+        }
+        return Err((StatusCode::UNAUTHORIZED, Json(ApiError::new("Contraseña incorrecta"))));
+    };
+    if let Some(clave) = &clave_limite {
+        state.rate_limiter.limpiar(clave);
+    }
 
     let permisos: Vec<String> = serde_json::from_str::<serde_json::Value>(&permisos_json)
         .ok()
@@ -818,6 +858,7 @@ pub async fn pedidos_abrir(
     if mesa_existe == 0 {
         return Err(err400("Mesa no encontrada o inactiva"));
     }
+    crate::restaurante::reservas::validar_mesa_sin_reserva(&conn, req.mesa_id).map_err(err400)?;
 
     // Validar que no haya pedido activo en esta mesa
     let abierto: i64 = conn
@@ -893,11 +934,37 @@ pub async fn pedidos_de_mesa(
     }
 }
 
+/// `GET /api/v1/app/productos/:id/modificadores` — grupos de modificadores
+/// activos del producto (término, extras...) para armar el selector.
+pub async fn producto_modificadores(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(producto_id): Path<i64>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let session = extract_app_session(&headers, &state)?;
+    requiere_restaurante(&state)?;
+    session.requiere("atiende_mesas")?;
+
+    let conn = state.db.conn.lock().map_err(err500)?;
+    let grupos = crate::restaurante::modificadores::listar_grupos(&conn, Some(producto_id), true)
+        .map_err(err500)?;
+    Ok(Json(serde_json::json!({ "ok": true, "grupos": grupos })))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AgregarItemRequest {
     pub producto_id: i64,
     pub cantidad: Option<f64>,
     pub info_adicional: Option<String>,
+    /// IDs de `rest_modificadores` elegidos (ver `GET /productos/:id/modificadores`).
+    #[serde(default)]
+    pub modificadores: Vec<i64>,
+    /// ENTRADA | FUERTE | POSTRE. Sin valor se usa el tiempo por defecto del producto.
+    #[serde(default)]
+    pub curso: Option<String>,
+    /// true = el item espera a que se dispare su tiempo (`POST /pedidos/:id/disparar-curso`).
+    #[serde(default)]
+    pub retener: bool,
 }
 
 /// `POST /api/v1/app/pedidos/:id/items` — agrega item al pedido.
@@ -935,6 +1002,12 @@ pub async fn pedidos_agregar_item(
         )
         .map_err(|_| err400("Producto no encontrado o inactivo"))?;
 
+    let elegidos = crate::restaurante::modificadores::resolver(&conn, req.producto_id, &req.modificadores)
+        .map_err(err400)?;
+    let precio = precio + crate::restaurante::modificadores::precio_extra(&elegidos);
+    let curso = crate::restaurante::tiempos::curso_para_item(&conn, req.producto_id, req.curso.as_deref())
+        .map_err(err400)?;
+
     let cantidad = req.cantidad.unwrap_or(1.0);
     let es_directo = destino == "DIRECTO";
 
@@ -942,19 +1015,21 @@ pub async fn pedidos_agregar_item(
         conn.execute(
             "INSERT INTO rest_pedido_items
              (pedido_id, producto_id, cantidad, precio_unit, info_adicional,
-              enviado_cocina, estado_cocina, fecha_envio_cocina)
-             VALUES (?1, ?2, ?3, ?4, ?5, 1, 'ENTREGADO', datetime('now', 'localtime'))",
-            params![pedido_id, req.producto_id, cantidad, precio, req.info_adicional],
+              enviado_cocina, estado_cocina, fecha_envio_cocina, curso)
+             VALUES (?1, ?2, ?3, ?4, ?5, 1, 'ENTREGADO', datetime('now', 'localtime'), ?6)",
+            params![pedido_id, req.producto_id, cantidad, precio, req.info_adicional, curso],
         ).map_err(err500)?;
     } else {
+        let estado_cocina = if req.retener { "RETENIDO" } else { "PENDIENTE" };
         conn.execute(
-            "INSERT INTO rest_pedido_items (pedido_id, producto_id, cantidad, precio_unit, info_adicional)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![pedido_id, req.producto_id, cantidad, precio, req.info_adicional],
+            "INSERT INTO rest_pedido_items (pedido_id, producto_id, cantidad, precio_unit, info_adicional, curso, estado_cocina)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![pedido_id, req.producto_id, cantidad, precio, req.info_adicional, curso, estado_cocina],
         ).map_err(err500)?;
     }
 
     let item_id = conn.last_insert_rowid();
+    crate::restaurante::modificadores::guardar(&conn, item_id, &elegidos).map_err(err500)?;
     Ok(Json(serde_json::json!({ "ok": true, "item_id": item_id })))
 }
 
@@ -1006,9 +1081,9 @@ pub async fn pedidos_enviar_cocina(
     let items: Vec<serde_json::Value> = {
         let mut stmt = conn.prepare(
             "SELECT i.id, p.nombre, i.cantidad, i.info_adicional,
-                    COALESCE(p.destino_preparacion, 'COCINA') as destino
+                    COALESCE(p.destino_preparacion, 'COCINA') as destino, i.modificadores, i.curso
              FROM rest_pedido_items i JOIN productos p ON i.producto_id = p.id
-             WHERE i.pedido_id = ?1 AND i.enviado_cocina = 0
+             WHERE i.pedido_id = ?1 AND i.enviado_cocina = 0 AND i.estado_cocina <> 'RETENIDO'
              ORDER BY i.id",
         ).map_err(err500)?;
         let rows = stmt.query_map(params![pedido_id], |r| {
@@ -1018,6 +1093,8 @@ pub async fn pedidos_enviar_cocina(
                 "cantidad": r.get::<_, f64>(2)?,
                 "info_adicional": r.get::<_, Option<String>>(3)?,
                 "destino_preparacion": r.get::<_, String>(4)?,
+                "modificadores": r.get::<_, Option<String>>(5)?,
+                "curso": r.get::<_, Option<String>>(6)?,
             }))
         }).map_err(err500)?
           .collect::<Result<Vec<_>, _>>().map_err(err500)?;
@@ -1031,7 +1108,7 @@ pub async fn pedidos_enviar_cocina(
     conn.execute(
         "UPDATE rest_pedido_items
          SET enviado_cocina = 1, fecha_envio_cocina = datetime('now', 'localtime')
-         WHERE pedido_id = ?1 AND enviado_cocina = 0",
+         WHERE pedido_id = ?1 AND enviado_cocina = 0 AND estado_cocina <> 'RETENIDO'",
         params![pedido_id],
     ).map_err(err500)?;
 
@@ -1044,8 +1121,27 @@ pub async fn pedidos_enviar_cocina(
         |r| r.get(0),
     ).unwrap_or_else(|_| format!("Pedido #{}", pedido_id));
 
+    let por_estaciones = crate::restaurante::estaciones::hay_estaciones(&conn);
+
     drop(conn); // soltamos el lock antes del spawn async para evitar bloquear
 
+    // Con estaciones configuradas el POS imprime cada comanda en su estación
+    // sin esperar a que el cajero la lance desde el escritorio.
+    if por_estaciones {
+        let db = state.db.clone();
+        let ids: Vec<i64> = items.iter().filter_map(|i| i.get("id").and_then(|v| v.as_i64())).collect();
+        tokio::task::spawn_blocking(move || {
+            match crate::restaurante::estaciones::imprimir_por_estacion(&db, pedido_id, &ids) {
+                Ok(resultados) => {
+                    for r in resultados.iter().filter(|r| r.estado == "FALLIDA") {
+                        eprintln!("[Restaurante] Comanda {} ({}) sin imprimir", r.comanda_id, r.estacion_nombre);
+                    }
+                }
+                Err(e) => eprintln!("[Restaurante] Error imprimiendo comandas del pedido {}: {}", pedido_id, e),
+            }
+        });
+    }
+
     // v0.2 Sprint 6.2: push notification a cocineros con permiso ve_cocina
     if let Ok(tokens) = super::push::tokens_por_permiso(&state.db, "ve_cocina") {
         let total_items: f64 = items.iter()
@@ -1074,6 +1170,66 @@ pub async fn pedidos_enviar_cocina(
     })))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct DispararCursoRequest {
+    /// ENTRADA | FUERTE | POSTRE; sin valor dispara todo lo retenido.
+    #[serde(default)]
+    pub curso: Option<String>,
+}
+
+/// `POST /api/v1/app/pedidos/:id/disparar-curso` — los items retenidos del
+/// tiempo salen a cocina (y se imprimen por estación si hay estaciones).
+pub async fn pedidos_disparar_curso(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(pedido_id): Path<i64>,
+    Json(req): Json<DispararCursoRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let session = extract_app_session(&headers, &state)?;
+    requiere_restaurante(&state)?;
+    session.requiere("atiende_mesas")?;
+
+    let (ids, por_estaciones) = {
+        let conn = state.db.conn.lock().map_err(err500)?;
+        let ids = crate::restaurante::tiempos::disparar_curso(&conn, pedido_id, req.curso.as_deref())
+            .map_err(err400)?;
+        (ids, crate::restaurante::estaciones::hay_estaciones(&conn))
+    };
+
+    if por_estaciones {
+        let db = state.db.clone();
+        let ids = ids.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = crate::restaurante::estaciones::imprimir_por_estacion(&db, pedido_id, &ids) {
+                eprintln!("[Restaurante] Error imprimiendo tiempo del pedido {}: {}", pedido_id, e);
+            }
+        });
+    }
+
+    Ok(Json(serde_json::json!({ "ok": true, "items_ids": ids, "total": ids.len() })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RetenerItemRequest {
+    pub retener: bool,
+}
+
+/// `POST /api/v1/app/pedidos/items/:item_id/retener` — retiene o libera un item no enviado.
+pub async fn pedidos_retener_item(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(item_id): Path<i64>,
+    Json(req): Json<RetenerItemRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let session = extract_app_session(&headers, &state)?;
+    requiere_restaurante(&state)?;
+    session.requiere("atiende_mesas")?;
+
+    let conn = state.db.conn.lock().map_err(err500)?;
+    crate::restaurante::tiempos::retener_item(&conn, item_id, req.retener).map_err(err400)?;
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
+
 /// `POST /api/v1/app/pedidos/:id/pedir-cuenta` — marca CUENTA_PEDIDA.
 pub async fn pedidos_pedir_cuenta(
     AxumState(state): AxumState<Arc<ServerState>>,
@@ -1123,6 +1279,24 @@ pub struct CobrarPedidoRequest {
     pub es_fiado: bool,
     pub cliente_id: Option<i64>,
     pub tipo_documento: Option<String>,
+    /// Propina / cargo por servicio (se registra aparte de la venta).
+    #[serde(default)]
+    pub propina: Option<crate::restaurante::models::PropinaCobro>,
+}
+
+/// Registra la propina de un cobro hecho desde la app. Se valida antes de
+/// crear la venta (`propinas::validar`), así que aquí solo fallan errores de BD.
+fn registrar_propina_app(
+    conn: &rusqlite::Connection,
+    venta_id: i64,
+    pedido_id: i64,
+    subcuenta_id: Option<i64>,
+    propina: Option<&crate::restaurante::models::PropinaCobro>,
+) -> Result<f64, (StatusCode, Json<ApiError>)> {
+    match propina {
+        Some(p) => crate::restaurante::propinas::registrar(conn, venta_id, pedido_id, subcuenta_id, p).map_err(err500),
+        None => Ok(0.0),
+    }
 }
 
 /// `POST /api/v1/app/pedidos/:id/cobrar` — combo: registrar venta + cerrar pedido.
@@ -1165,7 +1339,10 @@ pub async fn pedidos_cobrar(
             "descuento": 0.0,
             "iva_porcentaje": 0.0,
             "subtotal": i.cantidad * i.precio_unit,
-            "info_adicional": i.info_adicional,
+            "info_adicional": crate::restaurante::modificadores::info_venta(
+                i.modificadores.as_deref(),
+                i.info_adicional.as_deref(),
+            ),
         })
     }).collect();
 
@@ -1223,6 +1400,11 @@ pub async fn pedidos_cobrar(
         })
     };
 
+    // La propina se valida antes de crear la venta: después ya no se puede rechazar el cobro.
+    if let Some(p) = &req.propina {
+        crate::restaurante::propinas::validar(p).map_err(err400)?;
+    }
+
     // 3. Registrar venta vía dispatch (reusa toda la lógica del POS: SRI, secuencial, kardex, etc.)
     let resultado = crate::server::dispatch::dispatch_command(
         &state, "registrar_venta", venta_args
@@ -1236,27 +1418,37 @@ pub async fn pedidos_cobrar(
         .ok_or_else(|| err500("Respuesta de venta sin id"))?;
 
     // 4. Marcar pedido como COBRADO (libera la mesa principal y todas las extras automáticamente)
-    {
-        let conn = state.db.conn.lock().map_err(err500)?;
-        conn.execute(
+    let propina = {
+        let mut conn = state.db.conn.lock().map_err(err500)?;
+        // Propina, cierre e insumos de modificadores en una sola transacción
+        let tx = conn.transaction().map_err(err500)?;
+        let propina = registrar_propina_app(&tx, venta_id, pedido_id, None, req.propina.as_ref())?;
+        tx.execute(
             "UPDATE rest_pedidos_abiertos
              SET estado = 'COBRADO', venta_id = ?1, fecha_cierre = datetime('now', 'localtime')
              WHERE id = ?2",
             params![venta_id, pedido_id],
         ).map_err(err500)?;
         // v2.5.91 — los abonos en HOLDING pasan a APLICADO (ya forman parte de la venta).
-        conn.execute(
+        tx.execute(
             "UPDATE rest_pedido_abonos
              SET estado = 'APLICADO', venta_id_aplicado = ?1, fecha_aplicado = datetime('now', 'localtime')
              WHERE pedido_id = ?2 AND estado = 'HOLDING'",
             params![venta_id, pedido_id],
         ).map_err(err500)?;
-    }
+        crate::restaurante::entregas::al_cobrar(&tx, pedido_id).map_err(err500)?;
+        // Insumos de modificadores (la venta ya descontó los productos en sí)
+        let partes = crate::restaurante::commands::partes_pedido(&tx, pedido_id).map_err(err500)?;
+        crate::restaurante::modificadores::descontar_insumos(&tx, venta_id, &partes).map_err(err500)?;
+        tx.commit().map_err(err500)?;
+        propina
+    };
 
     Ok(Json(serde_json::json!({
         "ok": true,
         "venta_id": venta_id,
         "venta": resultado.get("venta"),
+        "propina": propina,
     })))
 }
 
@@ -1297,45 +1489,25 @@ pub async fn pedidos_abono(
 
 // ─── Cocina (Sprint 3b) ──────────────────────────────────────────────────
 
-/// `GET /api/v1/app/cocina/items` — lista items pendientes en cocina.
+#[derive(Debug, Deserialize)]
+pub struct CocinaListarQuery {
+    /// Filtra por estación (0 = 'General').
+    pub estacion_id: Option<i64>,
+}
+
+/// `GET /api/v1/app/cocina/items` — lista items pendientes en cocina con
+/// tiempos (edad del ticket, objetivo, atrasado).
 pub async fn cocina_listar(
     AxumState(state): AxumState<Arc<ServerState>>,
     headers: HeaderMap,
+    Query(qp): Query<CocinaListarQuery>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
     let session = extract_app_session(&headers, &state)?;
     requiere_restaurante(&state)?;
     session.requiere("ve_cocina")?;
 
     let conn = state.db.conn.lock().map_err(err500)?;
-    let mut stmt = conn.prepare(
-        "SELECT i.id, i.pedido_id, m.nombre, z.nombre, p.mesero_nombre,
-                pr.nombre, i.cantidad, i.info_adicional, i.estado_cocina, i.fecha_envio_cocina,
-                CAST((julianday('now', 'localtime') - julianday(i.fecha_envio_cocina)) * 24 * 60 AS INTEGER) AS mins
-         FROM rest_pedido_items i
-         JOIN rest_pedidos_abiertos p ON i.pedido_id = p.id
-         JOIN rest_mesas m ON p.mesa_id = m.id
-         LEFT JOIN rest_zonas z ON m.zona_id = z.id
-         JOIN productos pr ON i.producto_id = pr.id
-         WHERE i.enviado_cocina = 1
-           AND i.estado_cocina IN ('PENDIENTE', 'EN_PREPARACION', 'LISTO')
-         ORDER BY i.fecha_envio_cocina ASC",
-    ).map_err(err500)?;
-
-    let items: Vec<serde_json::Value> = stmt.query_map([], |r| {
-        Ok(serde_json::json!({
-            "id": r.get::<_, i64>(0)?,
-            "pedido_id": r.get::<_, i64>(1)?,
-            "mesa_nombre": r.get::<_, String>(2)?,
-            "zona_nombre": r.get::<_, Option<String>>(3)?,
-            "mesero_nombre": r.get::<_, Option<String>>(4)?,
-            "producto_nombre": r.get::<_, String>(5)?,
-            "cantidad": r.get::<_, f64>(6)?,
-            "info_adicional": r.get::<_, Option<String>>(7)?,
-            "estado_cocina": r.get::<_, String>(8)?,
-            "fecha_envio_cocina": r.get::<_, Option<String>>(9)?,
-            "minutos_en_cocina": r.get::<_, Option<i64>>(10)?,
-        }))
-    }).map_err(err500)?.collect::<Result<Vec<_>, _>>().map_err(err500)?;
+    let items = crate::restaurante::tiempos::listar_pendientes(&conn, qp.estacion_id).map_err(err500)?;
 
     Ok(Json(serde_json::json!({ "ok": true, "items": items, "total": items.len() })))
 }
@@ -1356,16 +1528,8 @@ pub async fn cocina_marcar(
     requiere_restaurante(&state)?;
     session.requiere("ve_cocina")?;
 
-    let estado_valido = matches!(req.estado.as_str(), "PENDIENTE" | "EN_PREPARACION" | "LISTO" | "ENTREGADO");
-    if !estado_valido {
-        return Err(err400("Estado de cocina inválido"));
-    }
-
     let conn = state.db.conn.lock().map_err(err500)?;
-    conn.execute(
-        "UPDATE rest_pedido_items SET estado_cocina = ?1 WHERE id = ?2",
-        params![req.estado, item_id],
-    ).map_err(err500)?;
+    crate::restaurante::tiempos::marcar_estado(&conn, item_id, &req.estado).map_err(err400)?;
     Ok(Json(serde_json::json!({ "ok": true })))
 }
 
@@ -1445,6 +1609,183 @@ pub async fn pedidos_desunir_mesa(
     Ok(Json(serde_json::json!({ "ok": true })))
 }
 
+// ─── Traspasos: cambiar mesa, mover items, reasignar mesero ─────────────
+
+fn autor_app(session: &AppSession) -> crate::restaurante::traspasos::Autor<'_> {
+    crate::restaurante::traspasos::Autor {
+        usuario_id: Some(session.usuario_id),
+        usuario_nombre: Some(session.nombre.as_str()),
+        origen: "APP",
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferirMesaRequest {
+    pub mesa_id: i64,
+    #[serde(default)]
+    pub motivo: Option<String>,
+}
+
+/// `POST /api/v1/app/pedidos/:id/transferir` — pasa el pedido completo a otra mesa libre.
+pub async fn pedidos_transferir(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(pedido_id): Path<i64>,
+    Json(req): Json<TransferirMesaRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let session = extract_app_session(&headers, &state)?;
+    requiere_restaurante(&state)?;
+    session.requiere("transfiere_mesas")?;
+
+    let mut conn = state.db.conn.lock().map_err(err500)?;
+    let tx = conn.transaction().map_err(err500)?;
+    crate::restaurante::traspasos::transferir_mesa(&tx, pedido_id, req.mesa_id, req.motivo.as_deref(), &autor_app(&session))
+        .map_err(err400)?;
+    tx.commit().map_err(err500)?;
+    Ok(Json(serde_json::json!({ "ok": true, "pedido_id": pedido_id, "mesa_id": req.mesa_id })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MoverItemsRequest {
+    pub items: Vec<crate::restaurante::models::ItemAMover>,
+    #[serde(default)]
+    pub pedido_destino_id: Option<i64>,
+    #[serde(default)]
+    pub mesa_destino_id: Option<i64>,
+    #[serde(default)]
+    pub motivo: Option<String>,
+}
+
+/// `POST /api/v1/app/pedidos/:id/mover-items` — mueve items (o parte) a otro
+/// pedido o mesa; si la mesa destino está libre se le abre pedido.
+pub async fn pedidos_mover_items(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(pedido_id): Path<i64>,
+    Json(req): Json<MoverItemsRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let session = extract_app_session(&headers, &state)?;
+    requiere_restaurante(&state)?;
+    session.requiere("transfiere_mesas")?;
+
+    let mut conn = state.db.conn.lock().map_err(err500)?;
+    let tx = conn.transaction().map_err(err500)?;
+    let destino = crate::restaurante::traspasos::mover_items(
+        &tx,
+        pedido_id,
+        &req.items,
+        (req.pedido_destino_id, req.mesa_destino_id),
+        req.motivo.as_deref(),
+        &autor_app(&session),
+    )
+    .map_err(err400)?;
+    tx.commit().map_err(err500)?;
+    Ok(Json(serde_json::json!({ "ok": true, "pedido_destino_id": destino })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReasignarMeseroRequest {
+    pub mesero_id: i64,
+    #[serde(default)]
+    pub motivo: Option<String>,
+}
+
+/// `POST /api/v1/app/pedidos/:id/reasignar-mesero` — entrega la mesa a otro mesero.
+pub async fn pedidos_reasignar_mesero(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(pedido_id): Path<i64>,
+    Json(req): Json<ReasignarMeseroRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let session = extract_app_session(&headers, &state)?;
+    requiere_restaurante(&state)?;
+    session.requiere("transfiere_mesas")?;
+
+    let mut conn = state.db.conn.lock().map_err(err500)?;
+    let tx = conn.transaction().map_err(err500)?;
+    crate::restaurante::traspasos::reasignar_mesero(&tx, pedido_id, req.mesero_id, req.motivo.as_deref(), &autor_app(&session))
+        .map_err(err400)?;
+    tx.commit().map_err(err500)?;
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
+
+/// `GET /api/v1/app/pedidos/:id/movimientos` — auditoría de traspasos del pedido.
+pub async fn pedidos_movimientos(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(pedido_id): Path<i64>,
+) -> Result<Json<Vec<crate::restaurante::models::MovimientoPedido>>, (StatusCode, Json<ApiError>)> {
+    let session = extract_app_session(&headers, &state)?;
+    requiere_restaurante(&state)?;
+    session.requiere("atiende_mesas")?;
+
+    let conn = state.db.conn.lock().map_err(err500)?;
+    let movimientos = crate::restaurante::traspasos::listar_movimientos(&conn, pedido_id).map_err(err500)?;
+    Ok(Json(movimientos))
+}
+
+// ─── Delivery (repartidores) ─────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct EntregasQuery {
+    pub estado: Option<String>,
+    #[serde(default)]
+    pub incluir_cerradas: bool,
+}
+
+/// `GET /api/v1/app/entregas` — con `cobra_caja` ve todas; el repartidor
+/// (`reparte_pedidos`) solo las asignadas a él y las que nadie tomó aún.
+pub async fn entregas_listar(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Query(q): Query<EntregasQuery>,
+) -> Result<Json<Vec<crate::restaurante::models::Entrega>>, (StatusCode, Json<ApiError>)> {
+    let session = extract_app_session(&headers, &state)?;
+    requiere_restaurante(&state)?;
+    let ve_todas = session.tiene("cobra_caja");
+    if !ve_todas {
+        session.requiere("reparte_pedidos")?;
+    }
+
+    let conn = state.db.conn.lock().map_err(err500)?;
+    let mut entregas = crate::restaurante::entregas::listar(&conn, q.estado.as_deref(), None, q.incluir_cerradas)
+        .map_err(err500)?;
+    if !ve_todas {
+        entregas.retain(|e| {
+            e.tipo == "DELIVERY" && e.repartidor_id.unwrap_or(session.usuario_id) == session.usuario_id
+        });
+    }
+    Ok(Json(entregas))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EstadoEntregaRequest {
+    pub estado: String,
+}
+
+/// `POST /api/v1/app/entregas/:pedido_id/estado` — el repartidor toma la
+/// entrega (EN_CAMINO la asigna a él si no tenía) y marca ENTREGADO.
+pub async fn entregas_estado(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(pedido_id): Path<i64>,
+    Json(req): Json<EstadoEntregaRequest>,
+) -> Result<Json<crate::restaurante::models::Entrega>, (StatusCode, Json<ApiError>)> {
+    let session = extract_app_session(&headers, &state)?;
+    requiere_restaurante(&state)?;
+    session.requiere("reparte_pedidos")?;
+
+    let conn = state.db.conn.lock().map_err(err500)?;
+    let actual = crate::restaurante::entregas::obtener(&conn, pedido_id).map_err(err400)?;
+    if !session.tiene("cobra_caja") && actual.repartidor_id.is_some_and(|id| id != session.usuario_id) {
+        return Err((StatusCode::FORBIDDEN, Json(ApiError::new("La entrega está asignada a otro repartidor"))));
+    }
+    crate::restaurante::entregas::cambiar_estado(&conn, pedido_id, &req.estado, Some(session.usuario_id))
+        .map_err(err400)?;
+    let entrega = crate::restaurante::entregas::obtener(&conn, pedido_id).map_err(err500)?;
+    Ok(Json(entrega))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DividirCuentaRequest { pub n_partes: i32 }
 
@@ -1540,6 +1881,8 @@ pub struct CobrarSubcuentaRequest {
     pub forma_pago: String,
     pub banco_id: Option<i64>,
     pub referencia_pago: Option<String>,
+    #[serde(default)]
+    pub propina: Option<crate::restaurante::models::PropinaCobro>,
 }
 
 /// `POST /api/v1/app/subcuentas/:id/cobrar` — combo: registrar venta del producto especial + marcar cobrada.
@@ -1554,20 +1897,29 @@ pub async fn subcuentas_cobrar(
     session.requiere("cobra_caja")?;
 
     // 1. Cargar datos sub-cuenta + pedido
-    let (pedido_id, monto, estado, total_subs): (i64, f64, String, i64) = {
+    let (pedido_id, monto, estado, modo, total_subs): (i64, f64, String, String, i64) = {
         let conn = state.db.conn.lock().map_err(err500)?;
-        let row: (i64, f64, String) = conn.query_row(
-            "SELECT pedido_id, total, estado FROM rest_subcuentas WHERE id = ?1",
-            params![subcuenta_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+        let row: (i64, f64, String, String) = conn.query_row(
+            "SELECT pedido_id, total, estado, modo FROM rest_subcuentas WHERE id = ?1",
+            params![subcuenta_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?))
         ).map_err(|_| err400("Sub-cuenta no encontrada"))?;
         let total_subs: i64 = conn.query_row(
             "SELECT COUNT(*) FROM rest_subcuentas WHERE pedido_id = ?1",
             params![row.0], |r| r.get(0)
         ).unwrap_or(0);
-        (row.0, row.1, row.2, total_subs)
+        (row.0, row.1, row.2, row.3, total_subs)
     };
     if estado == "COBRADA" { return Err(err400("Esta sub-cuenta ya fue cobrada")); }
 
+    // División por items: se venden los productos asignados a la sub-cuenta
+    // (como en desktop), no la cuota plana, para que cuadre el stock.
+    let partes_items = if modo == "ITEMS" {
+        let conn = state.db.conn.lock().map_err(err500)?;
+        Some(crate::restaurante::commands::partes_subcuenta(&conn, subcuenta_id).map_err(err500)?)
+    } else {
+        None
+    };
+
     // 2. Producto especial
     let prod_id: i64 = {
         let conn = state.db.conn.lock().map_err(err500)?;
@@ -1593,21 +1945,30 @@ pub async fn subcuentas_cobrar(
         detalle.zona_nombre.as_ref().map(|z| format!(" ({})", z)).unwrap_or_default(),
         pedido_id, total_subs
     );
+    let items = match &partes_items {
+        Some(partes) => {
+            let conn = state.db.conn.lock().map_err(err500)?;
+            let lineas = crate::restaurante::commands::lineas_venta_items(&conn, partes).map_err(err500)?;
+            crate::restaurante::commands::validar_cobro_subcuenta(&conn, subcuenta_id, &lineas).map_err(err400)?;
+            serde_json::to_value(lineas).map_err(err500)?
+        }
+        None => serde_json::json!([{
+            "producto_id": prod_id,
+            "cantidad": 1.0,
+            "precio_unitario": monto,
+            "descuento": 0.0,
+            "iva_porcentaje": 0.0,
+            "subtotal": monto,
+            "info_adicional": format!("Items consumidos: {}",
+                detalle.items.iter().map(|i|
+                    format!("{}x {}", i.cantidad, i.producto_nombre.clone().unwrap_or_default())
+                ).collect::<Vec<_>>().join(", ")
+            ),
+        }]),
+    };
     let venta_args = serde_json::json!({
         "venta": {
-            "items": [{
-                "producto_id": prod_id,
-                "cantidad": 1.0,
-                "precio_unitario": monto,
-                "descuento": 0.0,
-                "iva_porcentaje": 0.0,
-                "subtotal": monto,
-                "info_adicional": format!("Items consumidos: {}",
-                    detalle.items.iter().map(|i|
-                        format!("{}x {}", i.cantidad, i.producto_nombre.clone().unwrap_or_default())
-                    ).collect::<Vec<_>>().join(", ")
-                ),
-            }],
+            "items": items,
             "forma_pago": req.forma_pago.clone(),
             "monto_recibido": monto,
             "descuento": 0.0,
@@ -1618,6 +1979,9 @@ pub async fn subcuentas_cobrar(
             "referencia_pago": req.referencia_pago,
         }
     });
+    if let Some(p) = &req.propina {
+        crate::restaurante::propinas::validar(p).map_err(err400)?;
+    }
     let resultado = crate::server::dispatch::dispatch_command(
         &state, "registrar_venta", venta_args
     ).await
@@ -1628,33 +1992,41 @@ pub async fn subcuentas_cobrar(
         .ok_or_else(|| err500("Respuesta de venta sin id"))?;
 
     // 5. Marcar sub-cuenta cobrada + ¿todas pagas? → cerrar pedido
-    let (todas_cobradas, pendientes) = {
-        let conn = state.db.conn.lock().map_err(err500)?;
-        conn.execute(
+    let (todas_cobradas, pendientes, propina) = {
+        let mut conn = state.db.conn.lock().map_err(err500)?;
+        let tx = conn.transaction().map_err(err500)?;
+        let propina =
+            registrar_propina_app(&tx, venta_id, pedido_id, Some(subcuenta_id), req.propina.as_ref())?;
+        tx.execute(
             "UPDATE rest_subcuentas
              SET estado = 'COBRADA', forma_pago = ?1, banco_id = ?2, referencia_pago = ?3,
                  venta_id = ?4, fecha_cobro = datetime('now', 'localtime')
              WHERE id = ?5",
             params![req.forma_pago, req.banco_id, req.referencia_pago, venta_id, subcuenta_id]
         ).map_err(err500)?;
-        let pend: i32 = conn.query_row(
+        if let Some(partes) = &partes_items {
+            crate::restaurante::modificadores::descontar_insumos(&tx, venta_id, partes).map_err(err500)?;
+        }
+        let pend: i32 = tx.query_row(
             "SELECT COUNT(*) FROM rest_subcuentas WHERE pedido_id = ?1 AND estado = 'PENDIENTE'",
             params![pedido_id], |r| r.get(0)
         ).unwrap_or(0);
         let todas = pend == 0;
         if todas {
-            let primera_venta_id: i64 = conn.query_row(
+            let primera_venta_id: i64 = tx.query_row(
                 "SELECT venta_id FROM rest_subcuentas WHERE pedido_id = ?1 AND venta_id IS NOT NULL ORDER BY numero LIMIT 1",
                 params![pedido_id], |r| r.get(0)
             ).unwrap_or(venta_id);
-            conn.execute(
+            tx.execute(
                 "UPDATE rest_pedidos_abiertos
                  SET estado = 'COBRADO', venta_id = ?1, fecha_cierre = datetime('now', 'localtime')
                  WHERE id = ?2",
                 params![primera_venta_id, pedido_id]
             ).map_err(err500)?;
+            crate::restaurante::entregas::al_cobrar(&tx, pedido_id).map_err(err500)?;
         }
-        (todas, pend)
+        tx.commit().map_err(err500)?;
+        (todas, pend, propina)
     };
 
     Ok(Json(serde_json::json!({
@@ -1662,6 +2034,7 @@ pub async fn subcuentas_cobrar(
         "venta_id": venta_id,
         "todas_cobradas": todas_cobradas,
         "pendientes": pendientes,
+        "propina": propina,
     })))
 }
 
@@ -2731,6 +3104,71 @@ pub async fn dashboard_hoy(
     })))
 }
 
+// ─── Conteo físico (permiso inventaria) ─────────────────────────────────
+//
+// La app solo cuenta: ve la lista ciega (sin stock esperado) y manda
+// capturas. Crear, recontar y aprobar se hace desde el POS
+// (`commands::conteos`).
+
+/// `GET /api/v1/app/conteos` — conteos abiertos.
+pub async fn conteos_listar(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let session = extract_app_session(&headers, &state)?;
+    session.requiere("inventaria")?;
+    let conn = state.db.conn.lock().map_err(err500)?;
+    let conteos = crate::commands::conteos::listar(&conn, true).map_err(err500)?;
+    Ok(Json(serde_json::json!({ "ok": true, "conteos": conteos })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConteoItemsQuery {
+    pub solo_pendientes: Option<bool>,
+}
+
+/// `GET /api/v1/app/conteos/:id/items` — lista ciega (`?solo_pendientes=true`
+/// deja lo que falta contar, incluidos los reconteos).
+pub async fn conteos_items(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(conteo_id): Path<i64>,
+    Query(qp): Query<ConteoItemsQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let session = extract_app_session(&headers, &state)?;
+    session.requiere("inventaria")?;
+    let conn = state.db.conn.lock().map_err(err500)?;
+    let items = crate::commands::conteos::items_ciegos(&conn, conteo_id, qp.solo_pendientes.unwrap_or(false))
+        .map_err(err400)?;
+    Ok(Json(serde_json::json!({ "ok": true, "items": items })))
+}
+
+/// `POST /api/v1/app/conteos/:id/capturas` — `{ producto_id? | codigo?, cantidad, dispositivo? }`.
+/// Sin `dispositivo` se usa el nombre con que se emparejó el celular.
+pub async fn conteos_capturar(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(conteo_id): Path<i64>,
+    Json(mut req): Json<crate::models::CapturaConteo>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let session = extract_app_session(&headers, &state)?;
+    session.requiere("inventaria")?;
+    let conn = state.db.conn.lock().map_err(err500)?;
+    if req.dispositivo.is_none() {
+        req.dispositivo = conn
+            .query_row(
+                "SELECT dispositivo_nombre FROM app_tokens WHERE id = ?1",
+                params![session.token_id],
+                |r| r.get::<_, Option<String>>(0),
+            )
+            .ok()
+            .flatten();
+    }
+    let item = crate::commands::conteos::registrar_captura(&conn, conteo_id, &req, &session.nombre)
+        .map_err(err400)?;
+    Ok(Json(serde_json::json!({ "ok": true, "item": item })))
+}
+
 // ─── Router builder ──────────────────────────────────────────────────────
 
 /// Devuelve el router con todas las rutas del módulo, listo para `merge` en
@@ -2748,6 +3186,7 @@ pub fn rutas() -> Router<Arc<ServerState>> {
         // ── Datos del usuario / catálogo ────────────────────────────────
         .route("/api/v1/app/me", get(me))
         .route("/api/v1/app/productos", get(listar_productos))
+        .route("/api/v1/app/productos/:id/modificadores", get(producto_modificadores))
         // ── Mesas (restaurante) ─────────────────────────────────────────
         .route("/api/v1/app/mesas", get(listar_mesas))
         // ── Pedidos (Sprint 3b) ─────────────────────────────────────────
@@ -2757,6 +3196,8 @@ pub fn rutas() -> Router<Arc<ServerState>> {
         .route("/api/v1/app/pedidos/:id/items", post(pedidos_agregar_item))
         .route("/api/v1/app/pedidos/items/:item_id", delete(pedidos_eliminar_item))
         .route("/api/v1/app/pedidos/:id/enviar-cocina", post(pedidos_enviar_cocina))
+        .route("/api/v1/app/pedidos/:id/disparar-curso", post(pedidos_disparar_curso))
+        .route("/api/v1/app/pedidos/items/:item_id/retener", post(pedidos_retener_item))
         .route("/api/v1/app/pedidos/:id/pedir-cuenta", post(pedidos_pedir_cuenta))
         .route("/api/v1/app/pedidos/:id/cancelar", post(pedidos_cancelar))
         .route("/api/v1/app/pedidos/:id/cobrar", post(pedidos_cobrar))
@@ -2765,6 +3206,13 @@ pub fn rutas() -> Router<Arc<ServerState>> {
         .route("/api/v1/app/pedidos/:id/unir-mesas", post(pedidos_unir_mesas))
         .route("/api/v1/app/pedidos/:pedido_id/mesas-extra/:mesa_id", delete(pedidos_desunir_mesa))
         .route("/api/v1/app/pedidos/:id/mesas-libres-para-unir", get(pedidos_mesas_libres))
+        // ── Traspasos (auditados en rest_pedido_movimientos) ────────────
+        .route("/api/v1/app/pedidos/:id/transferir", post(pedidos_transferir))
+        .route("/api/v1/app/pedidos/:id/mover-items", post(pedidos_mover_items))
+        .route("/api/v1/app/pedidos/:id/reasignar-mesero", post(pedidos_reasignar_mesero))
+        .route("/api/v1/app/pedidos/:id/movimientos", get(pedidos_movimientos))
+        .route("/api/v1/app/entregas", get(entregas_listar))
+        .route("/api/v1/app/entregas/:pedido_id/estado", post(entregas_estado))
         // ── Dividir cuenta (Sprint 3b) ──────────────────────────────────
         .route("/api/v1/app/pedidos/:id/dividir", post(pedidos_dividir))
         .route("/api/v1/app/pedidos/:id/subcuentas", get(pedidos_listar_subcuentas))
@@ -2800,6 +3248,10 @@ pub fn rutas() -> Router<Arc<ServerState>> {
         .route("/api/v1/app/compras/:id", get(obtener_compra))
         // ── v2.5.53: Dashboard KPIs del día ─────────────────────────────
         .route("/api/v1/app/dashboard/hoy", get(dashboard_hoy))
+        // ── Conteo físico (inventarista) ────────────────────────────────
+        .route("/api/v1/app/conteos", get(conteos_listar))
+        .route("/api/v1/app/conteos/:id/items", get(conteos_items))
+        .route("/api/v1/app/conteos/:id/capturas", post(conteos_capturar))
         // ── Servicio Técnico (Sprint 6.4 — técnico móvil) ───────────────
         .route("/api/v1/app/st/mis-ordenes", get(super::http_st::st_mis_ordenes))
         .route("/api/v1/app/st/ordenes", post(super::http_st::st_crear_orden))
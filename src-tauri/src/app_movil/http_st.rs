@@ -274,6 +274,9 @@ pub async fn st_cambiar_estado(
          VALUES (?1, ?2, ?3, ?4, ?5)",
         params![id, estado_actual, req.nuevo_estado, req.observacion, session.nombre],
     );
+    crate::webhooks::emitir_estado_orden(
+        &conn, id, Some(&estado_actual), &req.nuevo_estado, req.observacion.as_deref(), &session.nombre,
+    );
 
     Ok(Json(serde_json::json!({ "ok": true })))
 }
@@ -2,6 +2,7 @@
 //!
 //! Tablas:
 //! - `app_tokens` — sesiones activas de dispositivos móviles emparejados
+//!   (y de pantallas de cocina KDS, `tipo = 'KDS'`)
 //!
 //! Cada vez que un mesero/cocinero/vendedor hace login PIN en la app, se
 //! genera un token único (UUID v4) y se persiste aquí. El token se envía
@@ -39,5 +40,12 @@ pub fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
         CREATE INDEX IF NOT EXISTS idx_app_tokens_usuario ON app_tokens(usuario_id);
         CREATE INDEX IF NOT EXISTS idx_app_tokens_revoked ON app_tokens(revoked);
         ",
-    )
+    )?;
+
+    // Pantallas de cocina (KDS web): tipo = 'KDS' solo sirve en `/kds/api/*`;
+    // la API de la app rechaza esos tokens. estacion_id = estación por defecto
+    // de la pantalla (NULL = todas).
+    let _ = conn.execute("ALTER TABLE app_tokens ADD COLUMN tipo TEXT NOT NULL DEFAULT 'APP'", []);
+    let _ = conn.execute("ALTER TABLE app_tokens ADD COLUMN estacion_id INTEGER", []);
+    Ok(())
 }
@@ -0,0 +1,1144 @@
+// Ubicaciones de bodega y listas de picking
+//
+// Ubicaciones (ubicaciones_bodega): pasillo / estante / casillero por
+// establecimiento, con orden_ruta = posicion en el recorrido de la bodega.
+// stock_ubicacion reparte el stock del establecimiento (stock::stock_en, que
+// sigue siendo la fuente de verdad) entre ubicaciones; lo no asignado queda
+// "sin ubicar". Lo que entra (compras, NC, transferencias) queda sin ubicar
+// hasta que se acomoda. Cada salida de stock (stock::mover con delta < 0)
+// llama a ajustar_a_stock: si lo ubicado supera el stock, el exceso se quita
+// de la principal y luego en orden de recorrido, asi nunca quedan unidades
+// fantasma. Al recoger, lo que el documento todavia no descontó (guia antes
+// de ENTREGADA) sale de la ubicacion; al entregarla lo ubicado ya bajo y no
+// hay exceso. Lo que ya salio del stock (venta COMPLETADA, guia ENTREGADA) ya
+// fue recortado por ajustar_a_stock: al recogerlo solo se traslada ese recorte
+// a la ubicacion recogida (recortar_en), sin descontar dos veces.
+//
+// Picking (listas_picking): para una guia de remision o un lote de ventas del
+// mismo establecimiento se suma lo pedido por producto (unidad base) y se
+// reparte entre sus ubicaciones (repartir_recogida): la primera en el
+// recorrido que alcance sola, si no, en orden de recorrido; lo que falte sale
+// de la principal o queda sin ubicacion. Las lineas se ordenan por recorrido.
+// Cada linea se confirma escaneando el producto (codigo, codigo de barras o
+// el de una presentacion, que cuenta su factor) o a mano; al recoger se
+// descuenta de la ubicacion. La primera confirmacion pasa las guias a
+// despacho PREPARANDO; con todo recogido o marcado FALTANTE, COMPLETADA.
+//
+// Igual que guia_cambiar_despacho, requiere el modulo multi_almacen.
+
+use crate::db::{Database, SesionState};
+use crate::models::{
+    DocumentoPicking, ItemPicking, ListaPicking, NuevaUbicacion, StockUbicacion, UbicacionBodega,
+    UbicacionesProducto,
+};
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::BTreeMap;
+use tauri::State;
+
+const EPS: f64 = 1e-9;
+
+fn requiere_inventario_avanzado(conn: &Connection) -> Result<(), String> {
+    let getc = |k: &str| -> String {
+        conn.query_row("SELECT value FROM config WHERE key=?1", rusqlite::params![k], |r| r.get(0))
+            .unwrap_or_default()
+    };
+    if getc("demo_activo") != "1" && !getc("licencia_modulos").contains("multi_almacen") {
+        return Err("Las ubicaciones y el picking requieren el módulo de inventario avanzado (multi-almacén). Actívelo en su licencia.".to_string());
+    }
+    Ok(())
+}
+
+/// A-03-2: partes no vacias en mayusculas unidas con guion.
+pub fn codigo_ubicacion(pasillo: &str, estante: Option<&str>, casillero: Option<&str>) -> String {
+    [Some(pasillo), estante, casillero]
+        .into_iter()
+        .flatten()
+        .map(|s| s.trim().to_uppercase())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Reparte `cantidad` entre ubicaciones (id, disponible) ya ordenadas por
+/// recorrido. Si una sola alcanza, se recoge todo de ahi; si no, en orden.
+/// Lo que no cubren va a la principal, o sin ubicacion (None).
+pub fn repartir_recogida(cantidad: f64, ubicaciones: &[(i64, f64)], principal: Option<i64>) -> Vec<(Option<i64>, f64)> {
+    if let Some((id, _)) = ubicaciones.iter().find(|(_, disp)| *disp + EPS >= cantidad) {
+        return vec![(Some(*id), cantidad)];
+    }
+    let mut partes: Vec<(Option<i64>, f64)> = Vec::new();
+    let mut falta = cantidad;
+    for (id, disp) in ubicaciones {
+        if falta <= EPS {
+            break;
+        }
+        let toma = disp.min(falta);
+        if toma > EPS {
+            partes.push((Some(*id), toma));
+            falta -= toma;
+        }
+    }
+    if falta > EPS {
+        match partes.iter_mut().find(|(id, _)| principal.is_some() && *id == principal) {
+            Some(p) => p.1 += falta,
+            None => partes.push((principal, falta)),
+        }
+    }
+    partes
+}
+
+// ─── Ubicaciones ─────────────────────────────────────────────────────────
+
+fn obtener_ubicacion(conn: &Connection, id: i64) -> Result<UbicacionBodega, String> {
+    conn.query_row(
+        "SELECT u.id, u.establecimiento_id, u.codigo, u.pasillo, u.estante, u.casillero, u.orden_ruta, u.activo,
+                (SELECT COUNT(*) FROM stock_ubicacion su WHERE su.ubicacion_id = u.id AND su.cantidad > 0)
+         FROM ubicaciones_bodega u WHERE u.id = ?1",
+        rusqlite::params![id],
+        |r| {
+            Ok(UbicacionBodega {
+                id: r.get(0)?,
+                establecimiento_id: r.get(1)?,
+                codigo: r.get(2)?,
+                pasillo: r.get(3)?,
+                estante: r.get(4)?,
+                casillero: r.get(5)?,
+                orden_ruta: r.get(6)?,
+                activo: r.get::<_, i64>(7)? != 0,
+                productos: r.get(8)?,
+            })
+        },
+    )
+    .map_err(|_| format!("Ubicación #{} no encontrada", id))
+}
+
+fn establecimiento_ubicacion(conn: &Connection, ubicacion_id: i64) -> Result<i64, String> {
+    let (est, activo): (i64, i64) = conn
+        .query_row(
+            "SELECT establecimiento_id, activo FROM ubicaciones_bodega WHERE id = ?1",
+            rusqlite::params![ubicacion_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .map_err(|_| format!("Ubicación #{} no encontrada", ubicacion_id))?;
+    if activo == 0 {
+        return Err("La ubicación está desactivada".to_string());
+    }
+    Ok(est)
+}
+
+fn cantidad_en(conn: &Connection, producto_id: i64, ubicacion_id: i64) -> f64 {
+    conn.query_row(
+        "SELECT cantidad FROM stock_ubicacion WHERE producto_id = ?1 AND ubicacion_id = ?2",
+        rusqlite::params![producto_id, ubicacion_id],
+        |r| r.get(0),
+    )
+    .unwrap_or(0.0)
+}
+
+/// Suma `delta` al stock de la ubicacion (sin bajar de 0).
+fn mover_en_ubicacion(conn: &Connection, producto_id: i64, ubicacion_id: i64, delta: f64) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO stock_ubicacion (producto_id, ubicacion_id, cantidad) VALUES (?1, ?2, MAX(?3, 0))
+         ON CONFLICT(producto_id, ubicacion_id) DO UPDATE SET cantidad = MAX(cantidad + ?3, 0)",
+        rusqlite::params![producto_id, ubicacion_id, delta],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Recorta lo ubicado del producto al stock del establecimiento: principal
+/// primero, luego en orden de recorrido.
+pub fn ajustar_a_stock(conn: &Connection, producto_id: i64, establecimiento_id: i64) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT su.ubicacion_id, su.cantidad FROM stock_ubicacion su
+             JOIN ubicaciones_bodega u ON u.id = su.ubicacion_id
+             WHERE su.producto_id = ?1 AND u.establecimiento_id = ?2 AND su.cantidad > 0
+             ORDER BY su.es_principal DESC, u.orden_ruta, u.codigo",
+        )
+        .map_err(|e| e.to_string())?;
+    let ubicaciones: Vec<(i64, f64)> = stmt
+        .query_map(rusqlite::params![producto_id, establecimiento_id], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let ubicado: f64 = ubicaciones.iter().map(|u| u.1).sum();
+    let stock = crate::commands::stock::stock_en(conn, producto_id, establecimiento_id).max(0.0);
+    let mut exceso = ubicado - stock;
+    for (ubicacion_id, cantidad) in ubicaciones {
+        if exceso <= EPS {
+            break;
+        }
+        let quita = cantidad.min(exceso);
+        mover_en_ubicacion(conn, producto_id, ubicacion_id, -quita)?;
+        exceso -= quita;
+    }
+    Ok(())
+}
+
+pub fn ubicaciones_de(conn: &Connection, producto_id: i64, establecimiento_id: i64) -> Result<UbicacionesProducto, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT su.ubicacion_id, u.codigo, su.producto_id, p.nombre, su.cantidad, su.es_principal
+             FROM stock_ubicacion su
+             JOIN ubicaciones_bodega u ON u.id = su.ubicacion_id
+             JOIN productos p ON p.id = su.producto_id
+             WHERE su.producto_id = ?1 AND u.establecimiento_id = ?2 AND (su.cantidad > 0 OR su.es_principal = 1)
+             ORDER BY u.orden_ruta, u.codigo",
+        )
+        .map_err(|e| e.to_string())?;
+    let ubicaciones: Vec<StockUbicacion> = stmt
+        .query_map(rusqlite::params![producto_id, establecimiento_id], |r| {
+            Ok(StockUbicacion {
+                ubicacion_id: r.get(0)?,
+                ubicacion_codigo: r.get(1)?,
+                producto_id: r.get(2)?,
+                producto_nombre: r.get(3)?,
+                cantidad: r.get(4)?,
+                es_principal: r.get::<_, i64>(5)? != 0,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let stock = crate::commands::stock::stock_en(conn, producto_id, establecimiento_id);
+    let ubicado: f64 = ubicaciones.iter().map(|u| u.cantidad).sum();
+    Ok(UbicacionesProducto {
+        producto_id,
+        establecimiento_id,
+        stock_establecimiento: stock,
+        ubicado,
+        sin_ubicar: (stock - ubicado).max(0.0),
+        ubicaciones,
+    })
+}
+
+// ─── Picking ─────────────────────────────────────────────────────────────
+
+/// PICK-XXXXXX, MAX existente + 1.
+fn proximo_numero_picking(conn: &Connection) -> String {
+    let next: i64 = conn
+        .query_row(
+            "SELECT COALESCE(MAX(CAST(SUBSTR(numero, 6) AS INTEGER)), 0) + 1
+             FROM listas_picking WHERE numero LIKE 'PICK-%'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap_or(1);
+    format!("PICK-{:06}", next)
+}
+
+/// Crea la lista de picking de `venta_ids` (guias o ventas del mismo
+/// establecimiento). Retorna el id.
+pub fn generar(conn: &Connection, venta_ids: &[i64], observacion: Option<&str>, usuario: &str) -> Result<i64, String> {
+    if venta_ids.is_empty() {
+        return Err("Seleccione al menos un documento".to_string());
+    }
+    let mut est_id: Option<i64> = None;
+    let mut pedido: BTreeMap<i64, f64> = BTreeMap::new();
+    for venta_id in venta_ids {
+        let (numero, tipo_estado, anulada, est_codigo): (String, String, i64, String) = conn
+            .query_row(
+                "SELECT numero, tipo_estado, COALESCE(anulada, 0), COALESCE(establecimiento, '001') FROM ventas WHERE id = ?1",
+                rusqlite::params![venta_id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+            )
+            .map_err(|_| format!("Documento #{} no encontrado", venta_id))?;
+        if anulada != 0 || !matches!(tipo_estado.as_str(), "COMPLETADA" | "GUIA_REMISION") {
+            return Err(format!("El documento {} no se puede despachar ({})", numero, if anulada != 0 { "ANULADA" } else { tipo_estado.as_str() }));
+        }
+        let est = crate::commands::stock::establecimiento_por_codigo(conn, &est_codigo)?;
+        if est_id.is_some_and(|e| e != est) {
+            return Err("Los documentos de una lista de picking deben ser del mismo establecimiento".to_string());
+        }
+        est_id = Some(est);
+        let en_lista: Option<String> = conn
+            .query_row(
+                "SELECT l.numero FROM lista_picking_documentos d JOIN listas_picking l ON l.id = d.lista_id
+                 WHERE d.venta_id = ?1 AND l.estado != 'ANULADA' LIMIT 1",
+                rusqlite::params![venta_id],
+                |r| r.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if let Some(lista) = en_lista {
+            return Err(format!("El documento {} ya está en la lista de picking {}", numero, lista));
+        }
+        // Lineas despachadas desde otro establecimiento no se recogen aqui
+        let mut stmt = conn
+            .prepare(
+                "SELECT vd.producto_id, SUM(vd.cantidad * COALESCE(vd.factor_unidad, 1))
+                 FROM venta_detalles vd JOIN productos p ON p.id = vd.producto_id
+                 WHERE vd.venta_id = ?1 AND p.es_servicio = 0 AND COALESCE(p.no_controla_stock, 0) = 0
+                   AND (vd.establecimiento_origen_id IS NULL OR vd.establecimiento_origen_id = ?2)
+                 GROUP BY vd.producto_id",
+            )
+            .map_err(|e| e.to_string())?;
+        let filas: Vec<(i64, f64)> = stmt
+            .query_map(rusqlite::params![venta_id, est], |r| Ok((r.get(0)?, r.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        for (pid, cant) in filas {
+            *pedido.entry(pid).or_default() += cant;
+        }
+    }
+    let est_id = est_id.unwrap_or_default();
+    pedido.retain(|_, c| *c > EPS);
+    if pedido.is_empty() {
+        return Err("Los documentos no tienen productos que recoger".to_string());
+    }
+
+    // (orden_ruta, codigo ubicacion, nombre) para ordenar el recorrido
+    let mut lineas: Vec<(Option<(i64, String)>, String, i64, Option<i64>, f64)> = Vec::new();
+    for (pid, cantidad) in pedido {
+        let nombre: String = conn
+            .query_row("SELECT nombre FROM productos WHERE id = ?1", rusqlite::params![pid], |r| r.get(0))
+            .map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT su.ubicacion_id, su.cantidad, su.es_principal, u.orden_ruta, u.codigo
+                 FROM stock_ubicacion su JOIN ubicaciones_bodega u ON u.id = su.ubicacion_id
+                 WHERE su.producto_id = ?1 AND u.establecimiento_id = ?2 AND u.activo = 1
+                 ORDER BY u.orden_ruta, u.codigo",
+            )
+            .map_err(|e| e.to_string())?;
+        let ubicaciones: Vec<(i64, f64, bool, i64, String)> = stmt
+            .query_map(rusqlite::params![pid, est_id], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get::<_, i64>(2)? != 0, r.get(3)?, r.get(4)?))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        let principal = ubicaciones.iter().find(|u| u.2).map(|u| u.0);
+        let disponibles: Vec<(i64, f64)> =
+            ubicaciones.iter().filter(|u| u.1 > EPS).map(|u| (u.0, u.1)).collect();
+        for (ubicacion_id, cant) in repartir_recogida(cantidad, &disponibles, principal) {
+            let ruta = ubicacion_id
+                .and_then(|id| ubicaciones.iter().find(|u| u.0 == id))
+                .map(|u| (u.3, u.4.clone()));
+            lineas.push((ruta, nombre.clone(), pid, ubicacion_id, cant));
+        }
+    }
+    // Con ubicacion por recorrido; sin ubicacion al final, por nombre
+    lineas.sort_by(|a, b| match (&a.0, &b.0) {
+        (Some(ra), Some(rb)) => ra.cmp(rb).then_with(|| a.1.cmp(&b.1)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.1.cmp(&b.1),
+    });
+
+    let numero = proximo_numero_picking(conn);
+    conn.execute(
+        "INSERT INTO listas_picking (numero, establecimiento_id, usuario, observacion) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![numero, est_id, usuario, observacion],
+    )
+    .map_err(|e| e.to_string())?;
+    let lista_id = conn.last_insert_rowid();
+    for venta_id in venta_ids {
+        conn.execute(
+            "INSERT INTO lista_picking_documentos (lista_id, venta_id) VALUES (?1, ?2)",
+            rusqlite::params![lista_id, venta_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    for (i, (_, _, pid, ubicacion_id, cant)) in lineas.into_iter().enumerate() {
+        conn.execute(
+            "INSERT INTO lista_picking_items (lista_id, orden, producto_id, ubicacion_id, cantidad)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![lista_id, i as i64 + 1, pid, ubicacion_id, cant],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(lista_id)
+}
+
+pub fn obtener_lista(conn: &Connection, id: i64) -> Result<ListaPicking, String> {
+    let mut lista = conn
+        .query_row(
+            "SELECT l.id, l.numero, l.establecimiento_id, e.nombre, l.estado, l.usuario, l.fecha_creacion,
+                    l.fecha_cierre, l.observacion
+             FROM listas_picking l LEFT JOIN establecimientos e ON e.id = l.establecimiento_id
+             WHERE l.id = ?1",
+            rusqlite::params![id],
+            |r| {
+                Ok(ListaPicking {
+                    id: r.get(0)?,
+                    numero: r.get(1)?,
+                    establecimiento_id: r.get(2)?,
+                    establecimiento_nombre: r.get(3)?,
+                    estado: r.get(4)?,
+                    usuario: r.get(5)?,
+                    fecha_creacion: r.get(6)?,
+                    fecha_cierre: r.get(7)?,
+                    observacion: r.get(8)?,
+                    documentos: Vec::new(),
+                    items: Vec::new(),
+                })
+            },
+        )
+        .map_err(|_| format!("Lista de picking #{} no encontrada", id))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT v.id, v.numero, v.tipo_estado, c.nombre, v.guia_direccion_destino
+             FROM lista_picking_documentos d
+             JOIN ventas v ON v.id = d.venta_id
+             LEFT JOIN clientes c ON c.id = v.cliente_id
+             WHERE d.lista_id = ?1 ORDER BY v.id",
+        )
+        .map_err(|e| e.to_string())?;
+    lista.documentos = stmt
+        .query_map(rusqlite::params![id], |r| {
+            Ok(DocumentoPicking {
+                venta_id: r.get(0)?,
+                numero: r.get(1)?,
+                tipo_estado: r.get(2)?,
+                cliente_nombre: r.get(3)?,
+                direccion_destino: r.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.id, i.orden, i.producto_id, p.codigo, p.codigo_barras, p.nombre, i.ubicacion_id, u.codigo,
+                    i.cantidad, i.cantidad_recogida, i.estado, i.usuario, i.fecha_confirmacion
+             FROM lista_picking_items i
+             JOIN productos p ON p.id = i.producto_id
+             LEFT JOIN ubicaciones_bodega u ON u.id = i.ubicacion_id
+             WHERE i.lista_id = ?1 ORDER BY i.orden",
+        )
+        .map_err(|e| e.to_string())?;
+    lista.items = stmt
+        .query_map(rusqlite::params![id], |r| {
+            Ok(ItemPicking {
+                id: r.get(0)?,
+                orden: r.get(1)?,
+                producto_id: r.get(2)?,
+                producto_codigo: r.get(3)?,
+                codigo_barras: r.get(4)?,
+                producto_nombre: r.get(5)?,
+                ubicacion_id: r.get(6)?,
+                ubicacion_codigo: r.get(7)?,
+                cantidad: r.get(8)?,
+                cantidad_recogida: r.get(9)?,
+                estado: r.get(10)?,
+                usuario: r.get(11)?,
+                fecha_confirmacion: r.get(12)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(lista)
+}
+
+fn lista_abierta(conn: &Connection, lista_id: i64) -> Result<(), String> {
+    let (numero, estado): (String, String) = conn
+        .query_row(
+            "SELECT numero, estado FROM listas_picking WHERE id = ?1",
+            rusqlite::params![lista_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .map_err(|_| format!("Lista de picking #{} no encontrada", lista_id))?;
+    if estado == "COMPLETADA" || estado == "ANULADA" {
+        return Err(format!("La lista {} está {}", numero, estado));
+    }
+    Ok(())
+}
+
+/// EN_PROCESO con la primera confirmacion (las guias pasan a despacho
+/// PREPARANDO); COMPLETADA cuando no queda nada pendiente.
+fn actualizar_estado_lista(conn: &Connection, lista_id: i64) -> Result<(), String> {
+    let pendientes: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM lista_picking_items WHERE lista_id = ?1 AND estado = 'PENDIENTE'",
+            rusqlite::params![lista_id],
+            |r| r.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if pendientes == 0 {
+        conn.execute(
+            "UPDATE listas_picking SET estado = 'COMPLETADA', fecha_cierre = datetime('now','localtime') WHERE id = ?1",
+            rusqlite::params![lista_id],
+        )
+        .map_err(|e| e.to_string())?;
+    } else {
+        conn.execute(
+            "UPDATE listas_picking SET estado = 'EN_PROCESO' WHERE id = ?1 AND estado = 'PENDIENTE'",
+            rusqlite::params![lista_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    conn.execute(
+        "UPDATE ventas SET despacho_estado = 'PREPARANDO'
+         WHERE id IN (SELECT venta_id FROM lista_picking_documentos WHERE lista_id = ?1)
+           AND tipo_estado = 'GUIA_REMISION' AND despacho_estado IS NULL",
+        rusqlite::params![lista_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Suma `cantidad` a lo recogido de la linea y la descuenta de su ubicacion.
+fn recoger(conn: &Connection, item_id: i64, cantidad: f64, usuario: &str) -> Result<i64, String> {
+    let (lista_id, producto_id, ubicacion_id, pedida, recogida, estado): (i64, i64, Option<i64>, f64, f64, String) = conn
+        .query_row(
+            "SELECT lista_id, producto_id, ubicacion_id, cantidad, cantidad_recogida, estado
+             FROM lista_picking_items WHERE id = ?1",
+            rusqlite::params![item_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?)),
+        )
+        .map_err(|_| format!("Línea de picking #{} no encontrada", item_id))?;
+    lista_abierta(conn, lista_id)?;
+    if estado != "PENDIENTE" {
+        return Err(format!("La línea ya está {}", estado));
+    }
+    if cantidad <= 0.0 {
+        return Err("La cantidad debe ser mayor a 0".to_string());
+    }
+    let nueva = recogida + cantidad;
+    if nueva > pedida + EPS {
+        return Err(format!("Se está recogiendo más de lo pedido ({} de {})", nueva, pedida));
+    }
+    conn.execute(
+        "UPDATE lista_picking_items SET cantidad_recogida = ?1,
+                estado = CASE WHEN ?1 >= cantidad - 1e-9 THEN 'RECOGIDO' ELSE 'PENDIENTE' END,
+                usuario = ?2, fecha_confirmacion = datetime('now','localtime')
+         WHERE id = ?3",
+        rusqlite::params![nueva, usuario, item_id],
+    )
+    .map_err(|e| e.to_string())?;
+    if let Some(ubicacion_id) = ubicacion_id {
+        // Primero se recoge lo que ya salio del stock (ver comentario del modulo)
+        let ya_descontado = (descontado_en_lista(conn, lista_id, producto_id)?
+            - recogido_en_lista(conn, lista_id, producto_id)?
+            + cantidad)
+            .clamp(0.0, cantidad);
+        if ya_descontado > EPS {
+            recortar_en(conn, producto_id, ubicacion_id, ya_descontado)?;
+        }
+        if cantidad - ya_descontado > EPS {
+            mover_en_ubicacion(conn, producto_id, ubicacion_id, -(cantidad - ya_descontado))?;
+        }
+    }
+    actualizar_estado_lista(conn, lista_id)?;
+    Ok(lista_id)
+}
+
+/// Unidades del producto en los documentos de la lista que ya movieron stock
+/// (venta COMPLETADA, guia ENTREGADA).
+fn descontado_en_lista(conn: &Connection, lista_id: i64, producto_id: i64) -> Result<f64, String> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(vd.cantidad * COALESCE(vd.factor_unidad, 1)), 0)
+         FROM lista_picking_documentos d
+         JOIN listas_picking l ON l.id = d.lista_id
+         JOIN ventas v ON v.id = d.venta_id
+         JOIN venta_detalles vd ON vd.venta_id = v.id
+         WHERE d.lista_id = ?1 AND vd.producto_id = ?2
+           AND (vd.establecimiento_origen_id IS NULL OR vd.establecimiento_origen_id = l.establecimiento_id)
+           AND (v.tipo_estado = 'COMPLETADA' OR (v.tipo_estado = 'GUIA_REMISION' AND v.estado = 'ENTREGADA'))",
+        rusqlite::params![lista_id, producto_id],
+        |r| r.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Recogido del producto en toda la lista (incluida la confirmacion en curso).
+fn recogido_en_lista(conn: &Connection, lista_id: i64, producto_id: i64) -> Result<f64, String> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(cantidad_recogida), 0) FROM lista_picking_items WHERE lista_id = ?1 AND producto_id = ?2",
+        rusqlite::params![lista_id, producto_id],
+        |r| r.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Traslada a `ubicacion_id` un recorte que ajustar_a_stock ya hizo: lo
+/// recortado se devuelve a la primera ubicacion que recorta (principal, luego
+/// recorrido) y sale de la recogida. Lo ubicado en total no cambia.
+fn recortar_en(conn: &Connection, producto_id: i64, ubicacion_id: i64, cantidad: f64) -> Result<(), String> {
+    let (est_id, en_ubicacion): (i64, f64) = conn
+        .query_row(
+            "SELECT u.establecimiento_id, COALESCE(su.cantidad, 0) FROM ubicaciones_bodega u
+             LEFT JOIN stock_ubicacion su ON su.ubicacion_id = u.id AND su.producto_id = ?1
+             WHERE u.id = ?2",
+            rusqlite::params![producto_id, ubicacion_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    let primera: Option<i64> = conn
+        .query_row(
+            "SELECT su.ubicacion_id FROM stock_ubicacion su
+             JOIN ubicaciones_bodega u ON u.id = su.ubicacion_id
+             WHERE su.producto_id = ?1 AND u.establecimiento_id = ?2
+             ORDER BY su.es_principal DESC, u.orden_ruta, u.codigo LIMIT 1",
+            rusqlite::params![producto_id, est_id],
+            |r| r.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let traslado = cantidad.min(en_ubicacion);
+    match primera {
+        Some(destino) if destino != ubicacion_id && traslado > EPS => {
+            mover_en_ubicacion(conn, producto_id, ubicacion_id, -traslado)?;
+            mover_en_ubicacion(conn, producto_id, destino, traslado)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Producto y unidades que representa un codigo escaneado: codigo interno o
+/// de barras (1) o codigo de barras de una presentacion (su factor).
+fn resolver_codigo(conn: &Connection, codigo: &str) -> Result<(i64, f64), String> {
+    let c = codigo.trim();
+    if c.is_empty() {
+        return Err("Código vacío".to_string());
+    }
+    if let Some(pid) = conn
+        .query_row(
+            "SELECT id FROM productos WHERE codigo_barras = ?1 OR codigo = ?1 ORDER BY codigo_barras = ?1 DESC LIMIT 1",
+            rusqlite::params![c],
+            |r| r.get::<_, i64>(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+    {
+        return Ok((pid, 1.0));
+    }
+    conn.query_row(
+        "SELECT producto_id, factor FROM producto_presentaciones WHERE codigo_barras = ?1 AND activo = 1 LIMIT 1",
+        rusqlite::params![c],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )
+    .map_err(|_| format!("Código {} no reconocido", c))
+}
+
+pub fn confirmar_escaneo(
+    conn: &Connection,
+    lista_id: i64,
+    codigo: &str,
+    ubicacion_codigo: Option<&str>,
+    cantidad: f64,
+    usuario: &str,
+) -> Result<(), String> {
+    lista_abierta(conn, lista_id)?;
+    let (producto_id, factor) = resolver_codigo(conn, codigo)?;
+    let unidades = cantidad * factor;
+    let ubic = ubicacion_codigo.map(|s| s.trim().to_uppercase()).filter(|s| !s.is_empty());
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.id, i.cantidad - i.cantidad_recogida
+             FROM lista_picking_items i LEFT JOIN ubicaciones_bodega u ON u.id = i.ubicacion_id
+             WHERE i.lista_id = ?1 AND i.producto_id = ?2 AND i.estado = 'PENDIENTE'
+               AND (?3 IS NULL OR u.codigo = ?3)
+             ORDER BY i.orden",
+        )
+        .map_err(|e| e.to_string())?;
+    let pendientes: Vec<(i64, f64)> = stmt
+        .query_map(rusqlite::params![lista_id, producto_id, ubic], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let total: f64 = pendientes.iter().map(|p| p.1).sum();
+    if pendientes.is_empty() {
+        return Err(match ubic {
+            Some(u) => format!("No hay nada pendiente de ese producto en la ubicación {}", u),
+            None => "Ese producto no está pendiente en esta lista".to_string(),
+        });
+    }
+    if unidades > total + EPS {
+        return Err(format!("Se está recogiendo más de lo pedido (faltan {})", total));
+    }
+    let mut falta = unidades;
+    for (item_id, pendiente) in pendientes {
+        if falta <= EPS {
+            break;
+        }
+        let toma = pendiente.min(falta);
+        recoger(conn, item_id, toma, usuario)?;
+        falta -= toma;
+    }
+    Ok(())
+}
+
+/// Anula la lista: lo ya recogido vuelve a su ubicacion.
+pub fn anular_lista(conn: &Connection, lista_id: i64) -> Result<(), String> {
+    lista_abierta(conn, lista_id)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT producto_id, ubicacion_id, cantidad_recogida FROM lista_picking_items
+             WHERE lista_id = ?1 AND ubicacion_id IS NOT NULL AND cantidad_recogida > 0",
+        )
+        .map_err(|e| e.to_string())?;
+    let recogidos: Vec<(i64, i64, f64)> = stmt
+        .query_map(rusqlite::params![lista_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let est_id: i64 = conn
+        .query_row("SELECT establecimiento_id FROM listas_picking WHERE id = ?1", rusqlite::params![lista_id], |r| r.get(0))
+        .map_err(|e| e.to_string())?;
+    // Si el documento ya movio stock, lo devuelto no puede superar el stock
+    for (pid, ubicacion_id, cant) in recogidos {
+        mover_en_ubicacion(conn, pid, ubicacion_id, cant)?;
+        ajustar_a_stock(conn, pid, est_id)?;
+    }
+    conn.execute(
+        "UPDATE listas_picking SET estado = 'ANULADA', fecha_cierre = datetime('now','localtime') WHERE id = ?1",
+        rusqlite::params![lista_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// ─── Comandos Tauri: ubicaciones ─────────────────────────────────────────
+
+#[tauri::command]
+pub fn guardar_ubicacion(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    ubicacion: NuevaUbicacion,
+) -> Result<UbicacionBodega, String> {
+    crate::db::usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    requiere_inventario_avanzado(&conn)?;
+    if ubicacion.pasillo.trim().is_empty() {
+        return Err("Indique el pasillo".to_string());
+    }
+    let est_id = match ubicacion.establecimiento_id {
+        Some(id) => id,
+        None => crate::commands::stock::establecimiento_terminal(&conn)?,
+    };
+    let codigo = ubicacion
+        .codigo
+        .as_deref()
+        .map(|c| c.trim().to_uppercase())
+        .filter(|c| !c.is_empty())
+        .unwrap_or_else(|| codigo_ubicacion(&ubicacion.pasillo, ubicacion.estante.as_deref(), ubicacion.casillero.as_deref()));
+    let limpio = |s: &Option<String>| s.as_deref().map(|v| v.trim().to_uppercase()).filter(|v| !v.is_empty());
+    let orden_ruta = match ubicacion.orden_ruta {
+        Some(o) => o,
+        None => conn
+            .query_row(
+                "SELECT COALESCE(MAX(orden_ruta), 0) + 10 FROM ubicaciones_bodega WHERE establecimiento_id = ?1",
+                rusqlite::params![est_id],
+                |r| r.get(0),
+            )
+            .unwrap_or(10),
+    };
+    let duplicado = |e: rusqlite::Error| {
+        if e.to_string().contains("UNIQUE") {
+            format!("Ya existe la ubicación {} en este establecimiento", codigo)
+        } else {
+            e.to_string()
+        }
+    };
+    let id = match ubicacion.id {
+        Some(id) => {
+            conn.execute(
+                "UPDATE ubicaciones_bodega SET codigo = ?1, pasillo = ?2, estante = ?3, casillero = ?4, orden_ruta = ?5
+                 WHERE id = ?6",
+                rusqlite::params![
+                    codigo, ubicacion.pasillo.trim().to_uppercase(), limpio(&ubicacion.estante),
+                    limpio(&ubicacion.casillero), orden_ruta, id
+                ],
+            )
+            .map_err(duplicado)?;
+            id
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO ubicaciones_bodega (establecimiento_id, codigo, pasillo, estante, casillero, orden_ruta)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![
+                    est_id, codigo, ubicacion.pasillo.trim().to_uppercase(), limpio(&ubicacion.estante),
+                    limpio(&ubicacion.casillero), orden_ruta
+                ],
+            )
+            .map_err(duplicado)?;
+            conn.last_insert_rowid()
+        }
+    };
+    obtener_ubicacion(&conn, id)
+}
+
+#[tauri::command]
+pub fn listar_ubicaciones(
+    db: State<Database>,
+    establecimiento_id: Option<i64>,
+    incluir_inactivas: Option<bool>,
+) -> Result<Vec<UbicacionBodega>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let est_id = match establecimiento_id {
+        Some(id) => id,
+        None => crate::commands::stock::establecimiento_terminal(&conn)?,
+    };
+    let mut stmt = conn
+        .prepare(
+            "SELECT id FROM ubicaciones_bodega WHERE establecimiento_id = ?1 AND (?2 = 1 OR activo = 1)
+             ORDER BY orden_ruta, codigo",
+        )
+        .map_err(|e| e.to_string())?;
+    let ids: Vec<i64> = stmt
+        .query_map(rusqlite::params![est_id, incluir_inactivas.unwrap_or(false) as i64], |r| r.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    ids.into_iter().map(|id| obtener_ubicacion(&conn, id)).collect()
+}
+
+/// Desactiva una ubicacion vacia (las historicas quedan en las listas).
+#[tauri::command]
+pub fn desactivar_ubicacion(db: State<Database>, sesion: State<SesionState>, id: i64) -> Result<(), String> {
+    crate::db::usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    requiere_inventario_avanzado(&conn)?;
+    let u = obtener_ubicacion(&conn, id)?;
+    if u.productos > 0 {
+        return Err(format!("La ubicación {} todavía tiene {} producto(s): muévalos antes", u.codigo, u.productos));
+    }
+    conn.execute("UPDATE ubicaciones_bodega SET activo = 0 WHERE id = ?1", rusqlite::params![id])
+        .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM stock_ubicacion WHERE ubicacion_id = ?1", rusqlite::params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn listar_stock_ubicacion(db: State<Database>, ubicacion_id: i64) -> Result<Vec<StockUbicacion>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT su.ubicacion_id, u.codigo, su.producto_id, p.nombre, su.cantidad, su.es_principal
+             FROM stock_ubicacion su
+             JOIN ubicaciones_bodega u ON u.id = su.ubicacion_id
+             JOIN productos p ON p.id = su.producto_id
+             WHERE su.ubicacion_id = ?1 AND (su.cantidad > 0 OR su.es_principal = 1)
+             ORDER BY p.nombre",
+        )
+        .map_err(|e| e.to_string())?;
+    let filas = stmt
+        .query_map(rusqlite::params![ubicacion_id], |r| {
+            Ok(StockUbicacion {
+                ubicacion_id: r.get(0)?,
+                ubicacion_codigo: r.get(1)?,
+                producto_id: r.get(2)?,
+                producto_nombre: r.get(3)?,
+                cantidad: r.get(4)?,
+                es_principal: r.get::<_, i64>(5)? != 0,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(filas)
+}
+
+#[tauri::command]
+pub fn ubicaciones_producto(
+    db: State<Database>,
+    producto_id: i64,
+    establecimiento_id: Option<i64>,
+) -> Result<UbicacionesProducto, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let est_id = match establecimiento_id {
+        Some(id) => id,
+        None => crate::commands::stock::establecimiento_terminal(&conn)?,
+    };
+    ubicaciones_de(&conn, producto_id, est_id)
+}
+
+/// Fija cuanto hay del producto en la ubicacion (conteo o acomodo inicial).
+/// Lo ubicado no puede superar el stock del establecimiento.
+#[tauri::command]
+pub fn asignar_stock_ubicacion(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    producto_id: i64,
+    ubicacion_id: i64,
+    cantidad: f64,
+    es_principal: Option<bool>,
+) -> Result<UbicacionesProducto, String> {
+    crate::db::usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    if cantidad < 0.0 {
+        return Err("La cantidad no puede ser negativa".to_string());
+    }
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    requiere_inventario_avanzado(&conn)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let est_id = establecimiento_ubicacion(&tx, ubicacion_id)?;
+    let actual = ubicaciones_de(&tx, producto_id, est_id)?;
+    let ubicado_resto = actual.ubicado - cantidad_en(&tx, producto_id, ubicacion_id);
+    if ubicado_resto + cantidad > actual.stock_establecimiento + EPS {
+        return Err(format!(
+            "Solo hay {} sin ubicar en el establecimiento (stock {}, ya ubicado {})",
+            (actual.stock_establecimiento - ubicado_resto).max(0.0), actual.stock_establecimiento, ubicado_resto
+        ));
+    }
+    if es_principal == Some(true) {
+        tx.execute(
+            "UPDATE stock_ubicacion SET es_principal = 0
+             WHERE producto_id = ?1 AND ubicacion_id IN (SELECT id FROM ubicaciones_bodega WHERE establecimiento_id = ?2)",
+            rusqlite::params![producto_id, est_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.execute(
+        "INSERT INTO stock_ubicacion (producto_id, ubicacion_id, cantidad, es_principal) VALUES (?1, ?2, ?3, COALESCE(?4, 0))
+         ON CONFLICT(producto_id, ubicacion_id) DO UPDATE SET cantidad = ?3, es_principal = COALESCE(?4, es_principal)",
+        rusqlite::params![producto_id, ubicacion_id, cantidad, es_principal.map(|b| b as i64)],
+    )
+    .map_err(|e| e.to_string())?;
+    let resultado = ubicaciones_de(&tx, producto_id, est_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(resultado)
+}
+
+#[tauri::command]
+pub fn mover_stock_ubicacion(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    producto_id: i64,
+    desde_ubicacion_id: i64,
+    hacia_ubicacion_id: i64,
+    cantidad: f64,
+) -> Result<UbicacionesProducto, String> {
+    crate::db::usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    if cantidad <= 0.0 {
+        return Err("La cantidad debe ser mayor a 0".to_string());
+    }
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    requiere_inventario_avanzado(&conn)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let est_desde = establecimiento_ubicacion(&tx, desde_ubicacion_id)?;
+    if establecimiento_ubicacion(&tx, hacia_ubicacion_id)? != est_desde {
+        return Err("Para mover entre establecimientos use una transferencia".to_string());
+    }
+    let disponible = cantidad_en(&tx, producto_id, desde_ubicacion_id);
+    if cantidad > disponible + EPS {
+        return Err(format!("En la ubicación de origen solo hay {}", disponible));
+    }
+    mover_en_ubicacion(&tx, producto_id, desde_ubicacion_id, -cantidad)?;
+    mover_en_ubicacion(&tx, producto_id, hacia_ubicacion_id, cantidad)?;
+    let resultado = ubicaciones_de(&tx, producto_id, est_desde)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(resultado)
+}
+
+// ─── Comandos Tauri: picking ─────────────────────────────────────────────
+
+#[tauri::command]
+pub fn generar_picking_guia(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    guia_id: i64,
+    observacion: Option<String>,
+) -> Result<ListaPicking, String> {
+    let usuario = crate::db::usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    requiere_inventario_avanzado(&conn)?;
+    let es_guia: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM ventas WHERE id = ?1 AND tipo_estado = 'GUIA_REMISION'",
+            rusqlite::params![guia_id],
+            |r| r.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if es_guia == 0 {
+        return Err("Nota de entrega no encontrada".to_string());
+    }
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let id = generar(&tx, &[guia_id], observacion.as_deref(), &usuario)?;
+    let lista = obtener_lista(&tx, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(lista)
+}
+
+/// Una lista para varias ventas/guias (ruta de reparto, pedidos del dia).
+#[tauri::command]
+pub fn generar_picking_ventas(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    venta_ids: Vec<i64>,
+    observacion: Option<String>,
+) -> Result<ListaPicking, String> {
+    let usuario = crate::db::usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    requiere_inventario_avanzado(&conn)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let id = generar(&tx, &venta_ids, observacion.as_deref(), &usuario)?;
+    let lista = obtener_lista(&tx, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(lista)
+}
+
+#[tauri::command]
+pub fn listar_listas_picking(
+    db: State<Database>,
+    estado: Option<String>,
+    establecimiento_id: Option<i64>,
+) -> Result<Vec<ListaPicking>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id FROM listas_picking
+             WHERE (?1 IS NULL OR estado = ?1) AND (?2 IS NULL OR establecimiento_id = ?2)
+             ORDER BY id DESC LIMIT 200",
+        )
+        .map_err(|e| e.to_string())?;
+    let ids: Vec<i64> = stmt
+        .query_map(rusqlite::params![estado.filter(|s| !s.is_empty()), establecimiento_id], |r| r.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    ids.into_iter().map(|id| obtener_lista(&conn, id)).collect()
+}
+
+#[tauri::command]
+pub fn obtener_lista_picking(db: State<Database>, id: i64) -> Result<ListaPicking, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    obtener_lista(&conn, id)
+}
+
+/// Confirmacion con lector: cada lectura recoge `cantidad` (1 por defecto)
+/// del producto, en la ubicacion escaneada si se indica.
+#[tauri::command]
+pub fn confirmar_picking_escaneo(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    lista_id: i64,
+    codigo: String,
+    ubicacion_codigo: Option<String>,
+    cantidad: Option<f64>,
+) -> Result<ListaPicking, String> {
+    let usuario = crate::db::usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    requiere_inventario_avanzado(&conn)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    confirmar_escaneo(&tx, lista_id, &codigo, ubicacion_codigo.as_deref(), cantidad.unwrap_or(1.0), &usuario)?;
+    let lista = obtener_lista(&tx, lista_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(lista)
+}
+
+/// Confirmacion manual de una linea (sin cantidad: lo que falta).
+#[tauri::command]
+pub fn confirmar_item_picking(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    item_id: i64,
+    cantidad: Option<f64>,
+) -> Result<ListaPicking, String> {
+    let usuario = crate::db::usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    requiere_inventario_avanzado(&conn)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let pendiente: f64 = tx
+        .query_row(
+            "SELECT cantidad - cantidad_recogida FROM lista_picking_items WHERE id = ?1",
+            rusqlite::params![item_id],
+            |r| r.get(0),
+        )
+        .map_err(|_| format!("Línea de picking #{} no encontrada", item_id))?;
+    let lista_id = recoger(&tx, item_id, cantidad.unwrap_or(pendiente), &usuario)?;
+    let lista = obtener_lista(&tx, lista_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(lista)
+}
+
+/// Cierra la linea con lo recogido hasta ahora (no se encontro el resto).
+#[tauri::command]
+pub fn marcar_faltante_picking(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    item_id: i64,
+) -> Result<ListaPicking, String> {
+    let usuario = crate::db::usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    requiere_inventario_avanzado(&conn)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let lista_id: i64 = tx
+        .query_row(
+            "SELECT lista_id FROM lista_picking_items WHERE id = ?1 AND estado = 'PENDIENTE'",
+            rusqlite::params![item_id],
+            |r| r.get(0),
+        )
+        .map_err(|_| "La línea no existe o ya fue confirmada".to_string())?;
+    lista_abierta(&tx, lista_id)?;
+    tx.execute(
+        "UPDATE lista_picking_items SET estado = 'FALTANTE', usuario = ?1, fecha_confirmacion = datetime('now','localtime')
+         WHERE id = ?2",
+        rusqlite::params![usuario, item_id],
+    )
+    .map_err(|e| e.to_string())?;
+    actualizar_estado_lista(&tx, lista_id)?;
+    let lista = obtener_lista(&tx, lista_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(lista)
+}
+
+#[tauri::command]
+pub fn anular_lista_picking(db: State<Database>, sesion: State<SesionState>, id: i64) -> Result<ListaPicking, String> {
+    crate::db::usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    requiere_inventario_avanzado(&conn)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    anular_lista(&tx, id)?;
+    let lista = obtener_lista(&tx, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(lista)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codigo_ubicacion_une_partes() {
+        assert_eq!(codigo_ubicacion("a", Some("03"), Some(" 2 ")), "A-03-2");
+        assert_eq!(codigo_ubicacion("b", None, Some("")), "B");
+    }
+
+    #[test]
+    fn repartir_recogida_prefiere_una_sola_ubicacion() {
+        // La segunda alcanza sola
+        assert_eq!(repartir_recogida(5.0, &[(1, 3.0), (2, 8.0)], None), vec![(Some(2), 5.0)]);
+        // Ninguna alcanza: en orden de recorrido, el resto a la principal
+        assert_eq!(
+            repartir_recogida(10.0, &[(1, 3.0), (2, 4.0)], Some(2)),
+            vec![(Some(1), 3.0), (Some(2), 7.0)]
+        );
+        // Sin principal el resto queda sin ubicacion
+        assert_eq!(repartir_recogida(4.0, &[(1, 1.0)], None), vec![(Some(1), 1.0), (None, 3.0)]);
+        assert_eq!(repartir_recogida(2.0, &[], Some(9)), vec![(Some(9), 2.0)]);
+    }
+
+    #[test]
+    fn recoger_lo_ya_vendido_traslada_el_recorte() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE ubicaciones_bodega (id INTEGER PRIMARY KEY, establecimiento_id INTEGER, codigo TEXT, orden_ruta INTEGER);
+             CREATE TABLE stock_ubicacion (producto_id INTEGER, ubicacion_id INTEGER, cantidad REAL, es_principal INTEGER DEFAULT 0,
+                                           UNIQUE(producto_id, ubicacion_id));
+             INSERT INTO ubicaciones_bodega VALUES (1, 1, 'A-01', 1), (2, 1, 'B-01', 2);
+             -- Stock 10 (5 + 5); la venta de 3 ya recorto la principal a 2
+             INSERT INTO stock_ubicacion VALUES (7, 1, 2, 1), (7, 2, 5, 0);",
+        )
+        .unwrap();
+        let cantidad = |u: i64| -> f64 {
+            conn.query_row("SELECT cantidad FROM stock_ubicacion WHERE producto_id = 7 AND ubicacion_id = ?1", [u], |r| r.get(0))
+                .unwrap()
+        };
+        // Se recogen las 3 de B-01: el recorte pasa de la principal a B-01
+        recortar_en(&conn, 7, 2, 3.0).unwrap();
+        assert_eq!((cantidad(1), cantidad(2)), (5.0, 2.0));
+        // Recogidas de la principal: el recorte ya estaba ahi
+        recortar_en(&conn, 7, 1, 1.0).unwrap();
+        assert_eq!((cantidad(1), cantidad(2)), (5.0, 2.0));
+    }
+}
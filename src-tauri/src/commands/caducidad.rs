@@ -0,0 +1,486 @@
+// Lotes con caducidad en la venta (FEFO) y descuentos por caducidad proxima
+//
+// Venta (ventas::registrar_venta):
+//   1. planificar_fefo reparte la cantidad vendida entre los lotes vigentes
+//      del establecimiento, primero el que vence antes. Un lote elegido a mano
+//      se respeta. Los lotes vencidos no se venden: si lo que falta solo puede
+//      salir de lotes vencidos, la venta se bloquea.
+//   2. aplicar_fefo descuenta los lotes y guarda el reparto en
+//      venta_detalle_lotes.
+// NC / devolucion / anulacion: reintegrar_lotes devuelve la cantidad a los
+// mismos lotes (ultimo asignado primero). Ventas anteriores al reparto usan
+// venta_detalles.lote_id como antes.
+//
+// Descuentos: si `descuento_caducidad_auto` = '1', las unidades que salen de
+// un lote que vence dentro de `dias_antes` de una regla activa llevan su
+// descuento (la regla mas generosa que aplique). registrar_venta lo fija en
+// las lineas sin descuento manual antes de calcular los totales; el POS puede
+// mostrarlo antes de cobrar con calcular_descuento_caducidad.
+
+use crate::db::{usuario_con_permiso, Database, SesionState};
+use crate::models::{ProductoDescuentoCaducidad, ReglaDescuentoCaducidad, VentaDetalle};
+use rusqlite::Connection;
+use tauri::State;
+
+/// Parte de una linea de venta que sale de un lote.
+#[derive(Debug, Clone)]
+pub struct AsignacionLote {
+    pub lote_id: i64,
+    pub cantidad: f64,
+}
+
+fn nombre_producto(conn: &Connection, producto_id: i64) -> String {
+    conn.query_row("SELECT nombre FROM productos WHERE id = ?1", rusqlite::params![producto_id], |r| r.get(0))
+        .unwrap_or_else(|_| format!("ID {}", producto_id))
+}
+
+/// Lotes vigentes (no vencidos, con saldo) del producto en el establecimiento,
+/// en orden FEFO: (id, cantidad, dias_restantes). Los lotes sin establecimiento
+/// (datos anteriores) cuentan en cualquiera.
+fn lotes_vigentes(conn: &Connection, producto_id: i64, establecimiento_id: i64) -> Result<Vec<(i64, f64, i64)>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, cantidad,
+                CAST(julianday(date(fecha_caducidad)) - julianday(date('now','localtime')) AS INTEGER)
+         FROM lotes_caducidad
+         WHERE producto_id = ?1 AND cantidad > 0
+           AND (establecimiento_id = ?2 OR establecimiento_id IS NULL)
+           AND date(fecha_caducidad) >= date('now','localtime')
+         ORDER BY date(fecha_caducidad) ASC, id ASC",
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params![producto_id, establecimiento_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+/// Reparto FEFO de `cantidad` (unidad base). Vacio si el producto no maneja
+/// caducidad y no se eligio lote.
+pub fn planificar_fefo(
+    conn: &Connection,
+    producto_id: i64,
+    establecimiento_id: i64,
+    cantidad: f64,
+    lote_elegido: Option<i64>,
+) -> Result<Vec<AsignacionLote>, String> {
+    if cantidad <= 0.0 {
+        return Ok(Vec::new());
+    }
+    if let Some(lid) = lote_elegido {
+        let (stock_lote, lote, vencido, lote_est): (f64, Option<String>, bool, Option<i64>) = conn
+            .query_row(
+                "SELECT cantidad, lote, date(fecha_caducidad) < date('now','localtime'), establecimiento_id
+                 FROM lotes_caducidad WHERE id = ?1 AND producto_id = ?2",
+                rusqlite::params![lid, producto_id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+            )
+            .map_err(|_| format!("El lote #{} no pertenece a '{}'", lid, nombre_producto(conn, producto_id)))?;
+        if lote_est.is_some_and(|e| e != establecimiento_id) {
+            return Err(format!(
+                "El lote {} de '{}' es de otro establecimiento.",
+                lote.unwrap_or_else(|| format!("#{}", lid)), nombre_producto(conn, producto_id)
+            ));
+        }
+        if vencido {
+            return Err(format!(
+                "El lote {} de '{}' está vencido: no se puede vender.",
+                lote.unwrap_or_else(|| format!("#{}", lid)), nombre_producto(conn, producto_id)
+            ));
+        }
+        // Validar que el lote tenga stock suficiente — anti-fraude/error UX
+        if cantidad > stock_lote + 1e-9 {
+            return Err(format!(
+                "El lote #{} de '{}' solo tiene {:.2} unidades. Intentas vender {:.2}. Reduce la cantidad o agrega otro lote/sin lote.",
+                lid, nombre_producto(conn, producto_id), stock_lote, cantidad
+            ));
+        }
+        return Ok(vec![AsignacionLote { lote_id: lid, cantidad }]);
+    }
+
+    let requiere_caducidad: bool = conn
+        .query_row(
+            "SELECT COALESCE(requiere_caducidad, 0) FROM productos WHERE id = ?1",
+            rusqlite::params![producto_id],
+            |r| r.get::<_, i32>(0).map(|v| v != 0),
+        )
+        .unwrap_or(false);
+    if !requiere_caducidad {
+        return Ok(Vec::new());
+    }
+
+    let mut plan = Vec::new();
+    let mut restante = cantidad;
+    for (lote_id, saldo, _) in lotes_vigentes(conn, producto_id, establecimiento_id)? {
+        if restante <= 1e-9 {
+            break;
+        }
+        let tomar = restante.min(saldo);
+        plan.push(AsignacionLote { lote_id, cantidad: tomar });
+        restante -= tomar;
+    }
+
+    if restante > 1e-9 {
+        // Lo que falta puede salir de stock sin lote (stock inicial, ajustes);
+        // si solo queda en lotes vencidos, se bloquea.
+        let (en_lotes, vencido): (f64, f64) = conn
+            .query_row(
+                "SELECT COALESCE(SUM(cantidad), 0),
+                        COALESCE(SUM(CASE WHEN date(fecha_caducidad) < date('now','localtime') THEN cantidad ELSE 0 END), 0)
+                 FROM lotes_caducidad
+                 WHERE producto_id = ?1 AND cantidad > 0
+                   AND (establecimiento_id = ?2 OR establecimiento_id IS NULL)",
+                rusqlite::params![producto_id, establecimiento_id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap_or((0.0, 0.0));
+        let sin_lote = (crate::commands::stock::stock_en(conn, producto_id, establecimiento_id) - en_lotes).max(0.0);
+        if vencido > 1e-9 && restante > sin_lote + 1e-9 {
+            return Err(format!(
+                "'{}': solo hay {:.2} unidades vigentes para vender {:.2}. Otras {:.2} están en lotes vencidos y no se pueden vender.",
+                nombre_producto(conn, producto_id),
+                cantidad - restante + sin_lote,
+                cantidad,
+                vencido
+            ));
+        }
+    }
+    Ok(plan)
+}
+
+/// Descuenta los lotes y registra el reparto de la linea de venta.
+pub fn aplicar_fefo(
+    conn: &Connection,
+    venta_id: i64,
+    venta_detalle_id: i64,
+    producto_id: i64,
+    plan: &[AsignacionLote],
+) -> Result<(), String> {
+    for a in plan {
+        conn.execute(
+            "UPDATE lotes_caducidad SET cantidad = MAX(cantidad - ?1, 0) WHERE id = ?2",
+            rusqlite::params![a.cantidad, a.lote_id],
+        ).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO venta_detalle_lotes (venta_id, venta_detalle_id, producto_id, lote_id, cantidad)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![venta_id, venta_detalle_id, producto_id, a.lote_id, a.cantidad],
+        ).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Devuelve `cantidad` (unidad base) de un producto de la venta a sus lotes.
+/// `lote_id_detalle` es el lote de venta_detalles, para ventas sin reparto.
+pub fn reintegrar_lotes(
+    conn: &Connection,
+    venta_id: i64,
+    producto_id: i64,
+    cantidad: f64,
+    lote_id_detalle: Option<i64>,
+) -> Result<(), String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, lote_id, cantidad - cantidad_devuelta FROM venta_detalle_lotes
+         WHERE venta_id = ?1 AND producto_id = ?2 AND cantidad - cantidad_devuelta > 1e-9
+         ORDER BY id DESC",
+    ).map_err(|e| e.to_string())?;
+    let asignados: Vec<(i64, i64, f64)> = stmt
+        .query_map(rusqlite::params![venta_id, producto_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let ya_repartida: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM venta_detalle_lotes WHERE venta_id = ?1 AND producto_id = ?2",
+            rusqlite::params![venta_id, producto_id],
+            |r| r.get(0),
+        )
+        .unwrap_or(false);
+    if !ya_repartida {
+        if let Some(lid) = lote_id_detalle {
+            conn.execute(
+                "UPDATE lotes_caducidad SET cantidad = cantidad + ?1 WHERE id = ?2",
+                rusqlite::params![cantidad, lid],
+            ).map_err(|e| e.to_string())?;
+        }
+        return Ok(());
+    }
+
+    let mut restante = cantidad;
+    for (id, lote_id, pendiente) in asignados {
+        if restante <= 1e-9 {
+            break;
+        }
+        let devolver = restante.min(pendiente);
+        conn.execute(
+            "UPDATE lotes_caducidad SET cantidad = cantidad + ?1 WHERE id = ?2",
+            rusqlite::params![devolver, lote_id],
+        ).map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE venta_detalle_lotes SET cantidad_devuelta = cantidad_devuelta + ?1 WHERE id = ?2",
+            rusqlite::params![devolver, id],
+        ).map_err(|e| e.to_string())?;
+        restante -= devolver;
+    }
+    Ok(())
+}
+
+/// Mejor descuento (%) de las reglas activas para un producto que vence en
+/// `dias` dias. None = ninguna regla aplica.
+fn mejor_regla(conn: &Connection, producto_id: i64, dias: i64) -> Option<(String, f64)> {
+    conn.query_row(
+        "SELECT r.nombre, r.descuento_pct
+         FROM reglas_descuento_caducidad r, productos p
+         WHERE p.id = ?1 AND r.activo = 1 AND ?2 <= r.dias_antes
+           AND (r.producto_id = p.id
+                OR (r.producto_id IS NULL AND (r.categoria_id IS NULL OR r.categoria_id = p.categoria_id)))
+         ORDER BY r.descuento_pct DESC LIMIT 1",
+        rusqlite::params![producto_id, dias],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )
+    .ok()
+}
+
+fn descuento_auto_activo(conn: &Connection) -> bool {
+    conn.query_row("SELECT value FROM config WHERE key = 'descuento_caducidad_auto'", [], |r| r.get::<_, String>(0))
+        .map(|v| v == "1")
+        .unwrap_or(false)
+}
+
+/// Descuento por caducidad (monto de la linea) que corresponde a una linea de
+/// venta. Solo la parte de la cantidad que sale (por FEFO) de lotes dentro de
+/// la ventana de una regla lleva descuento. 0 si no aplica ninguna regla.
+pub fn descuento_linea(
+    conn: &Connection,
+    producto_id: i64,
+    cantidad: f64,
+    factor_unidad: Option<f64>,
+    lote_id: Option<i64>,
+    precio_unitario: f64,
+    establecimiento_id: i64,
+) -> Result<f64, String> {
+    let factor = factor_unidad.unwrap_or(1.0);
+    if cantidad <= 0.0 || factor <= 0.0 {
+        return Ok(0.0);
+    }
+    let cantidad_base = cantidad * factor;
+    // Mismo orden de lotes que usara la venta
+    let lotes: Vec<(f64, i64)> = match lote_id {
+        Some(lid) => conn
+            .query_row(
+                "SELECT CAST(julianday(date(fecha_caducidad)) - julianday(date('now','localtime')) AS INTEGER)
+                 FROM lotes_caducidad WHERE id = ?1 AND producto_id = ?2",
+                rusqlite::params![lid, producto_id],
+                |r| r.get::<_, i64>(0),
+            )
+            .map(|dias| vec![(cantidad_base, dias)])
+            .unwrap_or_default(),
+        None => lotes_vigentes(conn, producto_id, establecimiento_id)?
+            .into_iter()
+            .map(|(_, saldo, dias)| (saldo, dias))
+            .collect(),
+    };
+    let mut restante = cantidad_base;
+    let mut base_ponderada = 0.0;
+    for (saldo, dias) in lotes {
+        if restante <= 1e-9 {
+            break;
+        }
+        let tomar = restante.min(saldo);
+        if let Some((_, pct)) = mejor_regla(conn, producto_id, dias) {
+            base_ponderada += tomar * pct / 100.0;
+        }
+        restante -= tomar;
+    }
+    Ok(((base_ponderada / factor) * precio_unitario * 100.0).round() / 100.0)
+}
+
+/// Con `descuento_caducidad_auto` activo, fija el descuento por caducidad de
+/// las lineas que no traen descuento manual (y su subtotal), antes de que
+/// registrar_venta calcule los totales.
+pub fn aplicar_descuentos(conn: &Connection, items: &mut [VentaDetalle], establecimiento_id: i64) -> Result<(), String> {
+    if !descuento_auto_activo(conn) {
+        return Ok(());
+    }
+    for item in items.iter_mut() {
+        let Some(pid) = item.producto_id else { continue };
+        if item.descuento > 0.0 {
+            continue;
+        }
+        let descuento = descuento_linea(
+            conn,
+            pid,
+            item.cantidad,
+            item.factor_unidad,
+            item.lote_id,
+            item.precio_unitario,
+            establecimiento_id,
+        )?;
+        if descuento >= 0.01 {
+            item.descuento = descuento;
+            item.subtotal = ((item.cantidad * item.precio_unitario - descuento) * 100.0).round() / 100.0;
+        }
+    }
+    Ok(())
+}
+
+/// Productos que hoy se venden con descuento por caducidad (lote mas proximo).
+pub fn productos_con_descuento(conn: &Connection, establecimiento_id: i64) -> Result<Vec<ProductoDescuentoCaducidad>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.codigo, p.nombre, p.precio_venta, l.id, l.lote, l.fecha_caducidad, l.cantidad,
+                CAST(julianday(date(l.fecha_caducidad)) - julianday(date('now','localtime')) AS INTEGER)
+         FROM lotes_caducidad l
+         JOIN productos p ON p.id = l.producto_id
+         WHERE l.cantidad > 0 AND p.activo = 1
+           AND (l.establecimiento_id = ?1 OR l.establecimiento_id IS NULL)
+           AND date(l.fecha_caducidad) >= date('now','localtime')
+           AND l.id = (SELECT l2.id FROM lotes_caducidad l2
+                       WHERE l2.producto_id = l.producto_id AND l2.cantidad > 0
+                         AND (l2.establecimiento_id = ?1 OR l2.establecimiento_id IS NULL)
+                         AND date(l2.fecha_caducidad) >= date('now','localtime')
+                       ORDER BY date(l2.fecha_caducidad), l2.id LIMIT 1)
+         ORDER BY date(l.fecha_caducidad)",
+    ).map_err(|e| e.to_string())?;
+    #[allow(clippy::type_complexity)]
+    let lotes: Vec<(i64, Option<String>, String, f64, i64, Option<String>, String, f64, i64)> = stmt
+        .query_map(rusqlite::params![establecimiento_id], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?, r.get(6)?, r.get(7)?, r.get(8)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut resultado = Vec::new();
+    for (pid, codigo, nombre, precio, lote_id, lote, fecha, cantidad, dias) in lotes {
+        if let Some((regla, pct)) = mejor_regla(conn, pid, dias) {
+            resultado.push(ProductoDescuentoCaducidad {
+                producto_id: pid,
+                codigo,
+                nombre,
+                lote_id,
+                lote,
+                fecha_caducidad: fecha,
+                dias_restantes: dias,
+                cantidad_lote: cantidad,
+                regla,
+                descuento_pct: pct,
+                precio_venta: precio,
+                precio_con_descuento: (precio * (1.0 - pct / 100.0) * 100.0).round() / 100.0,
+            });
+        }
+    }
+    Ok(resultado)
+}
+
+// ─── Comandos Tauri ──────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn listar_reglas_descuento_caducidad(db: State<Database>) -> Result<Vec<ReglaDescuentoCaducidad>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT id, nombre, dias_antes, descuento_pct, categoria_id, producto_id, activo
+         FROM reglas_descuento_caducidad ORDER BY dias_antes, id",
+    ).map_err(|e| e.to_string())?;
+    let reglas = stmt
+        .query_map([], |r| {
+            Ok(ReglaDescuentoCaducidad {
+                id: Some(r.get(0)?),
+                nombre: r.get(1)?,
+                dias_antes: r.get(2)?,
+                descuento_pct: r.get(3)?,
+                categoria_id: r.get(4)?,
+                producto_id: r.get(5)?,
+                activo: r.get::<_, i64>(6)? != 0,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(reglas)
+}
+
+/// Crea (sin id) o actualiza una regla.
+#[tauri::command]
+pub fn guardar_regla_descuento_caducidad(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    regla: ReglaDescuentoCaducidad,
+) -> Result<i64, String> {
+    usuario_con_permiso(&sesion, "gestionar_productos")?;
+    if regla.nombre.trim().is_empty() {
+        return Err("El nombre de la regla es obligatorio".to_string());
+    }
+    if regla.dias_antes < 0 {
+        return Err("Los días antes del vencimiento no pueden ser negativos".to_string());
+    }
+    if regla.descuento_pct <= 0.0 || regla.descuento_pct > 100.0 {
+        return Err("El descuento debe estar entre 0 y 100%".to_string());
+    }
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    match regla.id {
+        Some(id) => {
+            conn.execute(
+                "UPDATE reglas_descuento_caducidad SET nombre = ?1, dias_antes = ?2, descuento_pct = ?3,
+                        categoria_id = ?4, producto_id = ?5, activo = ?6
+                 WHERE id = ?7",
+                rusqlite::params![
+                    regla.nombre.trim(), regla.dias_antes, regla.descuento_pct,
+                    regla.categoria_id, regla.producto_id, regla.activo as i64, id
+                ],
+            ).map_err(|e| e.to_string())?;
+            Ok(id)
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO reglas_descuento_caducidad (nombre, dias_antes, descuento_pct, categoria_id, producto_id, activo)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![
+                    regla.nombre.trim(), regla.dias_antes, regla.descuento_pct,
+                    regla.categoria_id, regla.producto_id, regla.activo as i64
+                ],
+            ).map_err(|e| e.to_string())?;
+            Ok(conn.last_insert_rowid())
+        }
+    }
+}
+
+#[tauri::command]
+pub fn eliminar_regla_descuento_caducidad(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    id: i64,
+) -> Result<(), String> {
+    usuario_con_permiso(&sesion, "gestionar_productos")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM reglas_descuento_caducidad WHERE id = ?1", rusqlite::params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Descuento por caducidad de una linea, en el establecimiento de la terminal.
+/// 0 si el descuento automatico esta apagado o ninguna regla aplica.
+#[tauri::command]
+pub fn calcular_descuento_caducidad(
+    db: State<Database>,
+    producto_id: i64,
+    cantidad: f64,
+    factor_unidad: Option<f64>,
+    lote_id: Option<i64>,
+    precio_unitario: f64,
+) -> Result<f64, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    if !descuento_auto_activo(&conn) {
+        return Ok(0.0);
+    }
+    let est_id = crate::commands::stock::establecimiento_terminal(&conn)?;
+    descuento_linea(&conn, producto_id, cantidad, factor_unidad, lote_id, precio_unitario, est_id)
+}
+
+/// Productos con descuento por caducidad en el establecimiento de la terminal.
+#[tauri::command]
+pub fn listar_productos_descuento_caducidad(db: State<Database>) -> Result<Vec<ProductoDescuentoCaducidad>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let est_id = crate::commands::stock::establecimiento_terminal(&conn)?;
+    productos_con_descuento(&conn, est_id)
+}
@@ -385,7 +385,9 @@ pub fn cerrar_caja(
     // fuente de verdad confiable porque hace SUM en tiempo real de cada tabla.
     // Bonus: el frontend muestra exactamente este mismo valor (via obtener_caja_abierta
     // que tambien recalcula), asi NO hay sorpresas: lo que ves es lo que cuenta.
-    let monto_esperado = monto_inicial + total_efectivo + total_cobros_efectivo + total_ingresos_manuales - total_gastos - total_retiros;
+    // Propinas de restaurante en efectivo: entran al cajón pero no son venta.
+    let total_propinas = propinas_efectivo_caja(&conn, caja_id);
+    let monto_esperado = monto_inicial + total_efectivo + total_cobros_efectivo + total_ingresos_manuales + total_propinas - total_gastos - total_retiros;
     let diferencia = monto_real - monto_esperado;
 
     // Anti-fraude: si hay descuadre, exigir motivo (mínimo 5 caracteres).
@@ -496,6 +498,26 @@ pub fn cerrar_caja(
     log_evento_caja(&conn, caja_id, "CIERRE", &usuario_cierre, usuario_cierre_id,
         Some(&snapshot_anterior), Some(&snapshot_nuevo), motivo_descuadre.as_deref());
 
+    crate::webhooks::emitir(
+        &conn,
+        "caja.cerrada",
+        &format!("caja:{}", caja_id),
+        serde_json::json!({
+            "caja_id": caja_id,
+            "monto_inicial": monto_inicial,
+            "total_ventas": total_ventas,
+            "num_ventas": num_ventas,
+            "total_efectivo": total_efectivo,
+            "total_gastos": total_gastos,
+            "total_retiros": total_retiros,
+            "monto_esperado": monto_esperado,
+            "monto_real": monto_real,
+            "diferencia": diferencia,
+            "motivo_descuadre": motivo_descuadre,
+            "usuario_cierre": usuario_cierre,
+        }),
+    );
+
     // NOTA: Antes se cerraba la sesion automaticamente aqui, pero eso rompia el flujo
     // del cajero al querer abrir nueva caja inmediatamente despues (error "Debe iniciar
     // sesion para abrir la caja"). El cierre de sesion ahora es responsabilidad del
@@ -528,6 +550,7 @@ pub fn cerrar_caja(
         total_cobros_efectivo,
         total_cobros_banco,
         total_retiros,
+        total_propinas,
     })
 }
 
@@ -1108,7 +1131,23 @@ pub fn calcular_monto_esperado_actual(conn: &rusqlite::Connection, caja_id: i64)
         )
         .unwrap_or(0.0);
 
-    monto_inicial + total_efectivo + total_cobros_efectivo + total_ingresos_manuales - total_gastos - total_retiros
+    let total_propinas = propinas_efectivo_caja(conn, caja_id);
+
+    monto_inicial + total_efectivo + total_cobros_efectivo + total_ingresos_manuales + total_propinas - total_gastos - total_retiros
+}
+
+/// Propinas cobradas en EFECTIVO en esta caja (módulo restaurante).
+/// Se llevan aparte de `ventas.total` para no inflar las ventas, pero el
+/// efectivo sí está en el cajón. 0 si el módulo nunca creó su tabla.
+fn propinas_efectivo_caja(conn: &rusqlite::Connection, caja_id: i64) -> f64 {
+    conn.query_row(
+        "SELECT COALESCE(SUM(p.monto), 0) FROM rest_propinas p
+         JOIN ventas v ON v.id = p.venta_id
+         WHERE p.caja_id = ?1 AND v.anulada = 0 AND p.forma_pago = 'EFECTIVO'",
+        rusqlite::params![caja_id],
+        |row| row.get(0),
+    )
+    .unwrap_or(0.0)
 }
 
 #[tauri::command]
@@ -9,6 +9,23 @@ fn usuario_actual(sesion: &SesionState) -> String {
         .unwrap_or_else(|| "ADMIN".to_string())
 }
 
+/// Establecimiento donde ingresó la compra; las compras anteriores a
+/// establecimiento_id se revierten en el de la terminal.
+fn establecimiento_compra(conn: &rusqlite::Connection, compra_id: i64) -> Result<i64, String> {
+    let est: Option<i64> = conn
+        .query_row(
+            "SELECT establecimiento_id FROM compras WHERE id = ?1",
+            rusqlite::params![compra_id],
+            |r| r.get(0),
+        )
+        .ok()
+        .flatten();
+    match est {
+        Some(id) => Ok(id),
+        None => crate::commands::stock::establecimiento_terminal(conn),
+    }
+}
+
 /// v2.5.30: valida que numero_factura+tipo_documento no este duplicado para el proveedor.
 /// Retorna Err con mensaje amigable si ya existe. Tambien valida clave_acceso unica.
 fn validar_factura_unica(
@@ -168,12 +185,15 @@ pub fn registrar_compra(
         .map(|s| convertir_fecha_sri(s).unwrap_or_else(|| s.clone()))
         .filter(|s| !s.trim().is_empty());
 
+    // El ingreso va al establecimiento de la terminal
+    let est_id = crate::commands::stock::establecimiento_terminal(&conn)?;
+
     // Insertar compra
     conn.execute(
         "INSERT INTO compras (numero, proveedor_id, numero_factura, subtotal, iva, total,
                               forma_pago, es_credito, observacion, banco_id, referencia_pago,
-                              tipo_documento, estado_sri, clave_acceso, fecha_emision, usuario)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+                              tipo_documento, estado_sri, clave_acceso, fecha_emision, usuario, establecimiento_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
         rusqlite::params![
             numero,
             compra.proveedor_id,
@@ -191,6 +211,7 @@ pub fn registrar_compra(
             clave_acceso,
             fecha_emision_norm,
             usuario,
+            est_id,
         ],
     )
     .map_err(|e| {
@@ -273,23 +294,22 @@ pub fn registrar_compra(
                 }
             };
 
-            // Stock anterior antes del UPDATE — para kardex
-            let stock_antes_kardex = stock_actual;
-
-            // Actualizar stock + precio_costo (último) + costo_promedio (PMP)
+            // Actualizar stock (establecimiento + global) + precio_costo (último) + costo_promedio (PMP)
+            let mov = crate::commands::stock::mover(&conn, pid, est_id, cantidad_efectiva)?;
             conn.execute(
-                "UPDATE productos SET stock_actual = stock_actual + ?1, precio_costo = ?2,
-                 costo_promedio = ?3, updated_at = datetime('now','localtime') WHERE id = ?4",
-                rusqlite::params![cantidad_efectiva, precio_unitario_efectivo, nuevo_costo_promedio, pid],
+                "UPDATE productos SET precio_costo = ?1, costo_promedio = ?2 WHERE id = ?3",
+                rusqlite::params![precio_unitario_efectivo, nuevo_costo_promedio, pid],
             )
             .map_err(|e| e.to_string())?;
+            // Capa de costo FIFO de esta linea (commands/costeo.rs)
+            crate::commands::costeo::crear_capa(&conn, pid, est_id, "COMPRA", Some(compra_id), cantidad_efectiva, precio_unitario_efectivo)?;
 
             // v2.5.30: registrar movimiento en kardex (INGRESO_COMPRA)
             let motivo_kardex = format!("Compra {} - {}", numero, &nombre);
             let _ = conn.execute(
-                "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, motivo)
-                 VALUES (?1, 'INGRESO_COMPRA', ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-                rusqlite::params![pid, cantidad_efectiva, stock_antes_kardex, stock_antes_kardex + cantidad_efectiva, precio_unitario_efectivo, compra_id, usuario, motivo_kardex],
+                "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, motivo, establecimiento_id)
+                 VALUES (?1, 'INGRESO_COMPRA', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                rusqlite::params![pid, cantidad_efectiva, mov.anterior, mov.nuevo, precio_unitario_efectivo, compra_id, usuario, motivo_kardex, est_id],
             );
 
             Some(nombre)
@@ -608,7 +628,7 @@ pub fn obtener_compra(db: State<Database>, id: i64) -> Result<CompraCompleta, St
 /// cantidad_devuelta), calcula cuántas unidades se pueden todavía devolver o anular sin
 /// generar stock negativo. Fórmula:
 ///     pendiente_de_la_compra = cantidad_comprada - cantidad_devuelta
-///     stock_actual = lo que el producto tiene ahora
+///     stock_actual = lo que el producto tiene ahora en el establecimiento de la compra
 ///     devolvible = min(pendiente_de_la_compra, stock_actual)
 ///
 /// Retorna (devolvible, vendido_post_compra) donde vendido_post_compra =
@@ -618,12 +638,10 @@ fn calcular_devolvible(
     producto_id: i64,
     cantidad_comprada: f64,
     cantidad_devuelta: f64,
+    establecimiento_id: i64,
 ) -> (f64, f64) {
     let pendiente = (cantidad_comprada - cantidad_devuelta).max(0.0);
-    let stock_actual: f64 = conn.query_row(
-        "SELECT stock_actual FROM productos WHERE id = ?1",
-        rusqlite::params![producto_id], |r| r.get(0),
-    ).unwrap_or(0.0);
+    let stock_actual = crate::commands::stock::stock_en(conn, producto_id, establecimiento_id);
     let devolvible = pendiente.min(stock_actual.max(0.0));
     let vendido = (pendiente - devolvible).max(0.0);
     (devolvible, vendido)
@@ -671,7 +689,8 @@ pub fn anular_compra(
                     COALESCE(cd.cantidad_devuelta, 0)
              FROM compra_detalles cd
              LEFT JOIN productos p ON cd.producto_id = p.id
-             WHERE cd.compra_id = ?1 AND cd.producto_id IS NOT NULL",
+             WHERE cd.compra_id = ?1 AND cd.producto_id IS NOT NULL
+               AND COALESCE(cd.recibido_en_oc, 0) = 0",
         )
         .map_err(|e| e.to_string())?;
 
@@ -684,9 +703,10 @@ pub fn anular_compra(
         .map_err(|e| e.to_string())?;
 
     // Detectar items con stock_actual menor a la cantidad que habría que revertir
+    let est_id = establecimiento_compra(&conn, id)?;
     let mut conflictos: Vec<(String, f64, f64)> = Vec::new(); // (nombre, vendido, devolvible)
     for (pid, cant_comprada, nombre, cant_dev) in &detalles {
-        let (devolvible, vendido) = calcular_devolvible(&conn, *pid, *cant_comprada, *cant_dev);
+        let (devolvible, vendido) = calcular_devolvible(&conn, *pid, *cant_comprada, *cant_dev, est_id);
         if vendido > 0.001 {
             conflictos.push((nombre.clone(), vendido, devolvible));
         }
@@ -711,24 +731,16 @@ pub fn anular_compra(
         }
     }
 
+    // Los gastos repartidos (flete, aduana...) salen del costo antes de revertir
+    crate::commands::costos_proveedor::revertir_costos_compra(&conn, id)?;
+
     let motivo_str = motivo.unwrap_or_else(|| "Sin motivo".to_string());
     let mut items_con_negativo = 0i64;
     for (producto_id, cantidad, _nombre, _cant_dev) in detalles {
-        // Stock antes (para kardex)
-        let stock_antes: f64 = conn.query_row(
-            "SELECT stock_actual FROM productos WHERE id = ?1",
-            rusqlite::params![producto_id], |r| r.get(0),
-        ).unwrap_or(0.0);
-
-        conn.execute(
-            "UPDATE productos SET stock_actual = stock_actual - ?1,
-             updated_at = datetime('now','localtime') WHERE id = ?2",
-            rusqlite::params![cantidad, producto_id],
-        )
-        .map_err(|e| e.to_string())?;
-
-        let stock_nuevo = stock_antes - cantidad;
+        let mov = crate::commands::stock::mover(&conn, producto_id, est_id, -cantidad)?;
+        let stock_nuevo = mov.nuevo;
         if stock_nuevo < 0.0 { items_con_negativo += 1; }
+        crate::commands::costeo::retirar_capas_compra(&conn, id, producto_id, est_id, cantidad)?;
 
         // v2.5.30: kardex inverso. v2.5.42: marca si quedó stock negativo
         let motivo_kardex = if stock_nuevo < 0.0 {
@@ -737,12 +749,16 @@ pub fn anular_compra(
             format!("Anulacion compra {} - {}", numero, motivo_str)
         };
         let _ = conn.execute(
-            "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, motivo)
-             VALUES (?1, 'ANULACION_COMPRA', ?2, ?3, ?4, 0, ?5, ?6, ?7)",
-            rusqlite::params![producto_id, -cantidad, stock_antes, stock_nuevo, id, usuario, motivo_kardex],
+            "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, motivo, establecimiento_id)
+             VALUES (?1, 'ANULACION_COMPRA', ?2, ?3, ?4, 0, ?5, ?6, ?7, ?8)",
+            rusqlite::params![producto_id, -cantidad, mov.anterior, stock_nuevo, id, usuario, motivo_kardex, est_id],
         );
     }
 
+    // Los items recibidos por orden de compra no se revierten (la mercaderia
+    // sigue en bodega): vuelven a quedar pendientes de facturar en la orden.
+    crate::commands::ordenes_compra::revertir_facturado(&conn, id)?;
+
     // Marcar compra como anulada (guardar motivo en observacion)
     conn.execute(
         "UPDATE compras SET estado = 'ANULADA',
@@ -1216,6 +1232,10 @@ pub struct ImportarXmlInput {
     /// Clave de acceso SRI (49 dig) — clave única que evita doble importación
     #[serde(default)]
     pub clave_acceso: Option<String>,
+    /// Orden de compra que factura este XML: los productos de la orden ya
+    /// ingresaron al recibirla, asi que no mueven stock y se concilian.
+    #[serde(default)]
+    pub orden_compra_id: Option<i64>,
 }
 
 #[tauri::command]
@@ -1254,6 +1274,13 @@ pub fn importar_xml_compra(
 
     let tx = conn.transaction().map_err(|e| e.to_string())?;
 
+    // Productos pedidos en la orden de compra (3-way match)
+    let productos_orden: std::collections::HashSet<i64> = match input.orden_compra_id {
+        Some(oid) => crate::commands::ordenes_compra::validar_para_factura(&tx, oid, input.proveedor_id)?
+            .lineas.iter().map(|l| l.producto_id).collect(),
+        None => std::collections::HashSet::new(),
+    };
+
     // (producto_id, cantidad, precio_unitario, iva_porcentaje, subtotal, descripcion)
     let mut items_compra: Vec<(i64, f64, f64, f64, f64, String)> = Vec::new();
     let mut gastos_creados = 0_i64;
@@ -1360,6 +1387,8 @@ pub fn importar_xml_compra(
         }
     }
 
+    let mut diferencias: Vec<crate::models::DiferenciaConciliacion> = Vec::new();
+
     // Crear compra solo si hay items de producto
     let compra_id: Option<i64> = if !items_compra.is_empty() {
         // v2.5.30: usar el mismo helper (formato COMP-XXXXXXXXX)
@@ -1388,6 +1417,8 @@ pub fn importar_xml_compra(
             return Err("Debe seleccionar una cuenta bancaria para esta forma de pago".into());
         }
 
+        let est_id = crate::commands::stock::establecimiento_terminal(&tx)?;
+
         let observacion_xml = if input.autorizada {
             "Importado desde XML SRI autorizado"
         } else {
@@ -1396,9 +1427,10 @@ pub fn importar_xml_compra(
         tx.execute(
             "INSERT INTO compras (numero, proveedor_id, fecha, numero_factura, subtotal, iva, total, estado,
                                   forma_pago, es_credito, observacion, banco_id, referencia_pago,
-                                  tipo_documento, estado_sri, clave_acceso, fecha_emision, usuario) \
+                                  tipo_documento, estado_sri, clave_acceso, fecha_emision, usuario, establecimiento_id,
+                                  orden_compra_id) \
              VALUES (?1, ?2, COALESCE(?3, datetime('now','localtime')), ?4, ?5, ?6, ?7, 'REGISTRADA',
-                     ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+                     ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
             rusqlite::params![
                 numero_compra,
                 input.proveedor_id,
@@ -1417,6 +1449,8 @@ pub fn importar_xml_compra(
                 clave_acceso_norm,
                 fecha_iso,
                 usuario,
+                est_id,
+                input.orden_compra_id,
             ],
         )
         .map_err(|e| {
@@ -1430,13 +1464,27 @@ pub fn importar_xml_compra(
         let cid = tx.last_insert_rowid();
 
         // Insertar detalles y actualizar stock/costo
+        let mut items_orden: Vec<crate::commands::ordenes_compra::ItemFacturaOrden> = Vec::new();
         for (pid, cant, precio, _iva_p, sub, desc) in &items_compra {
+            let recibido_en_oc = productos_orden.contains(pid);
             tx.execute(
-                "INSERT INTO compra_detalles (compra_id, producto_id, descripcion, cantidad, precio_unitario, subtotal) \
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                rusqlite::params![cid, pid, desc, cant, precio, sub],
+                "INSERT INTO compra_detalles (compra_id, producto_id, descripcion, cantidad, precio_unitario, subtotal, recibido_en_oc) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![cid, pid, desc, cant, precio, sub, recibido_en_oc as i64],
             )
             .map_err(|e| e.to_string())?;
+            if input.orden_compra_id.is_some() {
+                items_orden.push(crate::commands::ordenes_compra::ItemFacturaOrden {
+                    producto_id: *pid,
+                    descripcion: desc.clone(),
+                    cantidad: *cant,
+                    precio_unitario: if *cant > 0.0 { sub / cant } else { *precio },
+                });
+            }
+            // Ya ingreso (stock + PMP) al recibir la orden de compra
+            if recibido_en_oc {
+                continue;
+            }
             // v2.5.22 PMP: recalcular costo_promedio antes del UPDATE (igual que registrar_compra)
             let (stock_actual_pmp, costo_prom_prev): (f64, f64) = tx
                 .query_row(
@@ -1453,21 +1501,32 @@ pub fn importar_xml_compra(
                     (stock_actual_pmp * costo_prom_prev + cant * precio) / total_after
                 }
             };
+            let mov = crate::commands::stock::mover(&tx, *pid, est_id, *cant)?;
             tx.execute(
-                "UPDATE productos SET stock_actual = stock_actual + ?1, precio_costo = ?2, costo_promedio = ?3, updated_at = datetime('now','localtime') WHERE id = ?4",
-                rusqlite::params![cant, precio, nuevo_pmp, pid],
+                "UPDATE productos SET precio_costo = ?1, costo_promedio = ?2 WHERE id = ?3",
+                rusqlite::params![precio, nuevo_pmp, pid],
             )
             .ok();
+            crate::commands::costeo::crear_capa(&tx, *pid, est_id, "COMPRA", Some(cid), *cant, *precio)?;
             // v2.5.30: registrar kardex INGRESO_COMPRA tambien para importacion XML
             let motivo_xml = format!("Compra {} - {} (XML SRI {})", numero_compra, desc,
                 if input.autorizada { "autorizada" } else { "sin autorizar" });
             let _ = tx.execute(
-                "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, motivo)
-                 VALUES (?1, 'INGRESO_COMPRA', ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-                rusqlite::params![pid, cant, stock_actual_pmp, stock_actual_pmp + cant, precio, cid, usuario, motivo_xml],
+                "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, motivo, establecimiento_id)
+                 VALUES (?1, 'INGRESO_COMPRA', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                rusqlite::params![pid, cant, mov.anterior, mov.nuevo, precio, cid, usuario, motivo_xml, est_id],
             );
         }
 
+        if let Some(oid) = input.orden_compra_id {
+            diferencias = crate::commands::ordenes_compra::conciliar_factura(&tx, oid, &items_orden)?;
+            tx.execute(
+                "UPDATE compras SET conciliacion_estado = ?1 WHERE id = ?2",
+                rusqlite::params![if diferencias.is_empty() { "OK" } else { "CON_DIFERENCIAS" }, cid],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+
         // Cuenta por pagar si crédito
         if es_credito {
             let dias = input.dias_credito.unwrap_or(30);
@@ -1489,7 +1548,8 @@ pub fn importar_xml_compra(
         "compra_id": compra_id,
         "productos_creados": productos_creados,
         "gastos_creados": gastos_creados,
-        "items_compra": items_compra.len()
+        "items_compra": items_compra.len(),
+        "diferencias_orden": diferencias
     }))
 }
 
@@ -1596,6 +1656,7 @@ pub fn registrar_devolucion_compra(
     if compra_estado == "ANULADA" {
         return Err("No se puede devolver una compra anulada".into());
     }
+    let est_id = establecimiento_compra(&conn, input.compra_id)?;
 
     // Cargar detalles con cantidad_devuelta acumulada para validacion
     let detalles_db: Vec<(i64, Option<i64>, Option<String>, f64, f64, f64, f64)> = {
@@ -1661,10 +1722,7 @@ pub fn registrar_devolucion_compra(
             let mut conflictos: Vec<String> = Vec::new();
             for (_, pid_opt, desc, cant, _) in &items_efectivos {
                 if let Some(pid) = pid_opt {
-                    let stock_actual: f64 = conn.query_row(
-                        "SELECT stock_actual FROM productos WHERE id = ?1",
-                        rusqlite::params![pid], |r| r.get(0),
-                    ).unwrap_or(0.0);
+                    let stock_actual = crate::commands::stock::stock_en(&conn, *pid, est_id);
                     if *cant > stock_actual + 0.0001 {
                         let disponible = stock_actual.max(0.0);
                         conflictos.push(format!(
@@ -1807,15 +1865,9 @@ pub fn registrar_devolucion_compra(
                 );
             } else {
                 // MERCANCIA (default): revertir stock + kardex normal
-                let stock_antes: f64 = tx.query_row(
-                    "SELECT stock_actual FROM productos WHERE id = ?1",
-                    rusqlite::params![pid], |r| r.get(0),
-                ).unwrap_or(0.0);
-                tx.execute(
-                    "UPDATE productos SET stock_actual = stock_actual - ?1, updated_at = datetime('now','localtime') WHERE id = ?2",
-                    rusqlite::params![cant, pid],
-                ).ok();
-                let stock_nuevo = stock_antes - cant;
+                let mov = crate::commands::stock::mover(&tx, *pid, est_id, -cant)?;
+                let stock_nuevo = mov.nuevo;
+                crate::commands::costeo::retirar_capas_compra(&tx, input.compra_id, *pid, est_id, cant)?;
                 let motivo_kardex = if stock_nuevo < 0.0 {
                     format!("Devolucion compra {} - {} ⚠ STOCK NEGATIVO (items ya vendidos)", numero_dev, desc.clone().unwrap_or_default())
                 } else {
@@ -1823,9 +1875,9 @@ pub fn registrar_devolucion_compra(
                         desc.clone().unwrap_or_default(), motivo_str)
                 };
                 let _ = tx.execute(
-                    "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, motivo)
-                     VALUES (?1, 'DEVOLUCION_COMPRA', ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-                    rusqlite::params![pid, -cant, stock_antes, stock_nuevo, precio, input.compra_id, usuario, motivo_kardex],
+                    "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, motivo, establecimiento_id)
+                     VALUES (?1, 'DEVOLUCION_COMPRA', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    rusqlite::params![pid, -cant, mov.anterior, stock_nuevo, precio, input.compra_id, usuario, motivo_kardex, est_id],
                 );
             }
         }
@@ -218,19 +218,49 @@ pub fn resetear_base_datos(
         // Notas de credito (deben ir antes de ventas)
         "nota_credito_detalles",
         "notas_credito",
+        // Lotes y capas de costo consumidos por linea (hijos de venta_detalles)
+        "venta_detalle_lotes",
+        "venta_detalle_costos",
+        "venta_insumos_modificador",
+        // Picking y ubicaciones de bodega (listas apuntan a ventas)
+        "lista_picking_items",
+        "lista_picking_documentos",
+        "listas_picking",
+        "stock_ubicacion",
+        "ubicaciones_bodega",
         // Detalles de venta y venta
         "venta_detalles",
         "ventas",
         // Lotes de caducidad (FK hacia compras)
         "lotes_caducidad",
+        "reglas_descuento_caducidad",
+        "capas_costo",
+        // Garantias / RMA de series
+        "rma_seguimiento",
+        "rma_lineas",
+        "rma_proveedor",
+        "serie_eventos",
+        // Costo en destino y listas de proveedores
+        "compra_costo_adicional_lineas",
+        "compra_costos_adicionales",
+        "precios_proveedor",
         // Detalles de compra y compra
         "compra_detalles",
         "compras",
+        // Ordenes de compra
+        "orden_compra_recepcion_items",
+        "orden_compra_recepciones",
+        "orden_compra_lineas",
+        "ordenes_compra",
         // Movimientos / kardex
         "movimientos_inventario",
         // Multi-almacen
         "transferencias_detalles",
         "transferencias",
+        "transferencia_recepciones",
+        "transferencia_series",
+        "transferencia_lineas",
+        "transferencias_documento",
         "stock_establecimiento",
         // Caja: eventos (FK hacia caja), retiros, gastos, caja
         "caja_eventos",
@@ -0,0 +1,526 @@
+// Conteos fisicos de inventario (toma de inventario)
+//
+// Flujo:
+//   1. crear_conteo_inventario: congela el stock esperado y el costo promedio
+//      de cada producto del alcance (todo o una categoria; global o de un
+//      establecimiento) en conteo_items.
+//   2. Los contadores registran capturas a ciegas (no ven el esperado) desde
+//      el POS, lectores de codigo de barras o la app movil (permiso
+//      `inventaria`). Las capturas de varios dispositivos se suman.
+//   3. solicitar_reconteo_conteo: los items contados fuera de tolerancia pasan a la
+//      ronda 2 y hay que contarlos de nuevo (la ronda 1 queda de historial).
+//   4. aprobar_conteo_inventario (permiso `aprobar_conteos`): en una sola
+//      transaccion ajusta el stock por la DIFERENCIA contado - esperado. El
+//      esperado de cada item es el congelado mas lo que el kardex movio entre
+//      la apertura y su ultima lectura (ventas, compras, transferencias hechas
+//      mientras se contaba), asi esos movimientos no se cuentan dos veces ni
+//      se pierden. Cada ajuste queda en el kardex como AJUSTE con
+//      referencia_id = conteo (lo toma tambien el reporte de varianza de
+//      insumos de recetas).
+//
+// Un conteo global (sin establecimiento) solo se permite mientras el stock
+// este en un solo establecimiento: con varios no hay a cual cargar la
+// diferencia.
+//
+// Las funciones sobre &Connection son pub para que las use app_movil::http.
+
+use crate::db::{usuario_con_permiso, Database, SesionState};
+use crate::models::{
+    CapturaConteo, ConteoInventario, ConteoItemCiego, NuevoConteo, ReporteVarianzaConteo, VarianzaConteo,
+};
+use rusqlite::{Connection, OptionalExtension};
+use tauri::State;
+
+fn round2(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+/// La diferencia supera la tolerancia (la mayor entre % del esperado y unidades).
+pub fn fuera_de_tolerancia(esperado: f64, contado: f64, tolerancia_pct: f64, tolerancia_unidades: f64) -> bool {
+    let permitido = (esperado.abs() * tolerancia_pct / 100.0).max(tolerancia_unidades);
+    (contado - esperado).abs() > permitido + 0.0001
+}
+
+/// Un conteo global ajusta el unico establecimiento con stock; con stock en
+/// varios hay que contar cada uno por separado.
+fn validar_alcance_global(conn: &Connection) -> Result<(), String> {
+    let con_stock: i64 = conn
+        .query_row(
+            "SELECT COUNT(DISTINCT establecimiento_id) FROM stock_establecimiento WHERE stock_actual != 0",
+            [],
+            |r| r.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if con_stock > 1 {
+        return Err("Hay stock en varios establecimientos: el conteo debe ser de un establecimiento".to_string());
+    }
+    Ok(())
+}
+
+const SELECT_CONTEO: &str = "
+    SELECT c.id, c.nombre, c.categoria_id, cat.nombre, c.establecimiento_id, e.nombre,
+           c.tolerancia_pct, c.tolerancia_unidades, c.estado, c.observacion,
+           c.usuario_creacion, c.usuario_aprobacion, c.fecha_creacion, c.fecha_cierre,
+           (SELECT COUNT(*) FROM conteo_items i WHERE i.conteo_id = c.id),
+           (SELECT COUNT(*) FROM conteo_items i WHERE i.conteo_id = c.id
+              AND EXISTS (SELECT 1 FROM conteo_capturas k WHERE k.conteo_id = i.conteo_id
+                            AND k.producto_id = i.producto_id AND k.ronda = i.ronda)),
+           (SELECT COUNT(*) FROM conteo_items i WHERE i.conteo_id = c.id AND i.requiere_reconteo = 1
+              AND NOT EXISTS (SELECT 1 FROM conteo_capturas k WHERE k.conteo_id = i.conteo_id
+                                AND k.producto_id = i.producto_id AND k.ronda = i.ronda))
+    FROM conteos_inventario c
+    LEFT JOIN categorias cat ON cat.id = c.categoria_id
+    LEFT JOIN establecimientos e ON e.id = c.establecimiento_id";
+
+fn fila_conteo(r: &rusqlite::Row) -> rusqlite::Result<ConteoInventario> {
+    Ok(ConteoInventario {
+        id: r.get(0)?,
+        nombre: r.get(1)?,
+        categoria_id: r.get(2)?,
+        categoria_nombre: r.get(3)?,
+        establecimiento_id: r.get(4)?,
+        establecimiento_nombre: r.get(5)?,
+        tolerancia_pct: r.get(6)?,
+        tolerancia_unidades: r.get(7)?,
+        estado: r.get(8)?,
+        observacion: r.get(9)?,
+        usuario_creacion: r.get(10)?,
+        usuario_aprobacion: r.get(11)?,
+        fecha_creacion: r.get(12)?,
+        fecha_cierre: r.get(13)?,
+        total_items: r.get(14)?,
+        items_contados: r.get(15)?,
+        reconteos_pendientes: r.get(16)?,
+    })
+}
+
+pub fn obtener(conn: &Connection, id: i64) -> Result<ConteoInventario, String> {
+    conn.query_row(&format!("{} WHERE c.id = ?1", SELECT_CONTEO), rusqlite::params![id], fila_conteo)
+        .map_err(|_| "Conteo no encontrado".to_string())
+}
+
+pub fn listar(conn: &Connection, solo_abiertos: bool) -> Result<Vec<ConteoInventario>, String> {
+    let sql = format!(
+        "{} WHERE (?1 = 0 OR c.estado = 'ABIERTO') ORDER BY c.id DESC LIMIT 200",
+        SELECT_CONTEO
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(rusqlite::params![solo_abiertos as i32], fila_conteo)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+fn conteo_abierto(conn: &Connection, id: i64) -> Result<ConteoInventario, String> {
+    let c = obtener(conn, id)?;
+    if c.estado != "ABIERTO" {
+        return Err(format!("El conteo ya está {}", c.estado));
+    }
+    Ok(c)
+}
+
+/// Abre la sesion y congela el stock esperado. Retorna el id.
+pub fn crear(conn: &Connection, nuevo: &NuevoConteo, usuario: &str) -> Result<i64, String> {
+    let nombre = nuevo.nombre.trim();
+    if nombre.is_empty() {
+        return Err("El nombre del conteo es obligatorio".to_string());
+    }
+    let tolerancia_pct = nuevo.tolerancia_pct.unwrap_or(0.0);
+    let tolerancia_unidades = nuevo.tolerancia_unidades.unwrap_or(0.0);
+    if tolerancia_pct < 0.0 || tolerancia_unidades < 0.0 {
+        return Err("La tolerancia no puede ser negativa".to_string());
+    }
+    match nuevo.establecimiento_id {
+        Some(eid) => {
+            conn.query_row("SELECT id FROM establecimientos WHERE id = ?1", rusqlite::params![eid], |r| r.get::<_, i64>(0))
+                .map_err(|_| "Establecimiento no encontrado".to_string())?;
+        }
+        None => validar_alcance_global(conn)?,
+    }
+    // Un producto no puede estar en dos conteos abiertos del mismo alcance de
+    // stock: la aprobacion del segundo duplicaria el ajuste.
+    let solapado: Option<String> = conn.query_row(
+        "SELECT c.nombre FROM conteos_inventario c
+         WHERE c.estado = 'ABIERTO'
+           AND COALESCE(c.establecimiento_id, 0) = COALESCE(?1, 0)
+           AND (c.categoria_id IS NULL OR ?2 IS NULL OR c.categoria_id = ?2)
+         LIMIT 1",
+        rusqlite::params![nuevo.establecimiento_id, nuevo.categoria_id],
+        |r| r.get(0),
+    ).optional().map_err(|e| e.to_string())?;
+    if let Some(otro) = solapado {
+        return Err(format!("Ya hay un conteo abierto que cubre estos productos: {}", otro));
+    }
+
+    conn.execute(
+        "INSERT INTO conteos_inventario (nombre, categoria_id, establecimiento_id, tolerancia_pct,
+                                         tolerancia_unidades, observacion, usuario_creacion, mov_inicial)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, (SELECT COALESCE(MAX(id), 0) FROM movimientos_inventario))",
+        rusqlite::params![
+            nombre, nuevo.categoria_id, nuevo.establecimiento_id, tolerancia_pct,
+            tolerancia_unidades, nuevo.observacion, usuario
+        ],
+    ).map_err(|e| e.to_string())?;
+    let id = conn.last_insert_rowid();
+
+    // Snapshot: productos fisicos activos que controlan stock.
+    let items = conn.execute(
+        "INSERT INTO conteo_items (conteo_id, producto_id, stock_esperado, costo_promedio)
+         SELECT ?1, p.id,
+                CASE WHEN ?2 IS NULL THEN p.stock_actual
+                     ELSE COALESCE((SELECT se.stock_actual FROM stock_establecimiento se
+                                    WHERE se.producto_id = p.id AND se.establecimiento_id = ?2), 0)
+                END,
+                CASE WHEN COALESCE(p.costo_promedio, 0) > 0 THEN p.costo_promedio ELSE p.precio_costo END
+         FROM productos p
+         WHERE p.activo = 1 AND p.es_servicio = 0 AND COALESCE(p.no_controla_stock, 0) = 0
+           AND COALESCE(p.tipo_producto, 'SIMPLE') = 'SIMPLE'
+           AND (?3 IS NULL OR p.categoria_id = ?3)",
+        rusqlite::params![id, nuevo.establecimiento_id, nuevo.categoria_id],
+    ).map_err(|e| e.to_string())?;
+    if items == 0 {
+        return Err("No hay productos con control de stock en el alcance elegido".to_string());
+    }
+    Ok(id)
+}
+
+fn item_ciego(conn: &Connection, conteo_id: i64, producto_id: i64) -> Result<ConteoItemCiego, String> {
+    items_ciegos_filtrados(conn, conteo_id, Some(producto_id), false)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| "El producto no forma parte de este conteo".to_string())
+}
+
+fn items_ciegos_filtrados(
+    conn: &Connection,
+    conteo_id: i64,
+    producto_id: Option<i64>,
+    solo_pendientes: bool,
+) -> Result<Vec<ConteoItemCiego>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.codigo, p.codigo_barras, p.nombre, p.unidad_medida, i.ronda, i.requiere_reconteo,
+                (SELECT SUM(k.cantidad) FROM conteo_capturas k
+                 WHERE k.conteo_id = i.conteo_id AND k.producto_id = i.producto_id AND k.ronda = i.ronda)
+         FROM conteo_items i
+         JOIN productos p ON p.id = i.producto_id
+         WHERE i.conteo_id = ?1 AND (?2 IS NULL OR i.producto_id = ?2)
+         ORDER BY p.nombre"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(rusqlite::params![conteo_id, producto_id], |r| {
+        Ok(ConteoItemCiego {
+            producto_id: r.get(0)?,
+            codigo: r.get(1)?,
+            codigo_barras: r.get(2)?,
+            nombre: r.get(3)?,
+            unidad_medida: r.get(4)?,
+            ronda: r.get(5)?,
+            requiere_reconteo: r.get::<_, i32>(6)? != 0,
+            contado: r.get(7)?,
+        })
+    }).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows.into_iter().filter(|i| !solo_pendientes || i.contado.is_none()).collect())
+}
+
+/// Lista ciega para los contadores. `solo_pendientes` deja lo que falta
+/// contar en la ronda vigente (incluye los reconteos).
+pub fn items_ciegos(conn: &Connection, conteo_id: i64, solo_pendientes: bool) -> Result<Vec<ConteoItemCiego>, String> {
+    obtener(conn, conteo_id)?;
+    items_ciegos_filtrados(conn, conteo_id, None, solo_pendientes)
+}
+
+/// Registra una lectura. Retorna la linea ciega actualizada (sin esperado).
+pub fn registrar_captura(
+    conn: &Connection,
+    conteo_id: i64,
+    captura: &CapturaConteo,
+    usuario: &str,
+) -> Result<ConteoItemCiego, String> {
+    conteo_abierto(conn, conteo_id)?;
+    if !captura.cantidad.is_finite() || captura.cantidad == 0.0 {
+        return Err("Cantidad inválida".to_string());
+    }
+    let producto_id = match (captura.producto_id, captura.codigo.as_deref().map(str::trim)) {
+        (Some(id), _) => id,
+        (None, Some(codigo)) if !codigo.is_empty() => conn
+            .query_row(
+                "SELECT id FROM productos WHERE codigo_barras = ?1 OR codigo = ?1
+                 ORDER BY CASE WHEN codigo_barras = ?1 THEN 0 ELSE 1 END LIMIT 1",
+                rusqlite::params![codigo],
+                |r| r.get(0),
+            )
+            .map_err(|_| format!("Código no encontrado: {}", codigo))?,
+        _ => return Err("Indique el producto o su código".to_string()),
+    };
+    let actual = item_ciego(conn, conteo_id, producto_id)?;
+    if actual.contado.unwrap_or(0.0) + captura.cantidad < -0.0001 {
+        return Err("La corrección deja el conteo en negativo".to_string());
+    }
+    conn.execute(
+        "INSERT INTO conteo_capturas (conteo_id, producto_id, ronda, cantidad, dispositivo, usuario, mov_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT COALESCE(MAX(id), 0) FROM movimientos_inventario))",
+        rusqlite::params![conteo_id, producto_id, actual.ronda, captura.cantidad, captura.dispositivo, usuario],
+    ).map_err(|e| e.to_string())?;
+    item_ciego(conn, conteo_id, producto_id)
+}
+
+/// Pasa a la siguiente ronda los items contados fuera de tolerancia que aun
+/// no se recontaron. Retorna cuantos quedaron para reconteo.
+pub fn marcar_reconteos(conn: &Connection, conteo_id: i64) -> Result<usize, String> {
+    let c = conteo_abierto(conn, conteo_id)?;
+    let reporte = reporte_varianza(conn, conteo_id)?;
+    let mut marcados = 0;
+    for it in reporte.items.iter().filter(|i| i.contado.is_some() && i.fuera_tolerancia && i.ronda == 1) {
+        conn.execute(
+            "UPDATE conteo_items SET ronda = ronda + 1, requiere_reconteo = 1
+             WHERE conteo_id = ?1 AND producto_id = ?2",
+            rusqlite::params![c.id, it.producto_id],
+        ).map_err(|e| e.to_string())?;
+        marcados += 1;
+    }
+    Ok(marcados)
+}
+
+/// Varianza por item. El esperado es el congelado mas lo que movio el kardex
+/// del alcance entre la apertura y la ultima lectura de la ronda vigente
+/// (hasta ahora si aun no se conto).
+pub fn reporte_varianza(conn: &Connection, conteo_id: i64) -> Result<ReporteVarianzaConteo, String> {
+    let conteo = obtener(conn, conteo_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.codigo, p.nombre, cat.nombre, p.unidad_medida,
+                i.stock_esperado + COALESCE((
+                    SELECT SUM(m.stock_nuevo - m.stock_anterior) FROM movimientos_inventario m
+                    WHERE m.producto_id = i.producto_id AND m.id > c.mov_inicial
+                      AND m.stock_nuevo IS NOT NULL AND m.stock_anterior IS NOT NULL
+                      AND (c.establecimiento_id IS NULL OR m.establecimiento_id = c.establecimiento_id)
+                      AND m.id <= COALESCE((SELECT MAX(k.mov_id) FROM conteo_capturas k
+                                            WHERE k.conteo_id = i.conteo_id AND k.producto_id = i.producto_id
+                                              AND k.ronda = i.ronda), m.id)), 0),
+                i.costo_promedio, i.ronda, i.requiere_reconteo,
+                (SELECT SUM(k.cantidad) FROM conteo_capturas k
+                 WHERE k.conteo_id = i.conteo_id AND k.producto_id = i.producto_id AND k.ronda = i.ronda)
+         FROM conteo_items i
+         JOIN conteos_inventario c ON c.id = i.conteo_id
+         JOIN productos p ON p.id = i.producto_id
+         LEFT JOIN categorias cat ON cat.id = p.categoria_id
+         WHERE i.conteo_id = ?1
+         ORDER BY p.nombre"
+    ).map_err(|e| e.to_string())?;
+    let items = stmt.query_map(rusqlite::params![conteo_id], |r| {
+        let esperado: f64 = r.get(5)?;
+        let costo: f64 = r.get(6)?;
+        let contado: Option<f64> = r.get(9)?;
+        let diferencia = contado.map(|c| c - esperado).unwrap_or(0.0);
+        Ok(VarianzaConteo {
+            producto_id: r.get(0)?,
+            codigo: r.get(1)?,
+            nombre: r.get(2)?,
+            categoria_nombre: r.get(3)?,
+            unidad_medida: r.get(4)?,
+            stock_esperado: esperado,
+            contado,
+            diferencia,
+            costo_promedio: costo,
+            valor_diferencia: round2(diferencia * costo),
+            fuera_tolerancia: contado
+                .map(|c| fuera_de_tolerancia(esperado, c, conteo.tolerancia_pct, conteo.tolerancia_unidades))
+                .unwrap_or(false),
+            ronda: r.get(7)?,
+            requiere_reconteo: r.get::<_, i32>(8)? != 0,
+        })
+    }).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut valor_faltante = 0.0;
+    let mut valor_sobrante = 0.0;
+    for it in &items {
+        if it.valor_diferencia < 0.0 {
+            valor_faltante += -it.valor_diferencia;
+        } else {
+            valor_sobrante += it.valor_diferencia;
+        }
+    }
+    Ok(ReporteVarianzaConteo {
+        items_con_diferencia: items.iter().filter(|i| i.diferencia.abs() > 0.0001).count() as i64,
+        items_fuera_tolerancia: items.iter().filter(|i| i.fuera_tolerancia).count() as i64,
+        valor_faltante: round2(valor_faltante),
+        valor_sobrante: round2(valor_sobrante),
+        valor_neto: round2(valor_sobrante - valor_faltante),
+        conteo,
+        items,
+    })
+}
+
+/// Aplica los ajustes del conteo y lo cierra. `no_contados_en_cero` ajusta a
+/// cero los items que nadie conto (si no, se dejan como estan).
+/// Retorna cuantos productos se ajustaron. Llamar dentro de una transaccion.
+pub fn aprobar(conn: &Connection, conteo_id: i64, usuario: &str, no_contados_en_cero: bool) -> Result<usize, String> {
+    let c = conteo_abierto(conn, conteo_id)?;
+    if c.reconteos_pendientes > 0 {
+        return Err(format!("Faltan {} reconteos por registrar", c.reconteos_pendientes));
+    }
+    let reporte = reporte_varianza(conn, conteo_id)?;
+    let motivo = format!("Conteo físico #{}: {}", c.id, c.nombre);
+    let est_id = match c.establecimiento_id {
+        Some(eid) => eid,
+        None => {
+            // Pudo entrar stock a otro establecimiento mientras se contaba
+            validar_alcance_global(conn)?;
+            let unico: Option<i64> = conn
+                .query_row(
+                    "SELECT establecimiento_id FROM stock_establecimiento WHERE stock_actual != 0 LIMIT 1",
+                    [],
+                    |r| r.get(0),
+                )
+                .optional()
+                .map_err(|e| e.to_string())?;
+            match unico {
+                Some(eid) => eid,
+                None => crate::commands::stock::establecimiento_terminal(conn)?,
+            }
+        }
+    };
+    let mut ajustados = 0;
+    for it in &reporte.items {
+        let diferencia = match it.contado {
+            Some(_) => it.diferencia,
+            None if no_contados_en_cero => -it.stock_esperado,
+            None => continue,
+        };
+        if diferencia.abs() < 0.0001 {
+            continue;
+        }
+        // Se aplica la diferencia sobre el stock de HOY: el esperado ya
+        // incluye lo movido hasta la lectura y lo movido despues sigue contando.
+        let mov = crate::commands::stock::mover(conn, it.producto_id, est_id, diferencia)?;
+        conn.execute(
+            "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo,
+                                                 costo_unitario, referencia_id, motivo, usuario, establecimiento_id)
+             VALUES (?1, 'AJUSTE', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                it.producto_id, diferencia, mov.anterior, mov.nuevo, it.costo_promedio,
+                c.id, motivo, usuario, est_id
+            ],
+        ).map_err(|e| format!("Error registrando movimiento: {}", e))?;
+        ajustados += 1;
+    }
+    conn.execute(
+        "UPDATE conteos_inventario SET estado = 'APROBADO', usuario_aprobacion = ?1,
+                fecha_cierre = datetime('now', 'localtime')
+         WHERE id = ?2",
+        rusqlite::params![usuario, c.id],
+    ).map_err(|e| e.to_string())?;
+    Ok(ajustados)
+}
+
+// ─── Comandos Tauri ──────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn crear_conteo_inventario(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    conteo: NuevoConteo,
+) -> Result<ConteoInventario, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let id = crear(&tx, &conteo, &usuario)?;
+    let creado = obtener(&tx, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(creado)
+}
+
+#[tauri::command]
+pub fn listar_conteos_inventario(
+    db: State<Database>,
+    solo_abiertos: Option<bool>,
+) -> Result<Vec<ConteoInventario>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    listar(&conn, solo_abiertos.unwrap_or(false))
+}
+
+/// Lista ciega para contar desde el POS (sin stock esperado).
+#[tauri::command]
+pub fn listar_items_conteo(
+    db: State<Database>,
+    conteo_id: i64,
+    solo_pendientes: Option<bool>,
+) -> Result<Vec<ConteoItemCiego>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    items_ciegos(&conn, conteo_id, solo_pendientes.unwrap_or(false))
+}
+
+#[tauri::command]
+pub fn registrar_captura_conteo(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    conteo_id: i64,
+    captura: CapturaConteo,
+) -> Result<ConteoItemCiego, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_inventario")
+        .or_else(|_| usuario_con_permiso(&sesion, "inventaria"))?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    registrar_captura(&conn, conteo_id, &captura, &usuario)
+}
+
+/// Manda a reconteo los items fuera de tolerancia. Retorna cuantos.
+#[tauri::command]
+pub fn solicitar_reconteo_conteo(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    conteo_id: i64,
+) -> Result<usize, String> {
+    usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    marcar_reconteos(&conn, conteo_id)
+}
+
+/// Esperado vs contado valorizado a costo promedio (solo supervisores: revela el esperado).
+#[tauri::command]
+pub fn reporte_varianza_conteo(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    conteo_id: i64,
+) -> Result<ReporteVarianzaConteo, String> {
+    usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    reporte_varianza(&conn, conteo_id)
+}
+
+/// Aprobación del supervisor: ajusta todo el stock del conteo de una vez.
+#[tauri::command]
+pub fn aprobar_conteo_inventario(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    conteo_id: i64,
+    no_contados_en_cero: Option<bool>,
+) -> Result<usize, String> {
+    let usuario = usuario_con_permiso(&sesion, "aprobar_conteos")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let ajustados = aprobar(&tx, conteo_id, &usuario, no_contados_en_cero.unwrap_or(false))?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(ajustados)
+}
+
+/// Cancela el conteo sin tocar el stock (las capturas quedan de historial).
+#[tauri::command]
+pub fn cancelar_conteo_inventario(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    conteo_id: i64,
+) -> Result<(), String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    conteo_abierto(&conn, conteo_id)?;
+    conn.execute(
+        "UPDATE conteos_inventario SET estado = 'CANCELADO', usuario_aprobacion = ?1,
+                fecha_cierre = datetime('now', 'localtime')
+         WHERE id = ?2",
+        rusqlite::params![usuario, conteo_id],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
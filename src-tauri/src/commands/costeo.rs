@@ -0,0 +1,445 @@
+// Costeo de inventario: capas FIFO, costo especifico de series y PMP
+//
+// Las capas de costo (capas_costo) se mantienen siempre, sea cual sea el
+// metodo elegido, para que los reportes puedan calcularse con cualquiera:
+//   - Compra (registrar_compra, XML, recepcion de orden): una capa por linea
+//     con su costo unitario. Anular/devolver la compra retira primero de sus
+//     propias capas.
+//   - Venta: consumir_fifo toma de las capas mas antiguas del establecimiento
+//     y guarda el consumo en venta_detalle_costos. Lo que no cubren las capas
+//     (stock inicial, ajustes, transferencias) sale a costo_promedio.
+//     venta_detalles guarda el costo de la linea con ambos metodos
+//     (costo_fifo, costo_pmp).
+//   - NC / devolucion / anulacion: reintegrar_capas devuelve a las mismas
+//     capas (ultimo consumo primero).
+//   - Salidas sin venta de una serie (RMA al proveedor, cambio por garantia):
+//     retirar_capas las toma FIFO; su reingreso abre una capa 'RMA'.
+//   - Series: al asignar una serie a la linea, esa unidad pasa a costar lo
+//     que costo la serie (identificacion especifica) en lugar de FIFO.
+//
+// Antes de consumir se recortan las capas mas antiguas que excedan el stock
+// del establecimiento: salidas que no consumen capas (ajustes, combos,
+// recetas) no dejan capas fantasma.
+//
+// config `metodo_costeo`: 'PROMEDIO' (default) o 'FIFO'. Decide el metodo por
+// defecto de reporte_utilidad y reporte_valuacion_inventario.
+
+use crate::db::Database;
+use crate::models::CapaCosto;
+use rusqlite::Connection;
+use tauri::State;
+
+pub const METODOS: [&str; 2] = ["PROMEDIO", "FIFO"];
+/// Metodos que aceptan los reportes: los configurables y ULTIMO (precio_costo).
+pub const METODOS_REPORTE: [&str; 3] = ["PROMEDIO", "FIFO", "ULTIMO"];
+
+/// Metodo de costeo configurado para el negocio.
+pub fn metodo_costeo(conn: &Connection) -> String {
+    conn.query_row("SELECT value FROM config WHERE key = 'metodo_costeo'", [], |r| r.get::<_, String>(0))
+        .ok()
+        .map(|v| v.trim().to_uppercase())
+        .filter(|v| METODOS.contains(&v.as_str()))
+        .unwrap_or_else(|| "PROMEDIO".to_string())
+}
+
+/// Metodo pedido por el reporte o, si no viene, el configurado.
+/// "PMP" se acepta como alias de PROMEDIO; cualquier otro valor fuera de
+/// METODOS_REPORTE es un error.
+pub fn resolver_metodo(conn: &Connection, metodo: Option<String>) -> Result<String, String> {
+    match metodo.map(|m| m.trim().to_uppercase()) {
+        Some(m) if m == "PMP" => Ok("PROMEDIO".to_string()),
+        Some(m) if METODOS_REPORTE.contains(&m.as_str()) => Ok(m),
+        Some(m) if !m.is_empty() => Err(format!("Método de costeo no soportado: {}", m)),
+        _ => Ok(metodo_costeo(conn)),
+    }
+}
+
+pub fn costo_promedio(conn: &Connection, producto_id: i64) -> f64 {
+    conn.query_row(
+        "SELECT CASE WHEN COALESCE(costo_promedio, 0) > 0 THEN costo_promedio ELSE COALESCE(precio_costo, 0) END
+         FROM productos WHERE id = ?1",
+        rusqlite::params![producto_id],
+        |r| r.get(0),
+    )
+    .unwrap_or(0.0)
+}
+
+/// Abre una capa de costo (entrada de mercaderia).
+pub fn crear_capa(
+    conn: &Connection,
+    producto_id: i64,
+    establecimiento_id: i64,
+    origen: &str,
+    referencia_id: Option<i64>,
+    cantidad: f64,
+    costo_unitario: f64,
+) -> Result<(), String> {
+    if cantidad <= 1e-9 {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO capas_costo (producto_id, establecimiento_id, origen, referencia_id, cantidad_inicial, cantidad_restante, costo_unitario)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?5, ?6)",
+        rusqlite::params![producto_id, establecimiento_id, origen, referencia_id, cantidad, costo_unitario],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Capas con saldo del producto en el establecimiento, mas antigua primero:
+/// (id, cantidad_restante, costo_unitario).
+fn capas_abiertas(conn: &Connection, producto_id: i64, establecimiento_id: i64) -> Result<Vec<(i64, f64, f64)>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, cantidad_restante, costo_unitario FROM capas_costo
+         WHERE producto_id = ?1 AND establecimiento_id = ?2 AND cantidad_restante > 1e-9
+         ORDER BY fecha ASC, id ASC",
+    ).map_err(|e| e.to_string())?;
+    let capas = stmt
+        .query_map(rusqlite::params![producto_id, establecimiento_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(capas)
+}
+
+/// Descuenta `cantidad` de las capas dadas en orden; devuelve lo tomado de
+/// cada una y lo que quedo sin cubrir.
+fn tomar_de_capas(conn: &Connection, capas: &[(i64, f64, f64)], cantidad: f64) -> Result<(Vec<(i64, f64, f64)>, f64), String> {
+    let mut tomado = Vec::new();
+    let mut restante = cantidad;
+    for &(id, saldo, costo) in capas {
+        if restante <= 1e-9 {
+            break;
+        }
+        let tomar = restante.min(saldo);
+        conn.execute(
+            "UPDATE capas_costo SET cantidad_restante = MAX(cantidad_restante - ?1, 0) WHERE id = ?2",
+            rusqlite::params![tomar, id],
+        ).map_err(|e| e.to_string())?;
+        tomado.push((id, tomar, costo));
+        restante -= tomar;
+    }
+    Ok((tomado, restante.max(0.0)))
+}
+
+/// Recorta las capas mas antiguas hasta que su saldo no exceda `stock`.
+fn recortar_exceso(conn: &Connection, producto_id: i64, establecimiento_id: i64, stock: f64) -> Result<(), String> {
+    let capas = capas_abiertas(conn, producto_id, establecimiento_id)?;
+    let en_capas: f64 = capas.iter().map(|c| c.1).sum();
+    let exceso = en_capas - stock.max(0.0);
+    if exceso > 1e-9 {
+        tomar_de_capas(conn, &capas, exceso)?;
+    }
+    Ok(())
+}
+
+/// Consume FIFO la cantidad vendida (unidad base) antes de mover el stock y
+/// registra el consumo de la linea. Lo no cubierto por capas va a PMP.
+/// Deja en venta_detalles el costo total de la linea con cada metodo.
+pub fn consumir_fifo(
+    conn: &Connection,
+    venta_id: i64,
+    venta_detalle_id: i64,
+    producto_id: i64,
+    establecimiento_id: i64,
+    cantidad: f64,
+) -> Result<(), String> {
+    if cantidad <= 1e-9 {
+        return Ok(());
+    }
+    let pmp = costo_promedio(conn, producto_id);
+    let stock = crate::commands::stock::stock_en(conn, producto_id, establecimiento_id);
+    recortar_exceso(conn, producto_id, establecimiento_id, stock)?;
+
+    let capas = capas_abiertas(conn, producto_id, establecimiento_id)?;
+    let (tomado, sin_capa) = tomar_de_capas(conn, &capas, cantidad)?;
+    let mut fifo = 0.0;
+    for (capa_id, cant, costo) in &tomado {
+        conn.execute(
+            "INSERT INTO venta_detalle_costos (venta_id, venta_detalle_id, producto_id, capa_id, cantidad, costo_unitario)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![venta_id, venta_detalle_id, producto_id, capa_id, cant, costo],
+        ).map_err(|e| e.to_string())?;
+        fifo += cant * costo;
+    }
+    if sin_capa > 1e-9 {
+        conn.execute(
+            "INSERT INTO venta_detalle_costos (venta_id, venta_detalle_id, producto_id, capa_id, cantidad, costo_unitario)
+             VALUES (?1, ?2, ?3, NULL, ?4, ?5)",
+            rusqlite::params![venta_id, venta_detalle_id, producto_id, sin_capa, pmp],
+        ).map_err(|e| e.to_string())?;
+        fifo += sin_capa * pmp;
+    }
+    conn.execute(
+        "UPDATE venta_detalles SET costo_fifo = ?1, costo_pmp = ?2 WHERE id = ?3",
+        rusqlite::params![fifo, cantidad * pmp, venta_detalle_id],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Devuelve `cantidad` (unidad base) de un producto de la venta a las capas
+/// de las que salio, ultimo consumo primero. Si la devolucion entra a otro
+/// establecimiento, o el consumo no tenia capa, abre una capa 'DEVOLUCION'
+/// con el mismo costo.
+pub fn reintegrar_capas(
+    conn: &Connection,
+    venta_id: i64,
+    producto_id: i64,
+    establecimiento_id: i64,
+    cantidad: f64,
+) -> Result<(), String> {
+    let mut stmt = conn.prepare(
+        "SELECT vc.id, vc.capa_id, c.establecimiento_id, vc.cantidad - vc.cantidad_devuelta, vc.costo_unitario
+         FROM venta_detalle_costos vc
+         LEFT JOIN capas_costo c ON c.id = vc.capa_id
+         WHERE vc.venta_id = ?1 AND vc.producto_id = ?2
+           AND vc.cantidad - vc.cantidad_devuelta > 1e-9
+         ORDER BY vc.id DESC",
+    ).map_err(|e| e.to_string())?;
+    #[allow(clippy::type_complexity)]
+    let consumos: Vec<(i64, Option<i64>, Option<i64>, f64, f64)> = stmt
+        .query_map(rusqlite::params![venta_id, producto_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut restante = cantidad;
+    for (id, capa_id, capa_est, pendiente, costo) in consumos {
+        if restante <= 1e-9 {
+            break;
+        }
+        let devolver = restante.min(pendiente);
+        match capa_id {
+            Some(cid) if capa_est == Some(establecimiento_id) => {
+                conn.execute(
+                    "UPDATE capas_costo SET cantidad_restante = cantidad_restante + ?1 WHERE id = ?2",
+                    rusqlite::params![devolver, cid],
+                ).map_err(|e| e.to_string())?;
+            }
+            _ => crear_capa(conn, producto_id, establecimiento_id, "DEVOLUCION", Some(venta_id), devolver, costo)?,
+        }
+        conn.execute(
+            "UPDATE venta_detalle_costos SET cantidad_devuelta = cantidad_devuelta + ?1 WHERE id = ?2",
+            rusqlite::params![devolver, id],
+        ).map_err(|e| e.to_string())?;
+        restante -= devolver;
+    }
+    Ok(())
+}
+
+/// Retira FIFO `cantidad` que sale sin venta (RMA al proveedor, cambio por
+/// garantia). Se llama antes de mover el stock, como consumir_fifo.
+pub fn retirar_capas(conn: &Connection, producto_id: i64, establecimiento_id: i64, cantidad: f64) -> Result<(), String> {
+    if cantidad <= 1e-9 {
+        return Ok(());
+    }
+    let stock = crate::commands::stock::stock_en(conn, producto_id, establecimiento_id);
+    recortar_exceso(conn, producto_id, establecimiento_id, stock)?;
+    let capas = capas_abiertas(conn, producto_id, establecimiento_id)?;
+    tomar_de_capas(conn, &capas, cantidad)?;
+    Ok(())
+}
+
+/// Retira `cantidad` que sale por anulacion/devolucion de una compra: primero
+/// de las capas de esa compra, luego FIFO.
+pub fn retirar_capas_compra(
+    conn: &Connection,
+    compra_id: i64,
+    producto_id: i64,
+    establecimiento_id: i64,
+    cantidad: f64,
+) -> Result<(), String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, cantidad_restante, costo_unitario FROM capas_costo
+         WHERE producto_id = ?1 AND establecimiento_id = ?2 AND origen = 'COMPRA' AND referencia_id = ?3
+           AND cantidad_restante > 1e-9
+         ORDER BY id DESC",
+    ).map_err(|e| e.to_string())?;
+    let propias: Vec<(i64, f64, f64)> = stmt
+        .query_map(rusqlite::params![producto_id, establecimiento_id, compra_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let (_, restante) = tomar_de_capas(conn, &propias, cantidad)?;
+    if restante > 1e-9 {
+        let capas = capas_abiertas(conn, producto_id, establecimiento_id)?;
+        tomar_de_capas(conn, &capas, restante)?;
+    }
+    Ok(())
+}
+
+/// Costo de la serie: el indicado, o el de la linea de su compra, o el PMP.
+pub fn costo_inicial_serie(conn: &Connection, producto_id: i64, compra_id: Option<i64>, costo: Option<f64>) -> f64 {
+    if let Some(c) = costo.filter(|c| *c > 0.0) {
+        return c;
+    }
+    compra_id
+        .and_then(|cid| {
+            conn.query_row(
+                "SELECT precio_unitario FROM compra_detalles WHERE compra_id = ?1 AND producto_id = ?2 ORDER BY id LIMIT 1",
+                rusqlite::params![cid, producto_id],
+                |r| r.get::<_, f64>(0),
+            )
+            .ok()
+        })
+        .unwrap_or_else(|| costo_promedio(conn, producto_id))
+}
+
+/// Recalcula costo_fifo de la linea: las unidades con serie asignada cuestan
+/// lo de su serie; el resto, el costo medio de las capas consumidas.
+pub fn recalcular_costo_serie(conn: &Connection, venta_detalle_id: i64) -> Result<(), String> {
+    let (cantidad_capas, costo_capas): (f64, f64) = conn
+        .query_row(
+            "SELECT COALESCE(SUM(cantidad), 0), COALESCE(SUM(cantidad * costo_unitario), 0)
+             FROM venta_detalle_costos WHERE venta_detalle_id = ?1",
+            rusqlite::params![venta_detalle_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    let (n_series, costo_series): (f64, f64) = conn
+        .query_row(
+            "SELECT COUNT(costo), COALESCE(SUM(costo), 0) FROM numeros_serie
+             WHERE venta_detalle_id = ?1 AND estado = 'VENDIDO'",
+            rusqlite::params![venta_detalle_id],
+            |r| Ok((r.get::<_, i64>(0)? as f64, r.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    if cantidad_capas <= 1e-9 {
+        return Ok(());
+    }
+    let medio = costo_capas / cantidad_capas;
+    let sin_serie = (cantidad_capas - n_series).max(0.0);
+    conn.execute(
+        "UPDATE venta_detalles SET costo_fifo = ?1 WHERE id = ?2",
+        rusqlite::params![sin_serie * medio + costo_series, venta_detalle_id],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Valor FIFO del stock: el inventario final son las capas mas recientes
+/// (`capas` de la mas antigua a la mas reciente: (saldo, costo)). Lo que
+/// exceda el saldo de las capas se valora a `costo_sin_capa`.
+pub fn valor_fifo(capas: &[(f64, f64)], stock: f64, costo_sin_capa: f64) -> f64 {
+    if stock <= 0.0 {
+        return 0.0;
+    }
+    let mut restante = stock;
+    let mut valor = 0.0;
+    for &(saldo, costo) in capas.iter().rev() {
+        if restante <= 1e-9 {
+            break;
+        }
+        let tomar = restante.min(saldo);
+        valor += tomar * costo;
+        restante -= tomar;
+    }
+    valor + restante.max(0.0) * costo_sin_capa
+}
+
+/// Valor del stock de un producto (todos los establecimientos) con FIFO e
+/// identificacion especifica para las series disponibles.
+pub fn valor_inventario_fifo(conn: &Connection, producto_id: i64) -> Result<f64, String> {
+    let pmp = costo_promedio(conn, producto_id);
+    let (series, costo_series): (f64, f64) = conn
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(CASE WHEN COALESCE(costo, 0) > 0 THEN costo ELSE ?2 END), 0)
+             FROM numeros_serie WHERE producto_id = ?1 AND estado = 'DISPONIBLE'",
+            rusqlite::params![producto_id, pmp],
+            |r| Ok((r.get::<_, i64>(0)? as f64, r.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    let stock: f64 = conn
+        .query_row("SELECT stock_actual FROM productos WHERE id = ?1", rusqlite::params![producto_id], |r| r.get(0))
+        .unwrap_or(0.0);
+    if series > 0.0 {
+        // Producto serializado: cada unidad disponible vale lo de su serie
+        return Ok(costo_series + (stock - series).max(0.0) * pmp);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT se.establecimiento_id, se.stock_actual FROM stock_establecimiento se
+         WHERE se.producto_id = ?1 AND se.stock_actual > 0",
+    ).map_err(|e| e.to_string())?;
+    let por_est: Vec<(i64, f64)> = stmt
+        .query_map(rusqlite::params![producto_id], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let mut valor = 0.0;
+    for (est_id, stock_est) in por_est {
+        let capas: Vec<(f64, f64)> = capas_abiertas(conn, producto_id, est_id)?
+            .into_iter()
+            .map(|(_, saldo, costo)| (saldo, costo))
+            .collect();
+        valor += valor_fifo(&capas, stock_est, pmp);
+    }
+    Ok(valor)
+}
+
+// ─── Comandos Tauri ──────────────────────────────────────────────────────
+
+/// Capas de costo con saldo de un producto (todas si `incluir_agotadas`).
+#[tauri::command]
+pub fn listar_capas_costo(
+    db: State<Database>,
+    producto_id: i64,
+    incluir_agotadas: Option<bool>,
+) -> Result<Vec<CapaCosto>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let filtro = if incluir_agotadas.unwrap_or(false) { "" } else { "AND c.cantidad_restante > 1e-9" };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT c.id, c.producto_id, c.establecimiento_id, e.nombre, c.origen, c.referencia_id,
+                c.fecha, c.cantidad_inicial, c.cantidad_restante, c.costo_unitario
+         FROM capas_costo c
+         LEFT JOIN establecimientos e ON e.id = c.establecimiento_id
+         WHERE c.producto_id = ?1 {}
+         ORDER BY c.fecha ASC, c.id ASC",
+        filtro
+    )).map_err(|e| e.to_string())?;
+    let capas = stmt
+        .query_map(rusqlite::params![producto_id], |r| {
+            Ok(CapaCosto {
+                id: r.get(0)?,
+                producto_id: r.get(1)?,
+                establecimiento_id: r.get(2)?,
+                establecimiento_nombre: r.get(3)?,
+                origen: r.get(4)?,
+                referencia_id: r.get(5)?,
+                fecha: r.get(6)?,
+                cantidad_inicial: r.get(7)?,
+                cantidad_restante: r.get(8)?,
+                costo_unitario: r.get(9)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(capas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valor_fifo_usa_capas_mas_recientes() {
+        // Capas: 10 @ 1.00 (antigua), 5 @ 2.00 (reciente)
+        let capas = [(10.0, 1.0), (5.0, 2.0)];
+        assert_eq!(valor_fifo(&capas, 5.0, 9.0), 10.0);
+        assert_eq!(valor_fifo(&capas, 8.0, 9.0), 13.0);
+        // Stock sin capa se valora al costo de respaldo
+        assert_eq!(valor_fifo(&capas, 17.0, 3.0), 26.0);
+        assert_eq!(valor_fifo(&capas, 0.0, 3.0), 0.0);
+    }
+
+    #[test]
+    fn resolver_metodo_rechaza_desconocidos() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE config (key TEXT PRIMARY KEY, value TEXT); INSERT INTO config VALUES ('metodo_costeo', 'fifo');")
+            .unwrap();
+        assert_eq!(resolver_metodo(&conn, None).unwrap(), "FIFO");
+        assert_eq!(resolver_metodo(&conn, Some(" pmp ".to_string())).unwrap(), "PROMEDIO");
+        assert_eq!(resolver_metodo(&conn, Some("ultimo".to_string())).unwrap(), "ULTIMO");
+        assert!(resolver_metodo(&conn, Some("LIFO".to_string())).is_err());
+    }
+}
@@ -0,0 +1,861 @@
+// Listas de costo por proveedor y costo en destino de las compras
+//
+// Listas (precios_proveedor): cada proveedor tiene su costo por producto con
+// vigencia (vigente_desde / vigente_hasta) y, opcionalmente, por volumen
+// (cantidad_minima). Una lista nueva sin fecha de fin cierra la anterior del
+// mismo producto y volumen el dia previo; una con fecha de fin (promocion) se
+// superpone y gana mientras esta vigente. Se usan para:
+//   - comparar_costos_proveedor: lista vigente vs ultima compra por proveedor;
+//   - preferir_proveedor_mas_barato: fija productos.proveedor_preferido_id;
+//   - reposicion: sin preferido se sugiere el mas barato de las listas, y la
+//     orden de compra se arma al costo de lista del proveedor.
+//
+// Costo en destino: flete, aduana, manipuleo... de una compra se reparten
+// entre sus lineas por VALOR (subtotal), PESO (cantidad * productos.peso_kg) o
+// CANTIDAD. El monto de cada linea:
+//   - se suma a compra_detalles.costo_adicional;
+//   - sube las capas FIFO y el costo de las series de esa compra;
+//   - entra a costo_promedio solo por las unidades que siguen en stock
+//     (monto_inventario); lo ya vendido queda en el costo de esas ventas. Al
+//     quitar el gasto se resta el mismo aumento por unidad (delta_promedio);
+//   - queda en el kardex como COSTO_ADICIONAL (cantidad 0).
+// El precio de venta sugerido sale del costo unitario en destino y el margen
+// objetivo (productos.margen_objetivo o config `margen_objetivo`, % sobre el
+// precio de venta).
+
+use crate::db::{usuario_con_permiso, Database, SesionState};
+use crate::models::{
+    ComparacionProveedor, CostoAdicionalCompra, DistribucionCostosCompra, ItemListaProveedor, LineaCostoDestino,
+    NuevoCostoAdicional, NuevoPrecioProveedor, PrecioProveedor, PrecioVentaNuevo, ResultadoImportacionLista,
+};
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::HashMap;
+use tauri::State;
+
+pub const TIPOS_COSTO: [&str; 5] = ["FLETE", "ADUANA", "MANIPULEO", "SEGURO", "OTRO"];
+pub const METODOS_REPARTO: [&str; 3] = ["VALOR", "PESO", "CANTIDAD"];
+
+const VIGENTE: &str = "vigente_desde <= date('now','localtime')
+     AND (vigente_hasta IS NULL OR vigente_hasta >= date('now','localtime'))";
+
+fn round2(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+/// Reparte `monto` proporcional a `bases`, en centavos. El residuo del
+/// redondeo va a la linea de mayor base.
+pub fn repartir(bases: &[f64], monto: f64) -> Vec<f64> {
+    let total: f64 = bases.iter().sum();
+    if total <= 0.0 {
+        return vec![0.0; bases.len()];
+    }
+    let mut partes: Vec<f64> = bases.iter().map(|b| round2(monto * b / total)).collect();
+    let diferencia = round2(monto - partes.iter().sum::<f64>());
+    if diferencia != 0.0 {
+        if let Some(i) = (0..bases.len()).max_by(|a, b| bases[*a].partial_cmp(&bases[*b]).unwrap_or(std::cmp::Ordering::Equal)) {
+            partes[i] = round2(partes[i] + diferencia);
+        }
+    }
+    partes
+}
+
+/// Precio de venta para ganar `margen` % sobre el precio (no sobre el costo).
+pub fn precio_sugerido(costo: f64, margen: f64) -> f64 {
+    let m = margen.clamp(0.0, 95.0) / 100.0;
+    round2(costo / (1.0 - m))
+}
+
+fn margen_defecto(conn: &Connection) -> f64 {
+    conn.query_row("SELECT value FROM config WHERE key = 'margen_objetivo'", [], |r| r.get::<_, String>(0))
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .unwrap_or(30.0)
+}
+
+// ─── Listas de costo ─────────────────────────────────────────────────────
+
+/// Costo vigente (y su fecha de fin) de la lista del proveedor para esa
+/// cantidad: el de mayor volumen que aplique, la vigencia mas reciente.
+fn lista_vigente(conn: &Connection, proveedor_id: i64, producto_id: i64, cantidad: f64) -> Option<(f64, Option<String>)> {
+    conn.query_row(
+        &format!(
+            "SELECT costo, vigente_hasta FROM precios_proveedor
+             WHERE proveedor_id = ?1 AND producto_id = ?2 AND cantidad_minima <= ?3 AND {}
+             ORDER BY cantidad_minima DESC, vigente_desde DESC, id DESC LIMIT 1",
+            VIGENTE
+        ),
+        rusqlite::params![proveedor_id, producto_id, cantidad.max(1.0)],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )
+    .ok()
+}
+
+pub fn costo_vigente(conn: &Connection, proveedor_id: i64, producto_id: i64, cantidad: f64) -> Option<f64> {
+    lista_vigente(conn, proveedor_id, producto_id, cantidad).map(|(c, _)| c)
+}
+
+/// Proveedor con el costo de lista vigente mas bajo (precio base, sin volumen).
+pub fn proveedor_mas_barato(conn: &Connection, producto_id: i64) -> Option<(i64, f64)> {
+    conn.query_row(
+        &format!(
+            "SELECT proveedor_id, MIN(costo) FROM precios_proveedor
+             WHERE producto_id = ?1 AND cantidad_minima <= 1 AND {}
+             GROUP BY proveedor_id ORDER BY 2, proveedor_id LIMIT 1",
+            VIGENTE
+        ),
+        rusqlite::params![producto_id],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )
+    .ok()
+}
+
+fn productos_con_lista(conn: &Connection) -> Result<Vec<i64>, String> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT DISTINCT producto_id FROM precios_proveedor WHERE {}", VIGENTE))
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([], |r| r.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<i64>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(ids)
+}
+
+fn validar_fecha(fecha: &str) -> Result<(), String> {
+    chrono::NaiveDate::parse_from_str(fecha, "%Y-%m-%d")
+        .map(|_| ())
+        .map_err(|_| format!("Fecha inválida '{}' (formato AAAA-MM-DD)", fecha))
+}
+
+/// Crea o modifica un costo de lista. Retorna el id.
+pub fn guardar(conn: &Connection, precio: &NuevoPrecioProveedor, usuario: &str) -> Result<i64, String> {
+    if precio.costo < 0.0 {
+        return Err("El costo no puede ser negativo".to_string());
+    }
+    let cantidad_minima = precio.cantidad_minima.unwrap_or(1.0).max(1.0);
+    let hoy = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let desde = precio.vigente_desde.clone().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).unwrap_or(hoy);
+    let hasta = precio.vigente_hasta.clone().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    validar_fecha(&desde)?;
+    if let Some(h) = &hasta {
+        validar_fecha(h)?;
+        if *h < desde {
+            return Err("La vigencia termina antes de empezar".to_string());
+        }
+    }
+    let codigo = precio.codigo_proveedor.clone().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+
+    let id = match precio.id {
+        Some(id) => {
+            conn.execute(
+                "UPDATE precios_proveedor SET proveedor_id = ?1, producto_id = ?2, codigo_proveedor = ?3, costo = ?4,
+                        cantidad_minima = ?5, vigente_desde = ?6, vigente_hasta = ?7, observacion = ?8, usuario = ?9
+                 WHERE id = ?10",
+                rusqlite::params![
+                    precio.proveedor_id, precio.producto_id, codigo, precio.costo, cantidad_minima, desde, hasta,
+                    precio.observacion, usuario, id
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            id
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO precios_proveedor (proveedor_id, producto_id, codigo_proveedor, costo, cantidad_minima,
+                        vigente_desde, vigente_hasta, observacion, usuario)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                rusqlite::params![
+                    precio.proveedor_id, precio.producto_id, codigo, precio.costo, cantidad_minima, desde, hasta,
+                    precio.observacion, usuario
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            conn.last_insert_rowid()
+        }
+    };
+
+    // Una lista sin fin reemplaza a la anterior abierta del mismo volumen
+    if hasta.is_none() {
+        conn.execute(
+            "UPDATE precios_proveedor SET vigente_hasta = date(?1, '-1 day')
+             WHERE proveedor_id = ?2 AND producto_id = ?3 AND cantidad_minima = ?4
+               AND vigente_hasta IS NULL AND vigente_desde < ?1 AND id != ?5",
+            rusqlite::params![desde, precio.proveedor_id, precio.producto_id, cantidad_minima, id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(id)
+}
+
+fn listar(
+    conn: &Connection,
+    proveedor_id: Option<i64>,
+    producto_id: Option<i64>,
+    solo_vigentes: bool,
+) -> Result<Vec<PrecioProveedor>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT pp.id, pp.proveedor_id, pr.nombre, pp.producto_id, p.nombre, pp.codigo_proveedor, pp.costo,
+                    pp.cantidad_minima, pp.vigente_desde, pp.vigente_hasta, ({0}) AS vigente, pp.observacion
+             FROM precios_proveedor pp
+             LEFT JOIN proveedores pr ON pr.id = pp.proveedor_id
+             LEFT JOIN productos p ON p.id = pp.producto_id
+             WHERE (?1 IS NULL OR pp.proveedor_id = ?1) AND (?2 IS NULL OR pp.producto_id = ?2)
+               AND (?3 = 0 OR ({0}))
+             ORDER BY p.nombre, pr.nombre, pp.cantidad_minima, pp.vigente_desde DESC",
+            VIGENTE.replace("vigente_", "pp.vigente_")
+        ))
+        .map_err(|e| e.to_string())?;
+    let filas = stmt
+        .query_map(rusqlite::params![proveedor_id, producto_id, solo_vigentes as i64], |r| {
+            Ok(PrecioProveedor {
+                id: r.get(0)?,
+                proveedor_id: r.get(1)?,
+                proveedor_nombre: r.get(2)?,
+                producto_id: r.get(3)?,
+                producto_nombre: r.get(4)?,
+                codigo_proveedor: r.get(5)?,
+                costo: r.get(6)?,
+                cantidad_minima: r.get(7)?,
+                vigente_desde: r.get(8)?,
+                vigente_hasta: r.get(9)?,
+                vigente: r.get::<_, i64>(10)? != 0,
+                observacion: r.get(11)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(filas)
+}
+
+/// Proveedores que tienen lista o compras del producto, del mas barato al mas
+/// caro (costo de lista vigente, o la ultima compra si no tiene lista).
+pub fn comparar(conn: &Connection, producto_id: i64, cantidad: f64) -> Result<Vec<ComparacionProveedor>, String> {
+    let preferido: Option<i64> = conn
+        .query_row(
+            "SELECT proveedor_preferido_id FROM productos WHERE id = ?1",
+            rusqlite::params![producto_id],
+            |r| r.get(0),
+        )
+        .map_err(|_| format!("Producto {} no encontrado", producto_id))?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT pr.id, pr.nombre, pr.dias_entrega FROM proveedores pr
+             WHERE pr.id IN (SELECT proveedor_id FROM precios_proveedor WHERE producto_id = ?1
+                             UNION
+                             SELECT c.proveedor_id FROM compra_detalles cd JOIN compras c ON c.id = cd.compra_id
+                             WHERE cd.producto_id = ?1 AND c.estado != 'ANULADA')",
+        )
+        .map_err(|e| e.to_string())?;
+    let proveedores: Vec<(i64, String, i64)> = stmt
+        .query_map(rusqlite::params![producto_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut resultado = Vec::new();
+    for (proveedor_id, proveedor_nombre, dias_entrega) in proveedores {
+        let lista = lista_vigente(conn, proveedor_id, producto_id, cantidad);
+        let ultima: Option<(f64, String)> = conn
+            .query_row(
+                "SELECT cd.precio_unitario, c.fecha FROM compra_detalles cd JOIN compras c ON c.id = cd.compra_id
+                 WHERE cd.producto_id = ?1 AND c.proveedor_id = ?2 AND c.estado != 'ANULADA'
+                 ORDER BY c.id DESC LIMIT 1",
+                rusqlite::params![producto_id, proveedor_id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        resultado.push(ComparacionProveedor {
+            proveedor_id,
+            proveedor_nombre,
+            dias_entrega,
+            costo_lista: lista.as_ref().map(|(c, _)| *c),
+            vigente_hasta: lista.and_then(|(_, h)| h),
+            ultimo_costo_compra: ultima.as_ref().map(|(c, _)| *c),
+            fecha_ultima_compra: ultima.map(|(_, f)| f),
+            es_preferido: preferido == Some(proveedor_id),
+            mas_barato: false,
+        });
+    }
+    let efectivo = |c: &ComparacionProveedor| c.costo_lista.or(c.ultimo_costo_compra).unwrap_or(f64::MAX);
+    resultado.sort_by(|a, b| efectivo(a).partial_cmp(&efectivo(b)).unwrap_or(std::cmp::Ordering::Equal));
+    if let Some(primero) = resultado.first_mut() {
+        primero.mas_barato = efectivo(primero) < f64::MAX;
+    }
+    Ok(resultado)
+}
+
+// ─── Costo en destino ────────────────────────────────────────────────────
+
+struct LineaReparto {
+    detalle_id: i64,
+    producto_id: i64,
+    cantidad: f64,
+    precio_unitario: f64,
+    cantidad_devuelta: f64,
+    base: f64,
+}
+
+fn compra_activa(conn: &Connection, compra_id: i64) -> Result<(String, i64), String> {
+    let (numero, estado, est): (String, String, Option<i64>) = conn
+        .query_row(
+            "SELECT numero, estado, establecimiento_id FROM compras WHERE id = ?1",
+            rusqlite::params![compra_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .map_err(|_| "Compra no encontrada".to_string())?;
+    if estado == "ANULADA" {
+        return Err(format!("La compra {} está anulada", numero));
+    }
+    let est_id = match est {
+        Some(id) => id,
+        None => crate::commands::stock::establecimiento_terminal(conn)?,
+    };
+    Ok((numero, est_id))
+}
+
+/// Cantidad total de cada producto en la compra (para repartir por unidad en
+/// capas y series, que no distinguen la linea).
+fn cantidades_compra(conn: &Connection, compra_id: i64) -> Result<HashMap<i64, f64>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT producto_id, SUM(cantidad) FROM compra_detalles
+             WHERE compra_id = ?1 AND producto_id IS NOT NULL GROUP BY producto_id",
+        )
+        .map_err(|e| e.to_string())?;
+    let filas = stmt
+        .query_map(rusqlite::params![compra_id], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashMap<i64, f64>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(filas)
+}
+
+/// Ajusta el costo de las unidades de la compra en `por_unidad` (negativo al
+/// revertir): capas FIFO, series y, si es la ultima compra del producto, su
+/// precio_costo.
+fn ajustar_costo_unidades(conn: &Connection, compra_id: i64, producto_id: i64, por_unidad: f64) -> Result<(), String> {
+    conn.execute(
+        "UPDATE capas_costo SET costo_unitario = MAX(costo_unitario + ?1, 0)
+         WHERE origen = 'COMPRA' AND referencia_id = ?2 AND producto_id = ?3",
+        rusqlite::params![por_unidad, compra_id, producto_id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE numeros_serie SET costo = MAX(costo + ?1, 0)
+         WHERE compra_id = ?2 AND producto_id = ?3 AND costo IS NOT NULL",
+        rusqlite::params![por_unidad, compra_id, producto_id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE productos SET precio_costo = (
+             SELECT cd.precio_unitario + COALESCE(cd.costo_adicional, 0) / cd.cantidad
+             FROM compra_detalles cd WHERE cd.compra_id = ?1 AND cd.producto_id = ?2 AND cd.cantidad > 0
+             ORDER BY cd.id DESC LIMIT 1)
+         WHERE id = ?2
+           AND ?1 = (SELECT MAX(c.id) FROM compra_detalles cd JOIN compras c ON c.id = cd.compra_id
+                     WHERE cd.producto_id = ?2 AND c.estado != 'ANULADA')",
+        rusqlite::params![compra_id, producto_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Reparte un gasto entre las lineas de la compra y lo lleva al costo.
+pub fn aplicar_costo(
+    conn: &Connection,
+    compra_id: i64,
+    costo: &NuevoCostoAdicional,
+    usuario: &str,
+) -> Result<i64, String> {
+    let tipo = costo.tipo.trim().to_uppercase();
+    let metodo = costo.metodo.trim().to_uppercase();
+    if !TIPOS_COSTO.contains(&tipo.as_str()) {
+        return Err(format!("Tipo de gasto inválido '{}' (FLETE, ADUANA, MANIPULEO, SEGURO u OTRO)", costo.tipo));
+    }
+    if !METODOS_REPARTO.contains(&metodo.as_str()) {
+        return Err(format!("Método de reparto inválido '{}' (VALOR, PESO o CANTIDAD)", costo.metodo));
+    }
+    if costo.monto <= 0.0 {
+        return Err("El monto del gasto debe ser mayor a 0".to_string());
+    }
+    let (numero, est_id) = compra_activa(conn, compra_id)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT cd.id, cd.producto_id, cd.cantidad, cd.precio_unitario, cd.subtotal,
+                    COALESCE(cd.cantidad_devuelta, 0), COALESCE(p.peso_kg, 0)
+             FROM compra_detalles cd JOIN productos p ON p.id = cd.producto_id
+             WHERE cd.compra_id = ?1 AND cd.cantidad > 0
+             ORDER BY cd.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let lineas: Vec<LineaReparto> = stmt
+        .query_map(rusqlite::params![compra_id], |r| {
+            let cantidad: f64 = r.get(2)?;
+            let subtotal: f64 = r.get(4)?;
+            let peso: f64 = r.get(6)?;
+            Ok(LineaReparto {
+                detalle_id: r.get(0)?,
+                producto_id: r.get(1)?,
+                cantidad,
+                precio_unitario: r.get(3)?,
+                cantidad_devuelta: r.get(5)?,
+                base: match metodo.as_str() {
+                    "VALOR" => subtotal,
+                    "PESO" => cantidad * peso,
+                    _ => cantidad,
+                },
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    if lineas.is_empty() {
+        return Err("La compra no tiene productos a los que asignar el gasto".to_string());
+    }
+    let bases: Vec<f64> = lineas.iter().map(|l| l.base).collect();
+    if bases.iter().sum::<f64>() <= 0.0 {
+        return Err(match metodo.as_str() {
+            "PESO" => "Los productos de la compra no tienen peso registrado".to_string(),
+            _ => "Las líneas de la compra no tienen valor para repartir el gasto".to_string(),
+        });
+    }
+    let montos = repartir(&bases, costo.monto);
+
+    conn.execute(
+        "INSERT INTO compra_costos_adicionales (compra_id, tipo, descripcion, monto, metodo, usuario)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![compra_id, tipo, costo.descripcion, costo.monto, metodo, usuario],
+    )
+    .map_err(|e| e.to_string())?;
+    let costo_id = conn.last_insert_rowid();
+    let cantidades = cantidades_compra(conn, compra_id)?;
+
+    for (linea, monto) in lineas.iter().zip(montos) {
+        if monto <= 0.0 {
+            continue;
+        }
+        let pid = linea.producto_id;
+        let stock: f64 = conn
+            .query_row("SELECT stock_actual FROM productos WHERE id = ?1", rusqlite::params![pid], |r| r.get(0))
+            .map_err(|e| e.to_string())?;
+        // Solo lo que sigue en bodega se capitaliza en el promedio
+        let en_stock = stock.min(linea.cantidad - linea.cantidad_devuelta).max(0.0);
+        let monto_inventario = round2(monto / linea.cantidad * en_stock);
+        let mut delta_promedio = 0.0;
+        if stock > 0.0 && monto_inventario > 0.0 {
+            delta_promedio = monto_inventario / stock;
+            conn.execute(
+                "UPDATE productos SET costo_promedio = ?1 WHERE id = ?2",
+                rusqlite::params![crate::commands::costeo::costo_promedio(conn, pid) + delta_promedio, pid],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        conn.execute(
+            "UPDATE compra_detalles SET costo_adicional = COALESCE(costo_adicional, 0) + ?1 WHERE id = ?2",
+            rusqlite::params![monto, linea.detalle_id],
+        )
+        .map_err(|e| e.to_string())?;
+        let total_producto = cantidades.get(&pid).copied().unwrap_or(linea.cantidad);
+        ajustar_costo_unidades(conn, compra_id, pid, monto / total_producto)?;
+        conn.execute(
+            "INSERT INTO compra_costo_adicional_lineas (costo_adicional_id, compra_detalle_id, producto_id, base, monto,
+                                                        monto_inventario, delta_promedio)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![costo_id, linea.detalle_id, pid, linea.base, monto, monto_inventario, delta_promedio],
+        )
+        .map_err(|e| e.to_string())?;
+
+        let stock_est = crate::commands::stock::stock_en(conn, pid, est_id);
+        conn.execute(
+            "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, motivo, establecimiento_id)
+             VALUES (?1, 'COSTO_ADICIONAL', 0, ?2, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                pid, stock_est, linea.precio_unitario + monto / linea.cantidad, compra_id, usuario,
+                format!("Compra {} - {} ${:.2} por {}", numero, tipo, monto, metodo), est_id
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(costo_id)
+}
+
+/// Quita un gasto ya repartido, deshaciendo su efecto en el costo.
+pub fn revertir_costo(conn: &Connection, costo_id: i64) -> Result<(), String> {
+    let (compra_id, anulado): (i64, i64) = conn
+        .query_row(
+            "SELECT compra_id, anulado FROM compra_costos_adicionales WHERE id = ?1",
+            rusqlite::params![costo_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .map_err(|_| "Gasto de compra no encontrado".to_string())?;
+    if anulado != 0 {
+        return Ok(());
+    }
+    let cantidades = cantidades_compra(conn, compra_id)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT compra_detalle_id, producto_id, monto, delta_promedio
+             FROM compra_costo_adicional_lineas WHERE costo_adicional_id = ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let lineas: Vec<(i64, i64, f64, f64)> = stmt
+        .query_map(rusqlite::params![costo_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for (detalle_id, pid, monto, delta_promedio) in lineas {
+        // Se resta lo mismo que se sumo por unidad: el stock pudo cambiar
+        if delta_promedio > 0.0 {
+            conn.execute(
+                "UPDATE productos SET costo_promedio = ?1 WHERE id = ?2",
+                rusqlite::params![(crate::commands::costeo::costo_promedio(conn, pid) - delta_promedio).max(0.0), pid],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        conn.execute(
+            "UPDATE compra_detalles SET costo_adicional = MAX(COALESCE(costo_adicional, 0) - ?1, 0) WHERE id = ?2",
+            rusqlite::params![monto, detalle_id],
+        )
+        .map_err(|e| e.to_string())?;
+        if let Some(total) = cantidades.get(&pid).filter(|t| **t > 0.0) {
+            ajustar_costo_unidades(conn, compra_id, pid, -monto / total)?;
+        }
+    }
+    conn.execute(
+        "UPDATE compra_costos_adicionales SET anulado = 1 WHERE id = ?1",
+        rusqlite::params![costo_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Revierte todos los gastos de la compra (al anularla).
+pub fn revertir_costos_compra(conn: &Connection, compra_id: i64) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("SELECT id FROM compra_costos_adicionales WHERE compra_id = ?1 AND anulado = 0")
+        .map_err(|e| e.to_string())?;
+    let ids: Vec<i64> = stmt
+        .query_map(rusqlite::params![compra_id], |r| r.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for id in ids {
+        revertir_costo(conn, id)?;
+    }
+    Ok(())
+}
+
+/// Gastos de la compra y costo en destino de cada linea.
+pub fn distribucion(conn: &Connection, compra_id: i64) -> Result<DistribucionCostosCompra, String> {
+    let compra_numero: String = conn
+        .query_row("SELECT numero FROM compras WHERE id = ?1", rusqlite::params![compra_id], |r| r.get(0))
+        .map_err(|_| "Compra no encontrada".to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, tipo, descripcion, monto, metodo, usuario, fecha FROM compra_costos_adicionales
+             WHERE compra_id = ?1 AND anulado = 0 ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let costos: Vec<CostoAdicionalCompra> = stmt
+        .query_map(rusqlite::params![compra_id], |r| {
+            Ok(CostoAdicionalCompra {
+                id: r.get(0)?,
+                tipo: r.get(1)?,
+                descripcion: r.get(2)?,
+                monto: r.get(3)?,
+                metodo: r.get(4)?,
+                usuario: r.get(5)?,
+                fecha: r.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let margen_base = margen_defecto(conn);
+    let mut stmt = conn
+        .prepare(
+            "SELECT cd.id, cd.producto_id, p.nombre, cd.cantidad, cd.precio_unitario, COALESCE(cd.costo_adicional, 0),
+                    p.precio_venta, p.margen_objetivo
+             FROM compra_detalles cd JOIN productos p ON p.id = cd.producto_id
+             WHERE cd.compra_id = ?1 AND cd.cantidad > 0
+             ORDER BY cd.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let lineas: Vec<LineaCostoDestino> = stmt
+        .query_map(rusqlite::params![compra_id], |r| {
+            let cantidad: f64 = r.get(3)?;
+            let precio_unitario: f64 = r.get(4)?;
+            let costo_adicional: f64 = r.get(5)?;
+            let costo_unitario_destino = precio_unitario + costo_adicional / cantidad;
+            let margen_objetivo = r.get::<_, Option<f64>>(7)?.unwrap_or(margen_base);
+            let producto_id: i64 = r.get(1)?;
+            Ok(LineaCostoDestino {
+                compra_detalle_id: r.get(0)?,
+                producto_id,
+                producto_nombre: r.get(2)?,
+                cantidad,
+                precio_unitario,
+                costo_adicional,
+                costo_unitario_destino: (costo_unitario_destino * 10000.0).round() / 10000.0,
+                costo_promedio: crate::commands::costeo::costo_promedio(conn, producto_id),
+                precio_venta: r.get(6)?,
+                margen_objetivo,
+                precio_venta_sugerido: precio_sugerido(costo_unitario_destino, margen_objetivo),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(DistribucionCostosCompra {
+        compra_id,
+        compra_numero,
+        total_adicional: round2(costos.iter().map(|c| c.monto).sum()),
+        costos,
+        lineas,
+    })
+}
+
+// ─── Comandos Tauri ──────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn guardar_precio_proveedor(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    precio: NuevoPrecioProveedor,
+) -> Result<i64, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    guardar(&conn, &precio, &usuario)
+}
+
+#[tauri::command]
+pub fn eliminar_precio_proveedor(db: State<Database>, sesion: State<SesionState>, id: i64) -> Result<(), String> {
+    usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM precios_proveedor WHERE id = ?1", rusqlite::params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn listar_precios_proveedor(
+    db: State<Database>,
+    proveedor_id: Option<i64>,
+    producto_id: Option<i64>,
+    solo_vigentes: Option<bool>,
+) -> Result<Vec<PrecioProveedor>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    listar(&conn, proveedor_id, producto_id, solo_vigentes.unwrap_or(false))
+}
+
+/// Carga la lista de costos de un proveedor (archivo o captura masiva).
+#[tauri::command]
+pub fn importar_lista_proveedor(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    proveedor_id: i64,
+    items: Vec<ItemListaProveedor>,
+    vigente_desde: Option<String>,
+    vigente_hasta: Option<String>,
+) -> Result<ResultadoImportacionLista, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut resultado = ResultadoImportacionLista { guardados: 0, no_encontrados: Vec::new() };
+    for (i, item) in items.iter().enumerate() {
+        let codigo = item.codigo.as_deref().map(str::trim).filter(|s| !s.is_empty());
+        let codigo_prov = item.codigo_proveedor.as_deref().map(str::trim).filter(|s| !s.is_empty());
+        let producto_id: Option<i64> = match (item.producto_id, codigo, codigo_prov) {
+            (Some(id), _, _) => Some(id),
+            (None, Some(c), _) => tx
+                .query_row(
+                    "SELECT id FROM productos WHERE codigo = ?1 OR codigo_barras = ?1 LIMIT 1",
+                    rusqlite::params![c],
+                    |r| r.get(0),
+                )
+                .ok(),
+            (None, None, Some(cp)) => tx
+                .query_row(
+                    "SELECT producto_id FROM precios_proveedor WHERE proveedor_id = ?1 AND codigo_proveedor = ?2
+                     ORDER BY id DESC LIMIT 1",
+                    rusqlite::params![proveedor_id, cp],
+                    |r| r.get(0),
+                )
+                .ok(),
+            (None, None, None) => None,
+        };
+        let Some(producto_id) = producto_id else {
+            resultado.no_encontrados.push(codigo.or(codigo_prov).map(str::to_string).unwrap_or_else(|| format!("Fila {}", i + 1)));
+            continue;
+        };
+        guardar(
+            &tx,
+            &NuevoPrecioProveedor {
+                id: None,
+                proveedor_id,
+                producto_id,
+                codigo_proveedor: codigo_prov.map(str::to_string),
+                costo: item.costo,
+                cantidad_minima: item.cantidad_minima,
+                vigente_desde: vigente_desde.clone(),
+                vigente_hasta: vigente_hasta.clone(),
+                observacion: None,
+            },
+            &usuario,
+        )?;
+        resultado.guardados += 1;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(resultado)
+}
+
+#[tauri::command]
+pub fn comparar_costos_proveedor(
+    db: State<Database>,
+    producto_id: i64,
+    cantidad: Option<f64>,
+) -> Result<Vec<ComparacionProveedor>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    comparar(&conn, producto_id, cantidad.unwrap_or(1.0))
+}
+
+/// Fija como preferido al proveedor de lista vigente mas barata. Sin ids,
+/// a todos los productos con lista vigente.
+#[tauri::command]
+pub fn preferir_proveedor_mas_barato(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    producto_ids: Option<Vec<i64>>,
+) -> Result<usize, String> {
+    usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let ids = match producto_ids {
+        Some(ids) => ids,
+        None => productos_con_lista(&conn)?,
+    };
+    let mut n = 0;
+    for pid in ids {
+        if let Some((proveedor_id, _)) = proveedor_mas_barato(&conn, pid) {
+            n += conn
+                .execute(
+                    "UPDATE productos SET proveedor_preferido_id = ?1 WHERE id = ?2",
+                    rusqlite::params![proveedor_id, pid],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(n)
+}
+
+#[tauri::command]
+pub fn obtener_costos_adicionales_compra(db: State<Database>, compra_id: i64) -> Result<DistribucionCostosCompra, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    distribucion(&conn, compra_id)
+}
+
+/// Calcula el reparto sin guardarlo (la transaccion se descarta).
+#[tauri::command]
+pub fn previsualizar_costos_adicionales(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    compra_id: i64,
+    costos: Vec<NuevoCostoAdicional>,
+) -> Result<DistribucionCostosCompra, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for costo in &costos {
+        aplicar_costo(&tx, compra_id, costo, &usuario)?;
+    }
+    distribucion(&tx, compra_id)
+}
+
+#[tauri::command]
+pub fn aplicar_costos_adicionales(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    compra_id: i64,
+    costos: Vec<NuevoCostoAdicional>,
+) -> Result<DistribucionCostosCompra, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_compras")?;
+    if costos.is_empty() {
+        return Err("Indique al menos un gasto".to_string());
+    }
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for costo in &costos {
+        aplicar_costo(&tx, compra_id, costo, &usuario)?;
+    }
+    let resultado = distribucion(&tx, compra_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(resultado)
+}
+
+#[tauri::command]
+pub fn quitar_costo_adicional(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    id: i64,
+) -> Result<DistribucionCostosCompra, String> {
+    usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let compra_id: i64 = tx
+        .query_row("SELECT compra_id FROM compra_costos_adicionales WHERE id = ?1", rusqlite::params![id], |r| r.get(0))
+        .map_err(|_| "Gasto de compra no encontrado".to_string())?;
+    compra_activa(&tx, compra_id)?;
+    revertir_costo(&tx, id)?;
+    let resultado = distribucion(&tx, compra_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(resultado)
+}
+
+/// Aplica los precios de venta sugeridos que el usuario acepto.
+#[tauri::command]
+pub fn aplicar_precios_sugeridos(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    precios: Vec<PrecioVentaNuevo>,
+) -> Result<usize, String> {
+    usuario_con_permiso(&sesion, "gestionar_productos")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut n = 0;
+    for p in precios {
+        if p.precio_venta <= 0.0 {
+            return Err("El precio de venta debe ser mayor a 0".to_string());
+        }
+        n += conn
+            .execute(
+                "UPDATE productos SET precio_venta = ?1, updated_at = datetime('now','localtime') WHERE id = ?2",
+                rusqlite::params![round2(p.precio_venta), p.producto_id],
+            )
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repartir_cuadra_al_centavo() {
+        let partes = repartir(&[100.0, 200.0, 0.0], 10.0);
+        assert_eq!(partes, vec![3.33, 6.67, 0.0]);
+        let partes = repartir(&[1.0, 1.0, 1.0], 100.0);
+        assert_eq!(round2(partes.iter().sum()), 100.0);
+        assert_eq!(repartir(&[0.0, 0.0], 5.0), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn precio_sugerido_es_margen_sobre_venta() {
+        assert_eq!(precio_sugerido(7.0, 30.0), 10.0);
+        assert_eq!(precio_sugerido(5.0, 0.0), 5.0);
+    }
+}
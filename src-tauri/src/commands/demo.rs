@@ -864,13 +864,34 @@ pub fn salir_demo(db: State<Database>) -> Result<(), String> {
         "DELETE FROM ordenes_servicio_imagenes",
         "DELETE FROM ordenes_servicio_movimientos",
         "DELETE FROM ordenes_servicio",
+        "DELETE FROM venta_detalle_lotes",
+        "DELETE FROM venta_detalle_costos",
+        "DELETE FROM venta_insumos_modificador",
+        "DELETE FROM capas_costo",
+        "DELETE FROM compra_costo_adicional_lineas",
+        "DELETE FROM compra_costos_adicionales",
+        "DELETE FROM precios_proveedor",
+        "DELETE FROM lista_picking_items",
+        "DELETE FROM lista_picking_documentos",
+        "DELETE FROM listas_picking",
+        "DELETE FROM stock_ubicacion",
+        "DELETE FROM ubicaciones_bodega",
         "DELETE FROM lotes_caducidad",
+        "DELETE FROM reglas_descuento_caducidad",
+        "DELETE FROM rma_seguimiento",
+        "DELETE FROM rma_lineas",
+        "DELETE FROM rma_proveedor",
+        "DELETE FROM serie_eventos",
         "DELETE FROM numeros_serie",
         "DELETE FROM presentaciones_producto",
         "DELETE FROM pagos_proveedor",
         "DELETE FROM cuentas_por_pagar",
         "DELETE FROM compra_detalles",
         "DELETE FROM compras",
+        "DELETE FROM orden_compra_recepcion_items",
+        "DELETE FROM orden_compra_recepciones",
+        "DELETE FROM orden_compra_lineas",
+        "DELETE FROM ordenes_compra",
         "DELETE FROM proveedores",
         "DELETE FROM retiros_caja",
         "DELETE FROM choferes",
@@ -880,6 +901,10 @@ pub fn salir_demo(db: State<Database>) -> Result<(), String> {
         "DELETE FROM ventas",
         "DELETE FROM movimientos_inventario",
         "DELETE FROM transferencias_stock",
+        "DELETE FROM transferencia_recepciones",
+        "DELETE FROM transferencia_series",
+        "DELETE FROM transferencia_lineas",
+        "DELETE FROM transferencias_documento",
         "DELETE FROM stock_establecimiento",
         "DELETE FROM precios_producto",
         "DELETE FROM productos",
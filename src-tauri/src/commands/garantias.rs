@@ -0,0 +1,885 @@
+// Garantias de unidades serializadas y RMA a proveedor
+//
+// Garantia:
+//   - productos.garantia_meses (cliente) y garantia_proveedor_meses se fijan con
+//     configurar_garantia_producto. Al vender una serie (marcar_serie_vendida)
+//     queda su numeros_serie.garantia_hasta; la del proveedor corre desde el
+//     ingreso de la serie.
+//   - consultar_garantia_serie resuelve un serial: cliente, venta, vigencia,
+//     ordenes de servicio y RMA en curso. abrir_orden_garantia crea la orden
+//     de servicio ya vinculada (servicio_tecnico::insertar_orden vincula
+//     cualquier orden cuyo equipo_serie sea una serie registrada).
+//   - cambiar_serie_garantia: cambio en tienda. El cliente recibe una serie
+//     DISPONIBLE (sale del stock) que hereda su venta y garantia; la devuelta
+//     queda DEFECTUOSO, lista para enviarse por RMA.
+//
+// RMA (estado ENVIADO -> RESUELTO_PARCIAL -> CERRADO, o ANULADO):
+//   - crear_rma_proveedor: las series pasan a EN_RMA. Si estaban DISPONIBLE
+//     salen del stock (kardex RMA_PROVEEDOR); DEFECTUOSO y VENDIDO (unidad del
+//     cliente) ya estaban fuera.
+//   - registrar_seguimiento_rma: bitacora de guia/transportista y novedades.
+//   - resolver_linea_rma: REEMPLAZADO crea la serie nueva en lugar de la vieja
+//     (la vieja queda REEMPLAZADO); REPARADO devuelve la misma serie;
+//     NOTA_CREDITO la deja DEVUELTO_PROVEEDOR; RECHAZADO la devuelve sin
+//     arreglar. Una unidad buena que vuelve a la tienda (origen no VENDIDO)
+//     reingresa al stock (kardex RMA_RETORNO); si era del cliente, la serie
+//     de reemplazo hereda su venta y garantia.
+//
+// serie_eventos guarda los hitos de cada serie para historial_serie, que los
+// junta con la compra, la venta y las ordenes de servicio.
+
+use crate::db::{usuario_con_permiso, Database, SesionState};
+use crate::models::{
+    EventoSerie, GarantiaSerie, HistorialSerie, LineaRma, NuevoRmaProveedor, OrdenServicio, RmaProveedor,
+    SeguimientoRma,
+};
+use rusqlite::{Connection, OptionalExtension};
+use tauri::State;
+
+/// Deja un hito en la historia de la serie.
+pub fn registrar_evento(
+    conn: &Connection,
+    numero_serie_id: i64,
+    tipo: &str,
+    documento: Option<&str>,
+    detalle: &str,
+    usuario: Option<&str>,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO serie_eventos (numero_serie_id, tipo, documento, detalle, usuario) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![numero_serie_id, tipo, documento, detalle, usuario],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Id de la serie con ese serial. Si dos productos comparten serial, se pide
+/// desambiguar.
+pub fn buscar_serie_id(conn: &Connection, serial: &str) -> Result<i64, String> {
+    let s = serial.trim().to_uppercase();
+    if s.is_empty() {
+        return Err("Indique el número de serie".to_string());
+    }
+    let mut stmt = conn
+        .prepare("SELECT id FROM numeros_serie WHERE serial = ?1 ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let ids: Vec<i64> = stmt
+        .query_map(rusqlite::params![s], |r| r.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    match ids.as_slice() {
+        [] => Err(format!("La serie {} no está registrada", s)),
+        [id] => Ok(*id),
+        _ => Err(format!("La serie {} está registrada en {} productos; búsquela desde el producto", s, ids.len())),
+    }
+}
+
+/// Vincula la orden de servicio a la serie registrada de su equipo (si la
+/// hay) y marca si entra en garantia.
+pub fn vincular_orden_serie(conn: &Connection, orden_id: i64, equipo_serie: Option<&str>) -> Result<(), String> {
+    let Some(serial) = equipo_serie.map(str::trim).filter(|s| !s.is_empty()) else {
+        return Ok(());
+    };
+    let Ok(serie_id) = buscar_serie_id(conn, serial) else {
+        return Ok(());
+    };
+    let g = garantia(conn, serie_id)?;
+    conn.execute(
+        "UPDATE ordenes_servicio SET numero_serie_id = ?1, en_garantia = ?2 WHERE id = ?3",
+        rusqlite::params![serie_id, g.en_garantia as i64, orden_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Garantia de la serie: la del cliente (guardada al vender; para ventas
+/// anteriores se calcula desde fecha_venta) y la del proveedor.
+pub fn garantia(conn: &Connection, serie_id: i64) -> Result<GarantiaSerie, String> {
+    #[allow(clippy::type_complexity)]
+    let fila: (
+        String, String, i64, String, Option<i64>, Option<String>, Option<i64>, Option<String>,
+        Option<String>, Option<String>, Option<i64>, Option<String>, Option<String>,
+        Option<String>, Option<String>,
+    ) = conn
+        .query_row(
+            "SELECT ns.serial, ns.estado, ns.producto_id, p.nombre, ns.cliente_id, ns.cliente_nombre,
+                    ns.venta_id, v.numero, ns.fecha_venta,
+                    COALESCE(ns.garantia_hasta,
+                             CASE WHEN ns.fecha_venta IS NOT NULL AND COALESCE(p.garantia_meses, 0) > 0
+                                  THEN date(ns.fecha_venta, '+' || p.garantia_meses || ' months') END),
+                    c.proveedor_id, pr.nombre,
+                    CASE WHEN COALESCE(p.garantia_proveedor_meses, 0) > 0
+                         THEN date(ns.fecha_ingreso, '+' || p.garantia_proveedor_meses || ' months') END,
+                    (SELECT s2.serial FROM numeros_serie s2 WHERE s2.id = ns.reemplaza_a_id),
+                    (SELECT s3.serial FROM numeros_serie s3 WHERE s3.id = ns.reemplazada_por_id)
+             FROM numeros_serie ns
+             JOIN productos p ON p.id = ns.producto_id
+             LEFT JOIN ventas v ON v.id = ns.venta_id
+             LEFT JOIN compras c ON c.id = ns.compra_id
+             LEFT JOIN proveedores pr ON pr.id = c.proveedor_id
+             WHERE ns.id = ?1",
+            rusqlite::params![serie_id],
+            |r| {
+                Ok((
+                    r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?, r.get(6)?, r.get(7)?,
+                    r.get(8)?, r.get(9)?, r.get(10)?, r.get(11)?, r.get(12)?, r.get(13)?, r.get(14)?,
+                ))
+            },
+        )
+        .map_err(|_| format!("Serie #{} no encontrada", serie_id))?;
+    let (
+        serial, estado, producto_id, producto_nombre, cliente_id, cliente_nombre, venta_id, venta_numero,
+        fecha_venta, garantia_hasta, proveedor_id, proveedor_nombre, garantia_proveedor_hasta,
+        reemplaza_a, reemplazada_por,
+    ) = fila;
+
+    let dias_hasta = |fecha: &Option<String>| -> Option<i64> {
+        fecha.as_ref().and_then(|f| {
+            conn.query_row(
+                "SELECT CAST(julianday(date(?1)) - julianday(date('now','localtime')) AS INTEGER)",
+                rusqlite::params![f],
+                |r| r.get(0),
+            )
+            .ok()
+        })
+    };
+    let dias_restantes = dias_hasta(&garantia_hasta);
+    let dias_proveedor = dias_hasta(&garantia_proveedor_hasta);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT numero, estado FROM ordenes_servicio
+             WHERE numero_serie_id = ?1 OR (numero_serie_id IS NULL AND UPPER(TRIM(equipo_serie)) = ?2)
+             ORDER BY fecha_ingreso DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let ordenes_servicio = stmt
+        .query_map(rusqlite::params![serie_id, serial], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let rma_abierto: Option<String> = conn
+        .query_row(
+            "SELECT r.numero FROM rma_lineas l JOIN rma_proveedor r ON r.id = l.rma_id
+             WHERE l.numero_serie_id = ?1 AND l.resolucion = 'PENDIENTE' AND r.estado != 'ANULADO'
+             ORDER BY r.id DESC LIMIT 1",
+            rusqlite::params![serie_id],
+            |r| r.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(GarantiaSerie {
+        numero_serie_id: serie_id,
+        serial,
+        estado,
+        producto_id,
+        producto_nombre,
+        cliente_id,
+        cliente_nombre,
+        venta_id,
+        venta_numero,
+        fecha_venta,
+        garantia_hasta,
+        en_garantia: dias_restantes.is_some_and(|d| d >= 0),
+        dias_restantes,
+        proveedor_id,
+        proveedor_nombre,
+        garantia_proveedor_hasta,
+        en_garantia_proveedor: dias_proveedor.is_some_and(|d| d >= 0),
+        reemplaza_a,
+        reemplazada_por,
+        ordenes_servicio,
+        rma_abierto,
+    })
+}
+
+/// Establecimiento donde esta la serie (el de la terminal si no se sabe).
+fn establecimiento_serie(conn: &Connection, serie_id: i64) -> Result<i64, String> {
+    let est: Option<i64> = conn
+        .query_row(
+            "SELECT establecimiento_id FROM numeros_serie WHERE id = ?1",
+            rusqlite::params![serie_id],
+            |r| r.get(0),
+        )
+        .unwrap_or(None);
+    match est {
+        Some(id) => Ok(id),
+        None => crate::commands::stock::establecimiento_terminal(conn),
+    }
+}
+
+/// Mueve una unidad de la serie en el stock con su kardex.
+fn mover_unidad(
+    conn: &Connection,
+    serie_id: i64,
+    delta: f64,
+    tipo: &str,
+    referencia_id: i64,
+    motivo: &str,
+    usuario: &str,
+) -> Result<(), String> {
+    let (producto_id, costo): (i64, Option<f64>) = conn
+        .query_row(
+            "SELECT producto_id, costo FROM numeros_serie WHERE id = ?1",
+            rusqlite::params![serie_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    let est_id = establecimiento_serie(conn, serie_id)?;
+    let costo = costo.unwrap_or_else(|| crate::commands::costeo::costo_promedio(conn, producto_id));
+    if delta < 0.0 {
+        crate::commands::costeo::retirar_capas(conn, producto_id, est_id, -delta)?;
+    }
+    let mov = crate::commands::stock::mover(conn, producto_id, est_id, delta)?;
+    if delta > 0.0 {
+        crate::commands::costeo::crear_capa(conn, producto_id, est_id, "RMA", Some(referencia_id), delta, costo)?;
+    }
+    conn.execute(
+        "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, motivo, establecimiento_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        rusqlite::params![producto_id, tipo, delta, mov.anterior, mov.nuevo, costo, referencia_id, usuario, motivo, est_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// RMA-XXXXXXXXX (9 dig), MAX existente + 1 como en compras.
+fn proximo_numero_rma(conn: &Connection) -> String {
+    let next: i64 = conn
+        .query_row(
+            "SELECT COALESCE(MAX(CAST(SUBSTR(numero, 5) AS INTEGER)), 0) + 1
+             FROM rma_proveedor WHERE numero LIKE 'RMA-%'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap_or(1);
+    format!("RMA-{:09}", next)
+}
+
+pub fn obtener_rma(conn: &Connection, id: i64) -> Result<RmaProveedor, String> {
+    let mut rma = conn
+        .query_row(
+            "SELECT r.id, r.numero, r.proveedor_id, p.nombre, r.estado, r.transportista, r.numero_guia,
+                    r.observacion, r.usuario, r.fecha_envio, r.fecha_cierre
+             FROM rma_proveedor r
+             LEFT JOIN proveedores p ON p.id = r.proveedor_id
+             WHERE r.id = ?1",
+            rusqlite::params![id],
+            |r| {
+                Ok(RmaProveedor {
+                    id: r.get(0)?,
+                    numero: r.get(1)?,
+                    proveedor_id: r.get(2)?,
+                    proveedor_nombre: r.get(3)?,
+                    estado: r.get(4)?,
+                    transportista: r.get(5)?,
+                    numero_guia: r.get(6)?,
+                    observacion: r.get(7)?,
+                    usuario: r.get(8)?,
+                    fecha_envio: r.get(9)?,
+                    fecha_cierre: r.get(10)?,
+                    lineas: Vec::new(),
+                    seguimiento: Vec::new(),
+                })
+            },
+        )
+        .map_err(|_| format!("RMA #{} no encontrado", id))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT l.id, l.numero_serie_id, ns.serial, ns.producto_id, p.nombre, l.motivo, l.orden_servicio_id,
+                    l.estado_origen, l.resolucion, nr.serial, l.fecha_resolucion, l.observacion
+             FROM rma_lineas l
+             JOIN numeros_serie ns ON ns.id = l.numero_serie_id
+             JOIN productos p ON p.id = ns.producto_id
+             LEFT JOIN numeros_serie nr ON nr.id = l.serie_reemplazo_id
+             WHERE l.rma_id = ?1 ORDER BY l.id",
+        )
+        .map_err(|e| e.to_string())?;
+    rma.lineas = stmt
+        .query_map(rusqlite::params![id], |r| {
+            Ok(LineaRma {
+                id: r.get(0)?,
+                numero_serie_id: r.get(1)?,
+                serial: r.get(2)?,
+                producto_id: r.get(3)?,
+                producto_nombre: r.get(4)?,
+                motivo: r.get(5)?,
+                orden_servicio_id: r.get(6)?,
+                estado_origen: r.get(7)?,
+                resolucion: r.get(8)?,
+                serial_reemplazo: r.get(9)?,
+                fecha_resolucion: r.get(10)?,
+                observacion: r.get(11)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare("SELECT estado, observacion, usuario, fecha FROM rma_seguimiento WHERE rma_id = ?1 ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    rma.seguimiento = stmt
+        .query_map(rusqlite::params![id], |r| {
+            Ok(SeguimientoRma { estado: r.get(0)?, observacion: r.get(1)?, usuario: r.get(2)?, fecha: r.get(3)? })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rma)
+}
+
+fn seguimiento(conn: &Connection, rma_id: i64, estado: &str, observacion: Option<&str>, usuario: &str) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO rma_seguimiento (rma_id, estado, observacion, usuario) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![rma_id, estado, observacion, usuario],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn crear_rma(conn: &Connection, nuevo: &NuevoRmaProveedor, usuario: &str) -> Result<i64, String> {
+    if nuevo.lineas.is_empty() {
+        return Err("Indique al menos una serie a devolver".to_string());
+    }
+    let numero = proximo_numero_rma(conn);
+    conn.execute(
+        "INSERT INTO rma_proveedor (numero, proveedor_id, transportista, numero_guia, observacion, usuario)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![numero, nuevo.proveedor_id, nuevo.transportista, nuevo.numero_guia, nuevo.observacion, usuario],
+    )
+    .map_err(|e| e.to_string())?;
+    let rma_id = conn.last_insert_rowid();
+
+    for linea in &nuevo.lineas {
+        if linea.motivo.trim().is_empty() {
+            return Err("Indique el motivo de cada serie".to_string());
+        }
+        let (serial, estado): (String, String) = conn
+            .query_row(
+                "SELECT serial, estado FROM numeros_serie WHERE id = ?1",
+                rusqlite::params![linea.numero_serie_id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .map_err(|_| format!("Serie #{} no encontrada", linea.numero_serie_id))?;
+        if !matches!(estado.as_str(), "DISPONIBLE" | "DEFECTUOSO" | "VENDIDO") {
+            return Err(format!("La serie {} está {}: no se puede enviar por RMA", serial, estado));
+        }
+        conn.execute(
+            "INSERT INTO rma_lineas (rma_id, numero_serie_id, motivo, orden_servicio_id, estado_origen)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![rma_id, linea.numero_serie_id, linea.motivo.trim(), linea.orden_servicio_id, estado],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE numeros_serie SET estado = 'EN_RMA' WHERE id = ?1",
+            rusqlite::params![linea.numero_serie_id],
+        )
+        .map_err(|e| e.to_string())?;
+        if estado == "DISPONIBLE" {
+            mover_unidad(
+                conn, linea.numero_serie_id, -1.0, "RMA_PROVEEDOR", rma_id,
+                &format!("{} - serie {} ({})", numero, serial, linea.motivo.trim()), usuario,
+            )?;
+        }
+        registrar_evento(conn, linea.numero_serie_id, "RMA_ENVIADO", Some(&numero), linea.motivo.trim(), Some(usuario))?;
+    }
+    seguimiento(conn, rma_id, "ENVIADO", nuevo.numero_guia.as_deref(), usuario)?;
+    Ok(rma_id)
+}
+
+/// Aplica la respuesta del proveedor a una unidad del RMA.
+pub fn resolver_linea(
+    conn: &Connection,
+    linea_id: i64,
+    resolucion: &str,
+    serial_reemplazo: Option<&str>,
+    observacion: Option<&str>,
+    usuario: &str,
+) -> Result<i64, String> {
+    let (rma_id, serie_id, estado_origen, actual): (i64, i64, String, String) = conn
+        .query_row(
+            "SELECT rma_id, numero_serie_id, estado_origen, resolucion FROM rma_lineas WHERE id = ?1",
+            rusqlite::params![linea_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .map_err(|_| format!("Línea de RMA #{} no encontrada", linea_id))?;
+    if actual != "PENDIENTE" {
+        return Err(format!("Esta unidad ya fue resuelta ({})", actual));
+    }
+    let rma = obtener_rma(conn, rma_id)?;
+    if rma.estado == "ANULADO" || rma.estado == "CERRADO" {
+        return Err(format!("El RMA {} está {}", rma.numero, rma.estado));
+    }
+    let serial: String = conn
+        .query_row("SELECT serial FROM numeros_serie WHERE id = ?1", rusqlite::params![serie_id], |r| r.get(0))
+        .map_err(|e| e.to_string())?;
+    let del_cliente = estado_origen == "VENDIDO";
+    let mut serie_reemplazo_id: Option<i64> = None;
+
+    match resolucion {
+        "REEMPLAZADO" => {
+            let nuevo = serial_reemplazo
+                .map(|s| s.trim().to_uppercase())
+                .filter(|s| !s.is_empty())
+                .ok_or("Indique el serial de la unidad de reemplazo")?;
+            // La nueva hereda producto, compra, costo, establecimiento y, si la
+            // unidad era del cliente, su venta y garantia.
+            conn.execute(
+                "INSERT INTO numeros_serie (producto_id, serial, estado, compra_id, venta_id, venta_detalle_id,
+                        cliente_id, cliente_nombre, fecha_venta, garantia_hasta, costo, establecimiento_id,
+                        reemplaza_a_id, observacion)
+                 SELECT producto_id, ?1, CASE WHEN ?2 THEN 'VENDIDO' ELSE 'DISPONIBLE' END, compra_id,
+                        CASE WHEN ?2 THEN venta_id END, CASE WHEN ?2 THEN venta_detalle_id END,
+                        CASE WHEN ?2 THEN cliente_id END, CASE WHEN ?2 THEN cliente_nombre END,
+                        CASE WHEN ?2 THEN fecha_venta END, CASE WHEN ?2 THEN garantia_hasta END,
+                        costo, establecimiento_id, id, ?3
+                 FROM numeros_serie WHERE id = ?4",
+                rusqlite::params![nuevo, del_cliente, format!("Reemplazo RMA {}", rma.numero), serie_id],
+            )
+            .map_err(|e| {
+                if e.to_string().contains("UNIQUE") {
+                    format!("La serie {} ya existe para este producto", nuevo)
+                } else {
+                    e.to_string()
+                }
+            })?;
+            let nueva_id = conn.last_insert_rowid();
+            conn.execute(
+                "UPDATE numeros_serie SET estado = 'REEMPLAZADO', reemplazada_por_id = ?1 WHERE id = ?2",
+                rusqlite::params![nueva_id, serie_id],
+            )
+            .map_err(|e| e.to_string())?;
+            registrar_evento(conn, serie_id, "RMA_REEMPLAZADO", Some(&rma.numero), &format!("Reemplazada por {}", nuevo), Some(usuario))?;
+            registrar_evento(conn, nueva_id, "INGRESO", Some(&rma.numero), &format!("Reemplazo de {}", serial), Some(usuario))?;
+            if !del_cliente {
+                mover_unidad(conn, nueva_id, 1.0, "RMA_RETORNO", rma_id, &format!("{} - reemplazo {} por {}", rma.numero, serial, nuevo), usuario)?;
+            }
+            serie_reemplazo_id = Some(nueva_id);
+        }
+        "REPARADO" => {
+            let estado = if del_cliente { "VENDIDO" } else { "DISPONIBLE" };
+            conn.execute("UPDATE numeros_serie SET estado = ?1 WHERE id = ?2", rusqlite::params![estado, serie_id])
+                .map_err(|e| e.to_string())?;
+            if !del_cliente {
+                mover_unidad(conn, serie_id, 1.0, "RMA_RETORNO", rma_id, &format!("{} - serie {} reparada", rma.numero, serial), usuario)?;
+            }
+            registrar_evento(conn, serie_id, "RMA_REPARADO", Some(&rma.numero), observacion.unwrap_or("Reparada por el proveedor"), Some(usuario))?;
+        }
+        "NOTA_CREDITO" => {
+            conn.execute("UPDATE numeros_serie SET estado = 'DEVUELTO_PROVEEDOR' WHERE id = ?1", rusqlite::params![serie_id])
+                .map_err(|e| e.to_string())?;
+            registrar_evento(conn, serie_id, "RMA_NOTA_CREDITO", Some(&rma.numero), observacion.unwrap_or("El proveedor acreditó la unidad"), Some(usuario))?;
+        }
+        "RECHAZADO" => {
+            let estado = if del_cliente { "VENDIDO" } else { "DEFECTUOSO" };
+            conn.execute("UPDATE numeros_serie SET estado = ?1 WHERE id = ?2", rusqlite::params![estado, serie_id])
+                .map_err(|e| e.to_string())?;
+            registrar_evento(conn, serie_id, "RMA_RECHAZADO", Some(&rma.numero), observacion.unwrap_or("El proveedor rechazó la garantía"), Some(usuario))?;
+        }
+        otra => return Err(format!("Resolución no válida: {}", otra)),
+    }
+
+    conn.execute(
+        "UPDATE rma_lineas SET resolucion = ?1, serie_reemplazo_id = ?2, observacion = ?3,
+                fecha_resolucion = datetime('now','localtime')
+         WHERE id = ?4",
+        rusqlite::params![resolucion, serie_reemplazo_id, observacion, linea_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let pendientes: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM rma_lineas WHERE rma_id = ?1 AND resolucion = 'PENDIENTE'",
+            rusqlite::params![rma_id],
+            |r| r.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let estado_rma = if pendientes == 0 { "CERRADO" } else { "RESUELTO_PARCIAL" };
+    conn.execute(
+        "UPDATE rma_proveedor SET estado = ?1,
+                fecha_cierre = CASE WHEN ?1 = 'CERRADO' THEN datetime('now','localtime') END
+         WHERE id = ?2",
+        rusqlite::params![estado_rma, rma_id],
+    )
+    .map_err(|e| e.to_string())?;
+    seguimiento(conn, rma_id, estado_rma, Some(&format!("{}: {}", serial, resolucion)), usuario)?;
+    Ok(rma_id)
+}
+
+/// Anula un RMA sin respuestas: las series vuelven a su estado y al stock.
+pub fn anular_rma(conn: &Connection, rma_id: i64, motivo: &str, usuario: &str) -> Result<(), String> {
+    let rma = obtener_rma(conn, rma_id)?;
+    if rma.estado != "ENVIADO" {
+        return Err(format!("Solo se anula un RMA sin resolver (está {})", rma.estado));
+    }
+    for l in &rma.lineas {
+        conn.execute(
+            "UPDATE numeros_serie SET estado = ?1 WHERE id = ?2",
+            rusqlite::params![l.estado_origen, l.numero_serie_id],
+        )
+        .map_err(|e| e.to_string())?;
+        if l.estado_origen == "DISPONIBLE" {
+            mover_unidad(conn, l.numero_serie_id, 1.0, "RMA_RETORNO", rma_id, &format!("Anulación {} - serie {}", rma.numero, l.serial), usuario)?;
+        }
+        registrar_evento(conn, l.numero_serie_id, "RMA_ANULADO", Some(&rma.numero), motivo, Some(usuario))?;
+    }
+    conn.execute(
+        "UPDATE rma_proveedor SET estado = 'ANULADO', fecha_cierre = datetime('now','localtime') WHERE id = ?1",
+        rusqlite::params![rma_id],
+    )
+    .map_err(|e| e.to_string())?;
+    seguimiento(conn, rma_id, "ANULADO", Some(motivo), usuario)
+}
+
+/// Historia completa de la serie: compra, ventas, devoluciones, ordenes de
+/// servicio, RMA y cambios, en orden cronologico.
+pub fn historial(conn: &Connection, serie_id: i64) -> Result<HistorialSerie, String> {
+    let garantia = garantia(conn, serie_id)?;
+    let mut eventos: Vec<EventoSerie> = Vec::new();
+
+    let mut stmt = conn
+        .prepare("SELECT fecha, tipo, documento, detalle, usuario FROM serie_eventos WHERE numero_serie_id = ?1")
+        .map_err(|e| e.to_string())?;
+    eventos.extend(
+        stmt.query_map(rusqlite::params![serie_id], |r| {
+            Ok(EventoSerie { fecha: r.get(0)?, tipo: r.get(1)?, documento: r.get(2)?, detalle: r.get(3)?, usuario: r.get(4)? })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?,
+    );
+
+    // Series anteriores a serie_eventos: la compra y la venta salen de la propia fila
+    if !eventos.iter().any(|e| e.tipo == "INGRESO") {
+        let ingreso: Option<EventoSerie> = conn
+            .query_row(
+                "SELECT ns.fecha_ingreso, c.numero, COALESCE(pr.nombre, 'Ingreso manual'), c.usuario
+                 FROM numeros_serie ns
+                 LEFT JOIN compras c ON c.id = ns.compra_id
+                 LEFT JOIN proveedores pr ON pr.id = c.proveedor_id
+                 WHERE ns.id = ?1",
+                rusqlite::params![serie_id],
+                |r| {
+                    Ok(EventoSerie {
+                        fecha: r.get(0)?,
+                        tipo: "INGRESO".to_string(),
+                        documento: r.get(1)?,
+                        detalle: r.get(2)?,
+                        usuario: r.get(3)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        eventos.extend(ingreso);
+    }
+    if let (Some(fecha), false) = (&garantia.fecha_venta, eventos.iter().any(|e| e.tipo == "VENTA")) {
+        eventos.push(EventoSerie {
+            fecha: fecha.clone(),
+            tipo: "VENTA".to_string(),
+            documento: garantia.venta_numero.clone(),
+            detalle: garantia.cliente_nombre.clone().unwrap_or_else(|| "Consumidor final".to_string()),
+            usuario: None,
+        });
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT fecha_ingreso, numero, problema_reportado, estado, fecha_entrega, trabajo_realizado, tecnico_nombre
+             FROM ordenes_servicio
+             WHERE numero_serie_id = ?1 OR (numero_serie_id IS NULL AND UPPER(TRIM(equipo_serie)) = ?2)",
+        )
+        .map_err(|e| e.to_string())?;
+    #[allow(clippy::type_complexity)]
+    let ordenes: Vec<(String, String, String, String, Option<String>, Option<String>, Option<String>)> = stmt
+        .query_map(rusqlite::params![serie_id, garantia.serial], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?, r.get(6)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for (fecha, numero, problema, estado, entrega, trabajo, tecnico) in ordenes {
+        eventos.push(EventoSerie {
+            fecha,
+            tipo: "ORDEN_SERVICIO".to_string(),
+            documento: Some(numero.clone()),
+            detalle: format!("{} ({})", problema, estado),
+            usuario: tecnico.clone(),
+        });
+        if let Some(fecha_entrega) = entrega {
+            eventos.push(EventoSerie {
+                fecha: fecha_entrega,
+                tipo: "ENTREGA_SERVICIO".to_string(),
+                documento: Some(numero),
+                detalle: trabajo.unwrap_or_default(),
+                usuario: tecnico,
+            });
+        }
+    }
+
+    eventos.sort_by(|a, b| a.fecha.cmp(&b.fecha));
+    Ok(HistorialSerie { garantia, eventos })
+}
+
+// ─── Comandos Tauri ──────────────────────────────────────────────────────
+
+/// Fija la garantia (meses) al cliente y del proveedor de varios productos.
+#[tauri::command]
+pub fn configurar_garantia_producto(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    producto_ids: Vec<i64>,
+    garantia_meses: i64,
+    garantia_proveedor_meses: i64,
+) -> Result<usize, String> {
+    usuario_con_permiso(&sesion, "gestionar_productos")?;
+    if garantia_meses < 0 || garantia_proveedor_meses < 0 {
+        return Err("Los meses de garantía no pueden ser negativos".to_string());
+    }
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut n = 0;
+    for pid in producto_ids {
+        n += conn
+            .execute(
+                "UPDATE productos SET garantia_meses = ?1, garantia_proveedor_meses = ?2 WHERE id = ?3",
+                rusqlite::params![garantia_meses, garantia_proveedor_meses, pid],
+            )
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(n)
+}
+
+#[tauri::command]
+pub fn consultar_garantia_serie(db: State<Database>, serial: String) -> Result<GarantiaSerie, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let id = buscar_serie_id(&conn, &serial)?;
+    garantia(&conn, id)
+}
+
+/// Crea la orden de servicio de una serie con los datos de su venta. Dentro
+/// de garantia el presupuesto arranca en 0.
+#[tauri::command]
+pub fn abrir_orden_garantia(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    serial: String,
+    problema_reportado: String,
+    accesorios: Option<String>,
+    tecnico_id: Option<i64>,
+    tecnico_nombre: Option<String>,
+) -> Result<i64, String> {
+    crate::commands::servicio_tecnico::requiere_modulo_servicio_tecnico(&db)?;
+    if problema_reportado.trim().is_empty() {
+        return Err("Describa el problema reportado".to_string());
+    }
+    let usuario = {
+        let s = sesion.sesion.lock().map_err(|e| e.to_string())?;
+        s.as_ref().map(|s| s.nombre.clone()).unwrap_or_else(|| "Sistema".to_string())
+    };
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let g = garantia(&conn, buscar_serie_id(&conn, &serial)?)?;
+    let telefono: Option<String> = g.cliente_id.and_then(|cid| {
+        conn.query_row("SELECT telefono FROM clientes WHERE id = ?1", rusqlite::params![cid], |r| r.get(0))
+            .unwrap_or(None)
+    });
+    let observaciones = match (&g.garantia_hasta, g.en_garantia) {
+        (Some(hasta), true) => format!("EN GARANTÍA hasta {} (venta {})", hasta, g.venta_numero.clone().unwrap_or_default()),
+        (Some(hasta), false) => format!("Garantía vencida el {}", hasta),
+        (None, _) => "Sin garantía registrada".to_string(),
+    };
+    let orden = OrdenServicio {
+        id: None,
+        numero: None,
+        cliente_id: g.cliente_id,
+        cliente_nombre: g.cliente_nombre.clone(),
+        cliente_telefono: telefono,
+        tipo_equipo: "GENERAL".to_string(),
+        equipo_descripcion: g.producto_nombre.clone(),
+        equipo_marca: None,
+        equipo_modelo: None,
+        equipo_serie: Some(g.serial.clone()),
+        equipo_placa: None,
+        equipo_kilometraje: None,
+        equipo_kilometraje_proximo: None,
+        equipo_kilometraje_intervalo: None,
+        equipo_kilometraje_salida: None,
+        accesorios,
+        problema_reportado: problema_reportado.trim().to_string(),
+        diagnostico: None,
+        trabajo_realizado: None,
+        observaciones: Some(observaciones),
+        tecnico_id,
+        tecnico_nombre,
+        estado: "RECIBIDO".to_string(),
+        fecha_ingreso: None,
+        fecha_promesa: None,
+        fecha_entrega: None,
+        presupuesto: 0.0,
+        monto_final: 0.0,
+        garantia_dias: 0,
+        venta_id: None,
+        usuario_creador: None,
+        tipo_equipo_id: None,
+        marca_id: None,
+        modelo_id: None,
+    };
+    crate::commands::servicio_tecnico::insertar_orden(&conn, &orden, &usuario)
+}
+
+/// Cambio en tienda: el cliente se lleva `serie_nueva_id` (DISPONIBLE) con su
+/// venta y garantia; la serie devuelta queda DEFECTUOSO.
+#[tauri::command]
+pub fn cambiar_serie_garantia(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    serie_id: i64,
+    serie_nueva_id: i64,
+    motivo: String,
+) -> Result<GarantiaSerie, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let vieja = garantia(&tx, serie_id)?;
+    let nueva = garantia(&tx, serie_nueva_id)?;
+    if vieja.estado != "VENDIDO" {
+        return Err(format!("La serie {} no está vendida (está {})", vieja.serial, vieja.estado));
+    }
+    if nueva.estado != "DISPONIBLE" || nueva.producto_id != vieja.producto_id {
+        return Err(format!("La serie {} no está disponible para este producto", nueva.serial));
+    }
+    tx.execute(
+        "UPDATE numeros_serie SET estado = 'VENDIDO', venta_id = v.venta_id, venta_detalle_id = v.venta_detalle_id,
+                cliente_id = v.cliente_id, cliente_nombre = v.cliente_nombre, fecha_venta = v.fecha_venta,
+                garantia_hasta = COALESCE(v.garantia_hasta, ?3), reemplaza_a_id = v.id
+         FROM (SELECT * FROM numeros_serie WHERE id = ?1) AS v
+         WHERE numeros_serie.id = ?2",
+        rusqlite::params![serie_id, serie_nueva_id, vieja.garantia_hasta],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE numeros_serie SET estado = 'DEFECTUOSO', reemplazada_por_id = ?1 WHERE id = ?2",
+        rusqlite::params![serie_nueva_id, serie_id],
+    )
+    .map_err(|e| e.to_string())?;
+    let doc = vieja.venta_numero.clone();
+    mover_unidad(&tx, serie_nueva_id, -1.0, "CAMBIO_GARANTIA", vieja.venta_id.unwrap_or(0),
+        &format!("Cambio por garantía: {} por {} ({})", vieja.serial, nueva.serial, motivo.trim()), &usuario)?;
+    registrar_evento(&tx, serie_id, "CAMBIO_GARANTIA", doc.as_deref(), &format!("Devuelta por el cliente, cambiada por {}: {}", nueva.serial, motivo.trim()), Some(&usuario))?;
+    registrar_evento(&tx, serie_nueva_id, "CAMBIO_GARANTIA", doc.as_deref(), &format!("Entregada al cliente en lugar de {}", vieja.serial), Some(&usuario))?;
+    let resultado = garantia(&tx, serie_nueva_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(resultado)
+}
+
+#[tauri::command]
+pub fn crear_rma_proveedor(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    rma: NuevoRmaProveedor,
+) -> Result<RmaProveedor, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let id = crear_rma(&tx, &rma, &usuario)?;
+    let creado = obtener_rma(&tx, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(creado)
+}
+
+#[tauri::command]
+pub fn listar_rmas_proveedor(
+    db: State<Database>,
+    estado: Option<String>,
+    proveedor_id: Option<i64>,
+) -> Result<Vec<RmaProveedor>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id FROM rma_proveedor
+             WHERE (?1 IS NULL OR estado = ?1) AND (?2 IS NULL OR proveedor_id = ?2)
+             ORDER BY id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let ids: Vec<i64> = stmt
+        .query_map(rusqlite::params![estado.filter(|s| !s.is_empty()), proveedor_id], |r| r.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    ids.into_iter().map(|id| obtener_rma(&conn, id)).collect()
+}
+
+#[tauri::command]
+pub fn obtener_rma_proveedor(db: State<Database>, id: i64) -> Result<RmaProveedor, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    obtener_rma(&conn, id)
+}
+
+/// Novedad de seguimiento (respuesta del proveedor, guia de retorno...).
+#[tauri::command]
+pub fn registrar_seguimiento_rma(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    id: i64,
+    observacion: String,
+    numero_guia: Option<String>,
+) -> Result<RmaProveedor, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let rma = obtener_rma(&conn, id)?;
+    if let Some(guia) = numero_guia.filter(|g| !g.trim().is_empty()) {
+        conn.execute(
+            "UPDATE rma_proveedor SET numero_guia = ?1 WHERE id = ?2",
+            rusqlite::params![guia.trim(), id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    seguimiento(&conn, id, &rma.estado, Some(observacion.trim()), &usuario)?;
+    obtener_rma(&conn, id)
+}
+
+#[tauri::command]
+pub fn resolver_linea_rma(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    linea_id: i64,
+    resolucion: String,
+    serial_reemplazo: Option<String>,
+    observacion: Option<String>,
+) -> Result<RmaProveedor, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let rma_id = resolver_linea(
+        &tx, linea_id, &resolucion.trim().to_uppercase(), serial_reemplazo.as_deref(), observacion.as_deref(), &usuario,
+    )?;
+    let rma = obtener_rma(&tx, rma_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(rma)
+}
+
+#[tauri::command]
+pub fn anular_rma_proveedor(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    id: i64,
+    motivo: String,
+) -> Result<RmaProveedor, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    anular_rma(&tx, id, motivo.trim(), &usuario)?;
+    let rma = obtener_rma(&tx, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(rma)
+}
+
+#[tauri::command]
+pub fn historial_serie(db: State<Database>, serial: String) -> Result<HistorialSerie, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let id = buscar_serie_id(&conn, &serial)?;
+    historial(&conn, id)
+}
@@ -180,7 +180,7 @@ pub fn imprimir_ticket(db: State<Database>, venta_id: i64) -> Result<String, Str
         return Err("No hay impresora configurada. Vaya a Configuración.".to_string());
     }
 
-    printing::imprimir_raw_windows(&impresora, &ticket_data)?;
+    printing::imprimir_raw(&impresora, &ticket_data)?;
 
     Ok("Ticket impreso correctamente".to_string())
 }
@@ -833,7 +833,7 @@ pub fn imprimir_reporte_caja(
     drop(conn);
 
     let ticket_data = generar_ticket_reporte_caja(&r, detallado.unwrap_or(false));
-    printing::imprimir_raw_windows(&impresora, &ticket_data)?;
+    printing::imprimir_raw(&impresora, &ticket_data)?;
 
     Ok("Reporte de caja impreso correctamente".to_string())
 }
@@ -1873,9 +1873,15 @@ fn enumerar_impresoras_sistema() -> Result<Vec<String>, String> {
         Ok(impresoras)
     }
 
+    // Linux/macOS: colas CUPS. Las térmicas de red (tcp://) o por dispositivo
+    // (file://) no se enumeran: se escriben a mano en Configuración.
     #[cfg(not(target_os = "windows"))]
     {
-        Ok(vec!["No disponible en este sistema".to_string()])
+        let colas = printing::destino::listar_colas_cups();
+        if colas.is_empty() {
+            return Ok(vec!["No disponible en este sistema".to_string()]);
+        }
+        Ok(colas)
     }
 }
 
@@ -1884,6 +1890,29 @@ pub fn listar_impresoras() -> Result<Vec<String>, String> {
     enumerar_impresoras_sistema()
 }
 
+/// Imprime una página de prueba en el destino indicado (nombre de impresora,
+/// `tcp://ip:9100`, `cups://cola` o `file:///dev/usb/lp0`) antes de guardarlo.
+#[tauri::command]
+pub fn probar_impresora(destino: String) -> Result<String, String> {
+    let tipo = match printing::destino::DestinoImpresora::parse(&destino)? {
+        printing::destino::DestinoImpresora::Sistema(_) => "sistema",
+        printing::destino::DestinoImpresora::Red { .. } => "red (TCP)",
+        printing::destino::DestinoImpresora::Cups(_) => "CUPS",
+        printing::destino::DestinoImpresora::Archivo(_) => "dispositivo",
+    };
+    let mut datos: Vec<u8> = vec![0x1B, 0x40, 0x1B, 0x61, 0x01, 0x1B, 0x45, 0x01];
+    datos.extend_from_slice(b"PRUEBA DE IMPRESION\n");
+    datos.extend_from_slice(&[0x1B, 0x45, 0x00]);
+    datos.extend_from_slice(format!("{}\n", destino.trim()).as_bytes());
+    datos.extend_from_slice(format!("Backend: {}\n", tipo).as_bytes());
+    datos.extend_from_slice(
+        format!("{}\n", chrono::Local::now().format("%d/%m/%Y %H:%M:%S")).as_bytes(),
+    );
+    datos.extend_from_slice(&[0x1B, 0x64, 0x04, 0x1D, 0x56, 0x00]);
+    printing::imprimir_raw(&destino, &datos)?;
+    Ok(format!("Página de prueba enviada ({})", tipo))
+}
+
 /// Lee impresoras desde cache en config. Si no hay cache, enumera y guarda.
 #[tauri::command]
 pub fn listar_impresoras_cached(db: State<Database>) -> Result<Vec<String>, String> {
@@ -2767,6 +2796,6 @@ pub fn imprimir_ticket_nc(db: State<Database>, nc_id: i64) -> Result<String, Str
         return Err("No hay impresora térmica configurada. Use 'Imprimir PDF' en su lugar.".to_string());
     }
 
-    crate::printing::imprimir_raw_windows(&impresora, &ticket_data)?;
+    crate::printing::imprimir_raw(&impresora, &ticket_data)?;
     Ok("Nota de crédito impresa correctamente".to_string())
 }
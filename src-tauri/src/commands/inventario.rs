@@ -17,6 +17,8 @@ pub struct MovimientoInventario {
     pub motivo: Option<String>,
     pub usuario: Option<String>,
     pub created_at: Option<String>,
+    pub establecimiento_id: Option<i64>,
+    pub establecimiento_nombre: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -29,8 +31,9 @@ pub struct ResumenInventario {
 }
 
 /// Registra un movimiento de inventario (ENTRADA, SALIDA, AJUSTE)
-/// Actualiza stock_actual automáticamente
+/// Actualiza el stock del establecimiento (por defecto el de la terminal) y el global
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn registrar_movimiento(
     db: State<Database>,
     producto_id: i64,
@@ -39,17 +42,23 @@ pub fn registrar_movimiento(
     motivo: Option<String>,
     costo_unitario: Option<f64>,
     usuario: Option<String>,
+    establecimiento_id: Option<i64>,
 ) -> Result<MovimientoInventario, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
-    // Obtener stock actual
-    let stock_actual: f64 = conn
-        .query_row(
-            "SELECT stock_actual FROM productos WHERE id = ?1",
-            rusqlite::params![producto_id],
-            |row| row.get(0),
-        )
-        .map_err(|_| "Producto no encontrado".to_string())?;
+    conn.query_row(
+        "SELECT id FROM productos WHERE id = ?1",
+        rusqlite::params![producto_id],
+        |row| row.get::<_, i64>(0),
+    )
+    .map_err(|_| "Producto no encontrado".to_string())?;
+    let est_id = match establecimiento_id {
+        Some(id) => id,
+        None => crate::commands::stock::establecimiento_terminal(&conn)?,
+    };
+
+    // Stock actual del establecimiento
+    let stock_actual = crate::commands::stock::stock_en(&conn, producto_id, est_id);
 
     // Calcular nuevo stock según tipo
     let stock_nuevo = match tipo.as_str() {
@@ -68,8 +77,8 @@ pub fn registrar_movimiento(
 
     // Registrar movimiento
     conn.execute(
-        "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, motivo, usuario)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, motivo, usuario, establecimiento_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
         rusqlite::params![
             producto_id,
             tipo,
@@ -79,18 +88,15 @@ pub fn registrar_movimiento(
             costo_unitario,
             motivo,
             usuario,
+            est_id,
         ],
     )
     .map_err(|e| e.to_string())?;
 
     let mov_id = conn.last_insert_rowid();
 
-    // Actualizar stock del producto
-    conn.execute(
-        "UPDATE productos SET stock_actual = ?1, updated_at = datetime('now','localtime') WHERE id = ?2",
-        rusqlite::params![stock_nuevo, producto_id],
-    )
-    .map_err(|e| e.to_string())?;
+    // Actualizar stock del establecimiento (el global se recalcula)
+    crate::commands::stock::fijar(&conn, producto_id, est_id, stock_nuevo)?;
 
     Ok(MovimientoInventario {
         id: Some(mov_id),
@@ -106,6 +112,8 @@ pub fn registrar_movimiento(
         motivo,
         usuario,
         created_at: None,
+        establecimiento_id: Some(est_id),
+        establecimiento_nombre: None,
     })
 }
 
@@ -118,6 +126,7 @@ pub fn listar_movimientos(
     fecha_fin: Option<String>,
     tipo: Option<String>,
     limite: Option<i64>,
+    establecimiento_id: Option<i64>,
 ) -> Result<Vec<MovimientoInventario>, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
@@ -147,9 +156,10 @@ pub fn listar_movimientos(
                         END
                     )
                 END as motivo,
-                m.usuario, m.created_at
+                m.usuario, m.created_at, m.establecimiento_id, est.nombre
          FROM movimientos_inventario m
          JOIN productos p ON m.producto_id = p.id
+         LEFT JOIN establecimientos est ON m.establecimiento_id = est.id
          LEFT JOIN ventas v ON m.referencia_id = v.id
               AND m.tipo NOT LIKE 'COMPRA%'
               AND m.tipo NOT IN ('ENTRADA','SALIDA','AJUSTE','AJUSTE_POSITIVO','AJUSTE_NEGATIVO','INGRESO_COMPRA')
@@ -182,6 +192,11 @@ pub fn listar_movimientos(
         params.push(Box::new(t.clone()));
     }
 
+    if let Some(eid) = establecimiento_id {
+        sql.push_str(&format!(" AND m.establecimiento_id = ?{}", params.len() + 1));
+        params.push(Box::new(eid));
+    }
+
     sql.push_str(" ORDER BY m.created_at DESC");
 
     let lim = limite.unwrap_or(200);
@@ -207,6 +222,8 @@ pub fn listar_movimientos(
                 motivo: row.get(10)?,
                 usuario: row.get(11)?,
                 created_at: row.get(12)?,
+                establecimiento_id: row.get(13)?,
+                establecimiento_nombre: row.get(14)?,
             })
         })
         .map_err(|e| e.to_string())?
@@ -223,23 +240,34 @@ pub fn exportar_kardex_csv(
     fecha_desde: String,
     fecha_hasta: String,
     producto_id: Option<i64>,
+    establecimiento_id: Option<i64>,
 ) -> Result<String, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
-    let where_producto = if producto_id.is_some() { "AND m.producto_id = ?3" } else { "" };
-    let sql = format!(
+    let mut sql = String::from(
         "SELECT m.created_at, COALESCE(p.codigo, ''), p.nombre, m.tipo, m.cantidad, m.stock_anterior, m.stock_nuevo,
-                COALESCE(m.costo_unitario, 0), COALESCE(m.referencia_id, 0), COALESCE(m.usuario, '')
+                COALESCE(m.costo_unitario, 0), COALESCE(m.referencia_id, 0), COALESCE(m.usuario, ''),
+                COALESCE(est.nombre, '')
          FROM movimientos_inventario m
          JOIN productos p ON m.producto_id = p.id
-         WHERE date(m.created_at) >= date(?1) AND date(m.created_at) <= date(?2) {}
-         ORDER BY m.created_at DESC",
-        where_producto
+         LEFT JOIN establecimientos est ON m.establecimiento_id = est.id
+         WHERE date(m.created_at) >= date(?1) AND date(m.created_at) <= date(?2)"
     );
+    let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(fecha_desde), Box::new(fecha_hasta)];
+    if let Some(pid) = producto_id {
+        sql.push_str(&format!(" AND m.producto_id = ?{}", params.len() + 1));
+        params.push(Box::new(pid));
+    }
+    if let Some(eid) = establecimiento_id {
+        sql.push_str(&format!(" AND m.establecimiento_id = ?{}", params.len() + 1));
+        params.push(Box::new(eid));
+    }
+    sql.push_str(" ORDER BY m.created_at DESC");
+    let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
     let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
 
-    let mut csv = String::from("Fecha,Codigo,Producto,Tipo,Cantidad,Stock Anterior,Stock Nuevo,Costo Unitario,Referencia ID,Usuario\n");
+    let mut csv = String::from("Fecha,Codigo,Producto,Tipo,Cantidad,Stock Anterior,Stock Nuevo,Costo Unitario,Referencia ID,Usuario,Establecimiento\n");
 
     let escape = |s: &str| -> String {
         if s.contains(',') || s.contains('"') || s.contains('\n') {
@@ -250,7 +278,7 @@ pub fn exportar_kardex_csv(
     };
 
     let row_handler = |row: &rusqlite::Row| -> rusqlite::Result<String> {
-        Ok(format!("{},{},{},{},{},{},{},{:.2},{},{}\n",
+        Ok(format!("{},{},{},{},{},{},{},{:.2},{},{},{}\n",
             row.get::<_, String>(0)?,
             escape(&row.get::<_, String>(1)?),
             escape(&row.get::<_, String>(2)?),
@@ -260,25 +288,22 @@ pub fn exportar_kardex_csv(
             row.get::<_, f64>(6)?,
             row.get::<_, f64>(7)?,
             row.get::<_, i64>(8)?,
-            escape(&row.get::<_, String>(9)?)
+            escape(&row.get::<_, String>(9)?),
+            escape(&row.get::<_, String>(10)?)
         ))
     };
 
-    let rows = if let Some(pid) = producto_id {
-        stmt.query_map(rusqlite::params![fecha_desde, fecha_hasta, pid], row_handler).map_err(|e| e.to_string())?
-            .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?
-    } else {
-        stmt.query_map(rusqlite::params![fecha_desde, fecha_hasta], row_handler).map_err(|e| e.to_string())?
-            .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?
-    };
+    let rows = stmt.query_map(param_refs.as_slice(), row_handler).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
 
     for r in rows { csv.push_str(&r); }
     Ok(csv)
 }
 
-/// Resumen general de inventario para el dashboard
+/// Resumen general de inventario para el dashboard.
+/// Con `establecimiento_id` los movimientos y el valor son los de esa ubicación.
 #[tauri::command]
-pub fn resumen_inventario(db: State<Database>) -> Result<ResumenInventario, String> {
+pub fn resumen_inventario(db: State<Database>, establecimiento_id: Option<i64>) -> Result<ResumenInventario, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
     let total_productos: i64 = conn
@@ -289,11 +314,13 @@ pub fn resumen_inventario(db: State<Database>) -> Result<ResumenInventario, Stri
         )
         .unwrap_or(0);
 
+    // ?1 NULL = todos los establecimientos
     let total_entradas_mes: f64 = conn
         .query_row(
             "SELECT COALESCE(SUM(cantidad), 0) FROM movimientos_inventario
-             WHERE tipo = 'ENTRADA' AND date(created_at) >= date('now', 'start of month', 'localtime')",
-            [],
+             WHERE tipo = 'ENTRADA' AND date(created_at) >= date('now', 'start of month', 'localtime')
+               AND (?1 IS NULL OR establecimiento_id = ?1)",
+            rusqlite::params![establecimiento_id],
             |row| row.get(0),
         )
         .unwrap_or(0.0);
@@ -301,8 +328,9 @@ pub fn resumen_inventario(db: State<Database>) -> Result<ResumenInventario, Stri
     let total_salidas_mes: f64 = conn
         .query_row(
             "SELECT COALESCE(SUM(ABS(cantidad)), 0) FROM movimientos_inventario
-             WHERE tipo IN ('SALIDA', 'VENTA') AND date(created_at) >= date('now', 'start of month', 'localtime')",
-            [],
+             WHERE tipo IN ('SALIDA', 'VENTA') AND date(created_at) >= date('now', 'start of month', 'localtime')
+               AND (?1 IS NULL OR establecimiento_id = ?1)",
+            rusqlite::params![establecimiento_id],
             |row| row.get(0),
         )
         .unwrap_or(0.0);
@@ -310,19 +338,28 @@ pub fn resumen_inventario(db: State<Database>) -> Result<ResumenInventario, Stri
     let total_ajustes_mes: i64 = conn
         .query_row(
             "SELECT COUNT(*) FROM movimientos_inventario
-             WHERE tipo = 'AJUSTE' AND date(created_at) >= date('now', 'start of month', 'localtime')",
-            [],
+             WHERE tipo = 'AJUSTE' AND date(created_at) >= date('now', 'start of month', 'localtime')
+               AND (?1 IS NULL OR establecimiento_id = ?1)",
+            rusqlite::params![establecimiento_id],
             |row| row.get(0),
         )
         .unwrap_or(0);
 
-    let valor_inventario: f64 = conn
-        .query_row(
+    let valor_inventario: f64 = match establecimiento_id {
+        Some(eid) => conn.query_row(
+            "SELECT COALESCE(SUM(se.stock_actual * p.precio_costo), 0)
+             FROM stock_establecimiento se JOIN productos p ON p.id = se.producto_id
+             WHERE se.establecimiento_id = ?1 AND p.activo = 1 AND p.es_servicio = 0",
+            rusqlite::params![eid],
+            |row| row.get(0),
+        ),
+        None => conn.query_row(
             "SELECT COALESCE(SUM(stock_actual * precio_costo), 0) FROM productos WHERE activo = 1 AND es_servicio = 0",
             [],
             |row| row.get(0),
-        )
-        .unwrap_or(0.0);
+        ),
+    }
+    .unwrap_or(0.0);
 
     Ok(ResumenInventario {
         total_productos,
@@ -345,16 +382,22 @@ pub struct ProductoStockNegativo {
 }
 
 /// Lista los productos con stock_actual < 0, para corregirlos en lote.
+/// Con `establecimiento_id` se mira el stock de esa ubicación.
 #[tauri::command]
-pub fn listar_productos_stock_negativo(db: State<Database>) -> Result<Vec<ProductoStockNegativo>, String> {
+pub fn listar_productos_stock_negativo(
+    db: State<Database>,
+    establecimiento_id: Option<i64>,
+) -> Result<Vec<ProductoStockNegativo>, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
     let mut stmt = conn.prepare(
-        "SELECT id, codigo, nombre, stock_actual, COALESCE(precio_costo, 0)
-         FROM productos
-         WHERE stock_actual < 0 AND COALESCE(es_servicio, 0) = 0
-         ORDER BY stock_actual ASC"
+        "SELECT p.id, p.codigo, p.nombre, COALESCE(se.stock_actual, p.stock_actual) AS stock, COALESCE(p.precio_costo, 0)
+         FROM productos p
+         LEFT JOIN stock_establecimiento se ON se.producto_id = p.id AND se.establecimiento_id = ?1
+         WHERE (?1 IS NULL OR se.id IS NOT NULL)
+           AND COALESCE(se.stock_actual, p.stock_actual) < 0 AND COALESCE(p.es_servicio, 0) = 0
+         ORDER BY stock ASC"
     ).map_err(|e| e.to_string())?;
-    let rows = stmt.query_map([], |r| {
+    let rows = stmt.query_map(rusqlite::params![establecimiento_id], |r| {
         Ok(ProductoStockNegativo {
             id: r.get(0)?,
             codigo: r.get(1).ok(),
@@ -373,7 +416,8 @@ pub struct AjusteLoteItem {
     pub stock_real: f64,   // el stock físico real que debe quedar
 }
 
-/// Ajusta en lote el stock de varios productos al valor real contado.
+/// Ajusta en lote el stock de varios productos al valor real contado, en el
+/// establecimiento indicado (por defecto el de la terminal).
 /// Registra un movimiento AJUSTE por cada uno (auditable) con el motivo dado.
 #[tauri::command]
 pub fn ajustar_stock_lote(
@@ -381,37 +425,39 @@ pub fn ajustar_stock_lote(
     items: Vec<AjusteLoteItem>,
     motivo: String,
     usuario: Option<String>,
+    establecimiento_id: Option<i64>,
 ) -> Result<usize, String> {
     if items.is_empty() {
         return Err("No hay productos para ajustar".into());
     }
     let motivo = if motivo.trim().is_empty() { "Corrección de stock negativo".to_string() } else { motivo.trim().to_string() };
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let est_id = match establecimiento_id {
+        Some(id) => id,
+        None => crate::commands::stock::establecimiento_terminal(&conn)?,
+    };
     let mut ajustados = 0;
 
     for it in &items {
-        let stock_actual: f64 = match conn.query_row(
-            "SELECT stock_actual FROM productos WHERE id = ?1",
-            rusqlite::params![it.producto_id], |r| r.get(0),
-        ) {
-            Ok(s) => s,
-            Err(_) => continue, // producto no existe, saltar
-        };
+        let existe = conn.query_row(
+            "SELECT 1 FROM productos WHERE id = ?1",
+            rusqlite::params![it.producto_id], |r| r.get::<_, i64>(0),
+        ).is_ok();
+        if !existe { continue; } // producto no existe, saltar
+        let stock_actual = crate::commands::stock::stock_en(&conn, it.producto_id, est_id);
         let diferencia = it.stock_real - stock_actual;
         if diferencia.abs() < 0.0001 { continue; } // sin cambio
 
         // Registrar movimiento AJUSTE (auditable)
         conn.execute(
-            "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, motivo, usuario)
-             VALUES (?1, 'AJUSTE', ?2, ?3, ?4, ?5, ?6)",
-            rusqlite::params![it.producto_id, diferencia, stock_actual, it.stock_real, motivo, usuario],
+            "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, motivo, usuario, establecimiento_id)
+             VALUES (?1, 'AJUSTE', ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![it.producto_id, diferencia, stock_actual, it.stock_real, motivo, usuario, est_id],
         ).map_err(|e| format!("Error registrando movimiento: {}", e))?;
 
-        // Actualizar stock
-        conn.execute(
-            "UPDATE productos SET stock_actual = ?1, updated_at = datetime('now','localtime') WHERE id = ?2",
-            rusqlite::params![it.stock_real, it.producto_id],
-        ).map_err(|e| format!("Error actualizando stock: {}", e))?;
+        // Actualizar stock del establecimiento (el global se recalcula)
+        crate::commands::stock::fijar(&conn, it.producto_id, est_id, it.stock_real)
+            .map_err(|e| format!("Error actualizando stock: {}", e))?;
 
         ajustados += 1;
     }
@@ -14,6 +14,7 @@ pub mod exportar;
 pub mod sri;
 pub mod listas_precios;
 pub mod inventario;
+pub mod stock;
 pub mod demo;
 pub mod establecimientos;
 pub mod transferencias;
@@ -22,6 +23,15 @@ pub mod cotizacion_pdf;
 pub mod nota_venta_pdf;
 pub mod proveedores;
 pub mod compras;
+pub mod ordenes_compra;
+pub mod orden_compra_pdf;
+pub mod reposicion;
+pub mod caducidad;
+pub mod costeo;
+pub mod garantias;
+pub mod costos_proveedor;
+pub mod bodega;
+pub mod picking_pdf;
 pub mod cuentas_pagar;
 pub mod servicio_tecnico;
 pub mod servicio_tecnico_catalogo;
@@ -29,6 +39,8 @@ pub mod servicio_tecnico_abonos;
 pub mod servicio_tecnico_items;
 pub mod servicio_tecnico_reportes;
 pub mod combos;
+pub mod recetas;
+pub mod conteos;
 pub mod verificacion;
 pub mod retenciones;
 pub mod contabilidad;
@@ -0,0 +1,449 @@
+use crate::db::{Database, SesionState};
+use crate::models::OrdenCompra;
+use genpdf::elements::{Break, LinearLayout, PaddedElement, Paragraph, StyledElement, TableLayout};
+use genpdf::style::{Color, Style};
+use genpdf::{Alignment, Document, Element, Margins, SimplePageDecorator};
+use tauri::State;
+
+// ============================================
+// HELPERS
+// ============================================
+
+fn p_aligned(text: &str, style: Style, align: Alignment) -> impl Element {
+    Paragraph::new(text).aligned(align).styled(style)
+}
+
+fn format_cantidad(cant: f64) -> String {
+    if cant == cant.floor() {
+        format!("{:.0}", cant)
+    } else {
+        format!("{:.2}", cant)
+    }
+}
+
+fn format_dinero(val: f64) -> String {
+    format!("{:.2}", val)
+}
+
+/// Paragraph con padding (izquierdo 3mm) para celdas de tabla
+fn pp(text: &str, style: Style) -> PaddedElement<StyledElement<Paragraph>> {
+    Paragraph::new(text)
+        .styled(style)
+        .padded(Margins::trbl(1, 1, 1, 3))
+}
+
+/// Paragraph alineado a la derecha con padding
+fn pp_right(text: &str, style: Style) -> impl Element {
+    Paragraph::new(text)
+        .aligned(Alignment::Right)
+        .styled(style)
+        .padded(Margins::trbl(1, 3, 1, 1))
+}
+
+/// Paragraph con padding + alineado al centro
+fn pp_center(text: &str, style: Style) -> impl Element {
+    Paragraph::new(text)
+        .aligned(Alignment::Center)
+        .styled(style)
+        .padded(Margins::trbl(1, 1, 1, 1))
+}
+
+/// Orden + datos del proveedor + config, leidos con un solo lock.
+fn cargar_orden(
+    db: &Database,
+    orden_id: i64,
+) -> Result<(OrdenCompra, String, String, std::collections::HashMap<String, String>), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let orden = crate::commands::ordenes_compra::obtener(&conn, orden_id)?;
+    let (direccion, telefono): (String, String) = conn
+        .query_row(
+            "SELECT COALESCE(direccion,''), COALESCE(telefono,'') FROM proveedores WHERE id = ?1",
+            rusqlite::params![orden.proveedor_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .unwrap_or_default();
+    let mut cfg_stmt = conn
+        .prepare("SELECT key, value FROM config")
+        .map_err(|e| e.to_string())?;
+    let config: std::collections::HashMap<String, String> = cfg_stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<std::collections::HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok((orden, direccion, telefono, config))
+}
+
+/// PDF de la orden de compra (para imprimir o adjuntar al email).
+pub fn generar_pdf_bytes(db: &Database, orden_id: i64) -> Result<(String, Vec<u8>), String> {
+    let (orden, prov_direccion, prov_telefono, config) = cargar_orden(db, orden_id)?;
+    if orden.estado == "BORRADOR" || orden.estado == "ANULADA" {
+        return Err(format!("La orden {} está {}: apruébela antes de enviarla", orden.numero, orden.estado));
+    }
+    let bytes = generar_pdf_orden(&orden, &prov_direccion, &prov_telefono, &config)?;
+    Ok((orden.numero, bytes))
+}
+
+// ============================================
+// COMMAND: generar_orden_compra_pdf
+// ============================================
+
+#[tauri::command]
+pub fn generar_orden_compra_pdf(db: State<Database>, orden_id: i64) -> Result<String, String> {
+    let (numero, pdf_bytes) = generar_pdf_bytes(&db, orden_id)?;
+
+    // Guardar en temp (nombre único + escritura robusta ante os error 32)
+    let temp_dir = std::env::temp_dir();
+    let pdf_path = crate::utils::escribir_pdf_robusto(&temp_dir, "OrdenCompra", &numero, &pdf_bytes)?;
+
+    // Abrir con visor del sistema
+    #[cfg(target_os = "windows")]
+    {
+        crate::utils::silent_command("cmd")
+            .args(["/C", "start", "", &pdf_path.to_string_lossy()])
+            .spawn()
+            .map_err(|e| format!("Error abriendo PDF: {}", e))?;
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        std::process::Command::new("xdg-open")
+            .arg(&pdf_path.to_string_lossy().to_string())
+            .spawn()
+            .map_err(|e| format!("Error abriendo PDF: {}", e))?;
+    }
+
+    Ok(pdf_path.to_string_lossy().to_string())
+}
+
+// ============================================
+// COMMAND: enviar_orden_compra_email
+// ============================================
+
+/// Envía la orden al proveedor por el servicio de email (mismo esquema que
+/// el RIDE). Sin `email` se usa el del proveedor.
+#[tauri::command]
+pub async fn enviar_orden_compra_email(
+    db: State<'_, Database>,
+    sesion: State<'_, SesionState>,
+    orden_id: i64,
+    email: Option<String>,
+) -> Result<String, String> {
+    crate::db::usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let (email_url, email_api_key, nombre_negocio, destinatario) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let get = |key: &str| -> String {
+            conn.query_row("SELECT value FROM config WHERE key = ?1", rusqlite::params![key], |r| r.get(0))
+                .unwrap_or_default()
+        };
+        if get("demo_activo") == "1" {
+            return Err("El envío de emails está deshabilitado en modo demo".to_string());
+        }
+        let email_url = get("email_service_url");
+        let email_api_key = get("email_service_api_key");
+        if email_url.is_empty() || email_api_key.is_empty() {
+            return Err("Servicio de email no configurado.".to_string());
+        }
+        let email_proveedor: Option<String> = conn
+            .query_row(
+                "SELECT p.email FROM ordenes_compra o JOIN proveedores p ON p.id = o.proveedor_id WHERE o.id = ?1",
+                rusqlite::params![orden_id],
+                |r| r.get(0),
+            )
+            .map_err(|_| "Orden de compra no encontrada".to_string())?;
+        let destinatario = email
+            .map(|e| e.trim().to_string())
+            .filter(|e| !e.is_empty())
+            .or(email_proveedor.filter(|e| !e.trim().is_empty()))
+            .ok_or_else(|| "El proveedor no tiene email: ingrese uno de destino".to_string())?;
+        (email_url, email_api_key, get("nombre_negocio"), destinatario)
+    };
+
+    let (numero, pdf) = generar_pdf_bytes(db.inner(), orden_id)?;
+    let pdf_b64 = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &pdf);
+    let adjuntos = vec![serde_json::json!({
+        "nombre": format!("{}.pdf", numero),
+        "contenido_base64": pdf_b64, "tipo": "application/pdf"
+    })];
+    let cuerpo_html = format!(
+        r#"<div style="font-family:Arial,sans-serif;max-width:600px;margin:0 auto"><h2 style="color:#1e40af">{}</h2><p>Estimado proveedor,</p><p>Adjuntamos nuestra orden de compra <strong>{}</strong>. Por favor confirme la recepción y la fecha de entrega.</p><p style="color:#64748b;font-size:12px">Mensaje automático generado por Clouget POS.</p></div>"#,
+        nombre_negocio, numero
+    );
+    let asunto = format!("Orden de Compra {} - {}", numero, nombre_negocio);
+
+    let cuenta_oauth = crate::commands::oauth_email::obtener_cuenta_oauth_activa(db.inner());
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| format!("Error HTTP: {}", e))?;
+    let (endpoint, body) = if let Some(ref cuenta) = cuenta_oauth {
+        let from_name = cuenta.from_name.clone().unwrap_or_else(|| nombre_negocio.clone());
+        (format!("{}/enviar-email-oauth", email_url), serde_json::json!({
+            "refresh_token": cuenta.refresh_token, "email_remitente": cuenta.email, "from_name": from_name,
+            "destinatario": destinatario, "asunto": asunto, "cuerpo_html": cuerpo_html, "adjuntos": adjuntos }))
+    } else {
+        (format!("{}/enviar-email", email_url), serde_json::json!({
+            "destinatario": destinatario, "asunto": asunto, "cuerpo_html": cuerpo_html, "adjuntos": adjuntos }))
+    };
+    let resp = client
+        .post(&endpoint)
+        .header("Authorization", format!("Bearer {}", email_api_key))
+        .header("Content-Type", "application/json")
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("No se pudo conectar al servicio de email: {}", e))?;
+    if !resp.status().is_success() {
+        let err_body = resp.text().await.unwrap_or_default();
+        return Err(format!("Error enviando email: {}", err_body));
+    }
+
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE ordenes_compra SET email_enviado_a = ?1, fecha_envio = datetime('now','localtime') WHERE id = ?2",
+        rusqlite::params![destinatario, orden_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(format!("Orden enviada a {}", destinatario))
+}
+
+// ============================================
+// PDF GENERATION
+// ============================================
+
+fn generar_pdf_orden(
+    orden: &OrdenCompra,
+    prov_direccion: &str,
+    prov_telefono: &str,
+    config: &std::collections::HashMap<String, String>,
+) -> Result<Vec<u8>, String> {
+    let fonts_dir = crate::utils::obtener_ruta_fuentes();
+
+    let font_family = genpdf::fonts::from_files(
+        fonts_dir.to_str().unwrap_or("fonts"),
+        "LiberationSans",
+        None,
+    )
+    .map_err(|e| {
+        format!(
+            "Error cargando fuentes: {}. Asegurese de que los archivos LiberationSans-*.ttf estan en src-tauri/fonts/",
+            e
+        )
+    })?;
+
+    let mut doc = Document::new(font_family);
+    doc.set_title("Orden de Compra");
+
+    let mut decorator = SimplePageDecorator::new();
+    decorator.set_margins(Margins::trbl(15, 15, 15, 15));
+    doc.set_page_decorator(decorator);
+
+    // Estilos
+    let s_normal = Style::new().with_font_size(9);
+    let s_bold = Style::new().with_font_size(9).bold();
+    let s_small = Style::new().with_font_size(8);
+    let s_small_bold = Style::new().with_font_size(8).bold();
+    let s_title = Style::new().with_font_size(14).bold();
+    let s_doc_type = Style::new().with_font_size(16).bold();
+    let s_doc_no = Style::new().with_font_size(11);
+    let s_ruc = Style::new().with_font_size(10).bold();
+    let s_total_bold = Style::new().with_font_size(11).bold();
+    let s_pie = Style::new().with_font_size(7).with_color(Color::Greyscale(128));
+    let s_note = Style::new().with_font_size(8).italic();
+    let s_section_title = Style::new().with_font_size(9).bold().with_color(Color::Greyscale(60));
+
+    // --- Datos del config ---
+    let nombre_negocio = config
+        .get("nombre_negocio")
+        .map(|s| s.as_str())
+        .unwrap_or("MI NEGOCIO");
+    let ruc = config.get("ruc").map(|s| s.as_str()).unwrap_or("");
+    let direccion_neg = config.get("direccion").map(|s| s.as_str()).unwrap_or("");
+    let telefono_neg = config.get("telefono").map(|s| s.as_str()).unwrap_or("");
+
+    // ===================================================================
+    // SECCION 1: ENCABEZADO (2 columnas)
+    // ===================================================================
+    let mut header_table = TableLayout::new(vec![1, 1]);
+    header_table.set_cell_decorator(genpdf::elements::FrameCellDecorator::new(true, true, false));
+
+    let mut col_izq = LinearLayout::vertical();
+    col_izq.push(Break::new(0.3));
+    col_izq.push(pp(nombre_negocio, s_title));
+    col_izq.push(Break::new(0.3));
+    if !ruc.is_empty() {
+        col_izq.push(pp(&format!("RUC: {}", ruc), s_ruc));
+        col_izq.push(Break::new(0.2));
+    }
+    if !direccion_neg.is_empty() {
+        col_izq.push(pp(&format!("Direccion: {}", direccion_neg), s_normal));
+    }
+    if !telefono_neg.is_empty() {
+        col_izq.push(pp(&format!("Telefono: {}", telefono_neg), s_normal));
+    }
+    if let Some(ref est) = orden.establecimiento_nombre {
+        col_izq.push(pp(&format!("Entregar en: {}", est), s_normal));
+    }
+    col_izq.push(Break::new(0.5));
+
+    let mut col_der = LinearLayout::vertical();
+    col_der.push(Break::new(1.0));
+    col_der.push(pp_center("ORDEN DE COMPRA", s_doc_type));
+    col_der.push(Break::new(1.0));
+    col_der.push(pp(&format!("No. {}", orden.numero), s_doc_no));
+    col_der.push(Break::new(0.5));
+    col_der.push(pp(&format!("Fecha: {}", orden.fecha_envio.as_deref().unwrap_or(&orden.fecha_creacion)), s_bold));
+    if let Some(ref f) = orden.fecha_entrega_esperada {
+        col_der.push(Break::new(0.3));
+        col_der.push(pp(&format!("Entrega esperada: {}", f), s_bold));
+    }
+    col_der.push(Break::new(1.0));
+
+    header_table
+        .row()
+        .element(col_izq.padded(Margins::trbl(2, 3, 2, 3)))
+        .element(col_der.padded(Margins::trbl(2, 3, 2, 3)))
+        .push()
+        .map_err(|e| format!("Error tabla header: {}", e))?;
+
+    doc.push(header_table);
+    doc.push(Break::new(1.5));
+
+    // ===================================================================
+    // SECCION 2: DATOS DEL PROVEEDOR
+    // ===================================================================
+    let mut prov_section = LinearLayout::vertical();
+    prov_section.push(Break::new(0.3));
+    prov_section.push(pp("PROVEEDOR", s_section_title));
+    prov_section.push(Break::new(0.5));
+
+    let mut fila1 = TableLayout::new(vec![3, 2]);
+    fila1
+        .row()
+        .element(pp(orden.proveedor_nombre.as_deref().unwrap_or(""), s_bold))
+        .element(pp(&format!("RUC: {}", orden.proveedor_ruc.as_deref().unwrap_or("")), s_normal))
+        .push()
+        .map_err(|e| format!("Error fila proveedor: {}", e))?;
+    prov_section.push(fila1);
+    if !prov_direccion.is_empty() || !prov_telefono.is_empty() {
+        let mut fila2 = TableLayout::new(vec![3, 2]);
+        fila2
+            .row()
+            .element(pp(prov_direccion, s_normal))
+            .element(pp(prov_telefono, s_normal))
+            .push()
+            .map_err(|e| format!("Error fila proveedor: {}", e))?;
+        prov_section.push(fila2);
+    }
+    prov_section.push(Break::new(0.3));
+    doc.push(prov_section.padded(Margins::trbl(3, 2, 3, 2)).framed());
+    doc.push(Break::new(1.5));
+
+    // ===================================================================
+    // SECCION 3: TABLA DE PRODUCTOS
+    // ===================================================================
+    // Columnas: #, Código, Descripción, Cantidad, Unidad, P.Unitario, Subtotal
+    let mut table = TableLayout::new(vec![1, 2, 6, 2, 2, 2, 2]);
+    table.set_cell_decorator(genpdf::elements::FrameCellDecorator::new(true, true, false));
+    table
+        .row()
+        .element(pp("#", s_small_bold))
+        .element(pp("Codigo", s_small_bold))
+        .element(pp("Descripcion", s_small_bold))
+        .element(pp_right("Cant.", s_small_bold))
+        .element(pp("Unidad", s_small_bold))
+        .element(pp_right("P.Unitario", s_small_bold))
+        .element(pp_right("Subtotal", s_small_bold))
+        .push()
+        .map_err(|e| format!("Error tabla header: {}", e))?;
+
+    for (i, l) in orden.lineas.iter().enumerate() {
+        // Si se pidio por presentacion, se imprime en presentaciones
+        let (cant, unidad, precio) = match &l.presentacion_nombre {
+            Some(nombre) if l.presentacion_factor > 0.0 => (
+                l.cantidad / l.presentacion_factor,
+                nombre.clone(),
+                l.precio_unitario * l.presentacion_factor,
+            ),
+            _ => (l.cantidad, "UND".to_string(), l.precio_unitario),
+        };
+        table
+            .row()
+            .element(pp(&format!("{}", i + 1), s_small))
+            .element(pp(l.producto_codigo.as_deref().unwrap_or(""), s_small))
+            .element(pp(&l.descripcion, s_small))
+            .element(pp_right(&format_cantidad(cant), s_small))
+            .element(pp(&unidad, s_small))
+            .element(pp_right(&format_dinero(precio), s_small))
+            .element(pp_right(&format_dinero(l.subtotal), s_small))
+            .push()
+            .map_err(|e| format!("Error tabla fila: {}", e))?;
+    }
+    doc.push(table);
+    doc.push(Break::new(1.5));
+
+    // ===================================================================
+    // SECCION 4: TOTALES
+    // ===================================================================
+    let mut bottom_table = TableLayout::new(vec![10, 6]);
+    let spacer = LinearLayout::vertical();
+    let mut totales_table = TableLayout::new(vec![4, 2]);
+    totales_table.set_cell_decorator(genpdf::elements::FrameCellDecorator::new(true, true, false));
+    totales_table
+        .row()
+        .element(pp("SUBTOTAL", s_small))
+        .element(pp_right(&format_dinero(orden.subtotal), s_small))
+        .push()
+        .map_err(|e| format!("Error totales: {}", e))?;
+    totales_table
+        .row()
+        .element(pp("IVA", s_small))
+        .element(pp_right(&format_dinero(orden.iva), s_small))
+        .push()
+        .map_err(|e| format!("Error totales: {}", e))?;
+    totales_table
+        .row()
+        .element(pp("TOTAL", s_total_bold))
+        .element(pp_right(&format!("${}", format_dinero(orden.total)), s_total_bold))
+        .push()
+        .map_err(|e| format!("Error totales: {}", e))?;
+    bottom_table
+        .row()
+        .element(spacer)
+        .element(totales_table)
+        .push()
+        .map_err(|e| format!("Error bottom table: {}", e))?;
+    doc.push(bottom_table);
+    doc.push(Break::new(2.0));
+
+    // ===================================================================
+    // SECCION 5: OBSERVACIONES
+    // ===================================================================
+    if let Some(ref obs) = orden.observacion {
+        if !obs.is_empty() {
+            let mut notas = LinearLayout::vertical();
+            notas.push(pp("OBSERVACIONES", s_section_title));
+            notas.push(Break::new(0.5));
+            notas.push(pp(obs, s_note));
+            notas.push(Break::new(0.3));
+            doc.push(notas.padded(Margins::trbl(3, 3, 3, 3)).framed());
+            doc.push(Break::new(3.0));
+        }
+    }
+
+    // ===================================================================
+    // SECCION 6: PIE DE PÁGINA
+    // ===================================================================
+    let mut footer = LinearLayout::vertical();
+    if let Some(ref aprobo) = orden.usuario_aprobacion {
+        footer.push(p_aligned(&format!("Aprobada por: {}", aprobo), s_pie, Alignment::Center));
+        footer.push(Break::new(0.3));
+    }
+    footer.push(p_aligned("Generado por Clouget POS", s_pie, Alignment::Center));
+    doc.push(footer);
+
+    let mut buf = Vec::new();
+    doc.render(&mut buf)
+        .map_err(|e| format!("Error renderizando PDF: {}", e))?;
+
+    Ok(buf)
+}
@@ -0,0 +1,781 @@
+// Ordenes de compra a proveedores
+//
+// Flujo:
+//   1. crear_orden_compra (permiso `gestionar_compras`): la orden nace en
+//      BORRADOR y se puede editar libremente. Las lineas se guardan siempre en
+//      unidad base; si se pidio por presentacion (caja x12) queda el snapshot.
+//   2. aprobar_orden_compra (permiso `aprobar_ordenes_compra`): BORRADOR ->
+//      ENVIADA. Desde aqui la orden ya no se edita; se imprime o se envia por
+//      email al proveedor (orden_compra_pdf.rs). El proveedor puede confirmar
+//      la fecha de entrega (confirmar_orden_compra_proveedor).
+//   3. recibir_orden_compra: cada entrega ingresa stock al establecimiento de
+//      la orden (kardex RECEPCION_OC, PMP al precio pactado, lotes con
+//      caducidad). La orden pasa a RECIBIDA_PARCIAL y se CIERRA sola cuando
+//      todo llego; cerrar_orden_compra cierra con faltantes.
+//   4. Al importar el XML del proveedor con orden_compra_id
+//      (compras::importar_xml_compra) se hace la conciliacion de 3 vias:
+//      orden (precio) vs recibido (cantidad) vs factura. Los items de la orden
+//      no vuelven a mover stock; las diferencias quedan marcadas en la compra.
+//
+// Las funciones sobre &Connection son pub para que las use compras.rs.
+
+use crate::db::{usuario_con_permiso, Database, SesionState};
+use crate::models::{
+    DiferenciaConciliacion, LineaOrdenCompra, NuevaLineaOrdenCompra, NuevaOrdenCompra, OrdenCompra,
+    RecepcionOrdenItem,
+};
+use rusqlite::{Connection, OptionalExtension};
+use tauri::State;
+
+fn round2(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+/// OC-XXXXXXXXX (9 dig), MAX existente + 1 como en compras.
+fn proximo_numero(conn: &Connection) -> String {
+    let next: i64 = conn.query_row(
+        "SELECT COALESCE(MAX(CAST(SUBSTR(numero, 4) AS INTEGER)), 0) + 1
+         FROM ordenes_compra WHERE numero LIKE 'OC-%'",
+        [], |r| r.get(0),
+    ).unwrap_or(1);
+    format!("OC-{:09}", next)
+}
+
+/// Tolerancia de precio (%) para la conciliacion factura vs orden.
+fn tolerancia_precio(conn: &Connection) -> f64 {
+    conn.query_row(
+        "SELECT value FROM config WHERE key = 'oc_tolerancia_precio_pct'",
+        [], |r| r.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|v| v.trim().parse::<f64>().ok())
+    .unwrap_or(1.0)
+    .max(0.0)
+}
+
+/// Resuelve una presentacion activa del producto: (nombre, factor).
+fn presentacion(conn: &Connection, presentacion_id: i64) -> Result<(String, f64), String> {
+    conn.query_row(
+        "SELECT nombre, factor FROM producto_presentaciones WHERE id = ?1 AND activo = 1",
+        rusqlite::params![presentacion_id],
+        |r| Ok((r.get::<_, String>(0)?, r.get::<_, f64>(1)?)),
+    )
+    .ok()
+    .filter(|(_, f)| *f > 0.0)
+    .ok_or_else(|| "Presentación no encontrada o inactiva".to_string())
+}
+
+const SELECT_ORDEN: &str = "
+    SELECT o.id, o.numero, o.proveedor_id, p.nombre, p.ruc, p.email, o.establecimiento_id, e.nombre,
+           o.estado, o.fecha_entrega_esperada, o.subtotal, o.iva, o.total, o.observacion,
+           o.usuario_creacion, o.usuario_aprobacion, o.fecha_creacion, o.fecha_envio, o.email_enviado_a,
+           o.confirmada_proveedor, o.fecha_entrega_prometida, o.fecha_cierre, o.motivo_cierre
+    FROM ordenes_compra o
+    LEFT JOIN proveedores p ON p.id = o.proveedor_id
+    LEFT JOIN establecimientos e ON e.id = o.establecimiento_id";
+
+fn fila_orden(r: &rusqlite::Row) -> rusqlite::Result<OrdenCompra> {
+    Ok(OrdenCompra {
+        id: r.get(0)?,
+        numero: r.get(1)?,
+        proveedor_id: r.get(2)?,
+        proveedor_nombre: r.get(3)?,
+        proveedor_ruc: r.get(4)?,
+        proveedor_email: r.get(5)?,
+        establecimiento_id: r.get(6)?,
+        establecimiento_nombre: r.get(7)?,
+        estado: r.get(8)?,
+        fecha_entrega_esperada: r.get(9)?,
+        subtotal: r.get(10)?,
+        iva: r.get(11)?,
+        total: r.get(12)?,
+        observacion: r.get(13)?,
+        usuario_creacion: r.get(14)?,
+        usuario_aprobacion: r.get(15)?,
+        fecha_creacion: r.get(16)?,
+        fecha_envio: r.get(17)?,
+        email_enviado_a: r.get(18)?,
+        confirmada_proveedor: r.get::<_, i64>(19)? != 0,
+        fecha_entrega_prometida: r.get(20)?,
+        fecha_cierre: r.get(21)?,
+        motivo_cierre: r.get(22)?,
+        lineas: Vec::new(),
+    })
+}
+
+pub fn lineas(conn: &Connection, orden_id: i64) -> Result<Vec<LineaOrdenCompra>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT l.id, l.producto_id, p.codigo, l.descripcion, l.presentacion_id, l.presentacion_nombre,
+                l.presentacion_factor, l.cantidad, l.precio_unitario, l.iva_porcentaje, l.subtotal,
+                l.cantidad_recibida, l.cantidad_facturada
+         FROM orden_compra_lineas l
+         LEFT JOIN productos p ON p.id = l.producto_id
+         WHERE l.orden_id = ?1 ORDER BY l.id",
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(rusqlite::params![orden_id], |r| {
+        let cantidad: f64 = r.get(7)?;
+        let recibida: f64 = r.get(11)?;
+        Ok(LineaOrdenCompra {
+            id: r.get(0)?,
+            producto_id: r.get(1)?,
+            producto_codigo: r.get(2)?,
+            descripcion: r.get(3)?,
+            presentacion_id: r.get(4)?,
+            presentacion_nombre: r.get(5)?,
+            presentacion_factor: r.get(6)?,
+            cantidad,
+            precio_unitario: r.get(8)?,
+            iva_porcentaje: r.get(9)?,
+            subtotal: r.get(10)?,
+            cantidad_recibida: recibida,
+            cantidad_facturada: r.get(12)?,
+            cantidad_pendiente: (cantidad - recibida).max(0.0),
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+pub fn obtener(conn: &Connection, id: i64) -> Result<OrdenCompra, String> {
+    let mut orden = conn
+        .query_row(&format!("{} WHERE o.id = ?1", SELECT_ORDEN), rusqlite::params![id], fila_orden)
+        .map_err(|_| "Orden de compra no encontrada".to_string())?;
+    orden.lineas = lineas(conn, id)?;
+    Ok(orden)
+}
+
+/// Listado sin lineas (usar obtener para el detalle).
+pub fn listar(conn: &Connection, estado: Option<&str>, proveedor_id: Option<i64>) -> Result<Vec<OrdenCompra>, String> {
+    let sql = format!(
+        "{} WHERE (?1 IS NULL OR o.estado = ?1) AND (?2 IS NULL OR o.proveedor_id = ?2)
+         ORDER BY o.id DESC LIMIT 300",
+        SELECT_ORDEN
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(rusqlite::params![estado, proveedor_id], fila_orden)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+/// Inserta las lineas y actualiza los totales de la orden.
+fn guardar_lineas(conn: &Connection, orden_id: i64, nuevas: &[NuevaLineaOrdenCompra]) -> Result<(), String> {
+    if nuevas.is_empty() {
+        return Err("La orden debe tener al menos un producto".to_string());
+    }
+    let mut subtotal = 0.0;
+    let mut iva = 0.0;
+    for l in nuevas {
+        let nombre: String = conn
+            .query_row(
+                "SELECT nombre FROM productos WHERE id = ?1",
+                rusqlite::params![l.producto_id],
+                |r| r.get(0),
+            )
+            .map_err(|_| format!("Producto {} no encontrado", l.producto_id))?;
+        // Igual que en registrar_compra: por presentacion el precio se tipea
+        // por presentacion y se guarda por unidad base.
+        let (pres_id, pres_nombre, factor, cantidad, precio) =
+            match (l.presentacion_id, l.cantidad_presentacion) {
+                (Some(pid), Some(cant_pres)) => {
+                    let (pnombre, factor) = presentacion(conn, pid)?;
+                    (Some(pid), Some(pnombre), factor, cant_pres * factor, l.precio_unitario / factor)
+                }
+                _ => (None, None, 1.0, l.cantidad, l.precio_unitario),
+            };
+        if cantidad <= 0.0 {
+            return Err(format!("Cantidad inválida para {}", nombre));
+        }
+        if precio < 0.0 {
+            return Err(format!("Precio inválido para {}", nombre));
+        }
+        let linea_sub = round2(cantidad * precio);
+        subtotal += linea_sub;
+        iva += linea_sub * l.iva_porcentaje / 100.0;
+        let descripcion = l.descripcion.clone().filter(|d| !d.trim().is_empty()).unwrap_or(nombre);
+        conn.execute(
+            "INSERT INTO orden_compra_lineas
+             (orden_id, producto_id, descripcion, presentacion_id, presentacion_nombre, presentacion_factor,
+              cantidad, precio_unitario, iva_porcentaje, subtotal)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            rusqlite::params![
+                orden_id, l.producto_id, descripcion, pres_id, pres_nombre, factor,
+                cantidad, precio, l.iva_porcentaje, linea_sub
+            ],
+        ).map_err(|e| e.to_string())?;
+    }
+    let subtotal = round2(subtotal);
+    let iva = round2(iva);
+    conn.execute(
+        "UPDATE ordenes_compra SET subtotal = ?1, iva = ?2, total = ?3 WHERE id = ?4",
+        rusqlite::params![subtotal, iva, round2(subtotal + iva), orden_id],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn validar_cabecera(conn: &Connection, orden: &NuevaOrdenCompra) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT id FROM proveedores WHERE id = ?1 AND activo = 1",
+        rusqlite::params![orden.proveedor_id],
+        |r| r.get::<_, i64>(0),
+    )
+    .map_err(|_| "Proveedor no encontrado o inactivo".to_string())?;
+    match orden.establecimiento_id {
+        Some(eid) => conn
+            .query_row("SELECT id FROM establecimientos WHERE id = ?1", rusqlite::params![eid], |r| r.get(0))
+            .map_err(|_| "Establecimiento no encontrado".to_string()),
+        None => crate::commands::stock::establecimiento_terminal(conn),
+    }
+}
+
+/// Crea la orden en BORRADOR. Retorna el id.
+pub fn crear(conn: &Connection, orden: &NuevaOrdenCompra, usuario: &str) -> Result<i64, String> {
+    let est_id = validar_cabecera(conn, orden)?;
+    let numero = proximo_numero(conn);
+    conn.execute(
+        "INSERT INTO ordenes_compra (numero, proveedor_id, establecimiento_id, fecha_entrega_esperada, observacion, usuario_creacion)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![numero, orden.proveedor_id, est_id, orden.fecha_entrega_esperada, orden.observacion, usuario],
+    ).map_err(|e| e.to_string())?;
+    let id = conn.last_insert_rowid();
+    guardar_lineas(conn, id, &orden.lineas)?;
+    Ok(id)
+}
+
+/// Reemplaza cabecera y lineas de una orden en BORRADOR.
+pub fn actualizar(conn: &Connection, id: i64, orden: &NuevaOrdenCompra) -> Result<(), String> {
+    let actual = obtener(conn, id)?;
+    if actual.estado != "BORRADOR" {
+        return Err(format!("Solo se edita una orden en BORRADOR (está {})", actual.estado));
+    }
+    let est_id = validar_cabecera(conn, orden)?;
+    conn.execute(
+        "UPDATE ordenes_compra SET proveedor_id = ?1, establecimiento_id = ?2, fecha_entrega_esperada = ?3, observacion = ?4
+         WHERE id = ?5",
+        rusqlite::params![orden.proveedor_id, est_id, orden.fecha_entrega_esperada, orden.observacion, id],
+    ).map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM orden_compra_lineas WHERE orden_id = ?1", rusqlite::params![id])
+        .map_err(|e| e.to_string())?;
+    guardar_lineas(conn, id, &orden.lineas)
+}
+
+/// Registra una entrega del proveedor. Cada item ingresa stock al
+/// establecimiento de la orden. Retorna el id de la recepcion.
+pub fn recibir(
+    conn: &Connection,
+    orden_id: i64,
+    items: &[RecepcionOrdenItem],
+    observacion: Option<&str>,
+    usuario: &str,
+) -> Result<i64, String> {
+    let orden = obtener(conn, orden_id)?;
+    if orden.estado != "ENVIADA" && orden.estado != "RECIBIDA_PARCIAL" {
+        return Err(format!("No se puede recibir una orden {}", orden.estado));
+    }
+    if items.is_empty() {
+        return Err("Indique al menos un item recibido".to_string());
+    }
+    conn.execute(
+        "INSERT INTO orden_compra_recepciones (orden_id, observacion, usuario) VALUES (?1, ?2, ?3)",
+        rusqlite::params![orden_id, observacion, usuario],
+    ).map_err(|e| e.to_string())?;
+    let recepcion_id = conn.last_insert_rowid();
+    let est_id = orden.establecimiento_id;
+
+    for item in items {
+        let linea = orden.lineas.iter().find(|l| l.id == item.linea_id)
+            .ok_or_else(|| format!("La línea {} no pertenece a la orden {}", item.linea_id, orden.numero))?;
+        let cantidad = match (item.presentacion_id, item.cantidad_presentacion) {
+            (Some(pid), Some(cant_pres)) => cant_pres * presentacion(conn, pid)?.1,
+            (None, Some(cant_pres)) => cant_pres * linea.presentacion_factor,
+            _ => item.cantidad,
+        };
+        if cantidad <= 0.0 {
+            continue;
+        }
+        // Releer: la misma linea puede venir dos veces (dos lotes)
+        let (pedida, recibida): (f64, f64) = conn.query_row(
+            "SELECT cantidad, cantidad_recibida FROM orden_compra_lineas WHERE id = ?1",
+            rusqlite::params![linea.id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        ).map_err(|e| e.to_string())?;
+        let pendiente = pedida - recibida;
+        if cantidad > pendiente + 0.0001 {
+            return Err(format!(
+                "{}: se reciben {} pero solo quedan {} pendientes",
+                linea.descripcion, cantidad, pendiente.max(0.0)
+            ));
+        }
+
+        // PMP al precio pactado en la orden (misma formula que registrar_compra)
+        let precio = linea.precio_unitario;
+        let (stock_actual, costo_prev): (f64, f64) = conn
+            .query_row(
+                "SELECT stock_actual, COALESCE(costo_promedio, precio_costo) FROM productos WHERE id = ?1",
+                rusqlite::params![linea.producto_id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap_or((0.0, precio));
+        let nuevo_pmp = if stock_actual <= 0.0 || stock_actual + cantidad <= 0.0 {
+            precio
+        } else {
+            (stock_actual * costo_prev + cantidad * precio) / (stock_actual + cantidad)
+        };
+        let mov = crate::commands::stock::mover(conn, linea.producto_id, est_id, cantidad)?;
+        conn.execute(
+            "UPDATE productos SET precio_costo = ?1, costo_promedio = ?2 WHERE id = ?3",
+            rusqlite::params![precio, nuevo_pmp, linea.producto_id],
+        ).map_err(|e| e.to_string())?;
+        crate::commands::costeo::crear_capa(conn, linea.producto_id, est_id, "ORDEN_COMPRA", Some(orden_id), cantidad, precio)?;
+        conn.execute(
+            "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, motivo, establecimiento_id)
+             VALUES (?1, 'RECEPCION_OC', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                linea.producto_id, cantidad, mov.anterior, mov.nuevo, precio, orden_id, usuario,
+                format!("Recepción orden {} - {}", orden.numero, linea.descripcion), est_id
+            ],
+        ).map_err(|e| e.to_string())?;
+
+        // Lote con caducidad, igual que en registrar_compra
+        let mut lote_id: Option<i64> = None;
+        let mut lote_num: Option<String> = None;
+        let caducidad = item.lote_fecha_caducidad.clone().filter(|f| !f.trim().is_empty());
+        if let Some(fecha_cad) = &caducidad {
+            let num = item.lote_numero.clone().filter(|s| !s.trim().is_empty()).unwrap_or_else(|| {
+                format!("LOT-{}-{}", chrono::Local::now().format("%Y%m%d"), orden.numero)
+            });
+            conn.execute(
+                "INSERT INTO lotes_caducidad (producto_id, lote, fecha_caducidad, cantidad, cantidad_inicial,
+                                              observacion, fecha_elaboracion, establecimiento_id)
+                 VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6, ?7)",
+                rusqlite::params![
+                    linea.producto_id, num, fecha_cad, cantidad,
+                    format!("Orden de compra {}", orden.numero), item.lote_fecha_elaboracion, est_id
+                ],
+            ).map_err(|e| e.to_string())?;
+            lote_id = Some(conn.last_insert_rowid());
+            lote_num = Some(num);
+        }
+
+        conn.execute(
+            "UPDATE orden_compra_lineas SET cantidad_recibida = cantidad_recibida + ?1 WHERE id = ?2",
+            rusqlite::params![cantidad, linea.id],
+        ).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO orden_compra_recepcion_items (recepcion_id, linea_id, cantidad, lote_id, lote, fecha_caducidad)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![recepcion_id, linea.id, cantidad, lote_id, lote_num, caducidad],
+        ).map_err(|e| e.to_string())?;
+    }
+
+    let pendientes: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM orden_compra_lineas WHERE orden_id = ?1 AND cantidad - cantidad_recibida > 0.0001",
+        rusqlite::params![orden_id],
+        |r| r.get(0),
+    ).map_err(|e| e.to_string())?;
+    if pendientes == 0 {
+        conn.execute(
+            "UPDATE ordenes_compra SET estado = 'CERRADA', fecha_cierre = datetime('now','localtime'),
+                    motivo_cierre = 'Recibida completa'
+             WHERE id = ?1",
+            rusqlite::params![orden_id],
+        ).map_err(|e| e.to_string())?;
+    } else {
+        conn.execute(
+            "UPDATE ordenes_compra SET estado = 'RECIBIDA_PARCIAL' WHERE id = ?1",
+            rusqlite::params![orden_id],
+        ).map_err(|e| e.to_string())?;
+    }
+    Ok(recepcion_id)
+}
+
+/// Valida que la factura del proveedor se pueda conciliar contra la orden.
+pub fn validar_para_factura(conn: &Connection, orden_id: i64, proveedor_id: i64) -> Result<OrdenCompra, String> {
+    let orden = obtener(conn, orden_id)?;
+    if orden.proveedor_id != proveedor_id {
+        return Err(format!("La orden {} es de otro proveedor", orden.numero));
+    }
+    if orden.estado == "BORRADOR" || orden.estado == "ANULADA" {
+        return Err(format!("La orden {} está {}: no se puede facturar", orden.numero, orden.estado));
+    }
+    Ok(orden)
+}
+
+/// Item de la factura a conciliar. `precio_unitario` es el neto (subtotal / cantidad).
+pub struct ItemFacturaOrden {
+    pub producto_id: i64,
+    pub descripcion: String,
+    pub cantidad: f64,
+    pub precio_unitario: f64,
+}
+
+/// Conciliacion de 3 vias de una factura: precio contra la orden y cantidad
+/// contra lo recibido aun no facturado. Registra lo facturado en las lineas.
+pub fn conciliar_factura(
+    conn: &Connection,
+    orden_id: i64,
+    items: &[ItemFacturaOrden],
+) -> Result<Vec<DiferenciaConciliacion>, String> {
+    let tolerancia = tolerancia_precio(conn);
+    let mut diferencias = Vec::new();
+    for item in items {
+        let mut stmt = conn.prepare(
+            "SELECT id, cantidad, cantidad_recibida, cantidad_facturada, precio_unitario
+             FROM orden_compra_lineas WHERE orden_id = ?1 AND producto_id = ?2 ORDER BY id",
+        ).map_err(|e| e.to_string())?;
+        let filas: Vec<(i64, f64, f64, f64, f64)> = stmt
+            .query_map(rusqlite::params![orden_id, item.producto_id], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        if filas.is_empty() {
+            diferencias.push(DiferenciaConciliacion {
+                tipo: "NO_PEDIDO".to_string(),
+                producto_id: Some(item.producto_id),
+                descripcion: item.descripcion.clone(),
+                esperado: 0.0,
+                facturado: item.cantidad,
+                diferencia: item.cantidad,
+            });
+            continue;
+        }
+
+        let pedido: f64 = filas.iter().map(|f| f.1).sum();
+        let precio_orden = if pedido > 0.0 {
+            filas.iter().map(|f| f.1 * f.4).sum::<f64>() / pedido
+        } else {
+            filas[0].4
+        };
+        if precio_orden > 0.0 && ((item.precio_unitario - precio_orden).abs() / precio_orden * 100.0) > tolerancia {
+            diferencias.push(DiferenciaConciliacion {
+                tipo: "PRECIO".to_string(),
+                producto_id: Some(item.producto_id),
+                descripcion: item.descripcion.clone(),
+                esperado: round2(precio_orden),
+                facturado: round2(item.precio_unitario),
+                diferencia: round2(item.precio_unitario - precio_orden),
+            });
+        }
+
+        let por_facturar: f64 = filas.iter().map(|f| (f.2 - f.3).max(0.0)).sum();
+        if (item.cantidad - por_facturar).abs() > 0.0001 {
+            diferencias.push(DiferenciaConciliacion {
+                tipo: "CANTIDAD".to_string(),
+                producto_id: Some(item.producto_id),
+                descripcion: item.descripcion.clone(),
+                esperado: por_facturar,
+                facturado: item.cantidad,
+                diferencia: item.cantidad - por_facturar,
+            });
+        }
+
+        // Repartir lo facturado entre las lineas del producto; el exceso
+        // queda en la ultima para que la diferencia se siga viendo.
+        let mut restante = item.cantidad;
+        for (i, (linea_id, _, recibida, facturada, _)) in filas.iter().enumerate() {
+            let asignar = if i == filas.len() - 1 {
+                restante
+            } else {
+                restante.min((recibida - facturada).max(0.0))
+            };
+            if asignar.abs() < 0.0001 {
+                continue;
+            }
+            conn.execute(
+                "UPDATE orden_compra_lineas SET cantidad_facturada = cantidad_facturada + ?1 WHERE id = ?2",
+                rusqlite::params![asignar, linea_id],
+            ).map_err(|e| e.to_string())?;
+            restante -= asignar;
+        }
+    }
+    Ok(diferencias)
+}
+
+/// Al anular una compra conciliada: devuelve lo facturado a "por facturar".
+pub fn revertir_facturado(conn: &Connection, compra_id: i64) -> Result<(), String> {
+    let orden_id: Option<i64> = conn
+        .query_row("SELECT orden_compra_id FROM compras WHERE id = ?1", rusqlite::params![compra_id], |r| r.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?
+        .flatten();
+    let Some(orden_id) = orden_id else { return Ok(()) };
+    let mut stmt = conn.prepare(
+        "SELECT producto_id, cantidad FROM compra_detalles
+         WHERE compra_id = ?1 AND recibido_en_oc = 1 AND producto_id IS NOT NULL",
+    ).map_err(|e| e.to_string())?;
+    let detalles: Vec<(i64, f64)> = stmt
+        .query_map(rusqlite::params![compra_id], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for (pid, cantidad) in detalles {
+        let mut restante = cantidad;
+        let mut stmt = conn.prepare(
+            "SELECT id, cantidad_facturada FROM orden_compra_lineas
+             WHERE orden_id = ?1 AND producto_id = ?2 AND cantidad_facturada > 0 ORDER BY id DESC",
+        ).map_err(|e| e.to_string())?;
+        let filas: Vec<(i64, f64)> = stmt
+            .query_map(rusqlite::params![orden_id, pid], |r| Ok((r.get(0)?, r.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        for (linea_id, facturada) in filas {
+            if restante <= 0.0001 {
+                break;
+            }
+            let quitar = restante.min(facturada);
+            conn.execute(
+                "UPDATE orden_compra_lineas SET cantidad_facturada = cantidad_facturada - ?1 WHERE id = ?2",
+                rusqlite::params![quitar, linea_id],
+            ).map_err(|e| e.to_string())?;
+            restante -= quitar;
+        }
+    }
+    Ok(())
+}
+
+/// Conciliacion de toda la orden: pedido (precio) vs recibido vs facturado en
+/// las compras vigentes que la referencian.
+pub fn conciliacion(conn: &Connection, orden_id: i64) -> Result<Vec<DiferenciaConciliacion>, String> {
+    let orden = obtener(conn, orden_id)?;
+    let tolerancia = tolerancia_precio(conn);
+
+    // producto_id -> (cantidad facturada, valor facturado, descripcion)
+    let mut facturado: std::collections::BTreeMap<i64, (f64, f64, String)> = std::collections::BTreeMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT cd.producto_id, cd.cantidad, cd.subtotal, cd.descripcion
+         FROM compra_detalles cd
+         JOIN compras c ON c.id = cd.compra_id
+         WHERE c.orden_compra_id = ?1 AND c.estado != 'ANULADA' AND cd.producto_id IS NOT NULL",
+    ).map_err(|e| e.to_string())?;
+    let filas: Vec<(i64, f64, f64, String)> = stmt
+        .query_map(rusqlite::params![orden_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for (pid, cant, sub, desc) in filas {
+        let e = facturado.entry(pid).or_insert((0.0, 0.0, desc));
+        e.0 += cant;
+        e.1 += sub;
+    }
+
+    // producto_id -> (pedido, valor pedido, recibido, descripcion)
+    let mut pedido: std::collections::BTreeMap<i64, (f64, f64, f64, String)> = std::collections::BTreeMap::new();
+    for l in &orden.lineas {
+        let e = pedido.entry(l.producto_id).or_insert((0.0, 0.0, 0.0, l.descripcion.clone()));
+        e.0 += l.cantidad;
+        e.1 += l.cantidad * l.precio_unitario;
+        e.2 += l.cantidad_recibida;
+    }
+
+    let mut diferencias = Vec::new();
+    for (pid, (cant_pedida, valor_pedido, recibido, desc)) in &pedido {
+        let (cant_fact, valor_fact) = facturado.get(pid).map(|f| (f.0, f.1)).unwrap_or((0.0, 0.0));
+        if cant_fact > 0.0 && *cant_pedida > 0.0 {
+            let precio_orden = valor_pedido / cant_pedida;
+            let precio_fact = valor_fact / cant_fact;
+            if precio_orden > 0.0 && ((precio_fact - precio_orden).abs() / precio_orden * 100.0) > tolerancia {
+                diferencias.push(DiferenciaConciliacion {
+                    tipo: "PRECIO".to_string(),
+                    producto_id: Some(*pid),
+                    descripcion: desc.clone(),
+                    esperado: round2(precio_orden),
+                    facturado: round2(precio_fact),
+                    diferencia: round2(precio_fact - precio_orden),
+                });
+            }
+        }
+        if (cant_fact - recibido).abs() > 0.0001 {
+            diferencias.push(DiferenciaConciliacion {
+                tipo: "CANTIDAD".to_string(),
+                producto_id: Some(*pid),
+                descripcion: desc.clone(),
+                esperado: *recibido,
+                facturado: cant_fact,
+                diferencia: cant_fact - recibido,
+            });
+        }
+    }
+    for (pid, (cant, _, desc)) in &facturado {
+        if !pedido.contains_key(pid) {
+            diferencias.push(DiferenciaConciliacion {
+                tipo: "NO_PEDIDO".to_string(),
+                producto_id: Some(*pid),
+                descripcion: desc.clone(),
+                esperado: 0.0,
+                facturado: *cant,
+                diferencia: *cant,
+            });
+        }
+    }
+    Ok(diferencias)
+}
+
+// ─── Comandos Tauri ──────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn crear_orden_compra(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    orden: NuevaOrdenCompra,
+) -> Result<OrdenCompra, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let id = crear(&tx, &orden, &usuario)?;
+    let creada = obtener(&tx, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(creada)
+}
+
+#[tauri::command]
+pub fn actualizar_orden_compra(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    id: i64,
+    orden: NuevaOrdenCompra,
+) -> Result<OrdenCompra, String> {
+    usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    actualizar(&tx, id, &orden)?;
+    let actualizada = obtener(&tx, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(actualizada)
+}
+
+#[tauri::command]
+pub fn listar_ordenes_compra(
+    db: State<Database>,
+    estado: Option<String>,
+    proveedor_id: Option<i64>,
+) -> Result<Vec<OrdenCompra>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    listar(&conn, estado.as_deref().filter(|s| !s.is_empty()), proveedor_id)
+}
+
+#[tauri::command]
+pub fn obtener_orden_compra(db: State<Database>, id: i64) -> Result<OrdenCompra, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    obtener(&conn, id)
+}
+
+/// Aprobacion: BORRADOR -> ENVIADA. Desde aqui se puede imprimir/enviar al proveedor.
+#[tauri::command]
+pub fn aprobar_orden_compra(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    id: i64,
+) -> Result<OrdenCompra, String> {
+    let usuario = usuario_con_permiso(&sesion, "aprobar_ordenes_compra")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let orden = obtener(&conn, id)?;
+    if orden.estado != "BORRADOR" {
+        return Err(format!("La orden ya está {}", orden.estado));
+    }
+    conn.execute(
+        "UPDATE ordenes_compra SET estado = 'ENVIADA', usuario_aprobacion = ?1,
+                fecha_envio = COALESCE(fecha_envio, datetime('now','localtime'))
+         WHERE id = ?2",
+        rusqlite::params![usuario, id],
+    ).map_err(|e| e.to_string())?;
+    obtener(&conn, id)
+}
+
+/// El proveedor confirmó la orden (opcional, con fecha de entrega prometida).
+#[tauri::command]
+pub fn confirmar_orden_compra_proveedor(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    id: i64,
+    fecha_entrega_prometida: Option<String>,
+) -> Result<OrdenCompra, String> {
+    usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let orden = obtener(&conn, id)?;
+    if orden.estado != "ENVIADA" && orden.estado != "RECIBIDA_PARCIAL" {
+        return Err(format!("No se puede confirmar una orden {}", orden.estado));
+    }
+    conn.execute(
+        "UPDATE ordenes_compra SET confirmada_proveedor = 1, fecha_entrega_prometida = ?1 WHERE id = ?2",
+        rusqlite::params![fecha_entrega_prometida, id],
+    ).map_err(|e| e.to_string())?;
+    obtener(&conn, id)
+}
+
+#[tauri::command]
+pub fn recibir_orden_compra(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    id: i64,
+    items: Vec<RecepcionOrdenItem>,
+    observacion: Option<String>,
+) -> Result<OrdenCompra, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_compras")
+        .or_else(|_| usuario_con_permiso(&sesion, "gestionar_inventario"))?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    recibir(&tx, id, &items, observacion.as_deref(), &usuario)?;
+    let orden = obtener(&tx, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(orden)
+}
+
+/// Cierra la orden aunque falte mercaderia (el proveedor no entregara el saldo).
+#[tauri::command]
+pub fn cerrar_orden_compra(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    id: i64,
+    motivo: Option<String>,
+) -> Result<OrdenCompra, String> {
+    usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let orden = obtener(&conn, id)?;
+    if orden.estado != "ENVIADA" && orden.estado != "RECIBIDA_PARCIAL" {
+        return Err(format!("No se puede cerrar una orden {}", orden.estado));
+    }
+    conn.execute(
+        "UPDATE ordenes_compra SET estado = 'CERRADA', fecha_cierre = datetime('now','localtime'), motivo_cierre = ?1
+         WHERE id = ?2",
+        rusqlite::params![motivo.unwrap_or_else(|| "Cerrada con saldo pendiente".to_string()), id],
+    ).map_err(|e| e.to_string())?;
+    obtener(&conn, id)
+}
+
+/// Anula una orden sin recepciones.
+#[tauri::command]
+pub fn anular_orden_compra(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    id: i64,
+    motivo: Option<String>,
+) -> Result<OrdenCompra, String> {
+    usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let orden = obtener(&conn, id)?;
+    if orden.estado == "ANULADA" || orden.estado == "CERRADA" {
+        return Err(format!("La orden ya está {}", orden.estado));
+    }
+    if orden.lineas.iter().any(|l| l.cantidad_recibida > 0.0) {
+        return Err("La orden ya tiene mercadería recibida: ciérrela en lugar de anularla".to_string());
+    }
+    conn.execute(
+        "UPDATE ordenes_compra SET estado = 'ANULADA', fecha_cierre = datetime('now','localtime'), motivo_cierre = ?1
+         WHERE id = ?2",
+        rusqlite::params![motivo.unwrap_or_else(|| "Sin motivo".to_string()), id],
+    ).map_err(|e| e.to_string())?;
+    obtener(&conn, id)
+}
+
+#[tauri::command]
+pub fn conciliacion_orden_compra(db: State<Database>, id: i64) -> Result<Vec<DiferenciaConciliacion>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    conciliacion(&conn, id)
+}
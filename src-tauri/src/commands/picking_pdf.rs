@@ -0,0 +1,260 @@
+use crate::db::Database;
+use crate::models::ListaPicking;
+use genpdf::elements::{Break, LinearLayout, PaddedElement, Paragraph, StyledElement, TableLayout};
+use genpdf::style::{Color, Style};
+use genpdf::{Alignment, Document, Element, Margins, SimplePageDecorator};
+use tauri::State;
+
+// ============================================
+// HELPERS
+// ============================================
+
+fn p_aligned(text: &str, style: Style, align: Alignment) -> impl Element {
+    Paragraph::new(text).aligned(align).styled(style)
+}
+
+fn format_cantidad(cant: f64) -> String {
+    if cant == cant.floor() {
+        format!("{:.0}", cant)
+    } else {
+        format!("{:.2}", cant)
+    }
+}
+
+/// Paragraph con padding (izquierdo 3mm) para celdas de tabla
+fn pp(text: &str, style: Style) -> PaddedElement<StyledElement<Paragraph>> {
+    Paragraph::new(text)
+        .styled(style)
+        .padded(Margins::trbl(1, 1, 1, 3))
+}
+
+/// Paragraph alineado a la derecha con padding
+fn pp_right(text: &str, style: Style) -> impl Element {
+    Paragraph::new(text)
+        .aligned(Alignment::Right)
+        .styled(style)
+        .padded(Margins::trbl(1, 3, 1, 1))
+}
+
+/// Paragraph con padding + alineado al centro
+fn pp_center(text: &str, style: Style) -> impl Element {
+    Paragraph::new(text)
+        .aligned(Alignment::Center)
+        .styled(style)
+        .padded(Margins::trbl(1, 1, 1, 1))
+}
+
+// ============================================
+// COMMAND: generar_picking_pdf
+// ============================================
+
+/// Hoja de picking para recoger en papel, en el orden del recorrido.
+#[tauri::command]
+pub fn generar_picking_pdf(db: State<Database>, lista_id: i64) -> Result<String, String> {
+    let (lista, nombre_negocio) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let lista = crate::commands::bodega::obtener_lista(&conn, lista_id)?;
+        let nombre: String = conn
+            .query_row("SELECT value FROM config WHERE key = 'nombre_negocio'", [], |r| r.get(0))
+            .unwrap_or_default();
+        (lista, nombre)
+    };
+    if lista.estado == "ANULADA" {
+        return Err(format!("La lista {} está ANULADA", lista.numero));
+    }
+    let pdf_bytes = generar_pdf_picking(&lista, &nombre_negocio)?;
+
+    // Guardar en temp (nombre único + escritura robusta ante os error 32)
+    let temp_dir = std::env::temp_dir();
+    let pdf_path = crate::utils::escribir_pdf_robusto(&temp_dir, "Picking", &lista.numero, &pdf_bytes)?;
+
+    // Abrir con visor del sistema
+    #[cfg(target_os = "windows")]
+    {
+        crate::utils::silent_command("cmd")
+            .args(["/C", "start", "", &pdf_path.to_string_lossy()])
+            .spawn()
+            .map_err(|e| format!("Error abriendo PDF: {}", e))?;
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        std::process::Command::new("xdg-open")
+            .arg(&pdf_path.to_string_lossy().to_string())
+            .spawn()
+            .map_err(|e| format!("Error abriendo PDF: {}", e))?;
+    }
+
+    Ok(pdf_path.to_string_lossy().to_string())
+}
+
+// ============================================
+// PDF GENERATION
+// ============================================
+
+fn generar_pdf_picking(lista: &ListaPicking, nombre_negocio: &str) -> Result<Vec<u8>, String> {
+    let fonts_dir = crate::utils::obtener_ruta_fuentes();
+
+    let font_family = genpdf::fonts::from_files(
+        fonts_dir.to_str().unwrap_or("fonts"),
+        "LiberationSans",
+        None,
+    )
+    .map_err(|e| {
+        format!(
+            "Error cargando fuentes: {}. Asegurese de que los archivos LiberationSans-*.ttf estan en src-tauri/fonts/",
+            e
+        )
+    })?;
+
+    let mut doc = Document::new(font_family);
+    doc.set_title("Lista de Picking");
+
+    let mut decorator = SimplePageDecorator::new();
+    decorator.set_margins(Margins::trbl(15, 15, 15, 15));
+    doc.set_page_decorator(decorator);
+
+    // Estilos
+    let s_normal = Style::new().with_font_size(9);
+    let s_bold = Style::new().with_font_size(9).bold();
+    let s_small = Style::new().with_font_size(8);
+    let s_small_bold = Style::new().with_font_size(8).bold();
+    let s_title = Style::new().with_font_size(14).bold();
+    let s_doc_type = Style::new().with_font_size(16).bold();
+    let s_doc_no = Style::new().with_font_size(11);
+    let s_pie = Style::new().with_font_size(7).with_color(Color::Greyscale(128));
+    let s_note = Style::new().with_font_size(8).italic();
+    let s_section_title = Style::new().with_font_size(9).bold().with_color(Color::Greyscale(60));
+
+    // ===================================================================
+    // SECCION 1: ENCABEZADO
+    // ===================================================================
+    let mut header_table = TableLayout::new(vec![1, 1]);
+    header_table.set_cell_decorator(genpdf::elements::FrameCellDecorator::new(true, true, false));
+
+    let mut col_izq = LinearLayout::vertical();
+    col_izq.push(Break::new(0.3));
+    col_izq.push(pp(if nombre_negocio.is_empty() { "MI NEGOCIO" } else { nombre_negocio }, s_title));
+    col_izq.push(Break::new(0.3));
+    if let Some(ref est) = lista.establecimiento_nombre {
+        col_izq.push(pp(&format!("Bodega: {}", est), s_normal));
+    }
+    if let Some(ref u) = lista.usuario {
+        col_izq.push(pp(&format!("Generada por: {}", u), s_normal));
+    }
+    col_izq.push(Break::new(0.5));
+
+    let mut col_der = LinearLayout::vertical();
+    col_der.push(Break::new(0.5));
+    col_der.push(pp_center("LISTA DE PICKING", s_doc_type));
+    col_der.push(Break::new(0.5));
+    col_der.push(pp(&format!("No. {}", lista.numero), s_doc_no));
+    col_der.push(Break::new(0.3));
+    col_der.push(pp(&format!("Fecha: {}", lista.fecha_creacion), s_bold));
+    col_der.push(Break::new(0.5));
+
+    header_table
+        .row()
+        .element(col_izq.padded(Margins::trbl(2, 3, 2, 3)))
+        .element(col_der.padded(Margins::trbl(2, 3, 2, 3)))
+        .push()
+        .map_err(|e| format!("Error tabla header: {}", e))?;
+
+    doc.push(header_table);
+    doc.push(Break::new(1.0));
+
+    // ===================================================================
+    // SECCION 2: DOCUMENTOS A DESPACHAR
+    // ===================================================================
+    let mut docs = LinearLayout::vertical();
+    docs.push(pp("DOCUMENTOS", s_section_title));
+    docs.push(Break::new(0.3));
+    let mut docs_table = TableLayout::new(vec![2, 3, 5]);
+    for d in &lista.documentos {
+        docs_table
+            .row()
+            .element(pp(&d.numero, s_small_bold))
+            .element(pp(d.cliente_nombre.as_deref().unwrap_or(""), s_small))
+            .element(pp(d.direccion_destino.as_deref().unwrap_or(""), s_small))
+            .push()
+            .map_err(|e| format!("Error tabla documentos: {}", e))?;
+    }
+    docs.push(docs_table);
+    doc.push(docs.padded(Margins::trbl(3, 2, 3, 2)).framed());
+    doc.push(Break::new(1.0));
+
+    // ===================================================================
+    // SECCION 3: LINEAS EN ORDEN DE RECORRIDO
+    // ===================================================================
+    // Columnas: #, Ubicación, Código, Descripción, Cantidad, Recogido
+    let mut table = TableLayout::new(vec![1, 2, 3, 7, 2, 2]);
+    table.set_cell_decorator(genpdf::elements::FrameCellDecorator::new(true, true, false));
+    table
+        .row()
+        .element(pp("#", s_small_bold))
+        .element(pp("Ubicacion", s_small_bold))
+        .element(pp("Codigo", s_small_bold))
+        .element(pp("Descripcion", s_small_bold))
+        .element(pp_right("Cant.", s_small_bold))
+        .element(pp_center("Recogido", s_small_bold))
+        .push()
+        .map_err(|e| format!("Error tabla header: {}", e))?;
+
+    for it in &lista.items {
+        let recogido = match it.estado.as_str() {
+            "RECOGIDO" => format_cantidad(it.cantidad_recogida),
+            "FALTANTE" => format!("{} (F)", format_cantidad(it.cantidad_recogida)),
+            _ => "[    ]".to_string(),
+        };
+        table
+            .row()
+            .element(pp(&it.orden.to_string(), s_small))
+            .element(pp(it.ubicacion_codigo.as_deref().unwrap_or("SIN UBICAR"), s_small_bold))
+            .element(pp(it.producto_codigo.as_deref().unwrap_or(""), s_small))
+            .element(pp(&it.producto_nombre, s_small))
+            .element(pp_right(&format_cantidad(it.cantidad), s_small_bold))
+            .element(pp_center(&recogido, s_small))
+            .push()
+            .map_err(|e| format!("Error tabla fila: {}", e))?;
+    }
+    doc.push(table);
+    doc.push(Break::new(1.5));
+
+    if let Some(ref obs) = lista.observacion {
+        if !obs.is_empty() {
+            let mut notas = LinearLayout::vertical();
+            notas.push(pp("OBSERVACIONES", s_section_title));
+            notas.push(Break::new(0.5));
+            notas.push(pp(obs, s_note));
+            notas.push(Break::new(0.3));
+            doc.push(notas.padded(Margins::trbl(3, 3, 3, 3)).framed());
+            doc.push(Break::new(2.0));
+        }
+    }
+
+    // ===================================================================
+    // SECCION 4: FIRMAS
+    // ===================================================================
+    let firma = |rol: &str| {
+        let mut col = LinearLayout::vertical();
+        col.push(p_aligned("______________________", s_normal, Alignment::Center));
+        col.push(p_aligned(rol, s_normal, Alignment::Center));
+        col
+    };
+    let mut firmas = TableLayout::new(vec![1, 1]);
+    firmas
+        .row()
+        .element(firma("Preparado por"))
+        .element(firma("Verificado por"))
+        .push()
+        .map_err(|e| format!("Error firmas: {}", e))?;
+    doc.push(firmas);
+    doc.push(Break::new(1.5));
+    doc.push(p_aligned("Generado por Clouget POS", s_pie, Alignment::Center));
+
+    let mut buf = Vec::new();
+    doc.render(&mut buf)
+        .map_err(|e| format!("Error renderizando PDF: {}", e))?;
+
+    Ok(buf)
+}
@@ -97,6 +97,10 @@ pub fn crear_producto(db: State<Database>, producto: Producto) -> Result<i64, St
 
     let nuevo_id = conn.last_insert_rowid();
 
+    // El stock inicial queda en el establecimiento de la terminal
+    let est_id = crate::commands::stock::establecimiento_terminal(&conn)?;
+    crate::commands::stock::fijar(&conn, nuevo_id, est_id, producto.stock_actual)?;
+
     // Movimiento INICIAL en el kardex para que el stock inicial tenga origen
     // trazable (stock_anterior=0 → stock_nuevo=stock_actual). Solo si el
     // producto controla stock y tiene cantidad inicial > 0.
@@ -105,9 +109,9 @@ pub fn crear_producto(db: State<Database>, producto: Producto) -> Result<i64, St
     if controla_stock && producto.stock_actual > 0.0 {
         conn.execute(
             "INSERT INTO movimientos_inventario
-                (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, motivo, usuario)
-             VALUES (?1, 'INICIAL', ?2, 0, ?2, ?3, 'Stock inicial', NULL)",
-            rusqlite::params![nuevo_id, producto.stock_actual, producto.precio_costo],
+                (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, motivo, usuario, establecimiento_id)
+             VALUES (?1, 'INICIAL', ?2, 0, ?2, ?3, 'Stock inicial', NULL, ?4)",
+            rusqlite::params![nuevo_id, producto.stock_actual, producto.precio_costo, est_id],
         )
         .map_err(|e| e.to_string())?;
     }
@@ -1292,7 +1296,9 @@ pub fn importar_productos_excel(db: State<Database>, archivo_bytes: Vec<u8>) ->
     let col_iva = find_col("iva_porcentaje");
     // v2.5.37: incluye_iva opcional. Si vacío en el archivo, se usa el default del producto.
     let col_incluye_iva = find_col("incluye_iva");
+    // El stock del archivo es el del establecimiento de la terminal
     let col_stock = find_col("stock_actual");
+    let est_id = crate::commands::stock::establecimiento_terminal(&conn)?;
     let col_stock_min = find_col("stock_minimo");
     let col_unidad = find_col("unidad_medida");
     let col_servicio = find_col("es_servicio");
@@ -1499,6 +1505,10 @@ pub fn importar_productos_excel(db: State<Database>, archivo_bytes: Vec<u8>) ->
             }
         };
 
+        if let Some(pid) = producto_id_afectado {
+            crate::commands::stock::fijar(&conn, pid, est_id, stock)?;
+        }
+
         // v2.5.37: sync de precios por lista (precios_producto). Para cada columna
         // precio_<NombreLista> que vino llena, hacer UPSERT en precios_producto.
         // Si la celda venía vacía pero precio_venta tiene valor, replicar precio_venta
@@ -1538,7 +1548,7 @@ pub fn importar_productos_excel(db: State<Database>, archivo_bytes: Vec<u8>) ->
                     }
                 } else if warn_sin_fecha {
                     // Asegurar stock=0 en BD (el UPDATE/INSERT ya usó stock=0, pero por seguridad)
-                    conn.execute("UPDATE productos SET stock_actual = 0 WHERE id = ?1", rusqlite::params![pid]).ok();
+                    crate::commands::stock::fijar(&conn, pid, est_id, 0.0).ok();
                     warnings_caducidad.push(nombre.clone());
                 }
             }
@@ -1563,18 +1573,29 @@ pub fn registrar_series(
     producto_id: i64,
     seriales: Vec<String>,
     compra_id: Option<i64>,
+    costo: Option<f64>,
 ) -> Result<serde_json::Value, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    // Costo especifico de cada unidad (si no viene: el de la linea de compra, o el PMP)
+    let costo_serie = crate::commands::costeo::costo_inicial_serie(&conn, producto_id, compra_id, costo);
+    let compra_doc: Option<String> = compra_id.and_then(|cid| {
+        conn.query_row("SELECT numero FROM compras WHERE id = ?1", rusqlite::params![cid], |r| r.get(0)).ok()
+    });
     let mut insertados = 0i64;
     let mut duplicados = 0i64;
     for serial in &seriales {
         let s = serial.trim();
         if s.is_empty() { continue; }
         match conn.execute(
-            "INSERT INTO numeros_serie (producto_id, serial, compra_id) VALUES (?1, ?2, ?3)",
-            rusqlite::params![producto_id, s.to_uppercase(), compra_id],
+            "INSERT INTO numeros_serie (producto_id, serial, compra_id, costo) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![producto_id, s.to_uppercase(), compra_id, costo_serie],
         ) {
-            Ok(_) => insertados += 1,
+            Ok(_) => {
+                insertados += 1;
+                crate::commands::garantias::registrar_evento(
+                    &conn, conn.last_insert_rowid(), "INGRESO", compra_doc.as_deref(), "Ingreso de la serie", None,
+                )?;
+            }
             Err(_) => duplicados += 1,
         }
     }
@@ -1635,10 +1656,32 @@ pub fn marcar_serie_vendida(
     cliente_nombre: Option<String>,
 ) -> Result<(), String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    conn.execute(
+    let vendida = conn.execute(
         "UPDATE numeros_serie SET estado = 'VENDIDO', venta_id = ?1, venta_detalle_id = ?2, cliente_id = ?3, cliente_nombre = ?4, fecha_venta = datetime('now','localtime') WHERE id = ?5 AND estado = 'DISPONIBLE'",
         rusqlite::params![venta_id, venta_detalle_id, cliente_id, cliente_nombre, serie_id],
     ).map_err(|e| e.to_string())?;
+    // Garantia al cliente desde hoy, segun los meses del producto
+    if vendida > 0 {
+        conn.execute(
+            "UPDATE numeros_serie SET garantia_hasta = (
+                 SELECT CASE WHEN COALESCE(p.garantia_meses, 0) > 0
+                             THEN date('now','localtime', '+' || p.garantia_meses || ' months') END
+                 FROM productos p WHERE p.id = numeros_serie.producto_id)
+             WHERE id = ?1 AND venta_id = ?2",
+            rusqlite::params![serie_id, venta_id],
+        ).map_err(|e| e.to_string())?;
+        let venta_numero: Option<String> = conn.query_row(
+            "SELECT numero FROM ventas WHERE id = ?1", rusqlite::params![venta_id], |r| r.get(0),
+        ).ok();
+        crate::commands::garantias::registrar_evento(
+            &conn, serie_id, "VENTA", venta_numero.as_deref(),
+            cliente_nombre.as_deref().unwrap_or("Consumidor final"), None,
+        )?;
+    }
+    // Identificacion especifica: la unidad vendida cuesta lo de su serie
+    if let Some(vd_id) = venta_detalle_id {
+        crate::commands::costeo::recalcular_costo_serie(&conn, vd_id)?;
+    }
     Ok(())
 }
 
@@ -1726,10 +1769,20 @@ pub fn devolver_serie(
     serie_id: i64,
 ) -> Result<(), String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let (venta_detalle_id, venta_numero): (Option<i64>, Option<String>) = conn.query_row(
+        "SELECT ns.venta_detalle_id, v.numero FROM numeros_serie ns LEFT JOIN ventas v ON v.id = ns.venta_id WHERE ns.id = ?1",
+        rusqlite::params![serie_id], |r| Ok((r.get(0)?, r.get(1)?)),
+    ).unwrap_or((None, None));
     conn.execute(
-        "UPDATE numeros_serie SET estado = 'DISPONIBLE', venta_id = NULL, venta_detalle_id = NULL, cliente_id = NULL, cliente_nombre = NULL, fecha_venta = NULL WHERE id = ?1",
+        "UPDATE numeros_serie SET estado = 'DISPONIBLE', venta_id = NULL, venta_detalle_id = NULL, cliente_id = NULL, cliente_nombre = NULL, fecha_venta = NULL, garantia_hasta = NULL WHERE id = ?1",
         rusqlite::params![serie_id],
     ).map_err(|e| e.to_string())?;
+    crate::commands::garantias::registrar_evento(
+        &conn, serie_id, "DEVOLUCION", venta_numero.as_deref(), "Devuelta por el cliente", None,
+    )?;
+    if let Some(vd_id) = venta_detalle_id {
+        crate::commands::costeo::recalcular_costo_serie(&conn, vd_id)?;
+    }
     Ok(())
 }
 
@@ -7,8 +7,8 @@ pub fn crear_proveedor(db: State<Database>, proveedor: Proveedor) -> Result<Prov
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
     conn.execute(
-        "INSERT INTO proveedores (ruc, nombre, direccion, telefono, email, contacto, dias_credito, activo)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        "INSERT INTO proveedores (ruc, nombre, direccion, telefono, email, contacto, dias_credito, activo, dias_entrega)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
         rusqlite::params![
             proveedor.ruc,
             proveedor.nombre,
@@ -18,6 +18,7 @@ pub fn crear_proveedor(db: State<Database>, proveedor: Proveedor) -> Result<Prov
             proveedor.contacto,
             proveedor.dias_credito,
             proveedor.activo,
+            proveedor.dias_entrega,
         ],
     )
     .map_err(|e| e.to_string())?;
@@ -40,7 +41,7 @@ pub fn actualizar_proveedor(
 
     conn.execute(
         "UPDATE proveedores SET ruc = ?1, nombre = ?2, direccion = ?3, telefono = ?4,
-         email = ?5, contacto = ?6, dias_credito = ?7, activo = ?8
+         email = ?5, contacto = ?6, dias_credito = ?7, activo = ?8, dias_entrega = ?10
          WHERE id = ?9",
         rusqlite::params![
             proveedor.ruc,
@@ -52,6 +53,7 @@ pub fn actualizar_proveedor(
             proveedor.dias_credito,
             proveedor.activo,
             id,
+            proveedor.dias_entrega,
         ],
     )
     .map_err(|e| e.to_string())?;
@@ -65,7 +67,7 @@ pub fn listar_proveedores(db: State<Database>) -> Result<Vec<Proveedor>, String>
 
     let mut stmt = conn
         .prepare(
-            "SELECT id, ruc, nombre, direccion, telefono, email, contacto, dias_credito, activo
+            "SELECT id, ruc, nombre, direccion, telefono, email, contacto, dias_credito, activo, dias_entrega
              FROM proveedores WHERE activo = 1 ORDER BY nombre",
         )
         .map_err(|e| e.to_string())?;
@@ -82,6 +84,7 @@ pub fn listar_proveedores(db: State<Database>) -> Result<Vec<Proveedor>, String>
                 contacto: row.get(6)?,
                 dias_credito: row.get(7)?,
                 activo: row.get(8)?,
+                dias_entrega: row.get(9)?,
             })
         })
         .map_err(|e| e.to_string())?
@@ -98,7 +101,7 @@ pub fn buscar_proveedores(db: State<Database>, termino: String) -> Result<Vec<Pr
     let busqueda = format!("%{}%", termino);
     let mut stmt = conn
         .prepare(
-            "SELECT id, ruc, nombre, direccion, telefono, email, contacto, dias_credito, activo
+            "SELECT id, ruc, nombre, direccion, telefono, email, contacto, dias_credito, activo, dias_entrega
              FROM proveedores
              WHERE activo = 1 AND (nombre LIKE ?1 OR ruc LIKE ?1 OR contacto LIKE ?1)
              ORDER BY nombre
@@ -118,6 +121,7 @@ pub fn buscar_proveedores(db: State<Database>, termino: String) -> Result<Vec<Pr
                 contacto: row.get(6)?,
                 dias_credito: row.get(7)?,
                 activo: row.get(8)?,
+                dias_entrega: row.get(9)?,
             })
         })
         .map_err(|e| e.to_string())?
@@ -0,0 +1,381 @@
+// Comandos para Recetas / BOM (bill of materials) de platos
+//
+// Un plato (ej: "Seco de pollo") consume insumos que no se venden por unidad
+// (gramos de pollo, arroz). La receta define cuanto insumo consume UNA unidad
+// del plato, en unidades base del insumo (productos.unidad_medida).
+//   - cantidad se captura en una presentacion del insumo (unidades_producto,
+//     ej: "Gramo" factor 0.001 sobre KG); sin unidad_id el factor es 1.
+//   - merma_pct infla el consumo (limpieza, coccion): base * (1 + merma/100).
+//
+// Al vender un plato con receta (registrar_venta, y por tanto rest_cerrar_pedido
+// que cobra con una venta) NO se descuenta el stock del plato sino el de sus
+// insumos (kardex 'VENTA_RECETA'). Lo descontado queda en venta_detalle_receta
+// para reintegrar al anular y para el reporte de varianza vs conteo fisico.
+
+use crate::db::Database;
+use crate::models::{CostoReceta, RecetaInsumo, VarianzaInsumo};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use tauri::State;
+
+/// Tipos de movimiento que representan un conteo fisico (stock real vs sistema).
+const TIPOS_CONTEO: &str = "('AJUSTE', 'AJUSTE_POSITIVO', 'AJUSTE_NEGATIVO')";
+
+/// Datos de la venta que se graban en el kardex al descontar/reintegrar insumos.
+pub struct ContextoKardex<'a> {
+    pub venta_id: i64,
+    pub numero: &'a str,
+    pub usuario: &'a str,
+    /// Establecimiento donde se mueve el stock de los insumos
+    pub est_id: i64,
+}
+
+/// True si el producto tiene receta definida.
+pub fn tiene_receta(conn: &Connection, producto_id: Option<i64>) -> bool {
+    let Some(pid) = producto_id else { return false };
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM producto_recetas WHERE producto_id = ?1)",
+        rusqlite::params![pid],
+        |r| r.get::<_, bool>(0),
+    ).unwrap_or(false)
+}
+
+fn cargar_receta(conn: &Connection, producto_id: i64) -> Result<Vec<RecetaInsumo>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT r.id, r.producto_id, r.insumo_id, r.cantidad, r.unidad_id, r.merma_pct, r.orden, r.factor,
+                u.nombre, p.nombre, p.unidad_medida, p.precio_costo, p.stock_actual
+         FROM producto_recetas r
+         JOIN productos p ON r.insumo_id = p.id
+         LEFT JOIN unidades_producto u ON r.unidad_id = u.id
+         WHERE r.producto_id = ?1
+         ORDER BY r.orden ASC, r.id ASC"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(rusqlite::params![producto_id], |r| {
+        let cantidad: f64 = r.get(3)?;
+        let merma: f64 = r.get(5)?;
+        let factor: f64 = r.get(7)?;
+        let costo_insumo: f64 = r.get(11)?;
+        let base = cantidad * factor;
+        Ok(RecetaInsumo {
+            id: Some(r.get(0)?),
+            producto_id: r.get(1)?,
+            insumo_id: r.get(2)?,
+            cantidad,
+            unidad_id: r.get(4)?,
+            merma_pct: merma,
+            orden: r.get(6)?,
+            factor: Some(factor),
+            cantidad_base: Some(base),
+            unidad_nombre: r.get(8)?,
+            insumo_nombre: r.get(9)?,
+            insumo_unidad_medida: r.get(10)?,
+            insumo_precio_costo: Some(costo_insumo),
+            insumo_stock_actual: r.get(12)?,
+            costo: Some(base * (1.0 + merma / 100.0) * costo_insumo),
+        })
+    }).map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+fn calcular_costo(conn: &Connection, producto_id: i64) -> Result<CostoReceta, String> {
+    let (nombre, precio_venta, iva_porcentaje, incluye_iva): (String, f64, f64, bool) = conn.query_row(
+        "SELECT nombre, precio_venta, COALESCE(iva_porcentaje, 0), COALESCE(incluye_iva, 0) FROM productos WHERE id = ?1",
+        rusqlite::params![producto_id],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+    ).map_err(|_| "Producto no encontrado".to_string())?;
+    let precio_neto = if incluye_iva && iva_porcentaje > 0.0 {
+        precio_venta / (1.0 + iva_porcentaje / 100.0)
+    } else {
+        precio_venta
+    };
+    let lineas = cargar_receta(conn, producto_id)?;
+    let costo_teorico: f64 = lineas.iter().filter_map(|l| l.costo).sum();
+    let food_cost_pct = if precio_neto > 0.0 { costo_teorico / precio_neto * 100.0 } else { 0.0 };
+    Ok(CostoReceta {
+        producto_id,
+        nombre,
+        precio_neto,
+        costo_teorico,
+        food_cost_pct,
+        margen: precio_neto - costo_teorico,
+        lineas,
+    })
+}
+
+/// Lista la receta de un plato, enriquecida con datos del insumo y costo por linea.
+#[tauri::command]
+pub fn listar_receta(db: State<Database>, producto_id: i64) -> Result<Vec<RecetaInsumo>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    cargar_receta(&conn, producto_id)
+}
+
+/// Reemplaza atomicamente la receta de un plato. Lista vacia = quitar la receta
+/// (el plato vuelve a descontar su propio stock).
+#[tauri::command]
+pub fn guardar_receta(
+    db: State<Database>,
+    producto_id: i64,
+    lineas: Vec<RecetaInsumo>,
+) -> Result<(), String> {
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    for l in &lineas {
+        if l.insumo_id == producto_id {
+            return Err("Un plato no puede ser insumo de su propia receta".into());
+        }
+        if l.cantidad <= 0.0 {
+            return Err(format!("Insumo con cantidad invalida: {}", l.cantidad));
+        }
+        if !(0.0..100.0).contains(&l.merma_pct) {
+            return Err(format!("Merma invalida: {}% (debe estar entre 0 y 99)", l.merma_pct));
+        }
+        // Sin recetas anidadas: el insumo debe descontar su propio stock.
+        if tiene_receta(&conn, Some(l.insumo_id)) {
+            return Err(format!("El insumo {} tiene receta propia; no se permiten recetas anidadas", l.insumo_id));
+        }
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM producto_recetas WHERE producto_id = ?1", rusqlite::params![producto_id])
+        .map_err(|e| e.to_string())?;
+
+    for (i, l) in lineas.iter().enumerate() {
+        // El factor sale de la presentacion del INSUMO (no se confia en el cliente)
+        let factor: f64 = match l.unidad_id {
+            Some(uid) => tx.query_row(
+                "SELECT factor FROM unidades_producto WHERE id = ?1 AND producto_id = ?2",
+                rusqlite::params![uid, l.insumo_id],
+                |r| r.get(0),
+            ).map_err(|_| format!("La unidad {} no pertenece al insumo {}", uid, l.insumo_id))?,
+            None => 1.0,
+        };
+        let orden = if l.orden != 0 { l.orden } else { i as i64 };
+        tx.execute(
+            "INSERT INTO producto_recetas (producto_id, insumo_id, cantidad, unidad_id, factor, merma_pct, orden)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![producto_id, l.insumo_id, l.cantidad, l.unidad_id, factor, l.merma_pct, orden],
+        ).map_err(|e| {
+            if e.to_string().contains("UNIQUE") {
+                format!("El insumo {} esta repetido en la receta", l.insumo_id)
+            } else {
+                e.to_string()
+            }
+        })?;
+    }
+
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Food cost teorico de un plato (costo de insumos vs precio sin IVA).
+#[tauri::command]
+pub fn costo_receta(db: State<Database>, producto_id: i64) -> Result<CostoReceta, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    calcular_costo(&conn, producto_id)
+}
+
+/// Food cost teorico de todos los platos con receta, ordenado de mayor a menor %.
+#[tauri::command]
+pub fn reporte_food_cost(db: State<Database>) -> Result<Vec<CostoReceta>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT r.producto_id FROM producto_recetas r
+         JOIN productos p ON r.producto_id = p.id
+         WHERE COALESCE(p.activo, 1) = 1"
+    ).map_err(|e| e.to_string())?;
+    let ids: Vec<i64> = stmt
+        .query_map([], |r| r.get(0))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+    let mut costos = ids
+        .into_iter()
+        .map(|id| calcular_costo(&conn, id))
+        .collect::<Result<Vec<_>, _>>()?;
+    costos.sort_by(|a, b| b.food_cost_pct.partial_cmp(&a.food_cost_pct).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(costos)
+}
+
+/// Varianza por insumo en un rango de fechas: consumo teorico (recetas de
+/// ventas no anuladas + insumos de modificadores) contra los ajustes de conteo
+/// fisico. Como el sistema ya desconto el teorico, un ajuste negativo significa
+/// que se consumio mas de lo que dicen las recetas (porciones grandes, merma
+/// no registrada, robo).
+#[tauri::command]
+pub fn reporte_varianza_insumos(
+    db: State<Database>,
+    fecha_desde: String,
+    fecha_hasta: String,
+) -> Result<Vec<VarianzaInsumo>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    // insumo_id -> (consumo_teorico, ajuste_conteo)
+    let mut acumulado: HashMap<i64, (f64, f64)> = HashMap::new();
+
+    let mut stmt = conn.prepare(
+        "SELECT vdr.insumo_id, SUM(vdr.cantidad)
+         FROM venta_detalle_receta vdr
+         JOIN venta_detalles d ON vdr.venta_detalle_id = d.id
+         JOIN ventas v ON d.venta_id = v.id
+         WHERE v.anulada = 0
+           AND date(v.fecha) >= date(?1) AND date(v.fecha) <= date(?2)
+         GROUP BY vdr.insumo_id"
+    ).map_err(|e| e.to_string())?;
+    let rows: Vec<(i64, f64)> = stmt
+        .query_map(rusqlite::params![fecha_desde, fecha_hasta], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+    for (id, cant) in rows {
+        acumulado.entry(id).or_default().0 += cant;
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT m.producto_id, -SUM(m.cantidad)
+         FROM movimientos_inventario m
+         JOIN ventas v ON m.referencia_id = v.id
+         WHERE m.tipo IN ('VENTA_MODIFICADOR', 'NOTA_CREDITO_MODIFICADOR', 'DEVOLUCION_MODIFICADOR') AND v.anulada = 0
+           AND date(m.created_at) >= date(?1) AND date(m.created_at) <= date(?2)
+         GROUP BY m.producto_id"
+    ).map_err(|e| e.to_string())?;
+    let rows: Vec<(i64, f64)> = stmt
+        .query_map(rusqlite::params![fecha_desde, fecha_hasta], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+    for (id, cant) in rows {
+        acumulado.entry(id).or_default().0 += cant;
+    }
+
+    // Ajustes de conteo solo de productos que son insumo de alguna receta
+    let sql_ajustes = format!(
+        "SELECT m.producto_id, SUM(m.cantidad)
+         FROM movimientos_inventario m
+         WHERE m.tipo IN {}
+           AND m.producto_id IN (SELECT insumo_id FROM producto_recetas)
+           AND date(m.created_at) >= date(?1) AND date(m.created_at) <= date(?2)
+         GROUP BY m.producto_id",
+        TIPOS_CONTEO
+    );
+    let mut stmt = conn.prepare(&sql_ajustes).map_err(|e| e.to_string())?;
+    let rows: Vec<(i64, f64)> = stmt
+        .query_map(rusqlite::params![fecha_desde, fecha_hasta], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+    for (id, cant) in rows {
+        acumulado.entry(id).or_default().1 += cant;
+    }
+
+    let mut resultado = Vec::with_capacity(acumulado.len());
+    for (insumo_id, (teorico, ajuste)) in acumulado {
+        let (nombre, unidad_medida, costo): (String, String, f64) = match conn.query_row(
+            "SELECT nombre, unidad_medida, precio_costo FROM productos WHERE id = ?1",
+            rusqlite::params![insumo_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        ) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        let real = teorico - ajuste;
+        let varianza = real - teorico;
+        resultado.push(VarianzaInsumo {
+            insumo_id,
+            nombre,
+            unidad_medida,
+            consumo_teorico: teorico,
+            ajuste_conteo: ajuste,
+            consumo_real: real,
+            varianza,
+            varianza_pct: if teorico > 0.0 { varianza / teorico * 100.0 } else { 0.0 },
+            costo_varianza: varianza * costo,
+        });
+    }
+    resultado.sort_by(|a, b| b.costo_varianza.partial_cmp(&a.costo_varianza).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(resultado)
+}
+
+/// Consumo de insumos de `cantidad_base` unidades del plato, con merma:
+/// (insumo_id, cantidad en unidad base del insumo).
+pub fn consumo_insumos(conn: &Connection, producto_id: i64, cantidad_base: f64) -> Result<Vec<(i64, f64)>, String> {
+    Ok(cargar_receta(conn, producto_id)?
+        .into_iter()
+        .map(|l| (l.insumo_id, l.cantidad_base.unwrap_or(l.cantidad) * (1.0 + l.merma_pct / 100.0) * cantidad_base))
+        .collect())
+}
+
+/// Descuenta los insumos de la receta de una linea de venta.
+/// `cantidad_base` = unidades del plato vendidas (cantidad x factor de presentacion).
+/// Insumos de servicio / sin control de stock se registran en venta_detalle_receta
+/// (para food cost) pero no mueven stock.
+pub fn descontar_receta(
+    conn: &Connection,
+    ctx: &ContextoKardex,
+    venta_detalle_id: i64,
+    producto_id: i64,
+    cantidad_base: f64,
+    nombre_plato: &str,
+) -> Result<(), String> {
+    for (insumo_id, total) in consumo_insumos(conn, producto_id, cantidad_base)? {
+        if total <= 0.0 {
+            continue;
+        }
+        let (omite, costo): (bool, f64) = conn.query_row(
+            "SELECT (COALESCE(es_servicio,0)+COALESCE(no_controla_stock,0))>0, precio_costo
+             FROM productos WHERE id = ?1",
+            rusqlite::params![insumo_id],
+            |r| Ok((r.get::<_, i64>(0)? > 0, r.get(1)?)),
+        ).map_err(|e| format!("Insumo {} no encontrado: {}", insumo_id, e))?;
+
+        if !omite {
+            let mov = crate::commands::stock::mover(conn, insumo_id, ctx.est_id, -total)?;
+            let _ = conn.execute(
+                "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, establecimiento_id, motivo)
+                 VALUES (?1, 'VENTA_RECETA', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                rusqlite::params![
+                    insumo_id, -total, mov.anterior, mov.nuevo, costo,
+                    ctx.venta_id, ctx.usuario, ctx.est_id,
+                    format!("Venta {} (receta: {})", ctx.numero, nombre_plato)
+                ],
+            );
+        }
+
+        conn.execute(
+            "INSERT INTO venta_detalle_receta (venta_detalle_id, insumo_id, cantidad, costo_unitario, descontado)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![venta_detalle_id, insumo_id, total, costo, !omite],
+        ).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Reintegra los insumos descontados por recetas en una venta (anulacion).
+pub fn reintegrar_receta(conn: &Connection, ctx: &ContextoKardex, motivo: &str) -> Result<(), String> {
+    let mut stmt = conn.prepare(
+        "SELECT vdr.insumo_id, vdr.cantidad
+         FROM venta_detalle_receta vdr
+         JOIN venta_detalles d ON vdr.venta_detalle_id = d.id
+         WHERE d.venta_id = ?1 AND vdr.descontado = 1",
+    ).map_err(|e| e.to_string())?;
+    let insumos: Vec<(i64, f64)> = stmt
+        .query_map(rusqlite::params![ctx.venta_id], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+
+    for (insumo_id, cant) in insumos {
+        let mov = crate::commands::stock::mover(conn, insumo_id, ctx.est_id, cant)
+            .map_err(|e| format!("Error reintegrando insumo {}: {}", insumo_id, e))?;
+        let _ = conn.execute(
+            "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, motivo, usuario, referencia_id, establecimiento_id)
+             VALUES (?1, 'ANULACION_RECETA', ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![insumo_id, cant, mov.anterior, mov.nuevo,
+                format!("Anulacion venta {} (receta) - {}", ctx.numero, motivo), ctx.usuario, ctx.venta_id, ctx.est_id],
+        );
+    }
+    Ok(())
+}
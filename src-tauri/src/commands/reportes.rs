@@ -545,16 +545,33 @@ pub struct ReporteUtilidad {
     pub total_gastos: f64, pub utilidad_neta: f64, pub margen_neto: f64, pub num_ventas: i64,
     pub promedio_por_venta: f64, pub total_devoluciones: f64,
     pub por_categoria: Vec<CategoriaUtilidad>, pub gastos_por_categoria: Vec<GastoCategoria>,
+    pub metodo_costeo: String,
+}
+
+/// Costo total de una linea de venta (alias vd / p) con el metodo dado.
+/// Lineas sin costo registrado con el metodo (anteriores al costeo, combos,
+/// recetas) usan el snapshot precio_costo.
+fn expr_costo_linea(metodo: &str) -> Result<String, String> {
+    let snapshot = "CASE WHEN COALESCE(vd.precio_costo, 0) > 0 THEN vd.precio_costo ELSE p.precio_costo END * vd.cantidad";
+    match metodo {
+        "FIFO" => Ok(format!("COALESCE(vd.costo_fifo, {})", snapshot)),
+        "PROMEDIO" => Ok(format!("COALESCE(vd.costo_pmp, {})", snapshot)),
+        "ULTIMO" => Ok(snapshot.to_string()),
+        otro => Err(format!("Método de costeo no soportado: {}", otro)),
+    }
 }
 
 #[tauri::command]
-pub fn reporte_utilidad(db: State<Database>, fecha_inicio: String, fecha_hasta: String) -> Result<ReporteUtilidad, String> {
+pub fn reporte_utilidad(db: State<Database>, fecha_inicio: String, fecha_hasta: String, metodo: Option<String>) -> Result<ReporteUtilidad, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    // Costo de ventas con el metodo pedido o el configurado (commands/costeo.rs)
+    let metodo_costeo = crate::commands::costeo::resolver_metodo(&conn, metodo)?;
+    let costo_linea = expr_costo_linea(&metodo_costeo)?;
     let vf = "date(fecha) BETWEEN date(?1) AND date(?2) AND anulada = 0 AND COALESCE(tipo_estado, 'COMPLETADA') IN ('COMPLETADA', 'CONVERTIDA')";
 
     let ventas_brutas: f64 = conn.query_row(&format!("SELECT COALESCE(SUM(total), 0) FROM ventas WHERE {}", vf), rusqlite::params![fecha_inicio, fecha_hasta], |r| r.get(0)).unwrap_or(0.0);
     let num_ventas: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM ventas WHERE {}", vf), rusqlite::params![fecha_inicio, fecha_hasta], |r| r.get(0)).unwrap_or(0);
-    let costo_ventas: f64 = conn.query_row(&format!("SELECT COALESCE(SUM(CASE WHEN COALESCE(vd.precio_costo, 0) > 0 THEN vd.precio_costo ELSE p.precio_costo END * vd.cantidad), 0) FROM venta_detalles vd JOIN ventas v ON vd.venta_id = v.id JOIN productos p ON vd.producto_id = p.id WHERE date(v.fecha) BETWEEN date(?1) AND date(?2) AND v.anulada = 0 AND COALESCE(v.tipo_estado, 'COMPLETADA') IN ('COMPLETADA', 'CONVERTIDA')"), rusqlite::params![fecha_inicio, fecha_hasta], |r| r.get(0)).unwrap_or(0.0);
+    let costo_ventas: f64 = conn.query_row(&format!("SELECT COALESCE(SUM({}), 0) FROM venta_detalles vd JOIN ventas v ON vd.venta_id = v.id JOIN productos p ON vd.producto_id = p.id WHERE date(v.fecha) BETWEEN date(?1) AND date(?2) AND v.anulada = 0 AND COALESCE(v.tipo_estado, 'COMPLETADA') IN ('COMPLETADA', 'CONVERTIDA')", costo_linea), rusqlite::params![fecha_inicio, fecha_hasta], |r| r.get(0)).unwrap_or(0.0);
     let total_gastos: f64 = conn.query_row("SELECT COALESCE(SUM(monto), 0) FROM gastos WHERE date(fecha) BETWEEN date(?1) AND date(?2)", rusqlite::params![fecha_inicio, fecha_hasta], |r| r.get(0)).unwrap_or(0.0);
     let total_devoluciones: f64 = conn.query_row("SELECT COALESCE(SUM(total), 0) FROM notas_credito WHERE date(fecha) BETWEEN date(?1) AND date(?2)", rusqlite::params![fecha_inicio, fecha_hasta], |r| r.get(0)).unwrap_or(0.0);
 
@@ -564,13 +581,13 @@ pub fn reporte_utilidad(db: State<Database>, fecha_inicio: String, fecha_hasta:
     let margen_neto = if ventas_brutas > 0.0 { (utilidad_neta / ventas_brutas) * 100.0 } else { 0.0 };
     let promedio_por_venta = if num_ventas > 0 { ventas_brutas / num_ventas as f64 } else { 0.0 };
 
-    let mut sc = conn.prepare("SELECT COALESCE(cat.nombre, 'Sin categoría'), COALESCE(SUM(vd.subtotal), 0), COALESCE(SUM(CASE WHEN COALESCE(vd.precio_costo, 0) > 0 THEN vd.precio_costo ELSE p.precio_costo END * vd.cantidad), 0) FROM venta_detalles vd JOIN ventas v ON vd.venta_id = v.id JOIN productos p ON vd.producto_id = p.id LEFT JOIN categorias cat ON p.categoria_id = cat.id WHERE date(v.fecha) BETWEEN date(?1) AND date(?2) AND v.anulada = 0 AND COALESCE(v.tipo_estado, 'COMPLETADA') IN ('COMPLETADA', 'CONVERTIDA') GROUP BY COALESCE(cat.nombre, 'Sin categoría') ORDER BY SUM(vd.subtotal) DESC LIMIT 10").map_err(|e| e.to_string())?;
+    let mut sc = conn.prepare(&format!("SELECT COALESCE(cat.nombre, 'Sin categoría'), COALESCE(SUM(vd.subtotal), 0), COALESCE(SUM({}), 0) FROM venta_detalles vd JOIN ventas v ON vd.venta_id = v.id JOIN productos p ON vd.producto_id = p.id LEFT JOIN categorias cat ON p.categoria_id = cat.id WHERE date(v.fecha) BETWEEN date(?1) AND date(?2) AND v.anulada = 0 AND COALESCE(v.tipo_estado, 'COMPLETADA') IN ('COMPLETADA', 'CONVERTIDA') GROUP BY COALESCE(cat.nombre, 'Sin categoría') ORDER BY SUM(vd.subtotal) DESC LIMIT 10", costo_linea)).map_err(|e| e.to_string())?;
     let por_categoria = sc.query_map(rusqlite::params![fecha_inicio, fecha_hasta], |r| { let v: f64 = r.get(1)?; let c: f64 = r.get(2)?; Ok(CategoriaUtilidad { categoria: r.get(0)?, ventas: v, costo: c, utilidad: v - c }) }).map_err(|e| e.to_string())?.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
 
     let mut sg = conn.prepare("SELECT COALESCE(categoria, 'Sin categoría'), SUM(monto) FROM gastos WHERE date(fecha) BETWEEN date(?1) AND date(?2) GROUP BY COALESCE(categoria, 'Sin categoría') ORDER BY SUM(monto) DESC").map_err(|e| e.to_string())?;
     let gastos_por_categoria = sg.query_map(rusqlite::params![fecha_inicio, fecha_hasta], |r| Ok(GastoCategoria { categoria: r.get(0)?, monto: r.get(1)? })).map_err(|e| e.to_string())?.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
 
-    Ok(ReporteUtilidad { ventas_brutas, costo_ventas, utilidad_bruta, margen_bruto, total_gastos, utilidad_neta, margen_neto, num_ventas, promedio_por_venta, total_devoluciones, por_categoria, gastos_por_categoria })
+    Ok(ReporteUtilidad { ventas_brutas, costo_ventas, utilidad_bruta, margen_bruto, total_gastos, utilidad_neta, margen_neto, num_ventas, promedio_por_venta, total_devoluciones, por_categoria, gastos_por_categoria, metodo_costeo })
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1726,8 +1743,9 @@ pub fn reporte_ventas_filtros_disponibles(
 
 /// Reporte de valuación de inventario.
 ///
-/// Métodos soportados:
-/// - "PMP" (default): usa costo_promedio (Promedio Ponderado Móvil)
+/// Métodos soportados (default: config `metodo_costeo`):
+/// - "PMP" / "PROMEDIO": usa costo_promedio (Promedio Ponderado Móvil)
+/// - "FIFO": capas de costo más recientes; series disponibles a su costo propio
 /// - "ULTIMO": usa precio_costo (último precio de compra registrado)
 ///
 /// Excluye servicios y productos sin control de stock (no tienen valor de inventario).
@@ -1741,8 +1759,9 @@ pub fn reporte_valuacion_inventario(
 ) -> Result<serde_json::Value, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
-    let metodo_str = metodo.unwrap_or_else(|| "PMP".to_string()).to_uppercase();
-    let usar_pmp = metodo_str != "ULTIMO";
+    let metodo_str = crate::commands::costeo::resolver_metodo(&conn, metodo)?;
+    let usar_fifo = metodo_str == "FIFO";
+    let usar_pmp = metodo_str == "PROMEDIO";
 
     // Auto-healing: asegurar que la columna costo_promedio existe
     let _ = conn.execute("ALTER TABLE productos ADD COLUMN costo_promedio REAL NOT NULL DEFAULT 0", []);
@@ -1785,8 +1804,13 @@ pub fn reporte_valuacion_inventario(
         let costo_ultimo: f64 = row.get(5)?;
         let costo_pmp: f64 = row.get(6)?;
         let precio_venta: f64 = row.get(7)?;
-        let costo_usado = if usar_pmp { costo_pmp } else { costo_ultimo };
-        let valor = stock * costo_usado;
+        let (costo_usado, valor) = if usar_fifo {
+            let valor = crate::commands::costeo::valor_inventario_fifo(&conn, id).unwrap_or(stock * costo_pmp);
+            (if stock > 0.0 { valor / stock } else { costo_pmp }, valor)
+        } else {
+            let costo = if usar_pmp { costo_pmp } else { costo_ultimo };
+            (costo, stock * costo)
+        };
         let potencial = stock * precio_venta;
         let utilidad = potencial - valor;
         let margen_pct = if valor > 0.0 { (utilidad / valor) * 100.0 } else { 0.0 };
@@ -1818,8 +1842,14 @@ pub fn reporte_valuacion_inventario(
     let margen_total_pct = if total_valor > 0.0 { (utilidad_total / total_valor) * 100.0 } else { 0.0 };
 
     Ok(serde_json::json!({
-        "metodo": if usar_pmp { "PMP" } else { "ULTIMO" },
-        "metodo_descripcion": if usar_pmp { "Promedio Ponderado Móvil" } else { "Último precio de compra" },
+        "metodo": if usar_fifo { "FIFO" } else if usar_pmp { "PMP" } else { "ULTIMO" },
+        "metodo_descripcion": if usar_fifo {
+            "FIFO (primeras entradas, primeras salidas)"
+        } else if usar_pmp {
+            "Promedio Ponderado Móvil"
+        } else {
+            "Último precio de compra"
+        },
         "productos": productos,
         "totales": {
             "items": items_count,
@@ -0,0 +1,402 @@
+// Sugerencias de reposicion (reorden automatico)
+//
+// Para cada producto con control de stock:
+//   - venta promedio diaria = salidas por venta del kardex (VENTA y las de
+//     componentes de combos, insumos de recetas y modificadores, en unidades
+//     base y netas de notas de credito) de los ultimos
+//     `reposicion_dias_ventana` dias / dias de la ventana;
+//   - estacionalidad semanal: factor de cada dia de semana = venta promedio
+//     de ese dia / venta promedio diaria. La demanda proyectada de los
+//     proximos N dias suma promedio * factor de cada dia;
+//   - punto de reorden = demanda durante la entrega del proveedor
+//     (proveedores.dias_entrega) + dias de seguridad;
+//   - si stock + en camino (ordenes de compra abiertas) <= max(punto de
+//     reorden, stock_minimo), se sugiere pedir hasta cubrir entrega +
+//     seguridad + `reposicion_dias_cobertura`, redondeado al empaque
+//     (producto_presentaciones).
+// Combos y platos con receta no se sugieren: no se compran, se compran sus
+// componentes e insumos.
+// Las sugerencias se agrupan por proveedor preferido (productos.proveedor_preferido_id,
+// el de lista vigente mas barata o el de la ultima compra) y se convierten en
+// ordenes de compra en BORRADOR, al costo de lista del proveedor si lo tiene.
+
+use crate::db::{usuario_con_permiso, Database, SesionState};
+use crate::models::{
+    GrupoReposicion, ItemReposicion, NuevaLineaOrdenCompra, NuevaOrdenCompra, OrdenCompra,
+    ParametrosReposicion, SugerenciaReposicion,
+};
+use chrono::Datelike;
+use rusqlite::Connection;
+use std::collections::{BTreeMap, HashMap};
+use tauri::State;
+
+fn round2(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+fn config_dias(conn: &Connection, key: &str, defecto: i64) -> i64 {
+    conn.query_row("SELECT value FROM config WHERE key = ?1", rusqlite::params![key], |r| r.get::<_, String>(0))
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .unwrap_or(defecto)
+}
+
+/// Venta por dia de semana (0 = domingo) de cada producto en la ventana, en
+/// unidades base, desde el kardex: lo que salio por la venta (el producto, los
+/// componentes de sus combos, los insumos de sus recetas y modificadores)
+/// menos lo que volvio con NC o devolucion, en el dia de la venta original.
+fn ventas_por_dia_semana(
+    conn: &Connection,
+    desde: &str,
+    hasta: &str,
+    establecimiento: Option<&str>,
+) -> Result<HashMap<i64, [f64; 7]>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT m.producto_id, CAST(strftime('%w', v.fecha) AS INTEGER) AS dia, SUM(-m.cantidad)
+         FROM movimientos_inventario m
+         JOIN ventas v ON v.id = CASE WHEN m.tipo IN ('NOTA_CREDITO', 'DEVOLUCION')
+                                      THEN (SELECT nc.venta_id FROM notas_credito nc WHERE nc.id = m.referencia_id)
+                                      ELSE m.referencia_id END
+         WHERE m.tipo IN ('VENTA', 'VENTA_COMBO', 'VENTA_RECETA', 'VENTA_MODIFICADOR',
+                          'NOTA_CREDITO', 'DEVOLUCION', 'NOTA_CREDITO_MODIFICADOR', 'DEVOLUCION_MODIFICADOR')
+           AND date(v.fecha) >= date(?1) AND date(v.fecha) < date(?2)
+           AND v.anulada = 0 AND COALESCE(v.tipo_estado, 'COMPLETADA') IN ('COMPLETADA', 'CONVERTIDA')
+           AND (?3 IS NULL OR v.establecimiento = ?3)
+         GROUP BY m.producto_id, dia",
+    ).map_err(|e| e.to_string())?;
+    let filas: Vec<(i64, i64, f64)> = stmt
+        .query_map(rusqlite::params![desde, hasta, establecimiento], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let mut ventas: HashMap<i64, [f64; 7]> = HashMap::new();
+    for (pid, dia, cantidad) in filas {
+        if (0..7).contains(&dia) {
+            ventas.entry(pid).or_insert([0.0; 7])[dia as usize] += cantidad.max(0.0);
+        }
+    }
+    Ok(ventas)
+}
+
+/// Empaque en que se compra el producto: la presentacion de la ultima compra,
+/// o la activa de mayor factor. (id, nombre, factor, costo por presentacion)
+fn empaque(conn: &Connection, producto_id: i64) -> Option<(i64, String, f64, Option<f64>)> {
+    conn.query_row(
+        "SELECT pp.id, pp.nombre, pp.factor, pp.precio_costo
+         FROM producto_presentaciones pp
+         WHERE pp.producto_id = ?1 AND pp.activo = 1 AND pp.factor > 1
+         ORDER BY (pp.id = (SELECT cd.presentacion_id FROM compra_detalles cd
+                            JOIN compras c ON c.id = cd.compra_id
+                            WHERE cd.producto_id = ?1 AND cd.presentacion_id IS NOT NULL AND c.estado != 'ANULADA'
+                            ORDER BY c.id DESC LIMIT 1)) DESC,
+                  pp.factor DESC
+         LIMIT 1",
+        rusqlite::params![producto_id],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+    )
+    .ok()
+}
+
+pub fn sugerencias(conn: &Connection, params: &ParametrosReposicion) -> Result<Vec<GrupoReposicion>, String> {
+    let ventana = params.dias_ventana.unwrap_or_else(|| config_dias(conn, "reposicion_dias_ventana", 60)).clamp(7, 365);
+    let cobertura = params.dias_cobertura.unwrap_or_else(|| config_dias(conn, "reposicion_dias_cobertura", 15)).max(0);
+    let seguridad = params.dias_seguridad.unwrap_or_else(|| config_dias(conn, "reposicion_dias_seguridad", 3)).max(0);
+    let entrega_default = config_dias(conn, "reposicion_dias_entrega_default", 7).max(0);
+
+    let est_codigo: Option<String> = match params.establecimiento_id {
+        Some(eid) => Some(
+            conn.query_row("SELECT codigo FROM establecimientos WHERE id = ?1", rusqlite::params![eid], |r| r.get(0))
+                .map_err(|_| "Establecimiento no encontrado".to_string())?,
+        ),
+        None => None,
+    };
+
+    // Ventana [hoy - ventana, hoy): solo dias completos
+    let hoy = chrono::Local::now().date_naive();
+    let desde = hoy - chrono::Duration::days(ventana);
+    let ventas = ventas_por_dia_semana(
+        conn,
+        &desde.format("%Y-%m-%d").to_string(),
+        &hoy.format("%Y-%m-%d").to_string(),
+        est_codigo.as_deref(),
+    )?;
+    let mut dias_en_ventana = [0.0_f64; 7];
+    for k in 0..ventana {
+        let d = desde + chrono::Duration::days(k);
+        dias_en_ventana[d.weekday().num_days_from_sunday() as usize] += 1.0;
+    }
+
+    // Proveedores: (nombre, dias_entrega)
+    let mut stmt = conn.prepare("SELECT id, nombre, dias_entrega FROM proveedores").map_err(|e| e.to_string())?;
+    let proveedores: HashMap<i64, (String, i64)> = stmt
+        .query_map([], |r| Ok((r.get::<_, i64>(0)?, (r.get::<_, String>(1)?, r.get::<_, i64>(2)?))))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.codigo, p.nombre, p.stock_actual, p.stock_minimo,
+                COALESCE(p.proveedor_preferido_id,
+                         (SELECT pl.proveedor_id FROM precios_proveedor pl
+                          WHERE pl.producto_id = p.id AND pl.cantidad_minima <= 1
+                            AND pl.vigente_desde <= date('now','localtime')
+                            AND (pl.vigente_hasta IS NULL OR pl.vigente_hasta >= date('now','localtime'))
+                          ORDER BY pl.costo, pl.proveedor_id LIMIT 1),
+                         (SELECT c.proveedor_id FROM compra_detalles cd JOIN compras c ON c.id = cd.compra_id
+                          WHERE cd.producto_id = p.id AND c.estado != 'ANULADA'
+                          ORDER BY c.id DESC LIMIT 1)),
+                (SELECT COALESCE(SUM(l.cantidad - l.cantidad_recibida), 0)
+                 FROM orden_compra_lineas l JOIN ordenes_compra o ON o.id = l.orden_id
+                 WHERE l.producto_id = p.id AND o.estado IN ('BORRADOR', 'ENVIADA', 'RECIBIDA_PARCIAL')
+                   AND (?2 IS NULL OR o.establecimiento_id = ?2))
+         FROM productos p
+         WHERE p.activo = 1 AND p.es_servicio = 0 AND COALESCE(p.no_controla_stock, 0) = 0
+           AND COALESCE(p.tipo_producto, 'SIMPLE') NOT IN ('COMBO_FIJO', 'COMBO_FLEXIBLE')
+           AND NOT EXISTS (SELECT 1 FROM producto_componentes pc WHERE pc.producto_padre_id = p.id)
+           AND NOT EXISTS (SELECT 1 FROM producto_recetas r WHERE r.producto_id = p.id)
+           AND (?1 IS NULL OR p.categoria_id = ?1)",
+    ).map_err(|e| e.to_string())?;
+    #[allow(clippy::type_complexity)]
+    let productos: Vec<(i64, Option<String>, String, f64, f64, Option<i64>, f64)> = stmt
+        .query_map(rusqlite::params![params.categoria_id, params.establecimiento_id], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?, r.get(6)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut grupos: BTreeMap<Option<i64>, GrupoReposicion> = BTreeMap::new();
+    for (pid, codigo, nombre, stock_global, stock_minimo, proveedor_id, en_camino) in productos {
+        if params.proveedor_id.is_some() && params.proveedor_id != proveedor_id {
+            continue;
+        }
+        let stock = match params.establecimiento_id {
+            Some(eid) => crate::commands::stock::stock_en(conn, pid, eid),
+            None => stock_global,
+        };
+        let vendido = ventas.get(&pid).copied().unwrap_or([0.0; 7]);
+        let promedio = vendido.iter().sum::<f64>() / ventana as f64;
+        if promedio <= 0.0 && stock > stock_minimo {
+            continue;
+        }
+        let factores: Vec<f64> = (0..7)
+            .map(|d| {
+                if promedio > 0.0 && dias_en_ventana[d] > 0.0 {
+                    (vendido[d] / dias_en_ventana[d]) / promedio
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+        // Demanda de los proximos n dias (desde mañana) con estacionalidad
+        let demanda = |n: i64| -> f64 {
+            (1..=n)
+                .map(|k| {
+                    let d = hoy + chrono::Duration::days(k);
+                    promedio * factores[d.weekday().num_days_from_sunday() as usize]
+                })
+                .sum()
+        };
+
+        let dias_entrega = proveedor_id
+            .and_then(|id| proveedores.get(&id))
+            .map(|(_, d)| *d)
+            .filter(|d| *d > 0)
+            .unwrap_or(entrega_default);
+        let punto_reorden = demanda(dias_entrega + seguridad);
+        let disponible = stock + en_camino;
+        if disponible > punto_reorden.max(stock_minimo) {
+            continue;
+        }
+        let objetivo = demanda(dias_entrega + seguridad + cobertura).max(stock_minimo);
+        let faltante = objetivo - disponible;
+        if faltante <= 0.0001 {
+            continue;
+        }
+
+        let pack = empaque(conn, pid);
+        let (cantidad, cantidad_presentacion) = match &pack {
+            Some((_, _, factor, _)) => {
+                let cajas = (faltante / factor).ceil();
+                (cajas * factor, Some(cajas))
+            }
+            None => (faltante.ceil(), None),
+        };
+        let costo_unitario = match &pack {
+            Some((_, _, factor, Some(costo_pres))) if *costo_pres > 0.0 => costo_pres / factor,
+            _ => crate::commands::costeo::costo_promedio(conn, pid),
+        };
+
+        let grupo = grupos.entry(proveedor_id).or_insert_with(|| {
+            let (nombre_prov, _) = proveedor_id
+                .and_then(|id| proveedores.get(&id).cloned())
+                .unwrap_or_else(|| ("Sin proveedor".to_string(), 0));
+            GrupoReposicion {
+                proveedor_id,
+                proveedor_nombre: nombre_prov,
+                dias_entrega,
+                total_estimado: 0.0,
+                items: Vec::new(),
+            }
+        });
+        grupo.total_estimado = round2(grupo.total_estimado + cantidad * costo_unitario);
+        grupo.items.push(SugerenciaReposicion {
+            producto_id: pid,
+            codigo,
+            nombre,
+            proveedor_id,
+            stock_actual: stock,
+            stock_minimo,
+            en_camino,
+            venta_promedio_diaria: (promedio * 1000.0).round() / 1000.0,
+            factores_dia_semana: factores.iter().map(|f| round2(*f)).collect(),
+            dias_entrega,
+            punto_reorden: round2(punto_reorden),
+            dias_cobertura_actual: if promedio > 0.0 { Some(round2(disponible.max(0.0) / promedio)) } else { None },
+            cantidad_sugerida: cantidad,
+            presentacion_id: pack.as_ref().map(|p| p.0),
+            presentacion_nombre: pack.as_ref().map(|p| p.1.clone()),
+            presentacion_factor: pack.as_ref().map(|p| p.2).unwrap_or(1.0),
+            cantidad_presentacion,
+            costo_unitario: round2(costo_unitario),
+            costo_estimado: round2(cantidad * costo_unitario),
+        });
+    }
+
+    let mut resultado: Vec<GrupoReposicion> = grupos.into_values().collect();
+    for g in &mut resultado {
+        // Lo mas urgente primero (sin ventas al final)
+        g.items.sort_by(|a, b| {
+            a.dias_cobertura_actual
+                .unwrap_or(f64::MAX)
+                .partial_cmp(&b.dias_cobertura_actual.unwrap_or(f64::MAX))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+    Ok(resultado)
+}
+
+/// Una orden de compra en BORRADOR por proveedor, al costo de su lista vigente
+/// (o al ultimo costo). Retorna los ids.
+pub fn crear_ordenes(
+    conn: &Connection,
+    items: &[ItemReposicion],
+    establecimiento_id: Option<i64>,
+    usuario: &str,
+) -> Result<Vec<i64>, String> {
+    let mut por_proveedor: BTreeMap<i64, Vec<NuevaLineaOrdenCompra>> = BTreeMap::new();
+    for item in items {
+        let (costo, iva): (f64, f64) = conn
+            .query_row(
+                "SELECT COALESCE(precio_costo, 0), COALESCE(iva_porcentaje, 0) FROM productos WHERE id = ?1",
+                rusqlite::params![item.producto_id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .map_err(|_| format!("Producto {} no encontrado", item.producto_id))?;
+        let lista = |cantidad: f64| {
+            crate::commands::costos_proveedor::costo_vigente(conn, item.proveedor_id, item.producto_id, cantidad)
+        };
+        let linea = match (item.presentacion_id, item.cantidad_presentacion) {
+            (Some(pres_id), Some(cant_pres)) => {
+                let (factor, costo_pres): (f64, Option<f64>) = conn
+                    .query_row(
+                        "SELECT factor, precio_costo FROM producto_presentaciones WHERE id = ?1 AND activo = 1",
+                        rusqlite::params![pres_id],
+                        |r| Ok((r.get(0)?, r.get(1)?)),
+                    )
+                    .map_err(|_| "Presentación no encontrada o inactiva".to_string())?;
+                NuevaLineaOrdenCompra {
+                    producto_id: item.producto_id,
+                    descripcion: None,
+                    cantidad: 0.0,
+                    precio_unitario: match lista(cant_pres * factor) {
+                        Some(c) => c * factor,
+                        None => costo_pres.filter(|c| *c > 0.0).unwrap_or(costo * factor),
+                    },
+                    iva_porcentaje: iva,
+                    presentacion_id: Some(pres_id),
+                    cantidad_presentacion: Some(cant_pres),
+                }
+            }
+            _ => NuevaLineaOrdenCompra {
+                producto_id: item.producto_id,
+                descripcion: None,
+                cantidad: item.cantidad,
+                precio_unitario: lista(item.cantidad).unwrap_or(costo),
+                iva_porcentaje: iva,
+                presentacion_id: None,
+                cantidad_presentacion: None,
+            },
+        };
+        por_proveedor.entry(item.proveedor_id).or_default().push(linea);
+    }
+
+    let mut ids = Vec::new();
+    for (proveedor_id, lineas) in por_proveedor {
+        let orden = NuevaOrdenCompra {
+            proveedor_id,
+            establecimiento_id,
+            fecha_entrega_esperada: None,
+            observacion: Some("Generada desde sugerencias de reposición".to_string()),
+            lineas,
+        };
+        ids.push(crate::commands::ordenes_compra::crear(conn, &orden, usuario)?);
+    }
+    Ok(ids)
+}
+
+// ─── Comandos Tauri ──────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn sugerencias_reposicion(
+    db: State<Database>,
+    parametros: Option<ParametrosReposicion>,
+) -> Result<Vec<GrupoReposicion>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    sugerencias(&conn, &parametros.unwrap_or_default())
+}
+
+/// Convierte las sugerencias aceptadas en ordenes de compra BORRADOR.
+#[tauri::command]
+pub fn crear_ordenes_desde_reposicion(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    items: Vec<ItemReposicion>,
+    establecimiento_id: Option<i64>,
+) -> Result<Vec<OrdenCompra>, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_compras")?;
+    if items.is_empty() {
+        return Err("No hay productos seleccionados".to_string());
+    }
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let ids = crear_ordenes(&tx, &items, establecimiento_id, &usuario)?;
+    let ordenes = ids
+        .iter()
+        .map(|id| crate::commands::ordenes_compra::obtener(&tx, *id))
+        .collect::<Result<Vec<_>, _>>()?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(ordenes)
+}
+
+/// Fija (o quita, con None) el proveedor preferido de varios productos.
+#[tauri::command]
+pub fn asignar_proveedor_preferido(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    producto_ids: Vec<i64>,
+    proveedor_id: Option<i64>,
+) -> Result<usize, String> {
+    usuario_con_permiso(&sesion, "gestionar_compras")?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut n = 0;
+    for pid in producto_ids {
+        n += conn
+            .execute(
+                "UPDATE productos SET proveedor_preferido_id = ?1 WHERE id = ?2",
+                rusqlite::params![proveedor_id, pid],
+            )
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(n)
+}
@@ -1,5 +1,6 @@
 use crate::db::{Database, SesionState};
 use crate::models::{OrdenServicio, MovimientoOrden};
+use rusqlite::Connection;
 use tauri::State;
 
 /// v2.4.8 — Verifica que la licencia activa tenga el módulo `servicio_tecnico`.
@@ -40,7 +41,13 @@ pub fn crear_orden_servicio(
         s.as_ref().map(|s| s.nombre.clone()).unwrap_or_else(|| "Sistema".to_string())
     };
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    insertar_orden(&conn, &orden, &usuario)
+}
 
+/// Inserta la orden con su numero OT y el movimiento inicial. Si el equipo
+/// trae una serie registrada en numeros_serie, la orden queda vinculada a
+/// ella (garantias.rs). Compartida con abrir_orden_garantia.
+pub fn insertar_orden(conn: &Connection, orden: &OrdenServicio, usuario: &str) -> Result<i64, String> {
     // v2.4.27: prefijo OT (Orden de Trabajo) — terminologia local en EC.
     // El secuencial continua desde el max de OS- + OT- (compatibilidad con datos viejos).
     let next: i64 = conn.query_row(
@@ -70,7 +77,7 @@ pub fn crear_orden_servicio(
             orden.equipo_serie, orden.equipo_placa, orden.equipo_kilometraje, proximo_calc,
             orden.accesorios, orden.problema_reportado, orden.diagnostico, orden.trabajo_realizado,
             orden.observaciones, orden.tecnico_id, orden.tecnico_nombre,
-            orden.estado, orden.fecha_promesa, orden.presupuesto, orden.garantia_dias, usuario.clone(),
+            orden.estado, orden.fecha_promesa, orden.presupuesto, orden.garantia_dias, usuario,
             orden.tipo_equipo_id, orden.marca_id, orden.modelo_id,
             orden.equipo_kilometraje_intervalo, orden.equipo_kilometraje_salida,
         ],
@@ -84,6 +91,8 @@ pub fn crear_orden_servicio(
         rusqlite::params![id, &orden.estado, "Orden creada", usuario]
     ).ok();
 
+    crate::commands::garantias::vincular_orden_serie(conn, id, orden.equipo_serie.as_deref())?;
+
     Ok(id)
 }
 
@@ -188,6 +197,9 @@ pub fn cambiar_estado_orden(
         "INSERT INTO ordenes_servicio_movimientos (orden_id, estado_anterior, estado_nuevo, observacion, usuario) VALUES (?1, ?2, ?3, ?4, ?5)",
         rusqlite::params![orden_id, estado_anterior, nuevo_estado, observacion, usuario]
     ).ok();
+    crate::webhooks::emitir_estado_orden(
+        &conn, orden_id, Some(&estado_anterior), &nuevo_estado, observacion.as_deref(), &usuario,
+    );
     Ok(())
 }
 
@@ -639,6 +651,13 @@ pub fn cobrar_orden_servicio(
     // ─── Insertar venta ──────────────────────────────────────────────────
     let tx = conn.transaction().map_err(|e| e.to_string())?;
 
+    // El stock sale del establecimiento de la terminal; la venta lo guarda
+    // para que una anulación lo devuelva al mismo lugar.
+    let terminal_est: String = tx
+        .query_row("SELECT value FROM config WHERE key = 'terminal_establecimiento'", [], |r| r.get(0))
+        .unwrap_or_else(|_| "001".to_string());
+    let est_id = crate::commands::stock::establecimiento_por_codigo(&tx, &terminal_est)?;
+
     let next_seq: i64 = tx.query_row(
         "SELECT COALESCE(MAX(CAST(SUBSTR(numero, 4) AS INTEGER)), 0) + 1 FROM ventas WHERE numero LIKE 'NV-%'",
         [], |r| r.get(0),
@@ -652,8 +671,8 @@ pub fn cobrar_orden_servicio(
     };
 
     tx.execute(
-        "INSERT INTO ventas (numero, cliente_id, subtotal_sin_iva, subtotal_con_iva, descuento, iva, total, forma_pago, monto_recibido, cambio, estado, tipo_documento, estado_sri, observacion, usuario, usuario_id, tipo_estado) VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6, ?7, ?8, ?9, 'COMPLETADA', 'NOTA_VENTA', 'NO_APLICA', ?10, ?11, ?12, 'COMPLETADA')",
-        rusqlite::params![numero, cliente_id, subtotal_sin_iva, subtotal_con_iva, iva_total, total, forma_pago_principal, monto_recibido_total, cambio, observacion, usuario, usuario_id],
+        "INSERT INTO ventas (numero, cliente_id, subtotal_sin_iva, subtotal_con_iva, descuento, iva, total, forma_pago, monto_recibido, cambio, estado, tipo_documento, estado_sri, observacion, usuario, usuario_id, tipo_estado, establecimiento) VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6, ?7, ?8, ?9, 'COMPLETADA', 'NOTA_VENTA', 'NO_APLICA', ?10, ?11, ?12, 'COMPLETADA', ?13)",
+        rusqlite::params![numero, cliente_id, subtotal_sin_iva, subtotal_con_iva, iva_total, total, forma_pago_principal, monto_recibido_total, cambio, observacion, usuario, usuario_id, terminal_est],
     ).map_err(|e| e.to_string())?;
     let venta_id = tx.last_insert_rowid();
 
@@ -685,21 +704,20 @@ pub fn cobrar_orden_servicio(
                 if !es_combo {
                     // Producto simple: descontar del padre y registrar kardex con motivo
                     // (v2.5.27: agregar trazabilidad en kardex — antes solo hacía UPDATE)
-                    let (stock_p_antes, costo_p): (f64, f64) = tx.query_row(
-                        "SELECT stock_actual, precio_costo FROM productos WHERE id = ?1 AND COALESCE(es_servicio,0) = 0 AND COALESCE(no_controla_stock,0) = 0",
+                    let costo_p: Option<f64> = tx.query_row(
+                        "SELECT precio_costo FROM productos WHERE id = ?1 AND COALESCE(es_servicio,0) = 0 AND COALESCE(no_controla_stock,0) = 0",
                         rusqlite::params![pid],
-                        |row| Ok((row.get::<_, f64>(0)?, row.get::<_, f64>(1)?)),
-                    ).unwrap_or((0.0, 0.0));
-                    tx.execute(
-                        "UPDATE productos SET stock_actual = stock_actual - ?1 WHERE id = ?2 AND es_servicio = 0 AND no_controla_stock = 0",
-                        rusqlite::params![it.cantidad, pid],
+                        |row| row.get(0),
                     ).ok();
-                    let motivo_st = format!("Venta ST {} (orden {})", numero, numero_orden);
-                    let _ = tx.execute(
-                        "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, establecimiento_id, motivo)
-                         VALUES (?1, 'VENTA', ?2, ?3, ?4, ?5, ?6, ?7, NULL, ?8)",
-                        rusqlite::params![pid, -it.cantidad, stock_p_antes, stock_p_antes - it.cantidad, costo_p, venta_id, usuario, motivo_st],
-                    );
+                    if let Some(costo_p) = costo_p {
+                        let mov = crate::commands::stock::mover(&tx, pid, est_id, -it.cantidad)?;
+                        let motivo_st = format!("Venta ST {} (orden {})", numero, numero_orden);
+                        let _ = tx.execute(
+                            "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, establecimiento_id, motivo)
+                             VALUES (?1, 'VENTA', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                            rusqlite::params![pid, -it.cantidad, mov.anterior, mov.nuevo, costo_p, venta_id, usuario, est_id, motivo_st],
+                        );
+                    }
                 } else {
                     // Combo: descontar componentes según producto_componentes
                     // Obtener nombre del combo padre para incluirlo en el motivo del kardex
@@ -732,21 +750,19 @@ pub fn cobrar_orden_servicio(
                     for (hijo_id, cant_componente) in componentes {
                         let cant_total = cant_componente * it.cantidad;
                         // Solo descontar si el hijo controla stock (no servicio, no no_controla_stock)
-                        tx.execute(
-                            "UPDATE productos SET stock_actual = stock_actual - ?1
-                             WHERE id = ?2 AND COALESCE(es_servicio,0) = 0 AND COALESCE(no_controla_stock,0) = 0",
-                            rusqlite::params![cant_total, hijo_id],
+                        let costo_h: Option<f64> = tx.query_row(
+                            "SELECT precio_costo FROM productos
+                             WHERE id = ?1 AND COALESCE(es_servicio,0) = 0 AND COALESCE(no_controla_stock,0) = 0",
+                            rusqlite::params![hijo_id],
+                            |r| r.get(0),
                         ).ok();
+                        let Some(costo_h) = costo_h else { continue };
+                        let mov = crate::commands::stock::mover(&tx, hijo_id, est_id, -cant_total)?;
                         // Registrar movimiento de kardex (VENTA_COMBO) con motivo trazable
-                        let (stock_h_antes, costo_h): (f64, f64) = tx.query_row(
-                            "SELECT stock_actual + ?1, precio_costo FROM productos WHERE id = ?2",
-                            rusqlite::params![cant_total, hijo_id],
-                            |r| Ok((r.get::<_, f64>(0)?, r.get::<_, f64>(1)?)),
-                        ).unwrap_or((0.0, 0.0));
                         let _ = tx.execute(
                             "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, establecimiento_id, motivo)
-                             VALUES (?1, 'VENTA_COMBO', ?2, ?3, ?4, ?5, ?6, ?7, NULL, ?8)",
-                            rusqlite::params![hijo_id, -cant_total, stock_h_antes, stock_h_antes - cant_total, costo_h, venta_id, usuario, motivo_combo],
+                             VALUES (?1, 'VENTA_COMBO', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                            rusqlite::params![hijo_id, -cant_total, mov.anterior, mov.nuevo, costo_h, venta_id, usuario, est_id, motivo_combo],
                         );
                     }
                 }
@@ -787,6 +803,7 @@ pub fn cobrar_orden_servicio(
         "INSERT INTO ordenes_servicio_movimientos (orden_id, estado_anterior, estado_nuevo, observacion, usuario) VALUES (?1, 'LISTO', ?2, ?3, ?4)",
         rusqlite::params![orden_id, estado_final, obs_mov, usuario],
     ).ok();
+    crate::webhooks::emitir_estado_orden(&tx, orden_id, Some("LISTO"), estado_final, Some(&obs_mov), &usuario);
 
     tx.commit().map_err(|e| e.to_string())?;
     Ok(venta_id)
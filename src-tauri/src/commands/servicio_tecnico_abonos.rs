@@ -416,6 +416,9 @@ pub fn st_cancelar_orden(
          VALUES (?1, ?2, 'CANCELADA', ?3, ?4)",
         params![orden_id, estado_actual, obs_completa, usuario_nombre],
     ).ok();
+    crate::webhooks::emitir_estado_orden(
+        &tx, orden_id, Some(&estado_actual), "CANCELADA", Some(&obs_completa), &usuario_nombre,
+    );
 
     tx.commit().map_err(|e| e.to_string())?;
 
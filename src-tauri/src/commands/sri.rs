@@ -1,4 +1,4 @@
-use crate::db::Database;
+use crate::db::{usuario_con_permiso, Database, SesionState};
 use crate::sri::{clave_acceso, firma, soap, suscripcion, xml};
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
@@ -150,6 +150,7 @@ struct DatosVentaSri {
     estado_sri: String,
     clave_acceso_previa: Option<String>,
     xml_firmado_previo: Option<String>,
+    propina: f64,
 }
 
 /// Datos de detalle para emision SRI
@@ -351,7 +352,8 @@ pub async fn emitir_factura_sri_internal(
 
         // Leer venta
         let venta = conn.query_row(
-            "SELECT numero, cliente_id, fecha, descuento, forma_pago, tipo_documento, estado_sri, clave_acceso, xml_firmado, numero_factura
+            "SELECT numero, cliente_id, fecha, descuento, forma_pago, tipo_documento, estado_sri, clave_acceso, xml_firmado, numero_factura,
+                    COALESCE(propina, 0)
              FROM ventas WHERE id = ?1",
             rusqlite::params![venta_id],
             |row| {
@@ -366,6 +368,7 @@ pub async fn emitir_factura_sri_internal(
                     estado_sri: row.get::<_, String>(6).unwrap_or_else(|_| "PENDIENTE".to_string()),
                     clave_acceso_previa: row.get::<_, Option<String>>(7)?,
                     xml_firmado_previo: row.get::<_, Option<String>>(8)?,
+                    propina: row.get(10)?,
                 })
             },
         ).map_err(|e| format!("Venta no encontrada: {}", e))?;
@@ -607,7 +610,8 @@ pub async fn emitir_factura_sri_internal(
         }
 
         let total_sin_impuestos = subtotal_iva_0 + subtotal_iva_15;
-        let importe_total = total_sin_impuestos + iva_total;
+        let propina = (venta_data.propina * 100.0).round() / 100.0;
+        let importe_total = total_sin_impuestos + iva_total + propina;
 
         let contribuyente_rimpe = match regimen.as_str() {
             "RIMPE_EMPRENDEDOR" => Some("CONTRIBUYENTE RÉGIMEN RIMPE".to_string()),
@@ -635,8 +639,11 @@ pub async fn emitir_factura_sri_internal(
             drop(conn2);
 
             if !rows.is_empty() {
-                // Pago mixto: mapear cada uno
-                rows.into_iter().map(|(forma, monto)| {
+                // Pago mixto: mapear cada uno. pagos_venta cubre la venta sin
+                // propina; la propina se suma al último pago para cuadrar importeTotal.
+                let ultimo = rows.len() - 1;
+                rows.into_iter().enumerate().map(|(i, (forma, monto))| {
+                    let monto = if i == ultimo { monto + propina } else { monto };
                     // Si es credito y hay override, usar el override
                     let codigo = if forma.eq_ignore_ascii_case("CREDITO") {
                         forma_pago_credito_sri.clone().unwrap_or_else(|| "20".to_string())
@@ -695,6 +702,7 @@ pub async fn emitir_factura_sri_internal(
             direccion_comprador: cliente_data.direccion.clone(),
             total_sin_impuestos,
             total_descuento: venta_data.descuento,
+            propina,
             importe_total,
             impuestos_totales,
             pagos: pagos_xml,
@@ -769,6 +777,24 @@ pub async fn emitir_factura_sri_internal(
                     venta_id,
                 ],
             ).map_err(|e| format!("Error actualizando venta: {}", e))?;
+
+            let total_venta: f64 = conn
+                .query_row("SELECT total FROM ventas WHERE id = ?1", rusqlite::params![venta_id], |r| r.get(0))
+                .unwrap_or(0.0);
+            crate::webhooks::emitir(
+                &conn,
+                "factura.autorizada",
+                &format!("venta:{}", venta_id),
+                serde_json::json!({
+                    "venta_id": venta_id,
+                    "numero": venta_data.numero,
+                    "numero_factura": nf_para_guardar,
+                    "clave_acceso": clave,
+                    "numero_autorizacion": resultado_sri.numero_autorizacion,
+                    "fecha_autorizacion": resultado_sri.fecha_autorizacion,
+                    "total": total_venta,
+                }),
+            );
         } else {
             // PENDIENTE o RECHAZADA — mantener tipo_documento como estaba (NOTA_VENTA)
             conn.execute(
@@ -895,6 +921,158 @@ struct DatosGuiaSri {
     dir_destino: String,
 }
 
+/// La guía de remisión electrónica requiere el módulo `contabilidad` (o modo demo).
+fn verificar_modulo_guias(conn: &Connection) -> Result<(), String> {
+    let getc = |k: &str| -> String {
+        conn.query_row("SELECT value FROM config WHERE key=?1", rusqlite::params![k], |r| r.get(0))
+            .unwrap_or_default()
+    };
+    let demo = getc("demo_activo") == "1";
+    let mods = getc("licencia_modulos");
+    let tiene_contab = mods.contains("contabilidad") || mods.contains("sri_avanzado");
+    if !demo && !tiene_contab {
+        return Err("La guía de remisión electrónica requiere el módulo Contabilidad. Actívelo en su licencia.".to_string());
+    }
+    Ok(())
+}
+
+/// Config (key → value) y certificado P12 para firmar una guía.
+fn cargar_config_firma(
+    conn: &Connection,
+) -> Result<(std::collections::HashMap<String, String>, Vec<u8>, String), String> {
+    let mut config: std::collections::HashMap<String, String> = std::collections::HashMap::new();
+    let mut sc = conn.prepare("SELECT key, value FROM config").map_err(|e| e.to_string())?;
+    let rows = sc.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?;
+    for r in rows { let (k, v) = r.map_err(|e| e.to_string())?; config.insert(k, v); }
+    drop(sc);
+
+    let (p12_blob, p12_pass): (Vec<u8>, String) = conn.query_row(
+        "SELECT p12_data, password FROM sri_certificado WHERE id = 1", [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|_| "No hay certificado digital cargado. Cargue un P12 primero.".to_string())?;
+    Ok((config, p12_blob, p12_pass))
+}
+
+fn leyenda_rimpe(regimen: &str) -> Option<String> {
+    match regimen {
+        "RIMPE_EMPRENDEDOR" => Some("CONTRIBUYENTE RÉGIMEN RIMPE".to_string()),
+        "RIMPE_POPULAR" => Some("CONTRIBUYENTE NEGOCIO POPULAR - RÉGIMEN RIMPE".to_string()),
+        _ => None,
+    }
+}
+
+/// Tipo de identificación del transportista: el declarado o, si falta, el
+/// que corresponde al largo (RUC, cédula, pasaporte).
+fn tipo_id_transportista(declarado: &str, identificacion: &str) -> String {
+    if !declarado.trim().is_empty() { declarado.to_string() }
+    else if identificacion.len() == 13 { "04".to_string() }
+    else if identificacion.len() == 10 { "05".to_string() }
+    else { "06".to_string() }
+}
+
+/// Datos comunes de una emisión de guía (venta o transferencia).
+struct EmisionGuia {
+    ambiente: &'static str,
+    ruc: String,
+    establecimiento: String,
+    punto_emision: String,
+    fecha_emision: String,
+    estado_previo: String,
+    clave_previa: Option<String>,
+    xml_previo: Option<String>,
+    p12_data: Vec<u8>,
+    p12_password: String,
+}
+
+/// Firma, envía y guarda una guía de remisión (codDoc 06).
+///
+/// Si quedó PENDIENTE con clave y XML, consulta la autorización o reenvía el
+/// mismo comprobante. Si no, toma el secuencial y `armar(clave, secuencial)`
+/// arma los datos del XML propios de cada documento. `sql_guardar` recibe
+/// estado, clave, autorización, xml, fecha de autorización, número e `id`.
+async fn emitir_guia(
+    db: &Database,
+    e: EmisionGuia,
+    armar: impl FnOnce(&str, &str) -> xml::DatosGuiaRemision,
+    sql_guardar: &str,
+    id: i64,
+    mensaje_ok: &str,
+) -> Result<ResultadoEmision, String> {
+    let ambiente = e.ambiente;
+    let tipo_doc_sec = if ambiente == "1" { "GUIA_REMISION_PRUEBAS" } else { "GUIA_REMISION" };
+    let mut numero_sri = String::new();
+    let mut es_primera = false;
+
+    let (clave, xml_firmado_final, resultado_sri) = match (e.estado_previo.as_str(), e.clave_previa, e.xml_previo) {
+        ("PENDIENTE", Some(clave_previa), Some(xml_previo)) => {
+            soap::log_sri(&format!("=== REENVIO GUIA: consultando clave previa: {} ===", clave_previa));
+            let consulta = soap::consultar_autorizacion(&clave_previa, ambiente).await;
+            match consulta {
+                Ok(res) if res.exito => (clave_previa, xml_previo, res),
+                _ => {
+                    let r = soap::enviar_comprobante(&xml_previo, &clave_previa, ambiente).await?;
+                    (clave_previa, xml_previo, r)
+                }
+            }
+        }
+        _ => {
+            es_primera = true;
+            let secuencial_sri = {
+                let conn = db.conn.lock().map_err(|e| e.to_string())?;
+                obtener_secuencial(&conn, &e.establecimiento, &e.punto_emision, tipo_doc_sec)?
+            };
+            let secuencial = format!("{:09}", secuencial_sri);
+            numero_sri = format!("{}-{}-{}", e.establecimiento, e.punto_emision, secuencial);
+
+            let clave_nueva = clave_acceso::generar_clave_acceso(
+                &e.fecha_emision, "06", &e.ruc, ambiente, &e.establecimiento, &e.punto_emision, &secuencial, "1",
+            );
+            let datos = armar(&clave_nueva, &secuencial);
+            let xml_sin_firma = xml::generar_xml_guia_remision(&datos);
+            soap::log_sri(&format!("XML guía sin firma ({} bytes):\n{}", xml_sin_firma.len(), xml_sin_firma));
+            let firmado = firma::firmar_comprobante(&xml_sin_firma, &e.p12_data, &e.p12_password, "guiaRemision")?;
+            let r = soap::enviar_comprobante(&firmado.xml, &clave_nueva, ambiente).await?;
+            (clave_nueva, firmado.xml, r)
+        }
+    };
+
+    {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let nuevo_estado = if resultado_sri.exito { "AUTORIZADA" }
+                           else if resultado_sri.estado == "EN_PROCESO" { "PENDIENTE" }
+                           else { "RECHAZADA" };
+        let xml_guardar = if resultado_sri.exito || resultado_sri.estado == "EN_PROCESO" {
+            Some(xml_firmado_final)
+        } else { None };
+        let nf_guardar = if !numero_sri.is_empty() { Some(numero_sri.clone()) } else { None };
+        conn.execute(
+            sql_guardar,
+            rusqlite::params![
+                nuevo_estado, clave.clone(), resultado_sri.numero_autorizacion,
+                xml_guardar, resultado_sri.fecha_autorizacion.as_deref(), nf_guardar, id,
+            ],
+        ).map_err(|e| format!("Error guardando la guía: {}", e))?;
+
+        if resultado_sri.exito && es_primera {
+            incrementar_secuencial(&conn, &e.establecimiento, &e.punto_emision, tipo_doc_sec).ok();
+        }
+    }
+
+    Ok(ResultadoEmision {
+        exito: resultado_sri.exito,
+        estado_sri: resultado_sri.estado.clone(),
+        clave_acceso: Some(clave),
+        numero_autorizacion: resultado_sri.numero_autorizacion,
+        fecha_autorizacion: resultado_sri.fecha_autorizacion,
+        mensaje: resultado_sri.mensaje.unwrap_or_else(|| {
+            if resultado_sri.exito { mensaje_ok.to_string() }
+            else { format!("Estado: {}", resultado_sri.estado) }
+        }),
+        numero_factura: if !numero_sri.is_empty() { Some(numero_sri) } else { None },
+    })
+}
+
 /// Emite al SRI una guía de remisión electrónica (codDoc 06).
 /// Requiere el módulo `contabilidad` activo (o modo demo).
 #[tauri::command]
@@ -905,16 +1083,7 @@ pub async fn emitir_guia_remision_sri(
     // 0. Gating: módulo contabilidad
     {
         let conn = db.conn.lock().map_err(|e| e.to_string())?;
-        let getc = |k: &str| -> String {
-            conn.query_row("SELECT value FROM config WHERE key=?1", rusqlite::params![k], |r| r.get(0))
-                .unwrap_or_default()
-        };
-        let demo = getc("demo_activo") == "1";
-        let mods = getc("licencia_modulos");
-        let tiene_contab = mods.contains("contabilidad") || mods.contains("sri_avanzado");
-        if !demo && !tiene_contab {
-            return Err("La guía de remisión electrónica requiere el módulo Contabilidad. Actívelo en su licencia.".to_string());
-        }
+        verificar_modulo_guias(&conn)?;
     }
 
     // 1. Leer guía, detalles, cliente (destinatario), config y certificado P12
@@ -999,20 +1168,7 @@ pub async fn emitir_guia_remision_sri(
             )),
         ).map_err(|e| format!("Cliente no encontrado: {}", e))?;
 
-        // config
-        let mut config: std::collections::HashMap<String, String> = std::collections::HashMap::new();
-        let mut sc = conn.prepare("SELECT key, value FROM config").map_err(|e| e.to_string())?;
-        let rows = sc.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
-            .map_err(|e| e.to_string())?;
-        for r in rows { let (k, v) = r.map_err(|e| e.to_string())?; config.insert(k, v); }
-        drop(sc);
-
-        // certificado P12
-        let (p12_blob, p12_pass): (Vec<u8>, String) = conn.query_row(
-            "SELECT p12_data, password FROM sri_certificado WHERE id = 1", [],
-            |row| Ok((row.get(0)?, row.get(1)?)),
-        ).map_err(|_| "No hay certificado digital cargado. Cargue un P12 primero.".to_string())?;
-
+        let (config, p12_blob, p12_pass) = cargar_config_firma(&conn)?;
         (g, detalles, cliente, config, p12_blob, p12_pass)
     };
 
@@ -1037,8 +1193,6 @@ pub async fn emitir_guia_remision_sri(
     let establecimiento = if establecimiento.is_empty() { "001".to_string() } else { establecimiento };
     let punto_emision = { let t = cfg("terminal_punto_emision"); if t.is_empty() { cfg("punto_emision") } else { t } };
     let punto_emision = if punto_emision.is_empty() { "001".to_string() } else { punto_emision };
-    let regimen = cfg("regimen");
-    let tipo_doc_sec = if ambiente == "1" { "GUIA_REMISION_PRUEBAS" } else { "GUIA_REMISION" };
 
     let fecha_emision = formatear_fecha_emision(&g.fecha)?;
     let fecha_ini = if g.fecha_inicio.trim().is_empty() { fecha_emision.clone() }
@@ -1046,37 +1200,9 @@ pub async fn emitir_guia_remision_sri(
     let fecha_fin = if g.fecha_fin.trim().is_empty() { fecha_ini.clone() }
                     else { formatear_fecha_emision(&g.fecha_fin).unwrap_or_else(|_| fecha_ini.clone()) };
 
-    let mut numero_sri = String::new();
-    let mut es_primera = false;
-
-    // 3. Emisión (con reenvío si estaba PENDIENTE)
-    let (clave, xml_firmado_final, resultado_sri) = if g.estado_sri == "PENDIENTE"
-        && g.clave_acceso_previa.is_some() && g.xml_firmado_previo.is_some()
-    {
-        let clave_previa = g.clave_acceso_previa.clone().unwrap();
-        let xml_previo = g.xml_firmado_previo.clone().unwrap();
-        soap::log_sri(&format!("=== REENVIO GUIA: consultando clave previa: {} ===", clave_previa));
-        let consulta = soap::consultar_autorizacion(&clave_previa, ambiente).await;
-        match consulta {
-            Ok(ref res) if res.exito => (clave_previa, xml_previo, consulta.unwrap()),
-            _ => {
-                let r = soap::enviar_comprobante(&xml_previo, &clave_previa, ambiente).await?;
-                (clave_previa, xml_previo, r)
-            }
-        }
-    } else {
-        es_primera = true;
-        let secuencial_sri = {
-            let conn = db.conn.lock().map_err(|e| e.to_string())?;
-            obtener_secuencial(&conn, &establecimiento, &punto_emision, tipo_doc_sec)?
-        };
-        let secuencial = format!("{:09}", secuencial_sri);
-        numero_sri = format!("{}-{}-{}", establecimiento, punto_emision, secuencial);
-
-        let clave_nueva = clave_acceso::generar_clave_acceso(
-            &fecha_emision, "06", &ruc, ambiente, &establecimiento, &punto_emision, &secuencial, "1",
-        );
-
+    // 3. XML propio de la guía de venta: destinatario = cliente, con documento sustento
+    let (estab_xml, pto_xml) = (establecimiento.clone(), punto_emision.clone());
+    let armar = |clave: &str, secuencial: &str| {
         let (cli_id, cli_nombre, cli_dir) = &cliente_data;
         let ident_dest = if cli_id.trim().is_empty() { "9999999999999".to_string() } else { cli_id.clone() };
         let dir_dest = if g.dir_destino.trim().is_empty() { cli_dir.clone() } else { g.dir_destino.clone() };
@@ -1088,12 +1214,6 @@ pub async fn emitir_guia_remision_sri(
             cantidad: *cant,
         }).collect();
 
-        let contribuyente_rimpe = match regimen.as_str() {
-            "RIMPE_EMPRENDEDOR" => Some("CONTRIBUYENTE RÉGIMEN RIMPE".to_string()),
-            "RIMPE_POPULAR" => Some("CONTRIBUYENTE NEGOCIO POPULAR - RÉGIMEN RIMPE".to_string()),
-            _ => None,
-        };
-
         let destinatario = xml::DestinatarioGuia {
             identificacion_destinatario: ident_dest,
             razon_social_destinatario: cli_nombre.clone(),
@@ -1110,84 +1230,245 @@ pub async fn emitir_guia_remision_sri(
             detalles: detalles_guia,
         };
 
-        let tipo_id_transp = if g.tipo_id_transportista.trim().is_empty() {
-            if g.ruc_transportista.len() == 13 { "04".to_string() }
-            else if g.ruc_transportista.len() == 10 { "05".to_string() }
-            else { "06".to_string() }
-        } else { g.tipo_id_transportista.clone() };
-
-        let datos = xml::DatosGuiaRemision {
+        xml::DatosGuiaRemision {
             ambiente: ambiente.to_string(),
             tipo_emision: "1".to_string(),
             razon_social: cfg("nombre_negocio"),
             nombre_comercial: cfg("nombre_negocio"),
             ruc: ruc.clone(),
-            clave_acceso: clave_nueva.clone(),
-            estab: establecimiento.clone(),
-            pto_emi: punto_emision.clone(),
-            secuencial: secuencial.clone(),
+            clave_acceso: clave.to_string(),
+            estab: estab_xml,
+            pto_emi: pto_xml,
+            secuencial: secuencial.to_string(),
             dir_matriz: cfg("direccion"),
-            contribuyente_rimpe,
+            contribuyente_rimpe: leyenda_rimpe(&cfg("regimen")),
             dir_establecimiento: cfg("direccion"),
             dir_partida: g.dir_partida.clone(),
             razon_social_transportista: g.transportista.clone(),
-            tipo_identificacion_transportista: tipo_id_transp,
+            tipo_identificacion_transportista: tipo_id_transportista(&g.tipo_id_transportista, &g.ruc_transportista),
             ruc_transportista: g.ruc_transportista.clone(),
             rise: None,
             obligado_contabilidad: Some("NO".to_string()),
             contribuyente_especial: None,
-            fecha_ini_transporte: fecha_ini.clone(),
-            fecha_fin_transporte: fecha_fin.clone(),
+            fecha_ini_transporte: fecha_ini,
+            fecha_fin_transporte: fecha_fin,
             placa: g.placa.clone(),
             destinatarios: vec![destinatario],
             info_adicional: vec![],
-        };
+        }
+    };
 
-        let xml_sin_firma = xml::generar_xml_guia_remision(&datos);
-        soap::log_sri(&format!("XML guía sin firma ({} bytes):\n{}", xml_sin_firma.len(), xml_sin_firma));
-        let firmado = firma::firmar_comprobante(&xml_sin_firma, &p12_data, &p12_password, "guiaRemision")?;
-        let r = soap::enviar_comprobante(&firmado.xml, &clave_nueva, ambiente).await?;
-        (clave_nueva, firmado.xml, r)
+    // 4. Emisión (con reenvío si estaba PENDIENTE) y actualización de la guía
+    let emision = EmisionGuia {
+        ambiente,
+        ruc: ruc.clone(),
+        establecimiento,
+        punto_emision,
+        fecha_emision,
+        estado_previo: g.estado_sri.clone(),
+        clave_previa: g.clave_acceso_previa.clone(),
+        xml_previo: g.xml_firmado_previo.clone(),
+        p12_data,
+        p12_password,
     };
+    emitir_guia(
+        &db,
+        emision,
+        armar,
+        "UPDATE ventas SET estado_sri = ?1, clave_acceso = ?2, autorizacion_sri = ?3,
+                           xml_firmado = ?4, fecha_autorizacion = ?5,
+                           numero_factura = COALESCE(?6, numero_factura)
+         WHERE id = ?7",
+        guia_id,
+        "Guía de remisión autorizada correctamente",
+    )
+    .await
+}
 
-    // 4. Actualizar la guía
-    {
+// ════════════════════════════════════════════════════════════════════
+// Guía de remisión de una transferencia entre establecimientos
+// ════════════════════════════════════════════════════════════════════
+
+/// Emite al SRI la guía de remisión (codDoc 06) de un documento de
+/// transferencia (commands::transferencias). Se arma sola desde el documento:
+/// el emisor es el establecimiento de origen, el destinatario es el propio
+/// negocio en el establecimiento de destino y los detalles son las líneas
+/// enviadas (el lote va como código adicional; las series en info adicional).
+#[tauri::command]
+pub async fn emitir_guia_transferencia_sri(
+    db: State<'_, Database>,
+    sesion: State<'_, SesionState>,
+    transferencia_id: i64,
+) -> Result<ResultadoEmision, String> {
+    usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    // 1. Leer documento, establecimientos, config y certificado P12
+    let (doc, origen, destino, pto_origen, config_data, p12_data, p12_password, xml_previo) = {
         let conn = db.conn.lock().map_err(|e| e.to_string())?;
-        let nuevo_estado = if resultado_sri.exito { "AUTORIZADA" }
-                           else if resultado_sri.estado == "EN_PROCESO" { "PENDIENTE" }
-                           else { "RECHAZADA" };
-        let xml_guardar = if resultado_sri.exito || resultado_sri.estado == "EN_PROCESO" {
-            Some(xml_firmado_final.clone())
-        } else { None };
-        let nf_guardar = if !numero_sri.is_empty() { Some(numero_sri.clone()) } else { None };
-        conn.execute(
-            "UPDATE ventas SET estado_sri = ?1, clave_acceso = ?2, autorizacion_sri = ?3,
-                               xml_firmado = ?4, fecha_autorizacion = ?5,
-                               numero_factura = COALESCE(?6, numero_factura)
-             WHERE id = ?7",
-            rusqlite::params![
-                nuevo_estado, clave.clone(), resultado_sri.numero_autorizacion,
-                xml_guardar, resultado_sri.fecha_autorizacion.as_deref(), nf_guardar, guia_id,
-            ],
-        ).map_err(|e| format!("Error actualizando guía: {}", e))?;
+        verificar_modulo_guias(&conn)?;
 
-        if resultado_sri.exito && es_primera {
-            incrementar_secuencial(&conn, &establecimiento, &punto_emision, tipo_doc_sec).ok();
+        let doc = super::transferencias::obtener_documento(&conn, transferencia_id)?;
+        if doc.guia_estado_sri == "AUTORIZADA" {
+            return Err("La guía de esta transferencia ya fue autorizada por el SRI".to_string());
         }
+        let xml_previo: Option<String> = conn
+            .query_row(
+                "SELECT guia_xml_firmado FROM transferencias_documento WHERE id = ?1",
+                rusqlite::params![transferencia_id],
+                |r| r.get(0),
+            )
+            .unwrap_or(None);
+
+        let leer_estab = |id: i64| -> Result<(String, String), String> {
+            conn.query_row(
+                "SELECT codigo, COALESCE(direccion, '') FROM establecimientos WHERE id = ?1",
+                rusqlite::params![id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            ).map_err(|e| format!("Establecimiento no encontrado: {}", e))
+        };
+        let origen = leer_estab(doc.origen_establecimiento_id)?;
+        let destino = leer_estab(doc.destino_establecimiento_id)?;
+        let pto_origen: Option<String> = conn
+            .query_row(
+                "SELECT codigo FROM puntos_emision WHERE establecimiento_id = ?1 AND activo = 1 ORDER BY id LIMIT 1",
+                rusqlite::params![doc.origen_establecimiento_id],
+                |r| r.get(0),
+            )
+            .ok();
+
+        let (config, p12_blob, p12_pass) = cargar_config_firma(&conn)?;
+        (doc, origen, destino, pto_origen, config, p12_blob, p12_pass, xml_previo)
+    };
+
+    // 2. Validaciones
+    let cfg = |key: &str| -> String { config_data.get(key).cloned().unwrap_or_default() };
+    let ruc = cfg("ruc");
+    if ruc.is_empty() || ruc.len() != 13 {
+        return Err("Configure el RUC del negocio (13 dígitos) antes de emitir guías".to_string());
+    }
+    let transportista = doc.transportista.clone().unwrap_or_default();
+    let ruc_transportista = doc.ruc_transportista.clone().unwrap_or_default();
+    if transportista.is_empty() || ruc_transportista.is_empty() {
+        return Err("Falta el transportista (razón social e identificación) en la transferencia".to_string());
+    }
+    let placa = doc.placa.clone().unwrap_or_default();
+    if placa.is_empty() {
+        return Err("Falta la placa del vehículo en la transferencia".to_string());
+    }
+    let detalles_guia: Vec<xml::DetalleGuia> = doc.lineas.iter().map(|l| xml::DetalleGuia {
+        codigo_interno: Some(l.producto_codigo.clone().unwrap_or_else(|| "SIN-COD".to_string())),
+        codigo_adicional: l.lote.clone(),
+        descripcion: l.producto_nombre.clone(),
+        cantidad: l.cantidad_enviada,
+    }).collect();
+    if detalles_guia.is_empty() {
+        return Err("La transferencia no tiene productos para transportar".to_string());
     }
 
-    Ok(ResultadoEmision {
-        exito: resultado_sri.exito,
-        estado_sri: resultado_sri.estado.clone(),
-        clave_acceso: Some(clave),
-        numero_autorizacion: resultado_sri.numero_autorizacion,
-        fecha_autorizacion: resultado_sri.fecha_autorizacion,
-        mensaje: resultado_sri.mensaje.unwrap_or_else(|| {
-            if resultado_sri.exito { "Guía de remisión autorizada correctamente".to_string() }
-            else { format!("Estado: {}", resultado_sri.estado) }
-        }),
-        numero_factura: if !numero_sri.is_empty() { Some(numero_sri) } else { None },
-    })
+    let ambiente = match cfg("sri_ambiente").as_str() { "produccion" => "2", _ => "1" };
+    let establecimiento = format!("{:0>3}", origen.0.trim());
+    let punto_emision = pto_origen.map(|p| format!("{:0>3}", p.trim())).unwrap_or_else(|| {
+        let t = cfg("punto_emision");
+        if t.is_empty() { "001".to_string() } else { t }
+    });
+    let dir_matriz = cfg("direccion");
+    let dir_partida = if origen.1.trim().is_empty() { dir_matriz.clone() } else { origen.1.clone() };
+    let dir_destino = if destino.1.trim().is_empty() { dir_matriz.clone() } else { destino.1.clone() };
+
+    let fecha_emision = formatear_fecha_emision(&doc.fecha_creacion)?;
+    let fecha_ini = doc.fecha_inicio_transporte.as_deref()
+        .and_then(|f| formatear_fecha_emision(f).ok())
+        .unwrap_or_else(|| fecha_emision.clone());
+    let fecha_fin = doc.fecha_fin_transporte.as_deref()
+        .and_then(|f| formatear_fecha_emision(f).ok())
+        .unwrap_or_else(|| fecha_ini.clone());
+
+    // 3. XML propio de la transferencia: destinatario = el negocio en el
+    // establecimiento de destino, sin documento sustento
+    let (estab_xml, pto_xml) = (establecimiento.clone(), punto_emision.clone());
+    let armar = |clave: &str, secuencial: &str| {
+        // El SRI limita cada campo adicional a 300 caracteres.
+        let mut info_adicional = vec![xml::CampoAdicional {
+            nombre: "Transferencia".to_string(),
+            valor: format!("{} ({} → {})", doc.numero, doc.origen_nombre, doc.destino_nombre),
+        }];
+        for l in doc.lineas.iter().filter(|l| !l.series.is_empty()) {
+            let series: Vec<&str> = l.series.iter().map(|s| s.serial.as_str()).collect();
+            info_adicional.push(xml::CampoAdicional {
+                nombre: format!("Series {}", l.producto_codigo.as_deref().unwrap_or(&l.producto_nombre)),
+                valor: series.join(", ").chars().take(300).collect(),
+            });
+        }
+
+        let destinatario = xml::DestinatarioGuia {
+            identificacion_destinatario: ruc.clone(),
+            razon_social_destinatario: cfg("nombre_negocio"),
+            dir_destinatario: dir_destino,
+            motivo_traslado: "Transferencia entre establecimientos".to_string(),
+            doc_aduanero_unico: None,
+            cod_estab_destino: Some(format!("{:0>3}", destino.0.trim())),
+            ruta: doc.ruta.clone(),
+            cod_doc_sustento: None,
+            num_doc_sustento: None,
+            num_aut_doc_sustento: None,
+            fecha_emision_doc_sustento: None,
+            detalles: detalles_guia,
+        };
+
+        xml::DatosGuiaRemision {
+            ambiente: ambiente.to_string(),
+            tipo_emision: "1".to_string(),
+            razon_social: cfg("nombre_negocio"),
+            nombre_comercial: cfg("nombre_negocio"),
+            ruc: ruc.clone(),
+            clave_acceso: clave.to_string(),
+            estab: estab_xml,
+            pto_emi: pto_xml,
+            secuencial: secuencial.to_string(),
+            dir_matriz: dir_matriz.clone(),
+            contribuyente_rimpe: leyenda_rimpe(&cfg("regimen")),
+            dir_establecimiento: dir_partida.clone(),
+            dir_partida,
+            razon_social_transportista: transportista.clone(),
+            tipo_identificacion_transportista: tipo_id_transportista(
+                doc.tipo_id_transportista.as_deref().unwrap_or(""),
+                &ruc_transportista,
+            ),
+            ruc_transportista: ruc_transportista.clone(),
+            rise: None,
+            obligado_contabilidad: Some("NO".to_string()),
+            contribuyente_especial: None,
+            fecha_ini_transporte: fecha_ini,
+            fecha_fin_transporte: fecha_fin,
+            placa: placa.clone(),
+            destinatarios: vec![destinatario],
+            info_adicional,
+        }
+    };
+
+    // 4. Emisión (con reenvío si estaba PENDIENTE) y resultado en la transferencia
+    let emision = EmisionGuia {
+        ambiente,
+        ruc: ruc.clone(),
+        establecimiento,
+        punto_emision,
+        fecha_emision,
+        estado_previo: doc.guia_estado_sri.clone(),
+        clave_previa: doc.guia_clave_acceso.clone(),
+        xml_previo,
+        p12_data,
+        p12_password,
+    };
+    emitir_guia(
+        &db,
+        emision,
+        armar,
+        "UPDATE transferencias_documento SET guia_estado_sri = ?1, guia_clave_acceso = ?2, guia_autorizacion = ?3,
+                guia_xml_firmado = ?4, guia_fecha_autorizacion = ?5, guia_numero = COALESCE(?6, guia_numero)
+         WHERE id = ?7",
+        transferencia_id,
+        "Guía de remisión de la transferencia autorizada correctamente",
+    )
+    .await
 }
 
 // ════════════════════════════════════════════════════════════════════
@@ -1887,6 +2168,11 @@ pub fn generar_ride_pdf(
         )
         .map_err(|e| format!("Venta no encontrada: {}", e))?;
 
+    // Propina/servicio (restaurante): se muestra aparte y suma al VALOR TOTAL
+    let propina: f64 = conn
+        .query_row("SELECT COALESCE(propina, 0) FROM ventas WHERE id = ?1", rusqlite::params![venta_id], |r| r.get(0))
+        .unwrap_or(0.0);
+
     // Obtener detalles con codigo de producto
     let mut stmt = conn
         .prepare(
@@ -2002,6 +2288,7 @@ pub fn generar_ride_pdf(
         fecha_autorizacion.as_deref(),
         "FACTURA",
         None,
+        propina,
     )?;
 
     // Guardar en temporal con nombre ÚNICO + reintentos ante os error 32
@@ -2849,6 +3136,7 @@ pub fn generar_ride_nc_pdf(
         None, // fecha_autorizacion se toma de la venta
         "NOTA DE CREDITO",
         Some(&doc_mod),
+        0.0,
     )?;
 
     let temp_dir = std::env::temp_dir();
@@ -0,0 +1,90 @@
+// Stock por establecimiento: fuente de verdad del inventario
+//
+// Toda operacion que mueve stock (ventas, NC, anulaciones, compras y sus
+// devoluciones, ajustes, combos, recetas, restaurante, transferencias, conteos)
+// lo hace en stock_establecimiento:
+//   - del establecimiento de la terminal (config terminal_establecimiento)
+//     cuando la operacion es nueva, o
+//   - del establecimiento del documento original cuando la revierte
+//     (ventas.establecimiento, notas_credito.establecimiento, compras.establecimiento_id).
+// productos.stock_actual es derivado: la suma de todos los establecimientos,
+// recalculada en cada movimiento. El kardex guarda el stock anterior/nuevo del
+// establecimiento junto con su establecimiento_id.
+
+use rusqlite::Connection;
+
+/// Stock del establecimiento antes y despues de un movimiento.
+pub struct StockMovido {
+    pub anterior: f64,
+    pub nuevo: f64,
+}
+
+/// Id del establecimiento con ese codigo SRI ('001'); si no existe, el primero.
+pub fn establecimiento_por_codigo(conn: &Connection, codigo: &str) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT id FROM establecimientos WHERE codigo = ?1",
+        rusqlite::params![codigo],
+        |r| r.get(0),
+    )
+    .or_else(|_| conn.query_row("SELECT id FROM establecimientos ORDER BY id LIMIT 1", [], |r| r.get(0)))
+    .map_err(|_| "No hay establecimientos configurados".to_string())
+}
+
+/// Establecimiento donde mueve stock esta terminal.
+pub fn establecimiento_terminal(conn: &Connection) -> Result<i64, String> {
+    let codigo: String = conn
+        .query_row("SELECT value FROM config WHERE key = 'terminal_establecimiento'", [], |r| r.get(0))
+        .unwrap_or_else(|_| "001".to_string());
+    establecimiento_por_codigo(conn, &codigo)
+}
+
+pub fn stock_en(conn: &Connection, producto_id: i64, establecimiento_id: i64) -> f64 {
+    conn.query_row(
+        "SELECT stock_actual FROM stock_establecimiento WHERE producto_id = ?1 AND establecimiento_id = ?2",
+        rusqlite::params![producto_id, establecimiento_id],
+        |r| r.get(0),
+    )
+    .unwrap_or(0.0)
+}
+
+/// productos.stock_actual = suma de sus establecimientos.
+pub fn recalcular_global(conn: &Connection, producto_id: i64) -> Result<(), String> {
+    conn.execute(
+        "UPDATE productos SET stock_actual = (SELECT COALESCE(SUM(stock_actual), 0) FROM stock_establecimiento WHERE producto_id = ?1),
+                updated_at = datetime('now','localtime')
+         WHERE id = ?1",
+        rusqlite::params![producto_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Suma `delta` al stock del producto en el establecimiento (crea la fila si
+/// no existe) y recalcula el global. No registra kardex: eso queda a cargo de
+/// quien llama, que conoce el tipo de movimiento y el documento.
+pub fn mover(conn: &Connection, producto_id: i64, establecimiento_id: i64, delta: f64) -> Result<StockMovido, String> {
+    let anterior = stock_en(conn, producto_id, establecimiento_id);
+    let global_anterior: f64 = conn
+        .query_row("SELECT stock_actual FROM productos WHERE id = ?1", rusqlite::params![producto_id], |r| r.get(0))
+        .unwrap_or(0.0);
+    conn.execute(
+        "INSERT INTO stock_establecimiento (producto_id, establecimiento_id, stock_actual, stock_minimo)
+         VALUES (?1, ?2, ?3, 0)
+         ON CONFLICT(producto_id, establecimiento_id) DO UPDATE SET stock_actual = stock_actual + ?3",
+        rusqlite::params![producto_id, establecimiento_id, delta],
+    )
+    .map_err(|e| e.to_string())?;
+    recalcular_global(conn, producto_id)?;
+    if delta < 0.0 {
+        // Lo ubicado en bodega no puede superar el stock (commands/bodega.rs)
+        crate::commands::bodega::ajustar_a_stock(conn, producto_id, establecimiento_id)?;
+        crate::webhooks::emitir_stock_bajo(conn, producto_id, establecimiento_id, global_anterior);
+    }
+    Ok(StockMovido { anterior, nuevo: anterior + delta })
+}
+
+/// Fija el stock del producto en el establecimiento (ajustes manuales, conteos).
+pub fn fijar(conn: &Connection, producto_id: i64, establecimiento_id: i64, nuevo: f64) -> Result<StockMovido, String> {
+    let anterior = stock_en(conn, producto_id, establecimiento_id);
+    mover(conn, producto_id, establecimiento_id, nuevo - anterior)
+}
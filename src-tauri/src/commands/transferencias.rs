@@ -1,4 +1,6 @@
-use crate::db::Database;
+use crate::commands::stock;
+use crate::db::{usuario_con_permiso, Database, SesionState};
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use tauri::State;
 
@@ -48,23 +50,14 @@ pub fn crear_transferencia(
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
     // Verificar stock disponible en origen
-    let stock_origen: f64 = conn
-        .query_row(
-            "SELECT COALESCE(stock_actual, 0) FROM stock_establecimiento WHERE producto_id = ?1 AND establecimiento_id = ?2",
-            rusqlite::params![producto_id, origen_establecimiento_id],
-            |row| row.get(0),
-        )
-        .unwrap_or(0.0);
+    let stock_origen = stock::stock_en(&conn, producto_id, origen_establecimiento_id);
 
     if stock_origen < cantidad {
         return Err(format!("Stock insuficiente en origen. Disponible: {:.2}", stock_origen));
     }
 
-    // Descontar del origen
-    conn.execute(
-        "UPDATE stock_establecimiento SET stock_actual = stock_actual - ?1 WHERE producto_id = ?2 AND establecimiento_id = ?3",
-        rusqlite::params![cantidad, producto_id, origen_establecimiento_id],
-    ).map_err(|e| e.to_string())?;
+    // Descontar del origen (en tránsito no cuenta en el stock global)
+    stock::mover(&conn, producto_id, origen_establecimiento_id, -cantidad)?;
 
     // Crear registro de transferencia
     conn.execute(
@@ -112,12 +105,7 @@ pub fn recibir_transferencia(
     }
 
     // Incrementar stock en destino (crear registro si no existe)
-    conn.execute(
-        "INSERT INTO stock_establecimiento (producto_id, establecimiento_id, stock_actual, stock_minimo)
-         VALUES (?1, ?2, ?3, 0)
-         ON CONFLICT(producto_id, establecimiento_id) DO UPDATE SET stock_actual = stock_actual + ?3",
-        rusqlite::params![producto_id, destino_id, cantidad],
-    ).map_err(|e| e.to_string())?;
+    stock::mover(&conn, producto_id, destino_id, cantidad)?;
 
     // Marcar como recibida
     conn.execute(
@@ -237,11 +225,694 @@ pub fn actualizar_stock_establecimiento(
     ).map_err(|e| e.to_string())?;
 
     // Actualizar stock total en productos (suma de todos los establecimientos)
+    stock::recalcular_global(&conn, producto_id)?;
+
+    Ok(())
+}
+
+// --- Documentos de transferencia multi-línea ---
+//
+// Un documento agrupa varias líneas (producto + cantidad, con lote y series
+// cuando el producto los maneja). Flujo:
+//   1. crear_transferencia_documento: descuenta el stock del origen, el lote
+//      de origen y deja las series EN_TRANSITO. Estado EN_TRANSITO.
+//   2. recibir_transferencia_documento: el destino registra lo que llega, una
+//      o varias veces (RECIBIDA_PARCIAL). Los lotes se recrean en destino con
+//      el mismo código y fechas; las series pasan a DISPONIBLE en destino.
+//   3. Al recibir todo, o al cerrar con pendientes, lo no recibido queda como
+//      faltante (transferencia_lineas.cantidad_faltante) con un movimiento
+//      TRANSFERENCIA_FALTANTE informativo en el origen: el stock ya salió de
+//      ahí al crear el documento y el global es la suma de establecimientos,
+//      así que ninguno cambia y el movimiento va con cantidad 0. Las series
+//      no llegadas quedan EXTRAVIADO.
+//      Estado RECIBIDA o RECIBIDA_CON_DIFERENCIAS.
+// La guía de remisión electrónica se arma a partir del documento
+// (commands::sri::emitir_guia_transferencia_sri).
+
+const TOLERANCIA: f64 = 0.0001;
+
+#[derive(Debug, Deserialize)]
+pub struct NuevaLineaTransferencia {
+    pub producto_id: i64,
+    pub cantidad: f64,
+    /// Lote de origen (obligatorio si el producto requiere caducidad)
+    #[serde(default)]
+    pub lote_id: Option<i64>,
+    /// Seriales enviados (obligatorio si el producto requiere serie: uno por unidad)
+    #[serde(default)]
+    pub series: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NuevaTransferenciaDocumento {
+    pub origen_establecimiento_id: i64,
+    pub destino_establecimiento_id: i64,
+    pub lineas: Vec<NuevaLineaTransferencia>,
+    #[serde(default)]
+    pub observacion: Option<String>,
+    #[serde(default)]
+    pub transportista: Option<String>,
+    #[serde(default)]
+    pub ruc_transportista: Option<String>,
+    #[serde(default)]
+    pub tipo_id_transportista: Option<String>,
+    #[serde(default)]
+    pub placa: Option<String>,
+    #[serde(default)]
+    pub ruta: Option<String>,
+    #[serde(default)]
+    pub fecha_inicio_transporte: Option<String>,
+    #[serde(default)]
+    pub fecha_fin_transporte: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SerieTransferencia {
+    pub numero_serie_id: i64,
+    pub serial: String,
+    pub recibido: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LineaTransferencia {
+    pub id: i64,
+    pub producto_id: i64,
+    pub producto_codigo: Option<String>,
+    pub producto_nombre: String,
+    pub lote_id: Option<i64>,
+    pub lote: Option<String>,
+    pub fecha_caducidad: Option<String>,
+    pub cantidad_enviada: f64,
+    pub cantidad_recibida: f64,
+    pub cantidad_pendiente: f64,
+    pub cantidad_faltante: f64,
+    pub costo_unitario: f64,
+    pub observacion_recepcion: Option<String>,
+    pub series: Vec<SerieTransferencia>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransferenciaDocumento {
+    pub id: i64,
+    pub numero: String,
+    pub origen_establecimiento_id: i64,
+    pub origen_nombre: String,
+    pub destino_establecimiento_id: i64,
+    pub destino_nombre: String,
+    pub estado: String,
+    pub observacion: Option<String>,
+    pub usuario: Option<String>,
+    pub usuario_recepcion: Option<String>,
+    pub fecha_creacion: String,
+    pub fecha_recepcion: Option<String>,
+    pub transportista: Option<String>,
+    pub ruc_transportista: Option<String>,
+    pub tipo_id_transportista: Option<String>,
+    pub placa: Option<String>,
+    pub ruta: Option<String>,
+    pub fecha_inicio_transporte: Option<String>,
+    pub fecha_fin_transporte: Option<String>,
+    pub guia_estado_sri: String,
+    pub guia_numero: Option<String>,
+    pub guia_clave_acceso: Option<String>,
+    pub guia_autorizacion: Option<String>,
+    pub lineas: Vec<LineaTransferencia>,
+}
+
+/// Lo que llega al destino para una línea. `cantidad` es lo recibido en esta
+/// entrega (se acumula con entregas anteriores).
+#[derive(Debug, Deserialize)]
+pub struct RecepcionLinea {
+    pub linea_id: i64,
+    pub cantidad: f64,
+    #[serde(default)]
+    pub series: Vec<String>,
+    #[serde(default)]
+    pub observacion: Option<String>,
+}
+
+fn limpiar(v: &Option<String>) -> Option<String> {
+    v.as_ref().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Documento al que se cargan los movimientos del kardex.
+struct Referencia<'a> {
+    id: i64,
+    numero: &'a str,
+    usuario: Option<&'a str>,
+}
+
+/// Suma `delta` al stock del establecimiento (y recalcula el global) y deja
+/// el movimiento en el kardex con el stock del establecimiento.
+fn mover_stock_establecimiento(
+    conn: &Connection,
+    producto_id: i64,
+    establecimiento_id: i64,
+    delta: f64,
+    tipo: &str,
+    costo_unitario: f64,
+    referencia: &Referencia,
+) -> Result<(), String> {
+    let mov = stock::mover(conn, producto_id, establecimiento_id, delta)?;
     conn.execute(
-        "UPDATE productos SET stock_actual = (SELECT COALESCE(SUM(stock_actual), 0) FROM stock_establecimiento WHERE producto_id = ?1)
-         WHERE id = ?1",
-        rusqlite::params![producto_id],
+        "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo,
+                                             costo_unitario, referencia_id, motivo, usuario, establecimiento_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        rusqlite::params![
+            producto_id, tipo, delta, mov.anterior, mov.nuevo, costo_unitario,
+            referencia.id, format!("Transferencia {}", referencia.numero), referencia.usuario, establecimiento_id
+        ],
     ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Lo que hace falta saber de un producto para enviarlo.
+struct ProductoTransferible {
+    nombre: String,
+    es_servicio: bool,
+    controla_stock: bool,
+    requiere_serie: bool,
+    requiere_caducidad: bool,
+    costo: f64,
+}
+
+fn establecimiento_activo(conn: &Connection, id: i64) -> Result<(), String> {
+    conn.query_row(
+        "SELECT id FROM establecimientos WHERE id = ?1 AND activo = 1",
+        rusqlite::params![id],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|_| ())
+    .map_err(|_| format!("Establecimiento {} no encontrado o inactivo", id))
+}
+
+/// Crea el documento, descuenta el origen y deja lotes/series en tránsito.
+/// Llamar dentro de una transacción.
+pub fn crear_documento(
+    conn: &Connection,
+    nuevo: &NuevaTransferenciaDocumento,
+    usuario: Option<&str>,
+) -> Result<i64, String> {
+    let origen = nuevo.origen_establecimiento_id;
+    let destino = nuevo.destino_establecimiento_id;
+    if origen == destino {
+        return Err("Origen y destino no pueden ser el mismo establecimiento".to_string());
+    }
+    if nuevo.lineas.is_empty() {
+        return Err("La transferencia no tiene productos".to_string());
+    }
+    establecimiento_activo(conn, origen)?;
+    establecimiento_activo(conn, destino)?;
+
+    conn.execute(
+        "INSERT INTO transferencias_documento (origen_establecimiento_id, destino_establecimiento_id, observacion, usuario,
+                transportista, ruc_transportista, tipo_id_transportista, placa, ruta,
+                fecha_inicio_transporte, fecha_fin_transporte)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        rusqlite::params![
+            origen, destino, limpiar(&nuevo.observacion), usuario,
+            limpiar(&nuevo.transportista), limpiar(&nuevo.ruc_transportista),
+            limpiar(&nuevo.tipo_id_transportista), limpiar(&nuevo.placa).map(|p| p.to_uppercase()),
+            limpiar(&nuevo.ruta), limpiar(&nuevo.fecha_inicio_transporte), limpiar(&nuevo.fecha_fin_transporte)
+        ],
+    ).map_err(|e| e.to_string())?;
+    let doc_id = conn.last_insert_rowid();
+    let numero = format!("TR-{:06}", doc_id);
+    conn.execute(
+        "UPDATE transferencias_documento SET numero = ?1 WHERE id = ?2",
+        rusqlite::params![numero, doc_id],
+    ).map_err(|e| e.to_string())?;
+
+    for l in &nuevo.lineas {
+        if l.cantidad <= 0.0 {
+            return Err("Las cantidades deben ser mayores a 0".to_string());
+        }
+        let p: ProductoTransferible = conn
+            .query_row(
+                "SELECT nombre, COALESCE(es_servicio, 0), COALESCE(no_controla_stock, 0) = 0,
+                        COALESCE(requiere_serie, 0), COALESCE(requiere_caducidad, 0),
+                        CASE WHEN COALESCE(costo_promedio, 0) > 0 THEN costo_promedio ELSE COALESCE(precio_costo, 0) END
+                 FROM productos WHERE id = ?1",
+                rusqlite::params![l.producto_id],
+                |row| Ok(ProductoTransferible {
+                    nombre: row.get(0)?,
+                    es_servicio: row.get(1)?,
+                    controla_stock: row.get(2)?,
+                    requiere_serie: row.get(3)?,
+                    requiere_caducidad: row.get(4)?,
+                    costo: row.get(5)?,
+                }),
+            )
+            .map_err(|_| format!("Producto {} no encontrado", l.producto_id))?;
+        let nombre = &p.nombre;
+        if p.es_servicio {
+            return Err(format!("{} es un servicio y no se puede transferir", nombre));
+        }
+
+        if p.controla_stock {
+            let disponible = stock::stock_en(conn, l.producto_id, origen);
+            if disponible + TOLERANCIA < l.cantidad {
+                return Err(format!("Stock insuficiente de {} en origen. Disponible: {:.2}", nombre, disponible));
+            }
+        }
+
+        // Lote de origen: se descuenta ya; en destino se recrea al recibir.
+        let mut lote: (Option<String>, Option<String>, Option<String>) = (None, None, None);
+        if let Some(lote_id) = l.lote_id {
+            let (cant_lote, codigo, caducidad, elaboracion): (f64, Option<String>, String, Option<String>) = conn
+                .query_row(
+                    "SELECT cantidad, lote, fecha_caducidad, fecha_elaboracion FROM lotes_caducidad
+                     WHERE id = ?1 AND producto_id = ?2
+                       AND (establecimiento_id IS NULL OR establecimiento_id = ?3)",
+                    rusqlite::params![lote_id, l.producto_id, origen],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+                )
+                .map_err(|_| format!("El lote indicado para {} no existe en el establecimiento de origen", nombre))?;
+            if cant_lote + TOLERANCIA < l.cantidad {
+                return Err(format!("El lote {} de {} solo tiene {:.2}", codigo.unwrap_or_default(), nombre, cant_lote));
+            }
+            conn.execute(
+                "UPDATE lotes_caducidad SET cantidad = MAX(cantidad - ?1, 0) WHERE id = ?2",
+                rusqlite::params![l.cantidad, lote_id],
+            ).map_err(|e| e.to_string())?;
+            lote = (codigo, Some(caducidad), elaboracion);
+        } else if p.requiere_caducidad {
+            return Err(format!("Indique el lote de {} a transferir", nombre));
+        }
+
+        conn.execute(
+            "INSERT INTO transferencia_lineas (documento_id, producto_id, lote_id, lote, fecha_caducidad, fecha_elaboracion,
+                                               cantidad_enviada, costo_unitario)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![doc_id, l.producto_id, l.lote_id, lote.0, lote.1, lote.2, l.cantidad, p.costo],
+        ).map_err(|e| e.to_string())?;
+        let linea_id = conn.last_insert_rowid();
+
+        if p.requiere_serie {
+            let seriales: Vec<String> = l.series.iter()
+                .map(|s| s.trim().to_uppercase())
+                .filter(|s| !s.is_empty())
+                .collect();
+            if (seriales.len() as f64 - l.cantidad).abs() > TOLERANCIA {
+                return Err(format!(
+                    "{} requiere serie: indique {} seriales (recibidos {})",
+                    nombre, l.cantidad, seriales.len()
+                ));
+            }
+            for serial in &seriales {
+                let serie_id: i64 = conn
+                    .query_row(
+                        "SELECT id FROM numeros_serie
+                         WHERE producto_id = ?1 AND serial = ?2 AND estado = 'DISPONIBLE'
+                           AND (establecimiento_id IS NULL OR establecimiento_id = ?3)",
+                        rusqlite::params![l.producto_id, serial, origen],
+                        |row| row.get(0),
+                    )
+                    .map_err(|_| format!("La serie {} de {} no está disponible en origen", serial, nombre))?;
+                conn.execute(
+                    "UPDATE numeros_serie SET estado = 'EN_TRANSITO' WHERE id = ?1",
+                    rusqlite::params![serie_id],
+                ).map_err(|e| e.to_string())?;
+                conn.execute(
+                    "INSERT INTO transferencia_series (linea_id, numero_serie_id, serial) VALUES (?1, ?2, ?3)",
+                    rusqlite::params![linea_id, serie_id, serial],
+                ).map_err(|_| format!("La serie {} está repetida", serial))?;
+            }
+        }
+
+        if p.controla_stock {
+            mover_stock_establecimiento(
+                conn, l.producto_id, origen, -l.cantidad, "TRANSFERENCIA_SALIDA", p.costo,
+                &Referencia { id: doc_id, numero: &numero, usuario },
+            )?;
+        }
+    }
 
+    Ok(doc_id)
+}
+
+fn listar_lineas(conn: &Connection, documento_id: i64) -> Result<Vec<LineaTransferencia>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT l.id, l.producto_id, p.codigo, p.nombre, l.lote_id, l.lote, l.fecha_caducidad,
+                l.cantidad_enviada, l.cantidad_recibida, l.cantidad_faltante, l.costo_unitario, l.observacion_recepcion
+         FROM transferencia_lineas l
+         JOIN productos p ON l.producto_id = p.id
+         WHERE l.documento_id = ?1
+         ORDER BY l.id",
+    ).map_err(|e| e.to_string())?;
+    let mut lineas = stmt
+        .query_map(rusqlite::params![documento_id], |row| {
+            let enviada: f64 = row.get(7)?;
+            let recibida: f64 = row.get(8)?;
+            let faltante: f64 = row.get(9)?;
+            Ok(LineaTransferencia {
+                id: row.get(0)?,
+                producto_id: row.get(1)?,
+                producto_codigo: row.get(2)?,
+                producto_nombre: row.get(3)?,
+                lote_id: row.get(4)?,
+                lote: row.get(5)?,
+                fecha_caducidad: row.get(6)?,
+                cantidad_enviada: enviada,
+                cantidad_recibida: recibida,
+                cantidad_pendiente: (enviada - recibida - faltante).max(0.0),
+                cantidad_faltante: faltante,
+                costo_unitario: row.get(10)?,
+                observacion_recepcion: row.get(11)?,
+                series: Vec::new(),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt_series = conn.prepare(
+        "SELECT numero_serie_id, serial, recibido FROM transferencia_series WHERE linea_id = ?1 ORDER BY serial",
+    ).map_err(|e| e.to_string())?;
+    for linea in &mut lineas {
+        linea.series = stmt_series
+            .query_map(rusqlite::params![linea.id], |row| {
+                Ok(SerieTransferencia {
+                    numero_serie_id: row.get(0)?,
+                    serial: row.get(1)?,
+                    recibido: row.get::<_, i64>(2)? != 0,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(lineas)
+}
+
+const SELECT_DOCUMENTO: &str = "
+    SELECT t.id, COALESCE(t.numero, ''), t.origen_establecimiento_id, eo.nombre,
+           t.destino_establecimiento_id, ed.nombre, t.estado, t.observacion, t.usuario,
+           t.usuario_recepcion, t.fecha_creacion, t.fecha_recepcion, t.transportista,
+           t.ruc_transportista, t.tipo_id_transportista, t.placa, t.ruta,
+           t.fecha_inicio_transporte, t.fecha_fin_transporte, t.guia_estado_sri,
+           t.guia_numero, t.guia_clave_acceso, t.guia_autorizacion
+    FROM transferencias_documento t
+    JOIN establecimientos eo ON t.origen_establecimiento_id = eo.id
+    JOIN establecimientos ed ON t.destino_establecimiento_id = ed.id";
+
+fn mapear_documento(row: &rusqlite::Row) -> rusqlite::Result<TransferenciaDocumento> {
+    Ok(TransferenciaDocumento {
+        id: row.get(0)?,
+        numero: row.get(1)?,
+        origen_establecimiento_id: row.get(2)?,
+        origen_nombre: row.get(3)?,
+        destino_establecimiento_id: row.get(4)?,
+        destino_nombre: row.get(5)?,
+        estado: row.get(6)?,
+        observacion: row.get(7)?,
+        usuario: row.get(8)?,
+        usuario_recepcion: row.get(9)?,
+        fecha_creacion: row.get(10)?,
+        fecha_recepcion: row.get(11)?,
+        transportista: row.get(12)?,
+        ruc_transportista: row.get(13)?,
+        tipo_id_transportista: row.get(14)?,
+        placa: row.get(15)?,
+        ruta: row.get(16)?,
+        fecha_inicio_transporte: row.get(17)?,
+        fecha_fin_transporte: row.get(18)?,
+        guia_estado_sri: row.get(19)?,
+        guia_numero: row.get(20)?,
+        guia_clave_acceso: row.get(21)?,
+        guia_autorizacion: row.get(22)?,
+        lineas: Vec::new(),
+    })
+}
+
+/// Documento con sus líneas y series.
+pub fn obtener_documento(conn: &Connection, id: i64) -> Result<TransferenciaDocumento, String> {
+    let mut doc = conn
+        .query_row(&format!("{} WHERE t.id = ?1", SELECT_DOCUMENTO), rusqlite::params![id], mapear_documento)
+        .map_err(|_| "Transferencia no encontrada".to_string())?;
+    doc.lineas = listar_lineas(conn, id)?;
+    Ok(doc)
+}
+
+/// Registra una entrega en destino. Con `cerrar` (o cuando ya llegó todo)
+/// lo pendiente pasa a faltante. Llamar dentro de una transacción.
+pub fn recibir_documento(
+    conn: &Connection,
+    documento_id: i64,
+    recepciones: &[RecepcionLinea],
+    cerrar: bool,
+    usuario: Option<&str>,
+) -> Result<(), String> {
+    let doc = obtener_documento(conn, documento_id)?;
+    if doc.estado != "EN_TRANSITO" && doc.estado != "RECIBIDA_PARCIAL" {
+        return Err(format!("La transferencia {} ya fue cerrada ({})", doc.numero, doc.estado));
+    }
+    let destino = doc.destino_establecimiento_id;
+
+    for r in recepciones {
+        let linea = doc.lineas.iter().find(|l| l.id == r.linea_id)
+            .ok_or_else(|| format!("La línea {} no pertenece a la transferencia {}", r.linea_id, doc.numero))?;
+        if r.cantidad < 0.0 {
+            return Err("La cantidad recibida no puede ser negativa".to_string());
+        }
+        if r.cantidad > linea.cantidad_pendiente + TOLERANCIA {
+            return Err(format!(
+                "{}: se reciben {:.2} pero solo quedan {:.2} pendientes",
+                linea.producto_nombre, r.cantidad, linea.cantidad_pendiente
+            ));
+        }
+        let observacion = limpiar(&r.observacion);
+        if r.cantidad <= 0.0 {
+            if observacion.is_some() {
+                conn.execute(
+                    "UPDATE transferencia_lineas SET observacion_recepcion = ?1 WHERE id = ?2",
+                    rusqlite::params![observacion, linea.id],
+                ).map_err(|e| e.to_string())?;
+            }
+            continue;
+        }
+
+        if !linea.series.is_empty() {
+            let seriales: Vec<String> = r.series.iter()
+                .map(|s| s.trim().to_uppercase())
+                .filter(|s| !s.is_empty())
+                .collect();
+            if (seriales.len() as f64 - r.cantidad).abs() > TOLERANCIA {
+                return Err(format!(
+                    "{}: indique las {} series recibidas (vinieron {})",
+                    linea.producto_nombre, r.cantidad, seriales.len()
+                ));
+            }
+            for serial in &seriales {
+                let serie = linea.series.iter().find(|s| &s.serial == serial && !s.recibido)
+                    .ok_or_else(|| format!("La serie {} no viaja en esta línea o ya fue recibida", serial))?;
+                conn.execute(
+                    "UPDATE transferencia_series SET recibido = 1 WHERE linea_id = ?1 AND numero_serie_id = ?2",
+                    rusqlite::params![linea.id, serie.numero_serie_id],
+                ).map_err(|e| e.to_string())?;
+                conn.execute(
+                    "UPDATE numeros_serie SET estado = 'DISPONIBLE', establecimiento_id = ?1 WHERE id = ?2",
+                    rusqlite::params![destino, serie.numero_serie_id],
+                ).map_err(|e| e.to_string())?;
+            }
+        }
+
+        // El lote se recrea en destino con el mismo código y fechas (o se
+        // suma a uno ya creado por una entrega anterior).
+        if let Some(caducidad) = &linea.fecha_caducidad {
+            let existente: Option<i64> = conn
+                .query_row(
+                    "SELECT id FROM lotes_caducidad
+                     WHERE producto_id = ?1 AND establecimiento_id = ?2 AND fecha_caducidad = ?3
+                       AND COALESCE(lote, '') = COALESCE(?4, '')",
+                    rusqlite::params![linea.producto_id, destino, caducidad, linea.lote],
+                    |row| row.get(0),
+                )
+                .ok();
+            match existente {
+                Some(lote_id) => {
+                    conn.execute(
+                        "UPDATE lotes_caducidad SET cantidad = cantidad + ?1 WHERE id = ?2",
+                        rusqlite::params![r.cantidad, lote_id],
+                    ).map_err(|e| e.to_string())?;
+                }
+                None => {
+                    let elaboracion: Option<String> = conn
+                        .query_row(
+                            "SELECT fecha_elaboracion FROM transferencia_lineas WHERE id = ?1",
+                            rusqlite::params![linea.id],
+                            |row| row.get(0),
+                        )
+                        .unwrap_or(None);
+                    conn.execute(
+                        "INSERT INTO lotes_caducidad (producto_id, lote, fecha_caducidad, cantidad, cantidad_inicial,
+                                                      observacion, fecha_elaboracion, establecimiento_id)
+                         VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6, ?7)",
+                        rusqlite::params![
+                            linea.producto_id, linea.lote, caducidad, r.cantidad,
+                            format!("Transferencia {}", doc.numero), elaboracion, destino
+                        ],
+                    ).map_err(|e| e.to_string())?;
+                }
+            }
+        }
+
+        let controla_stock: bool = conn
+            .query_row(
+                "SELECT COALESCE(no_controla_stock, 0) = 0 FROM productos WHERE id = ?1",
+                rusqlite::params![linea.producto_id],
+                |row| row.get(0),
+            )
+            .unwrap_or(true);
+        if controla_stock {
+            mover_stock_establecimiento(
+                conn, linea.producto_id, destino, r.cantidad, "TRANSFERENCIA_ENTRADA", linea.costo_unitario,
+                &Referencia { id: doc.id, numero: &doc.numero, usuario },
+            )?;
+        }
+
+        conn.execute(
+            "UPDATE transferencia_lineas
+             SET cantidad_recibida = cantidad_recibida + ?1,
+                 observacion_recepcion = COALESCE(?2, observacion_recepcion)
+             WHERE id = ?3",
+            rusqlite::params![r.cantidad, observacion, linea.id],
+        ).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO transferencia_recepciones (documento_id, linea_id, cantidad, observacion, usuario)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![doc.id, linea.id, r.cantidad, observacion, usuario],
+        ).map_err(|e| e.to_string())?;
+    }
+
+    let lineas = listar_lineas(conn, documento_id)?;
+    let pendiente: f64 = lineas.iter().map(|l| l.cantidad_pendiente).sum();
+    if pendiente > TOLERANCIA && !cerrar {
+        conn.execute(
+            "UPDATE transferencias_documento SET estado = 'RECIBIDA_PARCIAL', usuario_recepcion = ?1 WHERE id = ?2",
+            rusqlite::params![usuario, documento_id],
+        ).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    // Cierre: lo que no llegó se pierde en el camino. El origen ya lo había
+    // descontado de su establecimiento (y con eso del global), así que el
+    // stock no cambia: el kardex solo deja constancia del faltante.
+    let mut con_diferencias = false;
+    for l in lineas.iter().filter(|l| l.cantidad_pendiente > TOLERANCIA) {
+        con_diferencias = true;
+        conn.execute(
+            "UPDATE transferencia_lineas SET cantidad_faltante = ?1 WHERE id = ?2",
+            rusqlite::params![l.cantidad_pendiente, l.id],
+        ).map_err(|e| e.to_string())?;
+        for s in l.series.iter().filter(|s| !s.recibido) {
+            conn.execute(
+                "UPDATE numeros_serie SET estado = 'EXTRAVIADO',
+                        observacion = ?1
+                 WHERE id = ?2",
+                rusqlite::params![format!("No llegó en la transferencia {}", doc.numero), s.numero_serie_id],
+            ).map_err(|e| e.to_string())?;
+        }
+
+        let controla_stock: bool = conn
+            .query_row(
+                "SELECT COALESCE(no_controla_stock, 0) = 0 FROM productos WHERE id = ?1",
+                rusqlite::params![l.producto_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !controla_stock {
+            continue;
+        }
+        let stock_origen = stock::stock_en(conn, l.producto_id, doc.origen_establecimiento_id);
+        conn.execute(
+            "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo,
+                                                 costo_unitario, referencia_id, motivo, usuario, establecimiento_id)
+             VALUES (?1, 'TRANSFERENCIA_FALTANTE', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                l.producto_id, 0.0, stock_origen, stock_origen,
+                l.costo_unitario, doc.id,
+                format!("Faltante transferencia {}: {} sin llegar{}", doc.numero, l.cantidad_pendiente,
+                    l.observacion_recepcion.as_ref().map(|o| format!(": {}", o)).unwrap_or_default()),
+                usuario, doc.origen_establecimiento_id
+            ],
+        ).map_err(|e| e.to_string())?;
+    }
+
+    conn.execute(
+        "UPDATE transferencias_documento
+         SET estado = ?1, usuario_recepcion = ?2, fecha_recepcion = datetime('now','localtime')
+         WHERE id = ?3",
+        rusqlite::params![
+            if con_diferencias { "RECIBIDA_CON_DIFERENCIAS" } else { "RECIBIDA" },
+            usuario, documento_id
+        ],
+    ).map_err(|e| e.to_string())?;
     Ok(())
 }
+
+/// Crea una transferencia de varios productos. Descuenta el origen de inmediato.
+#[tauri::command]
+pub fn crear_transferencia_documento(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    documento: NuevaTransferenciaDocumento,
+) -> Result<TransferenciaDocumento, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let id = crear_documento(&tx, &documento, Some(&usuario))?;
+    tx.commit().map_err(|e| e.to_string())?;
+    obtener_documento(&conn, id)
+}
+
+/// Registra lo recibido en destino (entrega parcial o total). Con `cerrar`
+/// lo que falte queda registrado como faltante.
+#[tauri::command]
+pub fn recibir_transferencia_documento(
+    db: State<Database>,
+    sesion: State<SesionState>,
+    id: i64,
+    lineas: Vec<RecepcionLinea>,
+    cerrar: bool,
+) -> Result<TransferenciaDocumento, String> {
+    let usuario = usuario_con_permiso(&sesion, "gestionar_inventario")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    recibir_documento(&tx, id, &lineas, cerrar, Some(&usuario))?;
+    tx.commit().map_err(|e| e.to_string())?;
+    obtener_documento(&conn, id)
+}
+
+#[tauri::command]
+pub fn obtener_transferencia_documento(
+    db: State<Database>,
+    id: i64,
+) -> Result<TransferenciaDocumento, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    obtener_documento(&conn, id)
+}
+
+/// Lista documentos de transferencia (sin líneas), filtrados opcionalmente
+/// por establecimiento (origen o destino) y estado.
+#[tauri::command]
+pub fn listar_transferencias_documento(
+    db: State<Database>,
+    establecimiento_id: Option<i64>,
+    estado: Option<String>,
+) -> Result<Vec<TransferenciaDocumento>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let sql = format!(
+        "{} WHERE (?1 IS NULL OR t.origen_establecimiento_id = ?1 OR t.destino_establecimiento_id = ?1)
+              AND (?2 IS NULL OR t.estado = ?2)
+         ORDER BY t.id DESC LIMIT 200",
+        SELECT_DOCUMENTO
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let docs = stmt
+        .query_map(rusqlite::params![establecimiento_id, estado], mapear_documento)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(docs)
+}
@@ -2,11 +2,81 @@ use crate::db::{Database, SesionState};
 use crate::models::{NuevaVenta, NuevaNotaCredito, NotaCreditoInfo, Venta, VentaCompleta, VentaDetalle, DocumentoReciente, ResumenGuias};
 use tauri::State;
 
+/// Stock requerido por producto fisico para validar en modo BLOQUEAR.
+fn stock_requerido(conn: &rusqlite::Connection, items: &[VentaDetalle]) -> Result<std::collections::HashMap<i64, f64>, String> {
+    // v2.5.20 BUG FIX: para COMBOS hay que validar stock de COMPONENTES,
+    // no del padre (que siempre es 0 porque combos no tienen stock propio).
+    //
+    // Estrategia: armar un mapa de stock REQUERIDO por producto físico final.
+    // - Producto simple: pid → cantidad * factor
+    // - Combo fijo: cada componente → cantidad_combo_vendido * cantidad_componente
+    // - Combo flexible: usar item.combo_seleccion
+    // - Plato con receta: cada insumo → cantidad de receta (con merma) * cantidad vendida
+    let mut requerido: std::collections::HashMap<i64, f64> = std::collections::HashMap::new();
+    for it in items {
+        let factor = it.factor_unidad.unwrap_or(1.0);
+        let cant_total = it.cantidad * factor;
+        // v2.6.36: linea "a medida" (sin producto del catalogo). Si lleva
+        // componentes elegidos, validar el stock de esos componentes.
+        let Some(pid) = it.producto_id else {
+            if let Some(sel) = &it.combo_seleccion {
+                for c in sel {
+                    if c.cantidad > 0.0 {
+                        *requerido.entry(c.producto_hijo_id).or_insert(0.0) += c.cantidad * cant_total;
+                    }
+                }
+            }
+            continue;
+        };
+
+        // ¿Es combo? Chequear tipo_producto + presencia de componentes
+        let (tipo_prod, tiene_componentes): (String, i64) = conn.query_row(
+            "SELECT COALESCE(tipo_producto, 'SIMPLE'),
+                    (SELECT COUNT(*) FROM producto_componentes WHERE producto_padre_id = productos.id)
+             FROM productos WHERE id = ?1",
+            rusqlite::params![pid],
+            |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)?)),
+        ).unwrap_or(("SIMPLE".to_string(), 0));
+        let es_combo = tipo_prod == "COMBO_FIJO" || tipo_prod == "COMBO_FLEXIBLE" || tiene_componentes > 0;
+
+        if !es_combo && crate::commands::recetas::tiene_receta(conn, Some(pid)) {
+            // Plato con receta: no tiene stock propio, sale de sus insumos
+            for (insumo_id, cant) in crate::commands::recetas::consumo_insumos(conn, pid, cant_total)? {
+                *requerido.entry(insumo_id).or_insert(0.0) += cant;
+            }
+        } else if !es_combo {
+            // Producto simple: cargar cantidad del producto base
+            *requerido.entry(pid).or_insert(0.0) += cant_total;
+        } else if tipo_prod == "COMBO_FLEXIBLE" {
+            // Usar combo_seleccion (lo que el cajero escogió en el momento)
+            if let Some(sel) = &it.combo_seleccion {
+                for c in sel {
+                    if c.cantidad > 0.0 {
+                        *requerido.entry(c.producto_hijo_id).or_insert(0.0) += c.cantidad * cant_total;
+                    }
+                }
+            }
+        } else {
+            // COMBO_FIJO (o producto con componentes detectados): leer de producto_componentes
+            let mut stmt = conn.prepare(
+                "SELECT producto_hijo_id, cantidad FROM producto_componentes WHERE producto_padre_id = ?1"
+            ).map_err(|e| e.to_string())?;
+            let rows = stmt.query_map(rusqlite::params![pid], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, f64>(1)?)))
+                .map_err(|e| e.to_string())?;
+            for row in rows {
+                let (hijo_id, cant_componente) = row.map_err(|e| e.to_string())?;
+                *requerido.entry(hijo_id).or_insert(0.0) += cant_componente * cant_total;
+            }
+        }
+    }
+    Ok(requerido)
+}
+
 #[tauri::command]
 pub fn registrar_venta(
     db: State<Database>,
     sesion: State<SesionState>,
-    venta: NuevaVenta,
+    mut venta: NuevaVenta,
 ) -> Result<VentaCompleta, String> {
     // Verificar sesión activa
     let sesion_guard = sesion.sesion.lock().map_err(|e| e.to_string())?;
@@ -39,6 +109,12 @@ pub fn registrar_venta(
         return Err("Debe abrir la caja antes de realizar ventas".to_string());
     }
 
+    // El stock se mueve en el establecimiento de la terminal (commands/stock.rs)
+    let est_id = crate::commands::stock::establecimiento_terminal(&conn)?;
+
+    // Descuento por lotes proximos a vencer (config 'descuento_caducidad_auto')
+    crate::commands::caducidad::aplicar_descuentos(&conn, &mut venta.items, est_id)?;
+
     // Validar stock segun config 'stock_negativo_modo'
     // PERMITIR (default): permite vender aunque deje stock < 0
     // BLOQUEAR | BLOQUEAR_OCULTAR: bloquea si la cantidad supera el stock disponible
@@ -46,73 +122,15 @@ pub fn registrar_venta(
         .query_row("SELECT value FROM config WHERE key = 'stock_negativo_modo'", [], |r| r.get(0))
         .unwrap_or_else(|_| "PERMITIR".to_string());
     if stock_modo == "BLOQUEAR" || stock_modo == "BLOQUEAR_OCULTAR" {
-        // v2.5.20 BUG FIX: para COMBOS hay que validar stock de COMPONENTES,
-        // no del padre (que siempre es 0 porque combos no tienen stock propio).
-        //
-        // Estrategia: armar un mapa de stock REQUERIDO por producto físico final.
-        // - Producto simple: pid → cantidad * factor
-        // - Combo fijo: cada componente → cantidad_combo_vendido * cantidad_componente
-        // - Combo flexible: usar item.combo_seleccion
-        let mut requerido: std::collections::HashMap<i64, f64> = std::collections::HashMap::new();
-        for it in &venta.items {
-            let factor = it.factor_unidad.unwrap_or(1.0);
-            let cant_total = it.cantidad * factor;
-            // v2.6.36: linea "a medida" (sin producto del catalogo). Si lleva
-            // componentes elegidos, validar el stock de esos componentes.
-            let Some(pid) = it.producto_id else {
-                if let Some(sel) = &it.combo_seleccion {
-                    for c in sel {
-                        if c.cantidad > 0.0 {
-                            *requerido.entry(c.producto_hijo_id).or_insert(0.0) += c.cantidad * cant_total;
-                        }
-                    }
-                }
-                continue;
-            };
-
-            // ¿Es combo? Chequear tipo_producto + presencia de componentes
-            let (tipo_prod, tiene_componentes): (String, i64) = conn.query_row(
-                "SELECT COALESCE(tipo_producto, 'SIMPLE'),
-                        (SELECT COUNT(*) FROM producto_componentes WHERE producto_padre_id = productos.id)
-                 FROM productos WHERE id = ?1",
-                rusqlite::params![pid],
-                |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)?)),
-            ).unwrap_or(("SIMPLE".to_string(), 0));
-            let es_combo = tipo_prod == "COMBO_FIJO" || tipo_prod == "COMBO_FLEXIBLE" || tiene_componentes > 0;
-
-            if !es_combo {
-                // Producto simple: cargar cantidad del producto base
-                *requerido.entry(pid).or_insert(0.0) += cant_total;
-            } else if tipo_prod == "COMBO_FLEXIBLE" {
-                // Usar combo_seleccion (lo que el cajero escogió en el momento)
-                if let Some(sel) = &it.combo_seleccion {
-                    for c in sel {
-                        if c.cantidad > 0.0 {
-                            *requerido.entry(c.producto_hijo_id).or_insert(0.0) += c.cantidad * cant_total;
-                        }
-                    }
-                }
-            } else {
-                // COMBO_FIJO (o producto con componentes detectados): leer de producto_componentes
-                let mut stmt = conn.prepare(
-                    "SELECT producto_hijo_id, cantidad FROM producto_componentes WHERE producto_padre_id = ?1"
-                ).map_err(|e| e.to_string())?;
-                let rows = stmt.query_map(rusqlite::params![pid], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, f64>(1)?)))
-                    .map_err(|e| e.to_string())?;
-                for row in rows {
-                    let (hijo_id, cant_componente) = row.map_err(|e| e.to_string())?;
-                    *requerido.entry(hijo_id).or_insert(0.0) += cant_componente * cant_total;
-                }
-            }
-        }
-
+        let requerido = stock_requerido(&conn, &venta.items)?;
         for (pid, cant_req) in &requerido {
-            let (stock_actual, es_serv, no_ctrl): (f64, bool, bool) = conn.query_row(
-                "SELECT stock_actual, COALESCE(es_servicio,0), COALESCE(no_controla_stock,0) FROM productos WHERE id = ?1",
+            let (es_serv, no_ctrl): (bool, bool) = conn.query_row(
+                "SELECT COALESCE(es_servicio,0), COALESCE(no_controla_stock,0) FROM productos WHERE id = ?1",
                 rusqlite::params![pid],
-                |r| Ok((r.get::<_, f64>(0)?, r.get::<_, i32>(1)? != 0, r.get::<_, i32>(2)? != 0)),
-            ).unwrap_or((0.0, false, false));
+                |r| Ok((r.get::<_, i32>(0)? != 0, r.get::<_, i32>(1)? != 0)),
+            ).unwrap_or((false, false));
             if es_serv || no_ctrl { continue; }
+            let stock_actual = crate::commands::stock::stock_en(&conn, *pid, est_id);
             if *cant_req > stock_actual + 1e-9 {
                 let nombre: String = conn.query_row(
                     "SELECT nombre FROM productos WHERE id = ?1",
@@ -134,17 +152,6 @@ pub fn registrar_venta(
         .query_row("SELECT value FROM config WHERE key = 'terminal_punto_emision'", [], |row| row.get(0))
         .unwrap_or_else(|_| "001".to_string());
 
-    // Multi-almacén: obtener ID del establecimiento para descontar stock
-    let multi_almacen: bool = conn
-        .query_row("SELECT value FROM config WHERE key = 'multi_almacen_activo'", [], |row| row.get::<_, String>(0))
-        .map(|v| v == "1")
-        .unwrap_or(false);
-    let est_id: Option<i64> = if multi_almacen {
-        conn.query_row("SELECT id FROM establecimientos WHERE codigo = ?1", rusqlite::params![terminal_est], |row| row.get(0)).ok()
-    } else {
-        None
-    };
-
     // Obtener secuencial interno de tabla secuenciales
     conn.execute(
         "INSERT OR IGNORE INTO secuenciales (establecimiento_codigo, punto_emision_codigo, tipo_documento, secuencial) VALUES (?1, ?2, 'NOTA_VENTA', 1)",
@@ -343,43 +350,13 @@ pub fn registrar_venta(
         let factor_unidad = item.factor_unidad.unwrap_or(1.0);
         let cantidad_base = item.cantidad * factor_unidad; // cantidad real a descontar del stock
 
-        // Lote de caducidad (v2.2.0): si producto requiere caducidad y no viene lote_id,
-        // aplicar FEFO automatico (el que vence primero con stock disponible)
-        let requiere_caducidad: bool = conn.query_row(
-            "SELECT COALESCE(requiere_caducidad, 0) FROM productos WHERE id = ?1",
-            rusqlite::params![item.producto_id],
-            |row| row.get::<_, i32>(0).map(|v| v != 0)
-        ).unwrap_or(false);
-
-        let lote_id_final: Option<i64> = if let Some(lid) = item.lote_id {
-            // Validar que el lote tenga stock suficiente — anti-fraude/error UX
-            let stock_lote: f64 = conn.query_row(
-                "SELECT cantidad FROM lotes_caducidad WHERE id = ?1 AND producto_id = ?2",
-                rusqlite::params![lid, item.producto_id],
-                |r| r.get(0),
-            ).unwrap_or(0.0);
-            if cantidad_base > stock_lote + 1e-9 {
-                let nombre_prod: String = conn.query_row(
-                    "SELECT nombre FROM productos WHERE id = ?1",
-                    rusqlite::params![item.producto_id], |r| r.get(0)
-                ).unwrap_or_else(|_| format!("ID {}", item.producto_id.unwrap_or(0)));
-                return Err(format!(
-                    "El lote #{} de '{}' solo tiene {:.2} unidades. Intentas vender {:.2}. Reduce la cantidad o agrega otro lote/sin lote.",
-                    lid, nombre_prod, stock_lote, cantidad_base
-                ));
-            }
-            Some(lid)
-        } else if requiere_caducidad {
-            // Auto FEFO: lote con fecha_caducidad mas proxima que tenga stock
-            conn.query_row(
-                "SELECT id FROM lotes_caducidad WHERE producto_id = ?1 AND cantidad >= ?2
-                 ORDER BY fecha_caducidad ASC LIMIT 1",
-                rusqlite::params![item.producto_id, cantidad_base],
-                |row| row.get(0)
-            ).ok()
-        } else {
-            None
+        // Lote de caducidad (v2.2.0): reparto FEFO entre lotes vigentes, o el
+        // lote elegido. Los vencidos no se venden (commands/caducidad.rs).
+        let plan_lotes = match item.producto_id {
+            Some(pid) => crate::commands::caducidad::planificar_fefo(&conn, pid, est_id, cantidad_base, item.lote_id)?,
+            None => Vec::new(),
         };
+        let lote_id_final: Option<i64> = plan_lotes.first().map(|a| a.lote_id);
 
         // v2.6.32: snapshot del lote (numero + caducidad) para trazabilidad/recall.
         // Se guarda copia en venta_detalles para no perderlo si el lote se borra.
@@ -398,8 +375,8 @@ pub fn registrar_venta(
             "INSERT INTO venta_detalles (venta_id, producto_id, cantidad, precio_unitario,
              descuento, iva_porcentaje, subtotal, info_adicional, precio_costo,
              unidad_id, unidad_nombre, factor_unidad, lote_id, lote_numero, lote_fecha_caducidad,
-             descripcion)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+             descripcion, establecimiento_origen_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
             rusqlite::params![
                 venta_id,
                 item.producto_id,
@@ -417,16 +394,15 @@ pub fn registrar_venta(
                 lote_numero_snap,
                 lote_fecha_snap,
                 item.descripcion,
+                est_id,
             ],
         )
         .map_err(|e| e.to_string())?;
+        let detalle_id = conn.last_insert_rowid();
 
-        // Descontar del lote (si aplica)
-        if let Some(lid) = lote_id_final {
-            conn.execute(
-                "UPDATE lotes_caducidad SET cantidad = MAX(cantidad - ?1, 0) WHERE id = ?2",
-                rusqlite::params![cantidad_base, lid],
-            ).ok();
+        // Descontar de los lotes (si aplica)
+        if let Some(pid) = item.producto_id {
+            crate::commands::caducidad::aplicar_fefo(&conn, venta_id, detalle_id, pid, &plan_lotes)?;
         }
 
         // La columna tipo_producto se garantiza en create_tables/run_migrations al
@@ -434,13 +410,13 @@ pub fn registrar_venta(
         // tomando el schema lock de SQLite innecesariamente en el hot-path.
 
         // Obtener stock antes de descontar y verificar si es servicio / no_controla_stock / combo
-        let (stock_antes, es_servicio, no_controla_stock, tipo_producto): (f64, bool, bool, String) = conn
+        let (es_servicio, no_controla_stock, tipo_producto): (bool, bool, String) = conn
             .query_row(
-                "SELECT stock_actual, es_servicio, COALESCE(no_controla_stock, 0), COALESCE(tipo_producto, 'SIMPLE') FROM productos WHERE id = ?1",
+                "SELECT es_servicio, COALESCE(no_controla_stock, 0), COALESCE(tipo_producto, 'SIMPLE') FROM productos WHERE id = ?1",
                 rusqlite::params![item.producto_id],
-                |row| Ok((row.get::<_, f64>(0)?, row.get::<_, bool>(1)?, row.get::<_, i32>(2)? != 0, row.get::<_, String>(3)?)),
+                |row| Ok((row.get::<_, bool>(0)?, row.get::<_, i32>(1)? != 0, row.get::<_, String>(2)?)),
             )
-            .unwrap_or((0.0, false, false, "SIMPLE".to_string()));
+            .unwrap_or((false, false, "SIMPLE".to_string()));
 
         // v2.5.18 DEFENSIVA: aunque tipo_producto sea "SIMPLE", si el producto TIENE
         // componentes registrados, tratarlo como COMBO_FIJO. Esto cubre casos donde
@@ -479,32 +455,17 @@ pub fn registrar_venta(
         // Los combos no descuentan stock del padre — se gestiona via componentes mas abajo.
         // v2.6.36: las lineas a medida (sin producto) tampoco descuentan stock de padre.
         let omite_stock = item.producto_id.is_none() || es_servicio || no_controla_stock || es_combo;
+        // Platos con receta: se descuentan sus insumos (mas abajo), no el plato.
+        let usa_receta = !es_combo && crate::commands::recetas::tiene_receta(&conn, item.producto_id);
+        let omite_stock = omite_stock || usa_receta;
 
-        // Descontar stock (cantidad_base = cantidad x factor de la unidad de venta)
-        if !omite_stock {
-            conn.execute(
-                "UPDATE productos SET stock_actual = stock_actual - ?1,
-                 updated_at = datetime('now','localtime')
-                 WHERE id = ?2",
-                rusqlite::params![cantidad_base, item.producto_id],
-            )
-            .map_err(|e| e.to_string())?;
-        }
-
-        // Multi-almacén: también descontar de stock_establecimiento
-        if let Some(eid) = est_id {
-            if !omite_stock {
-                conn.execute(
-                    "UPDATE stock_establecimiento SET stock_actual = stock_actual - ?1
-                     WHERE producto_id = ?2 AND establecimiento_id = ?3",
-                    rusqlite::params![cantidad_base, item.producto_id, eid],
-                ).ok();
-            }
-        }
-
-        // Registrar movimiento de inventario (kardex) para productos físicos
+        // Descontar stock del establecimiento (cantidad_base = cantidad x factor de la unidad de venta)
+        // y registrar movimiento de inventario (kardex) para productos físicos.
         // costo_unitario = precio_costo snapshot del momento de la venta (NO precio de venta)
-        if !omite_stock {
+        if let (false, Some(pid)) = (omite_stock, item.producto_id) {
+            // Costo FIFO/PMP de la linea, antes de que baje el stock (commands/costeo.rs)
+            crate::commands::costeo::consumir_fifo(&conn, venta_id, detalle_id, pid, est_id, cantidad_base)?;
+            let mov = crate::commands::stock::mover(&conn, pid, est_id, -cantidad_base)?;
             // v2.5.28: grabar motivo con el numero visible (NV-XXXXXXXXX) para que
             // el kardex muestre "Venta NV-000000093" en lugar del id interno.
             let motivo_venta = format!("Venta {}", numero);
@@ -512,10 +473,10 @@ pub fn registrar_venta(
                 "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, establecimiento_id, motivo)
                  VALUES (?1, 'VENTA', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
                 rusqlite::params![
-                    item.producto_id,
+                    pid,
                     -(cantidad_base),
-                    stock_antes,
-                    stock_antes - cantidad_base,
+                    mov.anterior,
+                    mov.nuevo,
                     precio_costo_prod,
                     venta_id,
                     usuario_nombre,
@@ -540,8 +501,6 @@ pub fn registrar_venta(
             .unwrap_or_default()
         };
 
-        let detalle_id = conn.last_insert_rowid();
-
         // === COMBOS: descontar stock de componentes ===
         // Para COMBO_FIJO: usa la definicion guardada en producto_componentes
         // Para COMBO_FLEXIBLE: usa item.combo_seleccion (lo que el cajero escogio)
@@ -586,32 +545,22 @@ pub fn registrar_venta(
 
             for (hijo_id, cant_total) in componentes_a_descontar {
                 // Stock anterior del hijo + flags
-                let (stock_h_antes, es_serv_h, no_ctrl_h, costo_h): (f64, bool, bool, f64) = conn.query_row(
-                    "SELECT stock_actual, COALESCE(es_servicio,0), COALESCE(no_controla_stock,0), precio_costo
+                let (es_serv_h, no_ctrl_h, costo_h): (bool, bool, f64) = conn.query_row(
+                    "SELECT COALESCE(es_servicio,0), COALESCE(no_controla_stock,0), precio_costo
                      FROM productos WHERE id = ?1",
                     rusqlite::params![hijo_id],
-                    |r| Ok((r.get::<_, f64>(0)?, r.get::<_, i32>(1)? != 0, r.get::<_, i32>(2)? != 0, r.get::<_, f64>(3)?)),
-                ).unwrap_or((0.0, false, false, 0.0));
+                    |r| Ok((r.get::<_, i32>(0)? != 0, r.get::<_, i32>(1)? != 0, r.get::<_, f64>(2)?)),
+                ).unwrap_or((false, false, 0.0));
 
                 let omite_h = es_serv_h || no_ctrl_h;
 
                 if !omite_h {
-                    conn.execute(
-                        "UPDATE productos SET stock_actual = stock_actual - ?1, updated_at = datetime('now','localtime') WHERE id = ?2",
-                        rusqlite::params![cant_total, hijo_id],
-                    ).map_err(|e| e.to_string())?;
-                    if let Some(eid) = est_id {
-                        conn.execute(
-                            "UPDATE stock_establecimiento SET stock_actual = stock_actual - ?1
-                             WHERE producto_id = ?2 AND establecimiento_id = ?3",
-                            rusqlite::params![cant_total, hijo_id, eid],
-                        ).ok();
-                    }
+                    let mov = crate::commands::stock::mover(&conn, hijo_id, est_id, -cant_total)?;
                     let motivo_combo = format!("Venta {} (combo: {})", numero, nombre_prod);
                     let _ = conn.execute(
                         "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, establecimiento_id, motivo)
                          VALUES (?1, 'VENTA_COMBO', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-                        rusqlite::params![hijo_id, -cant_total, stock_h_antes, stock_h_antes - cant_total, costo_h, venta_id, usuario_nombre, est_id, motivo_combo],
+                        rusqlite::params![hijo_id, -cant_total, mov.anterior, mov.nuevo, costo_h, venta_id, usuario_nombre, est_id, motivo_combo],
                     );
                 }
 
@@ -623,6 +572,19 @@ pub fn registrar_venta(
             }
         }
 
+        // === RECETAS: descontar insumos del plato ===
+        if usa_receta {
+            if let Some(pid) = item.producto_id {
+                let ctx = crate::commands::recetas::ContextoKardex {
+                    venta_id,
+                    numero: &numero,
+                    usuario: &usuario_nombre,
+                    est_id,
+                };
+                crate::commands::recetas::descontar_receta(&conn, &ctx, detalle_id, pid, cantidad_base, &nombre_prod)?;
+            }
+        }
+
         detalles_guardados.push(VentaDetalle {
             id: Some(detalle_id),
             venta_id: Some(venta_id),
@@ -766,6 +728,30 @@ pub fn registrar_venta(
         )
         .ok();
 
+    // Webhooks: solo encola (sin red); el worker entrega en background.
+    crate::webhooks::emitir(
+        &conn,
+        "venta.registrada",
+        &format!("venta:{}", venta_id),
+        serde_json::json!({
+            "venta_id": venta_id,
+            "numero": numero,
+            "tipo_documento": venta.tipo_documento,
+            "cliente_id": venta.cliente_id,
+            "cliente_nombre": cliente_nombre,
+            "subtotal_sin_iva": subtotal_sin_iva,
+            "subtotal_con_iva": subtotal_con_iva,
+            "descuento": venta.descuento,
+            "iva": iva_total,
+            "total": total,
+            "forma_pago": venta.forma_pago,
+            "usuario": usuario_nombre,
+            "establecimiento": terminal_est,
+            "punto_emision": terminal_pe,
+            "detalles": detalles_guardados,
+        }),
+    );
+
     Ok(VentaCompleta {
         venta: Venta {
             id: Some(venta_id),
@@ -1493,7 +1479,15 @@ pub fn registrar_nota_credito(
 
     let nc_id = conn.last_insert_rowid();
 
-    // Insertar detalles y devolver stock
+    // Insertar detalles y devolver stock al establecimiento de la venta original
+    let venta_est: String = conn
+        .query_row(
+            "SELECT COALESCE(establecimiento, '001') FROM ventas WHERE id = ?1",
+            rusqlite::params![nota.venta_id],
+            |row| row.get(0),
+        )
+        .unwrap_or_else(|_| "001".to_string());
+    let nc_est_id = crate::commands::stock::establecimiento_por_codigo(&conn, &venta_est)?;
     for item in &nota.items {
         let subtotal = item.cantidad * item.precio_unitario - item.descuento;
         conn.execute(
@@ -1507,39 +1501,31 @@ pub fn registrar_nota_credito(
         )
         .map_err(|e| e.to_string())?;
 
-        // Stock anterior + flag servicio para kardex
-        let stock_antes: f64 = conn.query_row(
-            "SELECT COALESCE(stock_actual, 0) FROM productos WHERE id = ?1",
-            rusqlite::params![item.producto_id], |r| r.get(0),
-        ).unwrap_or(0.0);
+        // Flag servicio para kardex
         let es_serv: bool = conn.query_row(
             "SELECT COALESCE(es_servicio, 0) FROM productos WHERE id = ?1",
             rusqlite::params![item.producto_id], |r| r.get::<_, i32>(0),
         ).map(|v| v != 0).unwrap_or(false);
-
-        // Devolver stock (si no es servicio)
-        conn.execute(
-            "UPDATE productos SET stock_actual = stock_actual + ?1,
-             updated_at = datetime('now','localtime')
-             WHERE id = ?2 AND es_servicio = 0",
-            rusqlite::params![item.cantidad, item.producto_id],
-        )
-        .ok();
-
-        // Multi-almacén: devolver stock a stock_establecimiento
-        let nc_est_id: Option<i64> = conn
-            .query_row("SELECT id FROM establecimientos WHERE codigo = ?1", rusqlite::params![establecimiento], |row| row.get(0))
-            .ok();
-        if let Some(eid) = nc_est_id {
-            conn.execute(
-                "UPDATE stock_establecimiento SET stock_actual = stock_actual + ?1
-                 WHERE producto_id = ?2 AND establecimiento_id = ?3",
-                rusqlite::params![item.cantidad, item.producto_id, eid],
+        // Un plato con receta devuelto no vuelve al inventario (ni el plato,
+        // que no tiene stock propio, ni los insumos ya cocinados).
+        let es_serv = es_serv || crate::commands::recetas::tiene_receta(&conn, item.producto_id);
+
+        // Devolver stock al establecimiento de la venta original (si no es
+        // servicio) y registrar movimiento kardex tipo 'NOTA_CREDITO'
+        // para trazabilidad (v2.3.49)
+        if let (false, Some(pid)) = (es_serv, item.producto_id) {
+            let mov = crate::commands::stock::mover(&conn, pid, nc_est_id, item.cantidad)?;
+            // Devolver a los mismos lotes de los que salio
+            let lote_legacy: Option<i64> = conn.query_row(
+                "SELECT lote_id FROM venta_detalles WHERE venta_id = ?1 AND producto_id = ?2 AND lote_id IS NOT NULL LIMIT 1",
+                rusqlite::params![nota.venta_id, pid], |r| r.get(0),
             ).ok();
-        }
-
-        // v2.3.49 FIX: registrar movimiento kardex tipo 'NOTA_CREDITO' para trazabilidad
-        if !es_serv {
+            crate::commands::caducidad::reintegrar_lotes(&conn, nota.venta_id, pid, item.cantidad, lote_legacy)?;
+            crate::commands::costeo::reintegrar_capas(&conn, nota.venta_id, pid, nc_est_id, item.cantidad)?;
+            crate::restaurante::modificadores::reintegrar_insumos(
+                &conn, nota.venta_id, Some((pid, item.cantidad)), "NOTA_CREDITO_MODIFICADOR",
+                &format!("NC {} (modificadores)", numero), &usuario_nombre,
+            )?;
             let costo_snap: f64 = conn.query_row(
                 "SELECT COALESCE(precio_costo, 0) FROM productos WHERE id = ?1",
                 rusqlite::params![item.producto_id], |r| r.get(0),
@@ -1550,7 +1536,7 @@ pub fn registrar_nota_credito(
                  VALUES (?1, 'NOTA_CREDITO', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
                 rusqlite::params![
                     item.producto_id, item.cantidad,
-                    stock_antes, stock_antes + item.cantidad,
+                    mov.anterior, mov.nuevo,
                     costo_snap, nc_id, usuario_nombre, nc_est_id, motivo_kardex,
                 ],
             );
@@ -1743,7 +1729,15 @@ pub fn crear_devolucion_interna(
 
     let nc_id = conn.last_insert_rowid();
 
-    // Insertar detalles y devolver stock (si aplica)
+    // Insertar detalles y devolver stock (si aplica) al establecimiento de la venta
+    let venta_est: String = conn
+        .query_row(
+            "SELECT COALESCE(establecimiento, '001') FROM ventas WHERE id = ?1",
+            rusqlite::params![venta_id],
+            |row| row.get(0),
+        )
+        .unwrap_or_else(|_| "001".to_string());
+    let venta_est_id = crate::commands::stock::establecimiento_por_codigo(&conn, &venta_est)?;
     for item in &items {
         let producto_id = item.get("producto_id").and_then(|v| v.as_i64()).unwrap_or(0);
         let cantidad = item.get("cantidad").and_then(|v| v.as_f64()).unwrap_or(0.0);
@@ -1771,41 +1765,27 @@ pub fn crear_devolucion_interna(
         // Si devolver_stock_item=false → solo se devuelve dinero (NC), el cliente
         // conserva el producto. Util para descuento/compensacion sin retornar fisicamente.
         if devolver_stock_item {
-            // Leer stock anterior para el kardex
-            let stock_antes: f64 = conn.query_row(
-                "SELECT COALESCE(stock_actual, 0) FROM productos WHERE id = ?1",
-                rusqlite::params![producto_id], |r| r.get(0),
-            ).unwrap_or(0.0);
             let es_servicio: bool = conn.query_row(
                 "SELECT COALESCE(es_servicio, 0) FROM productos WHERE id = ?1",
                 rusqlite::params![producto_id], |r| r.get::<_, i32>(0),
             ).map(|v| v != 0).unwrap_or(false);
 
-            // Devolver stock (si no es servicio)
-            conn.execute(
-                "UPDATE productos SET stock_actual = stock_actual + ?1,
-                 updated_at = datetime('now','localtime')
-                 WHERE id = ?2 AND es_servicio = 0",
-                rusqlite::params![cantidad, producto_id],
-            )
-            .ok();
-
-            // Multi-almacén: devolver stock a stock_establecimiento
-            let est_id: Option<i64> = conn
-                .query_row("SELECT id FROM establecimientos WHERE codigo = ?1", rusqlite::params![establecimiento], |row| row.get(0))
-                .ok();
-            if let Some(eid) = est_id {
-                conn.execute(
-                    "UPDATE stock_establecimiento SET stock_actual = stock_actual + ?1
-                     WHERE producto_id = ?2 AND establecimiento_id = ?3",
-                    rusqlite::params![cantidad, producto_id, eid],
-                ).ok();
-            }
-
+            // Devolver stock al establecimiento de la venta (si no es servicio).
             // v2.3.48 FIX: registrar movimiento en kardex (tipo DEVOLUCION) para trazabilidad.
             // Sin esto el stock subia en productos.stock_actual pero el usuario no veia
             // el movimiento en la pantalla de Inventario / Kardex.
             if !es_servicio {
+                let mov = crate::commands::stock::mover(&conn, producto_id, venta_est_id, cantidad)?;
+                let lote_legacy: Option<i64> = conn.query_row(
+                    "SELECT lote_id FROM venta_detalles WHERE venta_id = ?1 AND producto_id = ?2 AND lote_id IS NOT NULL LIMIT 1",
+                    rusqlite::params![venta_id, producto_id], |r| r.get(0),
+                ).ok();
+                crate::commands::caducidad::reintegrar_lotes(&conn, venta_id, producto_id, cantidad, lote_legacy)?;
+                crate::commands::costeo::reintegrar_capas(&conn, venta_id, producto_id, venta_est_id, cantidad)?;
+                crate::restaurante::modificadores::reintegrar_insumos(
+                    &conn, venta_id, Some((producto_id, cantidad)), "DEVOLUCION_MODIFICADOR",
+                    &format!("Devolucion NC {} (modificadores)", numero), &usuario_nombre,
+                )?;
                 let costo_snap: f64 = conn.query_row(
                     "SELECT COALESCE(precio_costo, 0) FROM productos WHERE id = ?1",
                     rusqlite::params![producto_id], |r| r.get(0),
@@ -1817,12 +1797,12 @@ pub fn crear_devolucion_interna(
                     rusqlite::params![
                         producto_id,
                         cantidad,                  // positivo (entra al stock)
-                        stock_antes,
-                        stock_antes + cantidad,
+                        mov.anterior,
+                        mov.nuevo,
                         costo_snap,
                         nc_id,                     // referencia a la NC
                         usuario_nombre,
-                        est_id,
+                        venta_est_id,
                         motivo_kardex,
                     ],
                 );
@@ -2633,41 +2613,23 @@ pub fn convertir_guia_a_venta(
     //      se movió. Debemos descontar AHORA las cantidades finales (con overrides
     //      aplicados), exactamente una vez, con kardex tipo 'GUIA_REMISION'.
     if !ya_recibida {
-        // Multi-almacen: ID del establecimiento
-        let multi_almacen: bool = conn.query_row(
-            "SELECT value FROM config WHERE key = 'multi_almacen_activo'", [],
-            |r| r.get::<_, String>(0)
-        ).map(|v| v == "1").unwrap_or(false);
-        let est_id_fact: Option<i64> = if multi_almacen {
-            conn.query_row("SELECT id FROM establecimientos WHERE codigo = ?1",
-                rusqlite::params![terminal_est], |r| r.get(0)).ok()
-        } else { None };
+        // Establecimiento de la terminal que factura
+        let est_id_fact = crate::commands::stock::establecimiento_por_codigo(&conn, &terminal_est)?;
 
         // Descontar cantidades finales (una sola vez). referencia = nueva venta.
         for (pid, cant, _pu, _desc, _iva, _sub, _info) in &items_finales {
             if *cant <= 0.0 { continue; }
-            let (stock_antes, es_servicio, no_controla_stock): (f64, bool, bool) = conn
+            let (es_servicio, no_controla_stock): (bool, bool) = conn
                 .query_row(
-                    "SELECT stock_actual, es_servicio, COALESCE(no_controla_stock, 0) FROM productos WHERE id = ?1",
+                    "SELECT es_servicio, COALESCE(no_controla_stock, 0) FROM productos WHERE id = ?1",
                     rusqlite::params![pid],
-                    |row| Ok((row.get::<_, f64>(0)?, row.get::<_, bool>(1)?, row.get::<_, i32>(2)? != 0)),
+                    |row| Ok((row.get::<_, bool>(0)?, row.get::<_, i32>(1)? != 0)),
                 )
-                .unwrap_or((0.0, false, false));
+                .unwrap_or((false, false));
             let omite_stock = es_servicio || no_controla_stock;
             if omite_stock { continue; }
 
-            conn.execute(
-                "UPDATE productos SET stock_actual = stock_actual - ?1, updated_at = datetime('now','localtime')
-                 WHERE id = ?2",
-                rusqlite::params![cant, pid],
-            ).map_err(|e| e.to_string())?;
-            if let Some(eid) = est_id_fact {
-                conn.execute(
-                    "UPDATE stock_establecimiento SET stock_actual = stock_actual - ?1
-                     WHERE producto_id = ?2 AND establecimiento_id = ?3",
-                    rusqlite::params![cant, pid, eid],
-                ).ok();
-            }
+            let mov = crate::commands::stock::mover(&conn, *pid, est_id_fact, -cant)?;
             let costo_snap: f64 = conn.query_row(
                 "SELECT precio_costo FROM productos WHERE id = ?1",
                 rusqlite::params![pid], |r| r.get(0),
@@ -2676,7 +2638,7 @@ pub fn convertir_guia_a_venta(
             let _ = conn.execute(
                 "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, establecimiento_id)
                  VALUES (?1, 'GUIA_REMISION', ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-                rusqlite::params![pid, -(*cant), stock_antes, stock_antes - cant, costo_snap, guia_id, usuario_nombre, est_id_fact],
+                rusqlite::params![pid, -(*cant), mov.anterior, mov.nuevo, costo_snap, guia_id, usuario_nombre, est_id_fact],
             );
         }
     }
@@ -3153,20 +3115,16 @@ pub fn cambiar_estado_guia(
         return Err(format!("La guía ya está en estado {}", estado_actual));
     }
 
-    // Multi-almacén: obtener ID del establecimiento (necesario al descontar stock
-    // en la recepción ENTREGADA).
-    let terminal_est: String = conn
-        .query_row("SELECT value FROM config WHERE key = 'terminal_establecimiento'", [], |row| row.get(0))
+    // La mercadería sale del establecimiento que emitió la guía (necesario al
+    // descontar stock en la recepción ENTREGADA).
+    let guia_est: String = conn
+        .query_row(
+            "SELECT COALESCE(establecimiento, '001') FROM ventas WHERE id = ?1",
+            rusqlite::params![guia_id],
+            |row| row.get(0),
+        )
         .unwrap_or_else(|_| "001".to_string());
-    let multi_almacen: bool = conn
-        .query_row("SELECT value FROM config WHERE key = 'multi_almacen_activo'", [], |row| row.get::<_, String>(0))
-        .map(|v| v == "1")
-        .unwrap_or(false);
-    let est_id: Option<i64> = if multi_almacen {
-        conn.query_row("SELECT id FROM establecimientos WHERE codigo = ?1", rusqlite::params![terminal_est], |row| row.get(0)).ok()
-    } else {
-        None
-    };
+    let est_id = crate::commands::stock::establecimiento_por_codigo(&conn, &guia_est)?;
 
     // REFACTOR INVENTARIO: el stock se mueve (descuenta) AQUÍ, en la recepción.
     if nuevo_estado == "ENTREGADA" {
@@ -3198,31 +3156,18 @@ pub fn cambiar_estado_guia(
             let recibida = recibida_raw.max(0.0).min(*cantidad_orig);
 
             // Leer estado del producto (stock + flags de control).
-            let (stock_antes, es_servicio, no_controla_stock): (f64, bool, bool) = conn
+            let (es_servicio, no_controla_stock): (bool, bool) = conn
                 .query_row(
-                    "SELECT stock_actual, es_servicio, COALESCE(no_controla_stock, 0) FROM productos WHERE id = ?1",
+                    "SELECT es_servicio, COALESCE(no_controla_stock, 0) FROM productos WHERE id = ?1",
                     rusqlite::params![producto_id],
-                    |row| Ok((row.get::<_, f64>(0)?, row.get::<_, bool>(1)?, row.get::<_, i32>(2)? != 0)),
+                    |row| Ok((row.get::<_, bool>(0)?, row.get::<_, i32>(1)? != 0)),
                 )
-                .unwrap_or((0.0, false, false));
+                .unwrap_or((false, false));
             let omite_stock = es_servicio || no_controla_stock;
 
             // Descontar SOLO lo realmente recibido (y sólo productos físicos).
             if recibida > 0.0 && !omite_stock {
-                conn.execute(
-                    "UPDATE productos SET stock_actual = stock_actual - ?1,
-                     updated_at = datetime('now','localtime')
-                     WHERE id = ?2",
-                    rusqlite::params![recibida, producto_id],
-                ).map_err(|e| e.to_string())?;
-
-                if let Some(eid) = est_id {
-                    conn.execute(
-                        "UPDATE stock_establecimiento SET stock_actual = stock_actual - ?1
-                         WHERE producto_id = ?2 AND establecimiento_id = ?3",
-                        rusqlite::params![recibida, producto_id, eid],
-                    ).ok();
-                }
+                let mov = crate::commands::stock::mover(&conn, *producto_id, est_id, -recibida)?;
 
                 // Kardex: salida por recepción de nota de entrega.
                 let costo_snap: f64 = conn.query_row(
@@ -3236,8 +3181,8 @@ pub fn cambiar_estado_guia(
                     rusqlite::params![
                         producto_id,
                         -recibida,
-                        stock_antes,
-                        stock_antes - recibida,
+                        mov.anterior,
+                        mov.nuevo,
                         costo_snap,
                         guia_id,
                         usuario_nombre,
@@ -3556,27 +3501,25 @@ pub fn anular_venta(
         ));
     }
 
-    // Multi-almacen: obtener est_id para revertir stock por establecimiento
-    let multi_almacen: bool = conn
-        .query_row("SELECT value FROM config WHERE key = 'multi_almacen_activo'", [], |row| row.get::<_, String>(0))
-        .map(|v| v == "1")
-        .unwrap_or(false);
-    let est_id: Option<i64> = if multi_almacen {
-        let terminal_est: String = conn.query_row(
-            "SELECT value FROM config WHERE key = 'terminal_establecimiento'", [],
-            |row| row.get(0)
-        ).unwrap_or_else(|_| "001".to_string());
-        conn.query_row(
-            "SELECT id FROM establecimientos WHERE codigo = ?1",
-            rusqlite::params![terminal_est], |row| row.get(0)
-        ).ok()
-    } else { None };
+    // El stock vuelve al establecimiento donde se hizo la venta
+    let venta_est: String = conn
+        .query_row(
+            "SELECT COALESCE(establecimiento, '001') FROM ventas WHERE id = ?1",
+            rusqlite::params![venta_id],
+            |row| row.get(0),
+        )
+        .unwrap_or_else(|_| "001".to_string());
+    let est_id = crate::commands::stock::establecimiento_por_codigo(&conn, &venta_est)?;
 
     // Reintegrar stock de cada item (considerando factor_unidad para multi-unidad)
     // y lote_id para reintegrar al lote correspondiente
     let mut stmt = conn.prepare(
-        "SELECT producto_id, cantidad, COALESCE(factor_unidad, 1) as factor, lote_id FROM venta_detalles WHERE venta_id = ?1"
+        "SELECT producto_id, cantidad, COALESCE(factor_unidad, 1) as factor, lote_id FROM venta_detalles
+         WHERE venta_id = ?1
+           AND id NOT IN (SELECT venta_detalle_id FROM venta_detalle_receta)"
     ).map_err(|e| e.to_string())?;
+    // Los platos con receta no descontaron stock propio: sus insumos se
+    // reintegran aparte (venta_detalle_receta), despues de los combos.
     let items: Vec<(i64, f64, f64, Option<i64>)> = if omitir_reintegro_stock {
         // Nota de entrega PENDIENTE/RECHAZADA: nunca descontó stock → no reintegrar.
         Vec::new()
@@ -3593,11 +3536,9 @@ pub fn anular_venta(
     // críticos. Antes se usaba .ok() en todos los UPDATE, lo que ocultaba
     // fallos (ej. columna updated_at faltante en BDs viejas) → venta quedaba
     // anulada pero stock no se actualizaba. Ahora:
-    //   - UPDATE de productos.stock_actual → fail-fast con map_err
+    //   - UPDATE del stock (establecimiento + global derivado) → fail-fast con map_err
     //   - INSERT a movimientos_inventario → fail-fast (auditoría obligatoria)
-    //   - UPDATE de updated_at QUITADO (no es crítico y rompe en BDs viejas)
-    //   - lote_caducidad y stock_establecimiento → seguir como best-effort
-    //     porque son secundarios (multi-almacén/caducidad pueden estar off)
+    //   - lote_caducidad → sigue como best-effort (caducidad puede estar off)
     for (prod_id, cant, factor, lote_id) in &items {
         let cant_base = cant * factor;
         let omite: bool = conn.query_row(
@@ -3607,42 +3548,21 @@ pub fn anular_venta(
         ).unwrap_or(false);
         if omite { continue; }
 
-        // Best-effort: reversar al lote (no crítico, módulo caducidad puede no estar)
-        if let Some(lid) = lote_id {
-            let _ = conn.execute(
-                "UPDATE lotes_caducidad SET cantidad = cantidad + ?1 WHERE id = ?2",
-                rusqlite::params![cant_base, lid],
-            );
-        }
+        // Reversar a los lotes y capas de costo de los que salio (sin lotes no hace nada)
+        crate::commands::caducidad::reintegrar_lotes(&conn, venta_id, *prod_id, cant_base, *lote_id)?;
+        let _ = crate::commands::costeo::reintegrar_capas(&conn, venta_id, *prod_id, est_id, cant_base);
 
-        let stock_antes: f64 = conn.query_row(
-            "SELECT stock_actual FROM productos WHERE id = ?1",
-            rusqlite::params![prod_id], |row| row.get(0)
-        ).unwrap_or(0.0);
-
-        // CRÍTICO: UPDATE stock (sin updated_at para compatibilidad con BDs viejas)
-        conn.execute(
-            "UPDATE productos SET stock_actual = stock_actual + ?1 WHERE id = ?2",
-            rusqlite::params![cant_base, prod_id]
-        ).map_err(|e| format!(
+        // CRÍTICO: UPDATE stock
+        let mov = crate::commands::stock::mover(&conn, *prod_id, est_id, cant_base).map_err(|e| format!(
             "Error reintegrando stock del producto {}: {}. La anulación NO se aplicó para evitar inconsistencia.",
             prod_id, e
         ))?;
 
-        // Best-effort: stock por establecimiento (multi-almacén puede no estar)
-        if let Some(eid) = est_id {
-            let _ = conn.execute(
-                "UPDATE stock_establecimiento SET stock_actual = stock_actual + ?1
-                 WHERE producto_id = ?2 AND establecimiento_id = ?3",
-                rusqlite::params![cant_base, prod_id, eid]
-            );
-        }
-
         // CRÍTICO: movimiento auditable (sin esto no podemos auto-reparar en el futuro)
         conn.execute(
             "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, motivo, usuario, referencia_id, establecimiento_id)
              VALUES (?1, 'ANULACION_VENTA', ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            rusqlite::params![prod_id, cant_base, stock_antes, stock_antes + cant_base,
+            rusqlite::params![prod_id, cant_base, mov.anterior, mov.nuevo,
                 format!("Anulacion venta {} - {}", numero, motivo.trim()), usuario_nombre, venta_id, est_id]
         ).map_err(|e| format!(
             "Error registrando movimiento de auditoría para producto {}: {}. La anulación NO se aplicó.",
@@ -3674,28 +3594,27 @@ pub fn anular_venta(
                 |r| r.get::<_, i64>(0).map(|v| v > 0),
             ).unwrap_or(false);
             if omite { continue; }
-            let stock_antes: f64 = conn.query_row(
-                "SELECT stock_actual FROM productos WHERE id=?1",
-                rusqlite::params![hijo_id], |r| r.get(0),
-            ).unwrap_or(0.0);
-            conn.execute(
-                "UPDATE productos SET stock_actual = stock_actual + ?1 WHERE id = ?2",
-                rusqlite::params![cant, hijo_id],
-            ).map_err(|e| format!("Error reintegrando componente {}: {}", hijo_id, e))?;
-            if let Some(eid) = est_id {
-                let _ = conn.execute(
-                    "UPDATE stock_establecimiento SET stock_actual = stock_actual + ?1
-                     WHERE producto_id = ?2 AND establecimiento_id = ?3",
-                    rusqlite::params![cant, hijo_id, eid],
-                );
-            }
+            let mov = crate::commands::stock::mover(&conn, *hijo_id, est_id, *cant)
+                .map_err(|e| format!("Error reintegrando componente {}: {}", hijo_id, e))?;
             let _ = conn.execute(
                 "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, motivo, usuario, referencia_id, establecimiento_id)
                  VALUES (?1, 'ANULACION_COMBO', ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-                rusqlite::params![hijo_id, cant, stock_antes, stock_antes + cant,
+                rusqlite::params![hijo_id, cant, mov.anterior, mov.nuevo,
                     format!("Anulacion venta {} (combo) - {}", numero, motivo.trim()), usuario_nombre, venta_id, est_id],
             );
         }
+
+        let ctx = crate::commands::recetas::ContextoKardex {
+            venta_id,
+            numero: &numero,
+            usuario: &usuario_nombre,
+            est_id,
+        };
+        crate::commands::recetas::reintegrar_receta(&conn, &ctx, motivo.trim())?;
+        crate::restaurante::modificadores::reintegrar_insumos(
+            &conn, venta_id, None, "ANULACION_MODIFICADOR",
+            &format!("Anulacion venta {} (modificadores) - {}", numero, motivo.trim()), &usuario_nombre,
+        )?;
     }
 
     // v2.3.49: calcular EFECTIVO real de la venta ANTES de borrar pagos_venta.
@@ -3761,6 +3680,21 @@ pub fn anular_venta(
         e
     ))?;
 
+    crate::webhooks::emitir(
+        &conn,
+        "venta.anulada",
+        &format!("venta:{}", venta_id),
+        serde_json::json!({
+            "venta_id": venta_id,
+            "numero": numero,
+            "total": total,
+            "estado_sri": estado_sri,
+            "motivo": motivo.trim(),
+            "usuario": usuario_nombre,
+            "stock_reintegrado": !omitir_reintegro_stock,
+        }),
+    );
+
     Ok(())
 }
 
@@ -3909,15 +3843,16 @@ pub fn reparar_anulacion_venta(
 
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
-    let (numero, anulada): (String, i32) = conn.query_row(
-        "SELECT numero, anulada FROM ventas WHERE id = ?1",
+    let (numero, anulada, venta_est): (String, i32, String) = conn.query_row(
+        "SELECT numero, anulada, COALESCE(establecimiento, '001') FROM ventas WHERE id = ?1",
         rusqlite::params![venta_id],
-        |r| Ok((r.get(0)?, r.get(1)?)),
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
     ).map_err(|_| "Venta no encontrada".to_string())?;
 
     if anulada == 0 {
         return Err("La venta NO está anulada. No hay nada que reparar.".into());
     }
+    let est_id = crate::commands::stock::establecimiento_por_codigo(&conn, &venta_est)?;
 
     // Cargar items
     let mut stmt = conn.prepare(
@@ -3960,35 +3895,23 @@ pub fn reparar_anulacion_venta(
         }
 
         let cant_base = cant * factor;
-        let stock_antes: f64 = conn.query_row(
-            "SELECT stock_actual FROM productos WHERE id = ?1",
-            rusqlite::params![pid], |r| r.get(0),
-        ).unwrap_or(0.0);
+        let mov = crate::commands::stock::mover(&conn, pid, est_id, cant_base)
+            .map_err(|e| format!("Error reintegrando stock producto {}: {}", pid, e))?;
 
-        conn.execute(
-            "UPDATE productos SET stock_actual = stock_actual + ?1 WHERE id = ?2",
-            rusqlite::params![cant_base, pid],
-        ).map_err(|e| format!("Error reintegrando stock producto {}: {}", pid, e))?;
-
-        // Reversar lote si aplica
-        if let Some(lid) = lote_id {
-            let _ = conn.execute(
-                "UPDATE lotes_caducidad SET cantidad = cantidad + ?1 WHERE id = ?2",
-                rusqlite::params![cant_base, lid],
-            );
-        }
+        // Reversar lotes si aplica
+        crate::commands::caducidad::reintegrar_lotes(&conn, venta_id, pid, cant_base, lote_id)?;
+        let _ = crate::commands::costeo::reintegrar_capas(&conn, venta_id, pid, est_id, cant_base);
 
         // Registrar movimiento de reparación (importante: tipo ANULACION_VENTA
         // para que un re-verificar marque ya_correcto). Motivo distintivo.
-        let stock_despues = stock_antes + cant_base;
         let _ = conn.execute(
             "INSERT INTO movimientos_inventario
-                (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, motivo, usuario, referencia_id)
-             VALUES (?1, 'ANULACION_VENTA', ?2, ?3, ?4, ?5, ?6, ?7)",
+                (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, motivo, usuario, referencia_id, establecimiento_id)
+             VALUES (?1, 'ANULACION_VENTA', ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
             rusqlite::params![
-                pid, cant_base, stock_antes, stock_despues,
+                pid, cant_base, mov.anterior, mov.nuevo,
                 format!("REPARACION manual anulacion {}", numero),
-                usuario_nombre, venta_id
+                usuario_nombre, venta_id, est_id
             ],
         );
 
@@ -3996,8 +3919,8 @@ pub fn reparar_anulacion_venta(
             producto_id: pid,
             producto_nombre: nombre,
             cantidad_sumada: cant_base,
-            stock_antes,
-            stock_despues,
+            stock_antes: mov.anterior,
+            stock_despues: mov.nuevo,
         });
     }
 
@@ -4116,3 +4039,24 @@ pub fn obtener_nota_credito(
     }))
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stock_requerido_de_plato_con_receta_va_a_sus_insumos() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::schema::create_tables(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO productos (id, codigo, nombre) VALUES (9001, 'T-POLLO', 'Pollo (kg)'), (9002, 'T-SECO', 'Seco de pollo');
+             INSERT INTO producto_recetas (producto_id, insumo_id, cantidad, factor, merma_pct) VALUES (9002, 9001, 250, 0.001, 20);",
+        )
+        .unwrap();
+        let items = vec![VentaDetalle { producto_id: Some(9002), cantidad: 2.0, ..Default::default() }];
+        let requerido = stock_requerido(&conn, &items).unwrap();
+        // El plato no exige stock propio; el pollo: 0.25 kg * 1.2 de merma * 2 platos
+        assert!(!requerido.contains_key(&9002));
+        assert!((requerido[&9001] - 0.6).abs() < 1e-9);
+    }
+}
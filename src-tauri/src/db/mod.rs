@@ -17,6 +17,34 @@ pub struct SesionState {
     pub sesion: Arc<Mutex<Option<SesionActiva>>>,
 }
 
+impl SesionState {
+    /// Sesión activa, exigiendo `permiso` si no es ADMIN. `None` = sin sesión
+    /// (arranque, comandos internos), que pasa como ADMIN.
+    pub fn con_permiso(&self, permiso: &str) -> Result<Option<SesionActiva>, String> {
+        let g = self.sesion.lock().map_err(|e| e.to_string())?;
+        match g.as_ref() {
+            Some(s) => {
+                if s.rol != "ADMIN" {
+                    let tiene = serde_json::from_str::<serde_json::Value>(&s.permisos)
+                        .ok()
+                        .and_then(|v| v.get(permiso)?.as_bool())
+                        .unwrap_or(false);
+                    if !tiene {
+                        return Err(format!("No tiene permiso: {}", permiso));
+                    }
+                }
+                Ok(Some(s.clone()))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Nombre del usuario en sesión, exigiendo `permiso` si no es ADMIN.
+pub fn usuario_con_permiso(sesion: &SesionState, permiso: &str) -> Result<String, String> {
+    Ok(sesion.con_permiso(permiso)?.map(|s| s.nombre).unwrap_or_else(|| "ADMIN".to_string()))
+}
+
 impl Database {
     pub fn new() -> Result<Self, rusqlite::Error> {
         let db_path = Self::get_db_path();
@@ -693,6 +693,9 @@ pub fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
     let _ = conn.execute("ALTER TABLE ventas ADD COLUMN despacho_fecha_salida TEXT", []);
     let _ = conn.execute("ALTER TABLE ventas ADD COLUMN despacho_fecha_entrega TEXT", []);
     let _ = conn.execute("ALTER TABLE ventas ADD COLUMN despacho_observacion TEXT", []);
+    // Propina (servicio 10% o voluntaria). NO forma parte de `total`: va aparte
+    // en el XML (<propina>, suma a importeTotal) y en caja no infla las ventas.
+    let _ = conn.execute("ALTER TABLE ventas ADD COLUMN propina REAL NOT NULL DEFAULT 0", []);
 
     // Tabla de choferes/transportistas (autocompletar)
     conn.execute_batch(
@@ -1182,6 +1185,471 @@ pub fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
         CREATE INDEX IF NOT EXISTS idx_vd_combo_detalle ON venta_detalle_combo(venta_detalle_id);
     ");
 
+    // Recetas (BOM): un plato consume insumos en unidades base del insumo
+    // (ej: "Seco de pollo" = 0.180 KG de pollo + 0.150 KG de arroz).
+    // cantidad se captura en la presentacion unidad_id (unidades_producto del
+    // insumo, ej: gramos con factor 0.001); cantidad * factor = unidades base.
+    // merma_pct infla el consumo teorico (limpieza, coccion).
+    // venta_detalle_receta guarda lo descontado por linea para anular y para
+    // el reporte de varianza contra conteo fisico.
+    let _ = conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS producto_recetas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            producto_id INTEGER NOT NULL,         -- el plato
+            insumo_id INTEGER NOT NULL,           -- el ingrediente (producto no necesariamente vendible)
+            cantidad REAL NOT NULL,
+            unidad_id INTEGER,
+            factor REAL NOT NULL DEFAULT 1,
+            merma_pct REAL NOT NULL DEFAULT 0,
+            orden INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (producto_id) REFERENCES productos(id) ON DELETE CASCADE,
+            FOREIGN KEY (insumo_id) REFERENCES productos(id),
+            UNIQUE(producto_id, insumo_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_recetas_producto ON producto_recetas(producto_id);
+        CREATE INDEX IF NOT EXISTS idx_recetas_insumo ON producto_recetas(insumo_id);
+
+        CREATE TABLE IF NOT EXISTS venta_detalle_receta (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            venta_detalle_id INTEGER NOT NULL,
+            insumo_id INTEGER NOT NULL,
+            cantidad REAL NOT NULL,               -- unidades base descontadas
+            costo_unitario REAL NOT NULL DEFAULT 0,
+            descontado INTEGER NOT NULL DEFAULT 1, -- 0 si el insumo no controla stock
+            FOREIGN KEY (venta_detalle_id) REFERENCES venta_detalles(id) ON DELETE CASCADE,
+            FOREIGN KEY (insumo_id) REFERENCES productos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_vd_receta_detalle ON venta_detalle_receta(venta_detalle_id);
+        CREATE INDEX IF NOT EXISTS idx_vd_receta_insumo ON venta_detalle_receta(insumo_id);
+    ");
+
+    // Insumos de modificadores descontados al cobrar un pedido de restaurante
+    // (restaurante/modificadores.rs), para reintegrarlos al anular o en la NC.
+    let _ = conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS venta_insumos_modificador (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            venta_id INTEGER NOT NULL,
+            producto_id INTEGER,                  -- el plato que llevaba el modificador
+            insumo_id INTEGER NOT NULL,
+            cantidad REAL NOT NULL,               -- unidades base descontadas
+            reintegrado REAL NOT NULL DEFAULT 0,
+            costo_unitario REAL NOT NULL DEFAULT 0,
+            establecimiento_id INTEGER NOT NULL,
+            FOREIGN KEY (venta_id) REFERENCES ventas(id),
+            FOREIGN KEY (insumo_id) REFERENCES productos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_venta_insumos_mod ON venta_insumos_modificador(venta_id);
+    ");
+
+    // Conteos fisicos de inventario (ver commands/conteos.rs).
+    // conteo_items congela el stock esperado y el costo al abrir la sesion;
+    // conteo_capturas guarda cada lectura (varios dispositivos suman). La
+    // cantidad contada de un item es la suma de capturas de su ronda vigente:
+    // pedir reconteo sube la ronda y obliga a contarlo de nuevo. mov_inicial y
+    // mov_id marcan el ultimo id del kardex al abrir y en cada lectura, para
+    // sumar al esperado lo que se movio mientras se contaba.
+    let _ = conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS conteos_inventario (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL,
+            categoria_id INTEGER,                 -- NULL = todo el inventario
+            establecimiento_id INTEGER,           -- NULL = stock global (productos.stock_actual)
+            tolerancia_pct REAL NOT NULL DEFAULT 0,
+            tolerancia_unidades REAL NOT NULL DEFAULT 0,
+            estado TEXT NOT NULL DEFAULT 'ABIERTO', -- ABIERTO | APROBADO | CANCELADO
+            observacion TEXT,
+            usuario_creacion TEXT,
+            usuario_aprobacion TEXT,
+            fecha_creacion TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            fecha_cierre TEXT,
+            mov_inicial INTEGER                   -- MAX(movimientos_inventario.id) al congelar
+        );
+        CREATE INDEX IF NOT EXISTS idx_conteos_estado ON conteos_inventario(estado);
+
+        CREATE TABLE IF NOT EXISTS conteo_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            conteo_id INTEGER NOT NULL,
+            producto_id INTEGER NOT NULL,
+            stock_esperado REAL NOT NULL,
+            costo_promedio REAL NOT NULL DEFAULT 0,
+            ronda INTEGER NOT NULL DEFAULT 1,
+            requiere_reconteo INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (conteo_id) REFERENCES conteos_inventario(id) ON DELETE CASCADE,
+            FOREIGN KEY (producto_id) REFERENCES productos(id),
+            UNIQUE(conteo_id, producto_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS conteo_capturas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            conteo_id INTEGER NOT NULL,
+            producto_id INTEGER NOT NULL,
+            ronda INTEGER NOT NULL DEFAULT 1,
+            cantidad REAL NOT NULL,
+            dispositivo TEXT,
+            usuario TEXT,
+            fecha TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            mov_id INTEGER,                       -- MAX(movimientos_inventario.id) al leer
+            FOREIGN KEY (conteo_id) REFERENCES conteos_inventario(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_conteo_capturas_item ON conteo_capturas(conteo_id, producto_id, ronda);
+    ");
+    let _ = conn.execute("ALTER TABLE conteos_inventario ADD COLUMN mov_inicial INTEGER", []);
+    let _ = conn.execute("ALTER TABLE conteo_capturas ADD COLUMN mov_id INTEGER", []);
+
+    // Documentos de transferencia multi-linea (ver commands/transferencias.rs).
+    // transferencias_stock (una fila por producto) queda para el historial.
+    // Lotes y series pasan a saber en que establecimiento estan: NULL = sin
+    // asignar (datos anteriores), se tratan como disponibles en cualquiera.
+    let _ = conn.execute("ALTER TABLE lotes_caducidad ADD COLUMN establecimiento_id INTEGER", []);
+    let _ = conn.execute("ALTER TABLE numeros_serie ADD COLUMN establecimiento_id INTEGER", []);
+    let _ = conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS transferencias_documento (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            numero TEXT,
+            origen_establecimiento_id INTEGER NOT NULL,
+            destino_establecimiento_id INTEGER NOT NULL,
+            estado TEXT NOT NULL DEFAULT 'EN_TRANSITO', -- EN_TRANSITO | RECIBIDA_PARCIAL | RECIBIDA | RECIBIDA_CON_DIFERENCIAS
+            observacion TEXT,
+            usuario TEXT,
+            usuario_recepcion TEXT,
+            fecha_creacion TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+            fecha_recepcion TEXT,
+            -- Transporte (guia de remision)
+            transportista TEXT,
+            ruc_transportista TEXT,
+            tipo_id_transportista TEXT,
+            placa TEXT,
+            ruta TEXT,
+            fecha_inicio_transporte TEXT,
+            fecha_fin_transporte TEXT,
+            guia_estado_sri TEXT NOT NULL DEFAULT 'NO_EMITIDA', -- NO_EMITIDA | PENDIENTE | AUTORIZADA | RECHAZADA
+            guia_numero TEXT,
+            guia_clave_acceso TEXT,
+            guia_autorizacion TEXT,
+            guia_fecha_autorizacion TEXT,
+            guia_xml_firmado TEXT,
+            FOREIGN KEY (origen_establecimiento_id) REFERENCES establecimientos(id),
+            FOREIGN KEY (destino_establecimiento_id) REFERENCES establecimientos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_transf_doc_estado ON transferencias_documento(estado);
+
+        CREATE TABLE IF NOT EXISTS transferencia_lineas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            documento_id INTEGER NOT NULL,
+            producto_id INTEGER NOT NULL,
+            lote_id INTEGER,                      -- lote de origen (productos con caducidad)
+            lote TEXT,
+            fecha_caducidad TEXT,
+            fecha_elaboracion TEXT,
+            cantidad_enviada REAL NOT NULL,
+            cantidad_recibida REAL NOT NULL DEFAULT 0,
+            cantidad_faltante REAL NOT NULL DEFAULT 0, -- se fija al cerrar la recepcion
+            costo_unitario REAL NOT NULL DEFAULT 0,
+            observacion_recepcion TEXT,
+            FOREIGN KEY (documento_id) REFERENCES transferencias_documento(id) ON DELETE CASCADE,
+            FOREIGN KEY (producto_id) REFERENCES productos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_transf_lineas_doc ON transferencia_lineas(documento_id);
+
+        CREATE TABLE IF NOT EXISTS transferencia_series (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            linea_id INTEGER NOT NULL,
+            numero_serie_id INTEGER NOT NULL,
+            serial TEXT NOT NULL,
+            recibido INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (linea_id) REFERENCES transferencia_lineas(id) ON DELETE CASCADE,
+            UNIQUE(linea_id, numero_serie_id)
+        );
+
+        -- Cada entrega parcial registrada en destino
+        CREATE TABLE IF NOT EXISTS transferencia_recepciones (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            documento_id INTEGER NOT NULL,
+            linea_id INTEGER NOT NULL,
+            cantidad REAL NOT NULL,
+            observacion TEXT,
+            usuario TEXT,
+            fecha TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+            FOREIGN KEY (documento_id) REFERENCES transferencias_documento(id) ON DELETE CASCADE
+        );
+    ");
+
+    // Establecimiento donde ingreso la compra (devoluciones y anulaciones salen de ahi)
+    let _ = conn.execute("ALTER TABLE compras ADD COLUMN establecimiento_id INTEGER", []);
+
+    // Ordenes de compra a proveedores: BORRADOR -> ENVIADA -> RECIBIDA_PARCIAL -> CERRADA (o ANULADA)
+    let _ = conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS ordenes_compra (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            numero TEXT NOT NULL UNIQUE,          -- OC-000000001
+            proveedor_id INTEGER NOT NULL,
+            establecimiento_id INTEGER NOT NULL,  -- donde se recibe la mercaderia
+            estado TEXT NOT NULL DEFAULT 'BORRADOR', -- BORRADOR | ENVIADA | RECIBIDA_PARCIAL | CERRADA | ANULADA
+            fecha_entrega_esperada TEXT,
+            subtotal REAL NOT NULL DEFAULT 0,
+            iva REAL NOT NULL DEFAULT 0,
+            total REAL NOT NULL DEFAULT 0,
+            observacion TEXT,
+            usuario_creacion TEXT,
+            usuario_aprobacion TEXT,
+            fecha_creacion TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+            fecha_envio TEXT,
+            email_enviado_a TEXT,
+            -- Confirmacion del proveedor (opcional)
+            confirmada_proveedor INTEGER NOT NULL DEFAULT 0,
+            fecha_entrega_prometida TEXT,
+            fecha_cierre TEXT,
+            motivo_cierre TEXT,
+            FOREIGN KEY (proveedor_id) REFERENCES proveedores(id),
+            FOREIGN KEY (establecimiento_id) REFERENCES establecimientos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_oc_estado ON ordenes_compra(estado);
+        CREATE INDEX IF NOT EXISTS idx_oc_proveedor ON ordenes_compra(proveedor_id);
+
+        CREATE TABLE IF NOT EXISTS orden_compra_lineas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            orden_id INTEGER NOT NULL,
+            producto_id INTEGER NOT NULL,
+            descripcion TEXT NOT NULL,
+            presentacion_id INTEGER,              -- snapshot de la presentacion pedida (caja x12, etc.)
+            presentacion_nombre TEXT,
+            presentacion_factor REAL NOT NULL DEFAULT 1,
+            cantidad REAL NOT NULL,               -- siempre en unidad base
+            precio_unitario REAL NOT NULL,        -- por unidad base, sin IVA
+            iva_porcentaje REAL NOT NULL DEFAULT 0,
+            subtotal REAL NOT NULL DEFAULT 0,
+            cantidad_recibida REAL NOT NULL DEFAULT 0,
+            cantidad_facturada REAL NOT NULL DEFAULT 0,
+            FOREIGN KEY (orden_id) REFERENCES ordenes_compra(id) ON DELETE CASCADE,
+            FOREIGN KEY (producto_id) REFERENCES productos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_oc_lineas_orden ON orden_compra_lineas(orden_id);
+
+        -- Cada recepcion (entrega) contra la orden
+        CREATE TABLE IF NOT EXISTS orden_compra_recepciones (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            orden_id INTEGER NOT NULL,
+            observacion TEXT,
+            usuario TEXT,
+            fecha TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+            FOREIGN KEY (orden_id) REFERENCES ordenes_compra(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS orden_compra_recepcion_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            recepcion_id INTEGER NOT NULL,
+            linea_id INTEGER NOT NULL,
+            cantidad REAL NOT NULL,               -- unidad base
+            lote_id INTEGER,
+            lote TEXT,
+            fecha_caducidad TEXT,
+            FOREIGN KEY (recepcion_id) REFERENCES orden_compra_recepciones(id) ON DELETE CASCADE,
+            FOREIGN KEY (linea_id) REFERENCES orden_compra_lineas(id)
+        );
+    ");
+    // Factura del proveedor conciliada contra una orden de compra (3-way match)
+    let _ = conn.execute("ALTER TABLE compras ADD COLUMN orden_compra_id INTEGER", []);
+    let _ = conn.execute("ALTER TABLE compras ADD COLUMN conciliacion_estado TEXT", []); // OK | CON_DIFERENCIAS
+    // Detalle de factura cuyo ingreso fisico ya se hizo al recibir la orden (no mueve stock)
+    let _ = conn.execute("ALTER TABLE compra_detalles ADD COLUMN recibido_en_oc INTEGER NOT NULL DEFAULT 0", []);
+    let _ = conn.execute("INSERT OR IGNORE INTO config (key, value) VALUES ('oc_tolerancia_precio_pct', '1')", []);
+
+    // Sugerencias de reposicion: lead time del proveedor, proveedor preferido
+    // del producto (si no hay, el de la ultima compra) y parametros del calculo
+    let _ = conn.execute("ALTER TABLE proveedores ADD COLUMN dias_entrega INTEGER NOT NULL DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE productos ADD COLUMN proveedor_preferido_id INTEGER", []);
+    let _ = conn.execute("INSERT OR IGNORE INTO config (key, value) VALUES ('reposicion_dias_ventana', '60')", []);
+    let _ = conn.execute("INSERT OR IGNORE INTO config (key, value) VALUES ('reposicion_dias_cobertura', '15')", []);
+    let _ = conn.execute("INSERT OR IGNORE INTO config (key, value) VALUES ('reposicion_dias_seguridad', '3')", []);
+    let _ = conn.execute("INSERT OR IGNORE INTO config (key, value) VALUES ('reposicion_dias_entrega_default', '7')", []);
+
+    // FEFO: reparto de cada linea de venta entre lotes (para revertir exacto en
+    // NC/anulacion) y reglas de descuento por caducidad proxima
+    let _ = conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS venta_detalle_lotes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            venta_id INTEGER NOT NULL,
+            venta_detalle_id INTEGER NOT NULL,
+            producto_id INTEGER NOT NULL,
+            lote_id INTEGER NOT NULL,
+            cantidad REAL NOT NULL,               -- unidad base
+            cantidad_devuelta REAL NOT NULL DEFAULT 0,
+            FOREIGN KEY (venta_id) REFERENCES ventas(id),
+            FOREIGN KEY (lote_id) REFERENCES lotes_caducidad(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_vdl_venta ON venta_detalle_lotes(venta_id, producto_id);
+
+        CREATE TABLE IF NOT EXISTS reglas_descuento_caducidad (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL,
+            dias_antes INTEGER NOT NULL,          -- aplica si el lote vence en <= N dias
+            descuento_pct REAL NOT NULL,
+            categoria_id INTEGER,                 -- NULL = todas
+            producto_id INTEGER,                  -- NULL = segun categoria
+            activo INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL DEFAULT (datetime('now','localtime'))
+        );
+    ");
+    let _ = conn.execute("INSERT OR IGNORE INTO config (key, value) VALUES ('descuento_caducidad_auto', '0')", []);
+
+    // Costeo: capas por linea de compra (FIFO), consumo por linea de venta,
+    // costo de la linea con cada metodo y costo especifico por serie
+    let _ = conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS capas_costo (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            producto_id INTEGER NOT NULL,
+            establecimiento_id INTEGER NOT NULL,
+            origen TEXT NOT NULL,                 -- COMPRA | ORDEN_COMPRA | DEVOLUCION | RMA
+            referencia_id INTEGER,                -- compra_id / orden_id / venta_id
+            fecha TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+            cantidad_inicial REAL NOT NULL,       -- unidad base
+            cantidad_restante REAL NOT NULL,
+            costo_unitario REAL NOT NULL,
+            FOREIGN KEY (producto_id) REFERENCES productos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_capas_costo_prod ON capas_costo(producto_id, establecimiento_id, cantidad_restante);
+
+        CREATE TABLE IF NOT EXISTS venta_detalle_costos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            venta_id INTEGER NOT NULL,
+            venta_detalle_id INTEGER NOT NULL,
+            producto_id INTEGER NOT NULL,
+            capa_id INTEGER,                      -- NULL = stock sin capa, a PMP
+            cantidad REAL NOT NULL,
+            costo_unitario REAL NOT NULL,
+            cantidad_devuelta REAL NOT NULL DEFAULT 0,
+            FOREIGN KEY (venta_id) REFERENCES ventas(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_vdc_venta ON venta_detalle_costos(venta_id, producto_id);
+        CREATE INDEX IF NOT EXISTS idx_vdc_detalle ON venta_detalle_costos(venta_detalle_id);
+    ");
+    let _ = conn.execute("ALTER TABLE venta_detalles ADD COLUMN costo_fifo REAL", []);
+    let _ = conn.execute("ALTER TABLE venta_detalles ADD COLUMN costo_pmp REAL", []);
+    let _ = conn.execute("ALTER TABLE numeros_serie ADD COLUMN costo REAL", []);
+    // PROMEDIO (promedio ponderado movil) | FIFO
+    let _ = conn.execute("INSERT OR IGNORE INTO config (key, value) VALUES ('metodo_costeo', 'PROMEDIO')", []);
+
+    // Listas de costo por proveedor y costo en destino de compras
+    // (commands/costos_proveedor.rs)
+    let _ = conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS precios_proveedor (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            proveedor_id INTEGER NOT NULL,
+            producto_id INTEGER NOT NULL,
+            codigo_proveedor TEXT,
+            costo REAL NOT NULL,
+            cantidad_minima REAL NOT NULL DEFAULT 1,   -- costo por volumen
+            vigente_desde TEXT NOT NULL DEFAULT (date('now','localtime')),
+            vigente_hasta TEXT,                        -- NULL = sin fin
+            observacion TEXT,
+            usuario TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+            FOREIGN KEY (proveedor_id) REFERENCES proveedores(id),
+            FOREIGN KEY (producto_id) REFERENCES productos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_precios_proveedor_prod ON precios_proveedor(producto_id, proveedor_id);
+
+        CREATE TABLE IF NOT EXISTS compra_costos_adicionales (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            compra_id INTEGER NOT NULL,
+            tipo TEXT NOT NULL,                   -- FLETE | ADUANA | MANIPULEO | SEGURO | OTRO
+            descripcion TEXT,
+            monto REAL NOT NULL,
+            metodo TEXT NOT NULL,                 -- VALOR | PESO | CANTIDAD
+            usuario TEXT,
+            fecha TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+            anulado INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (compra_id) REFERENCES compras(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_compra_costos_adic ON compra_costos_adicionales(compra_id);
+
+        CREATE TABLE IF NOT EXISTS compra_costo_adicional_lineas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            costo_adicional_id INTEGER NOT NULL,
+            compra_detalle_id INTEGER NOT NULL,
+            producto_id INTEGER NOT NULL,
+            base REAL NOT NULL,                   -- valor, peso o cantidad de la linea
+            monto REAL NOT NULL,
+            monto_inventario REAL NOT NULL DEFAULT 0, -- parte que entro a costo_promedio
+            delta_promedio REAL NOT NULL DEFAULT 0,   -- lo que subio costo_promedio por unidad
+            FOREIGN KEY (costo_adicional_id) REFERENCES compra_costos_adicionales(id) ON DELETE CASCADE
+        );
+    ");
+    let _ = conn.execute("ALTER TABLE compra_costo_adicional_lineas ADD COLUMN delta_promedio REAL NOT NULL DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE compra_detalles ADD COLUMN costo_adicional REAL NOT NULL DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE productos ADD COLUMN peso_kg REAL NOT NULL DEFAULT 0", []);
+    // % sobre el precio de venta; NULL = config margen_objetivo
+    let _ = conn.execute("ALTER TABLE productos ADD COLUMN margen_objetivo REAL", []);
+    let _ = conn.execute("INSERT OR IGNORE INTO config (key, value) VALUES ('margen_objetivo', '30')", []);
+
+    // Ubicaciones de bodega y listas de picking (commands/bodega.rs)
+    let _ = conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS ubicaciones_bodega (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            establecimiento_id INTEGER NOT NULL,
+            codigo TEXT NOT NULL,                 -- A-03-2
+            pasillo TEXT NOT NULL,
+            estante TEXT,
+            casillero TEXT,
+            orden_ruta INTEGER NOT NULL DEFAULT 0, -- posicion en el recorrido
+            activo INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+            UNIQUE(establecimiento_id, codigo),
+            FOREIGN KEY (establecimiento_id) REFERENCES establecimientos(id)
+        );
+
+        -- Reparto del stock del establecimiento entre ubicaciones
+        CREATE TABLE IF NOT EXISTS stock_ubicacion (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            producto_id INTEGER NOT NULL,
+            ubicacion_id INTEGER NOT NULL,
+            cantidad REAL NOT NULL DEFAULT 0,
+            es_principal INTEGER NOT NULL DEFAULT 0,
+            UNIQUE(producto_id, ubicacion_id),
+            FOREIGN KEY (producto_id) REFERENCES productos(id),
+            FOREIGN KEY (ubicacion_id) REFERENCES ubicaciones_bodega(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_stock_ubicacion_ubic ON stock_ubicacion(ubicacion_id);
+
+        CREATE TABLE IF NOT EXISTS listas_picking (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            numero TEXT NOT NULL UNIQUE,          -- PICK-000001
+            establecimiento_id INTEGER NOT NULL,
+            estado TEXT NOT NULL DEFAULT 'PENDIENTE', -- PENDIENTE | EN_PROCESO | COMPLETADA | ANULADA
+            usuario TEXT,
+            observacion TEXT,
+            fecha_creacion TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+            fecha_cierre TEXT,
+            FOREIGN KEY (establecimiento_id) REFERENCES establecimientos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_listas_picking_estado ON listas_picking(estado);
+
+        CREATE TABLE IF NOT EXISTS lista_picking_documentos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            lista_id INTEGER NOT NULL,
+            venta_id INTEGER NOT NULL,
+            FOREIGN KEY (lista_id) REFERENCES listas_picking(id) ON DELETE CASCADE,
+            FOREIGN KEY (venta_id) REFERENCES ventas(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_picking_docs_venta ON lista_picking_documentos(venta_id);
+
+        CREATE TABLE IF NOT EXISTS lista_picking_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            lista_id INTEGER NOT NULL,
+            orden INTEGER NOT NULL,
+            producto_id INTEGER NOT NULL,
+            ubicacion_id INTEGER,                 -- NULL = sin ubicar
+            cantidad REAL NOT NULL,               -- unidad base
+            cantidad_recogida REAL NOT NULL DEFAULT 0,
+            estado TEXT NOT NULL DEFAULT 'PENDIENTE', -- PENDIENTE | RECOGIDO | FALTANTE
+            usuario TEXT,
+            fecha_confirmacion TEXT,
+            FOREIGN KEY (lista_id) REFERENCES listas_picking(id) ON DELETE CASCADE,
+            FOREIGN KEY (producto_id) REFERENCES productos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_picking_items_lista ON lista_picking_items(lista_id);
+    ");
+
     // Módulo Servicio Técnico
     let _ = conn.execute("INSERT OR IGNORE INTO config (key, value) VALUES ('modulo_servicio_tecnico', '0')", []);
     // Tipo de taller: MIXTO (default, permite escoger por orden), GENERAL, TECNOLOGIA, AUTOMOTRIZ, ELECTRODOMESTICO
@@ -1499,6 +1967,68 @@ pub fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
     let _ = conn.execute("ALTER TABLE ordenes_servicio ADD COLUMN equipo_kilometraje_intervalo INTEGER", []);
     let _ = conn.execute("ALTER TABLE ordenes_servicio ADD COLUMN equipo_kilometraje_salida INTEGER", []);
 
+    // Garantias de series y RMA a proveedor (commands/garantias.rs)
+    let _ = conn.execute("ALTER TABLE productos ADD COLUMN garantia_meses INTEGER NOT NULL DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE productos ADD COLUMN garantia_proveedor_meses INTEGER NOT NULL DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE numeros_serie ADD COLUMN garantia_hasta TEXT", []);
+    let _ = conn.execute("ALTER TABLE numeros_serie ADD COLUMN reemplaza_a_id INTEGER", []);
+    let _ = conn.execute("ALTER TABLE numeros_serie ADD COLUMN reemplazada_por_id INTEGER", []);
+    let _ = conn.execute("ALTER TABLE ordenes_servicio ADD COLUMN numero_serie_id INTEGER", []);
+    let _ = conn.execute("ALTER TABLE ordenes_servicio ADD COLUMN en_garantia INTEGER NOT NULL DEFAULT 0", []);
+    let _ = conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS serie_eventos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            numero_serie_id INTEGER NOT NULL,
+            tipo TEXT NOT NULL,                   -- INGRESO | VENTA | DEVOLUCION | CAMBIO_GARANTIA | RMA_*
+            documento TEXT,
+            detalle TEXT NOT NULL DEFAULT '',
+            usuario TEXT,
+            fecha TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+            FOREIGN KEY (numero_serie_id) REFERENCES numeros_serie(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_serie_eventos ON serie_eventos(numero_serie_id);
+
+        CREATE TABLE IF NOT EXISTS rma_proveedor (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            numero TEXT NOT NULL UNIQUE,
+            proveedor_id INTEGER NOT NULL,
+            estado TEXT NOT NULL DEFAULT 'ENVIADO', -- ENVIADO | RESUELTO_PARCIAL | CERRADO | ANULADO
+            transportista TEXT,
+            numero_guia TEXT,
+            observacion TEXT,
+            usuario TEXT,
+            fecha_envio TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+            fecha_cierre TEXT,
+            FOREIGN KEY (proveedor_id) REFERENCES proveedores(id)
+        );
+
+        CREATE TABLE IF NOT EXISTS rma_lineas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            rma_id INTEGER NOT NULL,
+            numero_serie_id INTEGER NOT NULL,
+            motivo TEXT NOT NULL,
+            orden_servicio_id INTEGER,
+            estado_origen TEXT NOT NULL,          -- DISPONIBLE | DEFECTUOSO | VENDIDO
+            resolucion TEXT NOT NULL DEFAULT 'PENDIENTE', -- PENDIENTE | REEMPLAZADO | REPARADO | NOTA_CREDITO | RECHAZADO
+            serie_reemplazo_id INTEGER,
+            fecha_resolucion TEXT,
+            observacion TEXT,
+            FOREIGN KEY (rma_id) REFERENCES rma_proveedor(id) ON DELETE CASCADE,
+            FOREIGN KEY (numero_serie_id) REFERENCES numeros_serie(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_rma_lineas_serie ON rma_lineas(numero_serie_id);
+
+        CREATE TABLE IF NOT EXISTS rma_seguimiento (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            rma_id INTEGER NOT NULL,
+            estado TEXT NOT NULL,
+            observacion TEXT,
+            usuario TEXT,
+            fecha TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+            FOREIGN KEY (rma_id) REFERENCES rma_proveedor(id) ON DELETE CASCADE
+        );
+    ");
+
     // ─── v2.4.15: producto_id NULLABLE en venta_detalles ────────────────────
     // Bug grave: en BDs existentes, producto_id era NOT NULL. Cuando una orden
     // de servicio tecnico se cobraba con servicios manuales (mano de obra,
@@ -2159,5 +2689,51 @@ pub fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
         );
     }
 
+    // Stock por establecimiento como fuente de verdad (ver commands/stock.rs).
+    // Hasta ahora el global era la verdad y stock_establecimiento solo se
+    // mantenia con multi-almacen activo: una sola vez, la diferencia se asigna
+    // al establecimiento de la terminal para que el global no cambie. Va al
+    // final para que las reparaciones de stock anteriores ya esten aplicadas.
+    let stock_migrado: bool = conn
+        .query_row("SELECT value FROM config WHERE key = 'stock_por_establecimiento'", [], |row| row.get::<_, String>(0))
+        .map(|v| v == "1")
+        .unwrap_or(false);
+    if !stock_migrado {
+        let est_terminal: Option<i64> = conn
+            .query_row(
+                "SELECT COALESCE(
+                    (SELECT id FROM establecimientos WHERE codigo = (SELECT value FROM config WHERE key = 'terminal_establecimiento')),
+                    (SELECT id FROM establecimientos ORDER BY id LIMIT 1))",
+                [],
+                |row| row.get(0),
+            )
+            .ok()
+            .flatten();
+        if let Some(est_id) = est_terminal {
+            let ok = conn.execute(
+                "INSERT INTO stock_establecimiento (producto_id, establecimiento_id, stock_actual, stock_minimo)
+                 SELECT p.id, ?1, 0, p.stock_minimo FROM productos p
+                 WHERE COALESCE(p.es_servicio, 0) = 0
+                 ON CONFLICT(producto_id, establecimiento_id) DO NOTHING",
+                rusqlite::params![est_id],
+            ).is_ok()
+                && conn.execute(
+                    "UPDATE stock_establecimiento
+                     SET stock_actual = stock_actual + (
+                         (SELECT p.stock_actual FROM productos p WHERE p.id = stock_establecimiento.producto_id)
+                         - (SELECT COALESCE(SUM(s.stock_actual), 0) FROM stock_establecimiento s
+                            WHERE s.producto_id = stock_establecimiento.producto_id))
+                     WHERE establecimiento_id = ?1",
+                    rusqlite::params![est_id],
+                ).is_ok();
+            if ok {
+                let _ = conn.execute(
+                    "INSERT OR REPLACE INTO config (key, value) VALUES ('stock_por_establecimiento', '1')",
+                    [],
+                );
+            }
+        }
+    }
+
     Ok(())
 }
@@ -1,3 +1,4 @@
+mod api_publica;
 mod backup;
 mod branding;
 // v2.6.27: expuestos como pub para que tests/smoke_test.rs los pueda importar.
@@ -11,6 +12,7 @@ mod app_movil;
 mod server;
 mod sri;
 pub mod utils;
+mod webhooks;
 
 use tauri::Manager;
 use tauri_plugin_updater::UpdaterExt;
@@ -132,6 +134,16 @@ pub fn run() {
         }
     }
 
+    // API pública `/api/v2` (integraciones externas): tabla `api_keys`.
+    if let Err(e) = api_publica::init(&database) {
+        eprintln!("[API pública] Error al inicializar módulo: {}", e);
+    }
+
+    // Webhooks salientes: tablas `webhooks` y `webhook_entregas`.
+    if let Err(e) = webhooks::init(&database) {
+        eprintln!("[Webhooks] Error al inicializar módulo: {}", e);
+    }
+
     // v2.4.8 — Auto-migración de licencia para clientes con órdenes preexistentes.
     // Si la base de datos tiene órdenes de servicio Y la licencia local NO incluye
     // `servicio_tecnico` (porque antes de v2.4.8 era parte de la licencia base),
@@ -189,11 +201,20 @@ pub fn run() {
         )
     };
 
+    // Inicializar BD offline en modo cliente (para cache y cola).
+    // Se crea antes del server porque `/api/v1/health` reporta su cola.
+    let offline_db: Option<offline::OfflineDb> = if modo_red == "cliente" {
+        offline::OfflineDb::new().ok()
+    } else {
+        None
+    };
+
     // v2.4.4 — Iniciar servidor HTTP si:
     //   (a) modo Multi-POS server con token configurado, O
     //   (b) la licencia tiene el módulo `app_movil`
-    // El server hospeda ambas APIs (`/api/v1/invoke` para Multi-POS y
-    // `/api/v1/app/*` para la app móvil), pero `/invoke` solo se monta si
+    // El server hospeda las APIs (`/api/v1/invoke` para Multi-POS,
+    // `/api/v1/app/*` para la app móvil y `/api/v2/*` para integraciones
+    // con API key), pero `/invoke` solo se monta si
     // `servidor_token` tiene valor (sino sería un endpoint sin auth).
     let modulos_actuales: String = {
         let conn = database.conn.lock().unwrap();
@@ -206,12 +227,17 @@ pub fn run() {
     };
     let licencia_tiene_app_movil = modulos_actuales.contains("app_movil");
     let server_modo_multipos = modo_red == "servidor" && !servidor_token.is_empty();
-    let arrancar_server = server_modo_multipos || licencia_tiene_app_movil;
+    // También si hay API keys activas: las integraciones (`/api/v2`) necesitan
+    // el server aunque no haya terminales Multi-POS ni app móvil.
+    let arrancar_server = server_modo_multipos
+        || licencia_tiene_app_movil
+        || api_publica::hay_claves_activas(&database);
 
     if arrancar_server {
         server::start_server(
             database.clone(),
             sesion_state.clone(),
+            offline_db.clone(),
             servidor_puerto,
             servidor_token.clone(),
         );
@@ -239,16 +265,10 @@ pub fn run() {
         }
     }
 
-    // Inicializar BD offline en modo cliente (para cache y cola)
-    let offline_db: Option<offline::OfflineDb> = if modo_red == "cliente" {
-        offline::OfflineDb::new().ok()
-    } else {
-        None
-    };
-
     // Iniciar scheduler de backup automático (solo en modo local o servidor)
     if modo_red != "cliente" {
         backup::scheduler::start_backup_scheduler(database.clone());
+        webhooks::worker::start_webhook_worker(database.clone());
     }
 
     tauri::Builder::default()
@@ -358,6 +378,19 @@ pub fn run() {
             commands::combos::guardar_combo_estructura,
             commands::combos::stock_combo,
             commands::combos::info_combo_resumen,
+            commands::recetas::listar_receta,
+            commands::recetas::guardar_receta,
+            commands::recetas::costo_receta,
+            commands::recetas::reporte_food_cost,
+            commands::recetas::reporte_varianza_insumos,
+            commands::conteos::crear_conteo_inventario,
+            commands::conteos::listar_conteos_inventario,
+            commands::conteos::listar_items_conteo,
+            commands::conteos::registrar_captura_conteo,
+            commands::conteos::solicitar_reconteo_conteo,
+            commands::conteos::reporte_varianza_conteo,
+            commands::conteos::aprobar_conteo_inventario,
+            commands::conteos::cancelar_conteo_inventario,
             commands::productos::eliminar_lote_caducidad,
             commands::productos::ajustar_cantidad_lote,
             commands::productos::reparar_fechas_caducidad,
@@ -455,6 +488,7 @@ pub fn run() {
             commands::impresion::listar_impresoras,
             commands::impresion::listar_impresoras_cached,
             commands::impresion::refrescar_impresoras,
+            commands::impresion::probar_impresora,
             commands::impresion::imprimir_guia_remision_pdf,
             commands::impresion::imprimir_ticket_nc,
             // Reportes
@@ -552,6 +586,7 @@ pub fn run() {
             commands::sri::emitir_nota_credito_sri,
             commands::sri::generar_ride_nc_pdf,
             commands::sri::emitir_guia_remision_sri,
+            commands::sri::emitir_guia_transferencia_sri,
             // Listas de precios
             commands::listas_precios::listar_listas_precios,
             commands::listas_precios::crear_lista_precio,
@@ -584,6 +619,10 @@ pub fn run() {
             commands::transferencias::listar_transferencias,
             commands::transferencias::stock_por_establecimiento,
             commands::transferencias::actualizar_stock_establecimiento,
+            commands::transferencias::crear_transferencia_documento,
+            commands::transferencias::recibir_transferencia_documento,
+            commands::transferencias::obtener_transferencia_documento,
+            commands::transferencias::listar_transferencias_documento,
             // Etiquetas de productos
             commands::etiquetas::generar_etiquetas_pdf,
             // Cotización PDF
@@ -607,6 +646,67 @@ pub fn run() {
             commands::compras::registrar_devolucion_compra,
             commands::compras::listar_devoluciones_compra,
             commands::compras::preview_xml_nc_compra,
+            commands::ordenes_compra::crear_orden_compra,
+            commands::ordenes_compra::actualizar_orden_compra,
+            commands::ordenes_compra::listar_ordenes_compra,
+            commands::ordenes_compra::obtener_orden_compra,
+            commands::ordenes_compra::aprobar_orden_compra,
+            commands::ordenes_compra::confirmar_orden_compra_proveedor,
+            commands::ordenes_compra::recibir_orden_compra,
+            commands::ordenes_compra::cerrar_orden_compra,
+            commands::ordenes_compra::anular_orden_compra,
+            commands::ordenes_compra::conciliacion_orden_compra,
+            commands::orden_compra_pdf::generar_orden_compra_pdf,
+            commands::orden_compra_pdf::enviar_orden_compra_email,
+            commands::reposicion::sugerencias_reposicion,
+            commands::reposicion::crear_ordenes_desde_reposicion,
+            commands::reposicion::asignar_proveedor_preferido,
+            commands::caducidad::listar_reglas_descuento_caducidad,
+            commands::caducidad::guardar_regla_descuento_caducidad,
+            commands::caducidad::eliminar_regla_descuento_caducidad,
+            commands::caducidad::listar_productos_descuento_caducidad,
+            commands::caducidad::calcular_descuento_caducidad,
+            commands::costeo::listar_capas_costo,
+            // Garantias y RMA de series
+            commands::garantias::configurar_garantia_producto,
+            commands::garantias::consultar_garantia_serie,
+            commands::garantias::abrir_orden_garantia,
+            commands::garantias::cambiar_serie_garantia,
+            commands::garantias::crear_rma_proveedor,
+            commands::garantias::listar_rmas_proveedor,
+            commands::garantias::obtener_rma_proveedor,
+            commands::garantias::registrar_seguimiento_rma,
+            commands::garantias::resolver_linea_rma,
+            commands::garantias::anular_rma_proveedor,
+            commands::garantias::historial_serie,
+            // Listas de costo de proveedores y costo en destino
+            commands::costos_proveedor::guardar_precio_proveedor,
+            commands::costos_proveedor::eliminar_precio_proveedor,
+            commands::costos_proveedor::listar_precios_proveedor,
+            commands::costos_proveedor::importar_lista_proveedor,
+            commands::costos_proveedor::comparar_costos_proveedor,
+            commands::costos_proveedor::preferir_proveedor_mas_barato,
+            commands::costos_proveedor::obtener_costos_adicionales_compra,
+            commands::costos_proveedor::previsualizar_costos_adicionales,
+            commands::costos_proveedor::aplicar_costos_adicionales,
+            commands::costos_proveedor::quitar_costo_adicional,
+            commands::costos_proveedor::aplicar_precios_sugeridos,
+            commands::bodega::guardar_ubicacion,
+            commands::bodega::listar_ubicaciones,
+            commands::bodega::desactivar_ubicacion,
+            commands::bodega::listar_stock_ubicacion,
+            commands::bodega::ubicaciones_producto,
+            commands::bodega::asignar_stock_ubicacion,
+            commands::bodega::mover_stock_ubicacion,
+            commands::bodega::generar_picking_guia,
+            commands::bodega::generar_picking_ventas,
+            commands::bodega::listar_listas_picking,
+            commands::bodega::obtener_lista_picking,
+            commands::bodega::confirmar_picking_escaneo,
+            commands::bodega::confirmar_item_picking,
+            commands::bodega::marcar_faltante_picking,
+            commands::bodega::anular_lista_picking,
+            commands::picking_pdf::generar_picking_pdf,
             // Cuentas por pagar
             commands::cuentas_pagar::alertas_pagos_vencidos,
             commands::cuentas_pagar::resumen_acreedores,
@@ -750,18 +850,65 @@ pub fn run() {
             restaurante::commands::rest_enviar_cocina,
             restaurante::commands::rest_listar_items_cocina_pendientes,
             restaurante::commands::rest_marcar_item_cocina,
+            restaurante::commands::rest_retener_item,
+            restaurante::commands::rest_disparar_curso,
+            restaurante::commands::rest_listar_tiempos_producto,
+            restaurante::commands::rest_guardar_tiempo_producto,
+            restaurante::commands::rest_reporte_tiempos_cocina,
+            restaurante::commands::rest_transferir_mesa,
+            restaurante::commands::rest_mover_items,
+            restaurante::commands::rest_reasignar_mesero,
+            restaurante::commands::rest_listar_movimientos_pedido,
+            restaurante::commands::rest_crear_entrega,
+            restaurante::commands::rest_listar_entregas,
+            restaurante::commands::rest_asignar_repartidor,
+            restaurante::commands::rest_cambiar_estado_entrega,
+            restaurante::commands::rest_liquidacion_repartidores,
+            restaurante::commands::rest_listar_reservas,
+            restaurante::commands::rest_guardar_reserva,
+            restaurante::commands::rest_confirmar_reserva,
+            restaurante::commands::rest_cancelar_reserva,
+            restaurante::commands::rest_marcar_no_show,
+            restaurante::commands::rest_registrar_deposito_reserva,
+            restaurante::commands::rest_listar_depositos_reserva,
+            restaurante::commands::rest_sentar_reserva,
+            restaurante::commands::rest_reporte_no_shows,
+            restaurante::commands::rest_listar_espera,
+            restaurante::commands::rest_agregar_espera,
+            restaurante::commands::rest_actualizar_espera,
+            restaurante::commands::rest_sentar_espera,
             restaurante::commands::rest_pedir_cuenta,
             restaurante::commands::rest_cerrar_pedido,
+            restaurante::commands::rest_sugerir_propina,
+            restaurante::commands::rest_reporte_propinas,
+            restaurante::commands::rest_reporte_salon,
+            restaurante::commands::rest_exportar_reporte_salon,
             restaurante::commands::rest_registrar_abono,
             restaurante::commands::rest_listar_abonos_holding_caja,
             restaurante::commands::rest_imprimir_pre_cuenta,
             restaurante::commands::rest_imprimir_comanda_cocina,
+            restaurante::commands::rest_listar_grupos_modificadores,
+            restaurante::commands::rest_modificadores_producto,
+            restaurante::commands::rest_guardar_grupo_modificadores,
+            restaurante::commands::rest_eliminar_grupo_modificadores,
+            restaurante::commands::rest_asignar_modificadores_producto,
+            restaurante::commands::rest_listar_estaciones,
+            restaurante::commands::rest_guardar_estacion,
+            restaurante::commands::rest_eliminar_estacion,
+            restaurante::commands::rest_listar_comandas,
+            restaurante::commands::rest_reimprimir_comanda,
+            restaurante::commands::rest_crear_pantalla_kds,
             // v2.3.68 — Unir mesas
             restaurante::commands::rest_unir_mesas,
             restaurante::commands::rest_desunir_mesa,
             restaurante::commands::rest_listar_mesas_libres_para_unir,
             // v2.3.69 — Dividir cuenta (sub-cuentas)
             restaurante::commands::rest_dividir_cuenta,
+            restaurante::commands::rest_dividir_cuenta_por_items,
+            restaurante::commands::rest_asignar_cliente_subcuenta,
+            restaurante::commands::rest_venta_subcuenta,
+            restaurante::commands::rest_validar_venta_subcuenta,
+            restaurante::commands::rest_venta_pedido,
             restaurante::commands::rest_listar_subcuentas,
             restaurante::commands::rest_cancelar_division,
             restaurante::commands::rest_marcar_subcuenta_cobrada,
@@ -772,6 +919,20 @@ pub fn run() {
             app_movil::commands::app_eliminar_dispositivo,
             // v2.4.4 — Sprint 3c: QR de emparejamiento
             app_movil::commands::app_generar_qr_emparejamiento,
+            // API pública /api/v2 — claves de integración
+            api_publica::commands::api_listar_scopes,
+            api_publica::commands::api_listar_claves,
+            api_publica::commands::api_crear_clave,
+            api_publica::commands::api_revocar_clave,
+            // Webhooks salientes
+            webhooks::commands::webhook_listar_eventos,
+            webhooks::commands::webhook_listar,
+            webhooks::commands::webhook_guardar,
+            webhooks::commands::webhook_regenerar_secreto,
+            webhooks::commands::webhook_eliminar,
+            webhooks::commands::webhook_listar_entregas,
+            webhooks::commands::webhook_reintentar_entrega,
+            webhooks::commands::webhook_probar,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+/// Ubicacion fisica dentro de la bodega de un establecimiento
+/// (pasillo / estante / casillero). orden_ruta define el recorrido de picking.
+#[derive(Debug, Serialize, Clone)]
+pub struct UbicacionBodega {
+    pub id: i64,
+    pub establecimiento_id: i64,
+    pub codigo: String,
+    pub pasillo: String,
+    pub estante: Option<String>,
+    pub casillero: Option<String>,
+    pub orden_ruta: i64,
+    pub activo: bool,
+    /// Productos con stock en la ubicacion
+    pub productos: i64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NuevaUbicacion {
+    #[serde(default)]
+    pub id: Option<i64>,
+    #[serde(default)]
+    pub establecimiento_id: Option<i64>,
+    pub pasillo: String,
+    #[serde(default)]
+    pub estante: Option<String>,
+    #[serde(default)]
+    pub casillero: Option<String>,
+    /// Por defecto pasillo-estante-casillero
+    #[serde(default)]
+    pub codigo: Option<String>,
+    #[serde(default)]
+    pub orden_ruta: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct StockUbicacion {
+    pub ubicacion_id: i64,
+    pub ubicacion_codigo: String,
+    pub producto_id: i64,
+    pub producto_nombre: String,
+    pub cantidad: f64,
+    /// Ubicacion donde se repone y de donde se recoge lo que falte
+    pub es_principal: bool,
+}
+
+/// Como se reparte el stock de un producto en las ubicaciones del
+/// establecimiento. sin_ubicar = stock del establecimiento no asignado.
+#[derive(Debug, Serialize, Clone)]
+pub struct UbicacionesProducto {
+    pub producto_id: i64,
+    pub establecimiento_id: i64,
+    pub stock_establecimiento: f64,
+    pub ubicado: f64,
+    pub sin_ubicar: f64,
+    pub ubicaciones: Vec<StockUbicacion>,
+}
+
+/// Lista de recogida para una guia o un lote de ventas.
+/// estado: PENDIENTE -> EN_PROCESO -> COMPLETADA (o ANULADA)
+#[derive(Debug, Serialize, Clone)]
+pub struct ListaPicking {
+    pub id: i64,
+    pub numero: String,
+    pub establecimiento_id: i64,
+    pub establecimiento_nombre: Option<String>,
+    pub estado: String,
+    pub usuario: Option<String>,
+    pub fecha_creacion: String,
+    pub fecha_cierre: Option<String>,
+    pub observacion: Option<String>,
+    pub documentos: Vec<DocumentoPicking>,
+    pub items: Vec<ItemPicking>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DocumentoPicking {
+    pub venta_id: i64,
+    pub numero: String,
+    pub tipo_estado: String,
+    pub cliente_nombre: Option<String>,
+    pub direccion_destino: Option<String>,
+}
+
+/// Linea de recogida, ya ordenada por el recorrido de la bodega.
+/// estado: PENDIENTE | RECOGIDO | FALTANTE
+#[derive(Debug, Serialize, Clone)]
+pub struct ItemPicking {
+    pub id: i64,
+    pub orden: i64,
+    pub producto_id: i64,
+    pub producto_codigo: Option<String>,
+    pub codigo_barras: Option<String>,
+    pub producto_nombre: String,
+    /// None = sin ubicacion asignada
+    pub ubicacion_id: Option<i64>,
+    pub ubicacion_codigo: Option<String>,
+    pub cantidad: f64,
+    pub cantidad_recogida: f64,
+    pub estado: String,
+    pub usuario: Option<String>,
+    pub fecha_confirmacion: Option<String>,
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+/// Regla de descuento por caducidad: si el lote más próximo a vencer de un
+/// producto vence dentro de `dias_antes` días, la venta aplica
+/// `descuento_pct` a las unidades que salen de ese lote.
+/// Alcance: un producto, una categoría o (ambos None) todos.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReglaDescuentoCaducidad {
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub nombre: String,
+    pub dias_antes: i64,
+    pub descuento_pct: f64,
+    #[serde(default)]
+    pub categoria_id: Option<i64>,
+    #[serde(default)]
+    pub producto_id: Option<i64>,
+    #[serde(default = "default_true")]
+    pub activo: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Producto que hoy se vende con descuento por caducidad (para el POS y
+/// etiquetas de góndola).
+#[derive(Debug, Serialize, Clone)]
+pub struct ProductoDescuentoCaducidad {
+    pub producto_id: i64,
+    pub codigo: Option<String>,
+    pub nombre: String,
+    pub lote_id: i64,
+    pub lote: Option<String>,
+    pub fecha_caducidad: String,
+    pub dias_restantes: i64,
+    pub cantidad_lote: f64,
+    pub regla: String,
+    pub descuento_pct: f64,
+    pub precio_venta: f64,
+    pub precio_con_descuento: f64,
+}
@@ -26,6 +26,9 @@ pub struct ResumenCaja {
     pub total_cobros_efectivo: f64,
     pub total_cobros_banco: f64,
     pub total_retiros: f64,
+    /// Propinas de restaurante en efectivo (fuera de total_ventas, dentro del esperado).
+    #[serde(default)]
+    pub total_propinas: f64,
 }
 
 /// Resumen extendido para reporte de cierre de caja
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+/// Sesión de conteo físico. Al crearla se congela el stock esperado de cada
+/// producto del alcance (todo, o una categoría) en un establecimiento o en el
+/// stock global.
+/// estado: ABIERTO → APROBADO | CANCELADO
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConteoInventario {
+    pub id: i64,
+    pub nombre: String,
+    pub categoria_id: Option<i64>,
+    pub categoria_nombre: Option<String>,
+    pub establecimiento_id: Option<i64>,
+    pub establecimiento_nombre: Option<String>,
+    /// Diferencia tolerada en % del stock esperado
+    pub tolerancia_pct: f64,
+    /// Diferencia tolerada en unidades (se usa la mayor de las dos)
+    pub tolerancia_unidades: f64,
+    pub estado: String,
+    pub observacion: Option<String>,
+    pub usuario_creacion: Option<String>,
+    pub usuario_aprobacion: Option<String>,
+    pub fecha_creacion: String,
+    pub fecha_cierre: Option<String>,
+    pub total_items: i64,
+    pub items_contados: i64,
+    /// Items marcados para reconteo que aún no tienen captura en la nueva ronda
+    pub reconteos_pendientes: i64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NuevoConteo {
+    pub nombre: String,
+    #[serde(default)]
+    pub categoria_id: Option<i64>,
+    #[serde(default)]
+    pub establecimiento_id: Option<i64>,
+    #[serde(default)]
+    pub tolerancia_pct: Option<f64>,
+    #[serde(default)]
+    pub tolerancia_unidades: Option<f64>,
+    #[serde(default)]
+    pub observacion: Option<String>,
+}
+
+/// Una lectura de un contador: por `producto_id` o por código / código de
+/// barras (lector). Las capturas de varios dispositivos se suman; una
+/// cantidad negativa corrige una lectura equivocada.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CapturaConteo {
+    #[serde(default)]
+    pub producto_id: Option<i64>,
+    #[serde(default)]
+    pub codigo: Option<String>,
+    pub cantidad: f64,
+    #[serde(default)]
+    pub dispositivo: Option<String>,
+}
+
+/// Línea que ve quien cuenta: conteo ciego, sin stock esperado.
+#[derive(Debug, Serialize, Clone)]
+pub struct ConteoItemCiego {
+    pub producto_id: i64,
+    pub codigo: Option<String>,
+    pub codigo_barras: Option<String>,
+    pub nombre: String,
+    pub unidad_medida: String,
+    /// Suma de capturas de la ronda vigente (None = aún sin contar)
+    pub contado: Option<f64>,
+    pub ronda: i64,
+    pub requiere_reconteo: bool,
+}
+
+/// Línea del reporte de varianza (supervisor).
+#[derive(Debug, Serialize, Clone)]
+pub struct VarianzaConteo {
+    pub producto_id: i64,
+    pub codigo: Option<String>,
+    pub nombre: String,
+    pub categoria_nombre: Option<String>,
+    pub unidad_medida: String,
+    pub stock_esperado: f64,
+    pub contado: Option<f64>,
+    /// contado − esperado (negativo = faltante)
+    pub diferencia: f64,
+    pub costo_promedio: f64,
+    pub valor_diferencia: f64,
+    pub fuera_tolerancia: bool,
+    pub ronda: i64,
+    pub requiere_reconteo: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReporteVarianzaConteo {
+    pub conteo: ConteoInventario,
+    pub items: Vec<VarianzaConteo>,
+    pub items_con_diferencia: i64,
+    pub items_fuera_tolerancia: i64,
+    pub valor_faltante: f64,
+    pub valor_sobrante: f64,
+    pub valor_neto: f64,
+}
@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+/// Capa de costo: una entrada de mercaderia (compra, devolucion) con su costo
+/// unitario y lo que aun queda de ella para costear ventas FIFO.
+#[derive(Debug, Serialize, Clone)]
+pub struct CapaCosto {
+    pub id: i64,
+    pub producto_id: i64,
+    pub establecimiento_id: i64,
+    pub establecimiento_nombre: Option<String>,
+    pub origen: String,
+    pub referencia_id: Option<i64>,
+    pub fecha: String,
+    pub cantidad_inicial: f64,
+    pub cantidad_restante: f64,
+    pub costo_unitario: f64,
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+/// Costo de un producto en la lista de un proveedor, con vigencia.
+/// vigente_hasta NULL = sin fecha de fin.
+#[derive(Debug, Serialize, Clone)]
+pub struct PrecioProveedor {
+    pub id: i64,
+    pub proveedor_id: i64,
+    pub proveedor_nombre: Option<String>,
+    pub producto_id: i64,
+    pub producto_nombre: Option<String>,
+    pub codigo_proveedor: Option<String>,
+    pub costo: f64,
+    /// Costo por volumen: aplica desde esta cantidad (unidad base)
+    pub cantidad_minima: f64,
+    pub vigente_desde: String,
+    pub vigente_hasta: Option<String>,
+    pub vigente: bool,
+    pub observacion: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NuevoPrecioProveedor {
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub proveedor_id: i64,
+    pub producto_id: i64,
+    #[serde(default)]
+    pub codigo_proveedor: Option<String>,
+    pub costo: f64,
+    #[serde(default)]
+    pub cantidad_minima: Option<f64>,
+    /// YYYY-MM-DD; por defecto hoy
+    #[serde(default)]
+    pub vigente_desde: Option<String>,
+    #[serde(default)]
+    pub vigente_hasta: Option<String>,
+    #[serde(default)]
+    pub observacion: Option<String>,
+}
+
+/// Fila de una lista de costos importada. El producto se identifica por id,
+/// por su codigo interno o por el codigo del proveedor ya registrado.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ItemListaProveedor {
+    #[serde(default)]
+    pub producto_id: Option<i64>,
+    #[serde(default)]
+    pub codigo: Option<String>,
+    #[serde(default)]
+    pub codigo_proveedor: Option<String>,
+    pub costo: f64,
+    #[serde(default)]
+    pub cantidad_minima: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ResultadoImportacionLista {
+    pub guardados: usize,
+    /// Codigos (o filas) que no se pudieron asociar a un producto
+    pub no_encontrados: Vec<String>,
+}
+
+/// Un proveedor en la comparacion de costos de un producto.
+#[derive(Debug, Serialize, Clone)]
+pub struct ComparacionProveedor {
+    pub proveedor_id: i64,
+    pub proveedor_nombre: String,
+    pub dias_entrega: i64,
+    /// Costo vigente de su lista para la cantidad consultada
+    pub costo_lista: Option<f64>,
+    pub vigente_hasta: Option<String>,
+    pub ultimo_costo_compra: Option<f64>,
+    pub fecha_ultima_compra: Option<String>,
+    pub es_preferido: bool,
+    pub mas_barato: bool,
+}
+
+/// Gasto de la compra a distribuir en el costo de sus lineas (costo en destino).
+/// tipo: FLETE | ADUANA | MANIPULEO | SEGURO | OTRO
+/// metodo: VALOR | PESO | CANTIDAD
+#[derive(Debug, Deserialize, Clone)]
+pub struct NuevoCostoAdicional {
+    pub tipo: String,
+    #[serde(default)]
+    pub descripcion: Option<String>,
+    pub monto: f64,
+    pub metodo: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CostoAdicionalCompra {
+    pub id: i64,
+    pub tipo: String,
+    pub descripcion: Option<String>,
+    pub monto: f64,
+    pub metodo: String,
+    pub usuario: Option<String>,
+    pub fecha: String,
+}
+
+/// Linea de la compra con su costo en destino y el precio sugerido.
+#[derive(Debug, Serialize, Clone)]
+pub struct LineaCostoDestino {
+    pub compra_detalle_id: i64,
+    pub producto_id: i64,
+    pub producto_nombre: String,
+    pub cantidad: f64,
+    pub precio_unitario: f64,
+    /// Total de gastos asignados a la linea
+    pub costo_adicional: f64,
+    /// precio_unitario + costo_adicional / cantidad
+    pub costo_unitario_destino: f64,
+    pub costo_promedio: f64,
+    pub precio_venta: f64,
+    pub margen_objetivo: f64,
+    pub precio_venta_sugerido: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DistribucionCostosCompra {
+    pub compra_id: i64,
+    pub compra_numero: String,
+    pub total_adicional: f64,
+    pub costos: Vec<CostoAdicionalCompra>,
+    pub lineas: Vec<LineaCostoDestino>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PrecioVentaNuevo {
+    pub producto_id: i64,
+    pub precio_venta: f64,
+}
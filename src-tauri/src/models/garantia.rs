@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+/// Estado de garantía de una unidad serializada: la del cliente (desde la
+/// venta) y la del proveedor (desde el ingreso).
+#[derive(Debug, Serialize, Clone)]
+pub struct GarantiaSerie {
+    pub numero_serie_id: i64,
+    pub serial: String,
+    pub estado: String,
+    pub producto_id: i64,
+    pub producto_nombre: String,
+    pub cliente_id: Option<i64>,
+    pub cliente_nombre: Option<String>,
+    pub venta_id: Option<i64>,
+    pub venta_numero: Option<String>,
+    pub fecha_venta: Option<String>,
+    pub garantia_hasta: Option<String>,
+    pub en_garantia: bool,
+    pub dias_restantes: Option<i64>,
+    pub proveedor_id: Option<i64>,
+    pub proveedor_nombre: Option<String>,
+    pub garantia_proveedor_hasta: Option<String>,
+    pub en_garantia_proveedor: bool,
+    /// Series anterior/siguiente si hubo cambio por garantía o RMA
+    pub reemplaza_a: Option<String>,
+    pub reemplazada_por: Option<String>,
+    /// Órdenes de servicio abiertas o cerradas sobre esta serie (número, estado)
+    pub ordenes_servicio: Vec<(String, String)>,
+    /// RMA en curso (número), si la serie está con el proveedor
+    pub rma_abierto: Option<String>,
+}
+
+/// RMA: devolución de unidades serializadas al proveedor.
+/// estado: ENVIADO → RESUELTO_PARCIAL → CERRADO (o ANULADO)
+#[derive(Debug, Serialize, Clone)]
+pub struct RmaProveedor {
+    pub id: i64,
+    pub numero: String,
+    pub proveedor_id: i64,
+    pub proveedor_nombre: Option<String>,
+    pub estado: String,
+    pub transportista: Option<String>,
+    pub numero_guia: Option<String>,
+    pub observacion: Option<String>,
+    pub usuario: Option<String>,
+    pub fecha_envio: String,
+    pub fecha_cierre: Option<String>,
+    pub lineas: Vec<LineaRma>,
+    pub seguimiento: Vec<SeguimientoRma>,
+}
+
+/// Unidad enviada en el RMA.
+/// resolucion: PENDIENTE | REEMPLAZADO | REPARADO | NOTA_CREDITO | RECHAZADO
+#[derive(Debug, Serialize, Clone)]
+pub struct LineaRma {
+    pub id: i64,
+    pub numero_serie_id: i64,
+    pub serial: String,
+    pub producto_id: i64,
+    pub producto_nombre: String,
+    pub motivo: String,
+    pub orden_servicio_id: Option<i64>,
+    /// Estado de la serie al enviarla (DISPONIBLE, DEFECTUOSO, VENDIDO)
+    pub estado_origen: String,
+    pub resolucion: String,
+    pub serial_reemplazo: Option<String>,
+    pub fecha_resolucion: Option<String>,
+    pub observacion: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SeguimientoRma {
+    pub estado: String,
+    pub observacion: Option<String>,
+    pub usuario: Option<String>,
+    pub fecha: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NuevoRmaProveedor {
+    pub proveedor_id: i64,
+    #[serde(default)]
+    pub transportista: Option<String>,
+    #[serde(default)]
+    pub numero_guia: Option<String>,
+    #[serde(default)]
+    pub observacion: Option<String>,
+    pub lineas: Vec<NuevaLineaRma>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NuevaLineaRma {
+    pub numero_serie_id: i64,
+    pub motivo: String,
+    #[serde(default)]
+    pub orden_servicio_id: Option<i64>,
+}
+
+/// Un hito en la vida de una serie (compra, venta, reparación, RMA, cambio).
+#[derive(Debug, Serialize, Clone)]
+pub struct EventoSerie {
+    pub fecha: String,
+    pub tipo: String,
+    pub documento: Option<String>,
+    pub detalle: String,
+    pub usuario: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HistorialSerie {
+    pub garantia: GarantiaSerie,
+    pub eventos: Vec<EventoSerie>,
+}
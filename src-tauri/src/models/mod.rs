@@ -11,6 +11,14 @@ pub mod proveedor;
 pub mod compra;
 pub mod cuenta_pagar;
 pub mod orden_servicio;
+pub mod conteo;
+pub mod orden_compra;
+pub mod reposicion;
+pub mod caducidad;
+pub mod costeo;
+pub mod garantia;
+pub mod costo_proveedor;
+pub mod bodega;
 
 pub use producto::*;
 pub use cliente::*;
@@ -25,3 +33,11 @@ pub use proveedor::*;
 pub use compra::*;
 pub use cuenta_pagar::*;
 pub use orden_servicio::*;
+pub use conteo::*;
+pub use orden_compra::*;
+pub use reposicion::*;
+pub use caducidad::*;
+pub use costeo::*;
+pub use garantia::*;
+pub use costo_proveedor::*;
+pub use bodega::*;
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+/// Orden de compra a un proveedor.
+/// estado: BORRADOR → ENVIADA → RECIBIDA_PARCIAL → CERRADA (o ANULADA)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrdenCompra {
+    pub id: i64,
+    pub numero: String,
+    pub proveedor_id: i64,
+    pub proveedor_nombre: Option<String>,
+    pub proveedor_ruc: Option<String>,
+    pub proveedor_email: Option<String>,
+    pub establecimiento_id: i64,
+    pub establecimiento_nombre: Option<String>,
+    pub estado: String,
+    pub fecha_entrega_esperada: Option<String>,
+    pub subtotal: f64,
+    pub iva: f64,
+    pub total: f64,
+    pub observacion: Option<String>,
+    pub usuario_creacion: Option<String>,
+    pub usuario_aprobacion: Option<String>,
+    pub fecha_creacion: String,
+    pub fecha_envio: Option<String>,
+    pub email_enviado_a: Option<String>,
+    pub confirmada_proveedor: bool,
+    pub fecha_entrega_prometida: Option<String>,
+    pub fecha_cierre: Option<String>,
+    pub motivo_cierre: Option<String>,
+    pub lineas: Vec<LineaOrdenCompra>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LineaOrdenCompra {
+    pub id: i64,
+    pub producto_id: i64,
+    pub producto_codigo: Option<String>,
+    pub descripcion: String,
+    pub presentacion_id: Option<i64>,
+    pub presentacion_nombre: Option<String>,
+    pub presentacion_factor: f64,
+    /// Cantidades en unidad base
+    pub cantidad: f64,
+    pub precio_unitario: f64,
+    pub iva_porcentaje: f64,
+    pub subtotal: f64,
+    pub cantidad_recibida: f64,
+    pub cantidad_facturada: f64,
+    pub cantidad_pendiente: f64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NuevaOrdenCompra {
+    pub proveedor_id: i64,
+    /// None = establecimiento de esta terminal
+    #[serde(default)]
+    pub establecimiento_id: Option<i64>,
+    #[serde(default)]
+    pub fecha_entrega_esperada: Option<String>,
+    #[serde(default)]
+    pub observacion: Option<String>,
+    pub lineas: Vec<NuevaLineaOrdenCompra>,
+}
+
+/// Línea pedida: en unidad base (`cantidad`, `precio_unitario`) o en una
+/// presentación (`cantidad_presentacion`, precio por presentación).
+#[derive(Debug, Deserialize, Clone)]
+pub struct NuevaLineaOrdenCompra {
+    pub producto_id: i64,
+    #[serde(default)]
+    pub descripcion: Option<String>,
+    #[serde(default)]
+    pub cantidad: f64,
+    pub precio_unitario: f64,
+    #[serde(default)]
+    pub iva_porcentaje: f64,
+    #[serde(default)]
+    pub presentacion_id: Option<i64>,
+    #[serde(default)]
+    pub cantidad_presentacion: Option<f64>,
+}
+
+/// Ítem recibido contra una línea de la orden. Igual que en compras, se
+/// puede recibir por presentación y con lote/caducidad.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RecepcionOrdenItem {
+    pub linea_id: i64,
+    #[serde(default)]
+    pub cantidad: f64,
+    #[serde(default)]
+    pub presentacion_id: Option<i64>,
+    #[serde(default)]
+    pub cantidad_presentacion: Option<f64>,
+    #[serde(default)]
+    pub lote_numero: Option<String>,
+    #[serde(default)]
+    pub lote_fecha_caducidad: Option<String>,
+    #[serde(default)]
+    pub lote_fecha_elaboracion: Option<String>,
+}
+
+/// Diferencia detectada al conciliar la factura del proveedor contra la
+/// orden y lo recibido.
+/// tipo: PRECIO | CANTIDAD | NO_PEDIDO
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiferenciaConciliacion {
+    pub tipo: String,
+    pub producto_id: Option<i64>,
+    pub descripcion: String,
+    /// Valor según la orden (precio) o lo recibido (cantidad)
+    pub esperado: f64,
+    /// Valor según la factura
+    pub facturado: f64,
+    pub diferencia: f64,
+}
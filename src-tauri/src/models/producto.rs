@@ -90,6 +90,71 @@ pub struct ComboComponente {
     pub hijo_es_servicio: Option<bool>,
 }
 
+/// Linea de receta: cuanto insumo consume una unidad del plato.
+/// cantidad va en la presentacion unidad_id del insumo; cantidad * factor = unidades base.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecetaInsumo {
+    pub id: Option<i64>,
+    pub producto_id: i64,
+    pub insumo_id: i64,
+    pub cantidad: f64,
+    #[serde(default)]
+    pub unidad_id: Option<i64>,
+    #[serde(default)]
+    pub merma_pct: f64,
+    #[serde(default)]
+    pub orden: i64,
+    // Campos enriquecidos en lecturas:
+    #[serde(default)]
+    pub factor: Option<f64>,
+    #[serde(default)]
+    pub cantidad_base: Option<f64>,
+    #[serde(default)]
+    pub unidad_nombre: Option<String>,
+    #[serde(default)]
+    pub insumo_nombre: Option<String>,
+    #[serde(default)]
+    pub insumo_unidad_medida: Option<String>,
+    #[serde(default)]
+    pub insumo_precio_costo: Option<f64>,
+    #[serde(default)]
+    pub insumo_stock_actual: Option<f64>,
+    /// Costo teorico de la linea: cantidad_base * (1 + merma) * precio_costo del insumo
+    #[serde(default)]
+    pub costo: Option<f64>,
+}
+
+/// Food cost teorico de un plato segun su receta.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CostoReceta {
+    pub producto_id: i64,
+    pub nombre: String,
+    /// Precio de venta sin IVA
+    pub precio_neto: f64,
+    pub costo_teorico: f64,
+    /// costo_teorico / precio_neto * 100 (0 si no hay precio)
+    pub food_cost_pct: f64,
+    pub margen: f64,
+    pub lineas: Vec<RecetaInsumo>,
+}
+
+/// Consumo teorico (recetas vendidas) vs ajustes de conteo fisico de un insumo.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VarianzaInsumo {
+    pub insumo_id: i64,
+    pub nombre: String,
+    pub unidad_medida: String,
+    pub consumo_teorico: f64,
+    /// Suma de ajustes por conteo fisico (negativo = falto producto)
+    pub ajuste_conteo: f64,
+    /// consumo_teorico - ajuste_conteo
+    pub consumo_real: f64,
+    /// consumo_real - consumo_teorico
+    pub varianza: f64,
+    pub varianza_pct: f64,
+    pub costo_varianza: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProductoTactil {
     pub id: i64,
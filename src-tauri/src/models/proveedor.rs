@@ -10,5 +10,8 @@ pub struct Proveedor {
     pub email: Option<String>,
     pub contacto: Option<String>,
     pub dias_credito: i64,
+    /// Tiempo de entrega (lead time) en dias, para sugerir reposicion
+    #[serde(default)]
+    pub dias_entrega: i64,
     pub activo: bool,
 }
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+/// Parámetros del cálculo de reposición. Lo que no venga se toma de config
+/// (reposicion_dias_*).
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ParametrosReposicion {
+    /// None = stock y ventas de todos los establecimientos
+    #[serde(default)]
+    pub establecimiento_id: Option<i64>,
+    #[serde(default)]
+    pub proveedor_id: Option<i64>,
+    #[serde(default)]
+    pub categoria_id: Option<i64>,
+    /// Días de ventas hacia atrás para el promedio
+    #[serde(default)]
+    pub dias_ventana: Option<i64>,
+    /// Días de venta que debe cubrir el pedido después de llegar
+    #[serde(default)]
+    pub dias_cobertura: Option<i64>,
+    /// Colchón de seguridad sobre el tiempo de entrega
+    #[serde(default)]
+    pub dias_seguridad: Option<i64>,
+}
+
+/// Producto que conviene pedir.
+#[derive(Debug, Serialize, Clone)]
+pub struct SugerenciaReposicion {
+    pub producto_id: i64,
+    pub codigo: Option<String>,
+    pub nombre: String,
+    pub proveedor_id: Option<i64>,
+    pub stock_actual: f64,
+    pub stock_minimo: f64,
+    /// Pedido en órdenes de compra abiertas y aún no recibido
+    pub en_camino: f64,
+    pub venta_promedio_diaria: f64,
+    /// Venta relativa por día de semana (0 = domingo); 1.0 = día promedio
+    pub factores_dia_semana: Vec<f64>,
+    pub dias_entrega: i64,
+    /// Venta proyectada durante la entrega + seguridad
+    pub punto_reorden: f64,
+    /// Días que alcanza el stock disponible (None = sin ventas)
+    pub dias_cobertura_actual: Option<f64>,
+    /// En unidad base, redondeada al empaque
+    pub cantidad_sugerida: f64,
+    pub presentacion_id: Option<i64>,
+    pub presentacion_nombre: Option<String>,
+    pub presentacion_factor: f64,
+    pub cantidad_presentacion: Option<f64>,
+    pub costo_unitario: f64,
+    pub costo_estimado: f64,
+}
+
+/// Sugerencias agrupadas por proveedor preferido.
+#[derive(Debug, Serialize, Clone)]
+pub struct GrupoReposicion {
+    pub proveedor_id: Option<i64>,
+    pub proveedor_nombre: String,
+    pub dias_entrega: i64,
+    pub total_estimado: f64,
+    pub items: Vec<SugerenciaReposicion>,
+}
+
+/// Línea aceptada (o corregida) por el usuario para generar la orden.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ItemReposicion {
+    pub producto_id: i64,
+    pub proveedor_id: i64,
+    #[serde(default)]
+    pub cantidad: f64,
+    #[serde(default)]
+    pub presentacion_id: Option<i64>,
+    #[serde(default)]
+    pub cantidad_presentacion: Option<f64>,
+}
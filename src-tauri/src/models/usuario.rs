@@ -30,11 +30,13 @@ pub const PERMISOS_DISPONIBLES: &[(&str, &str, &str)] = &[
     ("gestionar_productos",         "Gestionar productos",                                      CAT_CORE),
     ("eliminar_productos",          "Eliminar productos y categorías (botón borrar)",           CAT_CORE),
     ("gestionar_inventario",        "Gestionar inventario",                                     CAT_CORE),
+    ("aprobar_conteos",             "Aprobar conteos físicos (ajusta el stock)",                CAT_CORE),
     ("ver_guias",                   "Ver guias de remision",                                    CAT_CORE),
     ("ver_movimientos_bancarios",   "Ver movimientos bancarios",                                CAT_CORE),
     ("confirmar_depositos",         "Confirmar depósitos en tránsito (retiros a banco)",        CAT_CORE),
     ("crear_nota_credito",          "Crear notas de crédito",                                   CAT_CORE),
     ("gestionar_compras",           "Gestionar compras y proveedores",                          CAT_CORE),
+    ("aprobar_ordenes_compra",      "Aprobar y enviar órdenes de compra",                       CAT_CORE),
     // (gestionar_servicio_tecnico y ver_servicio_tecnico movidos a CAT_SERVICIO_TECNICO abajo, v2.4.8)
     ("cerrar_caja",                 "Cerrar caja (sin requerir supervisor)",                    CAT_CORE),
     ("aprobar_descuadre",           "Aprobar cierre con descuadre alto",                        CAT_CORE),
@@ -51,6 +53,8 @@ pub const PERMISOS_DISPONIBLES: &[(&str, &str, &str)] = &[
     ("imprime_comandas",            "Reimprimir comandas a cocina",                             CAT_RESTAURANTE),
     ("divide_cuenta",               "Dividir cuenta (sub-cuentas)",                             CAT_RESTAURANTE),
     ("une_mesas",                   "Unir mesas (grupos grandes)",                              CAT_RESTAURANTE),
+    ("transfiere_mesas",            "Transferir mesa, mover items y reasignar mesero",          CAT_RESTAURANTE),
+    ("reparte_pedidos",             "Repartidor: ver y actualizar sus entregas delivery",       CAT_RESTAURANTE),
     ("cancela_pedido",              "Cancelar pedido sin cobrar (libera mesa)",                 CAT_RESTAURANTE),
     ("config_mesas",                "Configurar zonas y mesas",                                 CAT_RESTAURANTE),
 
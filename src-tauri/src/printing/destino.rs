@@ -0,0 +1,241 @@
+//! Destinos de impresión RAW (ESC/POS).
+//!
+//! El valor guardado en config (`impresora`, `impresora_cocina`,
+//! `impresora_barra`) elige el backend según su prefijo:
+//!
+//! | Valor en config              | Backend                                        |
+//! |------------------------------|------------------------------------------------|
+//! | `tcp://192.168.1.50:9100`    | Socket TCP directo (puerto 9100 por defecto)   |
+//! | `cups://TM-T20` / `lp://...` | Cola CUPS vía `lp -o raw`                      |
+//! | `file:///dev/usb/lp0`        | Escritura directa a dispositivo/archivo        |
+//! | `EPSON TM-T20` (sin prefijo) | Spooler de Windows; en Linux/macOS, cola CUPS  |
+//!
+//! Sin prefijo se mantiene el comportamiento de siempre, así las configs
+//! existentes siguen funcionando sin migración.
+
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Puerto estándar de impresoras térmicas de red (HP JetDirect / "RAW 9100").
+pub const PUERTO_RAW: u16 = 9100;
+const TIMEOUT_RED: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DestinoImpresora {
+    /// Impresora instalada en el sistema operativo, por nombre.
+    Sistema(String),
+    /// Térmica de red con puerto RAW abierto.
+    Red { host: String, puerto: u16 },
+    /// Cola CUPS (Linux/macOS).
+    Cups(String),
+    /// Dispositivo o archivo (`/dev/usb/lp0`, `COM3`, archivo de depuración).
+    Archivo(PathBuf),
+}
+
+impl DestinoImpresora {
+    pub fn parse(valor: &str) -> Result<Self, String> {
+        let valor = valor.trim();
+        if valor.is_empty() {
+            return Err("No hay impresora configurada. Vaya a Configuración.".to_string());
+        }
+        if let Some(resto) = prefijo(valor, "tcp://") {
+            let resto = resto.trim_end_matches('/');
+            // IPv6 entre corchetes: tcp://[fe80::1]:9100
+            let (host, puerto) = if let Some(sin_corchete) = resto.strip_prefix('[') {
+                let (h, p) = sin_corchete
+                    .split_once(']')
+                    .ok_or_else(|| format!("Dirección de impresora inválida: {}", valor))?;
+                (h, p.strip_prefix(':'))
+            } else if resto.matches(':').count() > 1 {
+                // IPv6 sin corchetes (tcp://fe80::1): no se puede separar un
+                // puerto, todo es el host y va al puerto RAW.
+                (resto, None)
+            } else {
+                match resto.rsplit_once(':') {
+                    Some((h, p)) => (h, Some(p)),
+                    None => (resto, None),
+                }
+            };
+            if host.is_empty() {
+                return Err(format!("Dirección de impresora inválida: {}", valor));
+            }
+            let puerto = match puerto {
+                Some(p) => p
+                    .parse::<u16>()
+                    .map_err(|_| format!("Puerto de impresora inválido: {}", p))?,
+                None => PUERTO_RAW,
+            };
+            return Ok(Self::Red { host: host.to_string(), puerto });
+        }
+        if let Some(cola) = prefijo(valor, "cups://").or_else(|| prefijo(valor, "lp://")) {
+            let cola = cola.trim_end_matches('/');
+            if cola.is_empty() {
+                return Err("Falta el nombre de la cola CUPS".to_string());
+            }
+            return Ok(Self::Cups(cola.to_string()));
+        }
+        if let Some(ruta) = prefijo(valor, "file://") {
+            if ruta.is_empty() {
+                return Err("Falta la ruta del dispositivo".to_string());
+            }
+            return Ok(Self::Archivo(PathBuf::from(ruta)));
+        }
+        Ok(Self::Sistema(valor.to_string()))
+    }
+
+    /// `true` si el destino tiene un prefijo explícito (red/CUPS/archivo).
+    /// Esos destinos siempre son térmicas: nunca se tratan como impresora
+    /// virtual (PDF) aunque el nombre contenga "pdf".
+    pub fn es_explicito(&self) -> bool {
+        !matches!(self, Self::Sistema(_))
+    }
+}
+
+/// `strip_prefix` sin distinguir mayúsculas (`TCP://` también vale).
+fn prefijo<'a>(valor: &'a str, p: &str) -> Option<&'a str> {
+    if valor.len() >= p.len() && valor.is_char_boundary(p.len()) && valor[..p.len()].eq_ignore_ascii_case(p) {
+        Some(&valor[p.len()..])
+    } else {
+        None
+    }
+}
+
+/// Envía bytes RAW al destino configurado. Punto único de impresión ESC/POS:
+/// tickets, comandas de cocina y reportes de caja pasan por aquí.
+pub fn imprimir_raw(destino: &str, datos: &[u8]) -> Result<(), String> {
+    match DestinoImpresora::parse(destino)? {
+        DestinoImpresora::Red { host, puerto } => imprimir_tcp(&host, puerto, datos),
+        DestinoImpresora::Cups(cola) => imprimir_cups(&cola, datos),
+        DestinoImpresora::Archivo(ruta) => imprimir_archivo(&ruta, datos),
+        #[cfg(target_os = "windows")]
+        DestinoImpresora::Sistema(nombre) => super::imprimir_raw_windows(&nombre, datos),
+        #[cfg(not(target_os = "windows"))]
+        DestinoImpresora::Sistema(nombre) => imprimir_cups(&nombre, datos),
+    }
+}
+
+fn imprimir_tcp(host: &str, puerto: u16, datos: &[u8]) -> Result<(), String> {
+    let direccion = (host, puerto)
+        .to_socket_addrs()
+        .map_err(|e| format!("No se pudo resolver la impresora {}:{}: {}", host, puerto, e))?
+        .next()
+        .ok_or_else(|| format!("No se pudo resolver la impresora {}:{}", host, puerto))?;
+    let mut socket = TcpStream::connect_timeout(&direccion, TIMEOUT_RED)
+        .map_err(|e| format!("No se pudo conectar a la impresora {}:{}: {}", host, puerto, e))?;
+    socket.set_write_timeout(Some(TIMEOUT_RED)).map_err(|e| e.to_string())?;
+    socket
+        .write_all(datos)
+        .and_then(|_| socket.flush())
+        .map_err(|e| format!("Error enviando datos a la impresora {}:{}: {}", host, puerto, e))?;
+    // Cerrar escritura para que la impresora procese el trabajo de inmediato.
+    let _ = socket.shutdown(std::net::Shutdown::Write);
+    Ok(())
+}
+
+/// `lp -d <cola> -o raw` leyendo de stdin (sin archivo temporal).
+fn imprimir_cups(cola: &str, datos: &[u8]) -> Result<(), String> {
+    use std::process::{Command, Stdio};
+
+    let mut hijo = Command::new("lp")
+        .args(["-d", cola, "-o", "raw", "-s", "-t", "Ticket POS"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("No se pudo ejecutar 'lp' (¿CUPS instalado?): {}", e))?;
+    if let Some(mut stdin) = hijo.stdin.take() {
+        stdin
+            .write_all(datos)
+            .map_err(|e| format!("Error enviando datos a la cola '{}': {}", cola, e))?;
+    }
+    let salida = hijo.wait_with_output().map_err(|e| e.to_string())?;
+    if salida.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Error al imprimir en la cola '{}': {}",
+            cola,
+            String::from_utf8_lossy(&salida.stderr).trim()
+        ))
+    }
+}
+
+fn imprimir_archivo(ruta: &std::path::Path, datos: &[u8]) -> Result<(), String> {
+    let mut f = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(ruta)
+        .map_err(|e| format!("No se pudo abrir {}: {}", ruta.display(), e))?;
+    f.write_all(datos)
+        .and_then(|_| f.flush())
+        .map_err(|e| format!("Error escribiendo en {}: {}", ruta.display(), e))
+}
+
+/// Colas CUPS disponibles (`lpstat -e`). Vacío si CUPS no está instalado.
+#[cfg(not(target_os = "windows"))]
+pub fn listar_colas_cups() -> Vec<String> {
+    std::process::Command::new("lpstat")
+        .arg("-e")
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_red_con_y_sin_puerto() {
+        assert_eq!(
+            DestinoImpresora::parse("tcp://192.168.1.50").unwrap(),
+            DestinoImpresora::Red { host: "192.168.1.50".into(), puerto: 9100 }
+        );
+        assert_eq!(
+            DestinoImpresora::parse("TCP://cocina.local:9101/").unwrap(),
+            DestinoImpresora::Red { host: "cocina.local".into(), puerto: 9101 }
+        );
+        assert_eq!(
+            DestinoImpresora::parse("tcp://[fe80::1]:9100").unwrap(),
+            DestinoImpresora::Red { host: "fe80::1".into(), puerto: 9100 }
+        );
+        assert!(DestinoImpresora::parse("tcp://10.0.0.2:abc").is_err());
+    }
+
+    #[test]
+    fn parse_ipv6_sin_corchetes_va_al_puerto_raw() {
+        assert_eq!(
+            DestinoImpresora::parse("tcp://fe80::1").unwrap(),
+            DestinoImpresora::Red { host: "fe80::1".into(), puerto: 9100 }
+        );
+        assert_eq!(
+            DestinoImpresora::parse("tcp://2001:db8::20:9100").unwrap(),
+            DestinoImpresora::Red { host: "2001:db8::20:9100".into(), puerto: 9100 }
+        );
+    }
+
+    #[test]
+    fn parse_cups_archivo_y_sistema() {
+        assert_eq!(DestinoImpresora::parse("cups://TM-T20").unwrap(), DestinoImpresora::Cups("TM-T20".into()));
+        assert_eq!(DestinoImpresora::parse("lp://barra").unwrap(), DestinoImpresora::Cups("barra".into()));
+        assert_eq!(
+            DestinoImpresora::parse("file:///dev/usb/lp0").unwrap(),
+            DestinoImpresora::Archivo(PathBuf::from("/dev/usb/lp0"))
+        );
+        assert_eq!(
+            DestinoImpresora::parse(" EPSON TM-T20 ").unwrap(),
+            DestinoImpresora::Sistema("EPSON TM-T20".into())
+        );
+        assert!(DestinoImpresora::parse("").is_err());
+    }
+}
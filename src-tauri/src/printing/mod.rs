@@ -3,6 +3,9 @@ use image::GenericImageView;
 use crate::models::VentaCompleta;
 use std::collections::HashMap;
 
+pub mod destino;
+pub use destino::imprimir_raw;
+
 /// Genera el contenido de texto del ticket
 /// v2.5.14: nuevo parametro pagos_mixtos para desglosar pago MIXTO en el ticket
 /// v2.5.24: nuevo parametro componentes_combo para detallar componentes en venta de combos
@@ -498,7 +501,8 @@ fn logo_to_raster(logo_b64: &str, max_width: u32) -> Option<Vec<u8>> {
 }
 
 /// Imprime bytes RAW a una impresora de Windows por nombre
-/// Usa la API Win32 de impresión (WritePrinter) via PowerShell para enviar datos RAW
+/// Usa la API Win32 de impresión (WritePrinter) via PowerShell para enviar datos RAW.
+/// No llamar directo: usar [`imprimir_raw`], que elige el backend según la config.
 #[cfg(target_os = "windows")]
 pub fn imprimir_raw_windows(nombre_impresora: &str, datos: &[u8]) -> Result<(), String> {
     use std::io::Write;
@@ -581,8 +585,3 @@ Write-Output "OK"
         Err(format!("Error al imprimir: {}", msg.trim()))
     }
 }
-
-#[cfg(not(target_os = "windows"))]
-pub fn imprimir_raw_windows(_nombre_impresora: &str, _datos: &[u8]) -> Result<(), String> {
-    Err("Impresión solo disponible en Windows".to_string())
-}
@@ -0,0 +1,429 @@
+//! Analítica de salón: cubiertos por día y turno, ticket por cubierto,
+//! rotación y permanencia por zona, desempeño por mesero y mapa de calor de
+//! horas.
+//!
+//! Todo sale de `rest_pedidos_abiertos` (comensales, mesero, apertura y
+//! cierre). Un pedido cuenta en el día en que se abrió; su consumo es la suma
+//! de sus items, igual que el total de la mesa en el grid, así cuadra aunque
+//! se haya cobrado en sub-cuentas. Los pedidos cancelados solo cuentan como
+//! anulaciones del mesero, salvo los que quedaron sin items (se unieron a
+//! otra mesa); los cobrados cuya venta se anuló después no cuentan. Los
+//! canales para llevar / delivery suman ventas y cubiertos pero no entran en
+//! la rotación por zona (no ocupan mesa).
+//!
+//! [`tabla`] arma encabezados y filas de cada sección para
+//! `exportar_tabla_xlsx` / `exportar_tabla_pdf`.
+
+use super::models::{CeldaHora, CubiertosTurno, DesempenoMesero, ReporteSalon, RotacionZona};
+use rusqlite::{params, Connection};
+use std::collections::{BTreeMap, HashMap};
+
+/// Secciones exportables del reporte.
+pub const SECCIONES: [&str; 4] = ["TURNOS", "ZONAS", "MESEROS", "HORAS"];
+
+const DIAS_SEMANA: [&str; 7] = ["Dom", "Lun", "Mar", "Mié", "Jue", "Vie", "Sáb"];
+
+fn round2(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+fn promedio(total: f64, n: i64) -> f64 {
+    if n > 0 {
+        round2(total / n as f64)
+    } else {
+        0.0
+    }
+}
+
+/// Un pedido cerrado del rango, ya con su consumo y turno.
+struct PedidoFila {
+    mesero_id: Option<i64>,
+    mesero_nombre: String,
+    zona_id: Option<i64>,
+    zona_nombre: String,
+    /// LLEVAR | DELIVERY para los pedidos de canal
+    canal: Option<String>,
+    comensales: i64,
+    cobrado: bool,
+    fecha: String,
+    dia_semana: i64,
+    hora: i64,
+    permanencia_min: Option<f64>,
+    consumo: f64,
+    caja_id: Option<i64>,
+}
+
+fn cargar_pedidos(conn: &Connection, desde: &str, hasta: &str) -> Result<Vec<PedidoFila>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT p.mesero_id, COALESCE(p.mesero_nombre, 'Sin mesero'), m.zona_id,
+                    COALESCE(z.nombre, 'Sin zona'), m.canal, p.comensales, p.estado,
+                    date(p.fecha_apertura),
+                    CAST(strftime('%w', p.fecha_apertura) AS INTEGER),
+                    CAST(strftime('%H', p.fecha_apertura) AS INTEGER),
+                    (julianday(p.fecha_cierre) - julianday(p.fecha_apertura)) * 24 * 60,
+                    COALESCE((SELECT SUM(i.cantidad * i.precio_unit) FROM rest_pedido_items i
+                              WHERE i.pedido_id = p.id), 0),
+                    COALESCE(
+                        (SELECT v.caja_id FROM ventas v WHERE v.id = p.venta_id),
+                        (SELECT v.caja_id FROM rest_subcuentas s JOIN ventas v ON v.id = s.venta_id
+                         WHERE s.pedido_id = p.id ORDER BY s.id LIMIT 1)
+                    )
+             FROM rest_pedidos_abiertos p
+             JOIN rest_mesas m ON m.id = p.mesa_id
+             LEFT JOIN rest_zonas z ON z.id = m.zona_id
+             WHERE p.estado IN ('COBRADO', 'CANCELADO')
+               AND date(p.fecha_apertura) >= date(?1) AND date(p.fecha_apertura) <= date(?2)
+               -- cancelado sin items: se unió a otro pedido (traspasos) o quedó vacío
+               AND (p.estado = 'COBRADO' OR EXISTS (SELECT 1 FROM rest_pedido_items i WHERE i.pedido_id = p.id))
+               -- cobrado cuya venta (o todas las de sus sub-cuentas) se anuló después
+               AND (p.estado = 'CANCELADO' OR EXISTS (
+                       SELECT 1 FROM ventas v
+                       WHERE v.anulada = 0
+                         AND (v.id = p.venta_id
+                              OR v.id IN (SELECT s.venta_id FROM rest_subcuentas s WHERE s.pedido_id = p.id))))
+             ORDER BY p.fecha_apertura",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![desde, hasta], |r| {
+            Ok(PedidoFila {
+                mesero_id: r.get(0)?,
+                mesero_nombre: r.get(1)?,
+                zona_id: r.get(2)?,
+                zona_nombre: r.get(3)?,
+                canal: r.get(4)?,
+                comensales: r.get(5)?,
+                cobrado: r.get::<_, String>(6)? == "COBRADO",
+                fecha: r.get(7)?,
+                dia_semana: r.get(8)?,
+                hora: r.get(9)?,
+                permanencia_min: r.get(10)?,
+                consumo: r.get(11)?,
+                caja_id: r.get(12)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+fn clave_mesero(mesero_id: Option<i64>, nombre: &str) -> String {
+    mesero_id.map(|id| format!("#{}", id)).unwrap_or_else(|| nombre.to_string())
+}
+
+fn nombre_turno(conn: &Connection, caja_id: Option<i64>) -> String {
+    caja_id
+        .and_then(|id| {
+            conn.query_row(
+                "SELECT fecha_apertura || COALESCE(' → ' || fecha_cierre, '') || COALESCE(' (' || usuario || ')', '')
+                 FROM caja WHERE id = ?1",
+                params![id],
+                |r| r.get(0),
+            )
+            .ok()
+        })
+        .unwrap_or_else(|| "Sin caja".to_string())
+}
+
+pub fn reporte(conn: &Connection, desde: &str, hasta: &str) -> Result<ReporteSalon, String> {
+    let dias: i64 = conn
+        .query_row(
+            "SELECT CAST(julianday(date(?2)) - julianday(date(?1)) AS INTEGER) + 1",
+            params![desde, hasta],
+            |r| r.get::<_, Option<i64>>(0),
+        )
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Fechas inválidas (use YYYY-MM-DD)".to_string())?;
+    if dias < 1 {
+        return Err("La fecha desde es posterior a la fecha hasta".to_string());
+    }
+
+    let pedidos = cargar_pedidos(conn, desde, hasta)?;
+
+    let mesas_zona: HashMap<Option<i64>, i64> = {
+        let mut stmt = conn
+            .prepare("SELECT zona_id, COUNT(*) FROM rest_mesas WHERE activa = 1 AND canal IS NULL GROUP BY zona_id")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| e.to_string())?;
+        rows
+    };
+
+    let propinas: HashMap<String, f64> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT pr.mesero_id, COALESCE(pr.mesero_nombre, 'Sin mesero'), SUM(pr.monto)
+                 FROM rest_propinas pr
+                 JOIN ventas v ON v.id = pr.venta_id
+                 JOIN rest_pedidos_abiertos p ON p.id = pr.pedido_id
+                 WHERE v.anulada = 0
+                   AND date(p.fecha_apertura) >= date(?1) AND date(p.fecha_apertura) <= date(?2)
+                 GROUP BY pr.mesero_id, COALESCE(pr.mesero_nombre, 'Sin mesero')",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![desde, hasta], |r| {
+                let id: Option<i64> = r.get(0)?;
+                let nombre: String = r.get(1)?;
+                Ok((clave_mesero(id, &nombre), r.get::<_, f64>(2)?))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        let mut mapa = HashMap::new();
+        for (clave, monto) in rows {
+            *mapa.entry(clave).or_insert(0.0) += monto;
+        }
+        mapa
+    };
+
+    let mut turnos: BTreeMap<(String, i64), CubiertosTurno> = BTreeMap::new();
+    let mut zonas: BTreeMap<(String, Option<i64>), (RotacionZona, f64, i64)> = BTreeMap::new();
+    let mut meseros: BTreeMap<String, DesempenoMesero> = BTreeMap::new();
+    let mut horas: BTreeMap<(i64, i64), CeldaHora> = BTreeMap::new();
+    let (mut total_pedidos, mut total_cubiertos, mut total_ventas) = (0i64, 0i64, 0.0);
+
+    for p in &pedidos {
+        let mesero = meseros
+            .entry(clave_mesero(p.mesero_id, &p.mesero_nombre))
+            .or_insert_with(|| DesempenoMesero {
+                mesero_id: p.mesero_id,
+                mesero_nombre: p.mesero_nombre.clone(),
+                pedidos: 0,
+                cubiertos: 0,
+                ventas: 0.0,
+                ticket_promedio: 0.0,
+                promedio_cubierto: 0.0,
+                propinas: 0.0,
+                anulados: 0,
+                monto_anulado: 0.0,
+            });
+        if !p.cobrado {
+            mesero.anulados += 1;
+            mesero.monto_anulado += p.consumo;
+            continue;
+        }
+        mesero.pedidos += 1;
+        mesero.cubiertos += p.comensales;
+        mesero.ventas += p.consumo;
+
+        total_pedidos += 1;
+        total_cubiertos += p.comensales;
+        total_ventas += p.consumo;
+
+        let turno = turnos
+            .entry((p.fecha.clone(), p.caja_id.unwrap_or(0)))
+            .or_insert_with(|| CubiertosTurno {
+                fecha: p.fecha.clone(),
+                caja_id: p.caja_id,
+                turno: nombre_turno(conn, p.caja_id),
+                pedidos: 0,
+                cubiertos: 0,
+                ventas: 0.0,
+                ticket_promedio: 0.0,
+                promedio_cubierto: 0.0,
+            });
+        turno.pedidos += 1;
+        turno.cubiertos += p.comensales;
+        turno.ventas += p.consumo;
+
+        let celda = horas.entry((p.dia_semana, p.hora)).or_insert_with(|| CeldaHora {
+            dia_semana: p.dia_semana,
+            hora: p.hora,
+            pedidos: 0,
+            cubiertos: 0,
+            ventas: 0.0,
+        });
+        celda.pedidos += 1;
+        celda.cubiertos += p.comensales;
+        celda.ventas += p.consumo;
+
+        if p.canal.is_some() {
+            continue;
+        }
+        // (zona, suma de permanencias, pedidos con permanencia)
+        let (zona, suma_min, con_min) = zonas.entry((p.zona_nombre.clone(), p.zona_id)).or_insert_with(|| {
+            (
+                RotacionZona {
+                    zona_id: p.zona_id,
+                    zona_nombre: p.zona_nombre.clone(),
+                    mesas: mesas_zona.get(&p.zona_id).copied().unwrap_or(0),
+                    pedidos: 0,
+                    cubiertos: 0,
+                    rotacion: 0.0,
+                    permanencia_promedio_min: 0.0,
+                    permanencia_maxima_min: 0.0,
+                },
+                0.0,
+                0,
+            )
+        });
+        zona.pedidos += 1;
+        zona.cubiertos += p.comensales;
+        if let Some(min) = p.permanencia_min.filter(|m| *m >= 0.0) {
+            *suma_min += min;
+            *con_min += 1;
+            zona.permanencia_maxima_min = zona.permanencia_maxima_min.max(round2(min));
+        }
+    }
+
+    let turnos = turnos
+        .into_values()
+        .map(|mut t| {
+            t.ventas = round2(t.ventas);
+            t.ticket_promedio = promedio(t.ventas, t.pedidos);
+            t.promedio_cubierto = promedio(t.ventas, t.cubiertos);
+            t
+        })
+        .collect();
+    let zonas = zonas
+        .into_values()
+        .map(|(mut z, suma_min, con_min)| {
+            z.rotacion = promedio(z.pedidos as f64, z.mesas * dias);
+            z.permanencia_promedio_min = promedio(suma_min, con_min);
+            z
+        })
+        .collect();
+    let mut meseros: Vec<DesempenoMesero> = meseros
+        .into_iter()
+        .map(|(clave, mut m)| {
+            m.ventas = round2(m.ventas);
+            m.monto_anulado = round2(m.monto_anulado);
+            m.ticket_promedio = promedio(m.ventas, m.pedidos);
+            m.promedio_cubierto = promedio(m.ventas, m.cubiertos);
+            m.propinas = round2(propinas.get(&clave).copied().unwrap_or(0.0));
+            m
+        })
+        .collect();
+    meseros.sort_by(|a, b| b.ventas.total_cmp(&a.ventas));
+    let horas = horas
+        .into_values()
+        .map(|mut c| {
+            c.ventas = round2(c.ventas);
+            c
+        })
+        .collect();
+
+    Ok(ReporteSalon {
+        desde: desde.to_string(),
+        hasta: hasta.to_string(),
+        dias,
+        pedidos: total_pedidos,
+        cubiertos: total_cubiertos,
+        ventas: round2(total_ventas),
+        promedio_cubierto: promedio(total_ventas, total_cubiertos),
+        turnos,
+        zonas,
+        meseros,
+        horas,
+    })
+}
+
+fn dinero(v: f64) -> String {
+    format!("{:.2}", v)
+}
+
+/// Una sección del reporte lista para `exportar_tabla_xlsx` / `exportar_tabla_pdf`.
+pub struct TablaSalon {
+    pub titulo: String,
+    pub encabezados: Vec<String>,
+    pub filas: Vec<Vec<String>>,
+    pub columnas_numericas: Vec<usize>,
+}
+
+pub fn tabla(r: &ReporteSalon, seccion: &str) -> Result<TablaSalon, String> {
+    let enc = |cols: &[&str]| cols.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+    let (titulo, encabezados, filas, columnas_numericas) = match seccion {
+        "TURNOS" => (
+            "Cubiertos por día y turno".to_string(),
+            enc(&["Fecha", "Turno", "Pedidos", "Cubiertos", "Ventas", "Ticket promedio", "Por cubierto"]),
+            r.turnos
+                .iter()
+                .map(|t| {
+                    vec![
+                        t.fecha.clone(),
+                        t.turno.clone(),
+                        t.pedidos.to_string(),
+                        t.cubiertos.to_string(),
+                        dinero(t.ventas),
+                        dinero(t.ticket_promedio),
+                        dinero(t.promedio_cubierto),
+                    ]
+                })
+                .collect(),
+            vec![2, 3, 4, 5, 6],
+        ),
+        "ZONAS" => (
+            "Rotación y permanencia por zona".to_string(),
+            enc(&["Zona", "Mesas", "Pedidos", "Cubiertos", "Rotación (por mesa/día)", "Permanencia prom. (min)", "Permanencia máx. (min)"]),
+            r.zonas
+                .iter()
+                .map(|z| {
+                    vec![
+                        z.zona_nombre.clone(),
+                        z.mesas.to_string(),
+                        z.pedidos.to_string(),
+                        z.cubiertos.to_string(),
+                        dinero(z.rotacion),
+                        format!("{:.0}", z.permanencia_promedio_min),
+                        format!("{:.0}", z.permanencia_maxima_min),
+                    ]
+                })
+                .collect(),
+            vec![1, 2, 3, 4, 5, 6],
+        ),
+        "MESEROS" => (
+            "Ventas por mesero".to_string(),
+            enc(&["Mesero", "Pedidos", "Cubiertos", "Ventas", "Ticket promedio", "Por cubierto", "Propinas", "Anulados", "Monto anulado"]),
+            r.meseros
+                .iter()
+                .map(|m| {
+                    vec![
+                        m.mesero_nombre.clone(),
+                        m.pedidos.to_string(),
+                        m.cubiertos.to_string(),
+                        dinero(m.ventas),
+                        dinero(m.ticket_promedio),
+                        dinero(m.promedio_cubierto),
+                        dinero(m.propinas),
+                        m.anulados.to_string(),
+                        dinero(m.monto_anulado),
+                    ]
+                })
+                .collect(),
+            vec![1, 2, 3, 4, 5, 6, 7, 8],
+        ),
+        // Mapa de calor como tabla: una fila por hora con actividad, una
+        // columna por día de la semana (cubiertos).
+        "HORAS" => {
+            let mut filas: BTreeMap<i64, [i64; 7]> = BTreeMap::new();
+            for c in &r.horas {
+                if let Some(dia) = filas.entry(c.hora).or_insert([0; 7]).get_mut(c.dia_semana as usize) {
+                    *dia += c.cubiertos;
+                }
+            }
+            let mut encabezados = vec!["Hora".to_string()];
+            encabezados.extend(DIAS_SEMANA.iter().map(|d| d.to_string()));
+            (
+                "Cubiertos por hora y día de la semana".to_string(),
+                encabezados,
+                filas
+                    .into_iter()
+                    .map(|(hora, dias)| {
+                        let mut fila = vec![format!("{:02}:00", hora)];
+                        fila.extend(dias.iter().map(|n| n.to_string()));
+                        fila
+                    })
+                    .collect(),
+                (1..=7).collect(),
+            )
+        }
+        _ => return Err(format!("Sección inválida: {} (use {})", seccion, SECCIONES.join(", "))),
+    };
+    Ok(TablaSalon { titulo, encabezados, filas, columnas_numericas })
+}
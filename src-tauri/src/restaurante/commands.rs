@@ -24,16 +24,56 @@
 //!   - rest_cancelar_pedido (id)
 //!
 //! Items:
-//!   - rest_agregar_item, rest_actualizar_item_cantidad, rest_eliminar_item
+//!   - rest_agregar_item (con modificadores), rest_actualizar_item_cantidad, rest_eliminar_item
+//!
+//! Modificadores:
+//!   - rest_listar_grupos_modificadores, rest_guardar_grupo_modificadores, rest_eliminar_grupo_modificadores
+//!   - rest_asignar_modificadores_producto, rest_modificadores_producto
 //!
 //! Cocina:
 //!   - rest_enviar_cocina (pedido_id) — marca items pendientes y retorna lista para imprimir
-//!   - rest_listar_items_cocina_pendientes — vista cocina/TV
-//!   - rest_marcar_item_listo, rest_marcar_item_entregado
+//!   - rest_retener_item, rest_disparar_curso (pedido_id, curso) — tiempos entrada/fuerte/postre
+//!   - rest_listar_items_cocina_pendientes (estacion_id?) — vista cocina/TV con tiempos
+//!   - rest_marcar_item_cocina
+//!   - rest_listar_tiempos_producto, rest_guardar_tiempo_producto, rest_reporte_tiempos_cocina
+//!
+//! Traspasos (permiso `transfiere_mesas`, auditados):
+//!   - rest_transferir_mesa, rest_mover_items, rest_reasignar_mesero
+//!   - rest_listar_movimientos_pedido
+//!
+//! Para llevar / delivery:
+//!   - rest_crear_entrega (tipo, cliente, dirección, hora prometida, envío) — abre el pedido
+//!   - rest_listar_entregas (estado?, repartidor_id?), rest_asignar_repartidor
+//!   - rest_cambiar_estado_entrega (PREPARANDO → LISTO → EN_CAMINO → ENTREGADO | CANCELADO)
+//!   - rest_liquidacion_repartidores — efectivo contra entrega pendiente/liquidado en caja
+//!
+//! Reservas y lista de espera:
+//!   - rest_listar_reservas (fecha?), rest_guardar_reserva, rest_confirmar_reserva, rest_cancelar_reserva
+//!   - rest_marcar_no_show, rest_registrar_deposito_reserva, rest_listar_depositos_reserva
+//!   - rest_sentar_reserva (reserva_id, mesa_id?) — abre pedido y pasa el depósito como abono
+//!   - rest_listar_espera, rest_agregar_espera, rest_actualizar_espera, rest_sentar_espera
+//!   - rest_reporte_no_shows
+//!
+//! Estaciones (ruteo de comandas por impresora):
+//!   - rest_listar_estaciones, rest_guardar_estacion, rest_eliminar_estacion
+//!   - rest_listar_comandas (pedido_id?, solo_fallidas?), rest_reimprimir_comanda (comanda_id)
+//!   - rest_crear_pantalla_kds (nombre, estacion_id?) — token + URL de la pantalla de cocina web
 //!
 //! Cuenta y cobro:
 //!   - rest_pedir_cuenta (pedido_id)
 //!   - rest_cobrar_pedido (pedido_id, forma_pago) — vincula con venta y libera mesa
+//!   - rest_cerrar_pedido / rest_marcar_subcuenta_cobrada aceptan `propina` (servicio o voluntaria)
+//!   - rest_sugerir_propina (pedido_id, subcuenta_id?), rest_reporte_propinas (reparto por turno)
+//!
+//! Analítica de salón:
+//!   - rest_reporte_salon (desde, hasta) — cubiertos por turno, rotación por zona, meseros, mapa de horas
+//!   - rest_exportar_reporte_salon (seccion, formato XLSX|PDF, ruta)
+//!
+//! División de cuenta:
+//!   - rest_dividir_cuenta (partes iguales), rest_dividir_cuenta_por_items (por consumo)
+//!   - rest_asignar_cliente_subcuenta, rest_venta_subcuenta, rest_validar_venta_subcuenta
+//!   - rest_marcar_subcuenta_cobrada
+//!   - rest_venta_pedido — líneas de venta del pedido completo
 
 use super::models::*;
 use super::requiere_modulo_restaurante;
@@ -181,6 +221,8 @@ pub(crate) fn listar_mesas_con_estado_internal(conn: &Connection) -> Result<Vec<
            AND id NOT IN (SELECT DISTINCT pedido_id FROM rest_pedido_items)",
         [],
     );
+    // Reservas vencidas sin llegar → NO_SHOW (libera la mesa).
+    super::reservas::marcar_no_shows_vencidos(conn);
 
     // v2.3.68: COALESCE entre pedido propio y pedido al que esta mesa fue unida como EXTRA.
     // Una mesa "extra" muestra el estado del pedido principal y debe ser clickeable
@@ -247,6 +289,10 @@ pub(crate) fn listar_mesas_con_estado_internal(conn: &Connection) -> Result<Vec<
                 mesa_principal_id: if es_extra { row.get(13)? } else { None },
                 mesa_principal_nombre: if es_extra { row.get(14)? } else { None },
                 mesas_unidas_count: 0,
+                reserva_id: None,
+                reserva_cliente: None,
+                reserva_fecha_hora: None,
+                reserva_personas: None,
             })
         })
         .map_err(|e| e.to_string())?
@@ -310,6 +356,13 @@ pub(crate) fn listar_mesas_con_estado_internal(conn: &Connection) -> Result<Vec<
                     .unwrap_or(0);
                 m.mesas_unidas_count = count;
             }
+        } else if let Some((rid, cliente, fecha_hora, personas)) = super::reservas::reserva_que_bloquea(conn, m.id) {
+            // Mesa libre pero con reserva próxima: se bloquea para walk-ins.
+            m.estado = "RESERVADA".to_string();
+            m.reserva_id = Some(rid);
+            m.reserva_cliente = Some(cliente);
+            m.reserva_fecha_hora = Some(fecha_hora);
+            m.reserva_personas = Some(personas);
         }
     }
 
@@ -340,6 +393,8 @@ pub fn rest_abrir_pedido(
     if mesa_existe == 0 {
         return Err("Mesa no encontrada o inactiva".to_string());
     }
+    // Una mesa con reserva próxima solo se abre sentando la reserva
+    super::reservas::validar_mesa_sin_reserva(&conn, mesa_id)?;
 
     // Validar que no haya ya un pedido abierto para esta mesa
     let abierto_existe: i64 = conn
@@ -447,6 +502,9 @@ pub fn rest_agregar_item(
     producto_id: i64,
     cantidad: f64,
     info_adicional: Option<String>,
+    modificadores: Option<Vec<i64>>,
+    curso: Option<String>,
+    retener: Option<bool>,
 ) -> Result<i64, String> {
     requiere_modulo_restaurante(&db)?;
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
@@ -462,6 +520,16 @@ pub fn rest_agregar_item(
     if estado != "ABIERTO" && estado != "CUENTA_PEDIDA" {
         return Err(format!("No se pueden agregar items a un pedido {}", estado));
     }
+    let dividido_por_items: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM rest_subcuentas WHERE pedido_id = ?1 AND modo = 'ITEMS'",
+            params![pedido_id],
+            |row| row.get(0),
+        )
+        .unwrap_or(0);
+    if dividido_por_items > 0 {
+        return Err("La cuenta está dividida por items. Cancele la división para agregar más.".to_string());
+    }
 
     // Obtener precio + destino del producto
     let (precio, destino): (f64, String) = conn
@@ -473,6 +541,11 @@ pub fn rest_agregar_item(
         )
         .map_err(|_| "Producto no encontrado o inactivo".to_string())?;
 
+    // Modificadores: validar min/max por grupo y sumar deltas al precio
+    let elegidos = super::modificadores::resolver(&conn, producto_id, &modificadores.unwrap_or_default())?;
+    let precio = precio + super::modificadores::precio_extra(&elegidos);
+    let curso = super::tiempos::curso_para_item(&conn, producto_id, curso.as_deref())?;
+
     // Si el destino es DIRECTO (bebidas embotelladas, snacks, etc.):
     // marcar el item como YA enviado a cocina y YA entregado, para que NO
     // aparezca en /cocina. El mesero lo despacha del mostrador.
@@ -481,21 +554,25 @@ pub fn rest_agregar_item(
         conn.execute(
             "INSERT INTO rest_pedido_items
              (pedido_id, producto_id, cantidad, precio_unit, info_adicional,
-              enviado_cocina, estado_cocina, fecha_envio_cocina)
-             VALUES (?1, ?2, ?3, ?4, ?5, 1, 'ENTREGADO', datetime('now', 'localtime'))",
-            params![pedido_id, producto_id, cantidad, precio, info_adicional],
+              enviado_cocina, estado_cocina, fecha_envio_cocina, curso)
+             VALUES (?1, ?2, ?3, ?4, ?5, 1, 'ENTREGADO', datetime('now', 'localtime'), ?6)",
+            params![pedido_id, producto_id, cantidad, precio, info_adicional, curso],
         )
         .map_err(|e| e.to_string())?;
     } else {
-        // COCINA o BARRA: flujo normal — mesero lo enviará a cocina cuando esté listo
+        // COCINA o BARRA: flujo normal — mesero lo enviará a cocina cuando esté listo.
+        // RETENIDO: no sale con el envío normal, espera a que se dispare su tiempo.
+        let estado_cocina = if retener.unwrap_or(false) { "RETENIDO" } else { "PENDIENTE" };
         conn.execute(
-            "INSERT INTO rest_pedido_items (pedido_id, producto_id, cantidad, precio_unit, info_adicional)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![pedido_id, producto_id, cantidad, precio, info_adicional],
+            "INSERT INTO rest_pedido_items (pedido_id, producto_id, cantidad, precio_unit, info_adicional, curso, estado_cocina)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![pedido_id, producto_id, cantidad, precio, info_adicional, curso, estado_cocina],
         )
         .map_err(|e| e.to_string())?;
     }
-    Ok(conn.last_insert_rowid())
+    let item_id = conn.last_insert_rowid();
+    super::modificadores::guardar(&conn, item_id, &elegidos)?;
+    Ok(item_id)
 }
 
 #[tauri::command]
@@ -509,6 +586,9 @@ pub fn rest_actualizar_item_cantidad(
         return Err("La cantidad debe ser mayor a 0".to_string());
     }
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    if item_en_division_por_items(&conn, item_id) {
+        return Err("El item ya está repartido entre sub-cuentas. Cancele la división primero.".to_string());
+    }
     conn.execute(
         "UPDATE rest_pedido_items SET cantidad = ?1 WHERE id = ?2",
         params![cantidad, item_id],
@@ -541,6 +621,9 @@ pub fn rest_eliminar_item(db: State<'_, Database>, item_id: i64) -> Result<(), S
     if enviado != 0 && destino != "DIRECTO" {
         return Err("No se puede eliminar un item ya enviado a cocina. Use anulación.".to_string());
     }
+    if item_en_division_por_items(&conn, item_id) {
+        return Err("El item ya está repartido entre sub-cuentas. Cancele la división primero.".to_string());
+    }
     conn.execute("DELETE FROM rest_pedido_items WHERE id = ?1", params![item_id])
         .map_err(|e| e.to_string())?;
     Ok(())
@@ -562,15 +645,16 @@ pub fn rest_enviar_cocina(
     // Obtener items pendientes ANTES de marcarlos.
     // Nota: items con destino DIRECTO se insertaron ya con enviado_cocina=1, asi que
     // el filtro `enviado_cocina = 0` los excluye automáticamente. Solo COCINA + BARRA aqui.
+    // Los RETENIDOS esperan a rest_disparar_curso.
     let mut stmt = conn
         .prepare(
             "SELECT i.id, i.pedido_id, i.producto_id, p.nombre, i.cantidad, i.precio_unit,
                     i.info_adicional, i.enviado_cocina, i.estado_cocina,
                     i.fecha_creacion, i.fecha_envio_cocina,
-                    COALESCE(p.destino_preparacion, 'COCINA') as destino
+                    COALESCE(p.destino_preparacion, 'COCINA') as destino, i.modificadores, i.curso
              FROM rest_pedido_items i
              JOIN productos p ON i.producto_id = p.id
-             WHERE i.pedido_id = ?1 AND i.enviado_cocina = 0
+             WHERE i.pedido_id = ?1 AND i.enviado_cocina = 0 AND i.estado_cocina <> 'RETENIDO'
              ORDER BY i.id",
         )
         .map_err(|e| e.to_string())?;
@@ -589,6 +673,8 @@ pub fn rest_enviar_cocina(
                 fecha_creacion: row.get(9)?,
                 fecha_envio_cocina: row.get(10)?,
                 destino_preparacion: row.get(11)?,
+                modificadores: row.get(12)?,
+                curso: row.get(13)?,
             })
         })
         .map_err(|e| e.to_string())?
@@ -603,7 +689,7 @@ pub fn rest_enviar_cocina(
     conn.execute(
         "UPDATE rest_pedido_items
          SET enviado_cocina = 1, fecha_envio_cocina = datetime('now', 'localtime')
-         WHERE pedido_id = ?1 AND enviado_cocina = 0",
+         WHERE pedido_id = ?1 AND enviado_cocina = 0 AND estado_cocina <> 'RETENIDO'",
         params![pedido_id],
     )
     .map_err(|e| e.to_string())?;
@@ -611,47 +697,48 @@ pub fn rest_enviar_cocina(
     Ok(items)
 }
 
+/// Retiene un item no enviado (no sale con `rest_enviar_cocina`) o lo libera.
+#[tauri::command]
+pub fn rest_retener_item(
+    db: State<'_, Database>,
+    item_id: i64,
+    retener: bool,
+) -> Result<(), String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::tiempos::retener_item(&conn, item_id, retener)
+}
+
+/// Dispara un tiempo (ENTRADA/FUERTE/POSTRE; None = todo lo retenido):
+/// sus items salen a cocina. Retorna los items disparados para imprimir,
+/// igual que `rest_enviar_cocina`.
+#[tauri::command]
+pub fn rest_disparar_curso(
+    db: State<'_, Database>,
+    pedido_id: i64,
+    curso: Option<String>,
+) -> Result<Vec<PedidoItem>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let ids = super::tiempos::disparar_curso(&conn, pedido_id, curso.as_deref())?;
+    let detalle = obtener_pedido_detalle(&conn, pedido_id)?;
+    Ok(detalle
+        .items
+        .into_iter()
+        .filter(|i| i.id.is_some_and(|id| ids.contains(&id)))
+        .collect())
+}
+
+/// Vista cocina/TV: items activos con edad del ticket, objetivo y atraso.
+/// `estacion_id` filtra por estación (0 = 'General').
 #[tauri::command]
 pub fn rest_listar_items_cocina_pendientes(
     db: State<'_, Database>,
+    estacion_id: Option<i64>,
 ) -> Result<Vec<ItemCocina>, String> {
     requiere_modulo_restaurante(&db)?;
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    let mut stmt = conn
-        .prepare(
-            "SELECT i.id, i.pedido_id, m.nombre, z.nombre, p.mesero_nombre,
-                    pr.nombre, i.cantidad, i.info_adicional, i.estado_cocina, i.fecha_envio_cocina,
-                    CAST((julianday('now', 'localtime') - julianday(i.fecha_envio_cocina)) * 24 * 60 AS INTEGER) AS mins
-             FROM rest_pedido_items i
-             JOIN rest_pedidos_abiertos p ON i.pedido_id = p.id
-             JOIN rest_mesas m ON p.mesa_id = m.id
-             LEFT JOIN rest_zonas z ON m.zona_id = z.id
-             JOIN productos pr ON i.producto_id = pr.id
-             WHERE i.enviado_cocina = 1
-               AND i.estado_cocina IN ('PENDIENTE', 'EN_PREPARACION', 'LISTO')
-             ORDER BY i.fecha_envio_cocina ASC",
-        )
-        .map_err(|e| e.to_string())?;
-    let items: Vec<ItemCocina> = stmt
-        .query_map([], |row| {
-            Ok(ItemCocina {
-                id: row.get(0)?,
-                pedido_id: row.get(1)?,
-                mesa_nombre: row.get(2)?,
-                zona_nombre: row.get(3)?,
-                mesero_nombre: row.get(4)?,
-                producto_nombre: row.get(5)?,
-                cantidad: row.get(6)?,
-                info_adicional: row.get(7)?,
-                estado_cocina: row.get(8)?,
-                fecha_envio_cocina: row.get(9)?,
-                minutos_en_cocina: row.get(10).ok(),
-            })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
-    Ok(items)
+    super::tiempos::listar_pendientes(&conn, estacion_id)
 }
 
 #[tauri::command]
@@ -661,20 +748,337 @@ pub fn rest_marcar_item_cocina(
     estado: String,
 ) -> Result<(), String> {
     requiere_modulo_restaurante(&db)?;
-    let estado_valido = matches!(
-        estado.as_str(),
-        "PENDIENTE" | "EN_PREPARACION" | "LISTO" | "ENTREGADO"
-    );
-    if !estado_valido {
-        return Err(format!("Estado de cocina inválido: {}", estado));
-    }
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    conn.execute(
-        "UPDATE rest_pedido_items SET estado_cocina = ?1 WHERE id = ?2",
-        params![estado, item_id],
-    )
-    .map_err(|e| e.to_string())?;
-    Ok(())
+    super::tiempos::marcar_estado(&conn, item_id, &estado)
+}
+
+#[tauri::command]
+pub fn rest_listar_tiempos_producto(db: State<'_, Database>) -> Result<Vec<TiempoProducto>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::tiempos::listar_tiempos_producto(&conn)
+}
+
+/// Objetivo de preparación (minutos) y tiempo por defecto de un producto.
+#[tauri::command]
+pub fn rest_guardar_tiempo_producto(
+    db: State<'_, Database>,
+    tiempo: TiempoProducto,
+) -> Result<(), String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::tiempos::guardar_tiempo_producto(&conn, &tiempo)
+}
+
+/// Tiempo promedio/máximo de preparación por estación y producto.
+#[tauri::command]
+pub fn rest_reporte_tiempos_cocina(
+    db: State<'_, Database>,
+    fecha_desde: String,
+    fecha_hasta: String,
+) -> Result<ReporteTiemposCocina, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::tiempos::reporte(&conn, &fecha_desde, &fecha_hasta)
+}
+
+// ─── Traspasos (mesa, items, mesero) ─────────────────────────────────────
+
+/// Pasa el pedido completo a otra mesa libre.
+#[tauri::command]
+pub fn rest_transferir_mesa(
+    db: State<'_, Database>,
+    sesion: State<'_, crate::db::SesionState>,
+    pedido_id: i64,
+    mesa_destino_id: i64,
+    motivo: Option<String>,
+) -> Result<PedidoDetalle, String> {
+    requiere_modulo_restaurante(&db)?;
+    let activa = sesion.con_permiso("transfiere_mesas")?;
+    let (usuario_id, usuario_nombre) = (activa.as_ref().map(|s| s.usuario_id), activa.map(|s| s.nombre));
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let autor = super::traspasos::Autor { usuario_id, usuario_nombre: usuario_nombre.as_deref(), origen: "POS" };
+    super::traspasos::transferir_mesa(&tx, pedido_id, mesa_destino_id, motivo.as_deref(), &autor)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    obtener_pedido_detalle(&conn, pedido_id)
+}
+
+/// Mueve items a otro pedido (`pedido_destino_id`) o a otra mesa (`mesa_destino_id`,
+/// abre pedido si está libre). Retorna el pedido destino.
+#[tauri::command]
+pub fn rest_mover_items(
+    db: State<'_, Database>,
+    sesion: State<'_, crate::db::SesionState>,
+    pedido_id: i64,
+    items: Vec<ItemAMover>,
+    pedido_destino_id: Option<i64>,
+    mesa_destino_id: Option<i64>,
+    motivo: Option<String>,
+) -> Result<PedidoDetalle, String> {
+    requiere_modulo_restaurante(&db)?;
+    let activa = sesion.con_permiso("transfiere_mesas")?;
+    let (usuario_id, usuario_nombre) = (activa.as_ref().map(|s| s.usuario_id), activa.map(|s| s.nombre));
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let autor = super::traspasos::Autor { usuario_id, usuario_nombre: usuario_nombre.as_deref(), origen: "POS" };
+    let destino = super::traspasos::mover_items(
+        &tx,
+        pedido_id,
+        &items,
+        (pedido_destino_id, mesa_destino_id),
+        motivo.as_deref(),
+        &autor,
+    )?;
+    tx.commit().map_err(|e| e.to_string())?;
+    obtener_pedido_detalle(&conn, destino)
+}
+
+#[tauri::command]
+pub fn rest_reasignar_mesero(
+    db: State<'_, Database>,
+    sesion: State<'_, crate::db::SesionState>,
+    pedido_id: i64,
+    mesero_id: i64,
+    motivo: Option<String>,
+) -> Result<PedidoDetalle, String> {
+    requiere_modulo_restaurante(&db)?;
+    let activa = sesion.con_permiso("transfiere_mesas")?;
+    let (usuario_id, usuario_nombre) = (activa.as_ref().map(|s| s.usuario_id), activa.map(|s| s.nombre));
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let autor = super::traspasos::Autor { usuario_id, usuario_nombre: usuario_nombre.as_deref(), origen: "POS" };
+    super::traspasos::reasignar_mesero(&tx, pedido_id, mesero_id, motivo.as_deref(), &autor)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    obtener_pedido_detalle(&conn, pedido_id)
+}
+
+/// Auditoría de traspasos del pedido (como origen o destino).
+#[tauri::command]
+pub fn rest_listar_movimientos_pedido(
+    db: State<'_, Database>,
+    pedido_id: i64,
+) -> Result<Vec<MovimientoPedido>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::traspasos::listar_movimientos(&conn, pedido_id)
+}
+
+// ─── Para llevar / delivery ──────────────────────────────────────────────
+
+/// Abre un pedido para llevar o delivery (sin mesa física). El usuario de la
+/// sesión queda como mesero. Retorna el detalle para seguir agregando items.
+#[tauri::command]
+pub fn rest_crear_entrega(
+    db: State<'_, Database>,
+    sesion: State<'_, crate::db::SesionState>,
+    entrega: NuevaEntrega,
+) -> Result<PedidoDetalle, String> {
+    requiere_modulo_restaurante(&db)?;
+    let (usuario_id, usuario_nombre) = usuario_de_sesion(&sesion)?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let pedido_id = super::entregas::crear(&tx, &entrega, (usuario_id, usuario_nombre.as_deref()))?;
+    tx.commit().map_err(|e| e.to_string())?;
+    obtener_pedido_detalle(&conn, pedido_id)
+}
+
+#[tauri::command]
+pub fn rest_listar_entregas(
+    db: State<'_, Database>,
+    estado: Option<String>,
+    repartidor_id: Option<i64>,
+    incluir_cerradas: Option<bool>,
+) -> Result<Vec<Entrega>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::entregas::listar(&conn, estado.as_deref(), repartidor_id, incluir_cerradas.unwrap_or(false))
+}
+
+#[tauri::command]
+pub fn rest_asignar_repartidor(
+    db: State<'_, Database>,
+    pedido_id: i64,
+    repartidor_id: i64,
+) -> Result<Entrega, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::entregas::asignar_repartidor(&conn, pedido_id, repartidor_id)?;
+    super::entregas::obtener(&conn, pedido_id)
+}
+
+#[tauri::command]
+pub fn rest_cambiar_estado_entrega(
+    db: State<'_, Database>,
+    pedido_id: i64,
+    estado: String,
+    repartidor_id: Option<i64>,
+) -> Result<Entrega, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::entregas::cambiar_estado(&conn, pedido_id, &estado, repartidor_id)?;
+    super::entregas::obtener(&conn, pedido_id)
+}
+
+/// Efectivo contra entrega por repartidor: lo que trae pendiente (cobrarlo
+/// en caja al volver) y lo ya liquidado en la caja abierta.
+#[tauri::command]
+pub fn rest_liquidacion_repartidores(
+    db: State<'_, Database>,
+    repartidor_id: Option<i64>,
+) -> Result<Vec<LiquidacionRepartidor>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::entregas::liquidacion(&conn, repartidor_id)
+}
+
+// ─── Reservas y lista de espera ──────────────────────────────────────────
+
+fn usuario_de_sesion(sesion: &crate::db::SesionState) -> Result<(Option<i64>, Option<String>), String> {
+    let g = sesion.sesion.lock().map_err(|e| e.to_string())?;
+    Ok(match g.as_ref() {
+        Some(s) => (Some(s.usuario_id), Some(s.nombre.clone())),
+        None => (None, None),
+    })
+}
+
+/// Reservas del día (`fecha` 'YYYY-MM-DD', None = hoy). Marca primero los no-shows vencidos.
+#[tauri::command]
+pub fn rest_listar_reservas(
+    db: State<'_, Database>,
+    fecha: Option<String>,
+    incluir_cerradas: Option<bool>,
+) -> Result<Vec<Reserva>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::reservas::listar(&conn, fecha.as_deref(), incluir_cerradas.unwrap_or(false))
+}
+
+#[tauri::command]
+pub fn rest_guardar_reserva(
+    db: State<'_, Database>,
+    sesion: State<'_, crate::db::SesionState>,
+    reserva: Reserva,
+) -> Result<i64, String> {
+    requiere_modulo_restaurante(&db)?;
+    let (_, usuario_nombre) = usuario_de_sesion(&sesion)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::reservas::guardar(&conn, &reserva, usuario_nombre.as_deref())
+}
+
+#[tauri::command]
+pub fn rest_confirmar_reserva(db: State<'_, Database>, reserva_id: i64) -> Result<(), String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::reservas::cambiar_estado(&conn, reserva_id, "CONFIRMADA")
+}
+
+/// Cancela la reserva; sus depósitos en HOLDING quedan DEVUELTO.
+#[tauri::command]
+pub fn rest_cancelar_reserva(db: State<'_, Database>, reserva_id: i64) -> Result<(), String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::reservas::cambiar_estado(&conn, reserva_id, "CANCELADA")
+}
+
+/// Marca no-show manual (sin esperar la tolerancia).
+#[tauri::command]
+pub fn rest_marcar_no_show(db: State<'_, Database>, reserva_id: i64) -> Result<(), String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::reservas::cambiar_estado(&conn, reserva_id, "NO_SHOW")
+}
+
+#[tauri::command]
+pub fn rest_registrar_deposito_reserva(
+    db: State<'_, Database>,
+    sesion: State<'_, crate::db::SesionState>,
+    reserva_id: i64,
+    deposito: super::reservas::NuevoDeposito,
+) -> Result<Vec<DepositoReserva>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let (usuario_id, usuario_nombre) = usuario_de_sesion(&sesion)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::reservas::registrar_deposito(&conn, reserva_id, &deposito, usuario_id, usuario_nombre.as_deref())?;
+    super::reservas::listar_depositos(&conn, reserva_id)
+}
+
+#[tauri::command]
+pub fn rest_listar_depositos_reserva(
+    db: State<'_, Database>,
+    reserva_id: i64,
+) -> Result<Vec<DepositoReserva>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::reservas::listar_depositos(&conn, reserva_id)
+}
+
+/// Sienta la reserva (en `mesa_id` o en la mesa reservada) y retorna el pedido abierto.
+/// El depósito pasa como abono del pedido.
+#[tauri::command]
+pub fn rest_sentar_reserva(
+    db: State<'_, Database>,
+    sesion: State<'_, crate::db::SesionState>,
+    reserva_id: i64,
+    mesa_id: Option<i64>,
+) -> Result<PedidoDetalle, String> {
+    requiere_modulo_restaurante(&db)?;
+    let (usuario_id, usuario_nombre) = usuario_de_sesion(&sesion)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let pedido_id =
+        super::reservas::sentar_reserva(&conn, reserva_id, mesa_id, (usuario_id, usuario_nombre.as_deref()))?;
+    obtener_pedido_detalle(&conn, pedido_id)
+}
+
+#[tauri::command]
+pub fn rest_reporte_no_shows(
+    db: State<'_, Database>,
+    fecha_desde: String,
+    fecha_hasta: String,
+) -> Result<Vec<NoShowCliente>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::reservas::reporte_no_shows(&conn, &fecha_desde, &fecha_hasta)
+}
+
+/// Lista de espera activa con espera estimada recalculada.
+#[tauri::command]
+pub fn rest_listar_espera(
+    db: State<'_, Database>,
+    incluir_cerradas: Option<bool>,
+) -> Result<Vec<EntradaEspera>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::reservas::listar_espera(&conn, incluir_cerradas.unwrap_or(false))
+}
+
+#[tauri::command]
+pub fn rest_agregar_espera(db: State<'_, Database>, entrada: EntradaEspera) -> Result<EntradaEspera, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::reservas::agregar_espera(&conn, &entrada)
+}
+
+/// estado: AVISADO | ABANDONO
+#[tauri::command]
+pub fn rest_actualizar_espera(db: State<'_, Database>, id: i64, estado: String) -> Result<(), String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::reservas::cambiar_estado_espera(&conn, id, &estado)
+}
+
+#[tauri::command]
+pub fn rest_sentar_espera(
+    db: State<'_, Database>,
+    sesion: State<'_, crate::db::SesionState>,
+    id: i64,
+    mesa_id: i64,
+) -> Result<PedidoDetalle, String> {
+    requiere_modulo_restaurante(&db)?;
+    let (usuario_id, usuario_nombre) = usuario_de_sesion(&sesion)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let pedido_id = super::reservas::sentar_espera(&conn, id, mesa_id, (usuario_id, usuario_nombre.as_deref()))?;
+    obtener_pedido_detalle(&conn, pedido_id)
 }
 
 // ─── Cuenta y cobro ──────────────────────────────────────────────────────
@@ -699,32 +1103,130 @@ pub fn rest_pedir_cuenta(db: State<'_, Database>, pedido_id: i64) -> Result<(),
 ///
 /// En Fase 2 del módulo agregaremos un comando combo `rest_cobrar_pedido_completo`
 /// que delega al comando de ventas existente.
+///
+/// `propina` (opcional) se registra aparte de la venta (ver `propinas`); va
+/// primero para que un error deje el pedido abierto y se pueda reintentar.
 #[tauri::command]
 pub fn rest_cerrar_pedido(
     db: State<'_, Database>,
     pedido_id: i64,
     venta_id: i64,
+    propina: Option<PropinaCobro>,
 ) -> Result<(), String> {
     requiere_modulo_restaurante(&db)?;
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    conn.execute(
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    // Propina, cierre e insumos de modificadores van juntos: si algo falla el
+    // pedido queda abierto y el POS puede reintentar con la misma venta.
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    if let Some(p) = &propina {
+        super::propinas::registrar(&tx, venta_id, pedido_id, None, p)?;
+    }
+    let ya_cobrado: bool = tx
+        .query_row(
+            "SELECT estado = 'COBRADO' FROM rest_pedidos_abiertos WHERE id = ?1",
+            params![pedido_id],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+    tx.execute(
         "UPDATE rest_pedidos_abiertos
          SET estado = 'COBRADO', venta_id = ?1, fecha_cierre = datetime('now', 'localtime')
          WHERE id = ?2",
         params![venta_id, pedido_id],
     )
     .map_err(|e| e.to_string())?;
+    // Insumos de modificadores (la venta ya descontó los productos en sí).
+    if !ya_cobrado {
+        let partes = partes_pedido(&tx, pedido_id)?;
+        super::modificadores::descontar_insumos(&tx, venta_id, &partes)?;
+    }
     // v2.5.91 — los abonos en HOLDING pasan a APLICADO (ya forman parte de la venta).
-    conn.execute(
+    tx.execute(
         "UPDATE rest_pedido_abonos
          SET estado = 'APLICADO', venta_id_aplicado = ?1, fecha_aplicado = datetime('now', 'localtime')
          WHERE pedido_id = ?2 AND estado = 'HOLDING'",
         params![venta_id, pedido_id],
     )
     .map_err(|e| e.to_string())?;
+    // Delivery contra entrega: este cobro es la liquidación del repartidor.
+    super::entregas::al_cobrar(&tx, pedido_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
+/// Cargo por servicio sugerido (base sin IVA × `rest_propina_servicio_pct`).
+#[tauri::command]
+pub fn rest_sugerir_propina(
+    db: State<'_, Database>,
+    pedido_id: i64,
+    subcuenta_id: Option<i64>,
+) -> Result<SugerenciaPropina, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::propinas::sugerir(&conn, pedido_id, subcuenta_id)
+}
+
+/// Propinas del período repartidas por turno y mesero según `rest_propinas_regla`.
+#[tauri::command]
+pub fn rest_reporte_propinas(
+    db: State<'_, Database>,
+    fecha_desde: String,
+    fecha_hasta: String,
+) -> Result<ReportePropinas, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::propinas::reporte(&conn, &fecha_desde, &fecha_hasta)
+}
+
+// ─── Analítica de salón ─────────────────────────────────────────────────
+
+/// Cubiertos por turno, rotación por zona, ventas por mesero y mapa de horas.
+#[tauri::command]
+pub fn rest_reporte_salon(
+    db: State<'_, Database>,
+    fecha_desde: String,
+    fecha_hasta: String,
+) -> Result<ReporteSalon, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::analitica::reporte(&conn, &fecha_desde, &fecha_hasta)
+}
+
+/// Exporta una sección del reporte de salón (TURNOS | ZONAS | MESEROS | HORAS)
+/// a XLSX o PDF con los exportadores genéricos de tablas.
+#[tauri::command]
+pub fn rest_exportar_reporte_salon(
+    db: State<'_, Database>,
+    fecha_desde: String,
+    fecha_hasta: String,
+    seccion: String,
+    formato: String,
+    ruta: String,
+) -> Result<(), String> {
+    requiere_modulo_restaurante(&db)?;
+    let reporte = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        super::analitica::reporte(&conn, &fecha_desde, &fecha_hasta)?
+    };
+    let t = super::analitica::tabla(&reporte, &seccion.trim().to_uppercase())?;
+    let subtitulo = Some(format!(
+        "Del {} al {} — {} pedidos, {} cubiertos, ${:.2} (${:.2} por cubierto)",
+        reporte.desde, reporte.hasta, reporte.pedidos, reporte.cubiertos, reporte.ventas, reporte.promedio_cubierto
+    ));
+    match formato.trim().to_uppercase().as_str() {
+        "XLSX" => crate::commands::exportar::exportar_tabla_xlsx(
+            ruta,
+            t.titulo,
+            subtitulo,
+            t.encabezados,
+            t.filas,
+            Some(t.columnas_numericas),
+        ),
+        "PDF" => crate::commands::exportar::exportar_tabla_pdf(ruta, t.titulo, subtitulo, t.encabezados, t.filas, Some(true)),
+        otro => Err(format!("Formato inválido: {} (use XLSX o PDF)", otro)),
+    }
+}
+
 /// v2.5.91 — Registra un abono (pago parcial) sobre una mesa desde el escritorio.
 /// Devuelve el detalle actualizado del pedido (con saldo/abonado).
 #[tauri::command]
@@ -797,7 +1299,11 @@ pub fn rest_listar_abonos_holding_caja(
 /// (Microsoft Print to PDF, OneNote, XPS, Fax). Esas no entienden ESC/POS y
 /// generan basura si les enviamos los bytes crudos — para esos casos hay que
 /// generar PDF nativo y abrirlo con el visor del sistema.
-fn impresora_es_virtual(nombre: &str) -> bool {
+pub(crate) fn impresora_es_virtual(nombre: &str) -> bool {
+    // tcp://, cups://, file:// → siempre térmica (ver `printing::destino`)
+    if crate::printing::destino::DestinoImpresora::parse(nombre).is_ok_and(|d| d.es_explicito()) {
+        return false;
+    }
     let lower = nombre.to_lowercase();
     lower.is_empty()
         || lower.contains("pdf")
@@ -888,7 +1394,7 @@ pub fn rest_imprimir_pre_cuenta(
 
     // Caso 2: impresora térmica real → ESC/POS
     let ticket = super::printing::generar_pre_cuenta(&detalle, &config);
-    crate::printing::imprimir_raw_windows(&impresora, &ticket)?;
+    crate::printing::imprimir_raw(&impresora, &ticket)?;
 
     Ok("Pre-cuenta impresa".to_string())
 }
@@ -906,6 +1412,10 @@ pub fn rest_imprimir_pre_cuenta(
 /// Si es None/vacío, imprime TODOS los items pendientes del pedido (re-imprimir).
 ///
 /// Items DIRECTO (despacho directo, ej. bebidas embotelladas) NUNCA se imprimen.
+///
+/// Si hay estaciones activas (`rest_estaciones`) se ignora lo anterior: se
+/// imprime una comanda por estación con respaldo automático (ver
+/// [`super::estaciones`]).
 #[tauri::command]
 pub fn rest_imprimir_comanda_cocina(
     db: State<'_, Database>,
@@ -913,6 +1423,35 @@ pub fn rest_imprimir_comanda_cocina(
     items_ids: Option<Vec<i64>>,
 ) -> Result<String, String> {
     requiere_modulo_restaurante(&db)?;
+
+    let ids_estaciones = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        if super::estaciones::hay_estaciones(&conn) {
+            match items_ids.as_ref() {
+                Some(ids) => Some(ids.clone()),
+                None => Some(
+                    obtener_pedido_detalle(&conn, pedido_id)?
+                        .items
+                        .iter()
+                        .filter_map(|i| i.id)
+                        .collect::<Vec<i64>>(),
+                ),
+            }
+        } else {
+            None
+        }
+    };
+    if let Some(ids) = ids_estaciones {
+        if ids.is_empty() {
+            return Err("No se especificaron items para imprimir".to_string());
+        }
+        let resultados = super::estaciones::imprimir_por_estacion(&db, pedido_id, &ids)?;
+        if resultados.is_empty() {
+            return Err("No hay items para imprimir comanda".to_string());
+        }
+        return Ok(resultados.iter().map(|r| r.mensaje()).collect::<Vec<_>>().join(" · "));
+    }
+
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
     let detalle = obtener_pedido_detalle(&conn, pedido_id)?;
@@ -1011,31 +1550,339 @@ pub fn rest_imprimir_comanda_cocina(
             ) {
                 None => Ok(None),
                 Some(t) => {
-                    crate::printing::imprimir_raw_windows(impresora, &t)?;
+                    crate::printing::imprimir_raw(impresora, &t)?;
                     Ok(Some(format!("{} impresa", etiqueta)))
                 }
             }
         }
     };
 
-    if modo_separado {
-        if let Some(m) = imprimir_o_pdf(&impresora_cocina_efectiva, super::printing::DestinoComanda::Cocina, "🍳 Cocina")? {
-            mensajes.push(m);
-        }
-        if let Some(m) = imprimir_o_pdf(&impresora_barra, super::printing::DestinoComanda::Barra, "🍷 Barra")? {
-            mensajes.push(m);
+    if modo_separado {
+        if let Some(m) = imprimir_o_pdf(&impresora_cocina_efectiva, super::printing::DestinoComanda::Cocina, "🍳 Cocina")? {
+            mensajes.push(m);
+        }
+        if let Some(m) = imprimir_o_pdf(&impresora_barra, super::printing::DestinoComanda::Barra, "🍷 Barra")? {
+            mensajes.push(m);
+        }
+    } else {
+        if let Some(m) = imprimir_o_pdf(&impresora_cocina_efectiva, super::printing::DestinoComanda::Ambos, "🍽 Comanda")? {
+            mensajes.push(m);
+        }
+    }
+
+    if mensajes.is_empty() {
+        Ok("Sin items para cocina (todos despacho directo)".to_string())
+    } else {
+        Ok(mensajes.join(" + "))
+    }
+}
+
+// ─── Modificadores de producto ──────────────────────────────────────────
+
+#[tauri::command]
+pub fn rest_listar_grupos_modificadores(
+    db: State<'_, Database>,
+    incluir_inactivos: Option<bool>,
+) -> Result<Vec<GrupoModificadores>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::modificadores::listar_grupos(&conn, None, !incluir_inactivos.unwrap_or(false))
+}
+
+/// Grupos activos de un producto — el POS / app móvil los muestra al agregar el item.
+#[tauri::command]
+pub fn rest_modificadores_producto(
+    db: State<'_, Database>,
+    producto_id: i64,
+) -> Result<Vec<GrupoModificadores>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::modificadores::listar_grupos(&conn, Some(producto_id), true)
+}
+
+/// Crea o actualiza un grupo con sus opciones. Las opciones que ya no vienen
+/// se desactivan (no se borran: los pedidos guardan snapshot, pero así el
+/// historial de ids sigue siendo consultable).
+#[tauri::command]
+pub fn rest_guardar_grupo_modificadores(
+    db: State<'_, Database>,
+    grupo: GrupoModificadores,
+) -> Result<i64, String> {
+    requiere_modulo_restaurante(&db)?;
+    let nombre = grupo.nombre.trim();
+    if nombre.is_empty() {
+        return Err("El nombre del grupo es obligatorio".to_string());
+    }
+    if grupo.min_selecciones < 0 || grupo.max_selecciones < 0 {
+        return Err("Mínimo y máximo no pueden ser negativos".to_string());
+    }
+    if grupo.max_selecciones > 0 && grupo.min_selecciones > grupo.max_selecciones {
+        return Err("El mínimo de selecciones no puede superar al máximo".to_string());
+    }
+    if let Some(op) = grupo.opciones.iter().find(|o| o.nombre.trim().is_empty()) {
+        return Err(format!("Hay una opción sin nombre (precio {:.2})", op.precio_delta));
+    }
+    if grupo.opciones.iter().any(|o| o.producto_insumo_id.is_some() && o.cantidad_insumo <= 0.0) {
+        return Err("Indique la cantidad de insumo que consume cada opción con insumo".to_string());
+    }
+
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let grupo_id = match grupo.id {
+        Some(id) => {
+            let filas = tx
+                .execute(
+                    "UPDATE rest_modificador_grupos
+                     SET nombre = ?1, min_selecciones = ?2, max_selecciones = ?3, orden = ?4, activo = ?5
+                     WHERE id = ?6",
+                    params![nombre, grupo.min_selecciones, grupo.max_selecciones, grupo.orden, grupo.activo as i32, id],
+                )
+                .map_err(|e| e.to_string())?;
+            if filas == 0 {
+                return Err("Grupo no encontrado".to_string());
+            }
+            id
+        }
+        None => {
+            tx.execute(
+                "INSERT INTO rest_modificador_grupos (nombre, min_selecciones, max_selecciones, orden, activo)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![nombre, grupo.min_selecciones, grupo.max_selecciones, grupo.orden, grupo.activo as i32],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.last_insert_rowid()
+        }
+    };
+
+    let mut vigentes: Vec<i64> = Vec::new();
+    for op in &grupo.opciones {
+        let actualizada = match op.id {
+            Some(id) => tx
+                .execute(
+                    "UPDATE rest_modificadores
+                     SET nombre = ?1, precio_delta = ?2, producto_insumo_id = ?3, cantidad_insumo = ?4,
+                         orden = ?5, activo = ?6
+                     WHERE id = ?7 AND grupo_id = ?8",
+                    params![
+                        op.nombre.trim(),
+                        op.precio_delta,
+                        op.producto_insumo_id,
+                        op.cantidad_insumo,
+                        op.orden,
+                        op.activo as i32,
+                        id,
+                        grupo_id
+                    ],
+                )
+                .map_err(|e| e.to_string())?
+                > 0,
+            None => false,
+        };
+        if actualizada {
+            vigentes.push(op.id.unwrap_or_default());
+        } else {
+            tx.execute(
+                "INSERT INTO rest_modificadores
+                 (grupo_id, nombre, precio_delta, producto_insumo_id, cantidad_insumo, orden, activo)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    grupo_id,
+                    op.nombre.trim(),
+                    op.precio_delta,
+                    op.producto_insumo_id,
+                    op.cantidad_insumo,
+                    op.orden,
+                    op.activo as i32
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            vigentes.push(tx.last_insert_rowid());
+        }
+    }
+    let vigentes_json = serde_json::to_string(&vigentes).map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE rest_modificadores SET activo = 0
+         WHERE grupo_id = ?1 AND id NOT IN (SELECT value FROM json_each(?2))",
+        params![grupo_id, vigentes_json],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(grupo_id)
+}
+
+/// Desactiva el grupo: deja de ofrecerse en todos los productos.
+#[tauri::command]
+pub fn rest_eliminar_grupo_modificadores(db: State<'_, Database>, id: i64) -> Result<(), String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    conn.execute("UPDATE rest_modificador_grupos SET activo = 0 WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Reemplaza los grupos de modificadores de un producto (en el orden recibido).
+#[tauri::command]
+pub fn rest_asignar_modificadores_producto(
+    db: State<'_, Database>,
+    producto_id: i64,
+    grupos_ids: Vec<i64>,
+) -> Result<(), String> {
+    requiere_modulo_restaurante(&db)?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM rest_producto_modificador_grupos WHERE producto_id = ?1",
+        params![producto_id],
+    )
+    .map_err(|e| e.to_string())?;
+    for (orden, grupo_id) in grupos_ids.iter().enumerate() {
+        tx.execute(
+            "INSERT OR IGNORE INTO rest_producto_modificador_grupos (producto_id, grupo_id, orden)
+             VALUES (?1, ?2, ?3)",
+            params![producto_id, grupo_id, orden as i32],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// ─── Estaciones de preparación ──────────────────────────────────────────
+
+#[tauri::command]
+pub fn rest_listar_estaciones(db: State<'_, Database>) -> Result<Vec<Estacion>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::estaciones::listar(&conn)
+}
+
+/// Crea (`id = None`) o actualiza una estación con sus categorías. Una
+/// categoría asignada a otra estación se mueve a esta.
+#[tauri::command]
+pub fn rest_guardar_estacion(db: State<'_, Database>, estacion: Estacion) -> Result<i64, String> {
+    requiere_modulo_restaurante(&db)?;
+    let nombre = estacion.nombre.trim();
+    if nombre.is_empty() {
+        return Err("El nombre de la estación es obligatorio".to_string());
+    }
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let id = match estacion.id {
+        Some(id) => {
+            let filas = tx
+                .execute(
+                    "UPDATE rest_estaciones SET nombre = ?1, impresora = ?2, impresora_respaldo = ?3,
+                            orden = ?4, activa = ?5
+                     WHERE id = ?6",
+                    params![
+                        nombre,
+                        estacion.impresora.trim(),
+                        estacion.impresora_respaldo.trim(),
+                        estacion.orden,
+                        estacion.activa as i32,
+                        id
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            if filas == 0 {
+                return Err("Estación no encontrada".to_string());
+            }
+            id
         }
-    } else {
-        if let Some(m) = imprimir_o_pdf(&impresora_cocina_efectiva, super::printing::DestinoComanda::Ambos, "🍽 Comanda")? {
-            mensajes.push(m);
+        None => {
+            tx.execute(
+                "INSERT INTO rest_estaciones (nombre, impresora, impresora_respaldo, orden, activa)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    nombre,
+                    estacion.impresora.trim(),
+                    estacion.impresora_respaldo.trim(),
+                    estacion.orden,
+                    estacion.activa as i32
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.last_insert_rowid()
         }
+    };
+    tx.execute("DELETE FROM rest_estacion_categorias WHERE estacion_id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    for categoria_id in &estacion.categorias {
+        tx.execute(
+            "INSERT OR REPLACE INTO rest_estacion_categorias (categoria_id, estacion_id) VALUES (?1, ?2)",
+            params![categoria_id, id],
+        )
+        .map_err(|e| e.to_string())?;
     }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(id)
+}
 
-    if mensajes.is_empty() {
-        Ok("Sin items para cocina (todos despacho directo)".to_string())
-    } else {
-        Ok(mensajes.join(" + "))
+/// Elimina la estación. Sus categorías vuelven a la comanda "General"; el
+/// historial de comandas conserva el nombre.
+#[tauri::command]
+pub fn rest_eliminar_estacion(db: State<'_, Database>, id: i64) -> Result<(), String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM rest_estacion_categorias WHERE estacion_id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM rest_estaciones WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Crea una pantalla de cocina (KDS web) a nombre del usuario en sesión y
+/// devuelve la URL para abrir en el navegador de la TV / mini PC. Se revoca
+/// desde App Móvil → Dispositivos como cualquier otro token.
+#[tauri::command]
+pub fn rest_crear_pantalla_kds(
+    db: State<'_, Database>,
+    sesion: State<'_, crate::db::SesionState>,
+    nombre: String,
+    estacion_id: Option<i64>,
+) -> Result<PantallaKds, String> {
+    requiere_modulo_restaurante(&db)?;
+    let usuario_id = sesion
+        .con_permiso("ve_cocina")?
+        .map(|s| s.usuario_id)
+        .ok_or_else(|| "Inicie sesión para crear la pantalla".to_string())?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let (id, token) = super::kds::crear_pantalla(&conn, usuario_id, &nombre, estacion_id)?;
+    let port: u16 = conn
+        .query_row("SELECT value FROM config WHERE key = 'servidor_puerto'", [], |r| r.get::<_, String>(0))
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(8847);
+    let url = crate::app_movil::discovery::obtener_ip_local()
+        .map(|ip| format!("http://{}:{}/kds?token={}", ip, port, token));
+    Ok(PantallaKds { id, token, url })
+}
+
+/// Comandas impresas por estación. `solo_fallidas` alimenta el aviso
+/// "comandas sin imprimir" del POS.
+#[tauri::command]
+pub fn rest_listar_comandas(
+    db: State<'_, Database>,
+    pedido_id: Option<i64>,
+    solo_fallidas: Option<bool>,
+) -> Result<Vec<Comanda>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    super::estaciones::listar_comandas(&conn, pedido_id, solo_fallidas.unwrap_or(false))
+}
+
+/// Reimprime una comanda puntual (misma estación, mismos items).
+#[tauri::command]
+pub fn rest_reimprimir_comanda(db: State<'_, Database>, comanda_id: i64) -> Result<String, String> {
+    requiere_modulo_restaurante(&db)?;
+    let resultado = super::estaciones::reimprimir(&db, comanda_id)?;
+    if resultado.estado == "FALLIDA" {
+        return Err(format!(
+            "No se pudo reimprimir: {}",
+            resultado.error.unwrap_or_default()
+        ));
     }
+    Ok(resultado.mensaje())
 }
 
 // ─── v2.3.68 — Unir mesas ────────────────────────────────────────────────
@@ -1320,6 +2167,234 @@ pub fn rest_dividir_cuenta(
     listar_subcuentas_internal(&conn, pedido_id)
 }
 
+/// Divide el pedido por CONSUMO: cada sub-cuenta paga sus propios items (o
+/// una fracción de un item compartido) y al cobrarla se genera una venta con
+/// los productos reales — IVA por tarifa y descuento de stock correctos.
+///
+/// Todos los items del pedido deben quedar asignados por completo. Cuando un
+/// item se reparte en fracciones, la última parte absorbe el residuo para
+/// que la suma de cantidades sea exactamente la del item.
+#[tauri::command]
+pub fn rest_dividir_cuenta_por_items(
+    db: State<'_, Database>,
+    pedido_id: i64,
+    asignaciones: Vec<AsignacionSubcuenta>,
+) -> Result<Vec<Subcuenta>, String> {
+    requiere_modulo_restaurante(&db)?;
+    if !(2..=20).contains(&asignaciones.len()) {
+        return Err("El número de sub-cuentas debe ser entre 2 y 20".to_string());
+    }
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let estado: String = conn
+        .query_row(
+            "SELECT estado FROM rest_pedidos_abiertos WHERE id = ?1",
+            params![pedido_id],
+            |row| row.get(0),
+        )
+        .map_err(|_| "Pedido no encontrado".to_string())?;
+    if estado != "ABIERTO" && estado != "CUENTA_PEDIDA" {
+        return Err(format!("No se puede dividir un pedido {}", estado));
+    }
+    let existentes: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM rest_subcuentas WHERE pedido_id = ?1",
+            params![pedido_id],
+            |row| row.get(0),
+        )
+        .unwrap_or(0);
+    if existentes > 0 {
+        return Err("Este pedido ya está dividido. Cancele la división actual primero.".to_string());
+    }
+
+    // (id, nombre, cantidad, precio_unit) de cada item del pedido
+    let items_pedido: Vec<(i64, String, f64, f64)> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT i.id, p.nombre, i.cantidad, i.precio_unit
+                 FROM rest_pedido_items i JOIN productos p ON p.id = i.producto_id
+                 WHERE i.pedido_id = ?1 ORDER BY i.id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![pedido_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        rows
+    };
+    if items_pedido.is_empty() {
+        return Err("El pedido no tiene items para dividir".to_string());
+    }
+
+    // Cantidad asignada por (sub-cuenta, item), en el orden recibido
+    let mut partes: Vec<Vec<(i64, f64)>> = vec![Vec::new(); asignaciones.len()];
+    for (idx, asig) in asignaciones.iter().enumerate() {
+        if asig.items.is_empty() {
+            return Err(format!("La sub-cuenta {} no tiene items asignados", idx + 1));
+        }
+        for parte in &asig.items {
+            let (_, nombre, cant_item, _) = items_pedido
+                .iter()
+                .find(|(id, ..)| *id == parte.pedido_item_id)
+                .ok_or_else(|| format!("El item {} no pertenece a este pedido", parte.pedido_item_id))?;
+            let cantidad = match (parte.cantidad, parte.fraccion) {
+                (Some(c), None) => c,
+                (None, Some(f)) => cant_item * f,
+                _ => return Err(format!("Indique cantidad o fracción (no ambas) para '{}'", nombre)),
+            };
+            if cantidad <= 0.0 {
+                return Err(format!("Cantidad inválida para '{}'", nombre));
+            }
+            partes[idx].push((parte.pedido_item_id, cantidad));
+        }
+    }
+
+    // Cada item debe quedar cubierto exactamente; la última parte absorbe el residuo.
+    for (item_id, nombre, cant_item, _) in &items_pedido {
+        let posiciones: Vec<(usize, usize)> = partes
+            .iter()
+            .enumerate()
+            .flat_map(|(s, ps)| {
+                ps.iter()
+                    .enumerate()
+                    .filter(|(_, (id, _))| id == item_id)
+                    .map(move |(p, _)| (s, p))
+            })
+            .collect();
+        let asignado: f64 = posiciones.iter().map(|&(s, p)| partes[s][p].1).sum();
+        if (asignado - cant_item).abs() > 0.001 {
+            return Err(format!(
+                "'{}': asignado {:.3} de {:.3}. Reparta el item completo entre las sub-cuentas.",
+                nombre, asignado, cant_item
+            ));
+        }
+        if let Some(&(s, p)) = posiciones.last() {
+            let resto: f64 = posiciones[..posiciones.len() - 1]
+                .iter()
+                .map(|&(s, p)| partes[s][p].1)
+                .sum();
+            partes[s][p].1 = cant_item - resto;
+        }
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for (idx, (asig, ps)) in asignaciones.iter().zip(&partes).enumerate() {
+        let total: f64 = ps
+            .iter()
+            .map(|(item_id, cant)| {
+                let precio = items_pedido.iter().find(|(id, ..)| id == item_id).map(|i| i.3).unwrap_or(0.0);
+                cant * precio
+            })
+            .sum();
+        tx.execute(
+            "INSERT INTO rest_subcuentas (pedido_id, numero, total, modo, cliente_id)
+             VALUES (?1, ?2, ?3, 'ITEMS', ?4)",
+            params![pedido_id, (idx + 1) as i32, r2(total), asig.cliente_id],
+        )
+        .map_err(|e| e.to_string())?;
+        let subcuenta_id = tx.last_insert_rowid();
+        for (item_id, cant) in ps {
+            tx.execute(
+                "INSERT INTO rest_subcuenta_items (subcuenta_id, pedido_item_id, cantidad) VALUES (?1, ?2, ?3)",
+                params![subcuenta_id, item_id, (cant * 1_000_000.0).round() / 1_000_000.0],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    listar_subcuentas_internal(&conn, pedido_id)
+}
+
+/// Cambia el cliente al que se factura una sub-cuenta pendiente.
+#[tauri::command]
+pub fn rest_asignar_cliente_subcuenta(
+    db: State<'_, Database>,
+    subcuenta_id: i64,
+    cliente_id: Option<i64>,
+) -> Result<(), String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let filas = conn
+        .execute(
+            "UPDATE rest_subcuentas SET cliente_id = ?1 WHERE id = ?2 AND estado = 'PENDIENTE'",
+            params![cliente_id, subcuenta_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if filas == 0 {
+        return Err("Sub-cuenta no encontrada o ya cobrada".to_string());
+    }
+    Ok(())
+}
+
+/// Líneas de venta para cobrar una sub-cuenta. El POS las pasa tal cual a
+/// `registrar_venta` (con el `cliente_id` devuelto) y luego llama a
+/// `rest_marcar_subcuenta_cobrada` con la venta generada. Antes de registrar
+/// la venta las valida con `rest_validar_venta_subcuenta`.
+///
+/// - Modo ITEMS: productos reales. `precio_unitario` va SIN IVA (si el
+///   producto lo incluye se desglosa) para que la venta calcule la tarifa.
+/// - Modo MONTO: una línea del producto `_DIVISION_CUENTA_` por el total.
+#[tauri::command]
+pub fn rest_venta_subcuenta(db: State<'_, Database>, subcuenta_id: i64) -> Result<VentaSubcuenta, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let (modo, cliente_id, total, estado): (String, Option<i64>, f64, String) = conn
+        .query_row(
+            "SELECT modo, cliente_id, total, estado FROM rest_subcuentas WHERE id = ?1",
+            params![subcuenta_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .map_err(|_| "Sub-cuenta no encontrada".to_string())?;
+    if estado == "COBRADA" {
+        return Err("Esta sub-cuenta ya fue cobrada".to_string());
+    }
+
+    let items: Vec<crate::models::VentaDetalle> = if modo == "ITEMS" {
+        lineas_venta_items(&conn, &partes_subcuenta(&conn, subcuenta_id)?)?
+    } else {
+        let producto_id: i64 = conn
+            .query_row("SELECT id FROM productos WHERE codigo = '_DIVISION_CUENTA_'", [], |r| r.get(0))
+            .map_err(|_| "Producto especial _DIVISION_CUENTA_ no encontrado. Reinicia la app.".to_string())?;
+        vec![crate::models::VentaDetalle {
+            producto_id: Some(producto_id),
+            nombre_producto: Some("Cuota Mesa Restaurante".to_string()),
+            cantidad: 1.0,
+            precio_unitario: total,
+            subtotal: total,
+            ..Default::default()
+        }]
+    };
+
+    Ok(VentaSubcuenta { subcuenta_id, cliente_id, items })
+}
+
+/// Valida las líneas de una sub-cuenta ANTES de `registrar_venta`, para no
+/// dejar una venta huérfana si luego `rest_marcar_subcuenta_cobrada` la rechaza.
+#[tauri::command]
+pub fn rest_validar_venta_subcuenta(
+    db: State<'_, Database>,
+    subcuenta_id: i64,
+    items: Vec<crate::models::VentaDetalle>,
+) -> Result<(), String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    validar_cobro_subcuenta(&conn, subcuenta_id, &items)
+}
+
+/// Líneas de venta del pedido completo (cobro sin división): productos
+/// reales con precio sin IVA y modificadores en `info_adicional`.
+#[tauri::command]
+pub fn rest_venta_pedido(
+    db: State<'_, Database>,
+    pedido_id: i64,
+) -> Result<Vec<crate::models::VentaDetalle>, String> {
+    requiere_modulo_restaurante(&db)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    lineas_venta_items(&conn, &partes_pedido(&conn, pedido_id)?)
+}
+
 /// Lista las sub-cuentas asociadas al pedido (con datos de banco y venta JOIN).
 /// Vacío si el pedido no está dividido.
 #[tauri::command]
@@ -1356,6 +2431,12 @@ pub fn rest_cancelar_division(
         ));
     }
 
+    conn.execute(
+        "DELETE FROM rest_subcuenta_items
+         WHERE subcuenta_id IN (SELECT id FROM rest_subcuentas WHERE pedido_id = ?1)",
+        params![pedido_id],
+    )
+    .map_err(|e| e.to_string())?;
     conn.execute(
         "DELETE FROM rest_subcuentas WHERE pedido_id = ?1",
         params![pedido_id],
@@ -1379,23 +2460,44 @@ pub fn rest_marcar_subcuenta_cobrada(
     forma_pago: String,
     banco_id: Option<i64>,
     referencia_pago: Option<String>,
+    propina: Option<PropinaCobro>,
 ) -> Result<ResultadoCobroSubcuenta, String> {
     requiere_modulo_restaurante(&db)?;
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
 
     // Validar que exista y esté pendiente
-    let (pedido_id, estado): (i64, String) = conn
+    let (pedido_id, estado, modo): (i64, String, String) = tx
         .query_row(
-            "SELECT pedido_id, estado FROM rest_subcuentas WHERE id = ?1",
+            "SELECT pedido_id, estado, modo FROM rest_subcuentas WHERE id = ?1",
             params![subcuenta_id],
-            |row| Ok((row.get(0)?, row.get(1)?)),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
         )
         .map_err(|_| "Sub-cuenta no encontrada".to_string())?;
     if estado == "COBRADA" {
         return Err("Esta sub-cuenta ya fue cobrada".to_string());
     }
+    let otra: Option<i64> = tx
+        .query_row(
+            "SELECT numero FROM rest_subcuentas WHERE venta_id = ?1 AND id != ?2 LIMIT 1",
+            params![venta_id, subcuenta_id],
+            |row| row.get(0),
+        )
+        .ok();
+    if let Some(numero) = otra {
+        return Err(format!("La venta ya se usó para cobrar la sub-cuenta {}", numero));
+    }
+    // División por items: la venta debe llevar exactamente los productos
+    // asignados a la sub-cuenta (ver `rest_venta_subcuenta`), no la cuota
+    // plana ni otros productos, o el stock no cuadra.
+    if modo == "ITEMS" {
+        validar_venta_items(&tx, venta_id, &partes_subcuenta(&tx, subcuenta_id)?)?;
+    }
+    if let Some(p) = &propina {
+        super::propinas::registrar(&tx, venta_id, pedido_id, Some(subcuenta_id), p)?;
+    }
 
-    conn.execute(
+    tx.execute(
         "UPDATE rest_subcuentas
          SET estado = 'COBRADA', forma_pago = ?1, banco_id = ?2, referencia_pago = ?3,
              venta_id = ?4, fecha_cobro = datetime('now', 'localtime')
@@ -1403,9 +2505,13 @@ pub fn rest_marcar_subcuenta_cobrada(
         params![forma_pago, banco_id, referencia_pago, venta_id, subcuenta_id],
     )
     .map_err(|e| e.to_string())?;
+    if modo == "ITEMS" {
+        let partes = partes_subcuenta(&tx, subcuenta_id)?;
+        super::modificadores::descontar_insumos(&tx, venta_id, &partes)?;
+    }
 
     // ¿Quedan pendientes?
-    let pendientes: i32 = conn
+    let pendientes: i32 = tx
         .query_row(
             "SELECT COUNT(*) FROM rest_subcuentas WHERE pedido_id = ?1 AND estado = 'PENDIENTE'",
             params![pedido_id],
@@ -1418,7 +2524,7 @@ pub fn rest_marcar_subcuenta_cobrada(
     if todas_cobradas {
         // Vincular el pedido con la venta de la PRIMERA sub-cuenta cobrada
         // (cualquiera sirve como ancla — VentasDia mostrará todas independientes).
-        let primera_venta_id: i64 = conn
+        let primera_venta_id: i64 = tx
             .query_row(
                 "SELECT venta_id FROM rest_subcuentas
                  WHERE pedido_id = ?1 AND venta_id IS NOT NULL
@@ -1428,14 +2534,16 @@ pub fn rest_marcar_subcuenta_cobrada(
             )
             .unwrap_or(venta_id);
 
-        conn.execute(
+        tx.execute(
             "UPDATE rest_pedidos_abiertos
              SET estado = 'COBRADO', venta_id = ?1, fecha_cierre = datetime('now', 'localtime')
              WHERE id = ?2",
             params![primera_venta_id, pedido_id],
         )
         .map_err(|e| e.to_string())?;
+        super::entregas::al_cobrar(&tx, pedido_id)?;
     }
+    tx.commit().map_err(|e| e.to_string())?;
 
     Ok(ResultadoCobroSubcuenta {
         todas_cobradas,
@@ -1499,7 +2607,7 @@ pub(crate) fn obtener_pedido_detalle(conn: &Connection, pedido_id: i64) -> Resul
             "SELECT i.id, i.pedido_id, i.producto_id, p.nombre, i.cantidad, i.precio_unit,
                     i.info_adicional, i.enviado_cocina, i.estado_cocina,
                     i.fecha_creacion, i.fecha_envio_cocina,
-                    COALESCE(p.destino_preparacion, 'COCINA') as destino
+                    COALESCE(p.destino_preparacion, 'COCINA') as destino, i.modificadores, i.curso
              FROM rest_pedido_items i
              JOIN productos p ON i.producto_id = p.id
              WHERE i.pedido_id = ?1
@@ -1521,6 +2629,8 @@ pub(crate) fn obtener_pedido_detalle(conn: &Connection, pedido_id: i64) -> Resul
                 fecha_creacion: row.get(9)?,
                 fecha_envio_cocina: row.get(10)?,
                 destino_preparacion: row.get(11)?,
+                modificadores: row.get(12)?,
+                curso: row.get(13)?,
             })
         })
         .map_err(|e| e.to_string())?
@@ -1670,6 +2780,149 @@ pub fn registrar_abono_pedido(
 #[inline]
 fn r2(n: f64) -> f64 { (n * 100.0).round() / 100.0 }
 
+/// Líneas de venta con los productos reales para `(pedido_item_id, cantidad)`.
+/// `precio_unitario` va SIN IVA (si el producto lo incluye se desglosa) y los
+/// modificadores van en `info_adicional` junto a la observación.
+pub(crate) fn lineas_venta_items(conn: &Connection, partes: &[(i64, f64)]) -> Result<Vec<crate::models::VentaDetalle>, String> {
+    let mut lineas = Vec::with_capacity(partes.len());
+    for (item_id, cantidad) in partes {
+        let (producto_id, nombre, precio, iva, incluye_iva, info, mods): (
+            i64,
+            String,
+            f64,
+            f64,
+            bool,
+            Option<String>,
+            Option<String>,
+        ) = conn
+            .query_row(
+                "SELECT p.id, p.nombre, i.precio_unit, p.iva_porcentaje, COALESCE(p.incluye_iva, 0) != 0,
+                        i.info_adicional, i.modificadores
+                 FROM rest_pedido_items i JOIN productos p ON p.id = i.producto_id
+                 WHERE i.id = ?1",
+                params![item_id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?, r.get(6)?)),
+            )
+            .map_err(|_| format!("Item {} no encontrado", item_id))?;
+        let precio_sin_iva = if incluye_iva && iva > 0.0 {
+            ((precio / (1.0 + iva / 100.0)) * 1_000_000.0).round() / 1_000_000.0
+        } else {
+            precio
+        };
+        lineas.push(crate::models::VentaDetalle {
+            producto_id: Some(producto_id),
+            nombre_producto: Some(nombre),
+            cantidad: *cantidad,
+            precio_unitario: precio_sin_iva,
+            iva_porcentaje: iva,
+            subtotal: r2(cantidad * precio_sin_iva),
+            info_adicional: super::modificadores::info_venta(mods.as_deref(), info.as_deref()),
+            ..Default::default()
+        });
+    }
+    Ok(lineas)
+}
+
+/// Porción de items de una sub-cuenta (modo ITEMS) como `(pedido_item_id, cantidad)`.
+pub(crate) fn partes_subcuenta(conn: &Connection, subcuenta_id: i64) -> Result<Vec<(i64, f64)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT pedido_item_id, cantidad FROM rest_subcuenta_items WHERE subcuenta_id = ?1 ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let partes = stmt
+        .query_map(params![subcuenta_id], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(partes)
+}
+
+/// Items completos del pedido como `(pedido_item_id, cantidad)`.
+pub(crate) fn partes_pedido(conn: &Connection, pedido_id: i64) -> Result<Vec<(i64, f64)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, cantidad FROM rest_pedido_items WHERE pedido_id = ?1 ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let partes = stmt
+        .query_map(params![pedido_id], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(partes)
+}
+
+/// Verifica, antes de registrar la venta, que la sub-cuenta siga pendiente y
+/// que en división por items las líneas lleven exactamente sus productos.
+pub(crate) fn validar_cobro_subcuenta(
+    conn: &Connection,
+    subcuenta_id: i64,
+    items: &[crate::models::VentaDetalle],
+) -> Result<(), String> {
+    let (estado, modo): (String, String) = conn
+        .query_row(
+            "SELECT estado, modo FROM rest_subcuentas WHERE id = ?1",
+            params![subcuenta_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .map_err(|_| "Sub-cuenta no encontrada".to_string())?;
+    if estado == "COBRADA" {
+        return Err("Esta sub-cuenta ya fue cobrada".to_string());
+    }
+    if modo == "ITEMS" {
+        let mut vendido: std::collections::HashMap<i64, f64> = std::collections::HashMap::new();
+        for l in items {
+            *vendido.entry(l.producto_id.unwrap_or(0)).or_insert(0.0) += l.cantidad;
+        }
+        cuadra_items(conn, &vendido, &partes_subcuenta(conn, subcuenta_id)?)?;
+    }
+    Ok(())
+}
+
+/// Verifica que la venta lleve, por producto, las mismas cantidades que las
+/// `partes` de la división por items.
+fn validar_venta_items(conn: &Connection, venta_id: i64, partes: &[(i64, f64)]) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT COALESCE(producto_id, 0), SUM(cantidad) FROM venta_detalles
+             WHERE venta_id = ?1 GROUP BY COALESCE(producto_id, 0)",
+        )
+        .map_err(|e| e.to_string())?;
+    let vendido: std::collections::HashMap<i64, f64> = stmt
+        .query_map(params![venta_id], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    cuadra_items(conn, &vendido, partes)
+}
+
+fn cuadra_items(
+    conn: &Connection,
+    vendido: &std::collections::HashMap<i64, f64>,
+    partes: &[(i64, f64)],
+) -> Result<(), String> {
+    let mut esperado: std::collections::HashMap<i64, f64> = std::collections::HashMap::new();
+    for l in lineas_venta_items(conn, partes)? {
+        *esperado.entry(l.producto_id.unwrap_or(0)).or_insert(0.0) += l.cantidad;
+    }
+    let cuadra = vendido.len() == esperado.len()
+        && esperado
+            .iter()
+            .all(|(pid, cant)| vendido.get(pid).is_some_and(|v| (v - cant).abs() < 0.0001));
+    if !cuadra {
+        return Err("Esta sub-cuenta se divide por items: la venta debe llevar exactamente sus productos".to_string());
+    }
+    Ok(())
+}
+
+/// `true` si el item está asignado a alguna sub-cuenta (división por items).
+fn item_en_division_por_items(conn: &Connection, item_id: i64) -> bool {
+    conn.query_row(
+        "SELECT COUNT(*) FROM rest_subcuenta_items WHERE pedido_item_id = ?1",
+        params![item_id],
+        |row| row.get::<_, i64>(0),
+    )
+    .unwrap_or(0)
+        > 0
+}
+
 /// v2.3.69 — Lista las sub-cuentas del pedido con datos enriquecidos
 /// (nombre del banco, número de venta) — usado por `rest_listar_subcuentas`
 /// y por `rest_dividir_cuenta` (devuelve las recién creadas).
@@ -1679,15 +2932,16 @@ pub(crate) fn listar_subcuentas_internal(conn: &Connection, pedido_id: i64) -> R
             "SELECT s.id, s.pedido_id, s.numero, s.total, s.estado,
                     s.forma_pago, s.banco_id, b.nombre AS banco_nombre,
                     s.referencia_pago, s.venta_id, v.numero AS venta_numero,
-                    s.fecha_cobro
+                    s.fecha_cobro, s.modo, s.cliente_id, c.nombre AS cliente_nombre
              FROM rest_subcuentas s
              LEFT JOIN cuentas_banco b ON s.banco_id = b.id
              LEFT JOIN ventas v ON s.venta_id = v.id
+             LEFT JOIN clientes c ON s.cliente_id = c.id
              WHERE s.pedido_id = ?1
              ORDER BY s.numero",
         )
         .map_err(|e| e.to_string())?;
-    let subs: Vec<Subcuenta> = stmt
+    let mut subs: Vec<Subcuenta> = stmt
         .query_map(params![pedido_id], |row| {
             Ok(Subcuenta {
                 id: row.get(0)?,
@@ -1702,10 +2956,48 @@ pub(crate) fn listar_subcuentas_internal(conn: &Connection, pedido_id: i64) -> R
                 venta_id: row.get(9)?,
                 venta_numero: row.get(10)?,
                 fecha_cobro: row.get(11)?,
+                modo: row.get(12)?,
+                cliente_id: row.get(13)?,
+                cliente_nombre: row.get(14)?,
+                items: Vec::new(),
             })
         })
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
+    drop(stmt);
+
+    let mut items_stmt = conn
+        .prepare(
+            "SELECT si.subcuenta_id, si.pedido_item_id, i.producto_id, p.nombre, si.cantidad, i.precio_unit
+             FROM rest_subcuenta_items si
+             JOIN rest_subcuentas s ON s.id = si.subcuenta_id
+             JOIN rest_pedido_items i ON i.id = si.pedido_item_id
+             JOIN productos p ON p.id = i.producto_id
+             WHERE s.pedido_id = ?1
+             ORDER BY si.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let items: Vec<(i64, SubcuentaItem)> = items_stmt
+        .query_map(params![pedido_id], |row| {
+            Ok((
+                row.get(0)?,
+                SubcuentaItem {
+                    pedido_item_id: row.get(1)?,
+                    producto_id: row.get(2)?,
+                    producto_nombre: row.get(3)?,
+                    cantidad: row.get(4)?,
+                    precio_unit: row.get(5)?,
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for (subcuenta_id, item) in items {
+        if let Some(sub) = subs.iter_mut().find(|s| s.id == subcuenta_id) {
+            sub.items.push(item);
+        }
+    }
     Ok(subs)
 }
@@ -0,0 +1,429 @@
+//! Pedidos para llevar y delivery.
+//!
+//! Un pedido sin mesa física se abre sobre la mesa virtual de su canal
+//! (`rest_mesas.canal` = LLEVAR | DELIVERY, inactiva para que no salga en el
+//! grid). Así cocina, comandas, cobro y traspasos lo tratan como cualquier
+//! pedido; lo propio del canal vive en `rest_entregas`.
+//!
+//! Flujo: PREPARANDO → LISTO → EN_CAMINO (solo DELIVERY, con repartidor)
+//! → ENTREGADO. Para llevar se entrega en mostrador y ya cobrado.
+//!
+//! Contra entrega: el pedido sale sin cobrar; el repartidor cobra en la
+//! puerta y, al volver, la caja cobra el pedido normalmente (la venta entra a
+//! la caja abierta en ese momento) y la entrega queda `liquidado`.
+
+use super::models::{Entrega, LiquidacionRepartidor, NuevaEntrega};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeMap;
+
+fn config_i64(conn: &Connection, key: &str, default: i64) -> i64 {
+    conn.query_row("SELECT value FROM config WHERE key = ?1", params![key], |r| r.get::<_, String>(0))
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+fn texto(v: &Option<String>) -> Option<&str> {
+    v.as_deref().map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// ¿Se puede pasar de `actual` a `nuevo` en este tipo de pedido?
+fn transicion_valida(tipo: &str, actual: &str, nuevo: &str) -> bool {
+    match (actual, nuevo) {
+        ("PREPARANDO", "LISTO") => true,
+        ("PREPARANDO" | "LISTO", "EN_CAMINO") => tipo == "DELIVERY",
+        ("LISTO", "ENTREGADO") => tipo == "LLEVAR",
+        ("EN_CAMINO", "ENTREGADO") => true,
+        ("PREPARANDO" | "LISTO", "CANCELADO") => true,
+        _ => false,
+    }
+}
+
+/// Abre un pedido para llevar / delivery. Retorna el `pedido_id`.
+pub fn crear(conn: &Connection, e: &NuevaEntrega, mesero: (Option<i64>, Option<&str>)) -> Result<i64, String> {
+    let tipo = e.tipo.trim().to_uppercase();
+    if tipo != "LLEVAR" && tipo != "DELIVERY" {
+        return Err(format!("Tipo inválido: {} (LLEVAR | DELIVERY)", e.tipo));
+    }
+    let mesa_id: i64 = conn
+        .query_row("SELECT id FROM rest_mesas WHERE canal = ?1", params![tipo], |r| r.get(0))
+        .map_err(|_| "Falta la mesa virtual del canal. Reinicia la app.".to_string())?;
+
+    // Cliente: nombre/teléfono de la ficha si no vienen
+    let ficha: Option<(String, Option<String>)> = match e.cliente_id {
+        Some(id) => Some(
+            conn.query_row("SELECT nombre, telefono FROM clientes WHERE id = ?1", params![id], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .map_err(|_| "Cliente no encontrado".to_string())?,
+        ),
+        None => None,
+    };
+    let cliente_nombre = texto(&e.cliente_nombre)
+        .map(String::from)
+        .or_else(|| ficha.as_ref().map(|f| f.0.clone()));
+    let mut telefono = texto(&e.telefono).map(String::from).or_else(|| ficha.and_then(|f| f.1));
+
+    // Dirección: guardada, o nueva (se guarda en las direcciones del cliente)
+    let mut referencia = texto(&e.referencia).map(String::from);
+    let (direccion_id, direccion) = match (e.direccion_id, texto(&e.direccion)) {
+        (Some(id), _) => {
+            let (dir, tel, refe): (String, Option<String>, Option<String>) = conn
+                .query_row(
+                    "SELECT direccion, contacto_telefono, referencia FROM direcciones_cliente
+                     WHERE id = ?1 AND (?2 IS NULL OR cliente_id = ?2)",
+                    params![id, e.cliente_id],
+                    |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+                )
+                .map_err(|_| "Dirección no encontrada para este cliente".to_string())?;
+            telefono = telefono.or(tel);
+            referencia = referencia.or(refe);
+            (Some(id), Some(dir))
+        }
+        (None, Some(dir)) => {
+            let id = match e.cliente_id {
+                Some(cliente_id) => {
+                    let existe: Option<i64> = conn
+                        .query_row(
+                            "SELECT id FROM direcciones_cliente WHERE cliente_id = ?1 AND direccion = ?2 LIMIT 1",
+                            params![cliente_id, dir],
+                            |r| r.get(0),
+                        )
+                        .optional()
+                        .map_err(|e| e.to_string())?;
+                    match existe {
+                        Some(id) => Some(id),
+                        None => {
+                            conn.execute(
+                                "INSERT INTO direcciones_cliente (cliente_id, direccion, contacto_telefono, referencia)
+                                 VALUES (?1, ?2, ?3, ?4)",
+                                params![cliente_id, dir, telefono, referencia],
+                            )
+                            .map_err(|e| e.to_string())?;
+                            Some(conn.last_insert_rowid())
+                        }
+                    }
+                }
+                None => None,
+            };
+            (id, Some(dir.to_string()))
+        }
+        (None, None) => (None, None),
+    };
+    if tipo == "DELIVERY" && direccion.is_none() {
+        return Err("El delivery necesita una dirección de entrega".to_string());
+    }
+    if tipo == "DELIVERY" && telefono.is_none() {
+        return Err("El delivery necesita un teléfono de contacto".to_string());
+    }
+
+    let hora_prometida = match texto(&e.hora_prometida) {
+        Some(h) => super::reservas::normalizar_fecha_hora(h)?,
+        None => {
+            let minutos = if tipo == "DELIVERY" {
+                config_i64(conn, "rest_delivery_tiempo_min", 45)
+            } else {
+                config_i64(conn, "rest_llevar_tiempo_min", 20)
+            };
+            (chrono::Local::now() + chrono::Duration::minutes(minutos)).format("%Y-%m-%d %H:%M").to_string()
+        }
+    };
+
+    // La observación del pedido sale en la comanda: que cocina vea el canal y la hora.
+    let etiqueta = if tipo == "DELIVERY" { "Delivery" } else { "Para llevar" };
+    let observacion = format!(
+        "{} · {} · {}{}",
+        etiqueta,
+        cliente_nombre.as_deref().unwrap_or("Sin nombre"),
+        &hora_prometida[11..],
+        texto(&e.observacion).map(|o| format!(" · {}", o)).unwrap_or_default()
+    );
+    conn.execute(
+        "INSERT INTO rest_pedidos_abiertos (mesa_id, mesero_id, mesero_nombre, comensales, observacion)
+         VALUES (?1, ?2, ?3, 1, ?4)",
+        params![mesa_id, mesero.0, mesero.1, observacion],
+    )
+    .map_err(|e| e.to_string())?;
+    let pedido_id = conn.last_insert_rowid();
+
+    // Costo de envío como línea del pedido (DIRECTO: no pasa por cocina)
+    let mut costo_envio = 0.0;
+    if tipo == "DELIVERY" {
+        let (producto_id, precio): (i64, f64) = conn
+            .query_row(
+                "SELECT id, precio_venta FROM productos WHERE codigo = '_ENVIO_DELIVERY_'",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .map_err(|_| "Producto _ENVIO_DELIVERY_ no encontrado. Reinicia la app.".to_string())?;
+        costo_envio = e.costo_envio.unwrap_or(precio);
+        if costo_envio < 0.0 {
+            return Err("El costo de envío no puede ser negativo".to_string());
+        }
+        if costo_envio > 0.0 {
+            conn.execute(
+                "INSERT INTO rest_pedido_items
+                 (pedido_id, producto_id, cantidad, precio_unit, enviado_cocina, estado_cocina, fecha_envio_cocina)
+                 VALUES (?1, ?2, 1, ?3, 1, 'ENTREGADO', datetime('now', 'localtime'))",
+                params![pedido_id, producto_id, costo_envio],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO rest_entregas
+         (pedido_id, tipo, cliente_id, cliente_nombre, telefono, direccion_id, direccion, referencia,
+          hora_prometida, costo_envio, contra_entrega)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            pedido_id, tipo, e.cliente_id, cliente_nombre, telefono, direccion_id, direccion, referencia,
+            hora_prometida, costo_envio, e.contra_entrega as i32
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(pedido_id)
+}
+
+const SELECT_ENTREGA: &str = "
+    SELECT e.id, e.pedido_id, e.tipo, e.cliente_id, e.cliente_nombre, e.telefono, e.direccion,
+           e.referencia, e.hora_prometida,
+           CAST(ROUND((julianday(e.hora_prometida) - julianday('now', 'localtime')) * 1440) AS INTEGER),
+           e.costo_envio, e.estado, e.repartidor_id, e.repartidor_nombre, e.contra_entrega,
+           (SELECT COALESCE(SUM(i.cantidad * i.precio_unit), 0) FROM rest_pedido_items i WHERE i.pedido_id = e.pedido_id),
+           p.estado = 'COBRADO', e.liquidado, e.fecha_listo, e.fecha_salida, e.fecha_entrega, e.created_at
+    FROM rest_entregas e
+    JOIN rest_pedidos_abiertos p ON p.id = e.pedido_id";
+
+fn map_entrega(r: &rusqlite::Row) -> rusqlite::Result<Entrega> {
+    Ok(Entrega {
+        id: r.get(0)?,
+        pedido_id: r.get(1)?,
+        tipo: r.get(2)?,
+        cliente_id: r.get(3)?,
+        cliente_nombre: r.get(4)?,
+        telefono: r.get(5)?,
+        direccion: r.get(6)?,
+        referencia: r.get(7)?,
+        hora_prometida: r.get(8)?,
+        minutos_restantes: r.get::<_, Option<i64>>(9)?.unwrap_or(0),
+        costo_envio: r.get(10)?,
+        estado: r.get(11)?,
+        repartidor_id: r.get(12)?,
+        repartidor_nombre: r.get(13)?,
+        contra_entrega: r.get::<_, i32>(14)? != 0,
+        total: r.get(15)?,
+        cobrado: r.get(16)?,
+        liquidado: r.get::<_, i32>(17)? != 0,
+        fecha_listo: r.get(18)?,
+        fecha_salida: r.get(19)?,
+        fecha_entrega: r.get(20)?,
+        created_at: r.get(21)?,
+    })
+}
+
+pub fn obtener(conn: &Connection, pedido_id: i64) -> Result<Entrega, String> {
+    conn.query_row(&format!("{} WHERE e.pedido_id = ?1", SELECT_ENTREGA), params![pedido_id], map_entrega)
+        .map_err(|_| "El pedido no es para llevar ni delivery".to_string())
+}
+
+/// Entregas activas ordenadas por hora prometida. `incluir_cerradas` suma las
+/// entregadas/canceladas del día.
+pub fn listar(
+    conn: &Connection,
+    estado: Option<&str>,
+    repartidor_id: Option<i64>,
+    incluir_cerradas: bool,
+) -> Result<Vec<Entrega>, String> {
+    let sql = format!(
+        "{} WHERE (?1 IS NULL OR e.estado = ?1)
+           AND (?2 IS NULL OR e.repartidor_id = ?2)
+           AND (e.estado NOT IN ('ENTREGADO', 'CANCELADO')
+                OR (?3 = 1 AND date(e.created_at) = date('now', 'localtime')))
+         ORDER BY e.hora_prometida, e.id",
+        SELECT_ENTREGA
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![estado, repartidor_id, incluir_cerradas as i32], map_entrega)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+pub fn asignar_repartidor(conn: &Connection, pedido_id: i64, repartidor_id: i64) -> Result<(), String> {
+    let entrega = obtener(conn, pedido_id)?;
+    if entrega.tipo != "DELIVERY" {
+        return Err("Solo los pedidos delivery llevan repartidor".to_string());
+    }
+    if matches!(entrega.estado.as_str(), "ENTREGADO" | "CANCELADO") {
+        return Err(format!("La entrega ya está {}", entrega.estado));
+    }
+    let nombre: String = conn
+        .query_row("SELECT nombre FROM usuarios WHERE id = ?1 AND activo = 1", params![repartidor_id], |r| r.get(0))
+        .map_err(|_| "Repartidor no encontrado o inactivo".to_string())?;
+    conn.execute(
+        "UPDATE rest_entregas SET repartidor_id = ?1, repartidor_nombre = ?2 WHERE pedido_id = ?3",
+        params![repartidor_id, nombre, pedido_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Avanza el estado logístico. `repartidor_id` se asigna si la entrega sale
+/// EN_CAMINO sin repartidor (el repartidor que la toma desde la app).
+pub fn cambiar_estado(conn: &Connection, pedido_id: i64, nuevo: &str, repartidor_id: Option<i64>) -> Result<(), String> {
+    let entrega = obtener(conn, pedido_id)?;
+    let nuevo = nuevo.trim().to_uppercase();
+    if !transicion_valida(&entrega.tipo, &entrega.estado, &nuevo) {
+        return Err(format!("No se puede pasar de {} a {} ({})", entrega.estado, nuevo, entrega.tipo));
+    }
+    match nuevo.as_str() {
+        "EN_CAMINO" => {
+            if !entrega.cobrado && !entrega.contra_entrega {
+                return Err("El pedido no es contra entrega: cóbrelo antes de despacharlo".to_string());
+            }
+            if entrega.repartidor_id.is_none() {
+                let id = repartidor_id.ok_or("Asigne un repartidor antes de despachar")?;
+                asignar_repartidor(conn, pedido_id, id)?;
+            }
+        }
+        "ENTREGADO" if entrega.tipo == "LLEVAR" && !entrega.cobrado => {
+            return Err("Cobre el pedido antes de entregarlo".to_string());
+        }
+        "CANCELADO" => {
+            if entrega.cobrado {
+                return Err("El pedido ya fue cobrado: anule la venta antes de cancelar".to_string());
+            }
+            conn.execute(
+                "UPDATE rest_pedidos_abiertos SET estado = 'CANCELADO', fecha_cierre = datetime('now', 'localtime')
+                 WHERE id = ?1",
+                params![pedido_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        _ => {}
+    }
+    let columna_fecha = match nuevo.as_str() {
+        "LISTO" => ", fecha_listo = datetime('now', 'localtime')",
+        "EN_CAMINO" => ", fecha_salida = datetime('now', 'localtime')",
+        "ENTREGADO" => ", fecha_entrega = datetime('now', 'localtime')",
+        _ => "",
+    };
+    conn.execute(
+        &format!("UPDATE rest_entregas SET estado = ?1{} WHERE pedido_id = ?2", columna_fecha),
+        params![nuevo, pedido_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Llamar cuando el pedido queda COBRADO. Si la entrega ya había salido con
+/// el repartidor, ese cobro es su liquidación en la caja abierta; si aún no
+/// salía, se pagó en mostrador y deja de ser contra entrega.
+pub fn al_cobrar(conn: &Connection, pedido_id: i64) -> Result<(), String> {
+    conn.execute(
+        "UPDATE rest_entregas
+         SET liquidado = 1, fecha_liquidacion = datetime('now', 'localtime'),
+             caja_liquidacion_id = (SELECT id FROM caja WHERE estado = 'ABIERTA' LIMIT 1)
+         WHERE pedido_id = ?1 AND contra_entrega = 1 AND liquidado = 0
+           AND estado IN ('EN_CAMINO', 'ENTREGADO')",
+        params![pedido_id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE rest_entregas SET contra_entrega = 0
+         WHERE pedido_id = ?1 AND contra_entrega = 1 AND liquidado = 0",
+        params![pedido_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Cuánto efectivo contra entrega debe cada repartidor y cuánto ya liquidó
+/// en la caja abierta.
+pub fn liquidacion(conn: &Connection, repartidor_id: Option<i64>) -> Result<Vec<LiquidacionRepartidor>, String> {
+    let mut por_repartidor: BTreeMap<Option<i64>, LiquidacionRepartidor> = BTreeMap::new();
+    let pendientes = {
+        let sql = format!(
+            "{} WHERE e.contra_entrega = 1 AND e.liquidado = 0 AND e.estado IN ('EN_CAMINO', 'ENTREGADO')
+               AND p.estado <> 'COBRADO' AND (?1 IS NULL OR e.repartidor_id = ?1)
+             ORDER BY e.fecha_salida",
+            SELECT_ENTREGA
+        );
+        let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![repartidor_id], map_entrega)
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        rows
+    };
+    for e in pendientes {
+        let liq = por_repartidor.entry(e.repartidor_id).or_insert_with(|| nueva_liquidacion(e.repartidor_id, e.repartidor_nombre.clone()));
+        liq.monto_pendiente += e.total;
+        liq.pendientes.push(e);
+    }
+
+    let liquidadas: Vec<(Option<i64>, Option<String>, i64, f64)> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT e.repartidor_id, MAX(e.repartidor_nombre), COUNT(*), COALESCE(SUM(v.total), 0)
+                 FROM rest_entregas e
+                 JOIN rest_pedidos_abiertos p ON p.id = e.pedido_id
+                 LEFT JOIN ventas v ON v.id = p.venta_id
+                 WHERE e.liquidado = 1
+                   AND e.caja_liquidacion_id = (SELECT id FROM caja WHERE estado = 'ABIERTA' LIMIT 1)
+                   AND (?1 IS NULL OR e.repartidor_id = ?1)
+                 GROUP BY e.repartidor_id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![repartidor_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        rows
+    };
+    for (id, nombre, cantidad, monto) in liquidadas {
+        let liq = por_repartidor.entry(id).or_insert_with(|| nueva_liquidacion(id, nombre));
+        liq.liquidadas_caja = cantidad;
+        liq.monto_liquidado_caja = monto;
+    }
+
+    Ok(por_repartidor
+        .into_values()
+        .map(|mut l| {
+            l.monto_pendiente = (l.monto_pendiente * 100.0).round() / 100.0;
+            l
+        })
+        .collect())
+}
+
+fn nueva_liquidacion(repartidor_id: Option<i64>, nombre: Option<String>) -> LiquidacionRepartidor {
+    LiquidacionRepartidor {
+        repartidor_id,
+        repartidor_nombre: nombre.unwrap_or_else(|| "Sin repartidor".to_string()),
+        pendientes: Vec::new(),
+        monto_pendiente: 0.0,
+        liquidadas_caja: 0,
+        monto_liquidado_caja: 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flujo_delivery_y_para_llevar() {
+        assert!(transicion_valida("DELIVERY", "PREPARANDO", "EN_CAMINO"));
+        assert!(transicion_valida("DELIVERY", "EN_CAMINO", "ENTREGADO"));
+        assert!(!transicion_valida("DELIVERY", "LISTO", "ENTREGADO"));
+        assert!(!transicion_valida("LLEVAR", "LISTO", "EN_CAMINO"));
+        assert!(transicion_valida("LLEVAR", "LISTO", "ENTREGADO"));
+        assert!(!transicion_valida("DELIVERY", "EN_CAMINO", "CANCELADO"));
+        assert!(!transicion_valida("LLEVAR", "ENTREGADO", "PREPARANDO"));
+    }
+}
@@ -0,0 +1,356 @@
+//! Ruteo de comandas por estación de preparación.
+//!
+//! Cada item enviado a cocina se asigna a la estación que prepara su
+//! categoría (`rest_estacion_categorias`) y se imprime una comanda por
+//! estación con [`generar_comanda_cocina`]. Items cuya categoría no tiene
+//! estación van a la comanda "General" (impresora de cocina de siempre).
+//!
+//! Si la impresora de la estación no responde se prueba, en orden, la de
+//! respaldo de la estación y la impresora de cocina general. El resultado
+//! queda en `rest_comandas` y en `rest_pedido_items.estado_impresion`, así la
+//! UI puede mostrar qué comandas fallaron y reimprimirlas.
+//!
+//! Solo se usa si hay al menos una estación activa; si no, el flujo clásico
+//! de `rest_imprimir_comanda_cocina` (cocina/barra) sigue igual.
+
+use super::commands::{impresora_es_virtual, obtener_pedido_detalle};
+use super::models::{Comanda, Estacion, PedidoItem};
+use super::printing::{generar_comanda_cocina, DestinoComanda};
+use crate::db::Database;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Resultado de imprimir (o reimprimir) una comanda de estación.
+#[derive(Debug, Serialize, Clone)]
+pub struct ResultadoComanda {
+    pub comanda_id: i64,
+    pub estacion_nombre: String,
+    /// IMPRESA | RESPALDO | FALLIDA
+    pub estado: String,
+    pub impresora_usada: Option<String>,
+    pub error: Option<String>,
+}
+
+impl ResultadoComanda {
+    pub fn mensaje(&self) -> String {
+        match self.estado.as_str() {
+            "IMPRESA" => format!("{} impresa", self.estacion_nombre),
+            "RESPALDO" => format!(
+                "{} impresa en respaldo ({})",
+                self.estacion_nombre,
+                self.impresora_usada.as_deref().unwrap_or("?")
+            ),
+            _ => format!("⚠ {} NO impresa", self.estacion_nombre),
+        }
+    }
+}
+
+/// ¿Hay al menos una estación activa? Decide entre ruteo por estación y el clásico.
+pub fn hay_estaciones(conn: &Connection) -> bool {
+    conn.query_row("SELECT COUNT(*) FROM rest_estaciones WHERE activa = 1", [], |r| r.get::<_, i64>(0))
+        .unwrap_or(0)
+        > 0
+}
+
+/// Estaciones (activas e inactivas) con sus categorías.
+pub fn listar(conn: &Connection) -> Result<Vec<Estacion>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, nombre, impresora, impresora_respaldo, orden, activa
+             FROM rest_estaciones ORDER BY orden, nombre",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut estaciones: Vec<Estacion> = stmt
+        .query_map([], |row| {
+            Ok(Estacion {
+                id: Some(row.get(0)?),
+                nombre: row.get(1)?,
+                impresora: row.get(2)?,
+                impresora_respaldo: row.get(3)?,
+                orden: row.get(4)?,
+                activa: row.get::<_, i32>(5)? != 0,
+                categorias: Vec::new(),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut cat_stmt = conn
+        .prepare("SELECT estacion_id, categoria_id FROM rest_estacion_categorias ORDER BY categoria_id")
+        .map_err(|e| e.to_string())?;
+    let asignaciones: Vec<(i64, i64)> = cat_stmt
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for (estacion_id, categoria_id) in asignaciones {
+        if let Some(e) = estaciones.iter_mut().find(|e| e.id == Some(estacion_id)) {
+            e.categorias.push(categoria_id);
+        }
+    }
+    Ok(estaciones)
+}
+
+/// Comandas de un pedido (o de todos), más recientes primero.
+pub fn listar_comandas(
+    conn: &Connection,
+    pedido_id: Option<i64>,
+    solo_fallidas: bool,
+) -> Result<Vec<Comanda>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, pedido_id, estacion_id, estacion_nombre, items, estado, impresora_usada,
+                    error, reimpresiones, created_at, ultima_impresion_at
+             FROM rest_comandas
+             WHERE (?1 IS NULL OR pedido_id = ?1)
+               AND (?2 = 0 OR estado = 'FALLIDA')
+             ORDER BY id DESC
+             LIMIT 200",
+        )
+        .map_err(|e| e.to_string())?;
+    let comandas = stmt
+        .query_map(params![pedido_id, solo_fallidas as i32], |row| {
+            Ok(Comanda {
+                id: row.get(0)?,
+                pedido_id: row.get(1)?,
+                estacion_id: row.get(2)?,
+                estacion_nombre: row.get(3)?,
+                items: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
+                estado: row.get(5)?,
+                impresora_usada: row.get(6)?,
+                error: row.get(7)?,
+                reimpresiones: row.get(8)?,
+                created_at: row.get(9)?,
+                ultima_impresion_at: row.get(10)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(comandas)
+}
+
+fn cargar_config(conn: &Connection) -> Result<HashMap<String, String>, String> {
+    let mut stmt = conn.prepare("SELECT key, value FROM config").map_err(|e| e.to_string())?;
+    let config = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(config)
+}
+
+/// Impresora de cocina general: `impresora_cocina` o, si no hay, la principal.
+fn impresora_general(config: &HashMap<String, String>) -> String {
+    ["impresora_cocina", "impresora"]
+        .iter()
+        .filter_map(|k| config.get(*k))
+        .map(|s| s.trim().to_string())
+        .find(|s| !s.is_empty())
+        .unwrap_or_default()
+}
+
+/// Orden de intento: principal de la estación → respaldo → cocina general.
+/// Las impresoras virtuales (PDF) no sirven para comandas automáticas.
+fn candidatas(estacion: Option<&Estacion>, general: &str) -> Vec<String> {
+    let mut lista: Vec<String> = Vec::new();
+    let propias = estacion
+        .map(|e| vec![e.impresora.trim(), e.impresora_respaldo.trim()])
+        .unwrap_or_default();
+    for imp in propias.into_iter().chain(std::iter::once(general.trim())) {
+        if !impresora_es_virtual(imp) && !lista.iter().any(|l| l == imp) {
+            lista.push(imp.to_string());
+        }
+    }
+    lista
+}
+
+/// Prueba cada impresora hasta que una acepte el ticket.
+/// Devuelve `(estado, impresora_usada, error)`.
+fn imprimir_con_respaldo(candidatas: &[String], ticket: &[u8]) -> (String, Option<String>, Option<String>) {
+    if candidatas.is_empty() {
+        return (
+            "FALLIDA".to_string(),
+            None,
+            Some("La estación no tiene impresora térmica configurada".to_string()),
+        );
+    }
+    let mut errores: Vec<String> = Vec::new();
+    for (i, imp) in candidatas.iter().enumerate() {
+        match crate::printing::imprimir_raw(imp, ticket) {
+            Ok(()) => {
+                let estado = if i == 0 { "IMPRESA" } else { "RESPALDO" };
+                let error = if errores.is_empty() { None } else { Some(errores.join(" | ")) };
+                return (estado.to_string(), Some(imp.clone()), error);
+            }
+            Err(e) => {
+                eprintln!("[Restaurante] Impresora '{}' no respondió: {}", imp, e);
+                errores.push(format!("{}: {}", imp, e));
+            }
+        }
+    }
+    ("FALLIDA".to_string(), None, Some(errores.join(" | ")))
+}
+
+/// Imprime los items `items_ids` del pedido, una comanda por estación.
+///
+/// El lock de la BD se suelta mientras se imprime (una impresora de red
+/// caída tarda hasta el timeout en responder).
+pub fn imprimir_por_estacion(
+    db: &Database,
+    pedido_id: i64,
+    items_ids: &[i64],
+) -> Result<Vec<ResultadoComanda>, String> {
+    let (detalle, config, estaciones, estacion_de_item) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let detalle = obtener_pedido_detalle(&conn, pedido_id)?;
+        let config = cargar_config(&conn)?;
+        let estaciones: Vec<Estacion> = listar(&conn)?.into_iter().filter(|e| e.activa).collect();
+        let mut stmt = conn
+            .prepare(
+                "SELECT i.id, e.id
+                 FROM rest_pedido_items i
+                 JOIN productos p ON p.id = i.producto_id
+                 LEFT JOIN rest_estacion_categorias ec ON ec.categoria_id = p.categoria_id
+                 LEFT JOIN rest_estaciones e ON e.id = ec.estacion_id AND e.activa = 1
+                 WHERE i.pedido_id = ?1",
+            )
+            .map_err(|e| e.to_string())?;
+        let mapa: HashMap<i64, Option<i64>> = stmt
+            .query_map(params![pedido_id], |r| Ok((r.get(0)?, r.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+        (detalle, config, estaciones, mapa)
+    };
+    let general = impresora_general(&config);
+
+    // Agrupar por estación respetando el orden configurado; "General" al final.
+    let mut grupos: Vec<(Option<&Estacion>, Vec<PedidoItem>)> =
+        estaciones.iter().map(|e| (Some(e), Vec::new())).collect();
+    grupos.push((None, Vec::new()));
+    for item in detalle.items.iter().filter(|i| {
+        i.destino_preparacion != "DIRECTO" && i.id.is_some_and(|id| items_ids.contains(&id))
+    }) {
+        let estacion_id = item.id.and_then(|id| estacion_de_item.get(&id).copied().flatten());
+        if let Some(g) = grupos.iter_mut().find(|(e, _)| e.and_then(|e| e.id) == estacion_id) {
+            g.1.push(item.clone());
+        }
+    }
+
+    let mut resultados = Vec::new();
+    for (estacion, items) in grupos.into_iter().filter(|(_, items)| !items.is_empty()) {
+        let nombre = estacion.map(|e| e.nombre.clone()).unwrap_or_else(|| "General".to_string());
+        let Some(ticket) = generar_comanda_cocina(
+            pedido_id,
+            &detalle.mesa_nombre,
+            detalle.zona_nombre.as_deref(),
+            detalle.pedido.mesero_nombre.as_deref(),
+            &items,
+            DestinoComanda::Estacion(nombre.clone()),
+            &config,
+        ) else {
+            continue;
+        };
+        let (estado, usada, error) = imprimir_con_respaldo(&candidatas(estacion, &general), &ticket);
+
+        let ids: Vec<i64> = items.iter().filter_map(|i| i.id).collect();
+        let estacion_id = estacion.and_then(|e| e.id);
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO rest_comandas (pedido_id, estacion_id, estacion_nombre, items, estado,
+                                        impresora_usada, error, ultima_impresion_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, datetime('now', 'localtime'))",
+            params![
+                pedido_id,
+                estacion_id,
+                nombre,
+                serde_json::to_string(&ids).unwrap_or_else(|_| "[]".to_string()),
+                estado,
+                usada,
+                error
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        let comanda_id = conn.last_insert_rowid();
+        for id in &ids {
+            let _ = conn.execute(
+                "UPDATE rest_pedido_items SET estacion_id = ?1, comanda_id = ?2, estado_impresion = ?3
+                 WHERE id = ?4",
+                params![estacion_id, comanda_id, estado, id],
+            );
+        }
+        drop(conn);
+
+        resultados.push(ResultadoComanda {
+            comanda_id,
+            estacion_nombre: nombre,
+            estado,
+            impresora_usada: usada,
+            error,
+        });
+    }
+    Ok(resultados)
+}
+
+/// Reimprime una comanda ya registrada (papel perdido, impresora que falló).
+/// Usa la configuración ACTUAL de impresoras de la estación.
+pub fn reimprimir(db: &Database, comanda_id: i64) -> Result<ResultadoComanda, String> {
+    let (pedido_id, nombre, ids, detalle, config, estacion) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let (pedido_id, estacion_id, nombre, items_json): (i64, Option<i64>, String, String) = conn
+            .query_row(
+                "SELECT pedido_id, estacion_id, estacion_nombre, items FROM rest_comandas WHERE id = ?1",
+                params![comanda_id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+            )
+            .map_err(|_| "Comanda no encontrada".to_string())?;
+        let ids: Vec<i64> = serde_json::from_str(&items_json).unwrap_or_default();
+        let detalle = obtener_pedido_detalle(&conn, pedido_id)?;
+        let config = cargar_config(&conn)?;
+        let estacion = listar(&conn)?.into_iter().find(|e| e.id.is_some() && e.id == estacion_id);
+        (pedido_id, nombre, ids, detalle, config, estacion)
+    };
+
+    let items: Vec<PedidoItem> = detalle
+        .items
+        .iter()
+        .filter(|i| i.id.is_some_and(|id| ids.contains(&id)))
+        .cloned()
+        .collect();
+    let ticket = generar_comanda_cocina(
+        pedido_id,
+        &detalle.mesa_nombre,
+        detalle.zona_nombre.as_deref(),
+        detalle.pedido.mesero_nombre.as_deref(),
+        &items,
+        DestinoComanda::Estacion(format!("{} (REIMPRESION)", nombre)),
+        &config,
+    )
+    .ok_or("La comanda ya no tiene items para imprimir")?;
+    let (estado, usada, error) =
+        imprimir_con_respaldo(&candidatas(estacion.as_ref(), &impresora_general(&config)), &ticket);
+
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE rest_comandas SET estado = ?1, impresora_usada = ?2, error = ?3,
+                reimpresiones = reimpresiones + 1, ultima_impresion_at = datetime('now', 'localtime')
+         WHERE id = ?4",
+        params![estado, usada, error, comanda_id],
+    )
+    .map_err(|e| e.to_string())?;
+    let _ = conn.execute(
+        "UPDATE rest_pedido_items SET estado_impresion = ?1 WHERE comanda_id = ?2",
+        params![estado, comanda_id],
+    );
+
+    Ok(ResultadoComanda {
+        comanda_id,
+        estacion_nombre: nombre,
+        estado,
+        impresora_usada: usada,
+        error,
+    })
+}
@@ -0,0 +1,225 @@
+//! Pantalla de cocina (KDS) servida por el propio POS como página web.
+//!
+//! Cualquier navegador de la LAN (TV, mini PC Linux, tablet vieja) abre
+//! `http://<ip>:<puerto>/kds?token=…` y queda como pantalla de cocina:
+//! tickets agrupados por pedido, filtro por estación, bump (LISTO) y recall,
+//! color según la edad del ticket y aviso sonoro cuando entra uno nuevo.
+//! La página es un solo HTML sin dependencias (`kds.html`), así funciona sin
+//! internet.
+//!
+//! El token se crea desde el POS ([`crear_pantalla`]) y vive en `app_tokens`
+//! con `tipo = 'KDS'`: no sirve para la API de la app (ni un token de la app
+//! sirve aquí) y se revoca como cualquier dispositivo emparejado.
+//!
+//! Rutas:
+//! - `GET  /kds`            — la página (sin auth; el token lo lee del query)
+//! - `GET  /kds/api/config` — negocio, estación de la pantalla, estaciones y umbrales de color
+//! - `GET  /kds/api/items`  — items en cocina (`?estacion_id=`, 0 = 'General')
+//! - `POST /kds/api/bump`   — `{ items: [id] }` → LISTO
+//! - `POST /kds/api/recall` — `{ items: [id] }` → de LISTO a EN_PREPARACION
+//!
+//! Colores: si el producto tiene objetivo de preparación el ticket se pone
+//! amarillo al 75% del objetivo y rojo al pasarlo; si no, se usan
+//! `rest_kds_amarillo_min` (8) y `rest_kds_rojo_min` (15).
+
+use crate::app_movil::http::{err400, err500, ApiError};
+use crate::server::state::ServerState;
+use axum::{
+    extract::{Query, State as AxumState},
+    http::{HeaderMap, StatusCode},
+    response::Html,
+    routing::{get, post},
+    Json, Router,
+};
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use std::sync::Arc;
+
+pub fn rutas() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/kds", get(pagina))
+        .route("/kds/api/config", get(config))
+        .route("/kds/api/items", get(items))
+        .route("/kds/api/bump", post(bump))
+        .route("/kds/api/recall", post(recall))
+}
+
+fn config_i64(conn: &Connection, key: &str, default: i64) -> i64 {
+    conn.query_row("SELECT value FROM config WHERE key = ?1", params![key], |r| r.get::<_, String>(0))
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Crea el token de una pantalla de cocina a nombre de `usuario_id`.
+/// `estacion_id` es la estación que muestra al abrir (None = todas, 0 = 'General').
+pub fn crear_pantalla(
+    conn: &Connection,
+    usuario_id: i64,
+    nombre: &str,
+    estacion_id: Option<i64>,
+) -> Result<(i64, String), String> {
+    let nombre = nombre.trim();
+    if nombre.is_empty() {
+        return Err("Indique un nombre para la pantalla (ej. Cocina caliente)".to_string());
+    }
+    if let Some(eid) = estacion_id.filter(|e| *e != 0) {
+        conn.query_row("SELECT id FROM rest_estaciones WHERE id = ?1", params![eid], |r| r.get::<_, i64>(0))
+            .map_err(|_| "Estación no encontrada".to_string())?;
+    }
+    let token = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO app_tokens (usuario_id, token, dispositivo_nombre, dispositivo_modelo, tipo, estacion_id)
+         VALUES (?1, ?2, ?3, 'Pantalla de cocina', 'KDS', ?4)",
+        params![usuario_id, token, nombre, estacion_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok((conn.last_insert_rowid(), token))
+}
+
+/// Pantalla autenticada (resultado de [`autenticar`]).
+struct PantallaSesion {
+    estacion_id: Option<i64>,
+}
+
+/// Valida el `Authorization: Bearer <token>` contra `app_tokens` (tipo KDS),
+/// que el usuario siga activo con permiso `ve_cocina` y la licencia restaurante.
+fn autenticar(headers: &HeaderMap, state: &Arc<ServerState>) -> Result<PantallaSesion, (StatusCode, Json<ApiError>)> {
+    let token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("")
+        .trim();
+    if token.is_empty() {
+        return Err((StatusCode::UNAUTHORIZED, Json(ApiError::new("Pantalla sin token"))));
+    }
+
+    crate::restaurante::requiere_modulo_restaurante(&state.db)
+        .map_err(|m| (StatusCode::FORBIDDEN, Json(ApiError::new(m))))?;
+
+    let conn = state.db.conn.lock().map_err(err500)?;
+    let (token_id, estacion_id, rol, permisos): (i64, Option<i64>, String, String) = conn
+        .query_row(
+            "SELECT t.id, t.estacion_id, u.rol, COALESCE(u.permisos, '{}')
+             FROM app_tokens t
+             JOIN usuarios u ON t.usuario_id = u.id
+             WHERE t.token = ?1 AND t.tipo = 'KDS' AND t.revoked = 0 AND u.activo = 1",
+            params![token],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .map_err(|_| (StatusCode::UNAUTHORIZED, Json(ApiError::new("Token de pantalla inválido o revocado"))))?;
+
+    let ve_cocina = rol == "ADMIN"
+        || serde_json::from_str::<serde_json::Value>(&permisos)
+            .ok()
+            .and_then(|v| v.get("ve_cocina")?.as_bool())
+            .unwrap_or(false);
+    if !ve_cocina {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiError::new("El usuario de la pantalla ya no tiene permiso ve_cocina")),
+        ));
+    }
+
+    // Best-effort: así el admin ve en Dispositivos si la pantalla sigue viva.
+    let _ = conn.execute(
+        "UPDATE app_tokens SET last_used_at = datetime('now','localtime') WHERE id = ?1",
+        params![token_id],
+    );
+    Ok(PantallaSesion { estacion_id })
+}
+
+/// `GET /kds` — página autocontenida de la pantalla de cocina.
+async fn pagina() -> Html<&'static str> {
+    Html(include_str!("kds.html"))
+}
+
+/// `GET /kds/api/config` — datos para armar la pantalla.
+async fn config(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    let pantalla = autenticar(&headers, &state)?;
+    let conn = state.db.conn.lock().map_err(err500)?;
+
+    let negocio: String = conn
+        .query_row("SELECT value FROM config WHERE key = 'nombre_negocio'", [], |r| r.get(0))
+        .unwrap_or_else(|_| "Clouget POS".to_string());
+    let estaciones: Vec<serde_json::Value> = super::estaciones::listar(&conn)
+        .map_err(err500)?
+        .into_iter()
+        .filter(|e| e.activa)
+        .map(|e| serde_json::json!({ "id": e.id, "nombre": e.nombre }))
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "negocio": negocio,
+        "estacion_id": pantalla.estacion_id,
+        "estaciones": estaciones,
+        "amarillo_min": config_i64(&conn, "rest_kds_amarillo_min", 8),
+        "rojo_min": config_i64(&conn, "rest_kds_rojo_min", 15),
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+struct ItemsQuery {
+    /// Filtra por estación (0 = 'General'); sin parámetro = todas.
+    estacion_id: Option<i64>,
+}
+
+/// `GET /kds/api/items` — items activos en cocina (incluye LISTO para el recall).
+async fn items(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Query(qp): Query<ItemsQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    autenticar(&headers, &state)?;
+    let conn = state.db.conn.lock().map_err(err500)?;
+    let items = super::tiempos::listar_pendientes(&conn, qp.estacion_id).map_err(err500)?;
+    Ok(Json(serde_json::json!({ "ok": true, "items": items })))
+}
+
+#[derive(Debug, Deserialize)]
+struct ItemsRequest {
+    items: Vec<i64>,
+}
+
+/// `POST /kds/api/bump` — marca LISTO los items del ticket (todo o nada).
+async fn bump(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(req): Json<ItemsRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    autenticar(&headers, &state)?;
+    if req.items.is_empty() {
+        return Err(err400("Sin items"));
+    }
+    let mut conn = state.db.conn.lock().map_err(err500)?;
+    let tx = conn.transaction().map_err(err500)?;
+    for id in &req.items {
+        super::tiempos::marcar_estado(&tx, *id, "LISTO").map_err(err400)?;
+    }
+    tx.commit().map_err(err500)?;
+    Ok(Json(serde_json::json!({ "ok": true, "items": req.items.len() })))
+}
+
+/// `POST /kds/api/recall` — devuelve a preparación items marcados LISTO por error.
+async fn recall(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(req): Json<ItemsRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    autenticar(&headers, &state)?;
+    if req.items.is_empty() {
+        return Err(err400("Sin items"));
+    }
+    let mut conn = state.db.conn.lock().map_err(err500)?;
+    let tx = conn.transaction().map_err(err500)?;
+    for id in &req.items {
+        super::tiempos::recuperar_item(&tx, *id).map_err(err400)?;
+    }
+    tx.commit().map_err(err500)?;
+    Ok(Json(serde_json::json!({ "ok": true, "items": req.items.len() })))
+}
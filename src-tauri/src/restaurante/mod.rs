@@ -17,13 +17,31 @@
 //! - [`schema`]   — migración SQL (rest_zonas, rest_mesas, rest_pedidos_abiertos, rest_pedido_items)
 //! - [`models`]   — structs Rust serializables
 //! - [`commands`] — comandos Tauri (CRUD + flujo de pedido)
+//! - [`modificadores`] — término, extras y opciones por producto (validación, precio, insumos)
+//! - [`estaciones`] — ruteo de comandas por estación con impresora de respaldo
+//! - [`tiempos`]   — tiempos del menú (retener/disparar) y cronometraje de cocina
+//! - [`kds`]      — pantalla de cocina web (`/kds`) con token de dispositivo, bump/recall y alertas
+//! - [`reservas`]  — reservas con depósito, bloqueo de mesas, lista de espera y no-shows
+//! - [`traspasos`] — cambio de mesa, mover items entre pedidos y reasignar mesero (auditado)
+//! - [`propinas`]  — propina/cargo por servicio al cobrar y reparto por turno
+//! - [`analitica`] — cubiertos por turno, rotación por zona, ventas por mesero y mapa de horas
+//! - [`entregas`]  — pedidos para llevar / delivery con repartidor y liquidación contra entrega
 //! - [`http`]     — endpoints HTTP para app móvil (stub en Fase 1, completo en Fase 3)
 
+pub mod analitica;
 pub mod commands;
+pub mod entregas;
+pub mod estaciones;
 pub mod http;
+pub mod kds;
 pub mod models;
+pub mod modificadores;
 pub mod printing;
+pub mod propinas;
+pub mod reservas;
 pub mod schema;
+pub mod tiempos;
+pub mod traspasos;
 
 use crate::db::Database;
 use rusqlite::params;
@@ -44,7 +44,7 @@ pub struct MesaConEstado {
     pub nombre: String,
     pub capacidad: i32,
     pub orden: i32,
-    /// LIBRE | OCUPADA | CUENTA_PEDIDA
+    /// LIBRE | RESERVADA | OCUPADA | CUENTA_PEDIDA
     pub estado: String,
     pub pedido_id: Option<i64>,
     pub mesero_nombre: Option<String>,
@@ -66,6 +66,16 @@ pub struct MesaConEstado {
     /// 0 si no tiene mesas unidas o si esta mesa es extra.
     #[serde(default)]
     pub mesas_unidas_count: i32,
+    // ─── Reservas ──────────────────────────────────────────────────
+    /// Reserva que bloquea la mesa ahora (estado RESERVADA). Solo en mesas libres.
+    #[serde(default)]
+    pub reserva_id: Option<i64>,
+    #[serde(default)]
+    pub reserva_cliente: Option<String>,
+    #[serde(default)]
+    pub reserva_fecha_hora: Option<String>,
+    #[serde(default)]
+    pub reserva_personas: Option<i32>,
 }
 
 // ─── Pedido ──────────────────────────────────────────────────────────────
@@ -109,6 +119,13 @@ pub struct PedidoItem {
     /// o se despacha directo (no aparece en cocina).
     #[serde(default = "default_destino_preparacion")]
     pub destino_preparacion: String,
+    /// Modificadores elegidos, ya como texto ("Término: Medio; Sin cebolla").
+    /// El detalle (precios, insumos) está en `rest_pedido_item_modificadores`.
+    #[serde(default)]
+    pub modificadores: Option<String>,
+    /// Tiempo del menú: 'ENTRADA' | 'FUERTE' | 'POSTRE' | None (sin tiempo, sale al enviar).
+    #[serde(default)]
+    pub curso: Option<String>,
 }
 
 /// Mesa "ligera" (id + nombre + capacidad) — usado en listados embebidos
@@ -164,6 +181,128 @@ pub struct AbonoPedido {
     pub usuario_nombre: Option<String>,
 }
 
+// ─── Reservas y lista de espera ──────────────────────────────────────────
+
+/// Reserva de mesa.
+/// estado: PENDIENTE | CONFIRMADA | SENTADA | CANCELADA | NO_SHOW
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Reserva {
+    pub id: Option<i64>,
+    #[serde(default)]
+    pub cliente_id: Option<i64>,
+    /// Nombre a mostrar; si viene cliente_id se toma de `clientes`.
+    #[serde(default)]
+    pub cliente_nombre: String,
+    #[serde(default)]
+    pub telefono: Option<String>,
+    pub personas: i32,
+    /// 'YYYY-MM-DD HH:MM' (hora local)
+    pub fecha_hora: String,
+    #[serde(default = "default_duracion_reserva")]
+    pub duracion_min: i32,
+    #[serde(default)]
+    pub mesa_id: Option<i64>,
+    #[serde(default)]
+    pub mesa_nombre: Option<String>,
+    #[serde(default = "default_estado_reserva")]
+    pub estado: String,
+    #[serde(default)]
+    pub notas: Option<String>,
+    #[serde(default)]
+    pub pedido_id: Option<i64>,
+    /// Depósito en HOLDING (0 si no dejó)
+    #[serde(default)]
+    pub deposito: f64,
+    /// No-shows previos del mismo cliente/teléfono (para alertar al anfitrión)
+    #[serde(default)]
+    pub no_shows_previos: i64,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+/// Depósito de una reserva. Mismo ciclo que los abonos de mesa:
+/// HOLDING → APLICADO (pasa como abono al pedido al sentar) | DEVUELTO | RETENIDO (no-show).
+#[derive(Debug, Serialize, Clone)]
+pub struct DepositoReserva {
+    pub id: i64,
+    pub reserva_id: i64,
+    pub monto: f64,
+    pub forma_pago: String,
+    pub banco_id: Option<i64>,
+    pub referencia_pago: Option<String>,
+    pub estado: String,
+    pub fecha: String,
+    pub usuario_nombre: Option<String>,
+}
+
+/// Grupo sin reserva esperando mesa.
+/// estado: ESPERANDO | AVISADO | SENTADO | ABANDONO
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EntradaEspera {
+    pub id: Option<i64>,
+    pub cliente_nombre: String,
+    #[serde(default)]
+    pub telefono: Option<String>,
+    pub personas: i32,
+    #[serde(default)]
+    pub notas: Option<String>,
+    #[serde(default = "default_estado_espera")]
+    pub estado: String,
+    #[serde(default)]
+    pub fecha_llegada: Option<String>,
+    #[serde(default)]
+    pub minutos_esperando: Option<i64>,
+    /// Estimación calculada al listar (mesas ocupadas con capacidad suficiente
+    /// y duración promedio de las mesas cobradas recientes).
+    #[serde(default)]
+    pub minutos_estimados: Option<i64>,
+    #[serde(default)]
+    pub mesa_id: Option<i64>,
+    #[serde(default)]
+    pub pedido_id: Option<i64>,
+}
+
+/// No-shows por cliente en un rango.
+#[derive(Debug, Serialize, Clone)]
+pub struct NoShowCliente {
+    pub cliente_id: Option<i64>,
+    pub cliente_nombre: String,
+    pub telefono: Option<String>,
+    pub reservas: i64,
+    pub no_shows: i64,
+    pub deposito_retenido: f64,
+}
+
+// ─── Traspasos (mesa, items, mesero) ─────────────────────────────────────
+
+/// Item (o parte de él) a mover a otro pedido. cantidad None = la línea completa.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ItemAMover {
+    pub item_id: i64,
+    #[serde(default)]
+    pub cantidad: Option<f64>,
+}
+
+/// Registro de auditoría de un traspaso.
+/// tipo: TRANSFERIR_MESA | MOVER_ITEMS | REASIGNAR_MESERO
+#[derive(Debug, Serialize, Clone)]
+pub struct MovimientoPedido {
+    pub id: i64,
+    pub tipo: String,
+    pub pedido_id: i64,
+    pub pedido_destino_id: Option<i64>,
+    pub mesa_origen: Option<String>,
+    pub mesa_destino: Option<String>,
+    pub mesero_anterior: Option<String>,
+    pub mesero_nuevo: Option<String>,
+    pub detalle: Option<String>,
+    pub motivo: Option<String>,
+    pub usuario_nombre: Option<String>,
+    /// POS | APP
+    pub origen: String,
+    pub fecha: String,
+}
+
 // ─── v2.3.69 — Sub-cuentas (división de cuenta) ──────────────────────────
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -182,6 +321,54 @@ pub struct Subcuenta {
     /// numero asignado a la venta cuando ya se cobró (ej. NV-001-001-000000042)
     pub venta_numero: Option<String>,
     pub fecha_cobro: Option<String>,
+    /// MONTO (partes iguales, producto `_DIVISION_CUENTA_`) | ITEMS (productos reales)
+    #[serde(default = "default_modo_subcuenta")]
+    pub modo: String,
+    /// Cliente al que se factura esta sub-cuenta (RUC/cédula propios).
+    #[serde(default)]
+    pub cliente_id: Option<i64>,
+    #[serde(default)]
+    pub cliente_nombre: Option<String>,
+    /// Solo en modo ITEMS: porción de cada item del pedido que paga esta sub-cuenta.
+    #[serde(default)]
+    pub items: Vec<SubcuentaItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubcuentaItem {
+    pub pedido_item_id: i64,
+    pub producto_id: i64,
+    pub producto_nombre: String,
+    /// Puede ser fraccionaria (ej. 1/3 de una pizza compartida).
+    pub cantidad: f64,
+    pub precio_unit: f64,
+}
+
+/// Asignación de items a una sub-cuenta al dividir por consumo.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AsignacionSubcuenta {
+    #[serde(default)]
+    pub cliente_id: Option<i64>,
+    pub items: Vec<ParteItem>,
+}
+
+/// Parte de un item del pedido. Se indica `cantidad` (unidades) o `fraccion`
+/// (0–1 de la línea completa, para compartir un plato entre varios).
+#[derive(Debug, Deserialize, Clone)]
+pub struct ParteItem {
+    pub pedido_item_id: i64,
+    #[serde(default)]
+    pub cantidad: Option<f64>,
+    #[serde(default)]
+    pub fraccion: Option<f64>,
+}
+
+/// Líneas listas para `registrar_venta` al cobrar una sub-cuenta.
+#[derive(Debug, Serialize, Clone)]
+pub struct VentaSubcuenta {
+    pub subcuenta_id: i64,
+    pub cliente_id: Option<i64>,
+    pub items: Vec<crate::models::VentaDetalle>,
 }
 
 /// Resultado de marcar una sub-cuenta como cobrada — el frontend usa
@@ -193,6 +380,239 @@ pub struct ResultadoCobroSubcuenta {
     pub pendientes: i32,
 }
 
+// ─── Para llevar y delivery ──────────────────────────────────────────────
+
+/// Datos para abrir un pedido para llevar o a domicilio.
+#[derive(Debug, Deserialize, Clone)]
+pub struct NuevaEntrega {
+    /// LLEVAR | DELIVERY
+    pub tipo: String,
+    #[serde(default)]
+    pub cliente_id: Option<i64>,
+    #[serde(default)]
+    pub cliente_nombre: Option<String>,
+    #[serde(default)]
+    pub telefono: Option<String>,
+    /// Dirección guardada del cliente (`direcciones_cliente`).
+    #[serde(default)]
+    pub direccion_id: Option<i64>,
+    /// Dirección nueva; si hay `cliente_id` se guarda en sus direcciones.
+    #[serde(default)]
+    pub direccion: Option<String>,
+    #[serde(default)]
+    pub referencia: Option<String>,
+    /// 'YYYY-MM-DD HH:MM'. None = ahora + `rest_delivery_tiempo_min` / `rest_llevar_tiempo_min`.
+    #[serde(default)]
+    pub hora_prometida: Option<String>,
+    /// None = precio del producto `_ENVIO_DELIVERY_`. Solo DELIVERY.
+    #[serde(default)]
+    pub costo_envio: Option<f64>,
+    /// El repartidor cobra al entregar y liquida en caja al volver.
+    #[serde(default)]
+    pub contra_entrega: bool,
+    #[serde(default)]
+    pub observacion: Option<String>,
+}
+
+/// Pedido para llevar / delivery con su estado logístico.
+/// estado: PREPARANDO | LISTO | EN_CAMINO | ENTREGADO | CANCELADO
+#[derive(Debug, Serialize, Clone)]
+pub struct Entrega {
+    pub id: i64,
+    pub pedido_id: i64,
+    pub tipo: String,
+    pub cliente_id: Option<i64>,
+    pub cliente_nombre: Option<String>,
+    pub telefono: Option<String>,
+    pub direccion: Option<String>,
+    pub referencia: Option<String>,
+    pub hora_prometida: String,
+    /// Negativo = atrasada
+    pub minutos_restantes: i64,
+    pub costo_envio: f64,
+    pub estado: String,
+    pub repartidor_id: Option<i64>,
+    pub repartidor_nombre: Option<String>,
+    pub contra_entrega: bool,
+    /// Total del pedido (incluye envío)
+    pub total: f64,
+    pub cobrado: bool,
+    pub liquidado: bool,
+    pub fecha_listo: Option<String>,
+    pub fecha_salida: Option<String>,
+    pub fecha_entrega: Option<String>,
+    pub created_at: String,
+}
+
+/// Efectivo contra entrega que un repartidor debe (o ya entregó) en la caja abierta.
+#[derive(Debug, Serialize, Clone)]
+pub struct LiquidacionRepartidor {
+    pub repartidor_id: Option<i64>,
+    pub repartidor_nombre: String,
+    /// En camino o entregadas sin cobrar todavía en caja
+    pub pendientes: Vec<Entrega>,
+    pub monto_pendiente: f64,
+    pub liquidadas_caja: i64,
+    pub monto_liquidado_caja: f64,
+}
+
+// ─── Analítica de salón ──────────────────────────────────────────────────
+
+/// Cubiertos y consumo de un día y turno (caja). Solo pedidos cobrados.
+#[derive(Debug, Serialize, Clone)]
+pub struct CubiertosTurno {
+    pub fecha: String,
+    pub caja_id: Option<i64>,
+    /// Apertura → cierre de la caja y cajero
+    pub turno: String,
+    pub pedidos: i64,
+    pub cubiertos: i64,
+    pub ventas: f64,
+    pub ticket_promedio: f64,
+    pub promedio_cubierto: f64,
+}
+
+/// Rotación y permanencia por zona (sin canales para llevar / delivery).
+#[derive(Debug, Serialize, Clone)]
+pub struct RotacionZona {
+    pub zona_id: Option<i64>,
+    pub zona_nombre: String,
+    /// Mesas activas de la zona
+    pub mesas: i64,
+    pub pedidos: i64,
+    pub cubiertos: i64,
+    /// Pedidos cobrados por mesa y por día del rango
+    pub rotacion: f64,
+    /// Minutos de apertura a cobro
+    pub permanencia_promedio_min: f64,
+    pub permanencia_maxima_min: f64,
+}
+
+/// Ventas, propinas y anulaciones de un mesero en el rango.
+#[derive(Debug, Serialize, Clone)]
+pub struct DesempenoMesero {
+    pub mesero_id: Option<i64>,
+    pub mesero_nombre: String,
+    pub pedidos: i64,
+    pub cubiertos: i64,
+    pub ventas: f64,
+    pub ticket_promedio: f64,
+    pub promedio_cubierto: f64,
+    pub propinas: f64,
+    /// Pedidos cancelados y lo que tenían cargado
+    pub anulados: i64,
+    pub monto_anulado: f64,
+}
+
+/// Celda del mapa de calor: día de la semana × hora de apertura del pedido.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CeldaHora {
+    /// 0 = domingo … 6 = sábado
+    pub dia_semana: i64,
+    pub hora: i64,
+    pub pedidos: i64,
+    pub cubiertos: i64,
+    pub ventas: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReporteSalon {
+    pub desde: String,
+    pub hasta: String,
+    pub dias: i64,
+    pub pedidos: i64,
+    pub cubiertos: i64,
+    pub ventas: f64,
+    pub promedio_cubierto: f64,
+    pub turnos: Vec<CubiertosTurno>,
+    pub zonas: Vec<RotacionZona>,
+    pub meseros: Vec<DesempenoMesero>,
+    pub horas: Vec<CeldaHora>,
+}
+
+// ─── Pantallas de cocina (KDS web) ───────────────────────────────────────
+
+/// Pantalla de cocina recién creada: el token va en la URL que se abre en el
+/// navegador de la TV / mini PC.
+#[derive(Debug, Serialize, Clone)]
+pub struct PantallaKds {
+    /// ID en `app_tokens` (para revocarla como cualquier dispositivo)
+    pub id: i64,
+    pub token: String,
+    /// `http://<ip>:<puerto>/kds?token=…` (None si no se detectó la IP local)
+    pub url: Option<String>,
+}
+
+// ─── Propinas ────────────────────────────────────────────────────────────
+
+/// Propina que acompaña un cobro.
+/// tipo: SERVICIO (cargo sugerido, `rest_propina_servicio_pct`) | VOLUNTARIA.
+/// Sin `monto` se calcula con `porcentaje` sobre la base sin IVA.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PropinaCobro {
+    #[serde(default = "default_tipo_propina")]
+    pub tipo: String,
+    #[serde(default)]
+    pub monto: Option<f64>,
+    #[serde(default)]
+    pub porcentaje: Option<f64>,
+    /// Vacío = forma de pago de la venta (EFECTIVO si fue MIXTO/CREDITO).
+    #[serde(default)]
+    pub forma_pago: Option<String>,
+}
+
+/// Cargo por servicio sugerido para mostrar antes de cobrar.
+#[derive(Debug, Serialize, Clone)]
+pub struct SugerenciaPropina {
+    pub base: f64,
+    pub porcentaje: f64,
+    pub monto: f64,
+}
+
+/// Lo que le toca a un mesero en un turno (caja) según la regla de reparto.
+#[derive(Debug, Serialize, Clone)]
+pub struct RepartoPropina {
+    pub caja_id: Option<i64>,
+    /// Apertura → cierre de la caja y cajero
+    pub turno: String,
+    pub mesero_id: Option<i64>,
+    pub mesero_nombre: String,
+    pub pedidos: i64,
+    /// Ventas sin IVA del mesero en el turno (peso en POOL_VENTAS)
+    pub ventas: f64,
+    /// Propinas de sus propias mesas
+    pub generado: f64,
+    pub asignado: f64,
+}
+
+/// Acumulado por mesero en el período.
+#[derive(Debug, Serialize, Clone)]
+pub struct PropinaMesero {
+    pub mesero_id: Option<i64>,
+    pub mesero_nombre: String,
+    pub turnos: i64,
+    pub generado: f64,
+    pub asignado: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReportePropinas {
+    pub desde: String,
+    pub hasta: String,
+    /// INDIVIDUAL | POOL_IGUAL | POOL_VENTAS
+    pub regla: String,
+    pub casa_pct: f64,
+    pub cocina_pct: f64,
+    pub total_propinas: f64,
+    pub total_efectivo: f64,
+    /// Tarjeta / transferencia
+    pub total_otros: f64,
+    pub total_casa: f64,
+    pub total_cocina: f64,
+    pub repartos: Vec<RepartoPropina>,
+    pub por_mesero: Vec<PropinaMesero>,
+}
+
 /// Item enriquecido para la vista de cocina (incluye mesa para context).
 #[derive(Debug, Serialize, Clone)]
 pub struct ItemCocina {
@@ -204,9 +624,133 @@ pub struct ItemCocina {
     pub producto_nombre: String,
     pub cantidad: f64,
     pub info_adicional: Option<String>,
+    pub modificadores: Option<String>,
     pub estado_cocina: String,
     pub fecha_envio_cocina: Option<String>,
     pub minutos_en_cocina: Option<i64>,
+    pub curso: Option<String>,
+    /// Estación que lo prepara ('General' si la categoría no tiene estación).
+    pub estacion_nombre: String,
+    /// Minutos desde que salió el primer item aún activo de ese pedido.
+    pub minutos_ticket: Option<i64>,
+    /// Objetivo de preparación del producto (`rest_producto_tiempos`).
+    pub minutos_objetivo: Option<i64>,
+    /// Sin terminar y pasado del objetivo.
+    pub atrasado: bool,
+}
+
+// ─── Tiempos de cocina ───────────────────────────────────────────────────
+
+/// Objetivo de preparación y tiempo por defecto de un producto.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TiempoProducto {
+    pub producto_id: i64,
+    #[serde(default)]
+    pub producto_nombre: Option<String>,
+    #[serde(default)]
+    pub minutos_objetivo: Option<i64>,
+    /// Tiempo con el que entra el item si el mesero no elige otro.
+    #[serde(default)]
+    pub curso_default: Option<String>,
+}
+
+/// Estadística de preparación por estación (o por producto) en un rango.
+/// Tiempo de preparación = de `fecha_envio_cocina` a `fecha_listo`.
+#[derive(Debug, Serialize, Clone)]
+pub struct TiemposPreparacion {
+    pub id: Option<i64>,
+    pub nombre: String,
+    /// Items terminados (con fecha_listo) en el rango
+    pub items: i64,
+    pub promedio_min: f64,
+    pub maximo_min: f64,
+    /// % de items terminados dentro del objetivo (solo items con objetivo)
+    pub a_tiempo_pct: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReporteTiemposCocina {
+    pub fecha_desde: String,
+    pub fecha_hasta: String,
+    pub estaciones: Vec<TiemposPreparacion>,
+    pub productos: Vec<TiemposPreparacion>,
+}
+
+// ─── Modificadores de producto ───────────────────────────────────────────
+
+/// Grupo de modificadores ("Término de la carne", "Extras") con sus opciones.
+/// `min_selecciones > 0` lo vuelve obligatorio; `max_selecciones = 0` = sin límite.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GrupoModificadores {
+    pub id: Option<i64>,
+    pub nombre: String,
+    #[serde(default)]
+    pub min_selecciones: i32,
+    #[serde(default)]
+    pub max_selecciones: i32,
+    #[serde(default)]
+    pub orden: i32,
+    #[serde(default = "default_true")]
+    pub activo: bool,
+    #[serde(default)]
+    pub opciones: Vec<Modificador>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Modificador {
+    pub id: Option<i64>,
+    pub nombre: String,
+    /// Se suma al precio unitario del item (puede ser 0 o negativo).
+    #[serde(default)]
+    pub precio_delta: f64,
+    /// Insumo que consume (ej. "extra queso" → 0.05 kg de queso). Opcional.
+    #[serde(default)]
+    pub producto_insumo_id: Option<i64>,
+    #[serde(default)]
+    pub cantidad_insumo: f64,
+    #[serde(default)]
+    pub orden: i32,
+    #[serde(default = "default_true")]
+    pub activo: bool,
+}
+
+// ─── Estaciones de preparación y comandas por estación ───────────────────
+
+/// Estación de preparación (Parrilla, Barra, Fríos) con su impresora.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Estacion {
+    pub id: Option<i64>,
+    pub nombre: String,
+    /// Destino de impresión (nombre, `tcp://ip:9100`, `cups://cola`, ...).
+    #[serde(default)]
+    pub impresora: String,
+    /// Se usa si la principal no responde. Vacío = impresora de cocina general.
+    #[serde(default)]
+    pub impresora_respaldo: String,
+    #[serde(default)]
+    pub orden: i32,
+    #[serde(default = "default_true")]
+    pub activa: bool,
+    /// IDs de `categorias` que prepara esta estación.
+    #[serde(default)]
+    pub categorias: Vec<i64>,
+}
+
+/// Comanda impresa para una estación (una por estación por envío a cocina).
+#[derive(Debug, Serialize, Clone)]
+pub struct Comanda {
+    pub id: i64,
+    pub pedido_id: i64,
+    pub estacion_id: Option<i64>,
+    pub estacion_nombre: String,
+    pub items: Vec<i64>,
+    /// IMPRESA | RESPALDO | FALLIDA
+    pub estado: String,
+    pub impresora_usada: Option<String>,
+    pub error: Option<String>,
+    pub reimpresiones: i32,
+    pub created_at: String,
+    pub ultima_impresion_at: Option<String>,
 }
 
 // ─── Defaults ────────────────────────────────────────────────────────────
@@ -214,6 +758,22 @@ pub struct ItemCocina {
 fn default_color_zona() -> String {
     "#3b82f6".to_string()
 }
+fn default_modo_subcuenta() -> String {
+    "MONTO".to_string()
+}
+
+fn default_duracion_reserva() -> i32 {
+    90
+}
+fn default_estado_reserva() -> String {
+    "PENDIENTE".to_string()
+}
+fn default_estado_espera() -> String {
+    "ESPERANDO".to_string()
+}
+fn default_tipo_propina() -> String {
+    "VOLUNTARIA".to_string()
+}
 fn default_true() -> bool {
     true
 }
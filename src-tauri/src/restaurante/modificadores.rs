@@ -0,0 +1,374 @@
+//! Modificadores de producto: "término de la carne", "sin cebolla",
+//! "extra queso +$1.00".
+//!
+//! Al agregar un item se valida la selección contra los grupos del producto
+//! ([`resolver`]), el precio unitario sube con la suma de `precio_delta` y lo
+//! elegido queda como snapshot en `rest_pedido_item_modificadores` + texto en
+//! `rest_pedido_items.modificadores` ([`guardar`]). El texto viaja a la
+//! comanda, la pre-cuenta y el `info_adicional` de la venta.
+//!
+//! Los modificadores con insumo descuentan stock cuando el pedido (o la
+//! sub-cuenta) se cobra — ver [`descontar_insumos`] — en la misma transacción
+//! que cierra el pedido. Lo descontado queda en `venta_insumos_modificador`
+//! y vuelve al stock al anular la venta o con la NC ([`reintegrar_insumos`]).
+
+use super::models::{GrupoModificadores, Modificador};
+use rusqlite::{params, Connection};
+
+/// Separador entre modificadores en el texto guardado en el item.
+pub const SEPARADOR: &str = "; ";
+
+/// Opción elegida, ya validada.
+#[derive(Debug, Clone)]
+pub struct Elegido {
+    pub modificador_id: i64,
+    pub grupo_nombre: String,
+    /// El grupo admite una sola opción → el texto lleva el nombre del grupo
+    /// ("Término: Medio"); en grupos múltiples basta la opción ("Sin cebolla").
+    pub grupo_unico: bool,
+    pub nombre: String,
+    pub precio_delta: f64,
+    pub producto_insumo_id: Option<i64>,
+    pub cantidad_insumo: f64,
+}
+
+/// Valida `seleccion` (ids de `rest_modificadores`) contra los grupos activos
+/// del producto: que cada opción pertenezca a un grupo del producto y que cada
+/// grupo cumpla su mínimo/máximo. Devuelve lo elegido en el orden de los grupos.
+pub fn resolver(conn: &Connection, producto_id: i64, seleccion: &[i64]) -> Result<Vec<Elegido>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT g.id, g.nombre, g.min_selecciones, g.max_selecciones
+             FROM rest_producto_modificador_grupos pg
+             JOIN rest_modificador_grupos g ON g.id = pg.grupo_id
+             WHERE pg.producto_id = ?1 AND g.activo = 1
+             ORDER BY pg.orden, g.orden, g.nombre",
+        )
+        .map_err(|e| e.to_string())?;
+    let grupos: Vec<(i64, String, i32, i32)> = stmt
+        .query_map(params![producto_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut elegidos: Vec<(i64, Elegido)> = Vec::new();
+    for (pos, id) in seleccion.iter().enumerate() {
+        if seleccion[..pos].contains(id) {
+            return Err("Modificador repetido en la selección".to_string());
+        }
+        let (grupo_id, nombre, precio_delta, producto_insumo_id, cantidad_insumo): (i64, String, f64, Option<i64>, f64) =
+            conn.query_row(
+                "SELECT grupo_id, nombre, precio_delta, producto_insumo_id, cantidad_insumo
+                 FROM rest_modificadores WHERE id = ?1 AND activo = 1",
+                params![id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
+            )
+            .map_err(|_| format!("Modificador {} no encontrado o inactivo", id))?;
+        let (_, grupo_nombre, _, max) = grupos
+            .iter()
+            .find(|(gid, ..)| *gid == grupo_id)
+            .ok_or_else(|| format!("'{}' no aplica a este producto", nombre))?;
+        elegidos.push((
+            grupo_id,
+            Elegido {
+                modificador_id: *id,
+                grupo_nombre: grupo_nombre.clone(),
+                grupo_unico: *max == 1,
+                nombre,
+                precio_delta,
+                producto_insumo_id,
+                cantidad_insumo,
+            },
+        ));
+    }
+
+    for (grupo_id, nombre, min, max) in &grupos {
+        let n = elegidos.iter().filter(|(g, _)| g == grupo_id).count() as i32;
+        if n < *min {
+            return Err(if *min == 1 {
+                format!("Elija una opción de '{}'", nombre)
+            } else {
+                format!("Elija al menos {} opciones de '{}'", min, nombre)
+            });
+        }
+        if *max > 0 && n > *max {
+            return Err(format!("'{}' admite máximo {} opción(es)", nombre, max));
+        }
+    }
+
+    let orden = |g: i64| grupos.iter().position(|(id, ..)| *id == g).unwrap_or(usize::MAX);
+    elegidos.sort_by_key(|(g, _)| orden(*g));
+    Ok(elegidos.into_iter().map(|(_, e)| e).collect())
+}
+
+/// Grupos con sus opciones. Con `producto_id` solo los asignados a ese
+/// producto (en su orden); `solo_activos` oculta grupos/opciones dados de baja.
+pub fn listar_grupos(
+    conn: &Connection,
+    producto_id: Option<i64>,
+    solo_activos: bool,
+) -> Result<Vec<GrupoModificadores>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT g.id, g.nombre, g.min_selecciones, g.max_selecciones, g.orden, g.activo
+             FROM rest_modificador_grupos g
+             LEFT JOIN rest_producto_modificador_grupos pg ON pg.grupo_id = g.id AND pg.producto_id = ?1
+             WHERE (?1 IS NULL OR pg.producto_id IS NOT NULL)
+               AND (?2 = 0 OR g.activo = 1)
+             ORDER BY COALESCE(pg.orden, 0), g.orden, g.nombre",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut grupos: Vec<GrupoModificadores> = stmt
+        .query_map(params![producto_id, solo_activos as i32], |r| {
+            Ok(GrupoModificadores {
+                id: Some(r.get(0)?),
+                nombre: r.get(1)?,
+                min_selecciones: r.get(2)?,
+                max_selecciones: r.get(3)?,
+                orden: r.get(4)?,
+                activo: r.get::<_, i32>(5)? != 0,
+                opciones: Vec::new(),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut op_stmt = conn
+        .prepare(
+            "SELECT id, nombre, precio_delta, producto_insumo_id, cantidad_insumo, orden, activo
+             FROM rest_modificadores
+             WHERE grupo_id = ?1 AND (?2 = 0 OR activo = 1)
+             ORDER BY orden, nombre",
+        )
+        .map_err(|e| e.to_string())?;
+    for g in grupos.iter_mut() {
+        g.opciones = op_stmt
+            .query_map(params![g.id, solo_activos as i32], |r| {
+                Ok(Modificador {
+                    id: Some(r.get(0)?),
+                    nombre: r.get(1)?,
+                    precio_delta: r.get(2)?,
+                    producto_insumo_id: r.get(3)?,
+                    cantidad_insumo: r.get(4)?,
+                    orden: r.get(5)?,
+                    activo: r.get::<_, i32>(6)? != 0,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(grupos)
+}
+
+/// Suma de los `precio_delta` elegidos (se agrega al precio unitario del item).
+pub fn precio_extra(elegidos: &[Elegido]) -> f64 {
+    elegidos.iter().map(|e| e.precio_delta).sum()
+}
+
+/// Texto legible de la selección, `None` si no hay modificadores.
+pub fn texto(elegidos: &[Elegido]) -> Option<String> {
+    if elegidos.is_empty() {
+        return None;
+    }
+    Some(
+        elegidos
+            .iter()
+            .map(|e| {
+                let base = if e.grupo_unico {
+                    format!("{}: {}", e.grupo_nombre, e.nombre)
+                } else {
+                    e.nombre.clone()
+                };
+                if e.precio_delta.abs() >= 0.005 {
+                    format!("{} ({:+.2})", base, e.precio_delta)
+                } else {
+                    base
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(SEPARADOR),
+    )
+}
+
+/// Guarda el snapshot de lo elegido y el texto en el item recién insertado.
+pub fn guardar(conn: &Connection, pedido_item_id: i64, elegidos: &[Elegido]) -> Result<(), String> {
+    for e in elegidos {
+        conn.execute(
+            "INSERT INTO rest_pedido_item_modificadores
+             (pedido_item_id, modificador_id, grupo_nombre, nombre, precio_delta, producto_insumo_id, cantidad_insumo)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                pedido_item_id,
+                e.modificador_id,
+                e.grupo_nombre,
+                e.nombre,
+                e.precio_delta,
+                e.producto_insumo_id,
+                e.cantidad_insumo
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    conn.execute(
+        "UPDATE rest_pedido_items SET modificadores = ?1 WHERE id = ?2",
+        params![texto(elegidos), pedido_item_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// `info_adicional` para la línea de venta: modificadores + observación.
+pub fn info_venta(modificadores: Option<&str>, info_adicional: Option<&str>) -> Option<String> {
+    let partes: Vec<&str> = [modificadores, info_adicional]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    if partes.is_empty() {
+        None
+    } else {
+        Some(partes.join(SEPARADOR))
+    }
+}
+
+/// Descuenta el stock de los insumos de modificadores al cobrar.
+///
+/// `partes` = `(pedido_item_id, cantidad cobrada del item)`; con división por
+/// items la cantidad puede ser una fracción de la línea. El movimiento queda en
+/// kardex como `VENTA_MODIFICADOR` con `referencia_id = venta_id` y en
+/// `venta_insumos_modificador` para poder reintegrarlo.
+pub fn descontar_insumos(conn: &Connection, venta_id: i64, partes: &[(i64, f64)]) -> Result<(), String> {
+    let (numero, usuario, establecimiento): (String, Option<String>, String) = conn
+        .query_row(
+            "SELECT numero, usuario, COALESCE(establecimiento, '001') FROM ventas WHERE id = ?1",
+            params![venta_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .map_err(|_| "Venta no encontrada".to_string())?;
+    let est_id = crate::commands::stock::establecimiento_por_codigo(conn, &establecimiento)?;
+
+    for (item_id, cantidad_item) in partes {
+        let plato_id: Option<i64> = conn
+            .query_row("SELECT producto_id FROM rest_pedido_items WHERE id = ?1", params![item_id], |r| r.get(0))
+            .map_err(|_| format!("Item #{} del pedido no encontrado", item_id))?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT producto_insumo_id, cantidad_insumo, nombre
+                 FROM rest_pedido_item_modificadores
+                 WHERE pedido_item_id = ?1 AND producto_insumo_id IS NOT NULL AND cantidad_insumo > 0",
+            )
+            .map_err(|e| e.to_string())?;
+        let insumos: Vec<(i64, f64, String)> = stmt
+            .query_map(params![item_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        drop(stmt);
+
+        for (insumo_id, cantidad_insumo, nombre_mod) in insumos {
+            let total = cantidad_insumo * cantidad_item;
+            let (omite, costo): (bool, f64) = conn
+                .query_row(
+                    "SELECT COALESCE(es_servicio, 0) + COALESCE(no_controla_stock, 0) > 0, precio_costo
+                     FROM productos WHERE id = ?1",
+                    params![insumo_id],
+                    |r| Ok((r.get(0)?, r.get(1)?)),
+                )
+                .map_err(|_| format!("Insumo {} del modificador '{}' no existe", insumo_id, nombre_mod))?;
+            if omite {
+                continue;
+            }
+            let mov = crate::commands::stock::mover(conn, insumo_id, est_id, -total)?;
+            let _ = conn.execute(
+                "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, establecimiento_id, motivo)
+                 VALUES (?1, 'VENTA_MODIFICADOR', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    insumo_id,
+                    -total,
+                    mov.anterior,
+                    mov.nuevo,
+                    costo,
+                    venta_id,
+                    usuario,
+                    est_id,
+                    format!("Venta {} (modificador: {})", numero, nombre_mod)
+                ],
+            );
+            conn.execute(
+                "INSERT INTO venta_insumos_modificador (venta_id, producto_id, insumo_id, cantidad, costo_unitario, establecimiento_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![venta_id, plato_id, insumo_id, total, costo, est_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Reintegra al stock los insumos de modificadores descontados en una venta.
+///
+/// `devolucion = None` reintegra todo (anulación). Con `(plato, cantidad)` se
+/// devuelve la parte proporcional a lo devuelto de ese plato en la venta (NC
+/// parcial), sin pasar nunca de lo que queda por reintegrar. `tipo` va al kardex.
+pub fn reintegrar_insumos(
+    conn: &Connection,
+    venta_id: i64,
+    devolucion: Option<(i64, f64)>,
+    tipo: &str,
+    motivo: &str,
+    usuario: &str,
+) -> Result<(), String> {
+    let (producto_id, fraccion) = match devolucion {
+        None => (None, 1.0),
+        Some((pid, cantidad)) => {
+            let vendido: f64 = conn
+                .query_row(
+                    "SELECT COALESCE(SUM(cantidad), 0) FROM venta_detalles WHERE venta_id = ?1 AND producto_id = ?2",
+                    params![venta_id, pid],
+                    |r| r.get(0),
+                )
+                .unwrap_or(0.0);
+            let fraccion = if vendido > 0.0 { (cantidad / vendido).min(1.0) } else { 1.0 };
+            (Some(pid), fraccion)
+        }
+    };
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, insumo_id, cantidad - reintegrado, cantidad, costo_unitario, establecimiento_id
+             FROM venta_insumos_modificador
+             WHERE venta_id = ?1 AND (?2 IS NULL OR producto_id = ?2) AND cantidad - reintegrado > 1e-9",
+        )
+        .map_err(|e| e.to_string())?;
+    let filas: Vec<(i64, i64, f64, f64, f64, i64)> = stmt
+        .query_map(params![venta_id, producto_id], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    drop(stmt);
+
+    for (id, insumo_id, pendiente, cantidad, costo, est_id) in filas {
+        let cant = (cantidad * fraccion).min(pendiente);
+        if cant <= 1e-9 {
+            continue;
+        }
+        let mov = crate::commands::stock::mover(conn, insumo_id, est_id, cant)
+            .map_err(|e| format!("Error reintegrando insumo {}: {}", insumo_id, e))?;
+        conn.execute(
+            "INSERT INTO movimientos_inventario (producto_id, tipo, cantidad, stock_anterior, stock_nuevo, costo_unitario, referencia_id, usuario, establecimiento_id, motivo)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![insumo_id, tipo, cant, mov.anterior, mov.nuevo, costo, venta_id, usuario, est_id, motivo],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE venta_insumos_modificador SET reintegrado = reintegrado + ?1 WHERE id = ?2",
+            params![cant, id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
@@ -145,7 +145,9 @@ pub fn generar_pre_cuenta(
     let mut grupos: BTreeMap<String, Linea> = BTreeMap::new();
     for it in &detalle.items {
         let nombre = it.producto_nombre.clone().unwrap_or_else(|| "?".into());
-        let info_key = it.info_adicional.clone().unwrap_or_default();
+        // Modificadores + observación en una sola línea "↳" (la pre-cuenta es del cliente)
+        let info = super::modificadores::info_venta(it.modificadores.as_deref(), it.info_adicional.as_deref());
+        let info_key = info.clone().unwrap_or_default();
         let key = format!("{}|{}", nombre, info_key);
         grupos
             .entry(key)
@@ -155,7 +157,7 @@ pub fn generar_pre_cuenta(
             })
             .or_insert(Linea {
                 nombre: nombre.clone(),
-                info: it.info_adicional.clone(),
+                info: info.clone(),
                 cantidad: it.cantidad,
                 precio: it.precio_unit,
                 subtotal: it.cantidad * it.precio_unit,
@@ -360,7 +362,9 @@ pub fn generar_pre_cuenta_pdf(
     let mut grupos: BTreeMap<String, Linea> = BTreeMap::new();
     for it in &detalle.items {
         let nombre = it.producto_nombre.clone().unwrap_or_else(|| "?".into());
-        let info_key = it.info_adicional.clone().unwrap_or_default();
+        // Modificadores + observación en una sola línea "↳" (la pre-cuenta es del cliente)
+        let info = super::modificadores::info_venta(it.modificadores.as_deref(), it.info_adicional.as_deref());
+        let info_key = info.clone().unwrap_or_default();
         let key = format!("{}|{}", nombre, info_key);
         grupos
             .entry(key)
@@ -370,7 +374,7 @@ pub fn generar_pre_cuenta_pdf(
             })
             .or_insert(Linea {
                 nombre: nombre.clone(),
-                info: it.info_adicional.clone(),
+                info: info.clone(),
                 cantidad: it.cantidad,
                 subtotal: it.cantidad * it.precio_unit,
             });
@@ -475,10 +479,13 @@ pub fn generar_pre_cuenta_pdf(
 /// COCINA = items con destino_preparacion='COCINA'
 /// BARRA  = items con destino_preparacion='BARRA'
 /// AMBOS  = todos (1 ticket combinado)
+/// ESTACION = items ya filtrados por estación (ver `estaciones`); el nombre
+///            de la estación va de título
 pub enum DestinoComanda {
     Cocina,
     Barra,
     Ambos,
+    Estacion(String),
 }
 
 /// v2.4.5 — Genera la comanda de cocina como PDF (formato 80mm) usando genpdf.
@@ -497,11 +504,13 @@ pub fn generar_comanda_cocina_pdf(
     filtro: DestinoComanda,
     config: &HashMap<String, String>,
 ) -> Result<Option<Vec<u8>>, String> {
-    let items_filtrados: Vec<&PedidoItem> = items.iter().filter(|i| match filtro {
+    let mut items_filtrados: Vec<&PedidoItem> = items.iter().filter(|i| i.estado_cocina != "RETENIDO").filter(|i| match filtro {
         DestinoComanda::Cocina => i.destino_preparacion == "COCINA",
         DestinoComanda::Barra  => i.destino_preparacion == "BARRA",
-        DestinoComanda::Ambos  => i.destino_preparacion != "DIRECTO",
+        DestinoComanda::Ambos | DestinoComanda::Estacion(_) => i.destino_preparacion != "DIRECTO",
     }).collect();
+    // Agrupar por tiempo (entrada, fuerte, postre) en el orden en que se sirven
+    items_filtrados.sort_by_key(|i| super::tiempos::orden_curso(i.curso.as_deref()));
 
     if items_filtrados.is_empty() {
         return Ok(None);
@@ -535,12 +544,13 @@ pub fn generar_comanda_cocina_pdf(
     let s_barra  = Style::new().with_font_size(8).bold();
 
     // ── Título según destino ──
-    let titulo = match filtro {
-        DestinoComanda::Cocina => "COCINA",
-        DestinoComanda::Barra  => "BARRA",
-        DestinoComanda::Ambos  => "COMANDA",
+    let titulo = match &filtro {
+        DestinoComanda::Cocina => "COCINA".to_string(),
+        DestinoComanda::Barra  => "BARRA".to_string(),
+        DestinoComanda::Ambos  => "COMANDA".to_string(),
+        DestinoComanda::Estacion(nombre) => nombre.to_uppercase(),
     };
-    doc.push(p_aligned_pdf(titulo, s_titulo_destino, Alignment::Center));
+    doc.push(p_aligned_pdf(&titulo, s_titulo_destino, Alignment::Center));
     doc.push(p_aligned_pdf("================", s_normal, Alignment::Center));
 
     // ── Mesa cabecera GRANDE para lectura desde lejos ──
@@ -572,7 +582,14 @@ pub fn generar_comanda_cocina_pdf(
     doc.push(p_aligned_pdf("----------------", s_normal, Alignment::Center));
 
     // ── Items: cantidad x nombre + obs/info_adicional + tag [BARRA] si Ambos ──
+    let mut curso_actual: Option<&str> = None;
     for it in &items_filtrados {
+        if let Some(curso) = it.curso.as_deref() {
+            if curso_actual != Some(curso) {
+                doc.push(p_aligned_pdf(&format!("-- {} --", curso), s_bold, Alignment::Center));
+                curso_actual = Some(curso);
+            }
+        }
         let nombre = it.producto_nombre.as_deref().unwrap_or("(sin nombre)");
         let cant = if it.cantidad.fract() == 0.0 {
             format!("{}", it.cantidad as i64)
@@ -595,6 +612,11 @@ pub fn generar_comanda_cocina_pdf(
             Alignment::Left,
         ));
 
+        if let Some(mods) = it.modificadores.as_deref() {
+            for m in mods.split(super::modificadores::SEPARADOR).filter(|m| !m.is_empty()) {
+                doc.push(p_aligned_pdf(&format!("  + {}", m), s_obs, Alignment::Left));
+            }
+        }
         if let Some(info) = it.info_adicional.as_deref() {
             if !info.is_empty() {
                 doc.push(p_aligned_pdf(&format!("  -> {}", info), s_obs, Alignment::Left));
@@ -633,11 +655,11 @@ pub fn generar_comanda_cocina(
     config: &HashMap<String, String>,
 ) -> Option<Vec<u8>> {
     // Filtrar items según el destino de la comanda
-    let items_filtrados: Vec<&PedidoItem> = items.iter().filter(|i| {
+    let items_filtrados: Vec<&PedidoItem> = items.iter().filter(|i| i.estado_cocina != "RETENIDO").filter(|i| {
         match filtro {
             DestinoComanda::Cocina => i.destino_preparacion == "COCINA",
             DestinoComanda::Barra  => i.destino_preparacion == "BARRA",
-            DestinoComanda::Ambos  => i.destino_preparacion != "DIRECTO", // todo excepto directo
+            DestinoComanda::Ambos | DestinoComanda::Estacion(_) => i.destino_preparacion != "DIRECTO", // todo excepto directo
         }
     }).collect();
 
@@ -662,10 +684,11 @@ pub fn generar_comanda_cocina(
     ticket.extend_from_slice(esc_init);
 
     // === TÍTULO según destino ===
-    let titulo = match filtro {
-        DestinoComanda::Cocina => "🍳 COCINA",
-        DestinoComanda::Barra  => "🍷 BARRA",
-        DestinoComanda::Ambos  => "🍽 COMANDA",
+    let titulo = match &filtro {
+        DestinoComanda::Cocina => "🍳 COCINA".to_string(),
+        DestinoComanda::Barra  => "🍷 BARRA".to_string(),
+        DestinoComanda::Ambos  => "🍽 COMANDA".to_string(),
+        DestinoComanda::Estacion(nombre) => nombre.to_uppercase(),
     };
     ticket.extend_from_slice(esc_center);
     ticket.extend_from_slice(esc_bold_on);
@@ -710,27 +733,43 @@ pub fn generar_comanda_cocina(
     #[derive(Clone)]
     struct LineaCocina {
         nombre: String,
+        modificadores: Option<String>,
         info: Option<String>,
         cantidad: f64,
         destino: String,
+        curso: Option<String>,
     }
     let mut grupos: BTreeMap<String, LineaCocina> = BTreeMap::new();
     for it in &items_filtrados {
         let nombre = it.producto_nombre.clone().unwrap_or_else(|| "?".into());
         let info_key = it.info_adicional.clone().unwrap_or_default();
-        let key = format!("{}|{}|{}", nombre, info_key, it.destino_preparacion);
+        let mods_key = it.modificadores.clone().unwrap_or_default();
+        // El orden del tiempo va primero en la clave: la comanda sale por tiempos.
+        let orden = super::tiempos::orden_curso(it.curso.as_deref());
+        let key = format!("{}|{}|{}|{}|{}", orden, nombre, mods_key, info_key, it.destino_preparacion);
         grupos
             .entry(key)
             .and_modify(|g| g.cantidad += it.cantidad)
             .or_insert(LineaCocina {
                 nombre: nombre.clone(),
+                modificadores: it.modificadores.clone(),
                 info: it.info_adicional.clone(),
                 cantidad: it.cantidad,
                 destino: it.destino_preparacion.clone(),
+                curso: it.curso.clone(),
             });
     }
 
+    let mut curso_actual: Option<&str> = None;
     for grupo in grupos.values() {
+        if let Some(curso) = grupo.curso.as_deref() {
+            if curso_actual != Some(curso) {
+                ticket.extend_from_slice(esc_bold_on);
+                ticket.extend_from_slice(format!("-- {} --\n", curso).as_bytes());
+                ticket.extend_from_slice(esc_bold_off);
+                curso_actual = Some(curso);
+            }
+        }
         // Cantidad en negrita doble alto, nombre normal
         ticket.extend_from_slice(esc_bold_on);
         ticket.extend_from_slice(esc_double_h);
@@ -744,6 +783,13 @@ pub fn generar_comanda_cocina(
         ticket.push(b'\n');
         ticket.extend_from_slice(esc_bold_off);
 
+        // Modificadores: uno por línea para que cocina no los pase por alto
+        if let Some(ref mods) = grupo.modificadores {
+            for m in mods.split(super::modificadores::SEPARADOR).filter(|m| !m.is_empty()) {
+                ticket.extend_from_slice(format!("    + {}\n", m).as_bytes());
+            }
+        }
+
         // Observación destacada con flecha + indentada
         if let Some(ref info) = grupo.info {
             if !info.is_empty() {
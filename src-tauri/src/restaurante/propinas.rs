@@ -0,0 +1,367 @@
+//! Propinas y cargo por servicio.
+//!
+//! La propina se registra DESPUÉS de crear la venta (al cerrar el pedido o
+//! cobrar una sub-cuenta) y queda en `rest_propinas` + `ventas.propina`:
+//! - no suma a `ventas.total`, así no infla las ventas ni la base de IVA;
+//! - el XML de la factura la lleva en `<propina>` e `importeTotal` la incluye;
+//! - la caja suma solo la propina en EFECTIVO al monto esperado.
+//!
+//! Reparto por turno (caja) con la config `rest_propinas_regla`:
+//! - INDIVIDUAL  — cada mesero se queda con la propina de sus mesas
+//! - POOL_IGUAL  — el pozo del turno se divide en partes iguales
+//! - POOL_VENTAS — el pozo se divide en proporción a lo vendido (sin IVA)
+//!
+//! Antes de repartir se separa `rest_propinas_casa_pct` (casa) y
+//! `rest_propinas_cocina_pct` (cocina) del total del turno.
+
+use super::models::{PropinaCobro, PropinaMesero, RepartoPropina, ReportePropinas, SugerenciaPropina};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeMap;
+
+/// Porcentaje por defecto del cargo por servicio (Ecuador: 10%).
+const SERVICIO_PCT_DEFECTO: f64 = 10.0;
+
+fn config_f64(conn: &Connection, key: &str, default: f64) -> f64 {
+    conn.query_row("SELECT value FROM config WHERE key = ?1", params![key], |r| r.get::<_, String>(0))
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+fn round2(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+/// Valida la propina antes de crear la venta; devuelve el tipo normalizado.
+pub fn validar(propina: &PropinaCobro) -> Result<String, String> {
+    let tipo = propina.tipo.trim().to_uppercase();
+    if tipo != "SERVICIO" && tipo != "VOLUNTARIA" {
+        return Err(format!("Tipo de propina inválido: {} (SERVICIO | VOLUNTARIA)", propina.tipo));
+    }
+    if propina.monto.is_some_and(|m| m < 0.0) || propina.porcentaje.is_some_and(|p| p < 0.0) {
+        return Err("La propina no puede ser negativa".to_string());
+    }
+    if tipo == "VOLUNTARIA" && propina.monto.is_none() && propina.porcentaje.is_none() {
+        return Err("Indique el monto o el porcentaje de la propina".to_string());
+    }
+    Ok(tipo)
+}
+
+/// Registra (o reemplaza) la propina de una venta ya creada.
+/// Devuelve el monto registrado; 0 borra la propina de la venta.
+pub fn registrar(
+    conn: &Connection,
+    venta_id: i64,
+    pedido_id: i64,
+    subcuenta_id: Option<i64>,
+    propina: &PropinaCobro,
+) -> Result<f64, String> {
+    let tipo = validar(propina)?;
+    let (total, iva, forma_venta, caja_id): (f64, f64, String, Option<i64>) = conn
+        .query_row(
+            "SELECT total, iva, forma_pago, caja_id FROM ventas WHERE id = ?1",
+            params![venta_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .map_err(|_| "Venta no encontrada".to_string())?;
+    // Con el XML ya firmado (PENDIENTE/DEVUELTA se reenvian tal cual) la
+    // propina no llegaria al SRI y la factura no cuadraria con la caja.
+    let emitida: bool = conn
+        .query_row(
+            "SELECT COALESCE(estado_sri, '') = 'AUTORIZADA' OR xml_firmado IS NOT NULL OR clave_acceso IS NOT NULL
+             FROM ventas WHERE id = ?1",
+            params![venta_id],
+            |r| r.get(0),
+        )
+        .unwrap_or(false);
+    if emitida {
+        return Err("La factura ya fue emitida al SRI; no se puede cambiar la propina".to_string());
+    }
+
+    let base = round2(total - iva);
+    let porcentaje = match (tipo.as_str(), propina.porcentaje) {
+        (_, Some(p)) => Some(p),
+        ("SERVICIO", None) => Some(config_f64(conn, "rest_propina_servicio_pct", SERVICIO_PCT_DEFECTO)),
+        _ => None,
+    };
+    let monto = match (propina.monto, porcentaje) {
+        (Some(m), _) => round2(m),
+        (None, Some(p)) => round2(base * p / 100.0),
+        (None, None) => return Err("Indique el monto o el porcentaje de la propina".to_string()),
+    };
+    if monto < 0.0 {
+        return Err("La propina no puede ser negativa".to_string());
+    }
+
+    conn.execute("DELETE FROM rest_propinas WHERE venta_id = ?1", params![venta_id])
+        .map_err(|e| e.to_string())?;
+    if monto > 0.0 {
+        // MIXTO/CREDITO no dicen cómo se pagó la propina: se asume efectivo.
+        let forma_pago = match propina.forma_pago.as_deref().map(|f| f.trim().to_uppercase()) {
+            Some(f) if !f.is_empty() => f,
+            _ => match forma_venta.as_str() {
+                "MIXTO" | "CREDITO" => "EFECTIVO".to_string(),
+                f => f.to_string(),
+            },
+        };
+        let (mesero_id, mesero_nombre): (Option<i64>, Option<String>) = conn
+            .query_row(
+                "SELECT mesero_id, mesero_nombre FROM rest_pedidos_abiertos WHERE id = ?1",
+                params![pedido_id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .map_err(|_| "Pedido no encontrado".to_string())?;
+        conn.execute(
+            "INSERT INTO rest_propinas
+             (venta_id, pedido_id, subcuenta_id, tipo, porcentaje, base, monto, forma_pago,
+              caja_id, mesero_id, mesero_nombre)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                venta_id, pedido_id, subcuenta_id, tipo, porcentaje, base, monto, forma_pago,
+                caja_id, mesero_id, mesero_nombre
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    conn.execute("UPDATE ventas SET propina = ?1 WHERE id = ?2", params![monto, venta_id])
+        .map_err(|e| e.to_string())?;
+    Ok(monto)
+}
+
+/// Cargo por servicio sugerido para un pedido (o una de sus sub-cuentas),
+/// calculado sobre la base sin IVA.
+pub fn sugerir(conn: &Connection, pedido_id: i64, subcuenta_id: Option<i64>) -> Result<SugerenciaPropina, String> {
+    let detalle = super::commands::obtener_pedido_detalle(conn, pedido_id)?;
+    let base = match subcuenta_id {
+        None => detalle.subtotal,
+        Some(id) => {
+            let total_sub: f64 = conn
+                .query_row(
+                    "SELECT total FROM rest_subcuentas WHERE id = ?1 AND pedido_id = ?2",
+                    params![id, pedido_id],
+                    |r| r.get(0),
+                )
+                .map_err(|_| "Sub-cuenta no encontrada".to_string())?;
+            // La sub-cuenta guarda el total con IVA: se prorratea con la proporción del pedido.
+            if detalle.total > 0.0 { total_sub * detalle.subtotal / detalle.total } else { 0.0 }
+        }
+    };
+    let porcentaje = config_f64(conn, "rest_propina_servicio_pct", SERVICIO_PCT_DEFECTO);
+    Ok(SugerenciaPropina {
+        base: round2(base),
+        porcentaje,
+        monto: round2(base * porcentaje / 100.0),
+    })
+}
+
+/// Participación de un mesero en el turno antes del reparto.
+struct Participante {
+    mesero_id: Option<i64>,
+    mesero_nombre: String,
+    pedidos: i64,
+    ventas: f64,
+    generado: f64,
+}
+
+/// Aplica la regla a un turno. Devuelve (casa, cocina, asignado por participante).
+/// `participantes`: (propina generada, ventas sin IVA) de cada mesero.
+fn repartir(regla: &str, participantes: &[(f64, f64)], casa_pct: f64, cocina_pct: f64) -> (f64, f64, Vec<f64>) {
+    let pozo: f64 = participantes.iter().map(|p| p.0).sum();
+    let casa = round2(pozo * casa_pct / 100.0);
+    let cocina = round2(pozo * cocina_pct / 100.0);
+    let neto = pozo - casa - cocina;
+    let total_ventas: f64 = participantes.iter().map(|p| p.1).sum();
+    let n = participantes.len().max(1) as f64;
+    let asignado = participantes
+        .iter()
+        .map(|&(generado, ventas)| {
+            let v = match regla {
+                "POOL_IGUAL" => neto / n,
+                "POOL_VENTAS" if total_ventas > 0.0 => neto * ventas / total_ventas,
+                "POOL_VENTAS" => neto / n,
+                // INDIVIDUAL: cada uno lo suyo, menos la misma proporción para casa/cocina
+                _ if pozo > 0.0 => generado * neto / pozo,
+                _ => 0.0,
+            };
+            round2(v)
+        })
+        .collect();
+    (casa, cocina, asignado)
+}
+
+/// Reporte de propinas por turno (caja) y por mesero entre dos fechas (AAAA-MM-DD).
+pub fn reporte(conn: &Connection, desde: &str, hasta: &str) -> Result<ReportePropinas, String> {
+    let regla = conn
+        .query_row("SELECT value FROM config WHERE key = 'rest_propinas_regla'", [], |r| r.get::<_, String>(0))
+        .optional()
+        .map_err(|e| e.to_string())?
+        .map(|v| v.trim().to_uppercase())
+        .filter(|v| matches!(v.as_str(), "INDIVIDUAL" | "POOL_IGUAL" | "POOL_VENTAS"))
+        .unwrap_or_else(|| "INDIVIDUAL".to_string());
+    let casa_pct = config_f64(conn, "rest_propinas_casa_pct", 0.0);
+    let cocina_pct = config_f64(conn, "rest_propinas_cocina_pct", 0.0);
+
+    // Ventas de restaurante por turno y mesero: pedidos cobrados enteros + sub-cuentas.
+    // Turno = caja de la venta; clave de mesero = id o, si no tiene, su nombre.
+    let mut turnos: BTreeMap<(i64, String), Participante> = BTreeMap::new();
+    {
+        let mut stmt = conn
+            .prepare(
+                "SELECT v.caja_id, p.mesero_id, COALESCE(p.mesero_nombre, 'Sin mesero'), v.total - v.iva
+                 FROM rest_pedidos_abiertos p JOIN ventas v ON v.id = p.venta_id
+                 WHERE p.estado = 'COBRADO' AND v.anulada = 0
+                   AND date(v.fecha) >= date(?1) AND date(v.fecha) <= date(?2)
+                   AND NOT EXISTS (SELECT 1 FROM rest_subcuentas s WHERE s.pedido_id = p.id)
+                 UNION ALL
+                 SELECT v.caja_id, p.mesero_id, COALESCE(p.mesero_nombre, 'Sin mesero'), v.total - v.iva
+                 FROM rest_subcuentas s
+                 JOIN rest_pedidos_abiertos p ON p.id = s.pedido_id
+                 JOIN ventas v ON v.id = s.venta_id
+                 WHERE s.estado = 'COBRADA' AND v.anulada = 0
+                   AND date(v.fecha) >= date(?1) AND date(v.fecha) <= date(?2)",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![desde, hasta], |r| {
+                Ok((r.get::<_, Option<i64>>(0)?, r.get::<_, Option<i64>>(1)?, r.get::<_, String>(2)?, r.get::<_, f64>(3)?))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (caja_id, mesero_id, mesero_nombre, base) = row.map_err(|e| e.to_string())?;
+            let p = participante(&mut turnos, caja_id, mesero_id, mesero_nombre);
+            p.pedidos += 1;
+            p.ventas += base;
+        }
+    }
+
+    let (mut total_efectivo, mut total_otros) = (0.0, 0.0);
+    {
+        let mut stmt = conn
+            .prepare(
+                "SELECT pr.caja_id, pr.mesero_id, COALESCE(pr.mesero_nombre, 'Sin mesero'), pr.monto, pr.forma_pago
+                 FROM rest_propinas pr JOIN ventas v ON v.id = pr.venta_id
+                 WHERE v.anulada = 0 AND date(v.fecha) >= date(?1) AND date(v.fecha) <= date(?2)",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![desde, hasta], |r| {
+                Ok((
+                    r.get::<_, Option<i64>>(0)?,
+                    r.get::<_, Option<i64>>(1)?,
+                    r.get::<_, String>(2)?,
+                    r.get::<_, f64>(3)?,
+                    r.get::<_, String>(4)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (caja_id, mesero_id, mesero_nombre, monto, forma_pago) = row.map_err(|e| e.to_string())?;
+            participante(&mut turnos, caja_id, mesero_id, mesero_nombre).generado += monto;
+            if forma_pago == "EFECTIVO" { total_efectivo += monto } else { total_otros += monto }
+        }
+    }
+
+    // Agrupar participantes por caja y repartir cada turno por separado.
+    let mut por_caja: BTreeMap<i64, Vec<Participante>> = BTreeMap::new();
+    for ((caja_id, _), p) in turnos {
+        por_caja.entry(caja_id).or_default().push(p);
+    }
+    let (mut total_casa, mut total_cocina) = (0.0, 0.0);
+    let mut repartos = Vec::new();
+    let mut por_mesero: BTreeMap<String, PropinaMesero> = BTreeMap::new();
+    for (caja_id, participantes) in por_caja {
+        let datos: Vec<(f64, f64)> = participantes.iter().map(|p| (p.generado, p.ventas)).collect();
+        let (casa, cocina, asignado) = repartir(&regla, &datos, casa_pct, cocina_pct);
+        total_casa += casa;
+        total_cocina += cocina;
+        let caja = if caja_id > 0 { Some(caja_id) } else { None };
+        let turno: String = caja
+            .and_then(|id| {
+                conn.query_row(
+                    "SELECT fecha_apertura || COALESCE(' → ' || fecha_cierre, '') || COALESCE(' (' || usuario || ')', '')
+                     FROM caja WHERE id = ?1",
+                    params![id],
+                    |r| r.get(0),
+                )
+                .ok()
+            })
+            .unwrap_or_else(|| "Sin caja".to_string());
+        for (p, asignado) in participantes.into_iter().zip(asignado) {
+            let clave = p.mesero_id.map(|id| format!("#{}", id)).unwrap_or_else(|| p.mesero_nombre.clone());
+            let total = por_mesero.entry(clave).or_insert_with(|| PropinaMesero {
+                mesero_id: p.mesero_id,
+                mesero_nombre: p.mesero_nombre.clone(),
+                turnos: 0,
+                generado: 0.0,
+                asignado: 0.0,
+            });
+            total.turnos += 1;
+            total.generado = round2(total.generado + p.generado);
+            total.asignado = round2(total.asignado + asignado);
+            repartos.push(RepartoPropina {
+                caja_id: caja,
+                turno: turno.clone(),
+                mesero_id: p.mesero_id,
+                mesero_nombre: p.mesero_nombre,
+                pedidos: p.pedidos,
+                ventas: round2(p.ventas),
+                generado: round2(p.generado),
+                asignado,
+            });
+        }
+    }
+    let mut por_mesero: Vec<PropinaMesero> = por_mesero.into_values().collect();
+    por_mesero.sort_by(|a, b| b.asignado.total_cmp(&a.asignado));
+
+    Ok(ReportePropinas {
+        desde: desde.to_string(),
+        hasta: hasta.to_string(),
+        regla,
+        casa_pct,
+        cocina_pct,
+        total_propinas: round2(total_efectivo + total_otros),
+        total_efectivo: round2(total_efectivo),
+        total_otros: round2(total_otros),
+        total_casa: round2(total_casa),
+        total_cocina: round2(total_cocina),
+        repartos,
+        por_mesero,
+    })
+}
+
+fn participante(
+    turnos: &mut BTreeMap<(i64, String), Participante>,
+    caja_id: Option<i64>,
+    mesero_id: Option<i64>,
+    mesero_nombre: String,
+) -> &mut Participante {
+    let clave = mesero_id.map(|id| format!("#{}", id)).unwrap_or_else(|| mesero_nombre.clone());
+    turnos.entry((caja_id.unwrap_or(0), clave)).or_insert(Participante {
+        mesero_id,
+        mesero_nombre,
+        pedidos: 0,
+        ventas: 0.0,
+        generado: 0.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn individual_descuenta_casa_y_cocina_proporcional() {
+        let (casa, cocina, asignado) = repartir("INDIVIDUAL", &[(10.0, 100.0), (30.0, 100.0)], 10.0, 15.0);
+        assert_eq!((casa, cocina), (4.0, 6.0));
+        assert_eq!(asignado, vec![7.5, 22.5]);
+    }
+
+    #[test]
+    fn pozo_igual_y_por_ventas() {
+        let datos = [(10.0, 300.0), (30.0, 100.0)];
+        assert_eq!(repartir("POOL_IGUAL", &datos, 0.0, 0.0).2, vec![20.0, 20.0]);
+        assert_eq!(repartir("POOL_VENTAS", &datos, 0.0, 0.0).2, vec![30.0, 10.0]);
+        // Sin ventas registradas cae a partes iguales
+        assert_eq!(repartir("POOL_VENTAS", &[(8.0, 0.0), (0.0, 0.0)], 0.0, 0.0).2, vec![4.0, 4.0]);
+    }
+}
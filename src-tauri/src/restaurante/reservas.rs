@@ -0,0 +1,705 @@
+//! Reservas de mesa, depósitos y lista de espera (walk-ins).
+//!
+//! Una reserva PENDIENTE/CONFIRMADA con mesa asignada bloquea esa mesa en el
+//! grid (estado RESERVADA) desde `rest_reserva_bloqueo_min` minutos antes de
+//! la hora hasta que se sienta. Si pasan `rest_reserva_tolerancia_min`
+//! minutos sin llegar, pasa sola a NO_SHOW (ver [`marcar_no_shows_vencidos`]).
+//!
+//! El depósito sigue el ciclo de los abonos de mesa: entra a la caja activa en
+//! HOLDING y, al sentar la reserva, se traslada a `rest_pedido_abonos` del
+//! pedido nuevo, así el cobro lo descuenta como cualquier abono. En un no-show
+//! queda RETENIDO (o DEVUELTO si `rest_reserva_retener_deposito` = '0').
+//!
+//! La espera estimada de un walk-in se simula en orden de llegada: cada grupo
+//! toma la mesa con capacidad suficiente que se libera antes, estimando la
+//! liberación con la duración promedio de las mesas cobradas en 30 días.
+
+use super::models::{DepositoReserva, EntradaEspera, NoShowCliente, Reserva};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+
+/// Estados en que una reserva sigue viva (bloquea mesa y horario).
+const ACTIVAS: &str = "('PENDIENTE', 'CONFIRMADA')";
+
+/// Depósito a registrar sobre una reserva.
+#[derive(Debug, Deserialize, Clone)]
+pub struct NuevoDeposito {
+    pub monto: f64,
+    #[serde(default = "default_forma_pago")]
+    pub forma_pago: String,
+    #[serde(default)]
+    pub banco_id: Option<i64>,
+    #[serde(default)]
+    pub referencia_pago: Option<String>,
+}
+
+fn default_forma_pago() -> String {
+    "EFECTIVO".to_string()
+}
+
+fn config_i64(conn: &Connection, key: &str, default: i64) -> i64 {
+    conn.query_row("SELECT value FROM config WHERE key = ?1", params![key], |r| r.get::<_, String>(0))
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Acepta 'YYYY-MM-DD HH:MM', con 'T' o con segundos; devuelve 'YYYY-MM-DD HH:MM'.
+pub(super) fn normalizar_fecha_hora(valor: &str) -> Result<String, String> {
+    let v = valor.trim().replace('T', " ");
+    for formato in ["%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(f) = chrono::NaiveDateTime::parse_from_str(&v, formato) {
+            return Ok(f.format("%Y-%m-%d %H:%M").to_string());
+        }
+    }
+    Err(format!("Fecha/hora inválida: {} (use AAAA-MM-DD HH:MM)", valor))
+}
+
+// ─── Reservas ────────────────────────────────────────────────────────────
+
+/// Pasa a NO_SHOW las reservas vencidas y cierra sus depósitos.
+/// Idempotente; se llama al listar mesas y reservas.
+pub fn marcar_no_shows_vencidos(conn: &Connection) -> usize {
+    let tolerancia = config_i64(conn, "rest_reserva_tolerancia_min", 15);
+    let ids: Vec<i64> = {
+        let Ok(mut stmt) = conn.prepare(&format!(
+            "SELECT id FROM rest_reservas
+             WHERE estado IN {ACTIVAS}
+               AND julianday('now', 'localtime') > julianday(fecha_hora) + ?1 / 1440.0"
+        )) else {
+            return 0;
+        };
+        let rows = match stmt.query_map(params![tolerancia], |r| r.get(0)) {
+            Ok(rows) => rows.filter_map(|r| r.ok()).collect(),
+            Err(_) => Vec::new(),
+        };
+        rows
+    };
+    for id in &ids {
+        let _ = cerrar_reserva(conn, *id, "NO_SHOW");
+    }
+    ids.len()
+}
+
+/// Cambia el estado final de una reserva y resuelve sus depósitos en HOLDING.
+fn cerrar_reserva(conn: &Connection, reserva_id: i64, estado: &str) -> Result<(), String> {
+    conn.execute(
+        "UPDATE rest_reservas SET estado = ?1, fecha_cierre = datetime('now', 'localtime') WHERE id = ?2",
+        params![estado, reserva_id],
+    )
+    .map_err(|e| e.to_string())?;
+    let estado_deposito = if estado == "NO_SHOW" && config_i64(conn, "rest_reserva_retener_deposito", 1) == 1 {
+        "RETENIDO"
+    } else {
+        "DEVUELTO"
+    };
+    conn.execute(
+        "UPDATE rest_reserva_depositos SET estado = ?1, fecha_cierre = datetime('now', 'localtime')
+         WHERE reserva_id = ?2 AND estado = 'HOLDING'",
+        params![estado_deposito, reserva_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Reserva que bloquea la mesa en este momento: (id, cliente, fecha_hora, personas).
+pub fn reserva_que_bloquea(conn: &Connection, mesa_id: i64) -> Option<(i64, String, String, i32)> {
+    let bloqueo = config_i64(conn, "rest_reserva_bloqueo_min", 60);
+    let tolerancia = config_i64(conn, "rest_reserva_tolerancia_min", 15);
+    conn.query_row(
+        &format!(
+            "SELECT id, cliente_nombre, fecha_hora, personas FROM rest_reservas
+             WHERE mesa_id = ?1 AND estado IN {ACTIVAS}
+               AND julianday('now', 'localtime') >= julianday(fecha_hora) - ?2 / 1440.0
+               AND julianday('now', 'localtime') <= julianday(fecha_hora) + ?3 / 1440.0
+             ORDER BY fecha_hora LIMIT 1"
+        ),
+        params![mesa_id, bloqueo, tolerancia],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+    )
+    .ok()
+}
+
+/// Error si la mesa está bloqueada por una reserva (abrir pedido a un walk-in).
+pub fn validar_mesa_sin_reserva(conn: &Connection, mesa_id: i64) -> Result<(), String> {
+    match reserva_que_bloquea(conn, mesa_id) {
+        Some((id, cliente, fecha_hora, _)) => Err(format!(
+            "Mesa reservada para {} ({}, reserva #{}). Siente la reserva o cámbiela de mesa.",
+            cliente, fecha_hora, id
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Valida capacidad de la mesa y que no se cruce con otra reserva activa.
+fn validar_disponibilidad(conn: &Connection, r: &Reserva, fecha_hora: &str) -> Result<(), String> {
+    let Some(mesa_id) = r.mesa_id else { return Ok(()) };
+    let (nombre, capacidad): (String, i32) = conn
+        .query_row(
+            "SELECT nombre, capacidad FROM rest_mesas WHERE id = ?1 AND activa = 1",
+            params![mesa_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|_| "Mesa no encontrada o inactiva".to_string())?;
+    if r.personas > capacidad {
+        return Err(format!("{} es para {} personas; la reserva es de {}", nombre, capacidad, r.personas));
+    }
+    let choque: Option<(i64, String, String)> = conn
+        .query_row(
+            &format!(
+                "SELECT id, cliente_nombre, fecha_hora FROM rest_reservas
+                 WHERE mesa_id = ?1 AND estado IN {ACTIVAS} AND id <> ?2
+                   AND julianday(fecha_hora) < julianday(?3) + ?4 / 1440.0
+                   AND julianday(?3) < julianday(fecha_hora) + duracion_min / 1440.0
+                 LIMIT 1"
+            ),
+            params![mesa_id, r.id.unwrap_or(0), fecha_hora, r.duracion_min],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some((id, cliente, hora)) = choque {
+        return Err(format!("{} ya está reservada a las {} para {} (reserva #{})", nombre, hora, cliente, id));
+    }
+    Ok(())
+}
+
+/// Crea o actualiza una reserva (solo PENDIENTE/CONFIRMADA se pueden editar).
+pub fn guardar(conn: &Connection, r: &Reserva, usuario_nombre: Option<&str>) -> Result<i64, String> {
+    if r.personas <= 0 {
+        return Err("La reserva debe ser de al menos 1 persona".to_string());
+    }
+    if r.duracion_min <= 0 {
+        return Err("La duración debe ser mayor a 0".to_string());
+    }
+    let fecha_hora = normalizar_fecha_hora(&r.fecha_hora)?;
+    let (cliente_nombre, telefono) = match r.cliente_id {
+        Some(cid) => {
+            let (nombre, tel): (String, Option<String>) = conn
+                .query_row("SELECT nombre, telefono FROM clientes WHERE id = ?1", params![cid], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })
+                .map_err(|_| "Cliente no encontrado".to_string())?;
+            (nombre, r.telefono.clone().filter(|t| !t.trim().is_empty()).or(tel))
+        }
+        None => (r.cliente_nombre.trim().to_string(), r.telefono.clone()),
+    };
+    if cliente_nombre.is_empty() {
+        return Err("Indique el cliente de la reserva".to_string());
+    }
+    validar_disponibilidad(conn, r, &fecha_hora)?;
+
+    match r.id {
+        Some(id) => {
+            let n = conn
+                .execute(
+                    &format!(
+                        "UPDATE rest_reservas SET cliente_id = ?1, cliente_nombre = ?2, telefono = ?3, personas = ?4,
+                            fecha_hora = ?5, duracion_min = ?6, mesa_id = ?7, notas = ?8
+                         WHERE id = ?9 AND estado IN {ACTIVAS}"
+                    ),
+                    params![r.cliente_id, cliente_nombre, telefono, r.personas, fecha_hora, r.duracion_min, r.mesa_id, r.notas, id],
+                )
+                .map_err(|e| e.to_string())?;
+            if n == 0 {
+                return Err("Reserva no encontrada o ya cerrada".to_string());
+            }
+            Ok(id)
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO rest_reservas
+                    (cliente_id, cliente_nombre, telefono, personas, fecha_hora, duracion_min, mesa_id, notas, usuario_nombre)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![r.cliente_id, cliente_nombre, telefono, r.personas, fecha_hora, r.duracion_min, r.mesa_id, r.notas, usuario_nombre],
+            )
+            .map_err(|e| e.to_string())?;
+            Ok(conn.last_insert_rowid())
+        }
+    }
+}
+
+/// Reservas de un día ('YYYY-MM-DD'; None = hoy). Con `incluir_cerradas`
+/// también devuelve las sentadas, canceladas y no-shows.
+pub fn listar(conn: &Connection, fecha: Option<&str>, incluir_cerradas: bool) -> Result<Vec<Reserva>, String> {
+    marcar_no_shows_vencidos(conn);
+    let filtro_estado = if incluir_cerradas { String::new() } else { format!("AND r.estado IN {ACTIVAS}") };
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT r.id, r.cliente_id, r.cliente_nombre, r.telefono, r.personas, r.fecha_hora,
+                    r.duracion_min, r.mesa_id, m.nombre, r.estado, r.notas, r.pedido_id, r.created_at,
+                    (SELECT COALESCE(SUM(d.monto), 0) FROM rest_reserva_depositos d
+                     WHERE d.reserva_id = r.id AND d.estado = 'HOLDING'),
+                    (SELECT COUNT(*) FROM rest_reservas r2
+                     WHERE r2.estado = 'NO_SHOW' AND r2.id <> r.id
+                       AND ((r.cliente_id IS NOT NULL AND r2.cliente_id = r.cliente_id)
+                         OR (r.telefono IS NOT NULL AND r.telefono <> '' AND r2.telefono = r.telefono)))
+             FROM rest_reservas r
+             LEFT JOIN rest_mesas m ON m.id = r.mesa_id
+             WHERE date(r.fecha_hora) = date(COALESCE(?1, datetime('now', 'localtime')))
+               {filtro_estado}
+             ORDER BY r.fecha_hora, r.id"
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![fecha], |r| {
+            Ok(Reserva {
+                id: Some(r.get(0)?),
+                cliente_id: r.get(1)?,
+                cliente_nombre: r.get(2)?,
+                telefono: r.get(3)?,
+                personas: r.get(4)?,
+                fecha_hora: r.get(5)?,
+                duracion_min: r.get(6)?,
+                mesa_id: r.get(7)?,
+                mesa_nombre: r.get(8)?,
+                estado: r.get(9)?,
+                notas: r.get(10)?,
+                pedido_id: r.get(11)?,
+                created_at: r.get(12)?,
+                deposito: r.get(13)?,
+                no_shows_previos: r.get(14)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+/// CONFIRMADA, CANCELADA o NO_SHOW (manual). Sentar va por [`sentar_reserva`].
+pub fn cambiar_estado(conn: &Connection, reserva_id: i64, estado: &str) -> Result<(), String> {
+    let actual: String = conn
+        .query_row("SELECT estado FROM rest_reservas WHERE id = ?1", params![reserva_id], |r| r.get(0))
+        .map_err(|_| "Reserva no encontrada".to_string())?;
+    if actual != "PENDIENTE" && actual != "CONFIRMADA" {
+        return Err(format!("La reserva ya está {}", actual));
+    }
+    match estado {
+        "CONFIRMADA" => {
+            conn.execute("UPDATE rest_reservas SET estado = 'CONFIRMADA' WHERE id = ?1", params![reserva_id])
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        }
+        "CANCELADA" | "NO_SHOW" => cerrar_reserva(conn, reserva_id, estado),
+        _ => Err(format!("Estado de reserva inválido: {}", estado)),
+    }
+}
+
+/// Registra un depósito en HOLDING sobre la reserva (entra a la caja activa).
+pub fn registrar_deposito(
+    conn: &Connection,
+    reserva_id: i64,
+    d: &NuevoDeposito,
+    usuario_id: Option<i64>,
+    usuario_nombre: Option<&str>,
+) -> Result<i64, String> {
+    if d.monto <= 0.0 {
+        return Err("El monto del depósito debe ser mayor a 0".to_string());
+    }
+    let estado: String = conn
+        .query_row("SELECT estado FROM rest_reservas WHERE id = ?1", params![reserva_id], |r| r.get(0))
+        .map_err(|_| "Reserva no encontrada".to_string())?;
+    if estado != "PENDIENTE" && estado != "CONFIRMADA" {
+        return Err(format!("La reserva ya está {}", estado));
+    }
+    let caja_id: Option<i64> = conn
+        .query_row("SELECT id FROM caja WHERE estado = 'ABIERTA' LIMIT 1", [], |r| r.get(0))
+        .ok();
+    conn.execute(
+        "INSERT INTO rest_reserva_depositos
+            (reserva_id, monto, forma_pago, banco_id, referencia_pago, caja_id, estado, usuario_id, usuario_nombre)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'HOLDING', ?7, ?8)",
+        params![
+            reserva_id,
+            (d.monto * 100.0).round() / 100.0,
+            d.forma_pago,
+            d.banco_id,
+            d.referencia_pago,
+            caja_id,
+            usuario_id,
+            usuario_nombre
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn listar_depositos(conn: &Connection, reserva_id: i64) -> Result<Vec<DepositoReserva>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, reserva_id, monto, forma_pago, banco_id, referencia_pago, estado, fecha, usuario_nombre
+             FROM rest_reserva_depositos WHERE reserva_id = ?1 ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![reserva_id], |r| {
+            Ok(DepositoReserva {
+                id: r.get(0)?,
+                reserva_id: r.get(1)?,
+                monto: r.get(2)?,
+                forma_pago: r.get(3)?,
+                banco_id: r.get(4)?,
+                referencia_pago: r.get(5)?,
+                estado: r.get(6)?,
+                fecha: r.get(7)?,
+                usuario_nombre: r.get(8)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+/// Abre el pedido en una mesa libre (sin validar reservas: lo usan quienes
+/// ya son dueños de la mesa, la reserva o la lista de espera).
+fn abrir_pedido_en_mesa(
+    conn: &Connection,
+    mesa_id: i64,
+    mesero: (Option<i64>, Option<&str>),
+    comensales: i32,
+    observacion: &str,
+) -> Result<i64, String> {
+    let mesa_existe: i64 = conn
+        .query_row("SELECT COUNT(*) FROM rest_mesas WHERE id = ?1 AND activa = 1", params![mesa_id], |r| r.get(0))
+        .unwrap_or(0);
+    if mesa_existe == 0 {
+        return Err("Mesa no encontrada o inactiva".to_string());
+    }
+    let ocupada: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM rest_pedidos_abiertos WHERE mesa_id = ?1 AND estado IN ('ABIERTO', 'CUENTA_PEDIDA')",
+            params![mesa_id],
+            |r| r.get(0),
+        )
+        .unwrap_or(0);
+    if ocupada > 0 {
+        return Err("Ya existe un pedido abierto en esta mesa".to_string());
+    }
+    conn.execute(
+        "INSERT INTO rest_pedidos_abiertos (mesa_id, mesero_id, mesero_nombre, comensales, estado, observacion)
+         VALUES (?1, ?2, ?3, ?4, 'ABIERTO', ?5)",
+        params![mesa_id, mesero.0, mesero.1, comensales, observacion],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Sienta la reserva: abre el pedido (en `mesa_id` o la mesa de la reserva)
+/// y traslada los depósitos en HOLDING como abonos del pedido.
+pub fn sentar_reserva(
+    conn: &Connection,
+    reserva_id: i64,
+    mesa_id: Option<i64>,
+    mesero: (Option<i64>, Option<&str>),
+) -> Result<i64, String> {
+    let (estado, mesa_reserva, personas, cliente): (String, Option<i64>, i32, String) = conn
+        .query_row(
+            "SELECT estado, mesa_id, personas, cliente_nombre FROM rest_reservas WHERE id = ?1",
+            params![reserva_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .map_err(|_| "Reserva no encontrada".to_string())?;
+    if estado != "PENDIENTE" && estado != "CONFIRMADA" {
+        return Err(format!("La reserva ya está {}", estado));
+    }
+    let mesa_id = mesa_id.or(mesa_reserva).ok_or("Elija la mesa donde sentar la reserva")?;
+    // Otra reserva puede tener bloqueada la mesa elegida
+    if let Some((otra, otro_cliente, hora, _)) = reserva_que_bloquea(conn, mesa_id) {
+        if otra != reserva_id {
+            return Err(format!("La mesa está reservada para {} a las {}", otro_cliente, hora));
+        }
+    }
+    let pedido_id = abrir_pedido_en_mesa(
+        conn,
+        mesa_id,
+        mesero,
+        personas,
+        &format!("Reserva #{} - {}", reserva_id, cliente),
+    )?;
+
+    let depositos: Vec<(i64, f64)> = {
+        let mut stmt = conn
+            .prepare("SELECT id, monto FROM rest_reserva_depositos WHERE reserva_id = ?1 AND estado = 'HOLDING'")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![reserva_id], |r| Ok((r.get(0)?, r.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        rows
+    };
+    for (dep_id, monto) in depositos {
+        // Mismo caja_id y fecha: el dinero ya entró a esa caja al recibir el depósito.
+        conn.execute(
+            "INSERT INTO rest_pedido_abonos
+                (pedido_id, monto, forma_pago, banco_id, referencia_pago, caja_id, estado, fecha, usuario_id, usuario_nombre, observacion)
+             SELECT ?1, monto, forma_pago, banco_id, referencia_pago, caja_id, 'HOLDING', fecha, usuario_id, usuario_nombre, ?2
+             FROM rest_reserva_depositos WHERE id = ?3",
+            params![pedido_id, format!("Depósito reserva #{} (${:.2})", reserva_id, monto), dep_id],
+        )
+        .map_err(|e| e.to_string())?;
+        let abono_id = conn.last_insert_rowid();
+        conn.execute(
+            "UPDATE rest_reserva_depositos
+             SET estado = 'APLICADO', pedido_abono_id = ?1, fecha_cierre = datetime('now', 'localtime')
+             WHERE id = ?2",
+            params![abono_id, dep_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    conn.execute(
+        "UPDATE rest_reservas
+         SET estado = 'SENTADA', mesa_id = ?1, pedido_id = ?2,
+             fecha_llegada = datetime('now', 'localtime'), fecha_cierre = datetime('now', 'localtime')
+         WHERE id = ?3",
+        params![mesa_id, pedido_id, reserva_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(pedido_id)
+}
+
+/// No-shows por cliente (o teléfono si la reserva no tiene cliente) en un rango.
+pub fn reporte_no_shows(conn: &Connection, fecha_desde: &str, fecha_hasta: &str) -> Result<Vec<NoShowCliente>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT r.cliente_id, MAX(r.cliente_nombre), MAX(r.telefono), COUNT(*),
+                    SUM(CASE WHEN r.estado = 'NO_SHOW' THEN 1 ELSE 0 END),
+                    COALESCE(SUM((SELECT SUM(d.monto) FROM rest_reserva_depositos d
+                                  WHERE d.reserva_id = r.id AND d.estado = 'RETENIDO')), 0)
+             FROM rest_reservas r
+             WHERE date(r.fecha_hora) >= date(?1) AND date(r.fecha_hora) <= date(?2)
+               AND r.estado <> 'CANCELADA'
+             GROUP BY COALESCE(CAST(r.cliente_id AS TEXT), NULLIF(r.telefono, ''), LOWER(r.cliente_nombre))
+             HAVING SUM(CASE WHEN r.estado = 'NO_SHOW' THEN 1 ELSE 0 END) > 0
+             ORDER BY 5 DESC, 4 DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![fecha_desde, fecha_hasta], |r| {
+            Ok(NoShowCliente {
+                cliente_id: r.get(0)?,
+                cliente_nombre: r.get(1)?,
+                telefono: r.get(2)?,
+                reservas: r.get(3)?,
+                no_shows: r.get(4)?,
+                deposito_retenido: r.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+// ─── Lista de espera ─────────────────────────────────────────────────────
+
+/// Duración promedio (min) de las mesas cobradas en los últimos 30 días.
+fn duracion_promedio_mesa(conn: &Connection) -> f64 {
+    conn.query_row(
+        "SELECT AVG((julianday(fecha_cierre) - julianday(fecha_apertura)) * 24 * 60)
+         FROM rest_pedidos_abiertos
+         WHERE estado = 'COBRADO' AND fecha_cierre IS NOT NULL
+           AND fecha_cierre >= datetime('now', 'localtime', '-30 days')",
+        [],
+        |r| r.get::<_, Option<f64>>(0),
+    )
+    .ok()
+    .flatten()
+    .filter(|m| *m > 0.0)
+    .unwrap_or(60.0)
+    .max(15.0)
+}
+
+/// Mesas activas no bloqueadas por reserva: (capacidad, minutos para liberarse).
+fn liberacion_mesas(conn: &Connection, promedio: f64) -> Result<Vec<(i32, f64)>, String> {
+    let mesas = super::commands::listar_mesas_con_estado_internal(conn)?;
+    Ok(mesas
+        .iter()
+        .filter(|m| m.mesa_principal_id.is_none())
+        .filter_map(|m| {
+            let libre_en = match m.estado.as_str() {
+                "LIBRE" => 0.0,
+                "CUENTA_PEDIDA" => 5.0_f64.min((promedio - m.minutos_abierta.unwrap_or(0) as f64).max(0.0)),
+                "OCUPADA" => (promedio - m.minutos_abierta.unwrap_or(0) as f64).max(5.0),
+                _ => return None, // RESERVADA
+            };
+            Some((m.capacidad, libre_en))
+        })
+        .collect())
+}
+
+/// Simula la asignación en orden de llegada y devuelve la espera estimada de
+/// cada grupo (None si no hay mesa con capacidad suficiente).
+fn estimar(personas: &[i32], mut mesas: Vec<(i32, f64)>, promedio: f64) -> Vec<Option<i64>> {
+    personas
+        .iter()
+        .map(|p| {
+            let idx = mesas
+                .iter()
+                .enumerate()
+                .filter(|(_, (cap, _))| cap >= p)
+                .min_by(|a, b| {
+                    a.1 .1
+                        .partial_cmp(&b.1 .1)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                        .then(a.1 .0.cmp(&b.1 .0))
+                })
+                .map(|(i, _)| i)?;
+            let espera = mesas[idx].1;
+            mesas[idx].1 = espera + promedio;
+            Some(espera.round() as i64)
+        })
+        .collect()
+}
+
+/// Lista de espera con la estimación recalculada en el momento.
+pub fn listar_espera(conn: &Connection, incluir_cerradas: bool) -> Result<Vec<EntradaEspera>, String> {
+    let filtro = if incluir_cerradas {
+        "WHERE date(fecha_llegada) = date('now', 'localtime')"
+    } else {
+        "WHERE estado IN ('ESPERANDO', 'AVISADO')"
+    };
+    let mut entradas: Vec<EntradaEspera> = {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT id, cliente_nombre, telefono, personas, notas, estado, fecha_llegada,
+                        CAST((julianday('now', 'localtime') - julianday(fecha_llegada)) * 24 * 60 AS INTEGER),
+                        minutos_estimados, mesa_id, pedido_id
+                 FROM rest_lista_espera {filtro}
+                 ORDER BY fecha_llegada, id"
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |r| {
+                Ok(EntradaEspera {
+                    id: Some(r.get(0)?),
+                    cliente_nombre: r.get(1)?,
+                    telefono: r.get(2)?,
+                    personas: r.get(3)?,
+                    notas: r.get(4)?,
+                    estado: r.get(5)?,
+                    fecha_llegada: r.get(6)?,
+                    minutos_esperando: r.get(7)?,
+                    minutos_estimados: r.get(8)?,
+                    mesa_id: r.get(9)?,
+                    pedido_id: r.get(10)?,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        rows
+    };
+
+    let promedio = duracion_promedio_mesa(conn);
+    let mesas = liberacion_mesas(conn, promedio)?;
+    let activos: Vec<usize> = (0..entradas.len())
+        .filter(|i| matches!(entradas[*i].estado.as_str(), "ESPERANDO" | "AVISADO"))
+        .collect();
+    let personas: Vec<i32> = activos.iter().map(|i| entradas[*i].personas).collect();
+    for (i, est) in activos.into_iter().zip(estimar(&personas, mesas, promedio)) {
+        entradas[i].minutos_estimados = est;
+    }
+    Ok(entradas)
+}
+
+/// Agrega un grupo a la lista. Guarda la estimación inicial (para comparar luego).
+pub fn agregar_espera(conn: &Connection, e: &EntradaEspera) -> Result<EntradaEspera, String> {
+    if e.personas <= 0 {
+        return Err("El grupo debe ser de al menos 1 persona".to_string());
+    }
+    if e.cliente_nombre.trim().is_empty() {
+        return Err("Indique el nombre del grupo".to_string());
+    }
+    conn.execute(
+        "INSERT INTO rest_lista_espera (cliente_nombre, telefono, personas, notas) VALUES (?1, ?2, ?3, ?4)",
+        params![e.cliente_nombre.trim(), e.telefono, e.personas, e.notas],
+    )
+    .map_err(|e| e.to_string())?;
+    let id = conn.last_insert_rowid();
+    let nueva = listar_espera(conn, false)?
+        .into_iter()
+        .find(|x| x.id == Some(id))
+        .ok_or("No se pudo leer la entrada creada")?;
+    conn.execute(
+        "UPDATE rest_lista_espera SET minutos_estimados = ?1 WHERE id = ?2",
+        params![nueva.minutos_estimados, id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(nueva)
+}
+
+/// AVISADO (se le avisó que su mesa está lista) o ABANDONO (se fue).
+pub fn cambiar_estado_espera(conn: &Connection, id: i64, estado: &str) -> Result<(), String> {
+    let sql = match estado {
+        "AVISADO" => "UPDATE rest_lista_espera SET estado = 'AVISADO', fecha_aviso = datetime('now', 'localtime')
+                      WHERE id = ?1 AND estado IN ('ESPERANDO', 'AVISADO')",
+        "ABANDONO" => "UPDATE rest_lista_espera SET estado = 'ABANDONO', fecha_cierre = datetime('now', 'localtime')
+                       WHERE id = ?1 AND estado IN ('ESPERANDO', 'AVISADO')",
+        _ => return Err(format!("Estado de lista de espera inválido: {}", estado)),
+    };
+    let n = conn.execute(sql, params![id]).map_err(|e| e.to_string())?;
+    if n == 0 {
+        return Err("Entrada no encontrada o ya cerrada".to_string());
+    }
+    Ok(())
+}
+
+/// Sienta a un grupo de la lista de espera en una mesa libre.
+pub fn sentar_espera(
+    conn: &Connection,
+    id: i64,
+    mesa_id: i64,
+    mesero: (Option<i64>, Option<&str>),
+) -> Result<i64, String> {
+    let (estado, personas, nombre): (String, i32, String) = conn
+        .query_row(
+            "SELECT estado, personas, cliente_nombre FROM rest_lista_espera WHERE id = ?1",
+            params![id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .map_err(|_| "Entrada no encontrada".to_string())?;
+    if estado != "ESPERANDO" && estado != "AVISADO" {
+        return Err(format!("El grupo ya está {}", estado));
+    }
+    validar_mesa_sin_reserva(conn, mesa_id)?;
+    let pedido_id = abrir_pedido_en_mesa(conn, mesa_id, mesero, personas, &format!("Lista de espera - {}", nombre))?;
+    conn.execute(
+        "UPDATE rest_lista_espera
+         SET estado = 'SENTADO', mesa_id = ?1, pedido_id = ?2, fecha_cierre = datetime('now', 'localtime')
+         WHERE id = ?3",
+        params![mesa_id, pedido_id, id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(pedido_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimacion_respeta_capacidad_y_orden_de_llegada() {
+        // Mesa de 2 libre ya, mesa de 4 se libera en 20 min
+        let mesas = vec![(2, 0.0), (4, 20.0)];
+        let est = estimar(&[4, 2, 2], mesas, 60.0);
+        // El grupo de 4 solo cabe en la de 4; los de 2 toman la de 2 (libre)
+        // y luego esperan a que vuelva a liberarse (0 + 60).
+        assert_eq!(est, vec![Some(20), Some(0), Some(60)]);
+    }
+
+    #[test]
+    fn estimacion_sin_mesa_suficiente() {
+        assert_eq!(estimar(&[8], vec![(4, 0.0)], 60.0), vec![None]);
+    }
+
+    #[test]
+    fn normaliza_fecha_hora() {
+        assert_eq!(normalizar_fecha_hora("2026-10-18T20:30").unwrap(), "2026-10-18 20:30");
+        assert_eq!(normalizar_fecha_hora("2026-10-18 20:30:00").unwrap(), "2026-10-18 20:30");
+        assert!(normalizar_fecha_hora("18/10/2026").is_err());
+    }
+}
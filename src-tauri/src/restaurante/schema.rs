@@ -5,6 +5,19 @@
 //! - `rest_mesas`              — mesas físicas con capacidad
 //! - `rest_pedidos_abiertos`   — comanda activa por mesa (acumula items hasta cobrar)
 //! - `rest_pedido_items`       — líneas del pedido con info adicional + estado cocina
+//! - `rest_modificador_grupos` / `rest_modificadores` — opciones por producto (término, extras)
+//! - `rest_producto_modificador_grupos` — qué grupos aplica cada producto
+//! - `rest_pedido_item_modificadores`   — snapshot de lo elegido en cada item
+//! - `rest_estaciones`         — estaciones de preparación (parrilla, barra, fríos) con su impresora
+//! - `rest_estacion_categorias`— qué categorías de producto prepara cada estación
+//! - `rest_subcuenta_items`    — porción de items que paga cada sub-cuenta (división por consumo)
+//! - `rest_comandas`           — cada comanda impresa por estación (estado, impresora usada, reimpresiones)
+//! - `rest_producto_tiempos`   — objetivo de preparación y tiempo (curso) por defecto de cada producto
+//! - `rest_reservas` / `rest_reserva_depositos` — reservas de mesa con depósito en HOLDING
+//! - `rest_lista_espera`       — walk-ins esperando mesa
+//! - `rest_pedido_movimientos` — auditoría de traspasos de mesa, items y mesero
+//! - `rest_propinas`           — propina/servicio de cada venta, con mesero y caja para el reparto
+//! - `rest_entregas`           — pedidos para llevar / delivery (cliente, dirección, repartidor, estado)
 //!
 //! Todas las tablas usan prefijo `rest_` para no chocar con el resto del schema.
 
@@ -59,7 +72,8 @@ pub fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
         CREATE INDEX IF NOT EXISTS idx_rest_pedidos_estado ON rest_pedidos_abiertos(estado);
 
         -- ─── Items de un pedido (lo que va consumiendo cada mesa) ──
-        -- estado_cocina: PENDIENTE | EN_PREPARACION | LISTO | ENTREGADO
+        -- estado_cocina: RETENIDO | PENDIENTE | EN_PREPARACION | LISTO | ENTREGADO
+        --   RETENIDO = en espera de que el mesero 'dispare' su tiempo (curso)
         -- enviado_cocina: 0=nuevo (no impreso aún) / 1=ya se envió a cocina
         CREATE TABLE IF NOT EXISTS rest_pedido_items (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -144,8 +158,11 @@ pub fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
         -- marca el pedido como COBRADO (vinculándolo con la venta de la PRIMERA
         -- sub-cuenta cobrada) y libera la(s) mesa(s).
         --
-        -- LIMITACIÓN MVP: el stock de los items reales NO se descuenta porque
-        -- cada venta es por monto plano. Aceptable para restaurantes pequeños.
+        -- modo MONTO: cada venta es por monto plano con '_DIVISION_CUENTA_', así
+        -- que el stock de los items reales NO se descuenta.
+        -- modo ITEMS: cada sub-cuenta lleva su porción de items reales
+        -- (rest_subcuenta_items) y su venta usa los productos reales con su
+        -- tarifa de IVA y movimiento de stock. Puede facturarse a otro cliente.
         CREATE TABLE IF NOT EXISTS rest_subcuentas (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             pedido_id INTEGER NOT NULL,
@@ -163,8 +180,301 @@ pub fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
         );
         CREATE INDEX IF NOT EXISTS idx_rest_subcuentas_pedido ON rest_subcuentas(pedido_id);
         CREATE INDEX IF NOT EXISTS idx_rest_subcuentas_estado ON rest_subcuentas(estado);
+
+        -- Porción de cada item que paga una sub-cuenta en modo ITEMS.
+        -- cantidad puede ser fraccionaria (plato compartido entre 3 → 0.333333).
+        CREATE TABLE IF NOT EXISTS rest_subcuenta_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            subcuenta_id INTEGER NOT NULL,
+            pedido_item_id INTEGER NOT NULL,
+            cantidad REAL NOT NULL,
+            FOREIGN KEY (subcuenta_id) REFERENCES rest_subcuentas(id) ON DELETE CASCADE,
+            FOREIGN KEY (pedido_item_id) REFERENCES rest_pedido_items(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_rest_subcuenta_items_sub ON rest_subcuenta_items(subcuenta_id);
+
+        -- ─── Modificadores de producto ─────────────────────────────
+        -- Grupos reutilizables ('Término de la carne', 'Extras') asignados a
+        -- productos. min/max_selecciones validan la elección al agregar el
+        -- item (min > 0 = obligatorio; max 0 = sin límite).
+        CREATE TABLE IF NOT EXISTS rest_modificador_grupos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL,
+            min_selecciones INTEGER NOT NULL DEFAULT 0,
+            max_selecciones INTEGER NOT NULL DEFAULT 0,
+            orden INTEGER NOT NULL DEFAULT 0,
+            activo INTEGER NOT NULL DEFAULT 1
+        );
+
+        -- precio_delta se suma al precio del item. producto_insumo_id +
+        -- cantidad_insumo: stock que consume la opción al venderse (opcional).
+        CREATE TABLE IF NOT EXISTS rest_modificadores (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            grupo_id INTEGER NOT NULL,
+            nombre TEXT NOT NULL,
+            precio_delta REAL NOT NULL DEFAULT 0,
+            producto_insumo_id INTEGER,
+            cantidad_insumo REAL NOT NULL DEFAULT 0,
+            orden INTEGER NOT NULL DEFAULT 0,
+            activo INTEGER NOT NULL DEFAULT 1,
+            FOREIGN KEY (grupo_id) REFERENCES rest_modificador_grupos(id),
+            FOREIGN KEY (producto_insumo_id) REFERENCES productos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_rest_modificadores_grupo ON rest_modificadores(grupo_id);
+
+        CREATE TABLE IF NOT EXISTS rest_producto_modificador_grupos (
+            producto_id INTEGER NOT NULL,
+            grupo_id INTEGER NOT NULL,
+            orden INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (producto_id, grupo_id)
+        );
+
+        -- Snapshot de lo elegido en cada item: si luego se edita o borra el
+        -- modificador, la comanda / pre-cuenta / venta siguen mostrando lo pedido.
+        CREATE TABLE IF NOT EXISTS rest_pedido_item_modificadores (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pedido_item_id INTEGER NOT NULL,
+            modificador_id INTEGER,
+            grupo_nombre TEXT NOT NULL,
+            nombre TEXT NOT NULL,
+            precio_delta REAL NOT NULL DEFAULT 0,
+            producto_insumo_id INTEGER,
+            cantidad_insumo REAL NOT NULL DEFAULT 0,
+            FOREIGN KEY (pedido_item_id) REFERENCES rest_pedido_items(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_rest_item_mod_item ON rest_pedido_item_modificadores(pedido_item_id);
+
+        -- ─── Estaciones de preparación ─────────────────────────────
+        -- Cada estación (Parrilla, Barra, Fríos...) tiene su impresora y
+        -- una de respaldo para cuando la principal no responde. Si no hay
+        -- estaciones activas se mantiene el ruteo clásico por
+        -- `productos.destino_preparacion` (impresora_cocina / impresora_barra).
+        CREATE TABLE IF NOT EXISTS rest_estaciones (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL,
+            impresora TEXT NOT NULL DEFAULT '',
+            impresora_respaldo TEXT NOT NULL DEFAULT '',
+            orden INTEGER NOT NULL DEFAULT 0,
+            activa INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        );
+
+        -- Una categoría se prepara en UNA sola estación.
+        CREATE TABLE IF NOT EXISTS rest_estacion_categorias (
+            categoria_id INTEGER PRIMARY KEY,
+            estacion_id INTEGER NOT NULL,
+            FOREIGN KEY (estacion_id) REFERENCES rest_estaciones(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_rest_estacion_cat_estacion ON rest_estacion_categorias(estacion_id);
+
+        -- ─── Comandas impresas por estación ────────────────────────
+        -- estado: IMPRESA | RESPALDO (salió por la impresora de respaldo) | FALLIDA
+        -- items: JSON array de rest_pedido_items.id incluidos
+        CREATE TABLE IF NOT EXISTS rest_comandas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pedido_id INTEGER NOT NULL,
+            estacion_id INTEGER,                      -- NULL = 'General' (items sin estación)
+            estacion_nombre TEXT NOT NULL,
+            items TEXT NOT NULL DEFAULT '[]',
+            estado TEXT NOT NULL,
+            impresora_usada TEXT,
+            error TEXT,
+            reimpresiones INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            ultima_impresion_at TEXT,
+            FOREIGN KEY (pedido_id) REFERENCES rest_pedidos_abiertos(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_rest_comandas_pedido ON rest_comandas(pedido_id);
+        CREATE INDEX IF NOT EXISTS idx_rest_comandas_estado ON rest_comandas(estado);
+
+        -- ─── Tiempos de preparación por producto ───────────────────
+        -- minutos_objetivo: meta de cocina (marca el item como atrasado).
+        -- curso_default: ENTRADA | FUERTE | POSTRE con que entra el item.
+        CREATE TABLE IF NOT EXISTS rest_producto_tiempos (
+            producto_id INTEGER PRIMARY KEY,
+            minutos_objetivo INTEGER,
+            curso_default TEXT,
+            FOREIGN KEY (producto_id) REFERENCES productos(id) ON DELETE CASCADE
+        );
+
+        -- ─── Reservas ──────────────────────────────────────────────
+        -- estado: PENDIENTE | CONFIRMADA | SENTADA | CANCELADA | NO_SHOW
+        -- La mesa queda RESERVADA en el grid desde `rest_reserva_bloqueo_min`
+        -- antes de fecha_hora hasta que se sienta, se cancela o vence la
+        -- tolerancia (`rest_reserva_tolerancia_min`) y pasa a NO_SHOW.
+        CREATE TABLE IF NOT EXISTS rest_reservas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            cliente_id INTEGER,
+            cliente_nombre TEXT NOT NULL,
+            telefono TEXT,
+            personas INTEGER NOT NULL DEFAULT 2,
+            fecha_hora TEXT NOT NULL,
+            duracion_min INTEGER NOT NULL DEFAULT 90,
+            mesa_id INTEGER,
+            estado TEXT NOT NULL DEFAULT 'PENDIENTE',
+            notas TEXT,
+            pedido_id INTEGER,
+            fecha_llegada TEXT,
+            fecha_cierre TEXT,
+            usuario_nombre TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (cliente_id) REFERENCES clientes(id),
+            FOREIGN KEY (mesa_id) REFERENCES rest_mesas(id),
+            FOREIGN KEY (pedido_id) REFERENCES rest_pedidos_abiertos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_rest_reservas_fecha ON rest_reservas(fecha_hora);
+        CREATE INDEX IF NOT EXISTS idx_rest_reservas_mesa ON rest_reservas(mesa_id);
+
+        -- Depósito de la reserva: mismo esquema que rest_pedido_abonos. Al
+        -- sentar la reserva pasa a rest_pedido_abonos (HOLDING) del pedido y
+        -- aquí queda APLICADO con pedido_abono_id.
+        --   estado: HOLDING | APLICADO | DEVUELTO | RETENIDO (no-show)
+        CREATE TABLE IF NOT EXISTS rest_reserva_depositos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            reserva_id INTEGER NOT NULL,
+            monto REAL NOT NULL,
+            forma_pago TEXT NOT NULL DEFAULT 'EFECTIVO',
+            banco_id INTEGER,
+            referencia_pago TEXT,
+            caja_id INTEGER,
+            estado TEXT NOT NULL DEFAULT 'HOLDING',
+            pedido_abono_id INTEGER,
+            fecha TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            fecha_cierre TEXT,
+            usuario_id INTEGER,
+            usuario_nombre TEXT,
+            FOREIGN KEY (reserva_id) REFERENCES rest_reservas(id) ON DELETE CASCADE,
+            FOREIGN KEY (banco_id) REFERENCES cuentas_banco(id),
+            FOREIGN KEY (caja_id) REFERENCES caja(id),
+            FOREIGN KEY (pedido_abono_id) REFERENCES rest_pedido_abonos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_rest_reserva_dep_reserva ON rest_reserva_depositos(reserva_id);
+
+        -- ─── Lista de espera (walk-ins) ────────────────────────────
+        -- estado: ESPERANDO | AVISADO | SENTADO | ABANDONO
+        CREATE TABLE IF NOT EXISTS rest_lista_espera (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            cliente_nombre TEXT NOT NULL,
+            telefono TEXT,
+            personas INTEGER NOT NULL DEFAULT 2,
+            notas TEXT,
+            estado TEXT NOT NULL DEFAULT 'ESPERANDO',
+            minutos_estimados INTEGER,
+            mesa_id INTEGER,
+            pedido_id INTEGER,
+            fecha_llegada TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            fecha_aviso TEXT,
+            fecha_cierre TEXT,
+            FOREIGN KEY (mesa_id) REFERENCES rest_mesas(id),
+            FOREIGN KEY (pedido_id) REFERENCES rest_pedidos_abiertos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_rest_lista_espera_estado ON rest_lista_espera(estado);
+
+        -- ─── Auditoría de traspasos ────────────────────────────────
+        -- tipo: TRANSFERIR_MESA | MOVER_ITEMS | REASIGNAR_MESERO
+        -- detalle: texto legible de lo movido ('2 x Lomo; 1 x Cola')
+        CREATE TABLE IF NOT EXISTS rest_pedido_movimientos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tipo TEXT NOT NULL,
+            pedido_id INTEGER NOT NULL,
+            pedido_destino_id INTEGER,
+            mesa_origen_id INTEGER,
+            mesa_destino_id INTEGER,
+            mesero_anterior TEXT,
+            mesero_nuevo TEXT,
+            detalle TEXT,
+            motivo TEXT,
+            usuario_id INTEGER,
+            usuario_nombre TEXT,
+            origen TEXT NOT NULL DEFAULT 'POS',
+            fecha TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (pedido_id) REFERENCES rest_pedidos_abiertos(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_rest_pedido_mov_pedido ON rest_pedido_movimientos(pedido_id);
+        CREATE INDEX IF NOT EXISTS idx_rest_pedido_mov_destino ON rest_pedido_movimientos(pedido_destino_id);
+
+        -- ─── Propinas ──────────────────────────────────────────────
+        -- Una por venta (pedido completo o sub-cuenta). El monto NO está en
+        -- ventas.total; se copia a ventas.propina para el XML/RIDE.
+        -- tipo: SERVICIO | VOLUNTARIA
+        CREATE TABLE IF NOT EXISTS rest_propinas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            venta_id INTEGER NOT NULL UNIQUE,
+            pedido_id INTEGER NOT NULL,
+            subcuenta_id INTEGER,
+            tipo TEXT NOT NULL DEFAULT 'VOLUNTARIA',
+            porcentaje REAL,
+            base REAL NOT NULL DEFAULT 0,
+            monto REAL NOT NULL,
+            forma_pago TEXT NOT NULL DEFAULT 'EFECTIVO',
+            caja_id INTEGER,
+            mesero_id INTEGER,
+            mesero_nombre TEXT,
+            fecha TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (venta_id) REFERENCES ventas(id),
+            FOREIGN KEY (pedido_id) REFERENCES rest_pedidos_abiertos(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_rest_propinas_caja ON rest_propinas(caja_id);
+
+        -- ─── Para llevar / delivery ────────────────────────────────
+        -- El pedido vive en una mesa virtual del canal (rest_mesas.canal).
+        -- tipo: LLEVAR | DELIVERY
+        -- estado: PREPARANDO | LISTO | EN_CAMINO | ENTREGADO | CANCELADO
+        -- contra_entrega = 1: el repartidor cobra y liquida en caja al volver
+        -- (liquidado = 1 cuando el pedido se cobra con la caja abierta).
+        CREATE TABLE IF NOT EXISTS rest_entregas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pedido_id INTEGER NOT NULL UNIQUE,
+            tipo TEXT NOT NULL,
+            cliente_id INTEGER,
+            cliente_nombre TEXT,
+            telefono TEXT,
+            direccion_id INTEGER,
+            direccion TEXT,
+            referencia TEXT,
+            hora_prometida TEXT NOT NULL,
+            costo_envio REAL NOT NULL DEFAULT 0,
+            estado TEXT NOT NULL DEFAULT 'PREPARANDO',
+            repartidor_id INTEGER,
+            repartidor_nombre TEXT,
+            contra_entrega INTEGER NOT NULL DEFAULT 0,
+            liquidado INTEGER NOT NULL DEFAULT 0,
+            caja_liquidacion_id INTEGER,
+            fecha_liquidacion TEXT,
+            fecha_listo TEXT,
+            fecha_salida TEXT,
+            fecha_entrega TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            FOREIGN KEY (pedido_id) REFERENCES rest_pedidos_abiertos(id),
+            FOREIGN KEY (cliente_id) REFERENCES clientes(id),
+            FOREIGN KEY (repartidor_id) REFERENCES usuarios(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_rest_entregas_estado ON rest_entregas(estado);
+        CREATE INDEX IF NOT EXISTS idx_rest_entregas_repartidor ON rest_entregas(repartidor_id);
         ",
-    )
+    )?;
+
+    // Migraciones de columnas (idempotentes: fallan en silencio si ya existen).
+    // Estado de impresión por item cuando se rutea por estaciones:
+    //   estado_impresion: NULL (ruteo clásico) | IMPRESA | RESPALDO | FALLIDA
+    let _ = conn.execute("ALTER TABLE rest_pedido_items ADD COLUMN estacion_id INTEGER", []);
+    let _ = conn.execute("ALTER TABLE rest_pedido_items ADD COLUMN comanda_id INTEGER", []);
+    let _ = conn.execute("ALTER TABLE rest_pedido_items ADD COLUMN estado_impresion TEXT", []);
+    // Texto de modificadores elegidos (para comanda, pre-cuenta y venta).
+    let _ = conn.execute("ALTER TABLE rest_pedido_items ADD COLUMN modificadores TEXT", []);
+    // División por items: modo de la sub-cuenta y cliente a facturar.
+    let _ = conn.execute("ALTER TABLE rest_subcuentas ADD COLUMN modo TEXT NOT NULL DEFAULT 'MONTO'", []);
+    let _ = conn.execute("ALTER TABLE rest_subcuentas ADD COLUMN cliente_id INTEGER", []);
+    // Tiempos (cursos) y marcas de tiempo de cocina para estadísticas.
+    let _ = conn.execute("ALTER TABLE rest_pedido_items ADD COLUMN curso TEXT", []);
+    let _ = conn.execute("ALTER TABLE rest_pedido_items ADD COLUMN fecha_inicio_preparacion TEXT", []);
+    let _ = conn.execute("ALTER TABLE rest_pedido_items ADD COLUMN fecha_listo TEXT", []);
+    let _ = conn.execute("ALTER TABLE rest_pedido_items ADD COLUMN fecha_entregado TEXT", []);
+    // Mesas virtuales de canal (LLEVAR | DELIVERY): inactivas, no salen en el
+    // grid; alojan los pedidos sin mesa física. NULL = mesa normal.
+    let _ = conn.execute("ALTER TABLE rest_mesas ADD COLUMN canal TEXT", []);
+
+    Ok(())
 }
 
 /// Inserta zonas y mesas iniciales si la tabla está vacía.
@@ -221,5 +531,31 @@ pub fn seed_default(conn: &Connection) -> Result<(), rusqlite::Error> {
         );
     }
 
+    // Mesas virtuales de los canales para llevar / delivery (ver `entregas`).
+    for (canal, nombre) in [("LLEVAR", "Para llevar"), ("DELIVERY", "Delivery")] {
+        let existe: i64 = conn
+            .query_row("SELECT COUNT(*) FROM rest_mesas WHERE canal = ?1", params![canal], |row| row.get(0))
+            .unwrap_or(0);
+        if existe == 0 {
+            conn.execute(
+                "INSERT INTO rest_mesas (zona_id, nombre, capacidad, orden, activa, canal)
+                 VALUES (NULL, ?1, 0, 0, 0, ?2)",
+                params![nombre, canal],
+            )?;
+        }
+    }
+
+    // Producto del costo de envío: servicio, se agrega como línea del pedido
+    // delivery. El dueño ajusta precio e IVA desde Productos.
+    let _ = conn.execute(
+        "INSERT OR IGNORE INTO productos
+         (codigo, nombre, descripcion, precio_costo, precio_venta,
+          iva_porcentaje, incluye_iva, stock_actual, stock_minimo,
+          unidad_medida, es_servicio, activo, destino_preparacion)
+         VALUES ('_ENVIO_DELIVERY_', 'Envío a domicilio', 'Producto interno: costo de envío de pedidos delivery',
+                 0, 0, 0, 0, 0, 0, 'UND', 1, 1, 'DIRECTO')",
+        params![],
+    );
+
     Ok(())
 }
@@ -0,0 +1,318 @@
+//! Tiempos del menú (entrada, fuerte, postre) y cronometraje de cocina.
+//!
+//! Un item puede entrar RETENIDO: queda en el pedido pero no sale a cocina
+//! con `rest_enviar_cocina` hasta que el mesero "dispara" su tiempo
+//! ([`disparar_curso`]). Al dispararse pasa a PENDIENTE y se marca como
+//! enviado, igual que un envío normal.
+//!
+//! Cada cambio de estado de cocina deja su marca de tiempo
+//! (`fecha_inicio_preparacion`, `fecha_listo`, `fecha_entregado`); con ellas
+//! y el objetivo por producto (`rest_producto_tiempos`) se calcula qué items
+//! van atrasados y el tiempo promedio de preparación por estación.
+
+use super::models::{ItemCocina, ReporteTiemposCocina, TiempoProducto, TiemposPreparacion};
+use rusqlite::{params, Connection};
+
+/// Tiempos válidos, en el orden en que se sirven.
+pub const CURSOS: [&str; 3] = ["ENTRADA", "FUERTE", "POSTRE"];
+
+/// Normaliza el tiempo recibido del POS/app. Vacío = sin tiempo.
+pub fn normalizar_curso(curso: Option<&str>) -> Result<Option<String>, String> {
+    match curso.map(|c| c.trim().to_uppercase()) {
+        None => Ok(None),
+        Some(c) if c.is_empty() => Ok(None),
+        Some(c) if CURSOS.contains(&c.as_str()) => Ok(Some(c)),
+        Some(c) => Err(format!("Tiempo inválido: {} (use ENTRADA, FUERTE o POSTRE)", c)),
+    }
+}
+
+/// Posición del tiempo para ordenar comandas (sin tiempo va primero).
+pub fn orden_curso(curso: Option<&str>) -> usize {
+    curso
+        .and_then(|c| CURSOS.iter().position(|x| *x == c))
+        .map(|p| p + 1)
+        .unwrap_or(0)
+}
+
+/// Tiempo con que entra un item: el elegido o el `curso_default` del producto.
+pub fn curso_para_item(conn: &Connection, producto_id: i64, curso: Option<&str>) -> Result<Option<String>, String> {
+    if let Some(c) = normalizar_curso(curso)? {
+        return Ok(Some(c));
+    }
+    Ok(conn
+        .query_row(
+            "SELECT curso_default FROM rest_producto_tiempos WHERE producto_id = ?1",
+            params![producto_id],
+            |r| r.get::<_, Option<String>>(0),
+        )
+        .ok()
+        .flatten())
+}
+
+/// Retiene (o libera) un item aún no enviado a cocina.
+pub fn retener_item(conn: &Connection, item_id: i64, retener: bool) -> Result<(), String> {
+    let (enviado, estado): (i32, String) = conn
+        .query_row(
+            "SELECT enviado_cocina, estado_cocina FROM rest_pedido_items WHERE id = ?1",
+            params![item_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .map_err(|_| "Item no encontrado".to_string())?;
+    if enviado != 0 {
+        return Err("El item ya salió a cocina".to_string());
+    }
+    let nuevo = if retener { "RETENIDO" } else { "PENDIENTE" };
+    if estado == nuevo {
+        return Ok(());
+    }
+    conn.execute(
+        "UPDATE rest_pedido_items SET estado_cocina = ?1 WHERE id = ?2",
+        params![nuevo, item_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Dispara un tiempo del pedido: los items RETENIDOS de ese tiempo (todos si
+/// `curso` es None) salen a cocina. Retorna los ids disparados para imprimir.
+pub fn disparar_curso(conn: &Connection, pedido_id: i64, curso: Option<&str>) -> Result<Vec<i64>, String> {
+    let curso = normalizar_curso(curso)?;
+    let ids: Vec<i64> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT id FROM rest_pedido_items
+                 WHERE pedido_id = ?1 AND enviado_cocina = 0 AND estado_cocina = 'RETENIDO'
+                   AND (?2 IS NULL OR curso = ?2)
+                 ORDER BY id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![pedido_id, curso], |r| r.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        rows
+    };
+    if ids.is_empty() {
+        return Err(match curso {
+            Some(c) => format!("No hay items retenidos de {}", c),
+            None => "No hay items retenidos".to_string(),
+        });
+    }
+    for id in &ids {
+        conn.execute(
+            "UPDATE rest_pedido_items
+             SET estado_cocina = 'PENDIENTE', enviado_cocina = 1,
+                 fecha_envio_cocina = datetime('now', 'localtime')
+             WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(ids)
+}
+
+/// Cambia el estado de cocina de un item dejando la marca de tiempo del paso.
+pub fn marcar_estado(conn: &Connection, item_id: i64, estado: &str) -> Result<(), String> {
+    let columna = match estado {
+        "PENDIENTE" => None,
+        "EN_PREPARACION" => Some("fecha_inicio_preparacion"),
+        "LISTO" => Some("fecha_listo"),
+        "ENTREGADO" => Some("fecha_entregado"),
+        _ => return Err(format!("Estado de cocina inválido: {}", estado)),
+    };
+    let sql = match columna {
+        // COALESCE: volver a marcar LISTO no reinicia el cronómetro.
+        Some(col) => format!(
+            "UPDATE rest_pedido_items SET estado_cocina = ?1,
+                 {col} = COALESCE({col}, datetime('now', 'localtime'))
+             WHERE id = ?2 AND estado_cocina <> 'RETENIDO'"
+        ),
+        None => "UPDATE rest_pedido_items SET estado_cocina = ?1 WHERE id = ?2 AND estado_cocina <> 'RETENIDO'".to_string(),
+    };
+    let n = conn.execute(&sql, params![estado, item_id]).map_err(|e| e.to_string())?;
+    if n == 0 {
+        return Err("Item no encontrado o retenido (dispare su tiempo primero)".to_string());
+    }
+    Ok(())
+}
+
+/// Devuelve a preparación un item marcado LISTO por error (recall del KDS).
+/// Borra `fecha_listo` para que el cronómetro no cuente el falso "listo".
+pub fn recuperar_item(conn: &Connection, item_id: i64) -> Result<(), String> {
+    let n = conn
+        .execute(
+            "UPDATE rest_pedido_items SET estado_cocina = 'EN_PREPARACION', fecha_listo = NULL
+             WHERE id = ?1 AND estado_cocina = 'LISTO'",
+            params![item_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if n == 0 {
+        return Err("El item no está LISTO (ya se entregó o sigue en preparación)".to_string());
+    }
+    Ok(())
+}
+
+/// Items activos en cocina con tiempos: edad del ticket, objetivo y atraso.
+/// `estacion_id` filtra por estación (0 = solo 'General').
+pub fn listar_pendientes(conn: &Connection, estacion_id: Option<i64>) -> Result<Vec<ItemCocina>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.id, i.pedido_id, m.nombre, z.nombre, p.mesero_nombre,
+                    pr.nombre, i.cantidad, i.info_adicional, i.estado_cocina, i.fecha_envio_cocina,
+                    CAST((julianday('now', 'localtime') - julianday(i.fecha_envio_cocina)) * 24 * 60 AS INTEGER) AS mins,
+                    i.modificadores, i.curso, COALESCE(e.nombre, 'General'),
+                    CAST((julianday('now', 'localtime') - julianday(
+                        (SELECT MIN(i2.fecha_envio_cocina) FROM rest_pedido_items i2
+                         WHERE i2.pedido_id = i.pedido_id AND i2.enviado_cocina = 1
+                           AND i2.estado_cocina IN ('PENDIENTE', 'EN_PREPARACION', 'LISTO'))
+                    )) * 24 * 60 AS INTEGER) AS mins_ticket,
+                    t.minutos_objetivo
+             FROM rest_pedido_items i
+             JOIN rest_pedidos_abiertos p ON i.pedido_id = p.id
+             JOIN rest_mesas m ON p.mesa_id = m.id
+             LEFT JOIN rest_zonas z ON m.zona_id = z.id
+             JOIN productos pr ON i.producto_id = pr.id
+             LEFT JOIN rest_estacion_categorias ec ON ec.categoria_id = pr.categoria_id
+             LEFT JOIN rest_estaciones e ON e.id = COALESCE(i.estacion_id, ec.estacion_id) AND e.activa = 1
+             LEFT JOIN rest_producto_tiempos t ON t.producto_id = i.producto_id
+             WHERE i.enviado_cocina = 1
+               AND i.estado_cocina IN ('PENDIENTE', 'EN_PREPARACION', 'LISTO')
+               AND (?1 IS NULL OR COALESCE(e.id, 0) = ?1)
+             ORDER BY i.fecha_envio_cocina ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let items = stmt
+        .query_map(params![estacion_id], |row| {
+            let estado: String = row.get(8)?;
+            let minutos: Option<i64> = row.get(10).ok();
+            let objetivo: Option<i64> = row.get(15)?;
+            let atrasado = estado != "LISTO"
+                && matches!((minutos, objetivo), (Some(m), Some(o)) if o > 0 && m > o);
+            Ok(ItemCocina {
+                id: row.get(0)?,
+                pedido_id: row.get(1)?,
+                mesa_nombre: row.get(2)?,
+                zona_nombre: row.get(3)?,
+                mesero_nombre: row.get(4)?,
+                producto_nombre: row.get(5)?,
+                cantidad: row.get(6)?,
+                info_adicional: row.get(7)?,
+                estado_cocina: estado,
+                fecha_envio_cocina: row.get(9)?,
+                minutos_en_cocina: minutos,
+                modificadores: row.get(11)?,
+                curso: row.get(12)?,
+                estacion_nombre: row.get(13)?,
+                minutos_ticket: row.get(14).ok(),
+                minutos_objetivo: objetivo,
+                atrasado,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(items)
+}
+
+/// Objetivos de preparación configurados (solo productos con alguno definido).
+pub fn listar_tiempos_producto(conn: &Connection) -> Result<Vec<TiempoProducto>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.producto_id, p.nombre, t.minutos_objetivo, t.curso_default
+             FROM rest_producto_tiempos t
+             JOIN productos p ON p.id = t.producto_id
+             ORDER BY p.nombre",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |r| {
+            Ok(TiempoProducto {
+                producto_id: r.get(0)?,
+                producto_nombre: r.get(1)?,
+                minutos_objetivo: r.get(2)?,
+                curso_default: r.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+/// Guarda objetivo y tiempo por defecto. Ambos vacíos = se borra la fila.
+pub fn guardar_tiempo_producto(conn: &Connection, t: &TiempoProducto) -> Result<(), String> {
+    let curso = normalizar_curso(t.curso_default.as_deref())?;
+    let minutos = t.minutos_objetivo.filter(|m| *m > 0);
+    if minutos.is_none() && curso.is_none() {
+        conn.execute("DELETE FROM rest_producto_tiempos WHERE producto_id = ?1", params![t.producto_id])
+            .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO rest_producto_tiempos (producto_id, minutos_objetivo, curso_default)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(producto_id) DO UPDATE SET
+            minutos_objetivo = excluded.minutos_objetivo,
+            curso_default = excluded.curso_default",
+        params![t.producto_id, minutos, curso],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Tiempos de preparación (envío → listo) por estación y por producto en un
+/// rango de fechas de envío. Incluye pedidos ya cobrados.
+pub fn reporte(conn: &Connection, fecha_desde: &str, fecha_hasta: &str) -> Result<ReporteTiemposCocina, String> {
+    let agrupar = |col_id: &str, col_nombre: &str| -> Result<Vec<TiemposPreparacion>, String> {
+        let sql = format!(
+            "SELECT {col_id}, {col_nombre}, COUNT(*),
+                    AVG(mins), MAX(mins),
+                    SUM(CASE WHEN objetivo > 0 THEN 1 ELSE 0 END),
+                    SUM(CASE WHEN objetivo > 0 AND mins <= objetivo THEN 1 ELSE 0 END)
+             FROM (
+                SELECT i.producto_id, pr.nombre AS producto_nombre,
+                       e.id AS estacion_id, COALESCE(e.nombre, 'General') AS estacion_nombre,
+                       (julianday(i.fecha_listo) - julianday(i.fecha_envio_cocina)) * 24 * 60 AS mins,
+                       t.minutos_objetivo AS objetivo
+                FROM rest_pedido_items i
+                JOIN productos pr ON i.producto_id = pr.id
+                LEFT JOIN rest_estacion_categorias ec ON ec.categoria_id = pr.categoria_id
+                LEFT JOIN rest_estaciones e ON e.id = COALESCE(i.estacion_id, ec.estacion_id)
+                LEFT JOIN rest_producto_tiempos t ON t.producto_id = i.producto_id
+                WHERE i.fecha_listo IS NOT NULL AND i.fecha_envio_cocina IS NOT NULL
+                  AND date(i.fecha_envio_cocina) >= date(?1) AND date(i.fecha_envio_cocina) <= date(?2)
+             )
+             GROUP BY {col_id}, {col_nombre}
+             ORDER BY AVG(mins) DESC"
+        );
+        let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![fecha_desde, fecha_hasta], |r| {
+                let con_objetivo: i64 = r.get(5)?;
+                let a_tiempo: i64 = r.get(6)?;
+                Ok(TiemposPreparacion {
+                    id: r.get(0)?,
+                    nombre: r.get(1)?,
+                    items: r.get(2)?,
+                    promedio_min: r.get::<_, Option<f64>>(3)?.unwrap_or(0.0),
+                    maximo_min: r.get::<_, Option<f64>>(4)?.unwrap_or(0.0),
+                    a_tiempo_pct: if con_objetivo > 0 {
+                        Some(a_tiempo as f64 / con_objetivo as f64 * 100.0)
+                    } else {
+                        None
+                    },
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(rows)
+    };
+    Ok(ReporteTiemposCocina {
+        fecha_desde: fecha_desde.to_string(),
+        fecha_hasta: fecha_hasta.to_string(),
+        estaciones: agrupar("estacion_id", "estacion_nombre")?,
+        productos: agrupar("producto_id", "producto_nombre")?,
+    })
+}
@@ -0,0 +1,371 @@
+//! Traspasos de un pedido abierto: cambiar de mesa, mover items a otra mesa
+//! y reasignar el mesero. Todo queda auditado en `rest_pedido_movimientos`.
+//!
+//! Los comandos Tauri y los endpoints de la app llaman estas funciones dentro
+//! de una transacción y después de validar el permiso `transfiere_mesas`.
+
+use super::models::{ItemAMover, MovimientoPedido};
+use rusqlite::{params, Connection};
+
+/// Quién hace el traspaso y desde dónde (POS | APP).
+pub struct Autor<'a> {
+    pub usuario_id: Option<i64>,
+    pub usuario_nombre: Option<&'a str>,
+    pub origen: &'a str,
+}
+
+/// Fila de auditoría (campos que no aplican quedan en None).
+#[derive(Default)]
+struct Registro<'a> {
+    tipo: &'a str,
+    pedido_id: i64,
+    pedido_destino_id: Option<i64>,
+    mesa_origen_id: Option<i64>,
+    mesa_destino_id: Option<i64>,
+    mesero_anterior: Option<String>,
+    mesero_nuevo: Option<String>,
+    detalle: Option<String>,
+}
+
+fn registrar(conn: &Connection, r: &Registro, motivo: Option<&str>, autor: &Autor) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO rest_pedido_movimientos
+            (tipo, pedido_id, pedido_destino_id, mesa_origen_id, mesa_destino_id, mesero_anterior, mesero_nuevo,
+             detalle, motivo, usuario_id, usuario_nombre, origen)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            r.tipo,
+            r.pedido_id,
+            r.pedido_destino_id,
+            r.mesa_origen_id,
+            r.mesa_destino_id,
+            r.mesero_anterior,
+            r.mesero_nuevo,
+            r.detalle,
+            motivo.map(str::trim).filter(|m| !m.is_empty()),
+            autor.usuario_id,
+            autor.usuario_nombre,
+            autor.origen
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// (mesa_id, mesero_id, mesero_nombre) de un pedido ABIERTO o CUENTA_PEDIDA.
+fn pedido_activo(conn: &Connection, pedido_id: i64) -> Result<(i64, Option<i64>, Option<String>), String> {
+    let (mesa_id, mesero_id, mesero_nombre, estado): (i64, Option<i64>, Option<String>, String) = conn
+        .query_row(
+            "SELECT mesa_id, mesero_id, mesero_nombre, estado FROM rest_pedidos_abiertos WHERE id = ?1",
+            params![pedido_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .map_err(|_| format!("Pedido {} no encontrado", pedido_id))?;
+    if estado != "ABIERTO" && estado != "CUENTA_PEDIDA" {
+        return Err(format!("El pedido {} está {}", pedido_id, estado));
+    }
+    Ok((mesa_id, mesero_id, mesero_nombre))
+}
+
+/// Con la cuenta dividida los items ya están repartidos entre sub-cuentas.
+fn validar_sin_division(conn: &Connection, pedido_id: i64) -> Result<(), String> {
+    let n: i64 = conn
+        .query_row("SELECT COUNT(*) FROM rest_subcuentas WHERE pedido_id = ?1", params![pedido_id], |r| r.get(0))
+        .unwrap_or(0);
+    if n > 0 {
+        return Err("El pedido tiene la cuenta dividida; deshaga la división antes de mover".to_string());
+    }
+    Ok(())
+}
+
+/// Pedido activo propio de la mesa (no cuenta si está unida como extra).
+fn pedido_de_mesa(conn: &Connection, mesa_id: i64) -> Option<i64> {
+    conn.query_row(
+        "SELECT MAX(id) FROM rest_pedidos_abiertos WHERE mesa_id = ?1 AND estado IN ('ABIERTO', 'CUENTA_PEDIDA')",
+        params![mesa_id],
+        |r| r.get(0),
+    )
+    .ok()
+    .flatten()
+}
+
+/// Valida que la mesa esté activa, sin pedido, sin reserva encima y no unida
+/// a otro pedido (sí puede ser extra de `pedido_id`). Retorna su nombre.
+fn validar_mesa_libre(conn: &Connection, mesa_id: i64, pedido_id: Option<i64>) -> Result<String, String> {
+    let nombre: String = conn
+        .query_row("SELECT nombre FROM rest_mesas WHERE id = ?1 AND activa = 1", params![mesa_id], |r| r.get(0))
+        .map_err(|_| "Mesa destino no encontrada o inactiva".to_string())?;
+    if pedido_de_mesa(conn, mesa_id).is_some() {
+        return Err(format!("{} ya tiene un pedido abierto", nombre));
+    }
+    let unida: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM rest_pedido_mesas_extra pe
+             JOIN rest_pedidos_abiertos p ON pe.pedido_id = p.id
+             WHERE pe.mesa_id = ?1 AND pe.pedido_id <> ?2 AND p.estado IN ('ABIERTO', 'CUENTA_PEDIDA')",
+            params![mesa_id, pedido_id.unwrap_or(0)],
+            |r| r.get(0),
+        )
+        .unwrap_or(0);
+    if unida > 0 {
+        return Err(format!("{} está unida a otro pedido", nombre));
+    }
+    super::reservas::validar_mesa_sin_reserva(conn, mesa_id)?;
+    Ok(nombre)
+}
+
+fn formato_cantidad(c: f64) -> String {
+    if (c - c.round()).abs() < 1e-9 {
+        format!("{}", c.round() as i64)
+    } else {
+        format!("{:.2}", c)
+    }
+}
+
+/// Pasa el pedido completo (items, abonos, mesero) a otra mesa libre.
+pub fn transferir_mesa(
+    conn: &Connection,
+    pedido_id: i64,
+    mesa_destino_id: i64,
+    motivo: Option<&str>,
+    autor: &Autor,
+) -> Result<(), String> {
+    let (mesa_origen_id, _, _) = pedido_activo(conn, pedido_id)?;
+    if mesa_origen_id == mesa_destino_id {
+        return Err("El pedido ya está en esa mesa".to_string());
+    }
+    validar_mesa_libre(conn, mesa_destino_id, Some(pedido_id))?;
+    // Si el destino era una mesa extra del mismo grupo, pasa a ser la principal.
+    conn.execute(
+        "DELETE FROM rest_pedido_mesas_extra WHERE pedido_id = ?1 AND mesa_id = ?2",
+        params![pedido_id, mesa_destino_id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE rest_pedidos_abiertos SET mesa_id = ?1 WHERE id = ?2",
+        params![mesa_destino_id, pedido_id],
+    )
+    .map_err(|e| e.to_string())?;
+    registrar(
+        conn,
+        &Registro {
+            tipo: "TRANSFERIR_MESA",
+            pedido_id,
+            mesa_origen_id: Some(mesa_origen_id),
+            mesa_destino_id: Some(mesa_destino_id),
+            ..Default::default()
+        },
+        motivo,
+        autor,
+    )
+}
+
+/// Mueve items (completos o una parte de la cantidad) a otro pedido.
+///
+/// El destino es `pedido_destino_id` o, si no viene, el pedido activo de
+/// `mesa_destino_id`; si esa mesa está libre se le abre un pedido con el mismo
+/// mesero. Los items conservan su estado de cocina (no se reimprimen). Si el
+/// pedido origen queda vacío y sin abonos, se cancela y libera la mesa.
+/// Retorna el id del pedido destino.
+pub fn mover_items(
+    conn: &Connection,
+    pedido_origen_id: i64,
+    items: &[ItemAMover],
+    destino: (Option<i64>, Option<i64>),
+    motivo: Option<&str>,
+    autor: &Autor,
+) -> Result<i64, String> {
+    if items.is_empty() {
+        return Err("Seleccione al menos un item".to_string());
+    }
+    let (mesa_origen_id, mesero_id, mesero_nombre) = pedido_activo(conn, pedido_origen_id)?;
+    validar_sin_division(conn, pedido_origen_id)?;
+
+    let (pedido_destino_id, mesa_destino_id) = match destino {
+        (Some(pid), _) => (pid, pedido_activo(conn, pid)?.0),
+        (None, Some(mesa_id)) => match pedido_de_mesa(conn, mesa_id) {
+            Some(pid) => (pid, mesa_id),
+            None => {
+                validar_mesa_libre(conn, mesa_id, None)?;
+                conn.execute(
+                    "INSERT INTO rest_pedidos_abiertos (mesa_id, mesero_id, mesero_nombre, comensales, estado)
+                     VALUES (?1, ?2, ?3, 1, 'ABIERTO')",
+                    params![mesa_id, mesero_id, mesero_nombre],
+                )
+                .map_err(|e| e.to_string())?;
+                (conn.last_insert_rowid(), mesa_id)
+            }
+        },
+        (None, None) => return Err("Indique el pedido o la mesa destino".to_string()),
+    };
+    if pedido_destino_id == pedido_origen_id {
+        return Err("El pedido destino es el mismo que el origen".to_string());
+    }
+    validar_sin_division(conn, pedido_destino_id)?;
+
+    let mut detalle: Vec<String> = Vec::new();
+    for it in items {
+        let (pedido_item, total, nombre): (i64, f64, String) = conn
+            .query_row(
+                "SELECT i.pedido_id, i.cantidad, COALESCE(p.nombre, '?')
+                 FROM rest_pedido_items i LEFT JOIN productos p ON p.id = i.producto_id
+                 WHERE i.id = ?1",
+                params![it.item_id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .map_err(|_| format!("Item {} no encontrado", it.item_id))?;
+        if pedido_item != pedido_origen_id {
+            return Err(format!("El item {} no pertenece al pedido {}", it.item_id, pedido_origen_id));
+        }
+        let cantidad = it.cantidad.unwrap_or(total);
+        if cantidad <= 0.0 || cantidad > total + 1e-9 {
+            return Err(format!("Cantidad inválida para {}: {} (hay {})", nombre, cantidad, total));
+        }
+
+        if cantidad >= total - 1e-9 {
+            conn.execute(
+                "UPDATE rest_pedido_items SET pedido_id = ?1 WHERE id = ?2",
+                params![pedido_destino_id, it.item_id],
+            )
+            .map_err(|e| e.to_string())?;
+        } else {
+            // Parte de la línea: se divide en dos conservando estado y modificadores.
+            conn.execute(
+                "UPDATE rest_pedido_items SET cantidad = cantidad - ?1 WHERE id = ?2",
+                params![cantidad, it.item_id],
+            )
+            .map_err(|e| e.to_string())?;
+            conn.execute(
+                "INSERT INTO rest_pedido_items
+                    (pedido_id, producto_id, cantidad, precio_unit, info_adicional, enviado_cocina, estado_cocina,
+                     fecha_creacion, fecha_envio_cocina, estacion_id, comanda_id, estado_impresion, modificadores,
+                     curso, fecha_inicio_preparacion, fecha_listo, fecha_entregado)
+                 SELECT ?1, producto_id, ?2, precio_unit, info_adicional, enviado_cocina, estado_cocina,
+                        fecha_creacion, fecha_envio_cocina, estacion_id, comanda_id, estado_impresion, modificadores,
+                        curso, fecha_inicio_preparacion, fecha_listo, fecha_entregado
+                 FROM rest_pedido_items WHERE id = ?3",
+                params![pedido_destino_id, cantidad, it.item_id],
+            )
+            .map_err(|e| e.to_string())?;
+            let nuevo_id = conn.last_insert_rowid();
+            conn.execute(
+                "INSERT INTO rest_pedido_item_modificadores
+                    (pedido_item_id, modificador_id, grupo_nombre, nombre, precio_delta, producto_insumo_id, cantidad_insumo)
+                 SELECT ?1, modificador_id, grupo_nombre, nombre, precio_delta, producto_insumo_id, cantidad_insumo
+                 FROM rest_pedido_item_modificadores WHERE pedido_item_id = ?2",
+                params![nuevo_id, it.item_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        detalle.push(format!("{} x {}", formato_cantidad(cantidad), nombre));
+    }
+
+    let (quedan, abonos): (i64, i64) = conn
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM rest_pedido_items WHERE pedido_id = ?1),
+                    (SELECT COUNT(*) FROM rest_pedido_abonos WHERE pedido_id = ?1 AND estado = 'HOLDING')",
+            params![pedido_origen_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    if quedan == 0 && abonos == 0 {
+        conn.execute(
+            "UPDATE rest_pedidos_abiertos
+             SET estado = 'CANCELADO', fecha_cierre = datetime('now', 'localtime'),
+                 observacion = TRIM(COALESCE(observacion, '') || ' Items movidos al pedido ' || ?1)
+             WHERE id = ?2",
+            params![pedido_destino_id, pedido_origen_id],
+        )
+        .map_err(|e| e.to_string())?;
+        detalle.push("(pedido origen cerrado)".to_string());
+    }
+
+    registrar(
+        conn,
+        &Registro {
+            tipo: "MOVER_ITEMS",
+            pedido_id: pedido_origen_id,
+            pedido_destino_id: Some(pedido_destino_id),
+            mesa_origen_id: Some(mesa_origen_id),
+            mesa_destino_id: Some(mesa_destino_id),
+            detalle: Some(detalle.join("; ")),
+            ..Default::default()
+        },
+        motivo,
+        autor,
+    )?;
+    Ok(pedido_destino_id)
+}
+
+/// Entrega la mesa a otro mesero (usuario activo).
+pub fn reasignar_mesero(
+    conn: &Connection,
+    pedido_id: i64,
+    mesero_id: i64,
+    motivo: Option<&str>,
+    autor: &Autor,
+) -> Result<(), String> {
+    let (mesa_id, mesero_actual, nombre_actual) = pedido_activo(conn, pedido_id)?;
+    if mesero_actual == Some(mesero_id) {
+        return Err("La mesa ya está asignada a ese mesero".to_string());
+    }
+    let nombre: String = conn
+        .query_row("SELECT nombre FROM usuarios WHERE id = ?1 AND activo = 1", params![mesero_id], |r| r.get(0))
+        .map_err(|_| "Mesero no encontrado o inactivo".to_string())?;
+    conn.execute(
+        "UPDATE rest_pedidos_abiertos SET mesero_id = ?1, mesero_nombre = ?2 WHERE id = ?3",
+        params![mesero_id, nombre, pedido_id],
+    )
+    .map_err(|e| e.to_string())?;
+    registrar(
+        conn,
+        &Registro {
+            tipo: "REASIGNAR_MESERO",
+            pedido_id,
+            mesa_origen_id: Some(mesa_id),
+            mesero_anterior: nombre_actual,
+            mesero_nuevo: Some(nombre),
+            ..Default::default()
+        },
+        motivo,
+        autor,
+    )
+}
+
+/// Traspasos donde el pedido fue origen o destino, del más reciente al más antiguo.
+pub fn listar_movimientos(conn: &Connection, pedido_id: i64) -> Result<Vec<MovimientoPedido>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT mv.id, mv.tipo, mv.pedido_id, mv.pedido_destino_id, mo.nombre, md.nombre,
+                    mv.mesero_anterior, mv.mesero_nuevo, mv.detalle, mv.motivo, mv.usuario_nombre,
+                    mv.origen, mv.fecha
+             FROM rest_pedido_movimientos mv
+             LEFT JOIN rest_mesas mo ON mo.id = mv.mesa_origen_id
+             LEFT JOIN rest_mesas md ON md.id = mv.mesa_destino_id
+             WHERE mv.pedido_id = ?1 OR mv.pedido_destino_id = ?1
+             ORDER BY mv.id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![pedido_id], |r| {
+            Ok(MovimientoPedido {
+                id: r.get(0)?,
+                tipo: r.get(1)?,
+                pedido_id: r.get(2)?,
+                pedido_destino_id: r.get(3)?,
+                mesa_origen: r.get(4)?,
+                mesa_destino: r.get(5)?,
+                mesero_anterior: r.get(6)?,
+                mesero_nuevo: r.get(7)?,
+                detalle: r.get(8)?,
+                motivo: r.get(9)?,
+                usuario_nombre: r.get(10)?,
+                origen: r.get(11)?,
+                fecha: r.get(12)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
@@ -0,0 +1,91 @@
+//! Log de accesos del servidor embebido.
+//!
+//! Una línea JSON por request en `<datos>/logs/server-access.log`:
+//!
+//! ```text
+//! {"ts":"2026-03-02 10:15:03","ip":"192.168.1.20","metodo":"POST","ruta":"/api/v1/app/auth/pin","status":401,"ms":12}
+//! ```
+//!
+//! Rotación por tamaño: al pasar [`MAX_BYTES`] el archivo se renombra a
+//! `.1`, el `.1` a `.2`, etc. hasta [`MAX_ARCHIVOS`]. Nunca se loguean
+//! bodies ni el header Authorization (PINs y tokens no deben terminar en disco).
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+const MAX_BYTES: u64 = 5 * 1024 * 1024;
+const MAX_ARCHIVOS: usize = 5;
+
+/// Serializa escrituras + rotación entre requests concurrentes.
+static ESCRITURA: Mutex<()> = Mutex::new(());
+
+/// Ruta del log activo (carpeta de datos de la app, junto a la BD).
+pub fn ruta_log() -> PathBuf {
+    let mut dir = crate::db::Database::get_db_path_pub()
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."));
+    dir.push("logs");
+    dir.push("server-access.log");
+    dir
+}
+
+/// Middleware: mide la request y escribe la línea de acceso al terminar.
+pub async fn registrar_acceso(req: Request, next: Next) -> Response {
+    let inicio = Instant::now();
+    let metodo = req.method().to_string();
+    let ruta = req.uri().path().to_string();
+    let ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|c| c.0.ip().to_string());
+
+    let resp = next.run(req).await;
+
+    let linea = serde_json::json!({
+        "ts": chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        "ip": ip,
+        "metodo": metodo,
+        "ruta": ruta,
+        "status": resp.status().as_u16(),
+        "ms": inicio.elapsed().as_millis() as u64,
+    });
+    escribir_linea(&linea.to_string());
+    resp
+}
+
+/// Escribe una línea (best-effort: un error de disco no debe tumbar la request).
+fn escribir_linea(linea: &str) {
+    let _guard = match ESCRITURA.lock() {
+        Ok(g) => g,
+        Err(p) => p.into_inner(),
+    };
+    let ruta = ruta_log();
+    if let Some(dir) = ruta.parent() {
+        std::fs::create_dir_all(dir).ok();
+    }
+    if std::fs::metadata(&ruta).map(|m| m.len() >= MAX_BYTES).unwrap_or(false) {
+        rotar(&ruta);
+    }
+    if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(&ruta) {
+        let _ = writeln!(f, "{}", linea);
+    }
+}
+
+/// `server-access.log` → `.1` → `.2` … descartando el más viejo.
+fn rotar(ruta: &PathBuf) {
+    let con_sufijo = |n: usize| PathBuf::from(format!("{}.{}", ruta.display(), n));
+    std::fs::remove_file(con_sufijo(MAX_ARCHIVOS)).ok();
+    for n in (1..MAX_ARCHIVOS).rev() {
+        std::fs::rename(con_sufijo(n), con_sufijo(n + 1)).ok();
+    }
+    std::fs::rename(ruta, con_sufijo(1)).ok();
+}
@@ -432,6 +432,8 @@ pub async fn dispatch_command(
                 .query_row("SELECT value FROM config WHERE key = 'terminal_punto_emision'", [], |row| row.get(0))
                 .unwrap_or_else(|_| "001".to_string());
 
+            let est_id = crate::commands::stock::establecimiento_por_codigo(&conn, &terminal_est)?;
+
             // Secuencial
             conn.execute(
                 "INSERT OR IGNORE INTO secuenciales (establecimiento_codigo, punto_emision_codigo, tipo_documento, secuencial) VALUES (?1, ?2, 'NOTA_VENTA', 1)",
@@ -497,10 +499,16 @@ pub async fn dispatch_command(
                     rusqlite::params![venta_id, item.producto_id, item.cantidad, item.precio_unitario, item.descuento, item.iva_porcentaje, subtotal],
                 ).map_err(|e| e.to_string())?;
 
-                conn.execute(
-                    "UPDATE productos SET stock_actual = stock_actual - ?1, updated_at = datetime('now','localtime') WHERE id = ?2 AND es_servicio = 0",
-                    rusqlite::params![item.cantidad, item.producto_id],
-                ).map_err(|e| e.to_string())?;
+                let es_servicio: bool = conn
+                    .query_row(
+                        "SELECT COALESCE(es_servicio, 0) = 1 FROM productos WHERE id = ?1",
+                        rusqlite::params![item.producto_id],
+                        |row| row.get(0),
+                    )
+                    .unwrap_or(true);
+                if let (false, Some(pid)) = (es_servicio, item.producto_id) {
+                    crate::commands::stock::mover(&conn, pid, est_id, -item.cantidad)?;
+                }
             }
 
             // Incrementar secuencial
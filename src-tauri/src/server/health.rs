@@ -0,0 +1,73 @@
+//! `GET /api/v1/health` — diagnóstico del servidor para soporte en sitio.
+//!
+//! Sin auth (igual que `/api/v1/ping`) y sin datos sensibles: solo contadores.
+//! Pensado para abrirlo desde el navegador de cualquier equipo de la LAN y
+//! ver de un vistazo si el POS está trabado (lock de BD), si hay facturas o
+//! emails acumulados, o si los dispositivos móviles se están conectando.
+
+use super::state::ServerState;
+use axum::{extract::State as AxumState, Json};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Un dispositivo cuenta como "conectado" si usó su token en estos minutos.
+const MINUTOS_DISPOSITIVO_ACTIVO: i64 = 5;
+
+pub async fn handle_health(AxumState(state): AxumState<Arc<ServerState>>) -> Json<serde_json::Value> {
+    // Medir cuánto se espera por el Mutex de la conexión: si otro hilo tiene
+    // una transacción larga (backup, importación), aquí se nota.
+    let inicio_lock = Instant::now();
+    let conn = match state.db.conn.lock() {
+        Ok(c) => c,
+        Err(e) => {
+            return Json(serde_json::json!({
+                "ok": false,
+                "error": format!("BD no disponible: {}", e),
+                "version": env!("CARGO_PKG_VERSION"),
+                "uptime_seg": state.iniciado.elapsed().as_secs(),
+            }))
+        }
+    };
+    let db_lock_espera_ms = inicio_lock.elapsed().as_secs_f64() * 1000.0;
+
+    let contar = |sql: &str| -> i64 { conn.query_row(sql, [], |r| r.get(0)).unwrap_or(0) };
+    let cola_sri = contar("SELECT COUNT(*) FROM ventas WHERE estado_sri = 'PENDIENTE' AND anulada = 0");
+    let cola_email = contar("SELECT COUNT(*) FROM email_log WHERE estado = 'PENDIENTE' AND intentos < 4");
+    let dispositivos_conectados = conn
+        .query_row(
+            "SELECT COUNT(*) FROM app_tokens
+             WHERE revoked = 0 AND last_used_at >= datetime('now', 'localtime', ?1)",
+            rusqlite::params![format!("-{} minutes", MINUTOS_DISPOSITIVO_ACTIVO)],
+            |r| r.get::<_, i64>(0),
+        )
+        .unwrap_or(0);
+    drop(conn);
+
+    // La cola offline solo existe en terminales en modo cliente.
+    let cola_offline: Option<i64> = state.offline.as_ref().and_then(|o| {
+        o.conn.lock().ok().and_then(|c| {
+            c.query_row(
+                "SELECT COUNT(*) FROM cola_operaciones WHERE estado = 'PENDIENTE'",
+                [],
+                |r| r.get(0),
+            )
+            .ok()
+        })
+    });
+
+    Json(serde_json::json!({
+        "ok": true,
+        "service": "clouget-pos",
+        "version": env!("CARGO_PKG_VERSION"),
+        "uptime_seg": state.iniciado.elapsed().as_secs(),
+        "db_lock_espera_ms": (db_lock_espera_ms * 100.0).round() / 100.0,
+        "colas": {
+            "sri_pendientes": cola_sri,
+            "emails_pendientes": cola_email,
+            "offline_pendientes": cola_offline,
+        },
+        "dispositivos_conectados": dispositivos_conectados,
+        "rate_limit_claves": state.rate_limiter.claves_activas(),
+        "multipos_activo": !state.token.is_empty(),
+    }))
+}
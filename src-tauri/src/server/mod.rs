@@ -1,7 +1,11 @@
+pub mod access_log;
 pub mod dispatch;
+pub mod health;
+pub mod rate_limit;
 pub mod state;
 
 use crate::db::{Database, SesionState};
+use crate::offline::OfflineDb;
 use axum::{
     extract::State as AxumState,
     http::{HeaderMap, StatusCode},
@@ -15,7 +19,13 @@ use tower_http::cors::{Any, CorsLayer};
 /// Inicia el servidor HTTP embebido para multi-POS en red.
 /// Se ejecuta en un thread separado con su propio runtime tokio.
 /// Recibe clones de Database y SesionState que comparten la misma conexión.
-pub fn start_server(db: Database, sesion: SesionState, port: u16, token: String) {
+pub fn start_server(
+    db: Database,
+    sesion: SesionState,
+    offline: Option<OfflineDb>,
+    port: u16,
+    token: String,
+) {
     std::thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime for server");
         rt.block_on(async move {
@@ -23,6 +33,9 @@ pub fn start_server(db: Database, sesion: SesionState, port: u16, token: String)
                 db,
                 sesion,
                 token,
+                offline,
+                iniciado: std::time::Instant::now(),
+                rate_limiter: rate_limit::RateLimiter::new(),
             };
 
             // CORS: la app móvil corre en otro origen (Expo Dev en :8081, app
@@ -39,14 +52,30 @@ pub fn start_server(db: Database, sesion: SesionState, port: u16, token: String)
 
             let mut app = Router::new()
                 .route("/api/v1/ping", axum::routing::get(handle_ping))
+                .route("/api/v1/health", axum::routing::get(health::handle_health))
                 // v2.4.2 — Sprint 3a: rutas de la app móvil mergeadas
-                .merge(crate::app_movil::http::rutas());
+                .merge(crate::app_movil::http::rutas())
+                // Pantalla de cocina web (auth por token KDS de `app_tokens`)
+                .merge(crate::restaurante::kds::rutas())
+                // API pública versionada (auth por API key con scopes)
+                .merge(crate::api_publica::http::rutas());
 
             if token_multipos_activo {
                 app = app.route("/api/v1/invoke", post(handle_invoke));
             }
 
-            let app = app.layer(cors).with_state(Arc::new(state));
+            // Orden de capas: la última agregada es la más externa. El log de
+            // accesos envuelve todo para registrar también los 429 y los
+            // preflight CORS.
+            let state = Arc::new(state);
+            let app = app
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limitar_auth_por_ip,
+                ))
+                .layer(cors)
+                .layer(axum::middleware::from_fn(access_log::registrar_acceso))
+                .with_state(state);
 
             let addr = format!("0.0.0.0:{}", port);
             eprintln!("[Clouget Server] Iniciando servidor en {}", addr);
@@ -73,7 +102,9 @@ pub fn start_server(db: Database, sesion: SesionState, port: u16, token: String)
 
             eprintln!("[Clouget Server] Servidor activo en puerto {}", port);
 
-            if let Err(e) = axum::serve(listener, app).await {
+            // ConnectInfo: la IP del cliente la necesitan el log y el rate limit.
+            let servicio = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+            if let Err(e) = axum::serve(listener, servicio).await {
                 eprintln!("[Clouget Server] El servidor se detuvo con error: {}", e);
             }
         });
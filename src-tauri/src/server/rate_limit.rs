@@ -0,0 +1,202 @@
+//! Limitador de intentos en memoria para el servidor embebido.
+//!
+//! Ventana deslizante por clave (`"ip:192.168.1.20"`, `"usuario:7"`). Se usa
+//! para frenar fuerza bruta sobre `/api/v1/app/auth/*`: un PIN de 4 dígitos
+//! son solo 10.000 combinaciones y sin límite se recorren en minutos desde
+//! cualquier equipo de la LAN. En `/api/v2/*` se cuentan las respuestas 401
+//! (API key inválida) por IP.
+//!
+//! El estado vive solo en memoria: si se reinicia el POS, los contadores
+//! vuelven a cero (aceptable — el objetivo es frenar ataques automatizados,
+//! no auditar).
+
+use crate::server::state::ServerState;
+use axum::{
+    extract::{ConnectInfo, Request, State as AxumState},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Máximo de requests de auth por IP dentro de [`VENTANA_IP`].
+pub const MAX_AUTH_POR_IP: usize = 20;
+pub const VENTANA_IP: Duration = Duration::from_secs(60);
+
+/// Máximo de intentos FALLIDOS por usuario dentro de [`VENTANA_USUARIO`].
+/// Se cuenta por usuario (no por IP) para que rotar de dispositivo no sirva
+/// para seguir probando PINs contra la misma cuenta.
+pub const MAX_FALLOS_POR_USUARIO: usize = 5;
+pub const VENTANA_USUARIO: Duration = Duration::from_secs(300);
+
+/// Rutas a las que se aplica el límite por IP.
+const RUTAS_AUTH: [&str; 2] = ["/api/v1/app/auth/pin", "/api/v1/app/auth/password"];
+
+/// Máximo de API keys rechazadas (401) por IP dentro de [`VENTANA_API_KEY`].
+/// Solo cuentan los fallos: una integración con su clave válida no se frena.
+pub const MAX_FALLOS_API_KEY_POR_IP: usize = 10;
+pub const VENTANA_API_KEY: Duration = Duration::from_secs(300);
+
+#[derive(Default)]
+pub struct RateLimiter {
+    ventanas: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registra un evento para `clave` y verifica el límite.
+    /// Devuelve `Err(segundos)` con el tiempo a esperar si se excedió.
+    pub fn verificar_y_registrar(&self, clave: &str, max: usize, ventana: Duration) -> Result<(), u64> {
+        self.bloqueado(clave, max, ventana)?;
+        self.registrar(clave);
+        Ok(())
+    }
+
+    /// Solo consulta: `Err(segundos)` si `clave` ya alcanzó `max` eventos en la ventana.
+    pub fn bloqueado(&self, clave: &str, max: usize, ventana: Duration) -> Result<(), u64> {
+        let mut mapa = match self.ventanas.lock() {
+            Ok(m) => m,
+            Err(_) => return Ok(()), // Mutex envenenado: no bloquear el login por esto
+        };
+        let ahora = Instant::now();
+        let Some(cola) = mapa.get_mut(clave) else { return Ok(()) };
+        while cola.front().is_some_and(|t| ahora.duration_since(*t) > ventana) {
+            cola.pop_front();
+        }
+        if cola.len() >= max {
+            let espera = cola
+                .front()
+                .map(|t| ventana.saturating_sub(ahora.duration_since(*t)).as_secs() + 1)
+                .unwrap_or(1);
+            return Err(espera);
+        }
+        Ok(())
+    }
+
+    /// Agrega un evento a la ventana de `clave` (p. ej. un PIN incorrecto).
+    pub fn registrar(&self, clave: &str) {
+        if let Ok(mut mapa) = self.ventanas.lock() {
+            // Limpieza oportunista para que el mapa no crezca sin límite con
+            // IPs que ya no vuelven.
+            if mapa.len() > 1000 {
+                let ahora = Instant::now();
+                mapa.retain(|_, c| c.back().is_some_and(|t| ahora.duration_since(*t) < VENTANA_USUARIO));
+            }
+            mapa.entry(clave.to_string()).or_default().push_back(Instant::now());
+        }
+    }
+
+    /// Olvida los eventos de `clave` (login exitoso limpia los fallos del usuario).
+    pub fn limpiar(&self, clave: &str) {
+        if let Ok(mut mapa) = self.ventanas.lock() {
+            mapa.remove(clave);
+        }
+    }
+
+    /// Cantidad de claves con eventos en memoria (para `/api/v1/health`).
+    pub fn claves_activas(&self) -> usize {
+        self.ventanas.lock().map(|m| m.len()).unwrap_or(0)
+    }
+}
+
+/// Respuesta 429 con `Retry-After`, mismo formato de error que la app móvil.
+pub fn respuesta_limite(segundos: u64) -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, segundos.to_string())],
+        Json(crate::app_movil::http::ApiError::new(format!(
+            "Demasiados intentos. Espere {} segundos e intente de nuevo.",
+            segundos
+        ))),
+    )
+        .into_response()
+}
+
+/// Middleware: límite por IP sobre las rutas de login de la app móvil y
+/// sobre los fallos de API key de `/api/v2`. El resto de rutas pasa sin tocar.
+pub async fn limitar_auth_por_ip(
+    AxumState(state): AxumState<Arc<ServerState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let ruta = req.uri().path();
+    let es_api_v2 = ruta.starts_with("/api/v2/");
+    if !es_api_v2 && !RUTAS_AUTH.contains(&ruta) {
+        return next.run(req).await;
+    }
+    let ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|c| c.0.ip().to_string())
+        .unwrap_or_else(|| "desconocida".to_string());
+    if es_api_v2 {
+        let clave = format!("api_key_ip:{}", ip);
+        if let Err(seg) = state.rate_limiter.bloqueado(&clave, MAX_FALLOS_API_KEY_POR_IP, VENTANA_API_KEY) {
+            eprintln!("[Clouget Server] Límite de API keys inválidas excedido para IP {}", ip);
+            return respuesta_limite(seg);
+        }
+        let resp = next.run(req).await;
+        if resp.status() == StatusCode::UNAUTHORIZED {
+            state.rate_limiter.registrar(&clave);
+        }
+        return resp;
+    }
+    if let Err(seg) = state
+        .rate_limiter
+        .verificar_y_registrar(&format!("ip:{}", ip), MAX_AUTH_POR_IP, VENTANA_IP)
+    {
+        eprintln!("[Clouget Server] Límite de login excedido para IP {}", ip);
+        return respuesta_limite(seg);
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bloquea_al_alcanzar_el_maximo() {
+        let rl = RateLimiter::new();
+        let ventana = Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(rl.verificar_y_registrar("ip:10.0.0.1", 3, ventana).is_ok());
+        }
+        let espera = rl.verificar_y_registrar("ip:10.0.0.1", 3, ventana).unwrap_err();
+        assert!((1..=61).contains(&espera));
+        // Otra clave no se ve afectada
+        assert!(rl.bloqueado("ip:10.0.0.2", 3, ventana).is_ok());
+    }
+
+    #[test]
+    fn la_ventana_expira() {
+        let rl = RateLimiter::new();
+        let ventana = Duration::from_millis(20);
+        rl.registrar("usuario:7");
+        rl.registrar("usuario:7");
+        assert!(rl.bloqueado("usuario:7", 2, ventana).is_err());
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(rl.bloqueado("usuario:7", 2, ventana).is_ok());
+    }
+
+    #[test]
+    fn limpiar_olvida_los_fallos() {
+        let rl = RateLimiter::new();
+        let ventana = Duration::from_secs(300);
+        for _ in 0..MAX_FALLOS_POR_USUARIO {
+            rl.registrar("usuario:3");
+        }
+        assert!(rl.bloqueado("usuario:3", MAX_FALLOS_POR_USUARIO, ventana).is_err());
+        assert_eq!(rl.claves_activas(), 1);
+        rl.limpiar("usuario:3");
+        assert!(rl.bloqueado("usuario:3", MAX_FALLOS_POR_USUARIO, ventana).is_ok());
+        assert_eq!(rl.claves_activas(), 0);
+    }
+}
@@ -1,8 +1,17 @@
 use crate::db::{Database, SesionState};
+use crate::offline::OfflineDb;
+use super::rate_limit::RateLimiter;
+use std::time::Instant;
 
 /// Estado compartido del servidor HTTP
 pub struct ServerState {
     pub db: Database,
     pub sesion: SesionState,
     pub token: String,
+    /// Cola offline local (solo terminales en modo cliente). Para `/api/v1/health`.
+    pub offline: Option<OfflineDb>,
+    /// Momento de arranque del servidor (uptime en `/api/v1/health`).
+    pub iniciado: Instant,
+    /// Límites de intentos de login (por IP y por usuario).
+    pub rate_limiter: RateLimiter,
 }
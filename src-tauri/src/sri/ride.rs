@@ -90,7 +90,9 @@ pub fn generar_ride_pdf(
     fecha_autorizacion: Option<&str>,
     tipo_doc: &str,                        // "FACTURA" o "NOTA DE CREDITO"
     doc_modificado: Option<&DocModificado>, // Solo para NC
+    propina: f64,                           // Propina/servicio (0 en NC)
 ) -> Result<Vec<u8>, String> {
+    let total_con_propina = venta.venta.total + propina;
     let fonts_dir = crate::utils::obtener_ruta_fuentes();
 
     let font_family = genpdf::fonts::from_files(
@@ -440,7 +442,7 @@ pub fn generar_ride_pdf(
     pago_table
         .row()
         .element(pp(forma_pago_desc, s_small))
-        .element(pp_right(&format_dinero(venta.venta.total), s_small))
+        .element(pp_right(&format_dinero(total_con_propina), s_small))
         .push()
         .map_err(|e| format!("Error forma pago fila: {}", e))?;
 
@@ -486,7 +488,7 @@ pub fn generar_ride_pdf(
         ("IVA 5%", 0.0, s_small),
         ("IVA 15%", iva_15_valor, s_small),
         ("IRBPNR", 0.0, s_small),
-        ("PROPINA", 0.0, s_small),
+        ("PROPINA", propina, s_small),
     ];
 
     for (label, valor, style) in &totales_lines {
@@ -502,7 +504,7 @@ pub fn generar_ride_pdf(
     totales_table
         .row()
         .element(pp("VALOR TOTAL", s_total_bold))
-        .element(pp_right(&format_dinero(venta.venta.total), s_total_bold))
+        .element(pp_right(&format_dinero(total_con_propina), s_total_bold))
         .push()
         .map_err(|e| format!("Error totales valor total: {}", e))?;
 
@@ -1125,7 +1127,7 @@ mod tests {
         let logo_h = crear_png_solido(300, 100, 41, 98, 255); // azul
         config.insert("logo_negocio".to_string(), BASE64.encode(&logo_h));
         let result = generar_ride_pdf(&venta, &detalles_ride, &cliente, &config,
-            Some("18/02/2026 10:35:00"), "FACTURA", None);
+            Some("18/02/2026 10:35:00"), "FACTURA", None, 0.0);
         assert!(result.is_ok(), "Error RIDE logo horizontal: {:?}", result.err());
         let path_h = desktop.join("RIDE_logo_horizontal.pdf");
         std::fs::write(&path_h, result.unwrap()).expect("Error guardando PDF");
@@ -1135,7 +1137,7 @@ mod tests {
         let logo_v = crear_png_solido(150, 150, 34, 197, 94); // verde
         config.insert("logo_negocio".to_string(), BASE64.encode(&logo_v));
         let result = generar_ride_pdf(&venta, &detalles_ride, &cliente, &config,
-            Some("18/02/2026 10:35:00"), "FACTURA", None);
+            Some("18/02/2026 10:35:00"), "FACTURA", None, 0.0);
         assert!(result.is_ok(), "Error RIDE logo cuadrado: {:?}", result.err());
         let path_v = desktop.join("RIDE_logo_cuadrado.pdf");
         std::fs::write(&path_v, result.unwrap()).expect("Error guardando PDF");
@@ -1145,7 +1147,7 @@ mod tests {
         let logo_2_1 = crear_png_solido(300, 150, 220, 38, 38); // rojo
         config.insert("logo_negocio".to_string(), BASE64.encode(&logo_2_1));
         let result = generar_ride_pdf(&venta, &detalles_ride, &cliente, &config,
-            Some("18/02/2026 10:35:00"), "FACTURA", None);
+            Some("18/02/2026 10:35:00"), "FACTURA", None, 0.0);
         assert!(result.is_ok(), "Error RIDE logo 300x150: {:?}", result.err());
         let path_r = desktop.join("RIDE_logo_300x150.pdf");
         std::fs::write(&path_r, result.unwrap()).expect("Error guardando PDF");
@@ -1155,7 +1157,7 @@ mod tests {
         let logo_512 = crear_png_solido(512, 512, 255, 152, 0); // naranja
         config.insert("logo_negocio".to_string(), BASE64.encode(&logo_512));
         let result = generar_ride_pdf(&venta, &detalles_ride, &cliente, &config,
-            Some("18/02/2026 10:35:00"), "FACTURA", None);
+            Some("18/02/2026 10:35:00"), "FACTURA", None, 0.0);
         assert!(result.is_ok(), "Error RIDE logo 512x512: {:?}", result.err());
         let path_512 = desktop.join("RIDE_logo_512x512.pdf");
         std::fs::write(&path_512, result.unwrap()).expect("Error guardando PDF");
@@ -1164,7 +1166,7 @@ mod tests {
         // --- 5. PDF SIN logo ---
         config.remove("logo_negocio");
         let result = generar_ride_pdf(&venta, &detalles_ride, &cliente, &config,
-            Some("18/02/2026 10:35:00"), "FACTURA", None);
+            Some("18/02/2026 10:35:00"), "FACTURA", None, 0.0);
         assert!(result.is_ok(), "Error RIDE sin logo: {:?}", result.err());
         let path_s = desktop.join("RIDE_sin_logo.pdf");
         std::fs::write(&path_s, result.unwrap()).expect("Error guardando PDF");
@@ -1180,7 +1182,7 @@ mod tests {
         let logo_web_bytes = std::fs::read(&logo_web_path).expect("No se encontró Empresa de prueba.png en Downloads");
         config.insert("logo_negocio".to_string(), BASE64.encode(&logo_web_bytes));
         let result = generar_ride_pdf(&venta, &detalles_ride, &cliente, &config,
-            Some("18/02/2026 10:35:00"), "FACTURA", None);
+            Some("18/02/2026 10:35:00"), "FACTURA", None, 0.0);
         assert!(result.is_ok(), "Error RIDE web: {:?}", result.err());
         let path_web = desktop.join("RIDE_web_demo.pdf");
         std::fs::write(&path_web, result.unwrap()).expect("Error guardando PDF");
@@ -29,6 +29,9 @@ pub struct DatosFactura {
     // Totales
     pub total_sin_impuestos: f64,
     pub total_descuento: f64,
+    /// Propina / servicio (no grava IVA). importe_total ya la incluye.
+    #[serde(default)]
+    pub propina: f64,
     pub importe_total: f64,
 
     // Impuestos totales agrupados
@@ -183,7 +186,7 @@ pub fn generar_xml_factura(datos: &DatosFactura) -> String {
     }
     xml.push_str("    </totalConImpuestos>\n");
 
-    xml_tag(&mut xml, 4, "propina", "0.00");
+    xml_tag(&mut xml, 4, "propina", &format!("{:.2}", datos.propina));
     xml_tag(&mut xml, 4, "importeTotal", &format!("{:.2}", datos.importe_total));
     xml_tag(&mut xml, 4, "moneda", "DOLAR");
 
@@ -1116,6 +1119,7 @@ mod tests {
             direccion_comprador: None,
             total_sin_impuestos: 10.0,
             total_descuento: 0.0,
+            propina: 0.0,
             importe_total: 11.50,
             impuestos_totales: vec![ImpuestoTotal {
                 codigo: "2".to_string(),
@@ -149,6 +149,15 @@ pub fn hash_pin(salt: &str, pin: &str) -> String {
     format!("{:x}", hash)
 }
 
+/// Compara dos cadenas sin cortar en el primer byte distinto, para que el
+/// tiempo de respuesta no revele cuánto de un hash coincide.
+pub fn iguales_tiempo_constante(a: &str, b: &str) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 /// Obtiene la ruta a la carpeta de fuentes.
 /// Busca en múltiples ubicaciones para funcionar tanto en desarrollo como en producción.
 pub fn obtener_ruta_fuentes() -> PathBuf {
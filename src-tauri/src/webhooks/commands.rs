@@ -0,0 +1,321 @@
+//! Comandos Tauri admin de webhooks.
+//!
+//! Los consume Configuración → Integraciones del POS escritorio: registrar la
+//! URL de cada sistema externo, elegir qué eventos recibe, ver el log de
+//! entregas y reintentar las fallidas. Todos requieren sesión ADMIN.
+
+use crate::commands::usuarios::verificar_admin;
+use crate::db::{Database, SesionState};
+use rusqlite::params;
+use serde::Serialize;
+use tauri::State;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WebhookInfo {
+    pub id: i64,
+    pub nombre: String,
+    pub url: String,
+    /// Se muestra al admin para que lo configure en el receptor.
+    pub secreto: String,
+    pub eventos: Vec<String>,
+    pub activo: bool,
+    pub created_at: String,
+    pub pendientes: i64,
+    pub fallidos: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EntregaInfo {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub webhook_nombre: Option<String>,
+    pub evento: String,
+    pub entrega_uuid: String,
+    pub referencia: Option<String>,
+    pub payload: String,
+    pub estado: String,
+    pub intentos: i64,
+    pub proximo_intento_at: String,
+    pub ultimo_status: Option<i64>,
+    pub ultimo_error: Option<String>,
+    pub created_at: String,
+    pub entregado_at: Option<String>,
+}
+
+/// Resultado de [`webhook_probar`].
+#[derive(Debug, Serialize, Clone)]
+pub struct PruebaWebhook {
+    pub exito: bool,
+    pub status: Option<u16>,
+    pub mensaje: String,
+}
+
+fn generar_secreto() -> String {
+    format!("whsec_{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+fn validar(nombre: &str, url: &str, eventos: &[String]) -> Result<(), String> {
+    if nombre.is_empty() {
+        return Err("Ingrese un nombre para identificar el webhook".to_string());
+    }
+    let parsed = url::Url::parse(url).map_err(|_| "URL inválida".to_string())?;
+    if parsed.scheme() != "https" && parsed.scheme() != "http" {
+        return Err("La URL debe empezar con https:// o http://".to_string());
+    }
+    if eventos.is_empty() {
+        return Err("Seleccione al menos un evento".to_string());
+    }
+    if let Some(e) = eventos.iter().find(|e| *e != "*" && !super::EVENTOS.contains(&e.as_str())) {
+        return Err(format!("Evento desconocido: {}", e));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn webhook_listar_eventos() -> Vec<String> {
+    super::EVENTOS.iter().map(|s| s.to_string()).collect()
+}
+
+#[tauri::command]
+pub fn webhook_listar(
+    db: State<'_, Database>,
+    sesion: State<'_, SesionState>,
+) -> Result<Vec<WebhookInfo>, String> {
+    verificar_admin(&sesion)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT w.id, w.nombre, w.url, w.secreto, w.eventos, w.activo, w.created_at,
+                    (SELECT COUNT(*) FROM webhook_entregas e WHERE e.webhook_id = w.id AND e.estado = 'PENDIENTE'),
+                    (SELECT COUNT(*) FROM webhook_entregas e WHERE e.webhook_id = w.id AND e.estado = 'FALLIDO')
+             FROM webhooks w ORDER BY w.activo DESC, w.id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let webhooks = stmt
+        .query_map([], |r| {
+            Ok(WebhookInfo {
+                id: r.get(0)?,
+                nombre: r.get(1)?,
+                url: r.get(2)?,
+                secreto: r.get(3)?,
+                eventos: serde_json::from_str(&r.get::<_, String>(4)?).unwrap_or_default(),
+                activo: r.get::<_, i64>(5)? != 0,
+                created_at: r.get(6)?,
+                pendientes: r.get(7)?,
+                fallidos: r.get(8)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(webhooks)
+}
+
+/// Crea (`id = None`) o actualiza un webhook. Al crear se genera el secreto
+/// HMAC; al actualizar se conserva (ver [`webhook_regenerar_secreto`]).
+#[tauri::command]
+pub fn webhook_guardar(
+    db: State<'_, Database>,
+    sesion: State<'_, SesionState>,
+    id: Option<i64>,
+    nombre: String,
+    url: String,
+    eventos: Vec<String>,
+    activo: Option<bool>,
+) -> Result<i64, String> {
+    verificar_admin(&sesion)?;
+    let nombre = nombre.trim();
+    let url = url.trim();
+    validar(nombre, url, &eventos)?;
+    let eventos_json = serde_json::to_string(&eventos).map_err(|e| e.to_string())?;
+    let activo = activo.unwrap_or(true) as i64;
+
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    match id {
+        Some(id) => {
+            let filas = conn
+                .execute(
+                    "UPDATE webhooks SET nombre = ?1, url = ?2, eventos = ?3, activo = ?4,
+                            updated_at = datetime('now','localtime')
+                     WHERE id = ?5",
+                    params![nombre, url, eventos_json, activo, id],
+                )
+                .map_err(|e| e.to_string())?;
+            if filas == 0 {
+                return Err("Webhook no encontrado".to_string());
+            }
+            Ok(id)
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO webhooks (nombre, url, secreto, eventos, activo) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![nombre, url, generar_secreto(), eventos_json, activo],
+            )
+            .map_err(|e| e.to_string())?;
+            Ok(conn.last_insert_rowid())
+        }
+    }
+}
+
+/// Genera un secreto nuevo. El receptor debe actualizarse: las entregas
+/// pendientes se firmarán con el secreto nuevo.
+#[tauri::command]
+pub fn webhook_regenerar_secreto(
+    db: State<'_, Database>,
+    sesion: State<'_, SesionState>,
+    id: i64,
+) -> Result<String, String> {
+    verificar_admin(&sesion)?;
+    let secreto = generar_secreto();
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let filas = conn
+        .execute(
+            "UPDATE webhooks SET secreto = ?1, updated_at = datetime('now','localtime') WHERE id = ?2",
+            params![secreto, id],
+        )
+        .map_err(|e| e.to_string())?;
+    if filas == 0 {
+        return Err("Webhook no encontrado".to_string());
+    }
+    Ok(secreto)
+}
+
+/// Elimina el webhook y su log de entregas.
+#[tauri::command]
+pub fn webhook_eliminar(
+    db: State<'_, Database>,
+    sesion: State<'_, SesionState>,
+    id: i64,
+) -> Result<(), String> {
+    verificar_admin(&sesion)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM webhook_entregas WHERE webhook_id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    let filas = conn
+        .execute("DELETE FROM webhooks WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    if filas == 0 {
+        return Err("Webhook no encontrado".to_string());
+    }
+    Ok(())
+}
+
+/// Log de entregas, más recientes primero.
+#[tauri::command]
+pub fn webhook_listar_entregas(
+    db: State<'_, Database>,
+    sesion: State<'_, SesionState>,
+    webhook_id: Option<i64>,
+    estado: Option<String>,
+    limite: Option<i64>,
+) -> Result<Vec<EntregaInfo>, String> {
+    verificar_admin(&sesion)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT e.id, e.webhook_id, w.nombre, e.evento, e.entrega_uuid, e.referencia, e.payload,
+                    e.estado, e.intentos, e.proximo_intento_at, e.ultimo_status, e.ultimo_error,
+                    e.created_at, e.entregado_at
+             FROM webhook_entregas e
+             LEFT JOIN webhooks w ON w.id = e.webhook_id
+             WHERE (?1 IS NULL OR e.webhook_id = ?1)
+               AND (?2 IS NULL OR e.estado = ?2)
+             ORDER BY e.id DESC
+             LIMIT ?3",
+        )
+        .map_err(|e| e.to_string())?;
+    let entregas = stmt
+        .query_map(params![webhook_id, estado, limite.unwrap_or(200).clamp(1, 1000)], |r| {
+            Ok(EntregaInfo {
+                id: r.get(0)?,
+                webhook_id: r.get(1)?,
+                webhook_nombre: r.get(2)?,
+                evento: r.get(3)?,
+                entrega_uuid: r.get(4)?,
+                referencia: r.get(5)?,
+                payload: r.get(6)?,
+                estado: r.get(7)?,
+                intentos: r.get(8)?,
+                proximo_intento_at: r.get(9)?,
+                ultimo_status: r.get(10)?,
+                ultimo_error: r.get(11)?,
+                created_at: r.get(12)?,
+                entregado_at: r.get(13)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(entregas)
+}
+
+/// Vuelve a poner en cola una entrega FALLIDA (o adelanta una PENDIENTE).
+/// El worker la toma en su próxima vuelta.
+#[tauri::command]
+pub fn webhook_reintentar_entrega(
+    db: State<'_, Database>,
+    sesion: State<'_, SesionState>,
+    id: i64,
+) -> Result<(), String> {
+    verificar_admin(&sesion)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let filas = conn
+        .execute(
+            "UPDATE webhook_entregas SET estado = 'PENDIENTE', intentos = 0,
+                    proximo_intento_at = datetime('now','localtime')
+             WHERE id = ?1 AND estado != 'ENTREGADO'",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+    if filas == 0 {
+        return Err("Entrega no encontrada o ya entregada".to_string());
+    }
+    Ok(())
+}
+
+/// Envía un evento `webhook.prueba` al instante (sin pasar por la cola) para
+/// verificar URL y firma desde la pantalla de configuración.
+#[tauri::command]
+pub async fn webhook_probar(
+    db: State<'_, Database>,
+    sesion: State<'_, SesionState>,
+    id: i64,
+) -> Result<PruebaWebhook, String> {
+    verificar_admin(&sesion)?;
+    let (url, secreto, nombre_negocio): (String, String, String) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let (url, secreto) = conn
+            .query_row(
+                "SELECT url, secreto FROM webhooks WHERE id = ?1",
+                params![id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .map_err(|_| "Webhook no encontrado".to_string())?;
+        let negocio = conn
+            .query_row("SELECT value FROM config WHERE key = 'nombre_negocio'", [], |r| r.get(0))
+            .unwrap_or_default();
+        (url, secreto, negocio)
+    };
+
+    let uuid = uuid::Uuid::new_v4().to_string();
+    let cuerpo = serde_json::json!({
+        "id": uuid,
+        "evento": "webhook.prueba",
+        "fecha": chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%:z").to_string(),
+        "datos": { "webhook_id": id, "negocio": nombre_negocio },
+    })
+    .to_string();
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())?;
+    Ok(match super::worker::enviar(&client, &url, &secreto, "webhook.prueba", &uuid, &cuerpo).await {
+        Ok(status) => PruebaWebhook {
+            exito: true,
+            status: Some(status),
+            mensaje: format!("El receptor respondió HTTP {}", status),
+        },
+        Err((status, mensaje)) => PruebaWebhook { exito: false, status, mensaje },
+    })
+}
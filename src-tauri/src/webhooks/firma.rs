@@ -0,0 +1,61 @@
+//! Firma HMAC-SHA256 de los payloads.
+//!
+//! El receptor calcula `HMAC-SHA256(secreto, "<timestamp>.<cuerpo>")` y lo
+//! compara con el header `X-Webhook-Firma: sha256=<hex>`. Incluir el timestamp
+//! en lo firmado permite rechazar reenvíos viejos (replay).
+//!
+//! Implementado sobre `sha2` (RFC 2104) para no sumar otra dependencia.
+
+use sha2::{Digest, Sha256};
+
+const BLOQUE: usize = 64;
+
+/// HMAC-SHA256 en hex minúscula.
+pub fn hmac_sha256_hex(clave: &[u8], mensaje: &[u8]) -> String {
+    let mut k = [0u8; BLOQUE];
+    if clave.len() > BLOQUE {
+        k[..32].copy_from_slice(&Sha256::digest(clave));
+    } else {
+        k[..clave.len()].copy_from_slice(clave);
+    }
+
+    let mut ipad = [0x36u8; BLOQUE];
+    let mut opad = [0x5cu8; BLOQUE];
+    for i in 0..BLOQUE {
+        ipad[i] ^= k[i];
+        opad[i] ^= k[i];
+    }
+
+    let interno = Sha256::new().chain_update(ipad).chain_update(mensaje).finalize();
+    let externo = Sha256::new().chain_update(opad).chain_update(interno).finalize();
+    format!("{:x}", externo)
+}
+
+/// Valor del header `X-Webhook-Firma` para un envío.
+pub fn firmar(secreto: &str, timestamp: i64, cuerpo: &str) -> String {
+    let mensaje = format!("{}.{}", timestamp, cuerpo);
+    format!("sha256={}", hmac_sha256_hex(secreto.as_bytes(), mensaje.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hmac_rfc4231_caso_2() {
+        assert_eq!(
+            hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn hmac_clave_mas_larga_que_el_bloque() {
+        // RFC 4231 caso 6: clave de 131 bytes (se hashea antes de usarla)
+        let clave = [0xaau8; 131];
+        assert_eq!(
+            hmac_sha256_hex(&clave, b"Test Using Larger Than Block-Size Key - Hash Key First"),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! Webhooks salientes: avisar a sistemas externos (stock de la tienda online,
+//! bot de WhatsApp, contabilidad) cuando pasa algo en el POS.
+//!
+//! Flujo:
+//!  1. El comando de negocio (registrar venta, cerrar caja, ...) llama a
+//!     [`emitir`] con la conexión que ya tiene abierta. Esto solo INSERTA en
+//!     `webhook_entregas` — no hace red, así que no frena la venta.
+//!  2. [`worker`] corre en background, toma las entregas pendientes y las
+//!     envía con reqwest. Si el receptor falla, reintenta con backoff.
+//!  3. Todo queda en `webhook_entregas` como log consultable desde la UI.
+//!
+//! # Estructura del módulo
+//!
+//! - [`schema`]   — tablas `webhooks` y `webhook_entregas`
+//! - [`firma`]    — HMAC-SHA256 del cuerpo
+//! - [`worker`]   — loop de entrega con reintentos
+//! - [`commands`] — comandos Tauri admin (CRUD, log, reintento, prueba)
+
+pub mod commands;
+pub mod firma;
+pub mod schema;
+pub mod worker;
+
+use crate::db::Database;
+use rusqlite::{params, Connection};
+
+/// Eventos a los que se puede suscribir un webhook.
+pub const EVENTOS: [&str; 6] = [
+    "venta.registrada",
+    "venta.anulada",
+    "factura.autorizada",
+    "stock.bajo_minimo",
+    "orden_servicio.estado_cambiado",
+    "caja.cerrada",
+];
+
+/// Inicializa el módulo: corre migraciones SQL.
+pub fn init(db: &Database) -> Result<(), rusqlite::Error> {
+    let conn = db.conn.lock().unwrap();
+    schema::create_tables(&conn)?;
+    Ok(())
+}
+
+/// Encola `evento` para todos los webhooks activos suscritos.
+///
+/// Best-effort: un error aquí se loggea y NO se propaga, para que un webhook
+/// mal configurado nunca impida registrar una venta o cerrar caja.
+pub fn emitir(conn: &Connection, evento: &str, referencia: &str, datos: serde_json::Value) {
+    if let Err(e) = encolar(conn, evento, referencia, datos) {
+        eprintln!("[Webhooks] Error encolando '{}': {}", evento, e);
+    }
+}
+
+/// IDs de los webhooks activos suscritos a `evento`.
+fn suscritos(conn: &Connection, evento: &str) -> Result<Vec<i64>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, eventos FROM webhooks WHERE activo = 1")
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .filter(|(_, eventos)| {
+            serde_json::from_str::<Vec<String>>(eventos)
+                .unwrap_or_default()
+                .iter()
+                .any(|e| e == "*" || e == evento)
+        })
+        .map(|(id, _)| id)
+        .collect();
+    Ok(ids)
+}
+
+fn encolar(
+    conn: &Connection,
+    evento: &str,
+    referencia: &str,
+    datos: serde_json::Value,
+) -> Result<(), String> {
+    let suscritos = suscritos(conn, evento)?;
+    if suscritos.is_empty() {
+        return Ok(());
+    }
+
+    let fecha = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%:z").to_string();
+    for webhook_id in suscritos {
+        let uuid = uuid::Uuid::new_v4().to_string();
+        let cuerpo = serde_json::json!({
+            "id": uuid,
+            "evento": evento,
+            "fecha": fecha,
+            "datos": datos,
+        })
+        .to_string();
+        conn.execute(
+            "INSERT INTO webhook_entregas (webhook_id, evento, entrega_uuid, referencia, payload)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![webhook_id, evento, uuid, referencia, cuerpo],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Emite `stock.bajo_minimo` si una salida dejó el producto en o bajo su
+/// mínimo (mismo criterio que `alertas_stock_bajo`). Lo llama
+/// `commands::stock::mover`, así que cubre ventas, recetas, transferencias,
+/// ajustes, conteos y cualquier otra salida.
+///
+/// Solo avisa al cruzar el umbral (`stock_antes` > mínimo), así un producto
+/// que ya estaba bajo no dispara un webhook en cada movimiento.
+pub fn emitir_stock_bajo(conn: &Connection, producto_id: i64, establecimiento_id: i64, stock_antes: f64) {
+    // Sin suscriptores no vale la pena consultar el producto.
+    if suscritos(conn, "stock.bajo_minimo").unwrap_or_default().is_empty() {
+        return;
+    }
+    let producto: Option<(String, String, f64, f64)> = conn
+        .query_row(
+            "SELECT COALESCE(codigo, ''), nombre, stock_actual, stock_minimo
+             FROM productos
+             WHERE id = ?1 AND activo = 1 AND es_servicio = 0
+               AND stock_actual <= stock_minimo AND ?2 > stock_minimo",
+            params![producto_id, stock_antes],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .ok();
+    let Some((codigo, nombre, stock_actual, stock_minimo)) = producto else { return };
+    emitir(
+        conn,
+        "stock.bajo_minimo",
+        &format!("producto:{}", producto_id),
+        serde_json::json!({
+            "producto_id": producto_id,
+            "codigo": codigo,
+            "nombre": nombre,
+            "stock_actual": stock_actual,
+            "stock_minimo": stock_minimo,
+            "establecimiento_id": establecimiento_id,
+        }),
+    );
+}
+
+/// Emite `orden_servicio.estado_cambiado`. Se llama desde todos los caminos que
+/// cambian el estado (cambio manual, app móvil, cobro, cancelación) con la
+/// misma conexión/transacción que hizo el UPDATE.
+pub fn emitir_estado_orden(
+    conn: &Connection,
+    orden_id: i64,
+    estado_anterior: Option<&str>,
+    estado_nuevo: &str,
+    observacion: Option<&str>,
+    usuario: &str,
+) {
+    // Datos para el receptor (p. ej. bot de WhatsApp que avisa al cliente).
+    let (numero, cliente_nombre, cliente_telefono, equipo): (String, Option<String>, Option<String>, String) = conn
+        .query_row(
+            "SELECT numero, cliente_nombre, cliente_telefono, equipo_descripcion
+             FROM ordenes_servicio WHERE id = ?1",
+            params![orden_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .unwrap_or_default();
+    emitir(
+        conn,
+        "orden_servicio.estado_cambiado",
+        &format!("orden_servicio:{}", orden_id),
+        serde_json::json!({
+            "orden_id": orden_id,
+            "numero": numero,
+            "cliente_nombre": cliente_nombre,
+            "cliente_telefono": cliente_telefono,
+            "equipo": equipo,
+            "estado_anterior": estado_anterior,
+            "estado_nuevo": estado_nuevo,
+            "observacion": observacion,
+            "usuario": usuario,
+        }),
+    );
+}
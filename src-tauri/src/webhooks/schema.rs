@@ -0,0 +1,48 @@
+//! Schema SQL de webhooks.
+//!
+//! Tablas:
+//! - `webhooks`          — destinos configurados (URL, secreto, eventos suscritos)
+//! - `webhook_entregas`  — cola durable + log de entregas
+//!
+//! Cada evento genera UNA fila en `webhook_entregas` por webhook suscrito. La
+//! fila guarda el cuerpo JSON exacto que se envía, así los reintentos mandan
+//! el mismo payload (y la misma firma) aunque el dato original haya cambiado.
+
+use rusqlite::Connection;
+
+/// Crea las tablas del módulo si no existen.
+pub fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS webhooks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL,                     -- 'Tienda online', 'Bot WhatsApp', etc.
+            url TEXT NOT NULL,
+            secreto TEXT NOT NULL,                    -- clave HMAC compartida con el receptor
+            eventos TEXT NOT NULL DEFAULT '[]',       -- JSON array (ver webhooks::EVENTOS); '*' = todos
+            activo INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            updated_at TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS webhook_entregas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            webhook_id INTEGER NOT NULL,
+            evento TEXT NOT NULL,
+            entrega_uuid TEXT NOT NULL,               -- viaja en X-Webhook-Id (idempotencia del receptor)
+            referencia TEXT,                          -- 'venta:123', 'producto:45', ... (para el log)
+            payload TEXT NOT NULL,                    -- cuerpo JSON exacto a enviar
+            estado TEXT NOT NULL DEFAULT 'PENDIENTE', -- PENDIENTE | ENTREGADO | FALLIDO
+            intentos INTEGER NOT NULL DEFAULT 0,
+            proximo_intento_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            ultimo_status INTEGER,                    -- HTTP status de la última respuesta
+            ultimo_error TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            entregado_at TEXT,
+            FOREIGN KEY (webhook_id) REFERENCES webhooks(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_webhook_entregas_cola ON webhook_entregas(estado, proximo_intento_at);
+        CREATE INDEX IF NOT EXISTS idx_webhook_entregas_webhook ON webhook_entregas(webhook_id, created_at);
+        ",
+    )
+}
@@ -0,0 +1,165 @@
+//! Loop de entrega de webhooks en background.
+//!
+//! Mismo esquema que `backup::scheduler`: un thread con su propio runtime de
+//! Tokio. Cada [`INTERVALO`] toma las entregas PENDIENTES cuyo
+//! `proximo_intento_at` ya venció y las envía. El lock de la BD se suelta
+//! mientras se espera la red.
+//!
+//! Reintentos: si el receptor no responde 2xx, se reprograma según
+//! [`BACKOFF_SEG`]. Tras [`MAX_INTENTOS`] la entrega queda FALLIDA (se puede
+//! reintentar a mano desde la UI).
+
+use crate::db::Database;
+use rusqlite::params;
+use std::time::{Duration, Instant};
+
+const INTERVALO: Duration = Duration::from_secs(15);
+const LOTE: i64 = 20;
+pub const MAX_INTENTOS: i64 = 8;
+/// Espera antes del reintento N (1 min, 5 min, 15 min, 1 h, 3 h, 6 h, 12 h).
+const BACKOFF_SEG: [i64; 7] = [60, 300, 900, 3600, 10800, 21600, 43200];
+/// Entregas ENTREGADAS más viejas que esto se borran del log.
+const RETENCION_DIAS: i64 = 30;
+
+struct Pendiente {
+    id: i64,
+    uuid: String,
+    evento: String,
+    payload: String,
+    intentos: i64,
+    url: String,
+    secreto: String,
+}
+
+/// Inicia el worker de entregas en background.
+pub fn start_webhook_worker(db: Database) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().expect("Failed to create webhook worker runtime");
+        rt.block_on(async move {
+            let client = match reqwest::Client::builder().timeout(Duration::from_secs(10)).build() {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("[Webhooks] No se pudo crear el cliente HTTP: {}", e);
+                    return;
+                }
+            };
+            let mut proxima_limpieza = Instant::now();
+            loop {
+                if Instant::now() >= proxima_limpieza {
+                    limpiar_log(&db);
+                    proxima_limpieza = Instant::now() + Duration::from_secs(3600);
+                }
+                for p in tomar_pendientes(&db) {
+                    let resultado = enviar(&client, &p.url, &p.secreto, &p.evento, &p.uuid, &p.payload).await;
+                    registrar_resultado(&db, &p, resultado);
+                }
+                tokio::time::sleep(INTERVALO).await;
+            }
+        });
+    });
+}
+
+fn tomar_pendientes(db: &Database) -> Vec<Pendiente> {
+    let Ok(conn) = db.conn.lock() else { return Vec::new() };
+    let Ok(mut stmt) = conn.prepare(
+        "SELECT e.id, e.entrega_uuid, e.evento, e.payload, e.intentos, w.url, w.secreto
+         FROM webhook_entregas e
+         JOIN webhooks w ON w.id = e.webhook_id
+         WHERE e.estado = 'PENDIENTE' AND w.activo = 1
+           AND e.proximo_intento_at <= datetime('now', 'localtime')
+         ORDER BY e.id
+         LIMIT ?1",
+    ) else {
+        return Vec::new();
+    };
+    let pendientes = stmt.query_map(params![LOTE], |r| {
+        Ok(Pendiente {
+            id: r.get(0)?,
+            uuid: r.get(1)?,
+            evento: r.get(2)?,
+            payload: r.get(3)?,
+            intentos: r.get(4)?,
+            url: r.get(5)?,
+            secreto: r.get(6)?,
+        })
+    })
+    .map(|filas| filas.filter_map(|f| f.ok()).collect())
+    .unwrap_or_default();
+    pendientes
+}
+
+/// POST firmado al receptor. `Ok(status)` si respondió 2xx; si no,
+/// `Err((status, mensaje))` con `status = None` cuando ni siquiera hubo respuesta.
+pub async fn enviar(
+    client: &reqwest::Client,
+    url: &str,
+    secreto: &str,
+    evento: &str,
+    uuid: &str,
+    cuerpo: &str,
+) -> Result<u16, (Option<u16>, String)> {
+    let timestamp = chrono::Utc::now().timestamp();
+    let res = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header("User-Agent", concat!("POS-Webhooks/", env!("CARGO_PKG_VERSION")))
+        .header("X-Webhook-Evento", evento)
+        .header("X-Webhook-Id", uuid)
+        .header("X-Webhook-Timestamp", timestamp.to_string())
+        .header("X-Webhook-Firma", super::firma::firmar(secreto, timestamp, cuerpo))
+        .body(cuerpo.to_string())
+        .send()
+        .await
+        .map_err(|e| (None, e.to_string()))?;
+
+    let status = res.status();
+    if status.is_success() {
+        return Ok(status.as_u16());
+    }
+    let texto: String = res.text().await.unwrap_or_default().chars().take(500).collect();
+    Err((Some(status.as_u16()), format!("HTTP {}: {}", status, texto)))
+}
+
+fn registrar_resultado(db: &Database, p: &Pendiente, resultado: Result<u16, (Option<u16>, String)>) {
+    let Ok(conn) = db.conn.lock() else { return };
+    let intentos = p.intentos + 1;
+    let r = match resultado {
+        Ok(status) => conn.execute(
+            "UPDATE webhook_entregas SET estado = 'ENTREGADO', intentos = ?1, ultimo_status = ?2,
+                    ultimo_error = NULL, entregado_at = datetime('now', 'localtime')
+             WHERE id = ?3",
+            params![intentos, status, p.id],
+        ),
+        Err((status, error)) => {
+            if intentos >= MAX_INTENTOS {
+                eprintln!("[Webhooks] Entrega {} ({}) FALLIDA tras {} intentos: {}", p.id, p.evento, intentos, error);
+                conn.execute(
+                    "UPDATE webhook_entregas SET estado = 'FALLIDO', intentos = ?1, ultimo_status = ?2, ultimo_error = ?3
+                     WHERE id = ?4",
+                    params![intentos, status, error, p.id],
+                )
+            } else {
+                let espera = BACKOFF_SEG[((intentos - 1) as usize).min(BACKOFF_SEG.len() - 1)];
+                conn.execute(
+                    "UPDATE webhook_entregas SET intentos = ?1, ultimo_status = ?2, ultimo_error = ?3,
+                            proximo_intento_at = datetime('now', 'localtime', ?4)
+                     WHERE id = ?5",
+                    params![intentos, status, error, format!("+{} seconds", espera), p.id],
+                )
+            }
+        }
+    };
+    if let Err(e) = r {
+        eprintln!("[Webhooks] Error actualizando entrega {}: {}", p.id, e);
+    }
+}
+
+fn limpiar_log(db: &Database) {
+    if let Ok(conn) = db.conn.lock() {
+        let _ = conn.execute(
+            "DELETE FROM webhook_entregas
+             WHERE estado = 'ENTREGADO' AND entregado_at < datetime('now', 'localtime', ?1)",
+            params![format!("-{} days", RETENCION_DIAS)],
+        );
+    }
+}
@@ -55,7 +55,8 @@ export default function ReportesPage() {
   const [gastosReporte, setGastosReporte] = useState<import("../services/api").ResumenGastos | null>(null);
   // v2.5.22: estado para reporte de valuación de inventario
   const [valuacionData, setValuacionData] = useState<any | null>(null);
-  const [valuacionMetodo, setValuacionMetodo] = useState<"PMP" | "ULTIMO">("PMP");
+  // null = el método de costeo configurado en la empresa
+  const [valuacionMetodo, setValuacionMetodo] = useState<"PROMEDIO" | "FIFO" | "ULTIMO" | null>(null);
   const [valuacionCargando, setValuacionCargando] = useState(false);
   // v2.4.14: reportes de Servicio Tecnico
   const [cancelacionesST, setCancelacionesST] = useState<ResumenCancelaciones | null>(null);
@@ -1477,28 +1478,24 @@ export default function ReportesPage() {
               <div style={{ display: "flex", alignItems: "center", gap: 12, flexWrap: "wrap" }}>
                 <div style={{ fontSize: 12, fontWeight: 600 }}>Método de valuación:</div>
                 <div style={{ display: "flex", gap: 6 }}>
-                  <button type="button"
-                    onClick={() => setValuacionMetodo("PMP")}
-                    style={{
-                      padding: "6px 14px", borderRadius: 4,
-                      border: `1px solid ${valuacionMetodo === "PMP" ? "var(--color-primary)" : "var(--color-border)"}`,
-                      background: valuacionMetodo === "PMP" ? "var(--color-primary)" : "transparent",
-                      color: valuacionMetodo === "PMP" ? "#fff" : "var(--color-text)",
-                      fontSize: 12, fontWeight: 600, cursor: "pointer",
-                    }}>
-                    📊 Promedio Ponderado (PMP)
-                  </button>
-                  <button type="button"
-                    onClick={() => setValuacionMetodo("ULTIMO")}
-                    style={{
-                      padding: "6px 14px", borderRadius: 4,
-                      border: `1px solid ${valuacionMetodo === "ULTIMO" ? "var(--color-primary)" : "var(--color-border)"}`,
-                      background: valuacionMetodo === "ULTIMO" ? "var(--color-primary)" : "transparent",
-                      color: valuacionMetodo === "ULTIMO" ? "#fff" : "var(--color-text)",
-                      fontSize: 12, fontWeight: 600, cursor: "pointer",
-                    }}>
-                    🏷 Último precio de compra
-                  </button>
+                  {([
+                    [null, "⚙️ Según configuración"],
+                    ["PROMEDIO", "📊 Promedio Ponderado (PMP)"],
+                    ["FIFO", "📦 FIFO (capas de costo)"],
+                    ["ULTIMO", "🏷 Último precio de compra"],
+                  ] as const).map(([metodo, etiqueta]) => (
+                    <button type="button" key={metodo ?? "config"}
+                      onClick={() => setValuacionMetodo(metodo)}
+                      style={{
+                        padding: "6px 14px", borderRadius: 4,
+                        border: `1px solid ${valuacionMetodo === metodo ? "var(--color-primary)" : "var(--color-border)"}`,
+                        background: valuacionMetodo === metodo ? "var(--color-primary)" : "transparent",
+                        color: valuacionMetodo === metodo ? "#fff" : "var(--color-text)",
+                        fontSize: 12, fontWeight: 600, cursor: "pointer",
+                      }}>
+                      {etiqueta}
+                    </button>
+                  ))}
                 </div>
                 <button className="btn btn-primary" style={{ marginLeft: "auto" }}
                   disabled={valuacionCargando}
@@ -1516,6 +1513,7 @@ export default function ReportesPage() {
               </div>
               <div style={{ fontSize: 10, color: "var(--color-text-secondary)", marginTop: 8 }}>
                 💡 <strong>PMP (Promedio Ponderado Móvil)</strong>: el costo se recalcula con cada compra, suavizando variaciones de precios. Recomendado por SRI para PyMEs. ·
+                <strong>FIFO</strong>: valúa con las capas de costo abiertas, las más antiguas salen primero. ·
                 <strong>Último precio</strong>: usa el precio de la última compra registrada (modo "reposición").
               </div>
             </div>
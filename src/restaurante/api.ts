@@ -17,6 +17,7 @@ import type {
   Subcuenta,
   Zona,
 } from "./types";
+import type { VentaDetalle } from "../types";
 
 // ─── Zonas ───────────────────────────────────────────────────────────────
 
@@ -167,6 +168,11 @@ export const listarSubcuentas = (pedidoId: number) =>
 export const cancelarDivision = (pedidoId: number) =>
   invoke<void>("rest_cancelar_division", { pedidoId });
 
+/** Valida las líneas de una sub-cuenta antes de registrar la venta, para no
+ *  dejar una venta huérfana si el cobro se rechaza. */
+export const validarVentaSubcuenta = (subcuentaId: number, items: VentaDetalle[]) =>
+  invoke<void>("rest_validar_venta_subcuenta", { subcuentaId, items });
+
 /** Marca una sub-cuenta como cobrada — vincula con la venta ya generada
  *  por el frontend mediante registrarVenta(). Si todas quedaron cobradas,
  *  cierra el pedido y libera mesas automáticamente. */
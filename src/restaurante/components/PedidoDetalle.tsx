@@ -31,6 +31,7 @@ import {
   listarSubcuentas,
   cancelarDivision,
   marcarSubcuentaCobrada,
+  validarVentaSubcuenta,
   productoDivisionId,
 } from "../api";
 import { registrarVenta, obtenerCajaAbierta, listarCuentasBanco, obtenerConfig, emitirFacturaSri, enviarNotificacionSri, imprimirTicket } from "../../services/api";
@@ -351,6 +352,7 @@ export default function PedidoDetalle({ pedidoId, onCerrar }: Props) {
     };
 
     try {
+      await validarVentaSubcuenta(sub.id, payload.items);
       const resultado = await registrarVenta(payload);
       const cobro = await marcarSubcuentaCobrada(
         sub.id,
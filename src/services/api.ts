@@ -220,8 +220,8 @@ export async function listarProductosTactil(): Promise<ProductoTactil[]> {
 
 // --- Números de Serie ---
 
-export const registrarSeries = (productoId: number, seriales: string[], compraId?: number) =>
-  smartInvoke<{ insertados: number; duplicados: number }>("registrar_series", { productoId, seriales, compraId: compraId ?? null });
+export const registrarSeries = (productoId: number, seriales: string[], compraId?: number, costo?: number) =>
+  smartInvoke<{ insertados: number; duplicados: number }>("registrar_series", { productoId, seriales, compraId: compraId ?? null, costo: costo ?? null });
 
 export const listarSeriesProducto = (productoId: number, estado?: string) =>
   smartInvoke<any[]>("listar_series_producto", { productoId, estado: estado ?? null });
@@ -1541,8 +1541,8 @@ export async function conectarGdrive(): Promise<string> {
 
 // --- Reportes avanzados ---
 import type { ReporteUtilidad, ReporteBalance, ProductoRentabilidad } from "../types";
-export async function reporteUtilidad(fechaInicio: string, fechaHasta: string): Promise<ReporteUtilidad> {
-  return smartInvoke("reporte_utilidad", { fechaInicio, fechaHasta });
+export async function reporteUtilidad(fechaInicio: string, fechaHasta: string, metodo?: "PROMEDIO" | "FIFO"): Promise<ReporteUtilidad> {
+  return smartInvoke("reporte_utilidad", { fechaInicio, fechaHasta, metodo: metodo ?? null });
 }
 export async function reporteBalance(fechaInicio: string, fechaHasta: string): Promise<ReporteBalance> {
   return smartInvoke("reporte_balance", { fechaInicio, fechaHasta });
@@ -1669,7 +1669,7 @@ export const reporteKardexMulti = (categorias: number[] | null, productos: numbe
 
 // v2.5.22: Valuación de inventario (PMP / Último precio)
 export interface ValuacionInventario {
-  metodo: "PMP" | "ULTIMO";
+  metodo: "PMP" | "FIFO" | "ULTIMO";
   metodo_descripcion: string;
   productos: Array<{
     id: number; codigo?: string; nombre: string; categoria?: string;
@@ -1689,8 +1689,9 @@ export interface ValuacionInventario {
     margen_pct: number;
   };
 }
-export const reporteValuacionInventario = (metodo: "PMP" | "ULTIMO" = "PMP", categoriaId?: number | null) =>
-  smartInvoke<ValuacionInventario>("reporte_valuacion_inventario", { metodo, categoriaId: categoriaId ?? null });
+/** `metodo` null/omitido = el método de costeo configurado. */
+export const reporteValuacionInventario = (metodo?: "PROMEDIO" | "FIFO" | "ULTIMO" | null, categoriaId?: number | null) =>
+  smartInvoke<ValuacionInventario>("reporte_valuacion_inventario", { metodo: metodo ?? null, categoriaId: categoriaId ?? null });
 export const listarCategoriasSimple = () =>
   smartInvoke<Array<{ id: number; nombre: string }>>("listar_categorias_simple");
 
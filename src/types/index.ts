@@ -193,6 +193,7 @@ export interface ReporteUtilidad {
   promedio_por_venta: number; total_devoluciones: number;
   por_categoria: { categoria: string; ventas: number; costo: number; utilidad: number }[];
   gastos_por_categoria: { categoria: string; monto: number }[];
+  metodo_costeo: string;
 }
 export interface ReporteBalance {
   ingresos_efectivo: number; ingresos_transferencia: number; ingresos_credito_cobrado: number;